@@ -185,11 +185,14 @@ fn main() {
                 framebuffers[image_num].clone(), false,
                 renderpass.desc().start_clear_values()
                     .color([0.0, 0.0, 1.0, 1.0]).depth((1f32)))
+            .unwrap()
             .draw_indexed(
                 pipeline.clone(), vulkano::command_buffer::DynamicState::none(),
                 (vertex_buffer.clone(), normals_buffer.clone()), 
                 index_buffer.clone(), set.clone(), ())
+            .unwrap()
             .end_render_pass()
+            .unwrap()
             .build().unwrap();
         
         let future = future
@@ -16,6 +16,8 @@
 //! You can read the buffer multiple times simultaneously. Trying to read and write simultaneously,
 //! or write and write simultaneously will block.
 
+use std::error;
+use std::fmt;
 use std::marker::PhantomData;
 use std::mem;
 use std::ops::Deref;
@@ -25,7 +27,6 @@ use std::sync::Arc;
 use std::sync::RwLock;
 use std::sync::RwLockReadGuard;
 use std::sync::RwLockWriteGuard;
-use std::sync::TryLockError;
 use smallvec::SmallVec;
 
 use buffer::sys::BufferCreationError;
@@ -42,6 +43,7 @@ use device::Queue;
 use instance::QueueFamily;
 use memory::Content;
 use memory::CpuAccess as MemCpuAccess;
+use memory::MemoryUsage;
 use memory::pool::AllocLayout;
 use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
@@ -86,26 +88,20 @@ impl<T> CpuAccessibleBuffer<T> {
     }
 
     /// Builds a new buffer with some data in it. Only allowed for sized data.
+    #[inline]
     pub fn from_data<'a, I>(device: &Arc<Device>, usage: &Usage, queue_families: I, data: T)
                             -> Result<Arc<CpuAccessibleBuffer<T>>, OomError>
         where I: IntoIterator<Item = QueueFamily<'a>>,
               T: Content + 'static,
     {
-        unsafe {
-            let uninitialized = try!(
-                CpuAccessibleBuffer::raw(device, mem::size_of::<T>(), usage, queue_families)
-            );
-
-            // Note that we are in panic-unsafety land here. However a panic should never ever
-            // happen here, so in theory we are safe.
-            // TODO: check whether that's true ^
-
-            {
-                let mut mapping = uninitialized.write().unwrap();
-                ptr::write::<T>(&mut *mapping, data)
-            }
-
-            Ok(uninitialized)
+        match CpuAccessibleBuffer::from_data_with_pool(device, usage, queue_families, data,
+                                                       &Device::standard_pool(device),
+                                                       MemoryUsage::CpuOnly)
+        {
+            Ok(b) => Ok(b),
+            Err(BufferCreationError::OomError(err)) => Err(err),
+            Err(_) => unreachable!()        // `CpuOnly` requires host-visible memory, and we
+                                            // don't use sparse binding
         }
     }
 
@@ -119,18 +115,24 @@ impl<T> CpuAccessibleBuffer<T> {
     }
 }
 
-impl<T> CpuAccessibleBuffer<[T]> {
-    /// Builds a new buffer that contains an array `T`. The initial data comes from an iterator
-    /// that produces that list of Ts.
-    pub fn from_iter<'a, I, Q>(device: &Arc<Device>, usage: &Usage, queue_families: Q, data: I)
-                               -> Result<Arc<CpuAccessibleBuffer<[T]>>, OomError>
-        where I: ExactSizeIterator<Item = T>,
+impl<T, A> CpuAccessibleBuffer<T, A> where A: MemoryPool {
+    /// Same as `from_data`, but allocates the memory of the buffer from `pool`, in a memory type
+    /// chosen according to `memory_usage`.
+    ///
+    /// `from_data` uses `MemoryUsage::CpuOnly`. Use `MemoryUsage::CpuToGpu` for data that the
+    /// GPU reads often, and `MemoryUsage::GpuToCpu` for data that the CPU reads back.
+    ///
+    /// Returns `MemoryUsageNotHostVisible` if `memory_usage` doesn't require host-visible memory.
+    pub fn from_data_with_pool<'a, I>(device: &Arc<Device>, usage: &Usage, queue_families: I,
+                                      data: T, pool: &A, memory_usage: MemoryUsage)
+                                      -> Result<Arc<CpuAccessibleBuffer<T, A>>, BufferCreationError>
+        where I: IntoIterator<Item = QueueFamily<'a>>,
               T: Content + 'static,
-              Q: IntoIterator<Item = QueueFamily<'a>>
     {
         unsafe {
             let uninitialized = try!(
-                CpuAccessibleBuffer::uninitialized_array(device, data.len(), usage, queue_families)
+                CpuAccessibleBuffer::raw_with_pool(device, mem::size_of::<T>(), usage,
+                                                   queue_families, pool, memory_usage)
             );
 
             // Note that we are in panic-unsafety land here. However a panic should never ever
@@ -138,16 +140,39 @@ impl<T> CpuAccessibleBuffer<[T]> {
             // TODO: check whether that's true ^
 
             {
-                let mut mapping = uninitialized.write().unwrap();
-
-                for (i, o) in data.zip(mapping.iter_mut()) {
-                    ptr::write(o, i);
-                }
+                let mut mapping = match uninitialized.write() {
+                    Ok(m) => m,
+                    Err(LockError::OomError(err)) => return Err(err.into()),
+                    Err(LockError::AlreadyLocked) => unreachable!(),
+                };
+                ptr::write::<T>(&mut *mapping, data)
             }
 
             Ok(uninitialized)
         }
     }
+}
+
+impl<T> CpuAccessibleBuffer<[T]> {
+    /// Builds a new buffer that contains an array `T`. The initial data comes from an iterator
+    /// that produces that list of Ts.
+    #[inline]
+    pub fn from_iter<'a, I, Q>(device: &Arc<Device>, usage: &Usage, queue_families: Q, data: I)
+                               -> Result<Arc<CpuAccessibleBuffer<[T]>>, OomError>
+        where I: ExactSizeIterator<Item = T>,
+              T: Content + 'static,
+              Q: IntoIterator<Item = QueueFamily<'a>>
+    {
+        match CpuAccessibleBuffer::from_iter_with_pool(device, usage, queue_families, data,
+                                                       &Device::standard_pool(device),
+                                                       MemoryUsage::CpuOnly)
+        {
+            Ok(b) => Ok(b),
+            Err(BufferCreationError::OomError(err)) => Err(err),
+            Err(_) => unreachable!()        // `CpuOnly` requires host-visible memory, and we
+                                            // don't use sparse binding
+        }
+    }
 
     /// Deprecated. Use `uninitialized_array` or `from_iter` instead.
     // TODO: remove
@@ -173,6 +198,45 @@ impl<T> CpuAccessibleBuffer<[T]> {
     }
 }
 
+impl<T, A> CpuAccessibleBuffer<[T], A> where A: MemoryPool {
+    /// Same as `from_iter`, but allocates the memory of the buffer from `pool`, in a memory type
+    /// chosen according to `memory_usage`. See `from_data_with_pool`.
+    pub fn from_iter_with_pool<'a, I, Q>(device: &Arc<Device>, usage: &Usage, queue_families: Q,
+                                         data: I, pool: &A, memory_usage: MemoryUsage)
+                                         -> Result<Arc<CpuAccessibleBuffer<[T], A>>, BufferCreationError>
+        where I: ExactSizeIterator<Item = T>,
+              T: Content + 'static,
+              Q: IntoIterator<Item = QueueFamily<'a>>
+    {
+        unsafe {
+            let uninitialized = try!(
+                CpuAccessibleBuffer::<[T], A>::raw_with_pool(device,
+                                                             data.len() * mem::size_of::<T>(),
+                                                             usage, queue_families, pool,
+                                                             memory_usage)
+            );
+
+            // Note that we are in panic-unsafety land here. However a panic should never ever
+            // happen here, so in theory we are safe.
+            // TODO: check whether that's true ^
+
+            {
+                let mut mapping = match uninitialized.write() {
+                    Ok(m) => m,
+                    Err(LockError::OomError(err)) => return Err(err.into()),
+                    Err(LockError::AlreadyLocked) => unreachable!(),
+                };
+
+                for (i, o) in data.zip(mapping.iter_mut()) {
+                    ptr::write(o, i);
+                }
+            }
+
+            Ok(uninitialized)
+        }
+    }
+}
+
 impl<T: ?Sized> CpuAccessibleBuffer<T> {
     /// Builds a new buffer without checking the size.
     ///
@@ -180,10 +244,40 @@ impl<T: ?Sized> CpuAccessibleBuffer<T> {
     ///
     /// You must ensure that the size that you pass is correct for `T`.
     ///
+    #[inline]
     pub unsafe fn raw<'a, I>(device: &Arc<Device>, size: usize, usage: &Usage, queue_families: I)
                              -> Result<Arc<CpuAccessibleBuffer<T>>, OomError>
         where I: IntoIterator<Item = QueueFamily<'a>>
     {
+        match CpuAccessibleBuffer::raw_with_pool(device, size, usage, queue_families,
+                                                 &Device::standard_pool(device),
+                                                 MemoryUsage::CpuOnly)
+        {
+            Ok(b) => Ok(b),
+            Err(BufferCreationError::OomError(err)) => Err(err),
+            Err(_) => unreachable!()        // `CpuOnly` requires host-visible memory, and we
+                                            // don't use sparse binding
+        }
+    }
+}
+
+impl<T: ?Sized, A> CpuAccessibleBuffer<T, A> where A: MemoryPool {
+    /// Same as `raw`, but allocates the memory of the buffer from `pool`, in a memory type
+    /// chosen according to `memory_usage`. See `from_data_with_pool`.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that the size that you pass is correct for `T`.
+    ///
+    pub unsafe fn raw_with_pool<'a, I>(device: &Arc<Device>, size: usize, usage: &Usage,
+                                       queue_families: I, pool: &A, memory_usage: MemoryUsage)
+                                       -> Result<Arc<CpuAccessibleBuffer<T, A>>, BufferCreationError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        if !memory_usage.requires_host_visible() {
+            return Err(BufferCreationError::MemoryUsageNotHostVisible);
+        }
+
         let queue_families = queue_families.into_iter().map(|f| f.id())
                                            .collect::<SmallVec<[u32; 4]>>();
 
@@ -194,21 +288,15 @@ impl<T: ?Sized> CpuAccessibleBuffer<T> {
                 Sharing::Exclusive
             };
 
-            match UnsafeBuffer::new(device, size, &usage, sharing, SparseLevel::none()) {
-                Ok(b) => b,
-                Err(BufferCreationError::OomError(err)) => return Err(err),
-                Err(_) => unreachable!()        // We don't use sparse binding, therefore the other
-                                                // errors can't happen
-            }
+            try!(UnsafeBuffer::new(device, size, &usage, sharing, SparseLevel::none()))
         };
 
-        let mem_ty = device.physical_device().memory_types()
-                           .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
-                           .filter(|t| t.is_host_visible())
-                           .next().unwrap();    // Vk specs guarantee that this can't fail
+        // Vk specs guarantee that there's always a host-visible memory type for buffers.
+        let mem_ty = memory_usage.choose_memory_type(device.physical_device(),
+                                                     mem_reqs.memory_type_bits).unwrap();
 
-        let mem = try!(MemoryPool::alloc(&Device::standard_pool(device), mem_ty,
-                                         mem_reqs.size, mem_reqs.alignment, AllocLayout::Linear));
+        let mem = try!(MemoryPool::alloc(pool, mem_ty, mem_reqs.size, mem_reqs.alignment,
+                                         AllocLayout::Linear));
         debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
         debug_assert!(mem.mapped_memory().is_some());
         try!(buffer.bind_memory(mem.memory(), mem.offset()));
@@ -241,7 +329,7 @@ impl<T: ?Sized, A> CpuAccessibleBuffer<T, A> where A: MemoryPool {
 }
 
 impl<T: ?Sized, A> CpuAccessibleBuffer<T, A> where T: Content + 'static, A: MemoryPool {
-    /// Locks the buffer in order to write its content.
+    /// Locks the buffer in order to read its content.
     ///
     /// If the buffer is currently in use by the GPU, this function will block until either the
     /// buffer is available or the timeout is reached. A value of `0` for the timeout is valid and
@@ -249,15 +337,21 @@ impl<T: ?Sized, A> CpuAccessibleBuffer<T, A> where T: Content + 'static, A: Memo
     ///
     /// After this function successfully locks the buffer, any attempt to submit a command buffer
     /// that uses it will block until you unlock it.
+    ///
+    /// If the memory of the buffer is not host-coherent, it is invalidated so that the writes
+    /// made by the GPU are visible.
     #[inline]
-    pub fn read(&self) -> Result<ReadLock<T>, TryLockError<RwLockReadGuard<()>>> {
-        let lock = try!(self.access.try_read());
+    pub fn read(&self) -> Result<ReadLock<T>, LockError> {
+        let lock = match self.access.try_read() {
+            Ok(l) => l,
+            Err(_) => return Err(LockError::AlreadyLocked),
+        };
 
         let offset = self.memory.offset();
         let range = offset .. offset + self.inner.size();
 
         Ok(ReadLock {
-            inner: unsafe { self.memory.mapped_memory().unwrap().read_write(range) },
+            inner: unsafe { try!(self.memory.mapped_memory().unwrap().read(range)) },
             lock: lock,
         })
     }
@@ -270,15 +364,21 @@ impl<T: ?Sized, A> CpuAccessibleBuffer<T, A> where T: Content + 'static, A: Memo
     ///
     /// After this function successfully locks the buffer, any attempt to submit a command buffer
     /// that uses it will block until you unlock it.
+    ///
+    /// If the memory of the buffer is not host-coherent, the content is flushed when the lock is
+    /// destroyed so that the GPU can see the modifications.
     #[inline]
-    pub fn write(&self) -> Result<WriteLock<T>, TryLockError<RwLockWriteGuard<()>>> {
-        let lock = try!(self.access.try_write());
+    pub fn write(&self) -> Result<WriteLock<T>, LockError> {
+        let lock = match self.access.try_write() {
+            Ok(l) => l,
+            Err(_) => return Err(LockError::AlreadyLocked),
+        };
 
         let offset = self.memory.offset();
         let range = offset .. offset + self.inner.size();
 
         Ok(WriteLock {
-            inner: unsafe { self.memory.mapped_memory().unwrap().read_write(range) },
+            inner: unsafe { try!(self.memory.mapped_memory().unwrap().read_write(range)) },
             lock: lock,
         })
     }
@@ -421,3 +521,45 @@ impl<'a, T: ?Sized + 'a> DerefMut for WriteLock<'a, T> {
         self.inner.deref_mut()
     }
 }
+
+/// Error when locking the content of a `CpuAccessibleBuffer`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LockError {
+    /// The buffer is already locked by the CPU or the GPU in a way that conflicts with the
+    /// requested access.
+    AlreadyLocked,
+    /// Not enough memory to make the writes done by the GPU visible to the CPU.
+    OomError(OomError),
+}
+
+impl error::Error for LockError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            LockError::AlreadyLocked => "the buffer is already locked",
+            LockError::OomError(_) => "not enough memory available",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            LockError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for LockError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for LockError {
+    #[inline]
+    fn from(err: OomError) -> LockError {
+        LockError::OomError(err)
+    }
+}
@@ -30,6 +30,7 @@ use device::Device;
 use device::DeviceOwned;
 use device::Queue;
 use instance::QueueFamily;
+use memory::MemoryUsage;
 use memory::pool::AllocLayout;
 use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
@@ -72,6 +73,9 @@ pub struct CpuBufferPool<T: ?Sized, A = Arc<StdMemoryPool>> where A: MemoryPool
     // Buffer usage.
     usage: Usage,
 
+    // Used to choose the memory type of the buffers.
+    memory_usage: MemoryUsage,
+
     // Queue families allowed to access this buffer.
     queue_families: SmallVec<[u32; 4]>,
 
@@ -149,6 +153,28 @@ impl<T> CpuBufferPool<T> {
     }
 }
 
+impl<T, A> CpuBufferPool<T, A> where A: MemoryPool {
+    /// Same as `new`, but allocates the memory of the buffers from `pool`, in a memory type
+    /// chosen according to `memory_usage`.
+    ///
+    /// `new` uses `MemoryUsage::CpuOnly`. Passing `MemoryUsage::CpuToGpu` puts the buffers in
+    /// memory that is both host-visible and device-local if there is some, which is usually
+    /// faster for data that the GPU reads every frame.
+    ///
+    /// Returns `MemoryUsageNotHostVisible` if `memory_usage` doesn't require host-visible memory.
+    #[inline]
+    pub fn with_pool<'a, I>(device: Arc<Device>, usage: &Usage, queue_families: I, pool: A,
+                            memory_usage: MemoryUsage)
+                            -> Result<CpuBufferPool<T, A>, BufferCreationError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        unsafe {
+            CpuBufferPool::raw_with_pool(device, mem::size_of::<T>(), usage, queue_families, pool,
+                                         memory_usage)
+        }
+    }
+}
+
 impl<T> CpuBufferPool<[T]> {
     #[inline]
     pub fn array<'a, I>(device: Arc<Device>, len: usize, usage: &Usage, queue_families: I)
@@ -162,24 +188,46 @@ impl<T> CpuBufferPool<[T]> {
 }
 
 impl<T: ?Sized> CpuBufferPool<T> {
+    #[inline]
     pub unsafe fn raw<'a, I>(device: Arc<Device>, one_size: usize,
                              usage: &Usage, queue_families: I) -> CpuBufferPool<T>
         where I: IntoIterator<Item = QueueFamily<'a>>
     {
+        let pool = Device::standard_pool(&device);
+        match CpuBufferPool::raw_with_pool(device, one_size, usage, queue_families, pool,
+                                           MemoryUsage::CpuOnly)
+        {
+            Ok(pool) => pool,
+            Err(_) => unreachable!()        // `CpuOnly` requires host-visible memory
+        }
+    }
+}
+
+impl<T: ?Sized, A> CpuBufferPool<T, A> where A: MemoryPool {
+    /// Same as `raw`, but allocates the memory of the buffers from `pool`, in a memory type
+    /// chosen according to `memory_usage`. See `with_pool`.
+    pub unsafe fn raw_with_pool<'a, I>(device: Arc<Device>, one_size: usize, usage: &Usage,
+                                       queue_families: I, pool: A, memory_usage: MemoryUsage)
+                                       -> Result<CpuBufferPool<T, A>, BufferCreationError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        if !memory_usage.requires_host_visible() {
+            return Err(BufferCreationError::MemoryUsageNotHostVisible);
+        }
+
         let queue_families = queue_families.into_iter().map(|f| f.id())
                                            .collect::<SmallVec<[u32; 4]>>();
 
-        let pool = Device::standard_pool(&device);
-
-        CpuBufferPool {
+        Ok(CpuBufferPool {
             device: device,
             pool: pool,
             current_buffer: Mutex::new(None),
             one_size: one_size,
             usage: usage.clone(),
+            memory_usage: memory_usage,
             queue_families: queue_families,
             marker: PhantomData,
-        }
+        })
     }
 
     /// Returns the current capacity of the pool.
@@ -233,13 +281,14 @@ impl<T, A> CpuBufferPool<T, A> where A: MemoryPool, T: 'static {
 
         match self.try_next_impl(&mut mutex, data) {
             Ok(n) => n,
-            Err(_) => unreachable!()
+            Err(_) => panic!("failed to access the memory of a newly allocated buffer"),
         }
     }
 
     /// Grants access to a new subbuffer and puts `data` in it.
     ///
-    /// Returns `None` if no subbuffer is available.
+    /// Returns `None` if no subbuffer is available, or if the memory of the subbuffer couldn't be
+    /// accessed.
     ///
     /// A `CpuBufferPool` is always empty the first time you use it, so you shouldn't use
     /// `try_next` the first time you use it.
@@ -272,10 +321,10 @@ impl<T, A> CpuBufferPool<T, A> where A: MemoryPool, T: 'static {
                 }
             };
 
-            let mem_ty = self.device.physical_device().memory_types()
-                            .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
-                            .filter(|t| t.is_host_visible())
-                            .next().unwrap();    // Vk specs guarantee that this can't fail
+            // Vk specs guarantee that there's always a host-visible memory type for buffers.
+            let mem_ty = self.memory_usage.choose_memory_type(self.device.physical_device(),
+                                                              mem_reqs.memory_type_bits)
+                                          .unwrap();
 
             let mem = try!(MemoryPool::alloc(&self.pool, mem_ty,
                                             mem_reqs.size, mem_reqs.alignment, AllocLayout::Linear));
@@ -333,10 +382,19 @@ impl<T, A> CpuBufferPool<T, A> where A: MemoryPool, T: 'static {
         // Reset num_gpu_accesses.
         current_buffer.subbuffers[next_subbuffer].num_gpu_accesses.store(0, Ordering::SeqCst);
 
-        // Write `data` in the memory.
+        // Write `data` in the memory. If the memory can't be accessed, the subbuffer is released
+        // and the caller allocates a new buffer.
         unsafe {
             let range = (next_subbuffer * self.one_size) .. ((next_subbuffer + 1) * self.one_size);
-            let mut mapping = current_buffer.memory.mapped_memory().unwrap().read_write(range);
+            let memory = current_buffer.memory.mapped_memory().unwrap();
+            let mut mapping = match memory.read_write(range) {
+                Ok(m) => m,
+                Err(_) => {
+                    current_buffer.subbuffers[next_subbuffer].num_cpu_accesses
+                                  .store(0, Ordering::SeqCst);
+                    return Err(data);
+                },
+            };
             *mapping = data;
         }
 
@@ -361,6 +419,7 @@ impl<T: ?Sized, A> Clone for CpuBufferPool<T, A> where A: MemoryPool + Clone {
             current_buffer: Mutex::new(buf.clone()),
             one_size: self.one_size,
             usage: self.usage.clone(),
+            memory_usage: self.memory_usage,
             queue_families: self.queue_families.clone(),
             marker: PhantomData,
         }
@@ -480,8 +539,13 @@ impl<T: ?Sized, A> Drop for CpuBufferPoolSubbuffer<T, A>
 
 #[cfg(test)]
 mod tests {
+    use std::iter;
     use std::mem;
+    use buffer::BufferCreationError;
+    use buffer::BufferUsage;
     use buffer::CpuBufferPool;
+    use device::Device;
+    use memory::MemoryUsage;
 
     #[test]
     fn basic_create() {
@@ -537,4 +601,17 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn memory_usage_not_host_visible() {
+        let (device, _) = mock_dev_and_queue!();
+        let pool = Device::standard_pool(&device);
+
+        match CpuBufferPool::<u8, _>::with_pool(device, &BufferUsage::all(), iter::empty(), pool,
+                                                MemoryUsage::GpuOnly)
+        {
+            Err(BufferCreationError::MemoryUsageNotHostVisible) => (),
+            _ => panic!()
+        }
+    }
 }
@@ -32,9 +32,12 @@ use device::Device;
 use device::DeviceOwned;
 use device::Queue;
 use instance::QueueFamily;
+use memory::DedicatedAlloc;
+use memory::MemoryUsage;
 use memory::pool::AllocLayout;
 use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
+use memory::pool::PotentialDedicatedAllocation;
 use memory::pool::StdMemoryPool;
 use sync::Sharing;
 
@@ -48,7 +51,7 @@ pub struct DeviceLocalBuffer<T: ?Sized, A = Arc<StdMemoryPool>> where A: MemoryP
     inner: UnsafeBuffer,
 
     // The memory held by the buffer.
-    memory: A::Alloc,
+    memory: PotentialDedicatedAllocation<A::Alloc>,
 
     // Queue families allowed to access this buffer.
     queue_families: SmallVec<[u32; 4]>,
@@ -66,9 +69,27 @@ impl<T> DeviceLocalBuffer<T> {
     pub fn new<'a, I>(device: &Arc<Device>, usage: &Usage, queue_families: I)
                       -> Result<Arc<DeviceLocalBuffer<T>>, OomError>
         where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        DeviceLocalBuffer::new_with_pool(device, usage, queue_families,
+                                         &Device::standard_pool(device), MemoryUsage::GpuOnly)
+    }
+}
+
+impl<T, A> DeviceLocalBuffer<T, A> where A: MemoryPool {
+    /// Same as `new`, but allocates the memory of the buffer from `pool`, in a memory type
+    /// chosen according to `memory_usage`.
+    ///
+    /// `new` uses `MemoryUsage::GpuOnly`. The memory of the buffer is never mapped, even if the
+    /// chosen memory type is host-visible.
+    #[inline]
+    pub fn new_with_pool<'a, I>(device: &Arc<Device>, usage: &Usage, queue_families: I,
+                                pool: &A, memory_usage: MemoryUsage)
+                                -> Result<Arc<DeviceLocalBuffer<T, A>>, OomError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
     {
         unsafe {
-            DeviceLocalBuffer::raw(device, mem::size_of::<T>(), usage, queue_families)
+            DeviceLocalBuffer::raw_with_pool(device, mem::size_of::<T>(), usage, queue_families,
+                                             pool, memory_usage)
         }
     }
 }
@@ -79,9 +100,24 @@ impl<T> DeviceLocalBuffer<[T]> {
     pub fn array<'a, I>(device: &Arc<Device>, len: usize, usage: &Usage, queue_families: I)
                       -> Result<Arc<DeviceLocalBuffer<[T]>>, OomError>
         where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        DeviceLocalBuffer::array_with_pool(device, len, usage, queue_families,
+                                           &Device::standard_pool(device), MemoryUsage::GpuOnly)
+    }
+}
+
+impl<T, A> DeviceLocalBuffer<[T], A> where A: MemoryPool {
+    /// Same as `array`, but allocates the memory of the buffer from `pool`, in a memory type
+    /// chosen according to `memory_usage`. See `new_with_pool`.
+    #[inline]
+    pub fn array_with_pool<'a, I>(device: &Arc<Device>, len: usize, usage: &Usage,
+                                  queue_families: I, pool: &A, memory_usage: MemoryUsage)
+                                  -> Result<Arc<DeviceLocalBuffer<[T], A>>, OomError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
     {
         unsafe {
-            DeviceLocalBuffer::raw(device, len * mem::size_of::<T>(), usage, queue_families)
+            DeviceLocalBuffer::raw_with_pool(device, len * mem::size_of::<T>(), usage,
+                                             queue_families, pool, memory_usage)
         }
     }
 }
@@ -93,9 +129,28 @@ impl<T: ?Sized> DeviceLocalBuffer<T> {
     ///
     /// You must ensure that the size that you pass is correct for `T`.
     ///
+    #[inline]
     pub unsafe fn raw<'a, I>(device: &Arc<Device>, size: usize, usage: &Usage, queue_families: I)
                              -> Result<Arc<DeviceLocalBuffer<T>>, OomError>
         where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        DeviceLocalBuffer::raw_with_pool(device, size, usage, queue_families,
+                                         &Device::standard_pool(device), MemoryUsage::GpuOnly)
+    }
+}
+
+impl<T: ?Sized, A> DeviceLocalBuffer<T, A> where A: MemoryPool {
+    /// Same as `raw`, but allocates the memory of the buffer from `pool`, in a memory type
+    /// chosen according to `memory_usage`. See `new_with_pool`.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that the size that you pass is correct for `T`.
+    ///
+    pub unsafe fn raw_with_pool<'a, I>(device: &Arc<Device>, size: usize, usage: &Usage,
+                                       queue_families: I, pool: &A, memory_usage: MemoryUsage)
+                                       -> Result<Arc<DeviceLocalBuffer<T, A>>, OomError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
     {
         let queue_families = queue_families.into_iter().map(|f| f.id())
                                            .collect::<SmallVec<[u32; 4]>>();
@@ -115,17 +170,13 @@ impl<T: ?Sized> DeviceLocalBuffer<T> {
             }
         };
 
-        let mem_ty = {
-            let device_local = device.physical_device().memory_types()
-                                     .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
-                                     .filter(|t| t.is_device_local());
-            let any = device.physical_device().memory_types()
-                            .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0);
-            device_local.chain(any).next().unwrap()
-        };
+        // Vk specs guarantee that there's always a host-visible memory type for buffers.
+        let mem_ty = memory_usage.choose_memory_type(device.physical_device(),
+                                                     mem_reqs.memory_type_bits).unwrap();
 
-        let mem = try!(MemoryPool::alloc(&Device::standard_pool(device), mem_ty,
-                                         mem_reqs.size, mem_reqs.alignment, AllocLayout::Linear));
+        let mem = try!(MemoryPool::alloc_from_requirements(pool, device, mem_ty, &mem_reqs,
+                                                           AllocLayout::Linear,
+                                                           DedicatedAlloc::Buffer(&buffer)));
         debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
         try!(buffer.bind_memory(mem.memory(), mem.offset()));
 
@@ -222,3 +273,28 @@ unsafe impl<P, T: ?Sized, A> DeviceOwned for DeviceLocalBufferAccess<P>
         self.0.inner.device()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buffer::BufferUsage;
+    use buffer::device_local::DeviceLocalBuffer;
+    use device::Device;
+    use memory::MemoryUsage;
+    use memory::pool::MemoryPoolAlloc;
+
+    #[test]
+    fn memory_usage() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let buffer = DeviceLocalBuffer::<u32>::new(&device, &BufferUsage::all(),
+                                                   Some(queue.family())).unwrap();
+        assert!(buffer.memory.memory().memory_type().is_device_local());
+
+        let buffer = DeviceLocalBuffer::<[u32]>::array_with_pool(&device, 16,
+                                                                 &BufferUsage::all(),
+                                                                 Some(queue.family()),
+                                                                 &Device::standard_pool(&device),
+                                                                 MemoryUsage::GpuToCpu).unwrap();
+        assert!(buffer.memory.memory().memory_type().is_host_visible());
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Reading back the content of a buffer that isn't accessible from the CPU.
+//!
+//! A `BufferDownload` copies the content of a buffer to a temporary CPU-accessible buffer on the
+//! GPU, and lets you retrieve it once the copy is finished.
+
+use std::error;
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+use std::time::Duration;
+
+use buffer::Buffer;
+use buffer::BufferAccess;
+use buffer::BufferUsage;
+use buffer::CpuAccessibleBuffer;
+use buffer::TypedBuffer;
+use buffer::cpu_access::LockError;
+use command_buffer::AutoCommandBuffer;
+use command_buffer::AutoCommandBufferBuilder;
+use command_buffer::CommandBuffer;
+use command_buffer::CommandBufferBuild;
+use command_buffer::CommandBufferBuilder;
+use command_buffer::CommandBufferExecFuture;
+use device::Queue;
+use sync::DummyFuture;
+use sync::FenceSignalFuture;
+use sync::GpuFuture;
+use OomError;
+
+/// Copy of the content of a buffer to the CPU that is being executed by the GPU.
+///
+/// # Example
+///
+/// ```ignore
+/// let download = BufferDownload::new(buffer.clone(), queue.clone()).unwrap();
+/// let content: Vec<u32> = download.into_content().unwrap();
+/// ```
+#[must_use = "Dropping this object will immediately block the thread until the GPU has finished processing the copy"]
+pub struct BufferDownload<T> {
+    // Buffer the content is copied to.
+    buffer: Arc<CpuAccessibleBuffer<[T]>>,
+    // Future that is signaled when the copy is finished.
+    future: FenceSignalFuture<CommandBufferExecFuture<DummyFuture, AutoCommandBuffer>>,
+}
+
+impl<T> BufferDownload<T> where T: Clone + Send + Sync + 'static {
+    /// Records a command buffer that copies the content of `source` to the CPU, and submits it
+    /// to `queue`.
+    ///
+    /// Returns an error if `source` doesn't have the transfer source usage, or if `T` is a
+    /// zero-sized type.
+    pub fn new<B>(source: B, queue: Arc<Queue>) -> Result<BufferDownload<T>, BufferDownloadError>
+        where B: Buffer + Clone,
+              B::Access: TypedBuffer<Content = [T]> + Clone + Send + Sync + 'static
+    {
+        let source_access = source.clone().access();
+        if !source_access.inner().buffer.usage_transfer_src() {
+            return Err(BufferDownloadError::MissingTransferSourceUsage);
+        }
+
+        if mem::size_of::<T>() == 0 {
+            return Err(BufferDownloadError::ZeroSizedType);
+        }
+
+        let len = source_access.size() / mem::size_of::<T>();
+        let buffer = unsafe {
+            try!(CpuAccessibleBuffer::uninitialized_array(queue.device(), len,
+                                                          &BufferUsage::transfer_dest(),
+                                                          Some(queue.family())))
+        };
+
+        let cb = {
+            let cb = try!(AutoCommandBufferBuilder::new(queue.device().clone(), queue.family()));
+            // Can't fail, since the usages of both buffers have been checked.
+            let cb = cb.copy_buffer(source, buffer.clone()).unwrap();
+            try!(CommandBufferBuild::build(cb))
+        };
+
+        let future = cb.execute(queue).then_signal_fence();
+        try!(future.flush());
+
+        Ok(BufferDownload {
+            buffer: buffer,
+            future: future,
+        })
+    }
+
+    /// Blocks until the copy is finished or the timeout is reached.
+    ///
+    /// A value of `0` for the timeout is valid and means that the function should never block.
+    #[inline]
+    pub fn wait(&self, timeout: Duration) -> Result<(), BufferDownloadError> {
+        try!(self.future.wait(timeout));
+        Ok(())
+    }
+
+    /// Blocks until the copy is finished, then returns the content of the buffer.
+    pub fn into_content(self) -> Result<Vec<T>, BufferDownloadError> {
+        // Destroying the future waits for the copy to be finished.
+        drop(self.future);
+
+        // Can only fail if the memory can't be invalidated, since nobody else has access to the
+        // buffer.
+        let content = match self.buffer.read() {
+            Ok(c) => c,
+            Err(LockError::OomError(err)) => return Err(BufferDownloadError::OomError(err)),
+            Err(LockError::AlreadyLocked) => unreachable!(),
+        };
+        Ok(content.to_vec())
+    }
+}
+
+/// Error that can happen when downloading the content of a buffer.
+#[derive(Debug)]
+pub enum BufferDownloadError {
+    /// Not enough memory to create the destination buffer or the command buffer.
+    OomError(OomError),
+
+    /// Error while submitting the copy or waiting for it to finish.
+    FutureError(Box<error::Error>),
+
+    /// The source buffer is missing the transfer source usage.
+    MissingTransferSourceUsage,
+
+    /// The content of the buffer is an array of a zero-sized type.
+    ZeroSizedType,
+}
+
+impl error::Error for BufferDownloadError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            BufferDownloadError::OomError(_) => "not enough memory available",
+            BufferDownloadError::FutureError(_) => "error while submitting or waiting for the copy",
+            BufferDownloadError::MissingTransferSourceUsage => {
+                "the source buffer is missing the transfer source usage"
+            },
+            BufferDownloadError::ZeroSizedType => {
+                "the content of the buffer is an array of a zero-sized type"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            BufferDownloadError::OomError(ref err) => Some(err),
+            BufferDownloadError::FutureError(ref err) => Some(&**err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for BufferDownloadError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for BufferDownloadError {
+    #[inline]
+    fn from(err: OomError) -> BufferDownloadError {
+        BufferDownloadError::OomError(err)
+    }
+}
+
+impl From<Box<error::Error>> for BufferDownloadError {
+    #[inline]
+    fn from(err: Box<error::Error>) -> BufferDownloadError {
+        BufferDownloadError::FutureError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use buffer::download::BufferDownload;
+    use buffer::download::BufferDownloadError;
+    use mock;
+
+    #[test]
+    fn download() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let source = CpuAccessibleBuffer::from_iter(&device, &BufferUsage::transfer_source(),
+                                                    Some(queue.family()), 0 .. 16u32).unwrap();
+
+        let download = BufferDownload::new(source, queue.clone()).unwrap();
+        assert_eq!(download.into_content().unwrap().len(), 16);
+
+        let calls = mock::calls(&device);
+        assert!(calls.contains(&"CmdCopyBuffer"));
+        assert!(calls.contains(&"QueueSubmit"));
+    }
+
+    #[test]
+    fn missing_usage() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let source = CpuAccessibleBuffer::from_iter(&device, &BufferUsage::transfer_dest(),
+                                                    Some(queue.family()), 0 .. 16u32).unwrap();
+        match BufferDownload::new(source, queue.clone()) {
+            Err(BufferDownloadError::MissingTransferSourceUsage) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn zero_sized_type() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let source = unsafe {
+            CpuAccessibleBuffer::<[()]>::raw(&device, 16, &BufferUsage::transfer_source(),
+                                             Some(queue.family())).unwrap()
+        };
+        match BufferDownload::new(source, queue.clone()) {
+            Err(BufferDownloadError::ZeroSizedType) => (),
+            _ => panic!()
+        }
+    }
+}
@@ -11,19 +11,27 @@
 //! 
 //! Use this buffer when you have data that you never modify.
 //!
-//! Only the first ever command buffer that uses this buffer can write to it (for example by
-//! copying from another buffer). Any subsequent command buffer **must** only read from the buffer,
-//! or a panic will happen.
+//! The buffer is created along with an `ImmutableBufferInitialization` object, which is the only
+//! way to write to it (for example by copying from another buffer). The initialization object can
+//! only be used in a single submission, and the GPU can't access the buffer itself before this
+//! submission. Any subsequent command buffer can then only read from the buffer.
 //! 
 //! The buffer will be stored in device-local memory if possible
 //!
+//! The easiest way to create an `ImmutableBuffer` is with `from_data`, `from_iter` or
+//! `from_buffer`. These functions upload the content through a temporary CPU-accessible buffer and
+//! return a future that represents the upload. Any operation that uses the buffer must be
+//! submitted after this future.
+//!
 
 use std::marker::PhantomData;
 use std::mem;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use smallvec::SmallVec;
 
+use buffer::cpu_access::CpuAccessibleBuffer;
 use buffer::sys::BufferCreationError;
 use buffer::sys::SparseLevel;
 use buffer::sys::UnsafeBuffer;
@@ -32,14 +40,22 @@ use buffer::traits::BufferAccess;
 use buffer::traits::BufferInner;
 use buffer::traits::Buffer;
 use buffer::traits::TypedBuffer;
+use command_buffer::AutoCommandBuffer;
+use command_buffer::AutoCommandBufferBuilder;
+use command_buffer::CommandBuffer;
+use command_buffer::CommandBufferBuild;
+use command_buffer::CommandBufferBuilder;
+use command_buffer::CommandBufferExecFuture;
 use device::Device;
 use device::DeviceOwned;
 use device::Queue;
 use instance::QueueFamily;
+use memory::MemoryUsage;
 use memory::pool::AllocLayout;
 use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
 use memory::pool::StdMemoryPool;
+use sync::DummyFuture;
 use sync::Sharing;
 
 use OomError;
@@ -54,46 +70,206 @@ pub struct ImmutableBuffer<T: ?Sized, A = Arc<StdMemoryPool>> where A: MemoryPoo
     // Queue families allowed to access this buffer.
     queue_families: SmallVec<[u32; 4]>,
 
-    started_reading: AtomicBool,
+    // True if the initialization of the buffer has been submitted.
+    initialized: AtomicBool,
 
     marker: PhantomData<Box<T>>,
 }
 
 impl<T> ImmutableBuffer<T> {
-    /// Builds a new buffer. Only allowed for sized data.
+    /// Builds a new buffer with uninitialized data. Only allowed for sized data.
+    ///
+    /// Returns two things: the buffer, and a special access that should be used for the initial
+    /// upload to the buffer. The GPU can't access the buffer before the initialization has been
+    /// submitted.
+    #[inline]
+    pub fn uninitialized<'a, I>(device: &Arc<Device>, usage: &Usage, queue_families: I)
+                                -> Result<(Arc<ImmutableBuffer<T>>,
+                                           Arc<ImmutableBufferInitialization<T>>), OomError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        ImmutableBuffer::uninitialized_with_pool(device, usage, queue_families,
+                                                 &Device::standard_pool(device),
+                                                 MemoryUsage::GpuOnly)
+    }
+
+    /// Builds an `ImmutableBuffer` from some data.
+    ///
+    /// The data is written to a temporary CPU-accessible buffer, and a command buffer that copies
+    /// it to the new buffer is submitted to `queue`. The buffer can only be used on the family of
+    /// this queue.
+    ///
+    /// Returns the buffer and a future that represents the upload. You must submit the operations
+    /// that use the buffer after this future.
+    pub fn from_data(data: T, usage: &Usage, queue: Arc<Queue>)
+                     -> Result<(Arc<ImmutableBuffer<T>>, ImmutableBufferFromBufferFuture), OomError>
+        where T: 'static + Send + Sync
+    {
+        let source = try!(CpuAccessibleBuffer::from_data(queue.device(), &Usage::transfer_source(),
+                                                         Some(queue.family()), data));
+        match ImmutableBuffer::from_buffer(source, usage, queue) {
+            Ok(r) => Ok(r),
+            Err(BufferCreationError::OomError(err)) => Err(err),
+            Err(_) => unreachable!()        // The source has the transfer source usage.
+        }
+    }
+}
+
+impl<T, A> ImmutableBuffer<T, A> where A: MemoryPool {
+    /// Same as `uninitialized`, but allocates the memory of the buffer from `pool`, in a memory
+    /// type chosen according to `memory_usage`.
+    ///
+    /// `uninitialized` uses `MemoryUsage::GpuOnly`. The memory of the buffer is never mapped, even
+    /// if the chosen memory type is host-visible.
     #[inline]
-    pub fn new<'a, I>(device: &Arc<Device>, usage: &Usage, queue_families: I)
-                      -> Result<Arc<ImmutableBuffer<T>>, OomError>
+    pub fn uninitialized_with_pool<'a, I>(device: &Arc<Device>, usage: &Usage, queue_families: I,
+                                          pool: &A, memory_usage: MemoryUsage)
+                                          -> Result<(Arc<ImmutableBuffer<T, A>>,
+                                                     Arc<ImmutableBufferInitialization<T, A>>),
+                                                    OomError>
         where I: IntoIterator<Item = QueueFamily<'a>>
     {
         unsafe {
-            ImmutableBuffer::raw(device, mem::size_of::<T>(), usage, queue_families)
+            ImmutableBuffer::raw_with_pool(device, mem::size_of::<T>(), usage, queue_families,
+                                           pool, memory_usage)
         }
     }
 }
 
 impl<T> ImmutableBuffer<[T]> {
-    /// Builds a new buffer. Can be used for arrays.
+    /// Builds a new buffer with uninitialized data. Can be used for arrays.
+    ///
+    /// See `uninitialized` for more information.
     #[inline]
-    pub fn array<'a, I>(device: &Arc<Device>, len: usize, usage: &Usage, queue_families: I)
-                      -> Result<Arc<ImmutableBuffer<[T]>>, OomError>
+    pub fn uninitialized_array<'a, I>(device: &Arc<Device>, len: usize, usage: &Usage,
+                                      queue_families: I)
+                                      -> Result<(Arc<ImmutableBuffer<[T]>>,
+                                                 Arc<ImmutableBufferInitialization<[T]>>),
+                                                OomError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        ImmutableBuffer::uninitialized_array_with_pool(device, len, usage, queue_families,
+                                                       &Device::standard_pool(device),
+                                                       MemoryUsage::GpuOnly)
+    }
+
+    /// Builds an `ImmutableBuffer` from an iterator of data.
+    ///
+    /// Same as `from_data`, but for arrays.
+    pub fn from_iter<D>(data: D, usage: &Usage, queue: Arc<Queue>)
+                        -> Result<(Arc<ImmutableBuffer<[T]>>, ImmutableBufferFromBufferFuture),
+                                  OomError>
+        where D: ExactSizeIterator<Item = T>,
+              T: 'static + Send + Sync
+    {
+        let source = try!(CpuAccessibleBuffer::from_iter(queue.device(), &Usage::transfer_source(),
+                                                         Some(queue.family()), data));
+        match ImmutableBuffer::from_buffer(source, usage, queue) {
+            Ok(r) => Ok(r),
+            Err(BufferCreationError::OomError(err)) => Err(err),
+            Err(_) => unreachable!()        // The source has the transfer source usage.
+        }
+    }
+}
+
+impl<T: ?Sized> ImmutableBuffer<T> {
+    /// Builds an `ImmutableBuffer` that has the same size and content as an existing buffer.
+    ///
+    /// A command buffer that copies `source` to the new buffer is submitted to `queue`. The
+    /// buffer can only be used on the family of this queue. The transfer destination usage is
+    /// automatically added to `usage`.
+    ///
+    /// Returns the buffer and a future that represents the copy. You must submit the operations
+    /// that use the buffer after this future.
+    ///
+    /// Returns `SourceMissingTransferUsage` if `source` doesn't have the transfer source usage.
+    pub fn from_buffer<B>(source: B, usage: &Usage, queue: Arc<Queue>)
+                          -> Result<(Arc<ImmutableBuffer<T>>, ImmutableBufferFromBufferFuture),
+                                    BufferCreationError>
+        where B: Buffer + Clone,
+              B::Access: TypedBuffer<Content = T> + Clone + Send + Sync + 'static,
+              T: 'static + Send + Sync
+    {
+        let source_access = source.clone().access();
+        if !source_access.inner().buffer.usage_transfer_src() {
+            return Err(BufferCreationError::SourceMissingTransferUsage);
+        }
+
+        let usage = Usage {
+            transfer_dest: true,
+            .. *usage
+        };
+
+        let (buffer, init) = unsafe {
+            try!(ImmutableBuffer::raw(queue.device(), source_access.size(), &usage,
+                                      Some(queue.family())))
+        };
+
+        let cb = {
+            let cb = try!(AutoCommandBufferBuilder::new(queue.device().clone(), queue.family()));
+            // Can't fail, since the usages of both buffers have been checked.
+            let cb = cb.copy_buffer(source, init).unwrap();
+            try!(CommandBufferBuild::build(cb))
+        };
+
+        let future = cb.execute(queue);
+
+        Ok((buffer, future))
+    }
+}
+
+impl<T, A> ImmutableBuffer<[T], A> where A: MemoryPool {
+    /// Same as `uninitialized_array`, but allocates the memory of the buffer from `pool`, in a
+    /// memory type chosen according to `memory_usage`. See `uninitialized_with_pool`.
+    #[inline]
+    pub fn uninitialized_array_with_pool<'a, I>(device: &Arc<Device>, len: usize, usage: &Usage,
+                                                queue_families: I, pool: &A,
+                                                memory_usage: MemoryUsage)
+                                                -> Result<(Arc<ImmutableBuffer<[T], A>>,
+                                                  Arc<ImmutableBufferInitialization<[T], A>>),
+                                                  OomError>
         where I: IntoIterator<Item = QueueFamily<'a>>
     {
         unsafe {
-            ImmutableBuffer::raw(device, len * mem::size_of::<T>(), usage, queue_families)
+            ImmutableBuffer::raw_with_pool(device, len * mem::size_of::<T>(), usage,
+                                           queue_families, pool, memory_usage)
         }
     }
 }
 
 impl<T: ?Sized> ImmutableBuffer<T> {
-    /// Builds a new buffer without checking the size.
+    /// Builds a new buffer with uninitialized data, without checking the size.
+    ///
+    /// See `uninitialized` for more information.
     ///
     /// # Safety
     ///
     /// You must ensure that the size that you pass is correct for `T`.
     ///
+    #[inline]
     pub unsafe fn raw<'a, I>(device: &Arc<Device>, size: usize, usage: &Usage, queue_families: I)
-                             -> Result<Arc<ImmutableBuffer<T>>, OomError>
+                             -> Result<(Arc<ImmutableBuffer<T>>,
+                                        Arc<ImmutableBufferInitialization<T>>), OomError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        ImmutableBuffer::raw_with_pool(device, size, usage, queue_families,
+                                       &Device::standard_pool(device), MemoryUsage::GpuOnly)
+    }
+}
+
+impl<T: ?Sized, A> ImmutableBuffer<T, A> where A: MemoryPool {
+    /// Same as `raw`, but allocates the memory of the buffer from `pool`, in a memory type
+    /// chosen according to `memory_usage`. See `uninitialized_with_pool`.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that the size that you pass is correct for `T`.
+    ///
+    pub unsafe fn raw_with_pool<'a, I>(device: &Arc<Device>, size: usize, usage: &Usage,
+                                       queue_families: I, pool: &A, memory_usage: MemoryUsage)
+                                       -> Result<(Arc<ImmutableBuffer<T, A>>,
+                                                  Arc<ImmutableBufferInitialization<T, A>>),
+                                                 OomError>
         where I: IntoIterator<Item = QueueFamily<'a>>
     {
         let queue_families = queue_families.into_iter().map(|f| f.id())
@@ -114,30 +290,35 @@ impl<T: ?Sized> ImmutableBuffer<T> {
             }
         };
 
-        let mem_ty = {
-            let device_local = device.physical_device().memory_types()
-                                     .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
-                                     .filter(|t| t.is_device_local());
-            let any = device.physical_device().memory_types()
-                            .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0);
-            device_local.chain(any).next().unwrap()
-        };
+        // Vk specs guarantee that there's always a host-visible memory type for buffers.
+        let mem_ty = memory_usage.choose_memory_type(device.physical_device(),
+                                                     mem_reqs.memory_type_bits).unwrap();
 
-        let mem = try!(MemoryPool::alloc(&Device::standard_pool(device), mem_ty,
-                                         mem_reqs.size, mem_reqs.alignment, AllocLayout::Linear));
+        let mem = try!(MemoryPool::alloc(pool, mem_ty, mem_reqs.size, mem_reqs.alignment,
+                                         AllocLayout::Linear));
         debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
         try!(buffer.bind_memory(mem.memory(), mem.offset()));
 
-        Ok(Arc::new(ImmutableBuffer {
+        let buffer = Arc::new(ImmutableBuffer {
             inner: buffer,
             memory: mem,
             queue_families: queue_families,
-            started_reading: AtomicBool::new(false),
+            initialized: AtomicBool::new(false),
             marker: PhantomData,
-        }))
+        });
+
+        let init = Arc::new(ImmutableBufferInitialization {
+            buffer: buffer.clone(),
+            used: AtomicBool::new(false),
+        });
+
+        Ok((buffer, init))
     }
 }
 
+/// Future that represents the upload of the content of an `ImmutableBuffer`.
+pub type ImmutableBufferFromBufferFuture = CommandBufferExecFuture<DummyFuture, AutoCommandBuffer>;
+
 impl<T: ?Sized, A> ImmutableBuffer<T, A> where A: MemoryPool {
     /// Returns the device used to create this buffer.
     #[inline]
@@ -179,13 +360,15 @@ unsafe impl<T: ?Sized, A> BufferAccess for ImmutableBuffer<T, A>
     }
 
     #[inline]
-    fn try_gpu_lock(&self, exclusive_access: bool, queue: &Queue) -> bool {
-        true       // FIXME:
+    fn try_gpu_lock(&self, exclusive_access: bool, _: &Queue) -> bool {
+        // The buffer can only be written through its `ImmutableBufferInitialization`, and can't
+        // be read before the initialization has been submitted.
+        !exclusive_access && self.initialized.load(Ordering::SeqCst)
     }
 
     #[inline]
     unsafe fn increase_gpu_lock(&self) {
-        // FIXME:
+        debug_assert!(self.initialized.load(Ordering::SeqCst));
     }
 }
 
@@ -203,3 +386,143 @@ unsafe impl<T: ?Sized, A> DeviceOwned for ImmutableBuffer<T, A>
         self.inner.device()
     }
 }
+
+/// Access to the content of an `ImmutableBuffer` that must be used to initialize it.
+///
+/// Obtained along with the buffer when it is created. The object can only be used in a single
+/// submission, after which the buffer itself can be read by the GPU.
+pub struct ImmutableBufferInitialization<T: ?Sized, A = Arc<StdMemoryPool>> where A: MemoryPool {
+    buffer: Arc<ImmutableBuffer<T, A>>,
+    // True if the object has already been used in a submission.
+    used: AtomicBool,
+}
+
+impl<T: ?Sized, A> ImmutableBufferInitialization<T, A> where A: MemoryPool {
+    /// Returns the buffer being initialized.
+    #[inline]
+    pub fn buffer(&self) -> &Arc<ImmutableBuffer<T, A>> {
+        &self.buffer
+    }
+}
+
+unsafe impl<T: ?Sized, A> Buffer for Arc<ImmutableBufferInitialization<T, A>>
+    where T: 'static + Send + Sync, A: MemoryPool
+{
+    type Access = Self;
+
+    #[inline]
+    fn access(self) -> Self {
+        self
+    }
+}
+
+unsafe impl<T: ?Sized, A> BufferAccess for ImmutableBufferInitialization<T, A>
+    where T: 'static + Send + Sync, A: MemoryPool
+{
+    #[inline]
+    fn inner(&self) -> BufferInner {
+        self.buffer.inner()
+    }
+
+    #[inline]
+    fn try_gpu_lock(&self, _: bool, _: &Queue) -> bool {
+        if self.used.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+
+        self.buffer.initialized.store(true, Ordering::SeqCst);
+        true
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self) {
+        debug_assert!(self.used.load(Ordering::SeqCst));
+    }
+}
+
+unsafe impl<T: ?Sized, A> TypedBuffer for ImmutableBufferInitialization<T, A>
+    where T: 'static + Send + Sync, A: MemoryPool
+{
+    type Content = T;
+}
+
+unsafe impl<T: ?Sized, A> DeviceOwned for ImmutableBufferInitialization<T, A>
+    where A: MemoryPool
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.buffer.inner.device()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::BufferAccess;
+    use buffer::BufferUsage;
+    use buffer::cpu_access::CpuAccessibleBuffer;
+    use buffer::immutable::ImmutableBuffer;
+    use buffer::sys::BufferCreationError;
+    use mock;
+    use sync::GpuFuture;
+
+    #[test]
+    fn from_data() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let (buffer, future) = ImmutableBuffer::from_data(12u32, &BufferUsage::all(),
+                                                          queue.clone()).unwrap();
+        future.flush().unwrap();
+
+        assert_eq!(buffer.size(), 4);
+        let calls = mock::calls(&device);
+        assert!(calls.contains(&"CmdCopyBuffer"));
+        assert!(calls.contains(&"QueueSubmit"));
+    }
+
+    #[test]
+    fn from_iter() {
+        let (_, queue) = gfx_dev_and_queue!();
+
+        let (buffer, _future) = ImmutableBuffer::from_iter((0 .. 512u32).map(|n| n * 2),
+                                                           &BufferUsage::all(),
+                                                           queue.clone()).unwrap();
+        assert_eq!(buffer.size(), 512 * 4);
+    }
+
+    #[test]
+    fn gpu_access_requires_initialization() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let (buffer, init) = ImmutableBuffer::<u32>::uninitialized(&device, &BufferUsage::all(),
+                                                                   Some(queue.family())).unwrap();
+        assert!(!buffer.try_gpu_lock(false, &queue));
+
+        assert!(init.try_gpu_lock(true, &queue));
+        assert!(!init.try_gpu_lock(true, &queue));
+
+        assert!(buffer.try_gpu_lock(false, &queue));
+        assert!(!buffer.try_gpu_lock(true, &queue));
+    }
+
+    #[test]
+    fn from_data_initializes() {
+        let (_, queue) = mock_dev_and_queue!();
+
+        let (buffer, _future) = ImmutableBuffer::from_data(12u32, &BufferUsage::all(),
+                                                           queue.clone()).unwrap();
+        assert!(buffer.try_gpu_lock(false, &queue));
+    }
+
+    #[test]
+    fn from_buffer_missing_usage() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let usage = BufferUsage { uniform_buffer: true, .. BufferUsage::none() };
+        let source = CpuAccessibleBuffer::from_data(&device, &usage, Some(queue.family()),
+                                                    12u32).unwrap();
+        match ImmutableBuffer::from_buffer(source, &BufferUsage::all(), queue.clone()) {
+            Err(BufferCreationError::SourceMissingTransferUsage) => (),
+            _ => panic!()
+        }
+    }
+}
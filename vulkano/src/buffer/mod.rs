@@ -36,6 +36,9 @@
 //! If you just want to get started, you can use the `CpuAccessibleBuffer` everywhere, as it is
 //! the most flexible type of buffer.
 //!
+//! The content of a buffer that isn't accessible from the CPU can be read back with a
+//! `BufferDownload`.
+//!
 //! # Buffers usage
 //!
 //! When you create a buffer object, you have to specify its *usage*. In other words, you have to
@@ -65,6 +68,8 @@
 pub use self::cpu_access::CpuAccessibleBuffer;
 pub use self::cpu_pool::CpuBufferPool;
 pub use self::device_local::DeviceLocalBuffer;
+pub use self::download::BufferDownload;
+pub use self::download::BufferDownloadError;
 pub use self::immutable::ImmutableBuffer;
 pub use self::slice::BufferSlice;
 pub use self::sys::BufferCreationError;
@@ -79,6 +84,7 @@ pub use self::view::BufferViewRef;
 pub mod cpu_access;
 pub mod cpu_pool;
 pub mod device_local;
+pub mod download;
 pub mod immutable;
 pub mod sys;
 pub mod view;
@@ -113,12 +113,46 @@ impl UnsafeBuffer {
         let mem_reqs = {
             #[inline] fn align(val: usize, al: usize) -> usize { al * (1 + (val - 1) / al) }
 
-            let mut output: vk::MemoryRequirements = mem::uninitialized();
-            vk.GetBufferMemoryRequirements(device.internal_object(), buffer, &mut output);
-            debug_assert!(output.size >= size as u64);
-            debug_assert!(output.memoryTypeBits != 0);
-
-            let mut output: MemoryRequirements = output.into();
+            let mut output = if device.loaded_extensions().khr_get_memory_requirements2 {
+                let infos = vk::BufferMemoryRequirementsInfo2KHR {
+                    sType: vk::STRUCTURE_TYPE_BUFFER_MEMORY_REQUIREMENTS_INFO_2_KHR,
+                    pNext: ptr::null(),
+                    buffer: buffer,
+                };
+
+                let mut dedicated = vk::MemoryDedicatedRequirementsKHR {
+                    sType: vk::STRUCTURE_TYPE_MEMORY_DEDICATED_REQUIREMENTS_KHR,
+                    pNext: ptr::null_mut(),
+                    prefersDedicatedAllocation: vk::FALSE,
+                    requiresDedicatedAllocation: vk::FALSE,
+                };
+
+                let mut output = vk::MemoryRequirements2KHR {
+                    sType: vk::STRUCTURE_TYPE_MEMORY_REQUIREMENTS_2_KHR,
+                    pNext: if device.loaded_extensions().khr_dedicated_allocation {
+                        &mut dedicated as *mut _ as *mut _
+                    } else {
+                        ptr::null_mut()
+                    },
+                    memoryRequirements: mem::uninitialized(),
+                };
+
+                vk.GetBufferMemoryRequirements2KHR(device.internal_object(), &infos, &mut output);
+                debug_assert!(output.memoryRequirements.size >= size as u64);
+                debug_assert!(output.memoryRequirements.memoryTypeBits != 0);
+
+                let mut output: MemoryRequirements = output.memoryRequirements.into();
+                output.prefers_dedicated = dedicated.prefersDedicatedAllocation != vk::FALSE;
+                output.requires_dedicated = dedicated.requiresDedicatedAllocation != vk::FALSE;
+                output
+
+            } else {
+                let mut output: vk::MemoryRequirements = mem::uninitialized();
+                vk.GetBufferMemoryRequirements(device.internal_object(), buffer, &mut output);
+                debug_assert!(output.size >= size as u64);
+                debug_assert!(output.memoryTypeBits != 0);
+                output.into()
+            };
 
             // We have to manually enforce some additional requirements for some buffer types.
             let limits = device.physical_device().limits();
@@ -470,6 +504,12 @@ pub enum BufferCreationError {
     SparseResidencyBufferFeatureNotEnabled,
     /// Sparse aliasing was requested but the corresponding feature wasn't enabled.
     SparseResidencyAliasedFeatureNotEnabled,
+    /// The buffer must be accessible from the CPU, but the requested memory usage doesn't
+    /// require host-visible memory.
+    MemoryUsageNotHostVisible,
+    /// The buffer that holds the initial content of the buffer is missing the transfer source
+    /// usage.
+    SourceMissingTransferUsage,
 }
 
 impl error::Error for BufferCreationError {
@@ -486,6 +526,14 @@ impl error::Error for BufferCreationError {
             BufferCreationError::SparseResidencyAliasedFeatureNotEnabled => {
                 "sparse aliasing was requested but the corresponding feature wasn't enabled"
             },
+            BufferCreationError::MemoryUsageNotHostVisible => {
+                "the buffer must be accessible from the CPU, but the requested memory usage \
+                 doesn't require host-visible memory"
+            },
+            BufferCreationError::SourceMissingTransferUsage => {
+                "the buffer that holds the initial content of the buffer is missing the transfer \
+                 source usage"
+            },
         }
     }
 
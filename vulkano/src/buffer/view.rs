@@ -31,8 +31,9 @@
 //!     .. Usage::none()
 //! };
 //!
-//! let buffer = ImmutableBuffer::<[u32]>::array(&device, 128, &usage,
-//!                                              Some(queue.family())).unwrap();
+//! let (buffer, _) =
+//!     ImmutableBuffer::<[u32]>::uninitialized_array(&device, 128, &usage,
+//!                                                   Some(queue.family())).unwrap();
 //! let _view = BufferView::new(&buffer, format::R32Uint).unwrap();
 //! ```
 
@@ -48,7 +49,9 @@ use buffer::BufferInner;
 use buffer::TypedBuffer;
 use device::Device;
 use device::DeviceOwned;
+use format::Format;
 use format::FormatDesc;
+use format::FormatFeatures;
 use format::StrongStorage;
 
 use Error;
@@ -105,24 +108,19 @@ impl<F, B> BufferView<F, B> where B: BufferAccess {
                 }
             }
 
-            let format_props = {
-                let vk_i = device.instance().pointers();
-                let mut output = mem::uninitialized();
-                vk_i.GetPhysicalDeviceFormatProperties(device.physical_device().internal_object(),
-                                                    format as u32, &mut output);
-                output.bufferFeatures
-            };
+            let format_props = format.properties(device.physical_device()).buffer_features;
 
-            if buffer.usage_uniform_texel_buffer() {
-                if (format_props & vk::FORMAT_FEATURE_UNIFORM_TEXEL_BUFFER_BIT) == 0 {
-                    return Err(BufferViewCreationError::UnsupportedFormat);
-                }
-            }
+            let required = FormatFeatures {
+                uniform_texel_buffer: buffer.usage_uniform_texel_buffer(),
+                storage_texel_buffer: buffer.usage_storage_texel_buffer(),
+                .. FormatFeatures::none()
+            };
 
-            if buffer.usage_storage_texel_buffer() {
-                if (format_props & vk::FORMAT_FEATURE_STORAGE_TEXEL_BUFFER_BIT) == 0 {
-                    return Err(BufferViewCreationError::UnsupportedFormat);
-                }
+            if !format_props.superset_of(&required) {
+                return Err(BufferViewCreationError::UnsupportedFormat {
+                    format: format,
+                    missing_features: required.difference(&format_props),
+                });
             }
 
             let infos = vk::BufferViewCreateInfo {
@@ -146,8 +144,7 @@ impl<F, B> BufferView<F, B> where B: BufferAccess {
             view: view,
             buffer: org_buffer,
             marker: PhantomData,
-            atomic_accesses: (format_props &
-                              vk::FORMAT_FEATURE_STORAGE_TEXEL_BUFFER_ATOMIC_BIT) != 0,
+            atomic_accesses: format_props.storage_texel_buffer_atomic,
         }))
     }
 
@@ -242,8 +239,14 @@ pub enum BufferViewCreationError {
     /// `uniform_texel_buffer` usages.
     WrongBufferUsage,
 
-    /// The requested format is not supported for this usage.
-    UnsupportedFormat,
+    /// The requested format is not supported for the usage of the buffer.
+    UnsupportedFormat {
+        /// The format of the view.
+        format: Format,
+        /// The features that the usage of the buffer requires but that the format doesn't
+        /// support.
+        missing_features: FormatFeatures,
+    },
 
     /// The maximum number of elements in the buffer view has been exceeded.
     MaxTexelBufferElementsExceeded,
@@ -256,8 +259,9 @@ impl error::Error for BufferViewCreationError {
             BufferViewCreationError::OomError(_) => "out of memory when creating buffer view",
             BufferViewCreationError::WrongBufferUsage => "the buffer is missing correct usage \
                                                           flags",
-            BufferViewCreationError::UnsupportedFormat => "the requested format is not supported \
-                                                           for this usage",
+            BufferViewCreationError::UnsupportedFormat { .. } => {
+                "the requested format is not supported for the usage of the buffer"
+            },
             BufferViewCreationError::MaxTexelBufferElementsExceeded => {
                 "the maximum number of texel elements is exceeded"
             },
@@ -312,8 +316,9 @@ mod tests {
             .. Usage::none()
         };
 
-        let buffer = ImmutableBuffer::<[[u8; 4]]>::array(&device, 128, &usage,
-                                                         Some(queue.family())).unwrap();
+        let (buffer, _) =
+            ImmutableBuffer::<[[u8; 4]]>::uninitialized_array(&device, 128, &usage,
+                                                              Some(queue.family())).unwrap();
         let view = BufferView::new(&buffer, format::R8G8B8A8Unorm).unwrap();
 
         assert!(view.uniform_texel_buffer());
@@ -329,8 +334,9 @@ mod tests {
             .. Usage::none()
         };
 
-        let buffer = ImmutableBuffer::<[[u8; 4]]>::array(&device, 128, &usage,
-                                                         Some(queue.family())).unwrap();
+        let (buffer, _) =
+            ImmutableBuffer::<[[u8; 4]]>::uninitialized_array(&device, 128, &usage,
+                                                              Some(queue.family())).unwrap();
         let view = BufferView::new(&buffer, format::R8G8B8A8Unorm).unwrap();
 
         assert!(view.storage_texel_buffer());
@@ -346,8 +352,9 @@ mod tests {
             .. Usage::none()
         };
 
-        let buffer = ImmutableBuffer::<[u32]>::array(&device, 128, &usage,
-                                                     Some(queue.family())).unwrap();
+        let (buffer, _) =
+            ImmutableBuffer::<[u32]>::uninitialized_array(&device, 128, &usage,
+                                                          Some(queue.family())).unwrap();
         let view = BufferView::new(&buffer, format::R32Uint).unwrap();
 
         assert!(view.storage_texel_buffer());
@@ -359,8 +366,9 @@ mod tests {
         // `VK_FORMAT_R8G8B8A8_UNORM` guaranteed to be a supported format
         let (device, queue) = gfx_dev_and_queue!();
 
-        let buffer = ImmutableBuffer::<[[u8; 4]]>::array(&device, 128, &Usage::none(),
-                                                         Some(queue.family())).unwrap();
+        let (buffer, _) =
+            ImmutableBuffer::<[[u8; 4]]>::uninitialized_array(&device, 128, &Usage::none(),
+                                                              Some(queue.family())).unwrap();
 
         match BufferView::new(buffer, format::R8G8B8A8Unorm) {
             Err(BufferViewCreationError::WrongBufferUsage) => (),
@@ -378,12 +386,17 @@ mod tests {
             .. Usage::none()
         };
 
-        let buffer = ImmutableBuffer::<[[f64; 4]]>::array(&device, 128, &usage,
-                                                          Some(queue.family())).unwrap();
+        let (buffer, _) =
+            ImmutableBuffer::<[[f64; 4]]>::uninitialized_array(&device, 128, &usage,
+                                                               Some(queue.family())).unwrap();
 
         // TODO: what if R64G64B64A64Sfloat is supported?
         match BufferView::new(buffer, format::R64G64B64A64Sfloat) {
-            Err(BufferViewCreationError::UnsupportedFormat) => (),
+            Err(BufferViewCreationError::UnsupportedFormat { format, missing_features }) => {
+                assert_eq!(format, format::Format::R64G64B64A64Sfloat);
+                assert!(missing_features.uniform_texel_buffer ||
+                        missing_features.storage_texel_buffer);
+            },
             _ => panic!()
         }
     }
@@ -23,8 +23,16 @@ use command_buffer::pool::StandardCommandPool;
 use device::Device;
 use device::DeviceOwned;
 use device::Queue;
+use framebuffer::EmptySinglePassRenderPassDesc;
+use framebuffer::Framebuffer;
+use framebuffer::FramebufferAbstract;
+use framebuffer::RenderPass;
+use framebuffer::RenderPassAbstract;
+use framebuffer::Subpass;
 use image::ImageAccess;
+use image::ImageSubresourceRange;
 use instance::QueueFamily;
+use query::QueryPipelineStatisticFlags;
 use sync::AccessFlagBits;
 use sync::PipelineStages;
 use sync::GpuFuture;
@@ -36,23 +44,66 @@ pub struct AutoCommandBufferBuilder<P = Arc<StandardCommandPool>> where P: Comma
     inner: Cb<P>
 }
 
+/// Command buffer obtained by building an `AutoCommandBufferBuilder`.
+pub type AutoCommandBuffer<P = Arc<StandardCommandPool>> = <Cb<P> as CommandBufferBuild>::Out;
+
 impl AutoCommandBufferBuilder<Arc<StandardCommandPool>> {
     pub fn new(device: Arc<Device>, queue_family: QueueFamily)
                -> Result<AutoCommandBufferBuilder<Arc<StandardCommandPool>>, OomError>
     {
-        let supports_graphics = queue_family.supports_graphics();
-        let supports_compute = queue_family.supports_compute();
+        AutoCommandBufferBuilder::with_kind(device, queue_family, cb::Kind::primary(), false, true)
+    }
+
+    /// Starts building a secondary command buffer that can be executed outside of a render pass.
+    ///
+    /// The secondary command buffer can contain transfer and dispatch commands, but no draw
+    /// command.
+    pub fn secondary_compute(device: Arc<Device>, queue_family: QueueFamily)
+                             -> Result<AutoCommandBufferBuilder<Arc<StandardCommandPool>>, OomError>
+    {
+        AutoCommandBufferBuilder::with_kind(device, queue_family, cb::Kind::secondary(), false,
+                                            false)
+    }
 
+    /// Starts building a secondary command buffer that can be executed from within the given
+    /// subpass.
+    ///
+    /// The secondary command buffer can only contain commands that are allowed inside of a render
+    /// pass, such as draw commands.
+    pub fn secondary_graphics<R>(device: Arc<Device>, queue_family: QueueFamily,
+                                 subpass: Subpass<R>)
+                                 -> Result<AutoCommandBufferBuilder<Arc<StandardCommandPool>>, OomError>
+        where R: RenderPassAbstract
+    {
+        let kind = cb::Kind::SecondaryRenderPass {
+            subpass: subpass,
+            framebuffer: None::<Framebuffer<RenderPass<EmptySinglePassRenderPassDesc>, ()>>,
+            occlusion_query: cb::KindOcclusionQuery::Forbidden,
+            query_statistics_flags: QueryPipelineStatisticFlags::none(),
+        };
+
+        AutoCommandBufferBuilder::with_kind(device, queue_family, kind, true, false)
+    }
+
+    fn with_kind<R, F>(device: Arc<Device>, queue_family: QueueFamily, kind: cb::Kind<R, F>,
+                       inside_render_pass: bool, allow_render_pass_ops: bool)
+                       -> Result<AutoCommandBufferBuilder<Arc<StandardCommandPool>>, OomError>
+        where R: RenderPassAbstract, F: FramebufferAbstract
+    {
         let pool = Device::standard_command_pool(&device, queue_family);
 
         let cmd = unsafe {
-            let c = try!(cb::UnsafeCommandBufferBuilder::new(pool, cb::Kind::primary(), cb::Flags::SimultaneousUse /* TODO: */));
+            let c = try!(cb::UnsafeCommandBufferBuilder::new(pool, kind, cb::Flags::SimultaneousUse /* TODO: */));
             let c = cb::AbstractStorageLayer::new(c);
-            let c = cb::AutoPipelineBarriersLayer::new(c);
+            let c = if inside_render_pass {
+                cb::AutoPipelineBarriersLayer::inside_render_pass(c)
+            } else {
+                cb::AutoPipelineBarriersLayer::new(c)
+            };
             let c = cb::SubmitSyncBuilderLayer::new(c);
             let c = cb::StateCacheLayer::new(c);
-            let c = cb::ContextCheckLayer::new(c, false, true);
-            let c = cb::QueueTyCheckLayer::new(c, supports_graphics, supports_compute);
+            let c = cb::ContextCheckLayer::new(c, inside_render_pass, allow_render_pass_ops);
+            let c = cb::QueueTyCheckLayer::new(c, queue_family);
             let c = cb::DeviceCheckLayer::new(c);
             c
         };
@@ -101,10 +152,23 @@ unsafe impl<P> CommandBuffer for AutoCommandBufferBuilder<P>
     }
 
     #[inline]
-    fn check_image_access(&self, image: &ImageAccess, exclusive: bool, queue: &Queue)
+    fn check_image_access(&self, image: &ImageAccess, range: &ImageSubresourceRange,
+                          exclusive: bool, queue: &Queue)
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>
     {
-        self.inner.check_image_access(image, exclusive, queue)
+        self.inner.check_image_access(image, range, exclusive, queue)
+    }
+
+    #[inline]
+    fn buffers_list<'a>(&'a self) -> Box<Iterator<Item = (&'a BufferAccess, bool)> + 'a> {
+        self.inner.buffers_list()
+    }
+
+    #[inline]
+    fn images_list<'a>(&'a self)
+                       -> Box<Iterator<Item = (&'a ImageAccess, ImageSubresourceRange, bool)> + 'a>
+    {
+        self.inner.images_list()
     }
 }
 
@@ -131,6 +195,11 @@ unsafe impl<P> CommandBufferBuilder for AutoCommandBufferBuilder<P>
     fn supports_compute(&self) -> bool {
         self.inner.supports_compute()
     }
+
+    #[inline]
+    fn queue_family(&self) -> QueueFamily {
+        self.inner.queue_family()
+    }
 }
 
 macro_rules! pass_through {
@@ -142,29 +211,40 @@ macro_rules! pass_through {
             type Out = AutoCommandBufferBuilder<P>;
 
             #[inline]
-            fn add(self, command: $cmd) -> Self::Out {
-                AutoCommandBufferBuilder {
-                    inner: self.inner.add(command),
-                }
+            fn add(self, command: $cmd) -> Result<Self::Out, cb::CommandAddError> {
+                Ok(AutoCommandBufferBuilder {
+                    inner: try!(self.inner.add(command)),
+                })
             }
         }
     }
 }
 
+pass_through!((Q), commands_raw::CmdBeginQuery<Q>);
 pass_through!((Rp, F), commands_raw::CmdBeginRenderPass<Rp, F>);
 pass_through!((S, Pl), commands_raw::CmdBindDescriptorSets<S, Pl>);
 pass_through!((B), commands_raw::CmdBindIndexBuffer<B>);
 pass_through!((Pl), commands_raw::CmdBindPipeline<Pl>);
 pass_through!((V), commands_raw::CmdBindVertexBuffers<V>);
+pass_through!((S, D), commands_raw::CmdBlitImage<S, D>);
 pass_through!((), commands_raw::CmdClearAttachments);
 pass_through!((S, D), commands_raw::CmdCopyBuffer<S, D>);
 pass_through!((S, D), commands_raw::CmdCopyBufferToImage<S, D>);
+pass_through!((S, D), commands_raw::CmdCopyImage<S, D>);
+pass_through!((S, D), commands_raw::CmdCopyImageToBuffer<S, D>);
+pass_through!((Q, B), commands_raw::CmdCopyQueryPoolResults<Q, B>);
+pass_through!((), commands_raw::CmdDispatchRaw);
 pass_through!((), commands_raw::CmdDrawRaw);
 pass_through!((), commands_raw::CmdDrawIndexedRaw);
+pass_through!((Q), commands_raw::CmdEndQuery<Q>);
 pass_through!((), commands_raw::CmdEndRenderPass);
 pass_through!((C), commands_raw::CmdExecuteCommands<C>);
 pass_through!((B), commands_raw::CmdFillBuffer<B>);
+pass_through!((I), commands_raw::CmdGenerateMipmaps<I>);
 pass_through!((), commands_raw::CmdNextSubpass);
 pass_through!((Pc, Pl), commands_raw::CmdPushConstants<Pc, Pl>);
+pass_through!((Q), commands_raw::CmdResetQueryPool<Q>);
+pass_through!((S, D), commands_raw::CmdResolveImage<S, D>);
 pass_through!((), commands_raw::CmdSetState);
 pass_through!((B, D), commands_raw::CmdUpdateBuffer<B, D>);
+pass_through!((Q), commands_raw::CmdWriteTimestamp<Q>);
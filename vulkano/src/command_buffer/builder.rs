@@ -7,13 +7,18 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::error;
+use std::fmt;
+use std::ops::Range;
 use std::sync::Arc;
 
 use buffer::Buffer;
 use buffer::TypedBuffer;
 use device::DeviceOwned;
+use command_buffer::CommandBuffer;
 use command_buffer::DynamicState;
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::CommandBufferBuild;
 use command_buffer::commands_extra;
 use command_buffer::commands_raw;
@@ -22,10 +27,17 @@ use framebuffer::FramebufferAbstract;
 use framebuffer::RenderPassAbstract;
 use framebuffer::RenderPassDescClearValues;
 use image::Image;
+use image::ImageAspect;
+use instance::QueueFamily;
+use memory::Content;
 use pipeline::ComputePipelineAbstract;
 use pipeline::GraphicsPipelineAbstract;
 use pipeline::vertex::VertexSource;
 use pipeline::input_assembly::Index;
+use query::QueryPoolAbstract;
+use query::QueryResultFlags;
+use sampler::Filter;
+use sync::PipelineStages;
 
 ///
 /// > **Note**: This trait is just a utility trait. Do not implement it yourself. Instead
@@ -42,68 +54,242 @@ pub unsafe trait CommandBufferBuilder: DeviceOwned {
     /// > this function only for zeroing the content of a buffer by passing `0` for the data.
     // TODO: not safe because of signalling NaNs
     #[inline]
-    fn fill_buffer<B, O>(self, buffer: B, data: u32) -> Result<O, commands_raw::CmdFillBufferError>
+    fn fill_buffer<B, O>(self, buffer: B, data: u32)
+                        -> Result<O, CommandBufferBuilderError<commands_raw::CmdFillBufferError>>
         where Self: Sized + AddCommand<commands_raw::CmdFillBuffer<B::Access>, Out = O>,
               B: Buffer
     {
-        let cmd = commands_raw::CmdFillBuffer::new(buffer.access(), data)?;
-        Ok(self.add(cmd))
+        let cmd = try!(commands_raw::CmdFillBuffer::new(buffer.access(), data)
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
     }
 
     /// Adds a command that writes data to a buffer.
     #[inline]
-    fn update_buffer<B, D, O>(self, buffer: B, data: D) -> Result<O, commands_raw::CmdUpdateBufferError>
+    fn update_buffer<B, D, O>(self, buffer: B, data: D)
+                             -> Result<O, CommandBufferBuilderError<commands_raw::CmdUpdateBufferError>>
         where Self: Sized + AddCommand<commands_raw::CmdUpdateBuffer<B::Access, D>, Out = O>,
               B: Buffer
     {
-        let cmd = commands_raw::CmdUpdateBuffer::new(buffer.access(), data)?;
-        Ok(self.add(cmd))
+        let cmd = try!(commands_raw::CmdUpdateBuffer::new(buffer.access(), data)
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
     }
 
     /// Adds a command that copies from a buffer to another.
     #[inline]
-    fn copy_buffer<S, D, O>(self, src: S, dest: D) -> Result<O, commands_raw::CmdCopyBufferError>
+    fn copy_buffer<S, D, O>(self, src: S, dest: D)
+                           -> Result<O, CommandBufferBuilderError<commands_raw::CmdCopyBufferError>>
         where Self: Sized + AddCommand<commands_raw::CmdCopyBuffer<S::Access, D::Access>, Out = O>,
               S: Buffer,
               D: Buffer
     {
-        let cmd = commands_raw::CmdCopyBuffer::new(src.access(), dest.access())?;
-        Ok(self.add(cmd))
+        let cmd = try!(commands_raw::CmdCopyBuffer::new(src.access(), dest.access())
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
     }
 
     /// Adds a command that copies the content of a buffer to an image.
     ///
+    /// All the array layers of the first mipmap level are written, from texels tightly packed at
+    /// the start of the buffer.
+    ///
     /// For color images (ie. all formats except depth and/or stencil formats) this command does
     /// not perform any conversion. The data inside the buffer must already have the right format.
-    /// TODO: talk about depth/stencil
     ///
-    /// > **Note**: This function is technically safe because buffers can only contain integers or
-    /// > floating point numbers, which are always valid whatever their memory representation is.
-    /// > But unless your buffer actually contains only 32-bits integers, you are encouraged to use
-    /// > this function only for zeroing the content of a buffer by passing `0` for the data.
-    // TODO: not safe because of signalling NaNs
+    /// Depth and stencil values can't be written at the same time. For depth-stencil images only
+    /// the depth aspect is written, use `copy_buffer_to_image_regions` to write the stencil
+    /// aspect. Depth values of 24-bits formats occupy 4 bytes in the buffer, and stencil values
+    /// occupy 1 byte.
     #[inline]
     fn copy_buffer_to_image<B, I, O>(self, buffer: B, image: I)
-                                     -> Result<O, commands_raw::CmdCopyBufferToImageError>
+                                     -> Result<O, CommandBufferBuilderError<commands_raw::CmdCopyBufferToImageError>>
         where Self: Sized + AddCommand<commands_raw::CmdCopyBufferToImage<B::Access, I::Access>, Out = O>,
-              B: Buffer, I: Image
+              B: Buffer, B::Access: TypedBuffer, <B::Access as TypedBuffer>::Content: Content,
+              I: Image
     {
-        let cmd = commands_raw::CmdCopyBufferToImage::new(buffer.access(), image.access())?;
-        Ok(self.add(cmd))
+        let cmd = try!(commands_raw::CmdCopyBufferToImage::new(buffer.access(), image.access())
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
     }
 
     /// Same as `copy_buffer_to_image` but lets you specify a range for the destination image.
     #[inline]
     fn copy_buffer_to_image_dimensions<B, I, O>(self, buffer: B, image: I, offset: [u32; 3],
                                                 size: [u32; 3], first_layer: u32, num_layers: u32,
-                                                mipmap: u32) -> Result<O, commands_raw::CmdCopyBufferToImageError>
+                                                mipmap: u32) -> Result<O, CommandBufferBuilderError<commands_raw::CmdCopyBufferToImageError>>
         where Self: Sized + AddCommand<commands_raw::CmdCopyBufferToImage<B::Access, I::Access>, Out = O>,
-              B: Buffer, I: Image
+              B: Buffer, B::Access: TypedBuffer, <B::Access as TypedBuffer>::Content: Content,
+              I: Image
     {
-        let cmd = commands_raw::CmdCopyBufferToImage::with_dimensions(buffer.access(),
+        let cmd = try!(commands_raw::CmdCopyBufferToImage::with_dimensions(buffer.access(),
                                                              image.access(), offset, size,
-                                                             first_layer, num_layers, mipmap)?;
-        Ok(self.add(cmd))
+                                                             first_layer, num_layers, mipmap)
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
+    }
+
+    /// Same as `copy_buffer_to_image` but copies each of the given regions.
+    ///
+    /// Contrary to the other functions, this lets you specify the aspect of the image, the layout
+    /// of the texels in the buffer, and copy multiple regions at once.
+    #[inline]
+    fn copy_buffer_to_image_regions<B, I, R, O>(self, buffer: B, image: I, regions: R)
+                                                -> Result<O, CommandBufferBuilderError<commands_raw::CmdCopyBufferToImageError>>
+        where Self: Sized + AddCommand<commands_raw::CmdCopyBufferToImage<B::Access, I::Access>, Out = O>,
+              B: Buffer, B::Access: TypedBuffer, <B::Access as TypedBuffer>::Content: Content,
+              I: Image, R: IntoIterator<Item = commands_raw::BufferImageRegion>
+    {
+        let cmd = try!(commands_raw::CmdCopyBufferToImage::with_regions(buffer.access(),
+                                                                        image.access(), regions)
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
+    }
+
+    /// Adds a command that copies the content of an image to a buffer.
+    ///
+    /// All the array layers of the first mipmap level are copied, and the texels are tightly
+    /// packed at the start of the buffer. For depth-stencil images only the depth aspect is
+    /// copied, use `copy_image_to_buffer_dimensions` to copy the stencil aspect.
+    #[inline]
+    fn copy_image_to_buffer<I, B, O>(self, image: I, buffer: B)
+                                     -> Result<O, CommandBufferBuilderError<commands_raw::CmdCopyImageToBufferError>>
+        where Self: Sized + AddCommand<commands_raw::CmdCopyImageToBuffer<I::Access, B::Access>, Out = O>,
+              I: Image,
+              B: Buffer, B::Access: TypedBuffer, <B::Access as TypedBuffer>::Content: Content
+    {
+        let cmd = try!(commands_raw::CmdCopyImageToBuffer::new(image.access(), buffer.access())
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
+    }
+
+    /// Same as `copy_image_to_buffer` but lets you specify a range and an aspect of the source
+    /// image.
+    #[inline]
+    fn copy_image_to_buffer_dimensions<I, B, O>(self, image: I, buffer: B, offset: [u32; 3],
+                                                size: [u32; 3], first_layer: u32, num_layers: u32,
+                                                mipmap: u32, aspect: ImageAspect)
+                                                -> Result<O, CommandBufferBuilderError<commands_raw::CmdCopyImageToBufferError>>
+        where Self: Sized + AddCommand<commands_raw::CmdCopyImageToBuffer<I::Access, B::Access>, Out = O>,
+              I: Image,
+              B: Buffer, B::Access: TypedBuffer, <B::Access as TypedBuffer>::Content: Content
+    {
+        let cmd = try!(commands_raw::CmdCopyImageToBuffer::with_dimensions(image.access(),
+                                                             buffer.access(), offset, size,
+                                                             first_layer, num_layers, mipmap,
+                                                             aspect)
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
+    }
+
+    /// Same as `copy_image_to_buffer` but copies each of the given regions.
+    #[inline]
+    fn copy_image_to_buffer_regions<I, B, R, O>(self, image: I, buffer: B, regions: R)
+                                                -> Result<O, CommandBufferBuilderError<commands_raw::CmdCopyImageToBufferError>>
+        where Self: Sized + AddCommand<commands_raw::CmdCopyImageToBuffer<I::Access, B::Access>, Out = O>,
+              I: Image,
+              B: Buffer, B::Access: TypedBuffer, <B::Access as TypedBuffer>::Content: Content,
+              R: IntoIterator<Item = commands_raw::BufferImageRegion>
+    {
+        let cmd = try!(commands_raw::CmdCopyImageToBuffer::with_regions(image.access(),
+                                                                        buffer.access(), regions)
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
+    }
+
+    /// Adds a command that fills all the mipmap levels of an image from the content of its first
+    /// level.
+    ///
+    /// Each level is blitted from the previous one with a linear filter, or with a nearest
+    /// filter for depth and stencil images. If the format of a color image can't be blitted with
+    /// a linear filter, each level is computed from the previous one with a compute shader
+    /// instead. See `CmdGenerateMipmaps` for the requirements.
+    #[inline]
+    fn generate_mipmaps<I, O>(self, image: I)
+                              -> Result<O, CommandBufferBuilderError<commands_raw::CmdGenerateMipmapsError>>
+        where Self: Sized + AddCommand<commands_raw::CmdGenerateMipmaps<I::Access>, Out = O>,
+              I: Image
+    {
+        let cmd = try!(commands_raw::CmdGenerateMipmaps::new(image.access())
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
+    }
+
+    /// Adds a command that copies a region of an image to a region of another image, scaling
+    /// and converting the format if necessary.
+    ///
+    /// The region of the source delimited by the `source_top_left` and `source_bottom_right`
+    /// corners is blitted to the region of the destination delimited by the
+    /// `destination_top_left` and `destination_bottom_right` corners. `filter` is used when the
+    /// two regions don't have the same size.
+    #[inline]
+    fn blit_image<S, D, O>(self, source: S, source_top_left: [i32; 3],
+                           source_bottom_right: [i32; 3], source_base_array_layer: u32,
+                           source_mip_level: u32, destination: D,
+                           destination_top_left: [i32; 3], destination_bottom_right: [i32; 3],
+                           destination_base_array_layer: u32, destination_mip_level: u32,
+                           layer_count: u32, filter: Filter)
+                           -> Result<O, CommandBufferBuilderError<commands_raw::CmdBlitImageError>>
+        where Self: Sized + AddCommand<commands_raw::CmdBlitImage<S::Access, D::Access>, Out = O>,
+              S: Image, D: Image
+    {
+        let cmd = try!(commands_raw::CmdBlitImage::new(source.access(), source_top_left,
+                                                       source_bottom_right,
+                                                       source_base_array_layer,
+                                                       source_mip_level, destination.access(),
+                                                       destination_top_left,
+                                                       destination_bottom_right,
+                                                       destination_base_array_layer,
+                                                       destination_mip_level, layer_count,
+                                                       filter)
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
+    }
+
+    /// Adds a command that copies a region of an image to a region of another image.
+    ///
+    /// No conversion is performed. The formats of the two images must have the same size.
+    #[inline]
+    fn copy_image<S, D, O>(self, source: S, source_offset: [i32; 3],
+                           source_base_array_layer: u32, source_mip_level: u32, destination: D,
+                           destination_offset: [i32; 3], destination_base_array_layer: u32,
+                           destination_mip_level: u32, extent: [u32; 3], layer_count: u32)
+                           -> Result<O, CommandBufferBuilderError<commands_raw::CmdCopyImageError>>
+        where Self: Sized + AddCommand<commands_raw::CmdCopyImage<S::Access, D::Access>, Out = O>,
+              S: Image, D: Image
+    {
+        let cmd = try!(commands_raw::CmdCopyImage::new(source.access(), source_offset,
+                                                       source_base_array_layer,
+                                                       source_mip_level, destination.access(),
+                                                       destination_offset,
+                                                       destination_base_array_layer,
+                                                       destination_mip_level, extent,
+                                                       layer_count)
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
+    }
+
+    /// Adds a command that resolves a region of a multisampled image into a region of a
+    /// non-multisampled image.
+    #[inline]
+    fn resolve_image<S, D, O>(self, source: S, source_offset: [i32; 3],
+                              source_base_array_layer: u32, source_mip_level: u32,
+                              destination: D, destination_offset: [i32; 3],
+                              destination_base_array_layer: u32, destination_mip_level: u32,
+                              extent: [u32; 3], layer_count: u32)
+                              -> Result<O, CommandBufferBuilderError<commands_raw::CmdResolveImageError>>
+        where Self: Sized + AddCommand<commands_raw::CmdResolveImage<S::Access, D::Access>, Out = O>,
+              S: Image, D: Image
+    {
+        let cmd = try!(commands_raw::CmdResolveImage::new(source.access(), source_offset,
+                                                          source_base_array_layer,
+                                                          source_mip_level, destination.access(),
+                                                          destination_offset,
+                                                          destination_base_array_layer,
+                                                          destination_mip_level, extent,
+                                                          layer_count)
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
     }
 
     /// Adds a command that starts a render pass.
@@ -115,7 +301,7 @@ pub unsafe trait CommandBufferBuilder: DeviceOwned {
     /// You must call this before you can add draw commands.
     #[inline]
     fn begin_render_pass<F, C, O>(self, framebuffer: F, secondary: bool, clear_values: C)
-                                  -> O
+                                  -> Result<O, CommandAddError>
         where Self: Sized + AddCommand<commands_raw::CmdBeginRenderPass<Arc<RenderPassAbstract + Send + Sync>, F>, Out = O>,
               F: FramebufferAbstract + RenderPassDescClearValues<C>
     {
@@ -125,7 +311,7 @@ pub unsafe trait CommandBufferBuilder: DeviceOwned {
 
     /// Adds a command that jumps to the next subpass of the current render pass.
     #[inline]
-    fn next_subpass<O>(self, secondary: bool) -> O
+    fn next_subpass<O>(self, secondary: bool) -> Result<O, CommandAddError>
         where Self: Sized + AddCommand<commands_raw::CmdNextSubpass, Out = O>
     {
         let cmd = commands_raw::CmdNextSubpass::new(secondary);
@@ -137,19 +323,35 @@ pub unsafe trait CommandBufferBuilder: DeviceOwned {
     /// This must be called after you went through all the subpasses and before you can build
     /// the command buffer or add further commands.
     #[inline]
-    fn end_render_pass<O>(self) -> O
+    fn end_render_pass<O>(self) -> Result<O, CommandAddError>
         where Self: Sized + AddCommand<commands_raw::CmdEndRenderPass, Out = O>
     {
         let cmd = commands_raw::CmdEndRenderPass::new();
         self.add(cmd)
     }
 
+    /// Adds a command that clears some attachments of the current subpass, in each of the given
+    /// rectangles.
+    ///
+    /// Can only be used from inside a render pass.
+    #[inline]
+    fn clear_attachments<A, R, O>(self, attachments: A, rects: R)
+                                  -> Result<O, CommandBufferBuilderError<commands_raw::CmdClearAttachmentsError>>
+        where Self: Sized + AddCommand<commands_raw::CmdClearAttachments, Out = O>,
+              A: IntoIterator<Item = commands_raw::ClearAttachment>,
+              R: IntoIterator<Item = commands_raw::ClearRect>
+    {
+        let cmd = try!(commands_raw::CmdClearAttachments::new(attachments, rects)
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
+    }
+
     /// Adds a command that draws.
     ///
     /// Can only be used from inside a render pass.
     #[inline]
     fn draw<P, S, Pc, V, O>(self, pipeline: P, dynamic: DynamicState, vertices: V, sets: S,
-                            push_constants: Pc) -> O
+                            push_constants: Pc) -> Result<O, CommandAddError>
         where Self: Sized + AddCommand<commands_extra::CmdDraw<V, P, S, Pc>, Out = O>,
               S: DescriptorSetsCollection,
               P: VertexSource<V> + GraphicsPipelineAbstract + Clone
@@ -163,7 +365,8 @@ pub unsafe trait CommandBufferBuilder: DeviceOwned {
     /// Can only be used from inside a render pass.
     #[inline]
     fn draw_indexed<P, S, Pc, V, Ib, I, O>(self, pipeline: P, dynamic: DynamicState,
-        vertices: V, index_buffer: Ib, sets: S, push_constants: Pc) -> O
+        vertices: V, index_buffer: Ib, sets: S, push_constants: Pc)
+        -> Result<O, CommandAddError>
         where Self: Sized + AddCommand<commands_extra::CmdDrawIndexed<V, Ib::Access, P, S, Pc>, Out = O>,
               S: DescriptorSetsCollection,
               P: VertexSource<V> + GraphicsPipelineAbstract + Clone,
@@ -178,13 +381,107 @@ pub unsafe trait CommandBufferBuilder: DeviceOwned {
 
     /// Executes a compute shader.
     fn dispatch<P, S, Pc, O>(self, dimensions: [u32; 3], pipeline: P, sets: S, push_constants: Pc)
-                             -> Result<O, commands_extra::CmdDispatchError>
+                             -> Result<O, CommandBufferBuilderError<commands_extra::CmdDispatchError>>
         where Self: Sized + AddCommand<commands_extra::CmdDispatch<P, S, Pc>, Out = O>,
               S: DescriptorSetsCollection,
               P: Clone + ComputePipelineAbstract,
     {
-        let cmd = try!(commands_extra::CmdDispatch::new(dimensions, pipeline, sets, push_constants));
-        Ok(self.add(cmd))
+        let cmd = try!(commands_extra::CmdDispatch::new(dimensions, pipeline, sets, push_constants)
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
+    }
+
+    /// Adds a command that executes a secondary command buffer.
+    ///
+    /// If the builder is inside of a render pass, the current subpass must have been entered with
+    /// `secondary` set to true, and the secondary command buffer must have been created for the
+    /// current subpass of the current render pass. If the builder is outside of a render pass,
+    /// the secondary command buffer must not have been created for a subpass.
+    ///
+    /// Returns an error if `command_buffer` is not a secondary command buffer.
+    #[inline]
+    fn execute_commands<C, O>(self, command_buffer: C)
+                              -> Result<O, CommandBufferBuilderError<commands_raw::CmdExecuteCommandsError>>
+        where Self: Sized + AddCommand<commands_raw::CmdExecuteCommands<C>, Out = O>,
+              C: CommandBuffer
+    {
+        let cmd = try!(commands_raw::CmdExecuteCommands::new(command_buffer)
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
+    }
+
+    /// Adds a command that resets a range of queries of a query pool.
+    ///
+    /// Queries must be reset before they are used. Can't be used from inside a render pass.
+    #[inline]
+    fn reset_query_pool<Q, O>(self, pool: Q, range: Range<u32>)
+                              -> Result<O, CommandBufferBuilderError<commands_raw::CmdResetQueryPoolError>>
+        where Self: Sized + AddCommand<commands_raw::CmdResetQueryPool<Q>, Out = O>,
+              Q: QueryPoolAbstract
+    {
+        let cmd = try!(commands_raw::CmdResetQueryPool::new(pool, range)
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
+    }
+
+    /// Adds a command that starts an occlusion or a pipeline statistics query.
+    ///
+    /// If `precise` is true, an occlusion query counts the exact number of samples that passed
+    /// the tests.
+    #[inline]
+    fn begin_query<Q, O>(self, pool: Q, query: u32, precise: bool)
+                         -> Result<O, CommandBufferBuilderError<commands_raw::CmdBeginQueryError>>
+        where Self: Sized + AddCommand<commands_raw::CmdBeginQuery<Q>, Out = O>,
+              Q: QueryPoolAbstract
+    {
+        let cmd = try!(commands_raw::CmdBeginQuery::new(pool, query, precise)
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
+    }
+
+    /// Adds a command that ends a query started with `begin_query`.
+    #[inline]
+    fn end_query<Q, O>(self, pool: Q, query: u32)
+                       -> Result<O, CommandBufferBuilderError<commands_raw::CmdEndQueryError>>
+        where Self: Sized + AddCommand<commands_raw::CmdEndQuery<Q>, Out = O>,
+              Q: QueryPoolAbstract
+    {
+        let cmd = try!(commands_raw::CmdEndQuery::new(pool, query)
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
+    }
+
+    /// Adds a command that writes a timestamp to a query of a timestamp query pool once the
+    /// previous commands have reached `stage`.
+    ///
+    /// Exactly one stage must be enabled in `stage`.
+    #[inline]
+    fn write_timestamp<Q, O>(self, pool: Q, query: u32, stage: PipelineStages)
+                             -> Result<O, CommandBufferBuilderError<commands_raw::CmdWriteTimestampError>>
+        where Self: Sized + AddCommand<commands_raw::CmdWriteTimestamp<Q>, Out = O>,
+              Q: QueryPoolAbstract
+    {
+        let cmd = try!(commands_raw::CmdWriteTimestamp::new(pool, query, stage,
+                                                            self.queue_family())
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
+    }
+
+    /// Adds a command that copies the results of a range of queries to a buffer.
+    ///
+    /// The results are written as 64-bits integers. Can't be used from inside a render pass.
+    #[inline]
+    fn copy_query_pool_results<Q, B, O>(self, pool: Q, range: Range<u32>, destination: B,
+                                        flags: QueryResultFlags)
+                                        -> Result<O, CommandBufferBuilderError<commands_raw::CmdCopyQueryPoolResultsError>>
+        where Self: Sized + AddCommand<commands_raw::CmdCopyQueryPoolResults<Q, B::Access>, Out = O>,
+              Q: QueryPoolAbstract,
+              B: Buffer
+    {
+        let cmd = try!(commands_raw::CmdCopyQueryPoolResults::new(pool, range,
+                                                                  destination.access(), flags)
+                           .map_err(CommandBufferBuilderError::CommandBuildError));
+        Ok(try!(self.add(cmd)))
     }
 
     /// Builds the actual command buffer.
@@ -204,6 +501,9 @@ pub unsafe trait CommandBufferBuilder: DeviceOwned {
 
     /// Returns true if the pool of the builder supports compute operations.
     fn supports_compute(&self) -> bool;
+
+    /// Returns the queue family of the pool of the builder.
+    fn queue_family(&self) -> QueueFamily;
 }
 
 pub unsafe trait CommandBufferBuilderBuffered {
@@ -214,3 +514,49 @@ pub unsafe trait CommandBufferBuilderBuffered {
     /// Flushes all the commands that haven't been flushed to the inner builder.
     fn flush(&mut self);
 }
+
+/// Error that can happen when adding a command to a command buffer builder.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CommandBufferBuilderError<E> {
+    /// Error while creating the command.
+    CommandBuildError(E),
+
+    /// Error while adding the command to the builder.
+    CommandAddError(CommandAddError),
+}
+
+impl<E> From<CommandAddError> for CommandBufferBuilderError<E> {
+    #[inline]
+    fn from(err: CommandAddError) -> CommandBufferBuilderError<E> {
+        CommandBufferBuilderError::CommandAddError(err)
+    }
+}
+
+impl<E> error::Error for CommandBufferBuilderError<E> where E: error::Error {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CommandBufferBuilderError::CommandBuildError(_) => {
+                "error while creating the command"
+            },
+            CommandBufferBuilderError::CommandAddError(_) => {
+                "error while adding the command to the builder"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            CommandBufferBuilderError::CommandBuildError(ref err) => Some(err),
+            CommandBufferBuilderError::CommandAddError(ref err) => Some(err),
+        }
+    }
+}
+
+impl<E> fmt::Display for CommandBufferBuilderError<E> where E: error::Error {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
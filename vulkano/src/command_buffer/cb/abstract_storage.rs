@@ -13,6 +13,7 @@ use std::sync::Arc;
 
 use buffer::BufferAccess;
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::CommandBufferBuild;
 use command_buffer::cb::UnsafeCommandBuffer;
 use command_buffer::commands_raw;
@@ -22,6 +23,8 @@ use device::Device;
 use device::DeviceOwned;
 use device::Queue;
 use image::ImageAccess;
+use image::ImageSubresourceRange;
+use instance::QueueFamily;
 use sync::AccessFlagBits;
 use sync::GpuFuture;
 use sync::PipelineStages;
@@ -64,10 +67,23 @@ unsafe impl<I> CommandBuffer for AbstractStorageLayer<I> where I: CommandBuffer
     }
 
     #[inline]
-    fn check_image_access(&self, image: &ImageAccess, exclusive: bool, queue: &Queue)
+    fn check_image_access(&self, image: &ImageAccess, range: &ImageSubresourceRange,
+                          exclusive: bool, queue: &Queue)
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>
     {
-        self.inner.check_image_access(image, exclusive, queue)
+        self.inner.check_image_access(image, range, exclusive, queue)
+    }
+
+    #[inline]
+    fn buffers_list<'a>(&'a self) -> Box<Iterator<Item = (&'a BufferAccess, bool)> + 'a> {
+        self.inner.buffers_list()
+    }
+
+    #[inline]
+    fn images_list<'a>(&'a self)
+                       -> Box<Iterator<Item = (&'a ImageAccess, ImageSubresourceRange, bool)> + 'a>
+    {
+        self.inner.images_list()
     }
 }
 
@@ -105,6 +121,11 @@ unsafe impl<I> CommandBufferBuilder for AbstractStorageLayer<I> where I: Command
     fn supports_compute(&self) -> bool {
         self.inner.supports_compute()
     }
+
+    #[inline]
+    fn queue_family(&self) -> QueueFamily {
+        self.inner.queue_family()
+    }
 }
 
 macro_rules! pass_through {
@@ -115,20 +136,39 @@ macro_rules! pass_through {
             type Out = AbstractStorageLayer<I>;
 
             #[inline]
-            fn add(mut self, command: $cmd) -> Self::Out {
-                let new_inner = AddCommand::add(self.inner, &command);
+            fn add(mut self, command: $cmd) -> Result<Self::Out, CommandAddError> {
+                let new_inner = try!(AddCommand::add(self.inner, &command));
                 // TODO: should store a lightweight version of the command
                 self.commands.push(Box::new(command) as Box<_>);
                 
-                AbstractStorageLayer {
+                Ok(AbstractStorageLayer {
                     inner: new_inner,
                     commands: self.commands,
-                }
+                })
             }
         }
     }
 }
 
+// Pipeline barriers only hold raw handles and don't keep anything alive, therefore there is no
+// need to store them.
+unsafe impl<'a, I> AddCommand<&'a commands_raw::CmdPipelineBarrier<'a>> for AbstractStorageLayer<I>
+    where I: AddCommand<&'a commands_raw::CmdPipelineBarrier<'a>, Out = I>
+{
+    type Out = AbstractStorageLayer<I>;
+
+    #[inline]
+    fn add(self, command: &'a commands_raw::CmdPipelineBarrier<'a>)
+           -> Result<Self::Out, CommandAddError>
+    {
+        Ok(AbstractStorageLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
+            commands: self.commands,
+        })
+    }
+}
+
+pass_through!((Q), commands_raw::CmdBeginQuery<Q>);
 pass_through!((Rp, F), commands_raw::CmdBeginRenderPass<Rp, F>);
 pass_through!((S, Pl), commands_raw::CmdBindDescriptorSets<S, Pl>);
 pass_through!((B), commands_raw::CmdBindIndexBuffer<B>);
@@ -139,16 +179,22 @@ pass_through!((), commands_raw::CmdClearAttachments);
 pass_through!((S, D), commands_raw::CmdCopyBuffer<S, D>);
 pass_through!((S, D), commands_raw::CmdCopyBufferToImage<S, D>);
 pass_through!((S, D), commands_raw::CmdCopyImage<S, D>);
+pass_through!((S, D), commands_raw::CmdCopyImageToBuffer<S, D>);
+pass_through!((Q, B), commands_raw::CmdCopyQueryPoolResults<Q, B>);
 pass_through!((), commands_raw::CmdDispatchRaw);
 pass_through!((), commands_raw::CmdDrawIndexedRaw);
 pass_through!((B), commands_raw::CmdDrawIndirectRaw<B>);
 pass_through!((), commands_raw::CmdDrawRaw);
+pass_through!((Q), commands_raw::CmdEndQuery<Q>);
 pass_through!((), commands_raw::CmdEndRenderPass);
 pass_through!((C), commands_raw::CmdExecuteCommands<C>);
 pass_through!((B), commands_raw::CmdFillBuffer<B>);
+pass_through!((Img), commands_raw::CmdGenerateMipmaps<Img>);
 pass_through!((), commands_raw::CmdNextSubpass);
 pass_through!((Pc, Pl), commands_raw::CmdPushConstants<Pc, Pl>);
+pass_through!((Q), commands_raw::CmdResetQueryPool<Q>);
 pass_through!((S, D), commands_raw::CmdResolveImage<S, D>);
 pass_through!((), commands_raw::CmdSetEvent);
 pass_through!((), commands_raw::CmdSetState);
 pass_through!((B, D), commands_raw::CmdUpdateBuffer<B, D>);
+pass_through!((Q), commands_raw::CmdWriteTimestamp<Q>);
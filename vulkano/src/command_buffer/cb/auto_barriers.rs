@@ -7,49 +7,668 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::cmp;
 use std::sync::Arc;
+
+use buffer::BufferAccess;
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::CommandBufferBuild;
+use command_buffer::CommandBuffer;
 use command_buffer::CommandBufferBuilder;
 use command_buffer::commands_raw;
+use command_buffer::commands_raw::CmdPipelineBarrier;
+use descriptor::descriptor_set::DescriptorSetsCollection;
 use device::Device;
 use device::DeviceOwned;
+use image::ImageAccess;
+use image::ImageSubresourceRange;
+use image::Layout;
+use instance::QueueFamily;
+use sync::AccessFlagBits;
+use sync::PipelineStages;
+use VulkanObject;
+use vk;
 
+/// Layer that automatically adds pipeline barriers between the commands that need them.
+///
+/// Each command that is added to this layer is checked against the buffers and images that have
+/// been accessed by the previous commands. If the command reads from a resource that was written,
+/// writes to a resource that was accessed, or requires an image to be in a different layout, then
+/// a pipeline barrier is added right before the command. All the barriers required by a single
+/// command are coalesced into one `CmdPipelineBarrier`.
+///
+/// Images are transitioned from their initial layout to the layout required by each command, and
+/// back to their final layout at the beginning of each render pass and at the end of the command
+/// buffer. See `ImageAccess::initial_layout_requirement` and `final_layout_requirement`.
+///
+/// Accesses and layouts are tracked per range of mipmap levels and array layers. For example
+/// blitting from one mipmap level to the next only transitions these two levels, and writing to
+/// one face of a cubemap doesn't wait for the commands that read the other faces. The depth and
+/// stencil aspects of an image always share the same layout.
+///
+/// > **Note**: Pipeline barriers can't be added inside of a render pass. The accesses of the
+/// > commands inside of a render pass are synchronized with the commands before and after the
+/// > render pass, but not between each other.
 pub struct AutoPipelineBarriersLayer<I> {
     inner: I,
+    // Ranges of buffers that have been accessed by the previous commands.
+    buffers: Vec<BufferState>,
+    // Ranges of images that have been accessed by the previous commands. The ranges of the same
+    // image never overlap.
+    images: Vec<ImageState>,
+    // True if we are between a `CmdBeginRenderPass` and a `CmdEndRenderPass`.
+    in_render_pass: bool,
+    // True if a render pass has ended and its writes haven't been synchronized yet.
+    render_pass_writes: bool,
+}
+
+// Accesses to a range of a buffer by the previous commands.
+struct BufferState {
+    // Raw handle of the buffer.
+    raw: vk::Buffer,
+    // Range that was accessed, relative to the start of the raw buffer.
+    offset: usize,
+    size: usize,
+    // Accesses to the range.
+    accesses: Accesses,
+}
+
+// Accesses to a range of an image by the previous commands.
+#[derive(Clone)]
+struct ImageState {
+    // Raw handle of the image.
+    raw: vk::Image,
+    // Mipmap levels and array layers of the range. The aspect is always `None`.
+    range: ImageSubresourceRange,
+    // The image itself, if the command that accessed it allowed us to keep it alive. Always
+    // `Some` if `layout` is different from `default_layout`.
+    image: Option<Arc<ImageAccess + Send + Sync>>,
+    // Layout that the image must be in at the end of the command buffer.
+    default_layout: Layout,
+    // Current layout of the range.
+    layout: Layout,
+    // Accesses to the range.
+    accesses: Accesses,
+}
+
+// Accesses to a range of a buffer or of an image, and the synchronization between them.
+//
+// A write is only visible to the stages and access types of the barriers that have been added
+// after it. Each read by another stage or access type needs its own barrier, until the write is
+// flushed by a barrier that makes it visible to everything.
+#[derive(Copy, Clone)]
+struct Accesses {
+    // Stages and access types of the commands that accessed the range since the last write or
+    // layout transition, including this write or transition.
+    stages: PipelineStages,
+    access: AccessFlagBits,
+    // Stages and access types of the last write or layout transition. Both are `none` if the
+    // range hasn't been written or if the write has been flushed.
+    write_stages: PipelineStages,
+    write_access: AccessFlagBits,
+    // Stages and access types that the last write has already been made visible to.
+    visible_stages: PipelineStages,
+    visible_access: AccessFlagBits,
+}
+
+impl Accesses {
+    // Accesses of a command to a range that wasn't accessed before. `transition` must be true if
+    // the range is transitioned to a new layout right before the command.
+    fn new(stages: PipelineStages, access: AccessFlagBits, write: bool, transition: bool)
+           -> Accesses
+    {
+        let mut accesses = Accesses {
+            stages: stages,
+            access: access,
+            write_stages: PipelineStages::none(),
+            write_access: AccessFlagBits::none(),
+            visible_stages: PipelineStages::none(),
+            visible_access: AccessFlagBits::none(),
+        };
+
+        if write || transition {
+            accesses.written(stages, access, write);
+        }
+
+        accesses
+    }
+
+    // Returns true if the range has been written and the write hasn't been flushed yet.
+    #[inline]
+    fn has_pending_write(&self) -> bool {
+        !is_empty(self.write_stages)
+    }
+
+    // Registers an access by the command that is about to be added. If a barrier is needed,
+    // returns the stages and access types that it must wait upon.
+    //
+    // `transition` must be true if the barrier transitions the range to a new layout, in which
+    // case a barrier is always needed.
+    fn access(&mut self, stages: PipelineStages, access: AccessFlagBits, write: bool,
+              transition: bool) -> Option<(PipelineStages, AccessFlagBits)>
+    {
+        // Writes and layout transitions must wait for all the previous accesses.
+        if write || transition {
+            let src_stages = self.stages | self.write_stages;
+            let src_access = self.access | self.write_access;
+            self.stages = stages;
+            self.access = access;
+            self.written(stages, access, write);
+            return Some((src_stages, src_access));
+        }
+
+        self.stages |= stages;
+        self.access |= access;
+
+        // Reads must wait for the last write, unless a previous barrier has already made this
+        // write visible to them. The barriers that made the write visible are part of the
+        // dependency chain, so we wait for their destination stages as well.
+        if self.has_pending_write() &&
+           !is_included(stages, access, self.visible_stages, self.visible_access)
+        {
+            let src_stages = self.write_stages | self.visible_stages;
+            let src_access = self.write_access;
+            self.visible_stages |= stages;
+            self.visible_access |= access;
+            return Some((src_stages, src_access));
+        }
+
+        None
+    }
+
+    // Registers that the range has been written, or transitioned to a new layout by a barrier
+    // whose destination is the given stages and access types.
+    fn written(&mut self, stages: PipelineStages, access: AccessFlagBits, write: bool) {
+        self.write_stages = stages;
+        if write {
+            // The write isn't visible to anything, not even to the next commands of its stages.
+            self.write_access = access;
+            self.visible_stages = PipelineStages::none();
+            self.visible_access = AccessFlagBits::none();
+        } else {
+            // The barrier of the transition makes it visible to the command.
+            self.write_access = AccessFlagBits::none();
+            self.visible_stages = stages;
+            self.visible_access = access;
+        }
+    }
+
+    // Registers that the last write has been made visible to all the stages and access types.
+    #[inline]
+    fn flushed(&mut self) {
+        self.write_stages = PipelineStages::none();
+        self.write_access = AccessFlagBits::none();
+        self.visible_stages = PipelineStages::none();
+        self.visible_access = AccessFlagBits::none();
+    }
 }
 
 impl<I> AutoPipelineBarriersLayer<I> {
+    /// Builds a new layer that wraps around an existing builder.
     #[inline]
     pub fn new(inner: I) -> AutoPipelineBarriersLayer<I> {
         AutoPipelineBarriersLayer {
             inner: inner,
+            buffers: Vec::new(),
+            images: Vec::new(),
+            in_render_pass: false,
+            render_pass_writes: false,
         }
     }
-}
-
-/*unsafe impl<C, I, L> AddCommand<C> for AutoPipelineBarriersLayer<I, L>
-    where I: for<'r> AddCommand<&'r C, Out = I>
-{
-    type Out = AutoPipelineBarriersLayer<I, (L, C)>;
 
+    /// Builds a new layer that wraps around a builder that is already inside a render pass.
+    ///
+    /// This is the case of secondary command buffers that are executed inside of a subpass. No
+    /// pipeline barrier is ever added by the layer in this situation.
     #[inline]
-    fn add(self, command: C) -> Self::Out {
+    pub fn inside_render_pass(inner: I) -> AutoPipelineBarriersLayer<I> {
         AutoPipelineBarriersLayer {
-            inner: AddCommand::add(self.inner, command),
+            in_render_pass: true,
+            .. AutoPipelineBarriersLayer::new(inner)
+        }
+    }
+
+    // Registers an access to `buffer` by the command that is about to be added, and adds to
+    // `barrier` what is needed to synchronize this access with the previous commands.
+    fn access_buffer<'a, B: ?Sized>(&mut self, barrier: &mut CmdPipelineBarrier<'a>,
+                                    buffer: &'a B, stages: PipelineStages,
+                                    access: AccessFlagBits, write: bool)
+        where B: BufferAccess
+    {
+        let (raw, offset) = {
+            let inner = buffer.inner();
+            (inner.buffer.internal_object(), inner.offset)
+        };
+        let size = buffer.size();
+
+        let needs_barrier = self.access_raw_buffer(raw, offset, size, stages, access, write);
+        if let Some((src_stages, src_access, exact_range)) = needs_barrier {
+            unsafe {
+                if exact_range {
+                    barrier.add_buffer_memory_barrier(buffer, src_stages, src_access, stages,
+                                                      access, false, None, 0, size);
+                } else {
+                    barrier.add_memory_barrier(src_stages, src_access, stages, access, false);
+                }
+            }
         }
     }
-}*/
+
+    // Registers an access to the range of the raw buffer `raw` by the command that is about to
+    // be added. If a barrier is needed, returns the stages and access types that it must wait
+    // upon, and whether all the conflicting ranges are exactly the accessed range.
+    fn access_raw_buffer(&mut self, raw: vk::Buffer, offset: usize, size: usize,
+                         stages: PipelineStages, access: AccessFlagBits, write: bool)
+                         -> Option<(PipelineStages, AccessFlagBits, bool)>
+    {
+        let mut found = false;
+        let mut needs_barrier = false;
+        let mut src_stages = PipelineStages::none();
+        let mut src_access = AccessFlagBits::none();
+        // True if all the ranges we conflict with are exactly the range of `buffer`, in which
+        // case we can use a buffer memory barrier instead of a global memory barrier.
+        let mut exact_range = true;
+
+        for state in self.buffers.iter_mut() {
+            if state.raw != raw || state.offset >= offset + size ||
+               offset >= state.offset + state.size
+            {
+                continue;
+            }
+
+            found = true;
+
+            if let Some((s, a)) = state.accesses.access(stages, access, write, false) {
+                needs_barrier = true;
+                src_stages |= s;
+                src_access |= a;
+                exact_range = exact_range && state.offset == offset && state.size == size;
+            }
+
+            let end = cmp::max(state.offset + state.size, offset + size);
+            state.offset = cmp::min(state.offset, offset);
+            state.size = end - state.offset;
+        }
+
+        if !found {
+            self.buffers.push(BufferState {
+                raw: raw,
+                offset: offset,
+                size: size,
+                accesses: Accesses::new(stages, access, write, false),
+            });
+        }
+
+        if needs_barrier {
+            Some((src_stages, src_access, exact_range))
+        } else {
+            None
+        }
+    }
+
+    // Registers an access to `range` of `image` by the command that is about to be added, and
+    // adds to `barrier` what is needed to synchronize this access with the previous commands and
+    // to transition the range to `layout`.
+    //
+    // `keep_alive` must be `Some` if `layout` is different from the default layout of the image.
+    fn access_image<'a, T: ?Sized>(&mut self, barrier: &mut CmdPipelineBarrier<'a>, image: &'a T,
+                                   keep_alive: Option<Arc<ImageAccess + Send + Sync>>,
+                                   range: ImageSubresourceRange, stages: PipelineStages,
+                                   access: AccessFlagBits, write: bool, layout: Layout)
+        where T: ImageAccess
+    {
+        let raw = image.inner().internal_object();
+        let range = ImageSubresourceRange { aspect: None, .. range };
+
+        // Parts of `range` that haven't been accessed by the previous commands.
+        let mut remaining = vec![range];
+        // States that replace the parts of the existing states that are outside of `range`.
+        let mut split_states = Vec::new();
+
+        for state in self.images.iter_mut() {
+            if state.raw != raw {
+                continue;
+            }
+
+            let common = match state.range.intersection(&range) {
+                Some(r) => r,
+                None => continue,
+            };
+
+            // The parts of the existing range that we don't access keep their state.
+            for part in subtract_range(&state.range, &common) {
+                split_states.push(ImageState { range: part, .. state.clone() });
+            }
+
+            let transition = state.layout != layout;
+            if let Some((s, a)) = state.accesses.access(stages, access, write, transition) {
+                unsafe {
+                    barrier.add_image_memory_barrier(image, common.mipmap_levels(),
+                                                     common.array_layers(), s, a, stages, access,
+                                                     false, None, state.layout, layout);
+                }
+                state.layout = layout;
+            }
+
+            state.range = common;
+            if state.image.is_none() {
+                state.image = keep_alive.clone();
+            }
+
+            debug_assert!(state.layout == state.default_layout || state.image.is_some());
+
+            remaining = remaining.iter().flat_map(|r| subtract_range(r, &common)).collect();
+        }
+
+        self.images.extend(split_states);
+
+        if remaining.is_empty() {
+            return;
+        }
+
+        let initial_layout = image.initial_layout_requirement();
+        let default_layout = image.final_layout_requirement();
+        debug_assert!(layout == default_layout || keep_alive.is_some());
+        let top = PipelineStages { top_of_pipe: true, .. PipelineStages::none() };
+
+        for part in remaining {
+            if initial_layout != layout {
+                unsafe {
+                    barrier.add_image_memory_barrier(image, part.mipmap_levels(),
+                                                     part.array_layers(), top,
+                                                     AccessFlagBits::none(), stages, access,
+                                                     false, None, initial_layout, layout);
+                }
+            }
+
+            self.images.push(ImageState {
+                raw: raw,
+                range: part,
+                image: keep_alive.clone(),
+                default_layout: default_layout,
+                layout: layout,
+                accesses: Accesses::new(stages, access, write, initial_layout != layout),
+            });
+        }
+
+        // An image whose initial layout isn't its final layout must be entirely in its final
+        // layout at the end of the command buffer, including the parts that are never accessed.
+        // We track these parts as if they had been accessed at the top of the pipeline.
+        if initial_layout != default_layout && keep_alive.is_some() {
+            let mut untracked = vec![ImageSubresourceRange::whole_image(image)];
+            for state in self.images.iter().filter(|s| s.raw == raw) {
+                untracked = untracked.iter().flat_map(|r| subtract_range(r, &state.range))
+                                     .collect();
+            }
+
+            for part in untracked {
+                self.images.push(ImageState {
+                    raw: raw,
+                    range: part,
+                    image: keep_alive.clone(),
+                    default_layout: default_layout,
+                    layout: initial_layout,
+                    accesses: Accesses::new(top, AccessFlagBits::none(), false, false),
+                });
+            }
+        }
+    }
+
+    // Adds `barrier` to the inner builder.
+    fn add_barrier<'a>(mut self, mut barrier: CmdPipelineBarrier<'a>)
+                       -> Result<AutoPipelineBarriersLayer<I>, CommandAddError>
+        where I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>
+    {
+        // Pipeline barriers inside of render passes require a subpass self-dependency. Instead
+        // we synchronize everything before the render pass starts and after it ends.
+        if self.in_render_pass {
+            return Ok(self);
+        }
+
+        if self.render_pass_writes {
+            unsafe {
+                let src_access = AccessFlagBits {
+                    shader_write: true,
+                    color_attachment_write: true,
+                    depth_stencil_attachment_write: true,
+                    .. AccessFlagBits::none()
+                };
+                barrier.add_memory_barrier(all_graphics(), src_access, all_commands(),
+                                           memory_read_write(), false);
+            }
+            self.render_pass_writes = false;
+        }
+
+        if barrier.is_empty() {
+            return Ok(self);
+        }
+
+        Ok(AutoPipelineBarriersLayer {
+            inner: try!(AddCommand::add(self.inner, &barrier)),
+            buffers: self.buffers,
+            images: self.images,
+            in_render_pass: self.in_render_pass,
+            render_pass_writes: self.render_pass_writes,
+        })
+    }
+
+    // Adds `command` to the inner builder.
+    #[inline]
+    fn add_command<C, O>(self, command: C)
+                         -> Result<AutoPipelineBarriersLayer<O>, CommandAddError>
+        where I: AddCommand<C, Out = O>
+    {
+        Ok(AutoPipelineBarriersLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
+            buffers: self.buffers,
+            images: self.images,
+            in_render_pass: self.in_render_pass,
+            render_pass_writes: self.render_pass_writes,
+        })
+    }
+
+    // Adds a barrier that makes all the writes of the previous commands available to the given
+    // stages, and that transitions all the images back to their default layout.
+    //
+    // If `memory` is false, only the layout transitions are performed.
+    fn flush(self, dest_stages: PipelineStages, dest_access: AccessFlagBits, memory: bool)
+             -> Result<AutoPipelineBarriersLayer<I>, CommandAddError>
+        where I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>
+    {
+        let AutoPipelineBarriersLayer { inner, mut buffers, mut images, in_render_pass,
+                                        render_pass_writes } = self;
+
+        let inner = {
+            let mut barrier = CmdPipelineBarrier::new();
+            let mut src_stages = PipelineStages::none();
+            let mut src_access = AccessFlagBits::none();
+            let mut any_write = false;
+
+            for state in buffers.iter().filter(|s| s.accesses.has_pending_write()) {
+                src_stages |= state.accesses.write_stages;
+                src_access |= state.accesses.write_access;
+                any_write = true;
+            }
+
+            for state in images.iter() {
+                if needs_transition_back(state) {
+                    // Like any transition, this one must wait for all the previous accesses.
+                    let image = state.image.as_ref().unwrap();
+                    let accesses = &state.accesses;
+                    unsafe {
+                        barrier.add_image_memory_barrier(&**image, state.range.mipmap_levels(),
+                                                         state.range.array_layers(),
+                                                         accesses.stages | accesses.write_stages,
+                                                         accesses.access | accesses.write_access,
+                                                         dest_stages, dest_access, false, None,
+                                                         state.layout, state.default_layout);
+                    }
+                } else if state.accesses.has_pending_write() {
+                    src_stages |= state.accesses.write_stages;
+                    src_access |= state.accesses.write_access;
+                    any_write = true;
+                }
+            }
+
+            if memory && any_write {
+                unsafe {
+                    barrier.add_memory_barrier(src_stages, src_access, dest_stages, dest_access,
+                                               false);
+                }
+            }
+
+            if barrier.is_empty() {
+                inner
+            } else {
+                try!(AddCommand::add(inner, &barrier))
+            }
+        };
+
+        for state in images.iter_mut() {
+            if needs_transition_back(state) {
+                state.layout = state.default_layout;
+                state.accesses.access(dest_stages, dest_access, false, true);
+            }
+        }
+
+        if memory {
+            for state in buffers.iter_mut() {
+                state.accesses.flushed();
+            }
+            for state in images.iter_mut() {
+                state.accesses.flushed();
+            }
+        }
+
+        Ok(AutoPipelineBarriersLayer {
+            inner: inner,
+            buffers: buffers,
+            images: images,
+            in_render_pass: in_render_pass,
+            render_pass_writes: render_pass_writes,
+        })
+    }
+}
+
+// Returns the parts of `range` that are not in `other`, as a list of non-overlapping ranges.
+// Aspects are ignored.
+fn subtract_range(range: &ImageSubresourceRange, other: &ImageSubresourceRange)
+                  -> Vec<ImageSubresourceRange>
+{
+    let common = match range.intersection(other) {
+        Some(r) => r,
+        None => return vec![*range],
+    };
+
+    let mips = range.mipmap_levels();
+    let layers = range.array_layers();
+    let common_mips = common.mipmap_levels();
+    let common_layers = common.array_layers();
+
+    let mut out = Vec::with_capacity(4);
+
+    // The mipmap levels before and after the common ones, with all the array layers.
+    if mips.start < common_mips.start {
+        out.push(ImageSubresourceRange::new(mips.start .. common_mips.start, layers.clone()));
+    }
+    if common_mips.end < mips.end {
+        out.push(ImageSubresourceRange::new(common_mips.end .. mips.end, layers.clone()));
+    }
+
+    // The array layers before and after the common ones, within the common mipmap levels.
+    if layers.start < common_layers.start {
+        out.push(ImageSubresourceRange::new(common_mips.clone(),
+                                            layers.start .. common_layers.start));
+    }
+    if common_layers.end < layers.end {
+        out.push(ImageSubresourceRange::new(common_mips, common_layers.end .. layers.end));
+    }
+
+    out
+}
+
+// Returns true if none of the stages are set.
+#[inline]
+fn is_empty(stages: PipelineStages) -> bool {
+    let stages: vk::PipelineStageFlagBits = stages.into();
+    stages == 0
+}
+
+// Returns true if all the stages and access types of `stages` and `access` are also in
+// `in_stages` and `in_access`.
+#[inline]
+fn is_included(stages: PipelineStages, access: AccessFlagBits, in_stages: PipelineStages,
+               in_access: AccessFlagBits) -> bool
+{
+    let stages: vk::PipelineStageFlagBits = stages.into();
+    let access: vk::AccessFlagBits = access.into();
+    let in_stages: vk::PipelineStageFlagBits = in_stages.into();
+    let in_access: vk::AccessFlagBits = in_access.into();
+    stages & !in_stages == 0 && access & !in_access == 0
+}
+
+// Returns true if the image must be transitioned back to its default layout. Images whose default
+// layout is `Undefined` or `Preinitialized` can't be transitioned back.
+#[inline]
+fn needs_transition_back(state: &ImageState) -> bool {
+    state.layout != state.default_layout && state.default_layout != Layout::Undefined &&
+    state.default_layout != Layout::Preinitialized
+}
+
+#[inline]
+fn transfer() -> PipelineStages {
+    PipelineStages { transfer: true, .. PipelineStages::none() }
+}
+
+#[inline]
+fn all_graphics() -> PipelineStages {
+    PipelineStages { all_graphics: true, .. PipelineStages::none() }
+}
+
+#[inline]
+fn all_commands() -> PipelineStages {
+    PipelineStages { all_commands: true, .. PipelineStages::none() }
+}
+
+#[inline]
+fn transfer_read() -> AccessFlagBits {
+    AccessFlagBits { transfer_read: true, .. AccessFlagBits::none() }
+}
+
+#[inline]
+fn transfer_write() -> AccessFlagBits {
+    AccessFlagBits { transfer_write: true, .. AccessFlagBits::none() }
+}
+
+#[inline]
+fn memory_read() -> AccessFlagBits {
+    AccessFlagBits { memory_read: true, .. AccessFlagBits::none() }
+}
+
+#[inline]
+fn memory_read_write() -> AccessFlagBits {
+    AccessFlagBits { memory_read: true, memory_write: true, .. AccessFlagBits::none() }
+}
 
 unsafe impl<I, O, E> CommandBufferBuild for AutoPipelineBarriersLayer<I>
-    where I: CommandBufferBuild<Out = O, Err = E>
+    where I: CommandBufferBuild<Out = O, Err = E>,
+          I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>
 {
     type Out = O;
     type Err = E;
 
     #[inline]
     fn build(self) -> Result<O, E> {
-        self.inner.build()
+        if self.in_render_pass {
+            return self.inner.build();
+        }
+
+        // The layers below this one never refuse a pipeline barrier.
+        let this = self.flush(PipelineStages { bottom_of_pipe: true, .. PipelineStages::none() },
+                              AccessFlagBits::none(), false)
+                       .expect("failed to add the final pipeline barrier");
+        this.inner.build()
     }
 }
 
@@ -74,6 +693,11 @@ unsafe impl<I> CommandBufferBuilder for AutoPipelineBarriersLayer<I>
     fn supports_compute(&self) -> bool {
         self.inner.supports_compute()
     }
+
+    #[inline]
+    fn queue_family(&self) -> QueueFamily {
+        self.inner.queue_family()
+    }
 }
 
 macro_rules! pass_through {
@@ -84,35 +708,838 @@ macro_rules! pass_through {
             type Out = AutoPipelineBarriersLayer<O>;
 
             #[inline]
-            fn add(self, command: $cmd) -> Self::Out {
-                AutoPipelineBarriersLayer {
-                    inner: AddCommand::add(self.inner, command),
-                }
+            fn add(self, command: $cmd) -> Result<Self::Out, CommandAddError> {
+                self.add_command(command)
             }
         }
     }
 }
 
-pass_through!((Rp, F), commands_raw::CmdBeginRenderPass<Rp, F>);
-pass_through!((S, Pl), commands_raw::CmdBindDescriptorSets<S, Pl>);
-pass_through!((B), commands_raw::CmdBindIndexBuffer<B>);
+unsafe impl<I, O, Rp, F> AddCommand<commands_raw::CmdBeginRenderPass<Rp, F>> for AutoPipelineBarriersLayer<I>
+    where I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>,
+          I: AddCommand<commands_raw::CmdBeginRenderPass<Rp, F>, Out = O>
+{
+    type Out = AutoPipelineBarriersLayer<O>;
+
+    #[inline]
+    fn add(self, command: commands_raw::CmdBeginRenderPass<Rp, F>) -> Result<Self::Out, CommandAddError> {
+        let mut this = try!(try!(self.add_barrier(CmdPipelineBarrier::new()))
+                                .flush(all_graphics(), memory_read_write(), true));
+        this.in_render_pass = true;
+        this.add_command(command)
+    }
+}
+
+unsafe impl<I, O, S, Pl> AddCommand<commands_raw::CmdBindDescriptorSets<S, Pl>> for AutoPipelineBarriersLayer<I>
+    where I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>,
+          I: AddCommand<commands_raw::CmdBindDescriptorSets<S, Pl>, Out = O>,
+          S: DescriptorSetsCollection
+{
+    type Out = AutoPipelineBarriersLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdBindDescriptorSets<S, Pl>) -> Result<Self::Out, CommandAddError> {
+        let this = {
+            let mut barrier = CmdPipelineBarrier::new();
+
+            let stages = if command.is_graphics() {
+                all_graphics()
+            } else {
+                PipelineStages { compute_shader: true, .. PipelineStages::none() }
+            };
+
+            // We don't know whether the shaders are going to write to the resources, so we have
+            // to assume that they do.
+            let access = AccessFlagBits {
+                uniform_read: true,
+                shader_read: true,
+                shader_write: true,
+                .. AccessFlagBits::none()
+            };
+
+            for buffer in command.sets().buffers_list() {
+                self.access_buffer(&mut barrier, buffer, stages, access, true);
+            }
+
+            for (image, range) in command.sets().images_list() {
+                let layout = image.default_layout();
+                self.access_image(&mut barrier, image, None, range, stages, access, true,
+                                  layout);
+            }
+
+            try!(self.add_barrier(barrier))
+        };
+
+        this.add_command(command)
+    }
+}
+
+unsafe impl<I, O, B> AddCommand<commands_raw::CmdBindIndexBuffer<B>> for AutoPipelineBarriersLayer<I>
+    where I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>,
+          I: AddCommand<commands_raw::CmdBindIndexBuffer<B>, Out = O>,
+          B: BufferAccess
+{
+    type Out = AutoPipelineBarriersLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdBindIndexBuffer<B>) -> Result<Self::Out, CommandAddError> {
+        let this = {
+            let mut barrier = CmdPipelineBarrier::new();
+            let stages = PipelineStages { vertex_input: true, .. PipelineStages::none() };
+            let access = AccessFlagBits { index_read: true, .. AccessFlagBits::none() };
+            self.access_buffer(&mut barrier, command.buffer(), stages, access, false);
+            try!(self.add_barrier(barrier))
+        };
+
+        this.add_command(command)
+    }
+}
+
+unsafe impl<I, O, V> AddCommand<commands_raw::CmdBindVertexBuffers<V>> for AutoPipelineBarriersLayer<I>
+    where I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>,
+          I: AddCommand<commands_raw::CmdBindVertexBuffers<V>, Out = O>
+{
+    type Out = AutoPipelineBarriersLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdBindVertexBuffers<V>) -> Result<Self::Out, CommandAddError> {
+        let this = {
+            let stages = PipelineStages { vertex_input: true, .. PipelineStages::none() };
+            let access = AccessFlagBits { vertex_attribute_read: true, .. AccessFlagBits::none() };
+
+            // The command only gives us the raw buffers, so we use a single memory barrier
+            // instead of buffer memory barriers.
+            let mut needs_barrier = false;
+            let mut src_stages = PipelineStages::none();
+            let mut src_access = AccessFlagBits::none();
+            for (raw, offset, size) in command.raw_ranges() {
+                if let Some((s, a, _)) = self.access_raw_buffer(raw, offset, size, stages, access,
+                                                                false)
+                {
+                    needs_barrier = true;
+                    src_stages |= s;
+                    src_access |= a;
+                }
+            }
+
+            let mut barrier = CmdPipelineBarrier::new();
+            if needs_barrier {
+                unsafe {
+                    barrier.add_memory_barrier(src_stages, src_access, stages, access, false);
+                }
+            }
+            try!(self.add_barrier(barrier))
+        };
+
+        this.add_command(command)
+    }
+}
+
+unsafe impl<I, O, S, D> AddCommand<commands_raw::CmdBlitImage<S, D>> for AutoPipelineBarriersLayer<I>
+    where I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>,
+          I: AddCommand<commands_raw::CmdBlitImage<S, D>, Out = O>,
+          S: ImageAccess + Send + Sync + Clone + 'static,
+          D: ImageAccess + Send + Sync + Clone + 'static
+{
+    type Out = AutoPipelineBarriersLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdBlitImage<S, D>) -> Result<Self::Out, CommandAddError> {
+        let this = {
+            let mut barrier = CmdPipelineBarrier::new();
+            self.access_image(&mut barrier, command.source(),
+                              Some(Arc::new(command.source().clone()) as Arc<_>),
+                              command.source_subresource_range(), transfer(),
+                              transfer_read(), false, Layout::TransferSrcOptimal);
+            self.access_image(&mut barrier, command.destination(),
+                              Some(Arc::new(command.destination().clone()) as Arc<_>),
+                              command.destination_subresource_range(),
+                              transfer(), transfer_write(), true, Layout::TransferDstOptimal);
+            try!(self.add_barrier(barrier))
+        };
+
+        this.add_command(command)
+    }
+}
+
+unsafe impl<I, O, S, D> AddCommand<commands_raw::CmdCopyBuffer<S, D>> for AutoPipelineBarriersLayer<I>
+    where I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>,
+          I: AddCommand<commands_raw::CmdCopyBuffer<S, D>, Out = O>,
+          S: BufferAccess,
+          D: BufferAccess
+{
+    type Out = AutoPipelineBarriersLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdCopyBuffer<S, D>) -> Result<Self::Out, CommandAddError> {
+        let this = {
+            let mut barrier = CmdPipelineBarrier::new();
+            self.access_buffer(&mut barrier, command.source(), transfer(), transfer_read(),
+                               false);
+            self.access_buffer(&mut barrier, command.destination(), transfer(), transfer_write(),
+                               true);
+            try!(self.add_barrier(barrier))
+        };
+
+        this.add_command(command)
+    }
+}
+
+unsafe impl<I, O, S, D> AddCommand<commands_raw::CmdCopyBufferToImage<S, D>> for AutoPipelineBarriersLayer<I>
+    where I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>,
+          I: AddCommand<commands_raw::CmdCopyBufferToImage<S, D>, Out = O>,
+          S: BufferAccess,
+          D: ImageAccess + Send + Sync + Clone + 'static
+{
+    type Out = AutoPipelineBarriersLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdCopyBufferToImage<S, D>) -> Result<Self::Out, CommandAddError> {
+        let this = {
+            let mut barrier = CmdPipelineBarrier::new();
+            self.access_buffer(&mut barrier, command.source(), transfer(), transfer_read(),
+                               false);
+            self.access_image(&mut barrier, command.destination(),
+                              Some(Arc::new(command.destination().clone()) as Arc<_>),
+                              command.destination_subresource_range(),
+                              transfer(), transfer_write(), true, command.destination_layout());
+            try!(self.add_barrier(barrier))
+        };
+
+        this.add_command(command)
+    }
+}
+
+unsafe impl<I, O, S, D> AddCommand<commands_raw::CmdCopyImage<S, D>> for AutoPipelineBarriersLayer<I>
+    where I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>,
+          I: AddCommand<commands_raw::CmdCopyImage<S, D>, Out = O>,
+          S: ImageAccess + Send + Sync + Clone + 'static,
+          D: ImageAccess + Send + Sync + Clone + 'static
+{
+    type Out = AutoPipelineBarriersLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdCopyImage<S, D>) -> Result<Self::Out, CommandAddError> {
+        let this = {
+            let mut barrier = CmdPipelineBarrier::new();
+            self.access_image(&mut barrier, command.source(),
+                              Some(Arc::new(command.source().clone()) as Arc<_>),
+                              command.source_subresource_range(), transfer(),
+                              transfer_read(), false, Layout::TransferSrcOptimal);
+            self.access_image(&mut barrier, command.destination(),
+                              Some(Arc::new(command.destination().clone()) as Arc<_>),
+                              command.destination_subresource_range(),
+                              transfer(), transfer_write(), true, Layout::TransferDstOptimal);
+            try!(self.add_barrier(barrier))
+        };
+
+        this.add_command(command)
+    }
+}
+
+unsafe impl<I, O, S, D> AddCommand<commands_raw::CmdCopyImageToBuffer<S, D>> for AutoPipelineBarriersLayer<I>
+    where I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>,
+          I: AddCommand<commands_raw::CmdCopyImageToBuffer<S, D>, Out = O>,
+          S: ImageAccess + Send + Sync + Clone + 'static,
+          D: BufferAccess
+{
+    type Out = AutoPipelineBarriersLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdCopyImageToBuffer<S, D>) -> Result<Self::Out, CommandAddError> {
+        let this = {
+            let mut barrier = CmdPipelineBarrier::new();
+            self.access_image(&mut barrier, command.source(),
+                              Some(Arc::new(command.source().clone()) as Arc<_>),
+                              command.source_subresource_range(), transfer(),
+                              transfer_read(), false, command.source_layout());
+            self.access_buffer(&mut barrier, command.destination(), transfer(), transfer_write(),
+                               true);
+            try!(self.add_barrier(barrier))
+        };
+
+        this.add_command(command)
+    }
+}
+
+unsafe impl<I, O, Q, B> AddCommand<commands_raw::CmdCopyQueryPoolResults<Q, B>> for AutoPipelineBarriersLayer<I>
+    where I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>,
+          I: AddCommand<commands_raw::CmdCopyQueryPoolResults<Q, B>, Out = O>,
+          B: BufferAccess
+{
+    type Out = AutoPipelineBarriersLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdCopyQueryPoolResults<Q, B>)
+           -> Result<Self::Out, CommandAddError>
+    {
+        let this = {
+            let mut barrier = CmdPipelineBarrier::new();
+            self.access_buffer(&mut barrier, command.destination(), transfer(), transfer_write(),
+                               true);
+            try!(self.add_barrier(barrier))
+        };
+
+        this.add_command(command)
+    }
+}
+
+unsafe impl<I, O> AddCommand<commands_raw::CmdEndRenderPass> for AutoPipelineBarriersLayer<I>
+    where I: AddCommand<commands_raw::CmdEndRenderPass, Out = O>
+{
+    type Out = AutoPipelineBarriersLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdEndRenderPass) -> Result<Self::Out, CommandAddError> {
+        // We don't know which attachments have been written by the render pass, so the next
+        // barrier will wait upon all the writes of the graphics pipeline.
+        self.in_render_pass = false;
+        self.render_pass_writes = true;
+        self.add_command(command)
+    }
+}
+
+unsafe impl<I, O, Cb> AddCommand<commands_raw::CmdExecuteCommands<Cb>> for AutoPipelineBarriersLayer<I>
+    where I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>,
+          I: AddCommand<commands_raw::CmdExecuteCommands<Cb>, Out = O>,
+          Cb: CommandBuffer
+{
+    type Out = AutoPipelineBarriersLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdExecuteCommands<Cb>) -> Result<Self::Out, CommandAddError> {
+        // We don't know which stages of the secondary command buffer access the resources, so we
+        // have to be conservative. The secondary command buffer leaves its images in their
+        // default layout.
+        let this = {
+            let mut barrier = CmdPipelineBarrier::new();
+            for (buffer, exclusive) in command.command_buffer().buffers_list() {
+                let access = if exclusive { memory_read_write() } else { memory_read() };
+                self.access_buffer(&mut barrier, buffer, all_commands(), access, exclusive);
+            }
+            for (image, range, exclusive) in command.command_buffer().images_list() {
+                let access = if exclusive { memory_read_write() } else { memory_read() };
+                let layout = image.default_layout();
+                self.access_image(&mut barrier, image, None, range, all_commands(), access,
+                                  exclusive, layout);
+            }
+            try!(self.add_barrier(barrier))
+        };
+
+        this.add_command(command)
+    }
+}
+
+unsafe impl<I, O, B> AddCommand<commands_raw::CmdFillBuffer<B>> for AutoPipelineBarriersLayer<I>
+    where I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>,
+          I: AddCommand<commands_raw::CmdFillBuffer<B>, Out = O>,
+          B: BufferAccess
+{
+    type Out = AutoPipelineBarriersLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdFillBuffer<B>) -> Result<Self::Out, CommandAddError> {
+        let this = {
+            let mut barrier = CmdPipelineBarrier::new();
+            self.access_buffer(&mut barrier, command.buffer(), transfer(), transfer_write(),
+                               true);
+            try!(self.add_barrier(barrier))
+        };
+
+        this.add_command(command)
+    }
+}
+
+unsafe impl<I, O, Img> AddCommand<commands_raw::CmdGenerateMipmaps<Img>> for AutoPipelineBarriersLayer<I>
+    where I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>,
+          I: AddCommand<commands_raw::CmdGenerateMipmaps<Img>, Out = O>,
+          Img: ImageAccess + Send + Sync + Clone + 'static
+{
+    type Out = AutoPipelineBarriersLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdGenerateMipmaps<Img>) -> Result<Self::Out, CommandAddError> {
+        // The command handles the transitions between the mipmap levels by itself. From the
+        // outside the whole image is in the `TransferDstOptimal` layout before and after it.
+        let this = {
+            let mut barrier = CmdPipelineBarrier::new();
+            let (stages, access) = if command.uses_compute() {
+                (PipelineStages { compute_shader: true, .. PipelineStages::none() },
+                 AccessFlagBits { shader_read: true, shader_write: true,
+                                  .. AccessFlagBits::none() })
+            } else {
+                (transfer(),
+                 AccessFlagBits { transfer_read: true, transfer_write: true,
+                                  .. AccessFlagBits::none() })
+            };
+            self.access_image(&mut barrier, command.image(),
+                              Some(Arc::new(command.image().clone()) as Arc<_>),
+                              ImageSubresourceRange::whole_image(command.image()), stages,
+                              access, true, Layout::TransferDstOptimal);
+            try!(self.add_barrier(barrier))
+        };
+
+        this.add_command(command)
+    }
+}
+
+unsafe impl<I, O, S, D> AddCommand<commands_raw::CmdResolveImage<S, D>> for AutoPipelineBarriersLayer<I>
+    where I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>,
+          I: AddCommand<commands_raw::CmdResolveImage<S, D>, Out = O>,
+          S: ImageAccess + Send + Sync + Clone + 'static,
+          D: ImageAccess + Send + Sync + Clone + 'static
+{
+    type Out = AutoPipelineBarriersLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdResolveImage<S, D>) -> Result<Self::Out, CommandAddError> {
+        let this = {
+            let mut barrier = CmdPipelineBarrier::new();
+            self.access_image(&mut barrier, command.source(),
+                              Some(Arc::new(command.source().clone()) as Arc<_>),
+                              command.source_subresource_range(), transfer(),
+                              transfer_read(), false, Layout::TransferSrcOptimal);
+            self.access_image(&mut barrier, command.destination(),
+                              Some(Arc::new(command.destination().clone()) as Arc<_>),
+                              command.destination_subresource_range(),
+                              transfer(), transfer_write(), true, Layout::TransferDstOptimal);
+            try!(self.add_barrier(barrier))
+        };
+
+        this.add_command(command)
+    }
+}
+
+unsafe impl<I, O, B, D> AddCommand<commands_raw::CmdUpdateBuffer<B, D>> for AutoPipelineBarriersLayer<I>
+    where I: for<'r> AddCommand<&'r CmdPipelineBarrier<'r>, Out = I>,
+          I: AddCommand<commands_raw::CmdUpdateBuffer<B, D>, Out = O>,
+          B: BufferAccess
+{
+    type Out = AutoPipelineBarriersLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdUpdateBuffer<B, D>) -> Result<Self::Out, CommandAddError> {
+        let this = {
+            let mut barrier = CmdPipelineBarrier::new();
+            self.access_buffer(&mut barrier, command.buffer(), transfer(), transfer_write(),
+                               true);
+            try!(self.add_barrier(barrier))
+        };
+
+        this.add_command(command)
+    }
+}
+
+pass_through!((Q), commands_raw::CmdBeginQuery<Q>);
 pass_through!((Pl), commands_raw::CmdBindPipeline<Pl>);
-pass_through!((V), commands_raw::CmdBindVertexBuffers<V>);
-pass_through!((S, D), commands_raw::CmdBlitImage<S, D>);
 pass_through!((), commands_raw::CmdClearAttachments);
-pass_through!((S, D), commands_raw::CmdCopyBuffer<S, D>);
-pass_through!((S, D), commands_raw::CmdCopyBufferToImage<S, D>);
-pass_through!((S, D), commands_raw::CmdCopyImage<S, D>);
 pass_through!((), commands_raw::CmdDispatchRaw);
 pass_through!((), commands_raw::CmdDrawRaw);
 pass_through!((), commands_raw::CmdDrawIndexedRaw);
-pass_through!((), commands_raw::CmdEndRenderPass);
-pass_through!((C), commands_raw::CmdExecuteCommands<C>);
-pass_through!((B), commands_raw::CmdFillBuffer<B>);
+pass_through!((Q), commands_raw::CmdEndQuery<Q>);
 pass_through!((), commands_raw::CmdNextSubpass);
 pass_through!((Pc, Pl), commands_raw::CmdPushConstants<Pc, Pl>);
-pass_through!((S, D), commands_raw::CmdResolveImage<S, D>);
+pass_through!((Q), commands_raw::CmdResetQueryPool<Q>);
 pass_through!((), commands_raw::CmdSetEvent);
 pass_through!((), commands_raw::CmdSetState);
-pass_through!((B, D), commands_raw::CmdUpdateBuffer<B, D>);
+pass_through!((Q), commands_raw::CmdWriteTimestamp<Q>);
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+    use std::sync::Arc;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBufferBuilder;
+    use command_buffer::cb::AddCommand;
+    use command_buffer::cb::CommandAddError;
+    use command_buffer::commands_raw::CmdBindDescriptorSets;
+    use command_buffer::commands_raw::CmdBindVertexBuffers;
+    use command_buffer::commands_raw::CmdDispatchRaw;
+    use command_buffer::commands_raw::CmdDrawRaw;
+    use command_buffer::commands_raw::CmdPipelineBarrier;
+    use descriptor::descriptor::DescriptorBufferContentDesc;
+    use descriptor::descriptor::DescriptorBufferDesc;
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::DescriptorDescTy;
+    use descriptor::descriptor::DescriptorImageDesc;
+    use descriptor::descriptor::DescriptorImageDescArray;
+    use descriptor::descriptor::DescriptorImageDescDimensions;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::descriptor_set::SimpleDescriptorSetBufferExt;
+    use descriptor::descriptor_set::SimpleDescriptorSetBuilder;
+    use descriptor::descriptor_set::SimpleDescriptorSetImageExt;
+    use descriptor::pipeline_layout::PipelineLayout;
+    use descriptor::pipeline_layout::PipelineLayoutDesc;
+    use descriptor::pipeline_layout::PipelineLayoutDescNames;
+    use descriptor::pipeline_layout::PipelineLayoutDescPcRange;
+    use format::Format;
+    use framebuffer::EmptySinglePassRenderPassDesc;
+    use framebuffer::Framebuffer;
+    use framebuffer::RenderPass;
+    use image::Dimensions;
+    use image::ImageAccess;
+    use image::ImageSubresourceRange;
+    use image::ImmutableImage;
+    use image::Layout;
+    use image::StorageImage;
+    use image::Swizzle;
+    use image::ViewType;
+    use image::view::ImageView;
+    use mock;
+    use pipeline::vertex::SingleBufferDefinition;
+    use pipeline::vertex::Vertex;
+    use pipeline::vertex::VertexMemberInfo;
+    use super::AutoPipelineBarriersLayer;
+    use super::needs_transition_back;
+    use super::subtract_range;
+    use super::transfer;
+    use super::transfer_write;
+
+    #[derive(Debug, Copy, Clone)]
+    struct TestVertex {
+        position: [f32; 2],
+    }
 
+    unsafe impl Vertex for TestVertex {
+        #[inline]
+        fn member(_: &str) -> Option<VertexMemberInfo> {
+            None
+        }
+    }
+
+    // Pipeline layout with a single storage buffer named `buf`.
+    struct StorageBufferDesc;
+
+    unsafe impl PipelineLayoutDesc for StorageBufferDesc {
+        fn num_sets(&self) -> usize {
+            1
+        }
+
+        fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+            if set == 0 { Some(1) } else { None }
+        }
+
+        fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+            if set != 0 || binding != 0 {
+                return None;
+            }
+
+            Some(DescriptorDesc {
+                ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                    dynamic: Some(false),
+                    storage: true,
+                    content: DescriptorBufferContentDesc::F32,
+                }),
+                array_count: 1,
+                stages: ShaderStages::all(),
+                readonly: false,
+            })
+        }
+
+        fn num_push_constants_ranges(&self) -> usize {
+            0
+        }
+
+        fn push_constants_range(&self, _: usize) -> Option<PipelineLayoutDescPcRange> {
+            None
+        }
+    }
+
+    unsafe impl PipelineLayoutDescNames for StorageBufferDesc {
+        fn descriptor_by_name(&self, name: &str) -> Option<(usize, usize)> {
+            if name == "buf" { Some((0, 0)) } else { None }
+        }
+    }
+
+    // Pipeline layout with a single sampled 2D image named `img`.
+    struct SampledImageDesc;
+
+    unsafe impl PipelineLayoutDesc for SampledImageDesc {
+        fn num_sets(&self) -> usize {
+            1
+        }
+
+        fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+            if set == 0 { Some(1) } else { None }
+        }
+
+        fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+            if set != 0 || binding != 0 {
+                return None;
+            }
+
+            Some(DescriptorDesc {
+                ty: DescriptorDescTy::ImageAccess(DescriptorImageDesc {
+                    sampled: true,
+                    dimensions: DescriptorImageDescDimensions::TwoDimensional,
+                    format: None,
+                    multisampled: false,
+                    array_layers: DescriptorImageDescArray::NonArrayed,
+                }),
+                array_count: 1,
+                stages: ShaderStages::all(),
+                readonly: true,
+            })
+        }
+
+        fn num_push_constants_ranges(&self) -> usize {
+            0
+        }
+
+        fn push_constants_range(&self, _: usize) -> Option<PipelineLayoutDescPcRange> {
+            None
+        }
+    }
+
+    unsafe impl PipelineLayoutDescNames for SampledImageDesc {
+        fn descriptor_by_name(&self, name: &str) -> Option<(usize, usize)> {
+            if name == "img" { Some((0, 0)) } else { None }
+        }
+    }
+
+    // Builder that discards the commands, so that the state of the layer can be inspected.
+    struct Discard;
+
+    unsafe impl<C> AddCommand<C> for Discard {
+        type Out = Discard;
+
+        #[inline]
+        fn add(self, _: C) -> Result<Discard, CommandAddError> {
+            Ok(Discard)
+        }
+    }
+
+    #[test]
+    fn barrier_between_write_and_read() {
+        let (device, queue) = mock_dev_and_queue!();
+        let family = queue.family();
+
+        let a = CpuAccessibleBuffer::from_data(&device, &BufferUsage::all(), Some(family),
+                                               0u32).unwrap();
+        let b = CpuAccessibleBuffer::from_data(&device, &BufferUsage::all(), Some(family),
+                                               0u32).unwrap();
+        let c = CpuAccessibleBuffer::from_data(&device, &BufferUsage::all(), Some(family),
+                                               0u32).unwrap();
+
+        let _cb = AutoCommandBufferBuilder::new(device.clone(), family).unwrap()
+            .fill_buffer(a.clone(), 5).unwrap()
+            .copy_buffer(a.clone(), b.clone()).unwrap()
+            .copy_buffer(a.clone(), c.clone()).unwrap();
+
+        let calls = mock::calls(&device);
+        let calls = calls.iter().filter(|c| c.starts_with("Cmd")).cloned().collect::<Vec<_>>();
+        assert_eq!(calls, vec!["CmdFillBuffer", "CmdPipelineBarrier", "CmdCopyBuffer",
+                               "CmdCopyBuffer"]);
+    }
+
+    #[test]
+    fn bind_vertex_buffer_after_write() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let vertices = iter::repeat(TestVertex { position: [0.0; 2] }).take(3);
+        let vb = CpuAccessibleBuffer::from_iter(&device, &BufferUsage::all(),
+                                                Some(queue.family()), vertices).unwrap();
+
+        let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .fill_buffer(vb.clone(), 0).unwrap();
+        let def = SingleBufferDefinition::<TestVertex>::new();
+        let _cb = cb.add(CmdBindVertexBuffers::new(&def, vb.clone())).unwrap();
+
+        let calls = mock::calls(&device);
+        let calls = calls.iter().filter(|c| c.starts_with("Cmd")).cloned().collect::<Vec<_>>();
+        assert_eq!(calls, vec!["CmdFillBuffer", "CmdPipelineBarrier", "CmdBindVertexBuffers"]);
+    }
+
+    #[test]
+    fn reads_at_different_stages_after_write() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let vertices = iter::repeat(TestVertex { position: [0.0; 2] }).take(3);
+        let vb = CpuAccessibleBuffer::from_iter(&device, &BufferUsage::all(),
+                                                Some(queue.family()), vertices).unwrap();
+        let copy = CpuAccessibleBuffer::from_iter(&device, &BufferUsage::all(),
+                                                  Some(queue.family()),
+                                                  iter::repeat(0u8).take(24)).unwrap();
+        let def = SingleBufferDefinition::<TestVertex>::new();
+
+        // The barrier before the copy only makes the fill visible to the transfer stage, so the
+        // vertex input needs its own barrier.
+        let _cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .fill_buffer(vb.clone(), 0).unwrap()
+            .copy_buffer(vb.clone(), copy.clone()).unwrap()
+            .add(CmdBindVertexBuffers::new(&def, vb.clone())).unwrap()
+            .add(CmdBindVertexBuffers::new(&def, vb.clone())).unwrap();
+
+        let calls = mock::calls(&device);
+        let calls = calls.iter().filter(|c| c.starts_with("Cmd")).cloned().collect::<Vec<_>>();
+        assert_eq!(calls, vec!["CmdFillBuffer", "CmdPipelineBarrier", "CmdCopyBuffer",
+                               "CmdPipelineBarrier", "CmdBindVertexBuffers",
+                               "CmdBindVertexBuffers"]);
+    }
+
+    #[test]
+    fn draw() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let vertices = iter::repeat(TestVertex { position: [0.0; 2] }).take(3);
+        let vb = CpuAccessibleBuffer::from_iter(&device, &BufferUsage::all(),
+                                                Some(queue.family()), vertices).unwrap();
+
+        let rp = Arc::new(RenderPass::new(device.clone(), EmptySinglePassRenderPassDesc).unwrap());
+        let framebuffer = Framebuffer::new(rp, [32, 32, 1], ()).unwrap();
+        let def = SingleBufferDefinition::<TestVertex>::new();
+
+        // No barrier can be added inside of the render pass. The write before the render pass
+        // and the read by the draw are synchronized when the render pass begins, and the read
+        // and the write after the render pass when it ends.
+        let _cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .fill_buffer(vb.clone(), 0).unwrap()
+            .begin_render_pass(framebuffer, false, ()).unwrap()
+            .add(CmdBindVertexBuffers::new(&def, vb.clone())).unwrap()
+            .add(unsafe { CmdDrawRaw::new(3, 1, 0, 0) }).unwrap()
+            .end_render_pass().unwrap()
+            .fill_buffer(vb.clone(), 0).unwrap();
+
+        let calls = mock::calls(&device);
+        let calls = calls.iter().filter(|c| c.starts_with("Cmd")).cloned().collect::<Vec<_>>();
+        assert_eq!(calls, vec!["CmdFillBuffer", "CmdPipelineBarrier", "CmdBeginRenderPass",
+                               "CmdBindVertexBuffers", "CmdDraw", "CmdEndRenderPass",
+                               "CmdPipelineBarrier", "CmdFillBuffer"]);
+    }
+
+    #[test]
+    fn dispatch() {
+        let (device, queue) = mock_dev_and_queue!(queue: |q| q.supports_compute());
+
+        let buf = CpuAccessibleBuffer::from_data(&device, &BufferUsage::all(),
+                                                 Some(queue.family()), 0u32).unwrap();
+        let layout = Arc::new(PipelineLayout::new(&device, StorageBufferDesc).unwrap());
+        let set = {
+            let builder = SimpleDescriptorSetBuilder::new(layout.clone(), 0);
+            Arc::new(buf.clone().add_me(builder, "buf").build())
+        };
+
+        // The shader may read and write the buffer, so it must wait for the fill and the next
+        // fill must wait for it.
+        let _cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .fill_buffer(buf.clone(), 0).unwrap()
+            .add(CmdBindDescriptorSets::new(false, layout.clone(), set).unwrap()).unwrap()
+            .add(unsafe { CmdDispatchRaw::new(device.clone(), [1, 1, 1]).unwrap() }).unwrap()
+            .fill_buffer(buf.clone(), 0).unwrap();
+
+        let calls = mock::calls(&device);
+        let calls = calls.iter().filter(|c| c.starts_with("Cmd")).cloned().collect::<Vec<_>>();
+        assert_eq!(calls, vec!["CmdFillBuffer", "CmdPipelineBarrier", "CmdBindDescriptorSets",
+                               "CmdDispatch", "CmdPipelineBarrier", "CmdFillBuffer"]);
+    }
+
+    #[test]
+    fn copy_between_layers_of_same_image() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let dims = Dimensions::Dim2dArray { width: 32, height: 32, array_layers: 2 };
+        let img = StorageImage::new(&device, dims, Format::R8G8B8A8Unorm,
+                                    Some(queue.family())).unwrap();
+
+        // The two layers are in different layouts during each copy, which is only possible if
+        // they are tracked separately.
+        let _cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .copy_image(img.clone(), [0, 0, 0], 0, 0, img.clone(), [0, 0, 0], 1, 0, [32, 32, 1], 1)
+            .unwrap()
+            .copy_image(img.clone(), [0, 0, 0], 1, 0, img.clone(), [0, 0, 0], 0, 0, [32, 32, 1], 1)
+            .unwrap();
+
+        let calls = mock::calls(&device);
+        let calls = calls.iter().filter(|c| c.starts_with("Cmd")).cloned().collect::<Vec<_>>();
+        assert_eq!(calls, vec!["CmdPipelineBarrier", "CmdCopyImage", "CmdPipelineBarrier",
+                               "CmdCopyImage"]);
+    }
+
+    #[test]
+    fn untouched_levels_of_initialization() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let (_, init) = ImmutableImage::uninitialized(&device,
+                                                      Dimensions::Dim2d { width: 64, height: 64 },
+                                                      Format::R8G8B8A8Unorm, 3,
+                                                      Some(queue.family())).unwrap();
+        let keep_alive: Arc<ImageAccess + Send + Sync> = init.clone();
+
+        let mut layer = AutoPipelineBarriersLayer::new(());
+        let mut barrier = CmdPipelineBarrier::new();
+        layer.access_image(&mut barrier, &init, Some(keep_alive),
+                           ImageSubresourceRange::new(0 .. 1, 0 .. 1), transfer(),
+                           transfer_write(), true, Layout::TransferDstOptimal);
+
+        // The levels that are never written must be transitioned at the end as well.
+        assert_eq!(layer.images.len(), 2);
+        let untouched = &layer.images[1];
+        assert_eq!(untouched.range, ImageSubresourceRange::new(1 .. 3, 0 .. 1));
+        assert_eq!(untouched.layout, Layout::Undefined);
+        assert!(needs_transition_back(untouched));
+    }
+
+    #[test]
+    fn descriptor_set_image_view_range() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let (image, init) = ImmutableImage::uninitialized(&device,
+                                                          Dimensions::Dim2d { width: 64,
+                                                                              height: 64 },
+                                                          Format::R8G8B8A8Unorm, 2,
+                                                          Some(queue.family())).unwrap();
+        let keep_alive: Arc<ImageAccess + Send + Sync> = init.clone();
+
+        let layout = Arc::new(PipelineLayout::new(&device, SampledImageDesc).unwrap());
+        let view = ImageView::new(image.clone(), ViewType::Dim2d, 0 .. 1, 0 .. 1, None,
+                                  Swizzle::default()).unwrap();
+        let set = {
+            let builder = SimpleDescriptorSetBuilder::new(layout.clone(), 0);
+            Arc::new(view.add_me(builder, "img").build())
+        };
+
+        let mut layer = AutoPipelineBarriersLayer::new(Discard);
+        {
+            let mut barrier = CmdPipelineBarrier::new();
+            layer.access_image(&mut barrier, &init, Some(keep_alive),
+                               ImageSubresourceRange::new(1 .. 2, 0 .. 1), transfer(),
+                               transfer_write(), true, Layout::TransferDstOptimal);
+        }
+
+        // Sampling the first level through the view must leave the second level, which is
+        // being written, in its layout.
+        let cmd = CmdBindDescriptorSets::new(true, layout.clone(), set).unwrap();
+        let layer = AddCommand::add(layer, cmd).unwrap();
+
+        assert_eq!(layer.images.len(), 2);
+        for state in layer.images.iter() {
+            if state.range == ImageSubresourceRange::new(0 .. 1, 0 .. 1) {
+                assert_eq!(state.layout, Layout::ShaderReadOnlyOptimal);
+            } else {
+                assert_eq!(state.range, ImageSubresourceRange::new(1 .. 2, 0 .. 1));
+                assert_eq!(state.layout, Layout::TransferDstOptimal);
+            }
+        }
+    }
+
+    #[test]
+    fn subtract_ranges() {
+        let whole = ImageSubresourceRange::new(0 .. 4, 0 .. 6);
+
+        let face = ImageSubresourceRange::new(0 .. 4, 2 .. 3);
+        assert_eq!(subtract_range(&whole, &face),
+                   vec![ImageSubresourceRange::new(0 .. 4, 0 .. 2),
+                        ImageSubresourceRange::new(0 .. 4, 3 .. 6)]);
+
+        let level = ImageSubresourceRange::new(1 .. 2, 0 .. 6);
+        assert_eq!(subtract_range(&whole, &level),
+                   vec![ImageSubresourceRange::new(0 .. 1, 0 .. 6),
+                        ImageSubresourceRange::new(2 .. 4, 0 .. 6)]);
+
+        let outside = ImageSubresourceRange::new(4 .. 5, 0 .. 1);
+        assert_eq!(subtract_range(&whole, &outside), vec![whole]);
+        assert!(subtract_range(&face, &whole).is_empty());
+    }
+}
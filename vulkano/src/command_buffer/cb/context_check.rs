@@ -7,13 +7,19 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::ops::Range;
 use std::sync::Arc;
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::CommandBufferBuild;
 use command_buffer::CommandBufferBuilder;
 use command_buffer::commands_raw;
+use command_buffer::commands_raw::ClearAttachment;
+use command_buffer::commands_raw::ClearRect;
 use device::Device;
 use device::DeviceOwned;
+use framebuffer::RenderPassCompatibility;
+use instance::QueueFamily;
 
 /// Layer around a command buffer builder that checks whether the commands can be executed in the
 /// given context related to render passes.
@@ -26,6 +32,12 @@ use device::DeviceOwned;
 ///   subpasses of the current render pass is respected.
 /// - When binding a graphics pipeline or drawing, makes sure that the pipeline is valid for the
 ///   current render pass.
+/// - When executing secondary command buffers, makes sure that the builder is a primary command
+///   buffer and that the secondary command buffers were created for the current subpass.
+/// - When clearing attachments, makes sure that the attachments exist in the current subpass and
+///   that the rectangles are within the render area and the layers of the framebuffer.
+/// - When adding a command inline, makes sure that the content of the current subpass isn't
+///   provided by secondary command buffers.
 ///
 pub struct ContextCheckLayer<I> {
     // Inner command buffer builder.
@@ -34,6 +46,14 @@ pub struct ContextCheckLayer<I> {
     inside_render_pass: bool,
     // True if entering/leaving a render pass or going to the next subpass is allowed.
     allow_render_pass_ops: bool,
+    // If we entered a render pass, contains the summary of the render pass and the index of the
+    // current subpass.
+    subpass: Option<(Arc<RenderPassCompatibility>, u32)>,
+    // If we entered a render pass, contains the render area and the number of layers of the
+    // framebuffer.
+    render_area: Option<([Range<u32>; 2], u32)>,
+    // True if the content of the current subpass is provided by secondary command buffers.
+    secondary_contents: bool,
 }
 
 impl<I> ContextCheckLayer<I> {
@@ -55,6 +75,9 @@ impl<I> ContextCheckLayer<I> {
             inner: inner,
             inside_render_pass: inside_render_pass,
             allow_render_pass_ops: allow_render_pass_ops,
+            subpass: None,
+            render_area: None,
+            secondary_contents: false,
         }
     }
 
@@ -98,10 +121,12 @@ unsafe impl<I> CommandBufferBuilder for ContextCheckLayer<I>
     fn supports_compute(&self) -> bool {
         self.inner.supports_compute()
     }
-}
 
-// TODO:
-// impl!((C), commands_raw::CmdExecuteCommands<C>);
+    #[inline]
+    fn queue_family(&self) -> QueueFamily {
+        self.inner.queue_family()
+    }
+}
 
 // FIXME: must also check that a pipeline's render pass matches the render pass
 
@@ -111,7 +136,9 @@ unsafe impl<I> CommandBufferBuilder for ContextCheckLayer<I>
 // > a graphics pipeline after transitioning to the current subpass, then the sample count
 // > specified by this pipeline must match that set in the previous pipeline
 
-macro_rules! impl_always {
+// Commands that can be used both inside and outside of a render pass, but not in a subpass whose
+// content is provided by secondary command buffers.
+macro_rules! impl_inline_contents {
     (($($param:ident),*), $cmd:ty) => {
         unsafe impl<'a, I, O $(, $param)*> AddCommand<$cmd> for ContextCheckLayer<I>
             where I: AddCommand<$cmd, Out = O>
@@ -119,23 +146,33 @@ macro_rules! impl_always {
             type Out = ContextCheckLayer<O>;
 
             #[inline]
-            fn add(self, command: $cmd) -> Self::Out {
-                ContextCheckLayer {
-                    inner: self.inner.add(command),
+            fn add(self, command: $cmd) -> Result<Self::Out, CommandAddError> {
+                if self.secondary_contents {
+                    return Err(CommandAddError::SubpassContentsSecondary);
+                }
+
+                Ok(ContextCheckLayer {
+                    inner: try!(self.inner.add(command)),
                     inside_render_pass: self.inside_render_pass,
                     allow_render_pass_ops: self.allow_render_pass_ops,
-                }
+                    subpass: self.subpass,
+                    render_area: self.render_area,
+                    secondary_contents: self.secondary_contents,
+                })
             }
         }
     }
 }
 
-impl_always!((S, Pl), commands_raw::CmdBindDescriptorSets<S, Pl>);
-impl_always!((B), commands_raw::CmdBindIndexBuffer<B>);
-impl_always!((Pl), commands_raw::CmdBindPipeline<Pl>);
-impl_always!((V), commands_raw::CmdBindVertexBuffers<V>);
-impl_always!((Pc, Pl), commands_raw::CmdPushConstants<Pc, Pl>);
-impl_always!((), commands_raw::CmdSetState);
+impl_inline_contents!((Q), commands_raw::CmdBeginQuery<Q>);
+impl_inline_contents!((S, Pl), commands_raw::CmdBindDescriptorSets<S, Pl>);
+impl_inline_contents!((B), commands_raw::CmdBindIndexBuffer<B>);
+impl_inline_contents!((Pl), commands_raw::CmdBindPipeline<Pl>);
+impl_inline_contents!((V), commands_raw::CmdBindVertexBuffers<V>);
+impl_inline_contents!((Q), commands_raw::CmdEndQuery<Q>);
+impl_inline_contents!((Pc, Pl), commands_raw::CmdPushConstants<Pc, Pl>);
+impl_inline_contents!((), commands_raw::CmdSetState);
+impl_inline_contents!((Q), commands_raw::CmdWriteTimestamp<Q>);
 
 macro_rules! impl_inside_only {
     (($($param:ident),*), $cmd:ty) => {
@@ -145,20 +182,28 @@ macro_rules! impl_inside_only {
             type Out = ContextCheckLayer<O>;
 
             #[inline]
-            fn add(self, command: $cmd) -> Self::Out {
-                assert!(self.inside_render_pass);       // TODO: proper error
+            fn add(self, command: $cmd) -> Result<Self::Out, CommandAddError> {
+                if !self.inside_render_pass {
+                    return Err(CommandAddError::ForbiddenOutsideRenderPass);
+                }
+
+                if self.secondary_contents {
+                    return Err(CommandAddError::SubpassContentsSecondary);
+                }
 
-                ContextCheckLayer {
-                    inner: self.inner.add(command),
+                Ok(ContextCheckLayer {
+                    inner: try!(self.inner.add(command)),
                     inside_render_pass: self.inside_render_pass,
                     allow_render_pass_ops: self.allow_render_pass_ops,
-                }
+                    subpass: self.subpass,
+                    render_area: self.render_area,
+                    secondary_contents: self.secondary_contents,
+                })
             }
         }
     }
 }
 
-impl_inside_only!((), commands_raw::CmdClearAttachments);
 impl_inside_only!((), commands_raw::CmdDrawIndexedRaw);
 impl_inside_only!((B), commands_raw::CmdDrawIndirectRaw<B>);
 impl_inside_only!((), commands_raw::CmdDrawRaw);
@@ -171,14 +216,19 @@ macro_rules! impl_outside_only {
             type Out = ContextCheckLayer<O>;
 
             #[inline]
-            fn add(self, command: $cmd) -> Self::Out {
-                assert!(!self.inside_render_pass);       // TODO: proper error
+            fn add(self, command: $cmd) -> Result<Self::Out, CommandAddError> {
+                if self.inside_render_pass {
+                    return Err(CommandAddError::ForbiddenInsideRenderPass);
+                }
 
-                ContextCheckLayer {
-                    inner: self.inner.add(command),
+                Ok(ContextCheckLayer {
+                    inner: try!(self.inner.add(command)),
                     inside_render_pass: self.inside_render_pass,
                     allow_render_pass_ops: self.allow_render_pass_ops,
-                }
+                    subpass: self.subpass,
+                    render_area: self.render_area,
+                    secondary_contents: self.secondary_contents,
+                })
             }
         }
     }
@@ -188,27 +238,117 @@ impl_outside_only!((S, D), commands_raw::CmdBlitImage<S, D>);
 impl_outside_only!((S, D), commands_raw::CmdCopyBuffer<S, D>);
 impl_outside_only!((S, D), commands_raw::CmdCopyBufferToImage<S, D>);
 impl_outside_only!((S, D), commands_raw::CmdCopyImage<S, D>);
+impl_outside_only!((S, D), commands_raw::CmdCopyImageToBuffer<S, D>);
+impl_outside_only!((Q, B), commands_raw::CmdCopyQueryPoolResults<Q, B>);
 impl_outside_only!((), commands_raw::CmdDispatchRaw);
 impl_outside_only!((B), commands_raw::CmdFillBuffer<B>);
+impl_outside_only!((Img), commands_raw::CmdGenerateMipmaps<Img>);
+impl_outside_only!((Q), commands_raw::CmdResetQueryPool<Q>);
 impl_outside_only!((S, D), commands_raw::CmdResolveImage<S, D>);
 impl_outside_only!((), commands_raw::CmdSetEvent);
 impl_outside_only!((B, D), commands_raw::CmdUpdateBuffer<B, D>);
 
+unsafe impl<'a, I, O> AddCommand<commands_raw::CmdClearAttachments> for ContextCheckLayer<I>
+    where I: AddCommand<commands_raw::CmdClearAttachments, Out = O>
+{
+    type Out = ContextCheckLayer<O>;
+
+    #[inline]
+    fn add(self, command: commands_raw::CmdClearAttachments)
+           -> Result<Self::Out, CommandAddError>
+    {
+        if !self.inside_render_pass {
+            return Err(CommandAddError::ForbiddenOutsideRenderPass);
+        }
+
+        if self.secondary_contents {
+            return Err(CommandAddError::SubpassContentsSecondary);
+        }
+
+        // Inside of a secondary command buffer the render pass and the framebuffer are unknown,
+        // in which case nothing more can be checked.
+        if let Some((ref area, layers)) = self.render_area {
+            if !command.rects().iter().all(|rect| rect_in_range(rect, area, layers)) {
+                return Err(CommandAddError::ClearRectOutOfRange);
+            }
+        }
+
+        if let Some((ref rp, index)) = self.subpass {
+            let aspects = rp.depth_stencil_format(index).map(|f| f.aspects());
+
+            for attachment in command.attachments() {
+                let exists = match *attachment {
+                    ClearAttachment::Color(num, _) => {
+                        num < rp.num_color_attachments(index).unwrap_or(0)
+                    },
+                    ClearAttachment::Depth(_) => aspects.map(|a| a.depth).unwrap_or(false),
+                    ClearAttachment::Stencil(_) => aspects.map(|a| a.stencil).unwrap_or(false),
+                    ClearAttachment::DepthStencil(_) => {
+                        aspects.map(|a| a.depth && a.stencil).unwrap_or(false)
+                    },
+                };
+
+                if !exists {
+                    return Err(CommandAddError::ClearAttachmentNotInSubpass);
+                }
+            }
+        }
+
+        Ok(ContextCheckLayer {
+            inner: try!(self.inner.add(command)),
+            inside_render_pass: self.inside_render_pass,
+            allow_render_pass_ops: self.allow_render_pass_ops,
+            subpass: self.subpass,
+            render_area: self.render_area,
+            secondary_contents: self.secondary_contents,
+        })
+    }
+}
+
+// Returns true if `rect` is within the render area and the layers of the framebuffer.
+fn rect_in_range(rect: &ClearRect, area: &[Range<u32>; 2], layers: u32) -> bool {
+    for dim in 0 .. 2 {
+        if rect.offset[dim] < 0 || (rect.offset[dim] as u32) < area[dim].start {
+            return false;
+        }
+
+        if rect.offset[dim] as u64 + rect.extent[dim] as u64 > area[dim].end as u64 {
+            return false;
+        }
+    }
+
+    rect.base_array_layer as u64 + rect.layer_count as u64 <= layers as u64
+}
+
 unsafe impl<'a, I, O, Rp, F> AddCommand<commands_raw::CmdBeginRenderPass<Rp, F>> for ContextCheckLayer<I>
     where I: AddCommand<commands_raw::CmdBeginRenderPass<Rp, F>, Out = O>
 {
     type Out = ContextCheckLayer<O>;
 
     #[inline]
-    fn add(self, command: commands_raw::CmdBeginRenderPass<Rp, F>) -> Self::Out {
-        assert!(!self.inside_render_pass);       // TODO: proper error
-        assert!(self.allow_render_pass_ops);     // TODO: proper error
+    fn add(self, command: commands_raw::CmdBeginRenderPass<Rp, F>)
+           -> Result<Self::Out, CommandAddError>
+    {
+        if !self.allow_render_pass_ops {
+            return Err(CommandAddError::ForbiddenInSecondaryCommandBuffer);
+        }
 
-        ContextCheckLayer {
-            inner: self.inner.add(command),
+        if self.inside_render_pass {
+            return Err(CommandAddError::ForbiddenInsideRenderPass);
+        }
+
+        let subpass = Some((command.render_pass_compatibility().clone(), 0));
+        let render_area = Some((command.render_area().clone(), command.num_layers()));
+        let secondary_contents = command.secondary();
+
+        Ok(ContextCheckLayer {
+            inner: try!(self.inner.add(command)),
             inside_render_pass: true,
             allow_render_pass_ops: true,
-        }
+            subpass: subpass,
+            render_area: render_area,
+            secondary_contents: secondary_contents,
+        })
     }
 }
 
@@ -218,16 +358,27 @@ unsafe impl<'a, I, O> AddCommand<commands_raw::CmdNextSubpass> for ContextCheckL
     type Out = ContextCheckLayer<O>;
 
     #[inline]
-    fn add(self, command: commands_raw::CmdNextSubpass) -> Self::Out {
-        assert!(self.inside_render_pass);       // TODO: proper error
-        assert!(self.allow_render_pass_ops);    // TODO: proper error
+    fn add(self, command: commands_raw::CmdNextSubpass) -> Result<Self::Out, CommandAddError> {
+        if !self.allow_render_pass_ops {
+            return Err(CommandAddError::ForbiddenInSecondaryCommandBuffer);
+        }
+
+        if !self.inside_render_pass {
+            return Err(CommandAddError::ForbiddenOutsideRenderPass);
+        }
+
         // FIXME: check number of subpasses
 
-        ContextCheckLayer {
-            inner: self.inner.add(command),
+        let secondary_contents = command.secondary();
+
+        Ok(ContextCheckLayer {
+            inner: try!(self.inner.add(command)),
             inside_render_pass: true,
             allow_render_pass_ops: true,
-        }
+            subpass: self.subpass.map(|(rp, index)| (rp, index + 1)),
+            render_area: self.render_area,
+            secondary_contents: secondary_contents,
+        })
     }
 }
 
@@ -237,15 +388,155 @@ unsafe impl<'a, I, O> AddCommand<commands_raw::CmdEndRenderPass> for ContextChec
     type Out = ContextCheckLayer<O>;
 
     #[inline]
-    fn add(self, command: commands_raw::CmdEndRenderPass) -> Self::Out {
-        assert!(self.inside_render_pass);       // TODO: proper error
-        assert!(self.allow_render_pass_ops);    // TODO: proper error
+    fn add(self, command: commands_raw::CmdEndRenderPass) -> Result<Self::Out, CommandAddError> {
+        if !self.allow_render_pass_ops {
+            return Err(CommandAddError::ForbiddenInSecondaryCommandBuffer);
+        }
+
+        if !self.inside_render_pass {
+            return Err(CommandAddError::ForbiddenOutsideRenderPass);
+        }
+
         // FIXME: check number of subpasses
 
-        ContextCheckLayer {
-            inner: self.inner.add(command),
+        Ok(ContextCheckLayer {
+            inner: try!(self.inner.add(command)),
             inside_render_pass: false,
             allow_render_pass_ops: true,
+            subpass: None,
+            render_area: None,
+            secondary_contents: false,
+        })
+    }
+}
+
+unsafe impl<'a, I, O, Cb> AddCommand<commands_raw::CmdExecuteCommands<Cb>> for ContextCheckLayer<I>
+    where I: AddCommand<commands_raw::CmdExecuteCommands<Cb>, Out = O>
+{
+    type Out = ContextCheckLayer<O>;
+
+    #[inline]
+    fn add(self, command: commands_raw::CmdExecuteCommands<Cb>)
+           -> Result<Self::Out, CommandAddError>
+    {
+        // Secondary command buffers can't execute other secondary command buffers.
+        if !self.allow_render_pass_ops {
+            return Err(CommandAddError::ForbiddenInSecondaryCommandBuffer);
+        }
+
+        if self.inside_render_pass {
+            if !self.secondary_contents {
+                return Err(CommandAddError::SubpassContentsInline);
+            }
+
+            let compatible = match (command.inheritance_subpass(), self.subpass.as_ref()) {
+                (Some(&(ref cb_rp, cb_index)), Some(&(ref rp, index))) => {
+                    cb_index == index && cb_rp.is_compatible_with(rp)
+                },
+                _ => false,
+            };
+
+            if !compatible {
+                return Err(CommandAddError::IncompatibleSubpass);
+            }
+        } else if command.inside_render_pass() {
+            return Err(CommandAddError::IncompatibleSubpass);
+        }
+
+        Ok(ContextCheckLayer {
+            inner: try!(self.inner.add(command)),
+            inside_render_pass: self.inside_render_pass,
+            allow_render_pass_ops: self.allow_render_pass_ops,
+            subpass: self.subpass,
+            render_area: self.render_area,
+            secondary_contents: self.secondary_contents,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandAddError;
+    use command_buffer::CommandBufferBuilder;
+    use command_buffer::CommandBufferBuilderError;
+    use command_buffer::DynamicState;
+    use command_buffer::cb::AddCommand;
+    use command_buffer::commands_raw::CmdSetState;
+    use framebuffer::EmptySinglePassRenderPassDesc;
+    use framebuffer::Framebuffer;
+    use framebuffer::RenderPass;
+    use query::TimestampQueryPool;
+    use sync::PipelineStages;
+
+    #[test]
+    fn end_render_pass_outside() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        match cb.end_render_pass() {
+            Err(CommandAddError::ForbiddenOutsideRenderPass) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn graphics_command_on_transfer_queue() {
+        let (device, queue) = mock_dev_and_queue!(queue: |q| {
+            !q.supports_graphics() && !q.supports_compute()
+        });
+
+        let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        match cb.end_render_pass() {
+            Err(CommandAddError::RequiresGraphicsQueue) => (),
+            _ => panic!()
         }
     }
+
+    #[test]
+    fn set_state_secondary_contents() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let rp = Arc::new(RenderPass::new(device.clone(), EmptySinglePassRenderPassDesc).unwrap());
+        let framebuffer = Framebuffer::new(rp, [32, 32, 1], ()).unwrap();
+
+        let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .begin_render_pass(framebuffer, true, ()).unwrap();
+        match cb.add(CmdSetState::new(device.clone(), DynamicState::none())) {
+            Err(CommandAddError::SubpassContentsSecondary) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn write_timestamp_secondary_contents() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let rp = Arc::new(RenderPass::new(device.clone(), EmptySinglePassRenderPassDesc).unwrap());
+        let framebuffer = Framebuffer::new(rp, [32, 32, 1], ()).unwrap();
+        let pool = TimestampQueryPool::new(&device, 1).unwrap();
+        let top = PipelineStages { top_of_pipe: true, .. PipelineStages::none() };
+
+        let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .begin_render_pass(framebuffer, true, ()).unwrap();
+        match cb.write_timestamp(pool, 0, top) {
+            Err(CommandBufferBuilderError::CommandAddError(err)) => {
+                assert_eq!(err, CommandAddError::SubpassContentsSecondary);
+            },
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn set_state_inline_contents() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let rp = Arc::new(RenderPass::new(device.clone(), EmptySinglePassRenderPassDesc).unwrap());
+        let framebuffer = Framebuffer::new(rp, [32, 32, 1], ()).unwrap();
+
+        let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .begin_render_pass(framebuffer, false, ()).unwrap();
+        assert!(cb.add(CmdSetState::new(device.clone(), DynamicState::none())).is_ok());
+    }
 }
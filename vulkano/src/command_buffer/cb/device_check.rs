@@ -9,11 +9,13 @@
 
 use std::sync::Arc;
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::CommandBufferBuild;
 use command_buffer::CommandBufferBuilder;
 use command_buffer::commands_raw;
 use device::Device;
 use device::DeviceOwned;
+use instance::QueueFamily;
 use VulkanObject;
 
 /// Layer around a command buffer builder that checks whether the commands added to it belong to
@@ -59,6 +61,11 @@ unsafe impl<I> CommandBufferBuilder for DeviceCheckLayer<I>
     fn supports_compute(&self) -> bool {
         self.inner.supports_compute()
     }
+
+    #[inline]
+    fn queue_family(&self) -> QueueFamily {
+        self.inner.queue_family()
+    }
 }
 
 unsafe impl<I, O, E> CommandBufferBuild for DeviceCheckLayer<I>
@@ -81,14 +88,16 @@ macro_rules! pass_through {
             type Out = DeviceCheckLayer<O>;
 
             #[inline]
-            fn add(self, command: $cmd) -> Self::Out {
+            fn add(self, command: $cmd) -> Result<Self::Out, CommandAddError> {
                 let inner_device = self.inner.device().internal_object();
                 let cmd_device = command.device().internal_object();
-                assert_eq!(inner_device, cmd_device);
-
-                DeviceCheckLayer {
-                    inner: self.inner.add(command),
+                if inner_device != cmd_device {
+                    return Err(CommandAddError::WrongDevice);
                 }
+
+                Ok(DeviceCheckLayer {
+                    inner: try!(self.inner.add(command)),
+                })
             }
         }
     );
@@ -100,15 +109,16 @@ macro_rules! pass_through {
             type Out = DeviceCheckLayer<O>;
 
             #[inline]
-            fn add(self, command: $cmd) -> Self::Out {
-                DeviceCheckLayer {
-                    inner: self.inner.add(command),
-                }
+            fn add(self, command: $cmd) -> Result<Self::Out, CommandAddError> {
+                Ok(DeviceCheckLayer {
+                    inner: try!(self.inner.add(command)),
+                })
             }
         }
     );
 }
 
+pass_through!((Q), commands_raw::CmdBeginQuery<Q>);
 pass_through!((Rp, F), commands_raw::CmdBeginRenderPass<Rp, F>);
 pass_through!((S, Pl), commands_raw::CmdBindDescriptorSets<S, Pl>);
 pass_through!((B), commands_raw::CmdBindIndexBuffer<B>);
@@ -119,16 +129,22 @@ pass_through!((), commands_raw::CmdClearAttachments, no-device);
 pass_through!((S, D), commands_raw::CmdCopyBuffer<S, D>);
 pass_through!((S, D), commands_raw::CmdCopyBufferToImage<S, D>);
 pass_through!((S, D), commands_raw::CmdCopyImage<S, D>);
+pass_through!((S, D), commands_raw::CmdCopyImageToBuffer<S, D>);
+pass_through!((Q, B), commands_raw::CmdCopyQueryPoolResults<Q, B>);
 pass_through!((), commands_raw::CmdDispatchRaw);
 pass_through!((), commands_raw::CmdDrawIndexedRaw, no-device);
 pass_through!((B), commands_raw::CmdDrawIndirectRaw<B>);
 pass_through!((), commands_raw::CmdDrawRaw, no-device);
+pass_through!((Q), commands_raw::CmdEndQuery<Q>);
 pass_through!((), commands_raw::CmdEndRenderPass, no-device);
 pass_through!((C), commands_raw::CmdExecuteCommands<C>);
 pass_through!((B), commands_raw::CmdFillBuffer<B>);
+pass_through!((Img), commands_raw::CmdGenerateMipmaps<Img>);
 pass_through!((), commands_raw::CmdNextSubpass, no-device);
 pass_through!((Pc, Pl), commands_raw::CmdPushConstants<Pc, Pl>);
+pass_through!((Q), commands_raw::CmdResetQueryPool<Q>);
 pass_through!((S, D), commands_raw::CmdResolveImage<S, D>);
 pass_through!((), commands_raw::CmdSetEvent);
 pass_through!((), commands_raw::CmdSetState);
 pass_through!((B, D), commands_raw::CmdUpdateBuffer<B, D>);
+pass_through!((Q), commands_raw::CmdWriteTimestamp<Q>);
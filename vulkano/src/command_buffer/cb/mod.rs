@@ -80,10 +80,12 @@ pub use self::state_cache::StateCacheLayer;
 pub use self::submit_sync::SubmitSyncBuilderLayer;
 pub use self::submit_sync::SubmitSyncLayer;
 pub use self::sys::Kind;
+pub use self::sys::KindOcclusionQuery;
 pub use self::sys::Flags;
 pub use self::sys::UnsafeCommandBufferBuilder;
 pub use self::sys::UnsafeCommandBuffer;
 pub use self::traits::AddCommand;
+pub use self::traits::CommandAddError;
 
 // TODO: remove this line
 pub use command_buffer::traits::CommandBufferBuild;
@@ -9,11 +9,13 @@
 
 use std::sync::Arc;
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::CommandBufferBuild;
 use command_buffer::CommandBufferBuilder;
 use command_buffer::commands_raw;
 use device::Device;
 use device::DeviceOwned;
+use instance::QueueFamily;
 
 /// Layer around a command buffer builder that checks whether the commands added to it match the
 /// type of the queue family of the underlying builder.
@@ -24,20 +26,23 @@ pub struct QueueTyCheckLayer<I> {
     inner: I,
     supports_graphics: bool,
     supports_compute: bool,
+    // Identifier of the queue family of the underlying builder.
+    queue_family: u32,
 }
 
 impl<I> QueueTyCheckLayer<I> {
     /// Builds a new `QueueTyCheckLayer`.
     ///
-    /// Note that this layer will only protect you if you pass correct values for
-    /// `supports_graphics` and `supports_compute`. It is not unsafe to pass wrong values, but if
-    /// you do so then the layer will be inefficient as a safety tool.
+    /// Note that this layer will only protect you if you pass the queue family of the pool of the
+    /// underlying builder. It is not unsafe to pass a wrong queue family, but if you do so then
+    /// the layer will be inefficient as a safety tool.
     #[inline]
-    pub fn new(inner: I, supports_graphics: bool, supports_compute: bool) -> QueueTyCheckLayer<I> {
+    pub fn new(inner: I, queue_family: QueueFamily) -> QueueTyCheckLayer<I> {
         QueueTyCheckLayer {
             inner: inner,
-            supports_graphics: supports_graphics,
-            supports_compute: supports_compute,
+            supports_graphics: queue_family.supports_graphics(),
+            supports_compute: queue_family.supports_compute(),
+            queue_family: queue_family.id(),
         }
     }
 
@@ -49,7 +54,8 @@ impl<I> QueueTyCheckLayer<I> {
 
     /// Returns true if graphical operations can be added to this layer.
     ///
-    /// This returns the same value as what was passed to the constructor.
+    /// This is true if the queue family that was passed to the constructor supports graphics
+    /// operations.
     #[inline]
     pub fn supports_graphics(&self) -> bool {
         self.supports_graphics
@@ -57,7 +63,8 @@ impl<I> QueueTyCheckLayer<I> {
 
     /// Returns true if compute operations can be added to this layer.
     ///
-    /// This returns the same value as what was passed to the constructor.
+    /// This is true if the queue family that was passed to the constructor supports compute
+    /// operations.
     #[inline]
     pub fn supports_compute(&self) -> bool {
         self.supports_compute
@@ -83,6 +90,11 @@ unsafe impl<I> CommandBufferBuilder for QueueTyCheckLayer<I> where I: DeviceOwne
     fn supports_compute(&self) -> bool {
         self.supports_compute
     }
+
+    #[inline]
+    fn queue_family(&self) -> QueueFamily {
+        self.inner.device().physical_device().queue_family_by_id(self.queue_family).unwrap()
+    }
 }
 
 unsafe impl<I, O, E> CommandBufferBuild for QueueTyCheckLayer<I>
@@ -99,9 +111,6 @@ unsafe impl<I, O, E> CommandBufferBuild for QueueTyCheckLayer<I>
 
 // TODO: actually implement
 
-// TODO: implement CmdExecuteCommands
-//q_ty_impl!((C), commands_raw::CmdExecuteCommands<C>);
-
 macro_rules! q_ty_impl_always {
     (($($param:ident),*), $cmd:ty) => {
         unsafe impl<'a, I, O $(, $param)*> AddCommand<$cmd> for QueueTyCheckLayer<I>
@@ -110,12 +119,13 @@ macro_rules! q_ty_impl_always {
             type Out = QueueTyCheckLayer<O>;
 
             #[inline]
-            fn add(self, command: $cmd) -> Self::Out {
-                QueueTyCheckLayer {
-                    inner: self.inner.add(command),
+            fn add(self, command: $cmd) -> Result<Self::Out, CommandAddError> {
+                Ok(QueueTyCheckLayer {
+                    inner: try!(self.inner.add(command)),
                     supports_graphics: self.supports_graphics,
                     supports_compute: self.supports_compute,
-                }
+                    queue_family: self.queue_family,
+                })
             }
         }
     }
@@ -124,8 +134,11 @@ macro_rules! q_ty_impl_always {
 q_ty_impl_always!((S, D), commands_raw::CmdCopyBuffer<S, D>);
 q_ty_impl_always!((S, D), commands_raw::CmdCopyBufferToImage<S, D>);
 q_ty_impl_always!((S, D), commands_raw::CmdCopyImage<S, D>);
+q_ty_impl_always!((S, D), commands_raw::CmdCopyImageToBuffer<S, D>);
+q_ty_impl_always!((C), commands_raw::CmdExecuteCommands<C>);
 q_ty_impl_always!((B), commands_raw::CmdFillBuffer<B>);
 q_ty_impl_always!((B, D), commands_raw::CmdUpdateBuffer<B, D>);
+q_ty_impl_always!((Q), commands_raw::CmdWriteTimestamp<Q>);
 
 macro_rules! q_ty_impl_graphics {
     (($($param:ident),*), $cmd:ty) => {
@@ -135,13 +148,17 @@ macro_rules! q_ty_impl_graphics {
             type Out = QueueTyCheckLayer<O>;
 
             #[inline]
-            fn add(self, command: $cmd) -> Self::Out {
-                assert!(self.supports_graphics());      // TODO: proper error
-                QueueTyCheckLayer {
-                    inner: self.inner.add(command),
+            fn add(self, command: $cmd) -> Result<Self::Out, CommandAddError> {
+                if !self.supports_graphics() {
+                    return Err(CommandAddError::RequiresGraphicsQueue);
+                }
+
+                Ok(QueueTyCheckLayer {
+                    inner: try!(self.inner.add(command)),
                     supports_graphics: self.supports_graphics,
                     supports_compute: self.supports_compute,
-                }
+                    queue_family: self.queue_family,
+                })
             }
         }
     }
@@ -167,13 +184,17 @@ macro_rules! q_ty_impl_compute {
             type Out = QueueTyCheckLayer<O>;
 
             #[inline]
-            fn add(self, command: $cmd) -> Self::Out {
-                assert!(self.supports_compute());      // TODO: proper error
-                QueueTyCheckLayer {
-                    inner: self.inner.add(command),
+            fn add(self, command: $cmd) -> Result<Self::Out, CommandAddError> {
+                if !self.supports_compute() {
+                    return Err(CommandAddError::RequiresComputeQueue);
+                }
+
+                Ok(QueueTyCheckLayer {
+                    inner: try!(self.inner.add(command)),
                     supports_graphics: self.supports_graphics,
                     supports_compute: self.supports_compute,
-                }
+                    queue_family: self.queue_family,
+                })
             }
         }
     }
@@ -189,19 +210,27 @@ macro_rules! q_ty_impl_graphics_or_compute {
             type Out = QueueTyCheckLayer<O>;
 
             #[inline]
-            fn add(self, command: $cmd) -> Self::Out {
-                assert!(self.supports_graphics() || self.supports_compute());      // TODO: proper error
-                QueueTyCheckLayer {
-                    inner: self.inner.add(command),
+            fn add(self, command: $cmd) -> Result<Self::Out, CommandAddError> {
+                if !self.supports_graphics() && !self.supports_compute() {
+                    return Err(CommandAddError::RequiresGraphicsOrComputeQueue);
+                }
+
+                Ok(QueueTyCheckLayer {
+                    inner: try!(self.inner.add(command)),
                     supports_graphics: self.supports_graphics,
                     supports_compute: self.supports_compute,
-                }
+                    queue_family: self.queue_family,
+                })
             }
         }
     }
 }
 
+q_ty_impl_graphics_or_compute!((Q), commands_raw::CmdBeginQuery<Q>);
+q_ty_impl_graphics_or_compute!((Q, B), commands_raw::CmdCopyQueryPoolResults<Q, B>);
+q_ty_impl_graphics_or_compute!((Q), commands_raw::CmdEndQuery<Q>);
 q_ty_impl_graphics_or_compute!((Pc, Pl), commands_raw::CmdPushConstants<Pc, Pl>);
+q_ty_impl_graphics_or_compute!((Q), commands_raw::CmdResetQueryPool<Q>);
 q_ty_impl_graphics_or_compute!((), commands_raw::CmdSetEvent);
 q_ty_impl_graphics_or_compute!((), commands_raw::CmdSetState);
 
@@ -211,18 +240,21 @@ unsafe impl<I, O, Pl> AddCommand<commands_raw::CmdBindPipeline<Pl>> for QueueTyC
     type Out = QueueTyCheckLayer<O>;
 
     #[inline]
-    fn add(self, command: commands_raw::CmdBindPipeline<Pl>) -> Self::Out {
-        if command.is_graphics() {
-            assert!(self.supports_graphics());      // TODO: proper error
-        } else {
-            assert!(self.supports_compute());       // TODO: proper error
+    fn add(self, command: commands_raw::CmdBindPipeline<Pl>) -> Result<Self::Out, CommandAddError> {
+        if command.is_graphics() && !self.supports_graphics() {
+            return Err(CommandAddError::RequiresGraphicsQueue);
         }
 
-        QueueTyCheckLayer {
-            inner: self.inner.add(command),
+        if !command.is_graphics() && !self.supports_compute() {
+            return Err(CommandAddError::RequiresComputeQueue);
+        }
+
+        Ok(QueueTyCheckLayer {
+            inner: try!(self.inner.add(command)),
             supports_graphics: self.supports_graphics,
             supports_compute: self.supports_compute,
-        }
+            queue_family: self.queue_family,
+        })
     }
 }
 
@@ -232,17 +264,44 @@ unsafe impl<I, O, S, Pl> AddCommand<commands_raw::CmdBindDescriptorSets<S, Pl>>
     type Out = QueueTyCheckLayer<O>;
 
     #[inline]
-    fn add(self, command: commands_raw::CmdBindDescriptorSets<S, Pl>) -> Self::Out {
-        if command.is_graphics() {
-            assert!(self.supports_graphics());      // TODO: proper error
-        } else {
-            assert!(self.supports_compute());       // TODO: proper error
+    fn add(self, command: commands_raw::CmdBindDescriptorSets<S, Pl>) -> Result<Self::Out, CommandAddError> {
+        if command.is_graphics() && !self.supports_graphics() {
+            return Err(CommandAddError::RequiresGraphicsQueue);
         }
 
-        QueueTyCheckLayer {
-            inner: self.inner.add(command),
+        if !command.is_graphics() && !self.supports_compute() {
+            return Err(CommandAddError::RequiresComputeQueue);
+        }
+
+        Ok(QueueTyCheckLayer {
+            inner: try!(self.inner.add(command)),
             supports_graphics: self.supports_graphics,
             supports_compute: self.supports_compute,
+            queue_family: self.queue_family,
+        })
+    }
+}
+
+unsafe impl<I, O, Img> AddCommand<commands_raw::CmdGenerateMipmaps<Img>> for QueueTyCheckLayer<I>
+    where I: AddCommand<commands_raw::CmdGenerateMipmaps<Img>, Out = O>
+{
+    type Out = QueueTyCheckLayer<O>;
+
+    #[inline]
+    fn add(self, command: commands_raw::CmdGenerateMipmaps<Img>) -> Result<Self::Out, CommandAddError> {
+        if !command.uses_compute() && !self.supports_graphics() {
+            return Err(CommandAddError::RequiresGraphicsQueue);
+        }
+
+        if command.uses_compute() && !self.supports_compute() {
+            return Err(CommandAddError::RequiresComputeQueue);
         }
+
+        Ok(QueueTyCheckLayer {
+            inner: try!(self.inner.add(command)),
+            supports_graphics: self.supports_graphics,
+            supports_compute: self.supports_compute,
+            queue_family: self.queue_family,
+        })
     }
 }
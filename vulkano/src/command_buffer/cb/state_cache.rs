@@ -9,12 +9,14 @@
 
 use std::sync::Arc;
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::CommandBufferBuild;
 use command_buffer::CommandBufferBuilder;
 use command_buffer::commands_raw;
 use command_buffer::DynamicState;
 use device::Device;
 use device::DeviceOwned;
+use instance::QueueFamily;
 use VulkanObject;
 use vk;
 
@@ -87,6 +89,11 @@ unsafe impl<I> CommandBufferBuilder for StateCacheLayer<I>
     fn supports_compute(&self) -> bool {
         self.inner.supports_compute()
     }
+
+    #[inline]
+    fn queue_family(&self) -> QueueFamily {
+        self.inner.queue_family()
+    }
 }
 
 unsafe impl<Pl, I, O> AddCommand<commands_raw::CmdBindPipeline<Pl>> for StateCacheLayer<I>
@@ -95,7 +102,7 @@ unsafe impl<Pl, I, O> AddCommand<commands_raw::CmdBindPipeline<Pl>> for StateCac
     type Out = StateCacheLayer<O>;
 
     #[inline]
-    fn add(mut self, command: commands_raw::CmdBindPipeline<Pl>) -> Self::Out {
+    fn add(mut self, command: commands_raw::CmdBindPipeline<Pl>) -> Result<Self::Out, CommandAddError> {
         let raw_pipeline = command.sys().internal_object();
 
         let new_command = {
@@ -116,12 +123,12 @@ unsafe impl<Pl, I, O> AddCommand<commands_raw::CmdBindPipeline<Pl>> for StateCac
             }
         };
 
-        StateCacheLayer {
-            inner: self.inner.add(new_command),
+        Ok(StateCacheLayer {
+            inner: try!(self.inner.add(new_command)),
             dynamic_state: DynamicState::none(),
             graphics_pipeline: self.graphics_pipeline,
             compute_pipeline: self.compute_pipeline,
-        }
+        })
     }
 }
 
@@ -131,16 +138,37 @@ unsafe impl<Cb, I, O> AddCommand<commands_raw::CmdExecuteCommands<Cb>> for State
     type Out = StateCacheLayer<O>;
 
     #[inline]
-    fn add(self, command: commands_raw::CmdExecuteCommands<Cb>) -> Self::Out {
+    fn add(self, command: commands_raw::CmdExecuteCommands<Cb>) -> Result<Self::Out, CommandAddError> {
         // After a secondary command buffer is added, all states at reset to the "unknown" state.
-        let new_inner = self.inner.add(command);
+        let new_inner = try!(self.inner.add(command));
 
-        StateCacheLayer {
+        Ok(StateCacheLayer {
             inner: new_inner,
             dynamic_state: DynamicState::none(),
             compute_pipeline: 0,
             graphics_pipeline: 0,
+        })
+    }
+}
+
+unsafe impl<I, O, Img> AddCommand<commands_raw::CmdGenerateMipmaps<Img>> for StateCacheLayer<I>
+    where I: AddCommand<commands_raw::CmdGenerateMipmaps<Img>, Out = O>
+{
+    type Out = StateCacheLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdGenerateMipmaps<Img>) -> Result<Self::Out, CommandAddError> {
+        // Generating the mipmaps with a compute shader binds a compute pipeline of its own.
+        if command.uses_compute() {
+            self.compute_pipeline = 0;
         }
+
+        Ok(StateCacheLayer {
+            inner: try!(self.inner.add(command)),
+            dynamic_state: self.dynamic_state,
+            compute_pipeline: self.compute_pipeline,
+            graphics_pipeline: self.graphics_pipeline,
+        })
     }
 }
 
@@ -150,7 +178,7 @@ unsafe impl<I, O> AddCommand<commands_raw::CmdSetState> for StateCacheLayer<I>
     type Out = StateCacheLayer<O>;
 
     #[inline]
-    fn add(mut self, command: commands_raw::CmdSetState) -> Self::Out {
+    fn add(mut self, command: commands_raw::CmdSetState) -> Result<Self::Out, CommandAddError> {
         // We need to synchronize `self.dynamic_state` with the state in `command`.
         // While doing so, we tweak `command` to erase the states that are the same as what's
         // already in `self.dynamic_state`.
@@ -168,12 +196,13 @@ unsafe impl<I, O> AddCommand<commands_raw::CmdSetState> for StateCacheLayer<I>
 
         // TODO: missing implementations
 
-        StateCacheLayer {
-            inner: self.inner.add(commands_raw::CmdSetState::new(command.device().clone(), command_state)),
+        Ok(StateCacheLayer {
+            inner: try!(self.inner.add(commands_raw::CmdSetState::new(command.device().clone(),
+                                                                 command_state))),
             dynamic_state: self.dynamic_state,
             graphics_pipeline: self.graphics_pipeline,
             compute_pipeline: self.compute_pipeline,
-        }
+        })
     }
 }
 
@@ -197,18 +226,19 @@ macro_rules! pass_through {
             type Out = StateCacheLayer<O>;
 
             #[inline]
-            fn add(self, command: $cmd) -> Self::Out {
-                StateCacheLayer {
-                    inner: self.inner.add(command),
+            fn add(self, command: $cmd) -> Result<Self::Out, CommandAddError> {
+                Ok(StateCacheLayer {
+                    inner: try!(self.inner.add(command)),
                     dynamic_state: self.dynamic_state,
                     graphics_pipeline: self.graphics_pipeline,
                     compute_pipeline: self.compute_pipeline,
-                }
+                })
             }
         }
     }
 }
 
+pass_through!((Q), commands_raw::CmdBeginQuery<Q>);
 pass_through!((Rp, F), commands_raw::CmdBeginRenderPass<Rp, F>);
 pass_through!((S, Pl), commands_raw::CmdBindDescriptorSets<S, Pl>);
 pass_through!((B), commands_raw::CmdBindIndexBuffer<B>);
@@ -218,14 +248,19 @@ pass_through!((), commands_raw::CmdClearAttachments);
 pass_through!((S, D), commands_raw::CmdCopyBuffer<S, D>);
 pass_through!((S, D), commands_raw::CmdCopyBufferToImage<S, D>);
 pass_through!((S, D), commands_raw::CmdCopyImage<S, D>);
+pass_through!((S, D), commands_raw::CmdCopyImageToBuffer<S, D>);
+pass_through!((Q, B), commands_raw::CmdCopyQueryPoolResults<Q, B>);
 pass_through!((), commands_raw::CmdDispatchRaw);
 pass_through!((), commands_raw::CmdDrawIndexedRaw);
 pass_through!((B), commands_raw::CmdDrawIndirectRaw<B>);
 pass_through!((), commands_raw::CmdDrawRaw);
+pass_through!((Q), commands_raw::CmdEndQuery<Q>);
 pass_through!((), commands_raw::CmdEndRenderPass);
 pass_through!((B), commands_raw::CmdFillBuffer<B>);
 pass_through!((), commands_raw::CmdNextSubpass);
 pass_through!((Pc, Pl), commands_raw::CmdPushConstants<Pc, Pl>);
+pass_through!((Q), commands_raw::CmdResetQueryPool<Q>);
 pass_through!((S, D), commands_raw::CmdResolveImage<S, D>);
 pass_through!((), commands_raw::CmdSetEvent);
 pass_through!((B, D), commands_raw::CmdUpdateBuffer<B, D>);
+pass_through!((Q), commands_raw::CmdWriteTimestamp<Q>);
@@ -7,23 +7,28 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::cmp;
 use std::error::Error;
 use std::sync::Arc;
 
 use buffer::BufferAccess;
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::CommandBufferBuild;
 use command_buffer::cb::UnsafeCommandBuffer;
 use command_buffer::CommandBuffer;
 use command_buffer::CommandBufferBuilder;
 use command_buffer::commands_raw;
 use image::ImageAccess;
+use image::ImageSubresourceRange;
 use device::Device;
 use device::DeviceOwned;
 use device::Queue;
+use instance::QueueFamily;
 use sync::AccessFlagBits;
 use sync::PipelineStages;
 use sync::GpuFuture;
+use VulkanObject;
 
 /// Layers that ensures that synchronization with buffers and images between command buffers is
 /// properly handled.
@@ -39,7 +44,9 @@ use sync::GpuFuture;
 pub struct SubmitSyncBuilderLayer<I> {
     inner: I,
     buffers: Vec<(Box<BufferAccess + Send + Sync>, bool)>,
-    images: Vec<(Box<ImageAccess + Send + Sync>, bool)>,
+    images: Vec<(Box<ImageAccess + Send + Sync>, ImageSubresourceRange, bool)>,
+    // Secondary command buffers that are executed by this command buffer.
+    secondaries: Vec<Box<CommandBufferResources>>,
 }
 
 impl<I> SubmitSyncBuilderLayer<I> {
@@ -50,6 +57,7 @@ impl<I> SubmitSyncBuilderLayer<I> {
             inner: inner,
             buffers: Vec::new(),
             images: Vec::new(),
+            secondaries: Vec::new(),
         }
     }
 
@@ -69,12 +77,23 @@ impl<I> SubmitSyncBuilderLayer<I> {
         self.buffers.push((Box::new(buffer.clone()), exclusive));
     }
 
-    // Adds an image to the list.
-    fn add_image<T>(&mut self, image: &T, exclusive: bool)
+    // Adds a range of subresources of an image to the list.
+    fn add_image<T>(&mut self, image: &T, range: ImageSubresourceRange, exclusive: bool)
         where T: ImageAccess + Send + Sync + Clone + 'static
     {
-        // FIXME: actually implement
-        self.images.push((Box::new(image.clone()), exclusive));
+        let raw = image.inner().internal_object();
+
+        for &mut (ref existing_img, ref existing_range, ref mut existing_exclusive) in
+            self.images.iter_mut()
+        {
+            if existing_img.inner().internal_object() == raw && existing_range.contains(&range) {
+                *existing_exclusive = *existing_exclusive || exclusive;
+                return;
+            }
+        }
+
+        // Ranges that overlap without one containing the other are merged in `merge_images`.
+        self.images.push((Box::new(image.clone()), range, exclusive));
     }
 }
 
@@ -90,6 +109,7 @@ unsafe impl<I, O, E> CommandBufferBuild for SubmitSyncBuilderLayer<I>
             inner: try!(self.inner.build()),
             buffers: self.buffers,
             images: self.images,
+            secondaries: self.secondaries,
         })
     }
 }
@@ -115,6 +135,11 @@ unsafe impl<I> CommandBufferBuilder for SubmitSyncBuilderLayer<I>
     fn supports_compute(&self) -> bool {
         self.inner.supports_compute()
     }
+
+    #[inline]
+    fn queue_family(&self) -> QueueFamily {
+        self.inner.queue_family()
+    }
 }
 
 // FIXME: implement manually
@@ -126,12 +151,13 @@ macro_rules! pass_through {
             type Out = SubmitSyncBuilderLayer<O>;
 
             #[inline]
-            fn add(self, command: $cmd) -> Self::Out {
-                SubmitSyncBuilderLayer {
-                    inner: AddCommand::add(self.inner, command),
+            fn add(self, command: $cmd) -> Result<Self::Out, CommandAddError> {
+                Ok(SubmitSyncBuilderLayer {
+                    inner: try!(AddCommand::add(self.inner, command)),
                     buffers: self.buffers,
                     images: self.images,
-                }
+                    secondaries: self.secondaries,
+                })
             }
         }
     }
@@ -141,7 +167,10 @@ macro_rules! pass_through {
 pass_through!((Rp, F), commands_raw::CmdBeginRenderPass<Rp, F>);
 pass_through!((S, Pl), commands_raw::CmdBindDescriptorSets<S, Pl>);
 pass_through!((V), commands_raw::CmdBindVertexBuffers<V>);
-pass_through!((C), commands_raw::CmdExecuteCommands<C>);
+pass_through!((Q), commands_raw::CmdBeginQuery<Q>);
+pass_through!((Q), commands_raw::CmdEndQuery<Q>);
+pass_through!((Q), commands_raw::CmdResetQueryPool<Q>);
+pass_through!((Q), commands_raw::CmdWriteTimestamp<Q>);
 
 unsafe impl<I, O, B> AddCommand<commands_raw::CmdBindIndexBuffer<B>> for SubmitSyncBuilderLayer<I>
     where I: AddCommand<commands_raw::CmdBindIndexBuffer<B>, Out = O>,
@@ -150,14 +179,15 @@ unsafe impl<I, O, B> AddCommand<commands_raw::CmdBindIndexBuffer<B>> for SubmitS
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(mut self, command: commands_raw::CmdBindIndexBuffer<B>) -> Self::Out {
+    fn add(mut self, command: commands_raw::CmdBindIndexBuffer<B>) -> Result<Self::Out, CommandAddError> {
         self.add_buffer(command.buffer(), false);
 
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -167,12 +197,13 @@ unsafe impl<I, O, P> AddCommand<commands_raw::CmdBindPipeline<P>> for SubmitSync
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(self, command: commands_raw::CmdBindPipeline<P>) -> Self::Out {
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+    fn add(self, command: commands_raw::CmdBindPipeline<P>) -> Result<Self::Out, CommandAddError> {
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -184,15 +215,17 @@ unsafe impl<I, O, S, D> AddCommand<commands_raw::CmdBlitImage<S, D>> for SubmitS
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(mut self, command: commands_raw::CmdBlitImage<S, D>) -> Self::Out {
-        self.add_image(command.source(), false);
-        self.add_image(command.destination(), true);
+    fn add(mut self, command: commands_raw::CmdBlitImage<S, D>) -> Result<Self::Out, CommandAddError> {
+        self.add_image(command.source(), command.source_subresource_range(), false);
+        self.add_image(command.destination(), command.destination_subresource_range(),
+                       true);
 
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -202,12 +235,13 @@ unsafe impl<I, O> AddCommand<commands_raw::CmdClearAttachments> for SubmitSyncBu
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(self, command: commands_raw::CmdClearAttachments) -> Self::Out {
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+    fn add(self, command: commands_raw::CmdClearAttachments) -> Result<Self::Out, CommandAddError> {
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -219,15 +253,16 @@ unsafe impl<I, O, S, D> AddCommand<commands_raw::CmdCopyBuffer<S, D>> for Submit
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(mut self, command: commands_raw::CmdCopyBuffer<S, D>) -> Self::Out {
+    fn add(mut self, command: commands_raw::CmdCopyBuffer<S, D>) -> Result<Self::Out, CommandAddError> {
         self.add_buffer(command.source(), false);
         self.add_buffer(command.destination(), true);
 
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -239,15 +274,17 @@ unsafe impl<I, O, S, D> AddCommand<commands_raw::CmdCopyBufferToImage<S, D>> for
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(mut self, command: commands_raw::CmdCopyBufferToImage<S, D>) -> Self::Out {
+    fn add(mut self, command: commands_raw::CmdCopyBufferToImage<S, D>) -> Result<Self::Out, CommandAddError> {
         self.add_buffer(command.source(), false);
-        self.add_image(command.destination(), true);
+        self.add_image(command.destination(), command.destination_subresource_range(),
+                       true);
 
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -259,15 +296,59 @@ unsafe impl<I, O, S, D> AddCommand<commands_raw::CmdCopyImage<S, D>> for SubmitS
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(mut self, command: commands_raw::CmdCopyImage<S, D>) -> Self::Out {
-        self.add_image(command.source(), false);
-        self.add_image(command.destination(), true);
+    fn add(mut self, command: commands_raw::CmdCopyImage<S, D>) -> Result<Self::Out, CommandAddError> {
+        self.add_image(command.source(), command.source_subresource_range(), false);
+        self.add_image(command.destination(), command.destination_subresource_range(),
+                       true);
 
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
+    }
+}
+
+unsafe impl<I, O, S, D> AddCommand<commands_raw::CmdCopyImageToBuffer<S, D>> for SubmitSyncBuilderLayer<I>
+    where I: AddCommand<commands_raw::CmdCopyImageToBuffer<S, D>, Out = O>,
+          S: ImageAccess + Send + Sync + Clone + 'static,
+          D: BufferAccess + Send + Sync + Clone + 'static
+{
+    type Out = SubmitSyncBuilderLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdCopyImageToBuffer<S, D>) -> Result<Self::Out, CommandAddError> {
+        self.add_image(command.source(), command.source_subresource_range(), false);
+        self.add_buffer(command.destination(), true);
+
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
+            buffers: self.buffers,
+            images: self.images,
+            secondaries: self.secondaries,
+        })
+    }
+}
+
+unsafe impl<I, O, Q, B> AddCommand<commands_raw::CmdCopyQueryPoolResults<Q, B>> for SubmitSyncBuilderLayer<I>
+    where I: AddCommand<commands_raw::CmdCopyQueryPoolResults<Q, B>, Out = O>,
+          B: BufferAccess + Send + Sync + Clone + 'static
+{
+    type Out = SubmitSyncBuilderLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdCopyQueryPoolResults<Q, B>)
+           -> Result<Self::Out, CommandAddError>
+    {
+        self.add_buffer(command.destination(), true);
+
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
+            buffers: self.buffers,
+            images: self.images,
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -277,12 +358,13 @@ unsafe impl<I, O> AddCommand<commands_raw::CmdDispatchRaw> for SubmitSyncBuilder
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(self, command: commands_raw::CmdDispatchRaw) -> Self::Out {
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+    fn add(self, command: commands_raw::CmdDispatchRaw) -> Result<Self::Out, CommandAddError> {
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -292,12 +374,13 @@ unsafe impl<I, O> AddCommand<commands_raw::CmdDrawRaw> for SubmitSyncBuilderLaye
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(self, command: commands_raw::CmdDrawRaw) -> Self::Out {
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+    fn add(self, command: commands_raw::CmdDrawRaw) -> Result<Self::Out, CommandAddError> {
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -307,12 +390,32 @@ unsafe impl<I, O> AddCommand<commands_raw::CmdDrawIndexedRaw> for SubmitSyncBuil
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(self, command: commands_raw::CmdDrawIndexedRaw) -> Self::Out {
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+    fn add(self, command: commands_raw::CmdDrawIndexedRaw) -> Result<Self::Out, CommandAddError> {
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
+    }
+}
+
+unsafe impl<I, O, Cb> AddCommand<commands_raw::CmdExecuteCommands<Cb>> for SubmitSyncBuilderLayer<I>
+    where I: AddCommand<commands_raw::CmdExecuteCommands<Cb>, Out = O>,
+          Cb: CommandBuffer + Send + Sync + Clone + 'static
+{
+    type Out = SubmitSyncBuilderLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdExecuteCommands<Cb>) -> Result<Self::Out, CommandAddError> {
+        self.secondaries.push(Box::new(command.command_buffer().clone()));
+
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
+            buffers: self.buffers,
+            images: self.images,
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -322,12 +425,13 @@ unsafe impl<I, O> AddCommand<commands_raw::CmdEndRenderPass> for SubmitSyncBuild
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(self, command: commands_raw::CmdEndRenderPass) -> Self::Out {
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+    fn add(self, command: commands_raw::CmdEndRenderPass) -> Result<Self::Out, CommandAddError> {
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -338,14 +442,35 @@ unsafe impl<I, O, B> AddCommand<commands_raw::CmdFillBuffer<B>> for SubmitSyncBu
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(mut self, command: commands_raw::CmdFillBuffer<B>) -> Self::Out {
+    fn add(mut self, command: commands_raw::CmdFillBuffer<B>) -> Result<Self::Out, CommandAddError> {
         self.add_buffer(command.buffer(), true);
 
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
+    }
+}
+
+unsafe impl<I, O, Img> AddCommand<commands_raw::CmdGenerateMipmaps<Img>> for SubmitSyncBuilderLayer<I>
+    where I: AddCommand<commands_raw::CmdGenerateMipmaps<Img>, Out = O>,
+          Img: ImageAccess + Send + Sync + Clone + 'static
+{
+    type Out = SubmitSyncBuilderLayer<O>;
+
+    #[inline]
+    fn add(mut self, command: commands_raw::CmdGenerateMipmaps<Img>) -> Result<Self::Out, CommandAddError> {
+        let range = ImageSubresourceRange::whole_image(command.image());
+        self.add_image(command.image(), range, true);
+
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
+            buffers: self.buffers,
+            images: self.images,
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -355,12 +480,13 @@ unsafe impl<I, O> AddCommand<commands_raw::CmdNextSubpass> for SubmitSyncBuilder
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(self, command: commands_raw::CmdNextSubpass) -> Self::Out {
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+    fn add(self, command: commands_raw::CmdNextSubpass) -> Result<Self::Out, CommandAddError> {
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -370,12 +496,13 @@ unsafe impl<I, O, Pc, Pl> AddCommand<commands_raw::CmdPushConstants<Pc, Pl>> for
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(self, command: commands_raw::CmdPushConstants<Pc, Pl>) -> Self::Out {
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+    fn add(self, command: commands_raw::CmdPushConstants<Pc, Pl>) -> Result<Self::Out, CommandAddError> {
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -387,15 +514,17 @@ unsafe impl<I, O, S, D> AddCommand<commands_raw::CmdResolveImage<S, D>> for Subm
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(mut self, command: commands_raw::CmdResolveImage<S, D>) -> Self::Out {
-        self.add_image(command.source(), false);
-        self.add_image(command.destination(), true);
+    fn add(mut self, command: commands_raw::CmdResolveImage<S, D>) -> Result<Self::Out, CommandAddError> {
+        self.add_image(command.source(), command.source_subresource_range(), false);
+        self.add_image(command.destination(), command.destination_subresource_range(),
+                       true);
 
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -405,12 +534,13 @@ unsafe impl<I, O> AddCommand<commands_raw::CmdSetEvent> for SubmitSyncBuilderLay
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(self, command: commands_raw::CmdSetEvent) -> Self::Out {
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+    fn add(self, command: commands_raw::CmdSetEvent) -> Result<Self::Out, CommandAddError> {
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -420,12 +550,13 @@ unsafe impl<I, O> AddCommand<commands_raw::CmdSetState> for SubmitSyncBuilderLay
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(self, command: commands_raw::CmdSetState) -> Self::Out {
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+    fn add(self, command: commands_raw::CmdSetState) -> Result<Self::Out, CommandAddError> {
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -436,14 +567,15 @@ unsafe impl<I, O, B, D> AddCommand<commands_raw::CmdUpdateBuffer<B, D>> for Subm
     type Out = SubmitSyncBuilderLayer<O>;
 
     #[inline]
-    fn add(mut self, command: commands_raw::CmdUpdateBuffer<B, D>) -> Self::Out {
+    fn add(mut self, command: commands_raw::CmdUpdateBuffer<B, D>) -> Result<Self::Out, CommandAddError> {
         self.add_buffer(command.buffer(), true);
 
-        SubmitSyncBuilderLayer {
-            inner: AddCommand::add(self.inner, command),
+        Ok(SubmitSyncBuilderLayer {
+            inner: try!(AddCommand::add(self.inner, command)),
             buffers: self.buffers,
             images: self.images,
-        }
+            secondaries: self.secondaries,
+        })
     }
 }
 
@@ -451,7 +583,8 @@ unsafe impl<I, O, B, D> AddCommand<commands_raw::CmdUpdateBuffer<B, D>> for Subm
 pub struct SubmitSyncLayer<I> {
     inner: I,
     buffers: Vec<(Box<BufferAccess + Send + Sync>, bool)>,
-    images: Vec<(Box<ImageAccess + Send + Sync>, bool)>,
+    images: Vec<(Box<ImageAccess + Send + Sync>, ImageSubresourceRange, bool)>,
+    secondaries: Vec<Box<CommandBufferResources>>,
 }
 
 unsafe impl<I> CommandBuffer for SubmitSyncLayer<I> where I: CommandBuffer {
@@ -463,7 +596,9 @@ unsafe impl<I> CommandBuffer for SubmitSyncLayer<I> where I: CommandBuffer {
     }
 
     fn submit_check(&self, future: &GpuFuture, queue: &Queue) -> Result<(), Box<Error>> {
-        for &(ref buffer, exclusive) in self.buffers.iter() {
+        // The resources of the secondary command buffers are merged with ours, so that each
+        // resource is only locked once.
+        for (buffer, exclusive) in merge_buffers(CommandBuffer::buffers_list(self)) {
             if future.check_buffer_access(buffer, exclusive, queue).is_ok() {
                 unsafe { buffer.increase_gpu_lock(); }
                 continue;
@@ -474,13 +609,13 @@ unsafe impl<I> CommandBuffer for SubmitSyncLayer<I> where I: CommandBuffer {
             }
         }
 
-        for &(ref image, exclusive) in self.images.iter() {
-            if future.check_image_access(image, exclusive, queue).is_ok() {
-                unsafe { image.increase_gpu_lock(); }
+        for (image, range, exclusive) in merge_images(CommandBuffer::images_list(self)) {
+            if future.check_image_access(image, &range, exclusive, queue).is_ok() {
+                unsafe { image.increase_gpu_lock(&range); }
                 continue;
             }
 
-            if !image.try_gpu_lock(exclusive, queue) {
+            if !image.try_gpu_lock(&range, exclusive, queue) {
                 panic!()    // FIXME: return Err();
             }
         }
@@ -499,12 +634,128 @@ unsafe impl<I> CommandBuffer for SubmitSyncLayer<I> where I: CommandBuffer {
     }
 
     #[inline]
-    fn check_image_access(&self, image: &ImageAccess, exclusive: bool, queue: &Queue)
+    fn check_image_access(&self, image: &ImageAccess, range: &ImageSubresourceRange,
+                          exclusive: bool, queue: &Queue)
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>
     {
         // FIXME: implement
         Err(())
     }
+
+    #[inline]
+    fn buffers_list<'a>(&'a self) -> Box<Iterator<Item = (&'a BufferAccess, bool)> + 'a> {
+        let own = self.buffers.iter().map(|&(ref buffer, exclusive)| {
+            (&**buffer as &BufferAccess, exclusive)
+        });
+        let secondaries = self.secondaries.iter().flat_map(|cb| {
+            CommandBufferResources::buffers_list(&**cb)
+        });
+        Box::new(own.chain(secondaries))
+    }
+
+    #[inline]
+    fn images_list<'a>(&'a self)
+                       -> Box<Iterator<Item = (&'a ImageAccess, ImageSubresourceRange, bool)> + 'a>
+    {
+        let own = self.images.iter().map(|&(ref image, range, exclusive)| {
+            (&**image as &ImageAccess, range, exclusive)
+        });
+        let secondaries = self.secondaries.iter().flat_map(|cb| {
+            CommandBufferResources::images_list(&**cb)
+        });
+        Box::new(own.chain(secondaries))
+    }
+}
+
+// Merges the entries of the list that refer to the same buffer, so that each buffer only appears
+// once.
+fn merge_buffers<'a, I>(list: I) -> Vec<(&'a BufferAccess, bool)>
+    where I: Iterator<Item = (&'a BufferAccess, bool)>
+{
+    let mut out: Vec<(&'a BufferAccess, bool)> = Vec::new();
+
+    'outer: for (buffer, exclusive) in list {
+        for entry in out.iter_mut() {
+            if entry.0.conflicts_buffer(0, entry.0.size(), buffer, 0, buffer.size()) {
+                entry.1 = entry.1 || exclusive;
+                continue 'outer;
+            }
+        }
+
+        out.push((buffer, exclusive));
+    }
+
+    out
+}
+
+// Merges the entries of the list whose ranges of subresources overlap, so that each subresource
+// only appears once. Overlapping ranges are replaced with the smallest range that contains both.
+fn merge_images<'a, I>(list: I) -> Vec<(&'a ImageAccess, ImageSubresourceRange, bool)>
+    where I: Iterator<Item = (&'a ImageAccess, ImageSubresourceRange, bool)>
+{
+    let mut out: Vec<(&'a ImageAccess, ImageSubresourceRange, bool)> = Vec::new();
+
+    for (image, mut range, mut exclusive) in list {
+        // Merging two ranges can make the result overlap with other entries, so we loop until
+        // no entry overlaps anymore.
+        loop {
+            let pos = out.iter().position(|&(other, ref other_range, _)| {
+                other.conflicts_image(other_range.first_array_layer, other_range.num_array_layers,
+                                      other_range.first_mipmap_level,
+                                      other_range.num_mipmap_levels, image,
+                                      range.first_array_layer, range.num_array_layers,
+                                      range.first_mipmap_level, range.num_mipmap_levels)
+            });
+
+            match pos {
+                Some(pos) => {
+                    let (_, other_range, other_exclusive) = out.remove(pos);
+                    range = bounding_range(&range, &other_range);
+                    exclusive = exclusive || other_exclusive;
+                },
+                None => break,
+            }
+        }
+
+        out.push((image, range, exclusive));
+    }
+
+    out
+}
+
+// Returns the smallest range that contains both `a` and `b`.
+fn bounding_range(a: &ImageSubresourceRange, b: &ImageSubresourceRange) -> ImageSubresourceRange {
+    let mips = cmp::min(a.first_mipmap_level, b.first_mipmap_level) ..
+               cmp::max(a.mipmap_levels().end, b.mipmap_levels().end);
+    let layers = cmp::min(a.first_array_layer, b.first_array_layer) ..
+                 cmp::max(a.array_layers().end, b.array_layers().end);
+
+    ImageSubresourceRange {
+        aspect: if a.aspect == b.aspect { a.aspect } else { None },
+        .. ImageSubresourceRange::new(mips, layers)
+    }
+}
+
+// Object-safe subset of `CommandBuffer` that is used to keep track of the resources used by the
+// secondary command buffers.
+trait CommandBufferResources: Send + Sync {
+    fn buffers_list<'a>(&'a self) -> Box<Iterator<Item = (&'a BufferAccess, bool)> + 'a>;
+    fn images_list<'a>(&'a self)
+                       -> Box<Iterator<Item = (&'a ImageAccess, ImageSubresourceRange, bool)> + 'a>;
+}
+
+impl<T> CommandBufferResources for T where T: CommandBuffer + Send + Sync {
+    #[inline]
+    fn buffers_list<'a>(&'a self) -> Box<Iterator<Item = (&'a BufferAccess, bool)> + 'a> {
+        CommandBuffer::buffers_list(self)
+    }
+
+    #[inline]
+    fn images_list<'a>(&'a self)
+                       -> Box<Iterator<Item = (&'a ImageAccess, ImageSubresourceRange, bool)> + 'a>
+    {
+        CommandBuffer::images_list(self)
+    }
 }
 
 unsafe impl<I> DeviceOwned for SubmitSyncLayer<I> where I: DeviceOwned {
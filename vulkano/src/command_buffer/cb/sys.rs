@@ -8,6 +8,7 @@
 // according to those terms.
 
 use std::error::Error;
+use std::iter;
 use std::ptr;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
@@ -25,8 +26,11 @@ use framebuffer::Framebuffer;
 use framebuffer::FramebufferAbstract;
 use framebuffer::RenderPass;
 use framebuffer::RenderPassAbstract;
+use framebuffer::RenderPassCompatibility;
 use framebuffer::Subpass;
 use image::ImageAccess;
+use image::ImageSubresourceRange;
+use query::QueryPipelineStatisticFlags;
 use sync::AccessFlagBits;
 use sync::PipelineStages;
 use sync::GpuFuture;
@@ -44,7 +48,14 @@ pub enum Kind<R, F> {
 
     /// A secondary command buffer can execute all dispatch and transfer operations, but not
     /// drawing operations.
-    Secondary,
+    Secondary {
+        /// Whether the command buffer can be executed while an occlusion query is active.
+        occlusion_query: KindOcclusionQuery,
+
+        /// The pipeline statistics that can be gathered by a query that is active while the
+        /// command buffer is executed.
+        query_statistics_flags: QueryPipelineStatisticFlags,
+    },
 
     /// A secondary command buffer within a render pass can only call draw operations that can
     /// be executed from within a specific subpass.
@@ -55,9 +66,29 @@ pub enum Kind<R, F> {
         /// The framebuffer object that will be used when calling the command buffer.
         /// This parameter is optional and is an optimization hint for the implementation.
         framebuffer: Option<F>,
+
+        /// Whether the command buffer can be executed while an occlusion query is active.
+        occlusion_query: KindOcclusionQuery,
+
+        /// The pipeline statistics that can be gathered by a query that is active while the
+        /// command buffer is executed.
+        query_statistics_flags: QueryPipelineStatisticFlags,
     },
 }
 
+/// Whether a secondary command buffer can be executed while an occlusion query is active.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KindOcclusionQuery {
+    /// The command buffer can be executed while an occlusion query is active. If
+    /// `precise_allowed` is false, the active query must not be precise.
+    ///
+    /// Requires the `inherited_queries` feature.
+    Allowed { precise_allowed: bool },
+
+    /// The command buffer can't be executed while an occlusion query is active.
+    Forbidden,
+}
+
 impl Kind<RenderPass<EmptySinglePassRenderPassDesc>, Framebuffer<RenderPass<EmptySinglePassRenderPassDesc>, ()>> {
     /// Equivalent to `Kind::Primary`.
     ///
@@ -68,6 +99,20 @@ impl Kind<RenderPass<EmptySinglePassRenderPassDesc>, Framebuffer<RenderPass<Empt
     pub fn primary() -> Kind<RenderPass<EmptySinglePassRenderPassDesc>, Framebuffer<RenderPass<EmptySinglePassRenderPassDesc>, ()>> {
         Kind::Primary
     }
+
+    /// Builds a `Kind::Secondary` that can't be executed while a query is active.
+    ///
+    /// > **Note**: If you use `let kind = Kind::Secondary { ... };` in your code, you will
+    /// > probably get a compilation error because the Rust compiler couldn't determine the
+    /// > template parameters of `Kind`. To solve that problem in an easy way you can use this
+    /// > function instead.
+    #[inline]
+    pub fn secondary() -> Kind<RenderPass<EmptySinglePassRenderPassDesc>, Framebuffer<RenderPass<EmptySinglePassRenderPassDesc>, ()>> {
+        Kind::Secondary {
+            occlusion_query: KindOcclusionQuery::Forbidden,
+            query_statistics_flags: QueryPipelineStatisticFlags::none(),
+        }
+    }
 }
 
 /// Flags to pass when creating a command buffer.
@@ -109,6 +154,10 @@ pub struct UnsafeCommandBufferBuilder<P> where P: CommandPool {
 
     // True if we are a secondary command buffer.
     secondary_cb: bool,
+
+    // If we are a secondary command buffer that is executed inside of a render pass, contains the
+    // summary of the render pass and the index of the subpass.
+    inheritance_subpass: Option<(Arc<RenderPassCompatibility>, u32)>,
 }
 
 impl<P> UnsafeCommandBufferBuilder<P> where P: CommandPool {
@@ -131,7 +180,7 @@ impl<P> UnsafeCommandBufferBuilder<P> where P: CommandPool {
     {
         let secondary = match kind {
             Kind::Primary => false,
-            Kind::Secondary | Kind::SecondaryRenderPass { .. } => true,
+            Kind::Secondary { .. } | Kind::SecondaryRenderPass { .. } => true,
         };
 
         let cmd = try!(pool.alloc(secondary, 1)).next().unwrap();
@@ -172,7 +221,7 @@ impl<P> UnsafeCommandBufferBuilder<P> where P: CommandPool {
             };
 
             let b = match kind {
-                Kind::Primary | Kind::Secondary => 0,
+                Kind::Primary | Kind::Secondary { .. } => 0,
                 Kind::SecondaryRenderPass { .. } => {
                     vk::COMMAND_BUFFER_USAGE_RENDER_PASS_CONTINUE_BIT
                 },
@@ -187,7 +236,14 @@ impl<P> UnsafeCommandBufferBuilder<P> where P: CommandPool {
             (0, 0)
         };
 
-        let framebuffer = if let Kind::SecondaryRenderPass { ref subpass, framebuffer: Some(ref framebuffer) } = kind {
+        let inheritance_subpass = if let Kind::SecondaryRenderPass { ref subpass, .. } = kind {
+            let compatibility = RenderPassCompatibility::new(subpass.render_pass());
+            Some((Arc::new(compatibility), subpass.index()))
+        } else {
+            None
+        };
+
+        let framebuffer = if let Kind::SecondaryRenderPass { ref subpass, framebuffer: Some(ref framebuffer), .. } = kind {
             // TODO: restore check
             //assert!(framebuffer.is_compatible_with(subpass.render_pass()));     // TODO: proper error
             FramebufferAbstract::inner(&framebuffer).internal_object()
@@ -195,15 +251,36 @@ impl<P> UnsafeCommandBufferBuilder<P> where P: CommandPool {
             0
         };
 
+        let (occlusion_query, query_statistics_flags) = match kind {
+            Kind::Primary => (KindOcclusionQuery::Forbidden, QueryPipelineStatisticFlags::none()),
+            Kind::Secondary { occlusion_query, query_statistics_flags } |
+            Kind::SecondaryRenderPass { occlusion_query, query_statistics_flags, .. } => {
+                (occlusion_query, query_statistics_flags)
+            },
+        };
+
+        let (occlusion_query_enable, query_flags) = match occlusion_query {
+            KindOcclusionQuery::Allowed { precise_allowed } => {
+                debug_assert!(device.enabled_features().inherited_queries);
+                let flags = if precise_allowed { vk::QUERY_CONTROL_PRECISE_BIT } else { 0 };
+                (vk::TRUE, flags)
+            },
+            KindOcclusionQuery::Forbidden => (vk::FALSE, 0),
+        };
+
+        let pipeline_statistics: vk::QueryPipelineStatisticFlags = query_statistics_flags.into();
+        debug_assert!(pipeline_statistics == 0 ||
+                      device.enabled_features().pipeline_statistics_query);
+
         let inheritance = vk::CommandBufferInheritanceInfo {
             sType: vk::STRUCTURE_TYPE_COMMAND_BUFFER_INHERITANCE_INFO,
             pNext: ptr::null(),
             renderPass: rp,
             subpass: sp,
             framebuffer: framebuffer,
-            occlusionQueryEnable: 0,            // TODO:
-            queryFlags: 0,          // TODO:
-            pipelineStatistics: 0,          // TODO:
+            occlusionQueryEnable: occlusion_query_enable,
+            queryFlags: query_flags,
+            pipelineStatistics: pipeline_statistics,
         };
 
         let infos = vk::CommandBufferBeginInfo {
@@ -222,8 +299,9 @@ impl<P> UnsafeCommandBufferBuilder<P> where P: CommandPool {
             flags: flags,
             secondary_cb: match kind {
                 Kind::Primary => false,
-                Kind::Secondary | Kind::SecondaryRenderPass { .. } => true,
+                Kind::Secondary { .. } | Kind::SecondaryRenderPass { .. } => true,
             },
+            inheritance_subpass: inheritance_subpass,
         })
     }
 }
@@ -278,7 +356,8 @@ unsafe impl<P> CommandBufferBuild for UnsafeCommandBufferBuilder<P>
                 pool: self.pool.take().unwrap().finish(),
                 flags: self.flags,
                 already_submitted: AtomicBool::new(false),
-                secondary_cb: self.secondary_cb
+                secondary_cb: self.secondary_cb,
+                inheritance_subpass: self.inheritance_subpass.take(),
             })
         }
     }
@@ -305,7 +384,27 @@ pub struct UnsafeCommandBuffer<P> where P: CommandPool {
     already_submitted: AtomicBool,
 
     // True if this command buffer belongs to a secondary pool - needed for Drop
-    secondary_cb: bool
+    secondary_cb: bool,
+
+    // If this is a secondary command buffer that is executed inside of a render pass, contains
+    // the summary of the render pass and the index of the subpass.
+    inheritance_subpass: Option<(Arc<RenderPassCompatibility>, u32)>,
+}
+
+impl<P> UnsafeCommandBuffer<P> where P: CommandPool {
+    /// Returns true if this is a secondary command buffer.
+    #[inline]
+    pub fn is_secondary(&self) -> bool {
+        self.secondary_cb
+    }
+
+    /// If this is a secondary command buffer that can be executed inside of a render pass,
+    /// returns the summary of the render pass and the index of the subpass it was created for.
+    #[doc(hidden)]
+    #[inline]
+    pub fn inheritance_subpass(&self) -> Option<(Arc<RenderPassCompatibility>, u32)> {
+        self.inheritance_subpass.clone()
+    }
 }
 
 unsafe impl<P> CommandBuffer for UnsafeCommandBuffer<P> where P: CommandPool {
@@ -330,11 +429,25 @@ unsafe impl<P> CommandBuffer for UnsafeCommandBuffer<P> where P: CommandPool {
     }
 
     #[inline]
-    fn check_image_access(&self, image: &ImageAccess, exclusive: bool, queue: &Queue)
+    fn check_image_access(&self, image: &ImageAccess, range: &ImageSubresourceRange,
+                          exclusive: bool, queue: &Queue)
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>
     {
         Err(())
     }
+
+    #[inline]
+    fn buffers_list<'a>(&'a self) -> Box<Iterator<Item = (&'a BufferAccess, bool)> + 'a> {
+        // Not our job to keep track of the resources.
+        Box::new(iter::empty())
+    }
+
+    #[inline]
+    fn images_list<'a>(&'a self)
+                       -> Box<Iterator<Item = (&'a ImageAccess, ImageSubresourceRange, bool)> + 'a>
+    {
+        Box::new(iter::empty())
+    }
 }
 
 unsafe impl<P> DeviceOwned for UnsafeCommandBuffer<P> where P: CommandPool {
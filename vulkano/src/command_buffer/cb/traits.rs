@@ -7,6 +7,9 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::error;
+use std::fmt;
+
 /// Adds a command to a command buffer builder.
 pub unsafe trait AddCommand<C> {
     /// The new command buffer builder type.
@@ -14,5 +17,105 @@ pub unsafe trait AddCommand<C> {
 
     /// Adds the command. This takes ownership of the builder and returns a new builder with the
     /// command appended at the end of it.
-    fn add(self, cmd: C) -> Self::Out;
+    ///
+    /// Returns an error if the command can't be added to the builder in its current state. The
+    /// builder is destroyed in that situation.
+    fn add(self, cmd: C) -> Result<Self::Out, CommandAddError>;
+}
+
+/// Error that can happen when adding a command to a command buffer builder.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CommandAddError {
+    /// The command requires a queue family that supports graphics operations.
+    RequiresGraphicsQueue,
+
+    /// The command requires a queue family that supports compute operations.
+    RequiresComputeQueue,
+
+    /// The command requires a queue family that supports graphics or compute operations.
+    RequiresGraphicsOrComputeQueue,
+
+    /// The command can't be used inside of a render pass.
+    ForbiddenInsideRenderPass,
+
+    /// The command can only be used inside of a render pass.
+    ForbiddenOutsideRenderPass,
+
+    /// The command can't be used in a secondary command buffer.
+    ForbiddenInSecondaryCommandBuffer,
+
+    /// The content of the current subpass is provided by secondary command buffers, therefore
+    /// commands can't be added inline.
+    SubpassContentsSecondary,
+
+    /// The content of the current subpass is provided inline, therefore secondary command
+    /// buffers can't be executed.
+    SubpassContentsInline,
+
+    /// The secondary command buffer wasn't created for the current subpass, or was created for a
+    /// subpass while the builder is outside of a render pass.
+    IncompatibleSubpass,
+
+    /// One of the attachments to clear doesn't exist in the current subpass.
+    ClearAttachmentNotInSubpass,
+
+    /// One of the rectangles to clear is outside of the render area or of the layers of the
+    /// framebuffer.
+    ClearRectOutOfRange,
+
+    /// A resource used by the command doesn't belong to the same device as the command buffer.
+    WrongDevice,
+}
+
+impl error::Error for CommandAddError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CommandAddError::RequiresGraphicsQueue => {
+                "the command requires a queue family that supports graphics operations"
+            },
+            CommandAddError::RequiresComputeQueue => {
+                "the command requires a queue family that supports compute operations"
+            },
+            CommandAddError::RequiresGraphicsOrComputeQueue => {
+                "the command requires a queue family that supports graphics or compute operations"
+            },
+            CommandAddError::ForbiddenInsideRenderPass => {
+                "the command can't be used inside of a render pass"
+            },
+            CommandAddError::ForbiddenOutsideRenderPass => {
+                "the command can only be used inside of a render pass"
+            },
+            CommandAddError::ForbiddenInSecondaryCommandBuffer => {
+                "the command can't be used in a secondary command buffer"
+            },
+            CommandAddError::SubpassContentsSecondary => {
+                "the content of the current subpass is provided by secondary command buffers"
+            },
+            CommandAddError::SubpassContentsInline => {
+                "the content of the current subpass is provided inline"
+            },
+            CommandAddError::IncompatibleSubpass => {
+                "the secondary command buffer wasn't created for the current subpass"
+            },
+            CommandAddError::ClearAttachmentNotInSubpass => {
+                "one of the attachments to clear doesn't exist in the current subpass"
+            },
+            CommandAddError::ClearRectOutOfRange => {
+                "one of the rectangles to clear is outside of the render area or of the layers \
+                 of the framebuffer"
+            },
+            CommandAddError::WrongDevice => {
+                "a resource used by the command doesn't belong to the same device as the command \
+                 buffer"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CommandAddError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
 }
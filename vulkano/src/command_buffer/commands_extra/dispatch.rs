@@ -11,6 +11,7 @@ use std::error;
 use std::fmt;
 
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::commands_raw::CmdBindDescriptorSets;
 use command_buffer::commands_raw::CmdBindDescriptorSetsError;
 use command_buffer::commands_raw::CmdBindPipeline;
@@ -38,7 +39,7 @@ impl<P, S, Pc> CmdDispatch<P, S, Pc>
         where P: Clone
     {
         let bind_pipeline = CmdBindPipeline::bind_compute_pipeline(pipeline.clone());
-        let descriptor_sets = try!(CmdBindDescriptorSets::new(true, pipeline.clone(), sets));
+        let descriptor_sets = try!(CmdBindDescriptorSets::new(false, pipeline.clone(), sets));
         let push_constants = try!(CmdPushConstants::new(pipeline.clone(), push_constants));
         let dispatch_raw = try!(unsafe { CmdDispatchRaw::new(pipeline.device().clone(), dimensions) });
 
@@ -60,11 +61,11 @@ unsafe impl<Cb, P, S, Pc, O, O1, O2, O3> AddCommand<CmdDispatch<P, S, Pc>> for C
     type Out = O;
 
     #[inline]
-    fn add(self, command: CmdDispatch<P, S, Pc>) -> O {
-        self.add(command.push_constants)
-            .add(command.descriptor_sets)
-            .add(command.bind_pipeline)
-            .add(command.dispatch_raw)
+    fn add(self, command: CmdDispatch<P, S, Pc>) -> Result<O, CommandAddError> {
+        let cb = try!(self.add(command.push_constants));
+        let cb = try!(cb.add(command.descriptor_sets));
+        let cb = try!(cb.add(command.bind_pipeline));
+        cb.add(command.dispatch_raw)
     }
 }
 
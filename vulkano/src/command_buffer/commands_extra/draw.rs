@@ -9,6 +9,7 @@
 
 use command_buffer::DynamicState;
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::commands_raw::CmdBindDescriptorSets;
 use command_buffer::commands_raw::CmdBindPipeline;
 use command_buffer::commands_raw::CmdBindVertexBuffers;
@@ -69,12 +70,12 @@ unsafe impl<Cb, V, P, S, Pc, O, O1, O2, O3, O4, O5> AddCommand<CmdDraw<V, P, S,
     type Out = O;
 
     #[inline]
-    fn add(self, command: CmdDraw<V, P, S, Pc>) -> O {
-        self.add(command.vertex_buffers)
-            .add(command.push_constants)
-            .add(command.descriptor_sets)
-            .add(command.set_state)
-            .add(command.bind_pipeline)
-            .add(command.draw_raw)
+    fn add(self, command: CmdDraw<V, P, S, Pc>) -> Result<O, CommandAddError> {
+        let cb = try!(self.add(command.vertex_buffers));
+        let cb = try!(cb.add(command.push_constants));
+        let cb = try!(cb.add(command.descriptor_sets));
+        let cb = try!(cb.add(command.set_state));
+        let cb = try!(cb.add(command.bind_pipeline));
+        cb.add(command.draw_raw)
     }
 }
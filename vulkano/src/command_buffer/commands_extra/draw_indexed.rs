@@ -11,6 +11,7 @@ use buffer::BufferAccess;
 use buffer::TypedBuffer;
 use command_buffer::DynamicState;
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::commands_raw::CmdBindDescriptorSets;
 use command_buffer::commands_raw::CmdBindIndexBuffer;
 use command_buffer::commands_raw::CmdBindPipeline;
@@ -88,13 +89,13 @@ unsafe impl<Cb, V, Ib, P, S, Pc, O, O1, O2, O3, O4, O5, O6> AddCommand<CmdDrawIn
 {
     type Out = O;
     #[inline]
-    fn add(self, command: CmdDrawIndexed<V, Ib, P, S, Pc>) -> O {
-        self.add(command.vertex_buffers)
-            .add(command.index_buffer)
-            .add(command.push_constants)
-            .add(command.descriptor_sets)
-            .add(command.set_state)
-            .add(command.bind_pipeline)
-            .add(command.draw_indexed_raw)
+    fn add(self, command: CmdDrawIndexed<V, Ib, P, S, Pc>) -> Result<O, CommandAddError> {
+        let cb = try!(self.add(command.vertex_buffers));
+        let cb = try!(cb.add(command.index_buffer));
+        let cb = try!(cb.add(command.push_constants));
+        let cb = try!(cb.add(command.descriptor_sets));
+        let cb = try!(cb.add(command.set_state));
+        let cb = try!(cb.add(command.bind_pipeline));
+        cb.add(command.draw_indexed_raw)
     }
 }
@@ -0,0 +1,164 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
+use command_buffer::cb::UnsafeCommandBufferBuilder;
+use command_buffer::pool::CommandPool;
+use device::Device;
+use device::DeviceOwned;
+use query::QueryPoolAbstract;
+use query::QueryType;
+use VulkanObject;
+use VulkanPointers;
+use vk;
+
+/// Command that starts an occlusion or a pipeline statistics query.
+pub struct CmdBeginQuery<Q> {
+    // The query pool.
+    pool: Q,
+    // Raw query pool handle.
+    raw_pool: vk::QueryPool,
+    // Index of the query within the pool.
+    query: u32,
+    // Flags to pass to `vkCmdBeginQuery`.
+    flags: vk::QueryControlFlags,
+}
+
+impl<Q> CmdBeginQuery<Q>
+    where Q: QueryPoolAbstract
+{
+    /// Builds a command that starts the query `query` of `pool`.
+    ///
+    /// If `precise` is true, the result of an occlusion query is the exact number of samples
+    /// that passed the tests instead of just non-zero. This requires the
+    /// `occlusion_query_precise` feature.
+    pub fn new(pool: Q, query: u32, precise: bool)
+               -> Result<CmdBeginQuery<Q>, CmdBeginQueryError>
+    {
+        let (raw_pool, flags) = {
+            let inner = pool.inner();
+
+            if query >= inner.num_slots() {
+                return Err(CmdBeginQueryError::OutOfRange);
+            }
+
+            match inner.ty() {
+                QueryType::Occlusion => {
+                    if precise && !inner.device().enabled_features().occlusion_query_precise {
+                        return Err(CmdBeginQueryError::OcclusionQueryPreciseFeatureNotEnabled);
+                    }
+                },
+                QueryType::PipelineStatistics(_) => {
+                    if precise {
+                        return Err(CmdBeginQueryError::PreciseNotOcclusion);
+                    }
+                },
+                QueryType::Timestamp => {
+                    return Err(CmdBeginQueryError::TimestampQuery);
+                },
+            }
+
+            let flags = if precise { vk::QUERY_CONTROL_PRECISE_BIT } else { 0 };
+            (inner.internal_object(), flags)
+        };
+
+        Ok(CmdBeginQuery {
+            pool: pool,
+            raw_pool: raw_pool,
+            query: query,
+            flags: flags,
+        })
+    }
+}
+
+impl<Q> CmdBeginQuery<Q> {
+    /// Returns the query pool.
+    #[inline]
+    pub fn pool(&self) -> &Q {
+        &self.pool
+    }
+
+    /// Returns the index of the query within the pool.
+    #[inline]
+    pub fn query(&self) -> u32 {
+        self.query
+    }
+}
+
+unsafe impl<Q> DeviceOwned for CmdBeginQuery<Q>
+    where Q: QueryPoolAbstract
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.pool.inner().device()
+    }
+}
+
+unsafe impl<'a, P, Q> AddCommand<&'a CmdBeginQuery<Q>> for UnsafeCommandBufferBuilder<P>
+    where P: CommandPool
+{
+    type Out = UnsafeCommandBufferBuilder<P>;
+
+    #[inline]
+    fn add(self, command: &'a CmdBeginQuery<Q>) -> Result<Self::Out, CommandAddError> {
+        unsafe {
+            let vk = self.device().pointers();
+            let cmd = self.internal_object();
+            vk.CmdBeginQuery(cmd, command.raw_pool, command.query, command.flags);
+        }
+
+        Ok(self)
+    }
+}
+
+/// Error that can happen when creating a `CmdBeginQuery`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CmdBeginQueryError {
+    /// The query index is out of the range of the pool.
+    OutOfRange,
+    /// Timestamp queries can't be started. Use `CmdWriteTimestamp` instead.
+    TimestampQuery,
+    /// Only occlusion queries can be precise.
+    PreciseNotOcclusion,
+    /// A precise occlusion query was requested but the corresponding feature wasn't enabled.
+    OcclusionQueryPreciseFeatureNotEnabled,
+}
+
+impl error::Error for CmdBeginQueryError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CmdBeginQueryError::OutOfRange => {
+                "the query index is out of the range of the pool"
+            },
+            CmdBeginQueryError::TimestampQuery => {
+                "timestamp queries can't be started"
+            },
+            CmdBeginQueryError::PreciseNotOcclusion => {
+                "only occlusion queries can be precise"
+            },
+            CmdBeginQueryError::OcclusionQueryPreciseFeatureNotEnabled => {
+                "a precise occlusion query was requested but the corresponding feature wasn't \
+                 enabled"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CmdBeginQueryError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
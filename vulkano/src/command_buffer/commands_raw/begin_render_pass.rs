@@ -13,6 +13,7 @@ use std::ptr;
 use smallvec::SmallVec;
 
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::UnsafeCommandBufferBuilder;
 use command_buffer::pool::CommandPool;
 use device::Device;
@@ -21,6 +22,7 @@ use format::ClearValue;
 use framebuffer::FramebufferAbstract;
 use framebuffer::RenderPassDescClearValues;
 use framebuffer::RenderPassAbstract;
+use framebuffer::RenderPassCompatibility;
 use VulkanObject;
 use VulkanPointers;
 use vk;
@@ -31,10 +33,14 @@ pub struct CmdBeginRenderPass<Rp, F> {
     contents: vk::SubpassContents,
     // The draw area.
     rect: [Range<u32>; 2],
+    // Number of layers of the framebuffer.
+    layers: u32,
     // The clear values for the clear attachments.
     clear_values: SmallVec<[vk::ClearValue; 6]>,
     // The raw render pass handle to bind.
     raw_render_pass: vk::RenderPass,
+    // Summary of the render pass, used to check the commands that are added inside of it.
+    render_pass_compatibility: Arc<RenderPassCompatibility>,
     // The raw framebuffer handle to bind.
     raw_framebuffer: vk::Framebuffer,
     // The device.
@@ -55,6 +61,7 @@ impl<F> CmdBeginRenderPass<Arc<RenderPassAbstract + Send + Sync>, F>
         where F: RenderPassDescClearValues<C>
     {
         let raw_render_pass = RenderPassAbstract::inner(&framebuffer).internal_object();
+        let render_pass_compatibility = Arc::new(RenderPassCompatibility::new(&framebuffer));
         let device = framebuffer.device().clone();
         let raw_framebuffer = FramebufferAbstract::inner(&framebuffer).internal_object();
 
@@ -95,12 +102,16 @@ impl<F> CmdBeginRenderPass<Arc<RenderPassAbstract + Send + Sync>, F>
         let rect = [0 .. framebuffer.dimensions()[0],
                     0 .. framebuffer.dimensions()[1]];
 
+        let layers = framebuffer.dimensions()[2];
+
         CmdBeginRenderPass {
             contents: if secondary { vk::SUBPASS_CONTENTS_SECONDARY_COMMAND_BUFFERS }
                       else { vk::SUBPASS_CONTENTS_INLINE },
             rect: rect,
+            layers: layers,
             clear_values: clear_values,
             raw_render_pass: raw_render_pass,
+            render_pass_compatibility: render_pass_compatibility,
             raw_framebuffer: raw_framebuffer,
             device: device,
             render_pass: None,
@@ -109,6 +120,43 @@ impl<F> CmdBeginRenderPass<Arc<RenderPassAbstract + Send + Sync>, F>
     }
 }
 
+impl<Rp, F> CmdBeginRenderPass<Rp, F> {
+    /// Returns true if the content of the first subpass is going to be provided by secondary
+    /// command buffers.
+    #[inline]
+    pub fn secondary(&self) -> bool {
+        self.contents == vk::SUBPASS_CONTENTS_SECONDARY_COMMAND_BUFFERS
+    }
+
+    /// Returns the raw render pass that is being entered.
+    #[doc(hidden)]
+    #[inline]
+    pub fn raw_render_pass(&self) -> vk::RenderPass {
+        self.raw_render_pass
+    }
+
+    /// Returns the summary of the render pass that is being entered.
+    #[doc(hidden)]
+    #[inline]
+    pub fn render_pass_compatibility(&self) -> &Arc<RenderPassCompatibility> {
+        &self.render_pass_compatibility
+    }
+
+    /// Returns the area of the framebuffer that is affected by the render pass.
+    #[doc(hidden)]
+    #[inline]
+    pub fn render_area(&self) -> &[Range<u32>; 2] {
+        &self.rect
+    }
+
+    /// Returns the number of layers of the framebuffer.
+    #[doc(hidden)]
+    #[inline]
+    pub fn num_layers(&self) -> u32 {
+        self.layers
+    }
+}
+
 unsafe impl<Rp, F> DeviceOwned for CmdBeginRenderPass<Rp, F>
     where F: DeviceOwned
 {
@@ -124,7 +172,7 @@ unsafe impl<'a, P, Rp, F> AddCommand<&'a CmdBeginRenderPass<Rp, F>> for UnsafeCo
     type Out = UnsafeCommandBufferBuilder<P>;
 
     #[inline]
-    fn add(self, command: &'a CmdBeginRenderPass<Rp, F>) -> Self::Out {
+    fn add(self, command: &'a CmdBeginRenderPass<Rp, F>) -> Result<Self::Out, CommandAddError> {
         unsafe {
             let vk = self.device().pointers();
             let cmd = self.internal_object();
@@ -151,6 +199,6 @@ unsafe impl<'a, P, Rp, F> AddCommand<&'a CmdBeginRenderPass<Rp, F>> for UnsafeCo
             vk.CmdBeginRenderPass(cmd, &begin, command.contents);
         }
 
-        self
+        Ok(self)
     }
 }
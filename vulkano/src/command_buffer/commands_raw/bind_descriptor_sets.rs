@@ -14,6 +14,7 @@ use std::sync::Arc;
 use smallvec::SmallVec;
 
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::UnsafeCommandBufferBuilder;
 use command_buffer::pool::CommandPool;
 use descriptor::descriptor_set::DescriptorSetsCollection;
@@ -102,6 +103,12 @@ impl<S, P> CmdBindDescriptorSets<S, P> {
     pub fn is_graphics(&self) -> bool {
         self.pipeline_ty == vk::PIPELINE_BIND_POINT_GRAPHICS
     }
+
+    /// Returns the descriptor sets to bind.
+    #[inline]
+    pub fn sets(&self) -> &S {
+        &self.sets
+    }
 }
 
 unsafe impl<S, Pl> DeviceOwned for CmdBindDescriptorSets<S, Pl>
@@ -119,7 +126,7 @@ unsafe impl<'a, P, Pl, S> AddCommand<&'a CmdBindDescriptorSets<S, Pl>> for Unsaf
     type Out = UnsafeCommandBufferBuilder<P>;
 
     #[inline]
-    fn add(self, command: &'a CmdBindDescriptorSets<S, Pl>) -> Self::Out {
+    fn add(self, command: &'a CmdBindDescriptorSets<S, Pl>) -> Result<Self::Out, CommandAddError> {
         unsafe {
             let vk = self.device().pointers();
             let cmd = self.internal_object();
@@ -131,7 +138,7 @@ unsafe impl<'a, P, Pl, S> AddCommand<&'a CmdBindDescriptorSets<S, Pl>> for Unsaf
             }
         }
 
-        self
+        Ok(self)
     }
 }
 
@@ -11,6 +11,7 @@ use std::sync::Arc;
 use smallvec::SmallVec;
 
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::UnsafeCommandBufferBuilder;
 use command_buffer::pool::CommandPool;
 use device::Device;
@@ -26,6 +27,8 @@ pub struct CmdBindVertexBuffers<B> {
     raw_buffers: SmallVec<[vk::Buffer; 4]>,
     // Raw offsets of the buffers to bind.
     offsets: SmallVec<[vk::DeviceSize; 4]>,
+    // Number of bytes between each offset and the end of its buffer.
+    sizes: SmallVec<[usize; 4]>,
     // The device of the buffer, so that we can compare it with the command buffer's device.
     device: Arc<Device>,
     // The buffers to bind. Unused, but we need to keep it alive.
@@ -38,23 +41,36 @@ impl<B> CmdBindVertexBuffers<B> {
     pub fn new<S>(source_def: &S, buffers: B) -> CmdBindVertexBuffers<B>
         where S: VertexSource<B>
     {
-        let (device, raw_buffers, offsets) = {
+        let (device, raw_buffers, offsets, sizes) = {
             let (buffers, _, _) = source_def.decode(&buffers);
 
             let device = buffers.first().unwrap().buffer.device().clone();
             let raw_buffers = buffers.iter().map(|b| b.buffer.internal_object()).collect();
             let offsets = buffers.iter().map(|b| b.offset as vk::DeviceSize).collect();
+            let sizes = buffers.iter().map(|b| b.buffer.size() - b.offset).collect();
 
-            (device, raw_buffers, offsets)
+            (device, raw_buffers, offsets, sizes)
         };
 
         CmdBindVertexBuffers {
             raw_buffers: raw_buffers,
             offsets: offsets,
+            sizes: sizes,
             device: device,
             buffers: buffers,
         }
     }
+
+    /// Returns the raw handle, the offset and the size of the range of each buffer to bind.
+    ///
+    /// The vertex source doesn't tell how many bytes are read, so each range goes up to the end
+    /// of its buffer.
+    #[doc(hidden)]
+    #[inline]
+    pub fn raw_ranges<'a>(&'a self) -> Box<Iterator<Item = (vk::Buffer, usize, usize)> + 'a> {
+        Box::new(self.raw_buffers.iter().zip(self.offsets.iter()).zip(self.sizes.iter())
+                     .map(|((&raw, &offset), &size)| (raw, offset as usize, size)))
+    }
 }
 
 unsafe impl<B> DeviceOwned for CmdBindVertexBuffers<B> {
@@ -70,7 +86,7 @@ unsafe impl<'a, P, B> AddCommand<&'a CmdBindVertexBuffers<B>> for UnsafeCommandB
     type Out = UnsafeCommandBufferBuilder<P>;
 
     #[inline]
-    fn add(self, command: &'a CmdBindVertexBuffers<B>) -> Self::Out {
+    fn add(self, command: &'a CmdBindVertexBuffers<B>) -> Result<Self::Out, CommandAddError> {
         unsafe {
             let vk = self.device().pointers();
             let cmd = self.internal_object();
@@ -78,6 +94,6 @@ unsafe impl<'a, P, B> AddCommand<&'a CmdBindVertexBuffers<B>> for UnsafeCommandB
                                     command.raw_buffers.as_ptr(), command.offsets.as_ptr());
         }
 
-        self
+        Ok(self)
     }
 }
@@ -7,14 +7,22 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::cmp;
 use std::error;
 use std::fmt;
 use std::sync::Arc;
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::UnsafeCommandBufferBuilder;
+use command_buffer::commands_raw::aspect_mask;
+use command_buffer::commands_raw::region_in_range;
 use command_buffer::pool::CommandPool;
 use device::Device;
 use device::DeviceOwned;
+use format::FormatTy;
+use image::ImageAccess;
+use image::ImageSubresourceRange;
+use sampler::Filter;
 use VulkanObject;
 use VulkanPointers;
 use vk;
@@ -51,7 +59,149 @@ pub struct CmdBlitImage<S, D> {
     filter: vk::Filter,
 }
 
-// TODO: add constructor
+impl<S, D> CmdBlitImage<S, D> where S: ImageAccess, D: ImageAccess {
+    /// Builds a new command.
+    ///
+    /// The region of the source delimited by the `source_top_left` and `source_bottom_right`
+    /// corners is blitted to the region of the destination delimited by the
+    /// `destination_top_left` and `destination_bottom_right` corners. The content is scaled if
+    /// the two regions don't have the same size, and mirrored if the corners are swapped.
+    ///
+    /// `layer_count` layers are blitted, starting at `source_base_array_layer` in the source and
+    /// at `destination_base_array_layer` in the destination.
+    pub fn new(source: S, source_top_left: [i32; 3], source_bottom_right: [i32; 3],
+               source_base_array_layer: u32, source_mip_level: u32, destination: D,
+               destination_top_left: [i32; 3], destination_bottom_right: [i32; 3],
+               destination_base_array_layer: u32, destination_mip_level: u32, layer_count: u32,
+               filter: Filter) -> Result<CmdBlitImage<S, D>, CmdBlitImageError>
+    {
+        assert_eq!(source.inner().device().internal_object(),
+                   destination.inner().device().internal_object());
+
+        let source_raw = {
+            let inner = source.inner();
+            if !inner.usage_transfer_src() {
+                return Err(CmdBlitImageError::SourceMissingTransferUsage);
+            }
+            if !inner.supports_blit_source() {
+                return Err(CmdBlitImageError::SourceFormatNotSupported);
+            }
+            inner.internal_object()
+        };
+
+        let destination_raw = {
+            let inner = destination.inner();
+            if !inner.usage_transfer_dest() {
+                return Err(CmdBlitImageError::DestinationMissingTransferUsage);
+            }
+            if !inner.supports_blit_destination() {
+                return Err(CmdBlitImageError::DestinationFormatNotSupported);
+            }
+            inner.internal_object()
+        };
+
+        if source.samples() != 1 {
+            return Err(CmdBlitImageError::SourceMultisampled);
+        }
+
+        if destination.samples() != 1 {
+            return Err(CmdBlitImageError::DestinationMultisampled);
+        }
+
+        let source_ty = source.format().ty();
+        let destination_ty = destination.format().ty();
+
+        match (source_ty, destination_ty) {
+            (FormatTy::Uint, FormatTy::Uint) | (FormatTy::Sint, FormatTy::Sint) => (),
+            (FormatTy::Uint, _) | (_, FormatTy::Uint) | (FormatTy::Sint, _) |
+            (_, FormatTy::Sint) => {
+                return Err(CmdBlitImageError::IncompatibleFormatsTypes);
+            },
+            (FormatTy::Depth, _) | (_, FormatTy::Depth) | (FormatTy::Stencil, _) |
+            (_, FormatTy::Stencil) | (FormatTy::DepthStencil, _) |
+            (_, FormatTy::DepthStencil) => {
+                if source.format() != destination.format() {
+                    return Err(CmdBlitImageError::IncompatibleFormatsTypes);
+                }
+                if filter != Filter::Nearest {
+                    return Err(CmdBlitImageError::DepthStencilNeedsNearestFilter);
+                }
+            },
+            _ => (),
+        }
+
+        if filter == Filter::Linear && !source.inner().supports_linear_filtering() {
+            return Err(CmdBlitImageError::FilterFormatNotSupported);
+        }
+
+        if layer_count == 0 {
+            return Err(CmdBlitImageError::SourceCoordinatesOutOfRange);
+        }
+
+        {
+            let (offset, extent) = corners_to_region(source_top_left, source_bottom_right);
+            if !region_in_range(&source, offset, extent, source_base_array_layer,
+                                source_mip_level, layer_count)
+            {
+                return Err(CmdBlitImageError::SourceCoordinatesOutOfRange);
+            }
+        }
+
+        let (offset, extent) = corners_to_region(destination_top_left, destination_bottom_right);
+        if !region_in_range(&destination, offset, extent, destination_base_array_layer,
+                            destination_mip_level, layer_count)
+        {
+            return Err(CmdBlitImageError::DestinationCoordinatesOutOfRange);
+        }
+
+        if source.conflicts_image(source_base_array_layer, layer_count, source_mip_level, 1,
+                                  &destination, destination_base_array_layer, layer_count,
+                                  destination_mip_level, 1)
+        {
+            return Err(CmdBlitImageError::OverlappingRegions);
+        }
+
+        let source_aspect_mask = aspect_mask(&source);
+        let destination_aspect_mask = aspect_mask(&destination);
+
+        Ok(CmdBlitImage {
+            source: source,
+            source_raw: source_raw,
+            source_layout: vk::IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL,
+            source_offset1: source_top_left,
+            source_offset2: source_bottom_right,
+            source_aspect_mask: source_aspect_mask,
+            source_mip_level: source_mip_level,
+            source_base_array_layer: source_base_array_layer,
+            source_layer_count: layer_count,
+            destination: destination,
+            destination_raw: destination_raw,
+            destination_layout: vk::IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL,
+            destination_offset1: destination_top_left,
+            destination_offset2: destination_bottom_right,
+            destination_aspect_mask: destination_aspect_mask,
+            destination_mip_level: destination_mip_level,
+            destination_base_array_layer: destination_base_array_layer,
+            destination_layer_count: layer_count,
+            filter: filter as u32,
+        })
+    }
+}
+
+// Turns two corners of a blit region into an offset and an extent.
+//
+// The difference between two `i32`s always fits in a `u32`, but not in an `i32`, so it is
+// computed with `i64`s. Corners that are too far apart are then rejected by `region_in_range`.
+#[inline]
+fn corners_to_region(corner1: [i32; 3], corner2: [i32; 3]) -> ([i32; 3], [u32; 3]) {
+    let mut offset = [0; 3];
+    let mut extent = [0; 3];
+    for i in 0 .. 3 {
+        offset[i] = cmp::min(corner1[i], corner2[i]);
+        extent[i] = (corner1[i] as i64 - corner2[i] as i64).abs() as u32;
+    }
+    (offset, extent)
+}
 
 impl<S, D> CmdBlitImage<S, D> {
     /// Returns the source image.
@@ -65,12 +215,30 @@ impl<S, D> CmdBlitImage<S, D> {
     pub fn destination(&self) -> &D {
         &self.destination
     }
+
+    /// Returns the range of subresources of the source image that are accessed by the command.
+    #[inline]
+    pub fn source_subresource_range(&self) -> ImageSubresourceRange {
+        ImageSubresourceRange::new(self.source_mip_level .. self.source_mip_level + 1,
+                                   self.source_base_array_layer ..
+                                   self.source_base_array_layer + self.source_layer_count)
+    }
+
+    /// Returns the range of subresources of the destination image that are accessed by the
+    /// command.
+    #[inline]
+    pub fn destination_subresource_range(&self) -> ImageSubresourceRange {
+        ImageSubresourceRange::new(self.destination_mip_level .. self.destination_mip_level + 1,
+                                   self.destination_base_array_layer ..
+                                   self.destination_base_array_layer +
+                                   self.destination_layer_count)
+    }
 }
 
-unsafe impl<S, D> DeviceOwned for CmdBlitImage<S, D> where S: DeviceOwned {
+unsafe impl<S, D> DeviceOwned for CmdBlitImage<S, D> where S: ImageAccess {
     #[inline]
     fn device(&self) -> &Arc<Device> {
-        self.source.device()
+        self.source.inner().device()
     }
 }
 
@@ -80,7 +248,7 @@ unsafe impl<'a, P, S, D> AddCommand<&'a CmdBlitImage<S, D>> for UnsafeCommandBuf
     type Out = UnsafeCommandBufferBuilder<P>;
 
     #[inline]
-    fn add(self, command: &'a CmdBlitImage<S, D>) -> Self::Out {
+    fn add(self, command: &'a CmdBlitImage<S, D>) -> Result<Self::Out, CommandAddError> {
         unsafe {
             debug_assert!(command.source_layout == vk::IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL ||
                           command.source_layout == vk::IMAGE_LAYOUT_GENERAL);
@@ -133,19 +301,79 @@ unsafe impl<'a, P, S, D> AddCommand<&'a CmdBlitImage<S, D>> for UnsafeCommandBuf
                             1, &region as *const _, command.filter);
         }
 
-        self
+        Ok(self)
     }
 }
 
 /// Error that can happen when creating a `CmdBlitImage`.
 #[derive(Debug, Copy, Clone)]
 pub enum CmdBlitImageError {
+    /// The source image is missing the transfer source usage.
+    SourceMissingTransferUsage,
+    /// The destination image is missing the transfer destination usage.
+    DestinationMissingTransferUsage,
+    /// The format of the source image doesn't support blitting.
+    SourceFormatNotSupported,
+    /// The format of the destination image doesn't support blitting.
+    DestinationFormatNotSupported,
+    /// The source image has more than one sample per pixel.
+    SourceMultisampled,
+    /// The destination image has more than one sample per pixel.
+    DestinationMultisampled,
+    /// The formats of the source and destination images can't be blitted to each other.
+    IncompatibleFormatsTypes,
+    /// Depth and stencil images can only be blitted with the `Nearest` filter.
+    DepthStencilNeedsNearestFilter,
+    /// The format of the source image doesn't support linear filtering.
+    FilterFormatNotSupported,
+    /// The source coordinates are out of range of the image.
+    SourceCoordinatesOutOfRange,
+    /// The destination coordinates are out of range of the image.
+    DestinationCoordinatesOutOfRange,
+    /// The source and destination regions are overlapping in memory.
+    OverlappingRegions,
 }
 
 impl error::Error for CmdBlitImageError {
     #[inline]
     fn description(&self) -> &str {
         match *self {
+            CmdBlitImageError::SourceMissingTransferUsage => {
+                "the source image is missing the transfer source usage"
+            },
+            CmdBlitImageError::DestinationMissingTransferUsage => {
+                "the destination image is missing the transfer destination usage"
+            },
+            CmdBlitImageError::SourceFormatNotSupported => {
+                "the format of the source image doesn't support blitting"
+            },
+            CmdBlitImageError::DestinationFormatNotSupported => {
+                "the format of the destination image doesn't support blitting"
+            },
+            CmdBlitImageError::SourceMultisampled => {
+                "the source image has more than one sample per pixel"
+            },
+            CmdBlitImageError::DestinationMultisampled => {
+                "the destination image has more than one sample per pixel"
+            },
+            CmdBlitImageError::IncompatibleFormatsTypes => {
+                "the formats of the source and destination images can't be blitted to each other"
+            },
+            CmdBlitImageError::DepthStencilNeedsNearestFilter => {
+                "depth and stencil images can only be blitted with the `Nearest` filter"
+            },
+            CmdBlitImageError::FilterFormatNotSupported => {
+                "the format of the source image doesn't support linear filtering"
+            },
+            CmdBlitImageError::SourceCoordinatesOutOfRange => {
+                "the source coordinates are out of range of the image"
+            },
+            CmdBlitImageError::DestinationCoordinatesOutOfRange => {
+                "the destination coordinates are out of range of the image"
+            },
+            CmdBlitImageError::OverlappingRegions => {
+                "the source and destination regions are overlapping in memory"
+            },
         }
     }
 }
@@ -156,3 +384,77 @@ impl fmt::Display for CmdBlitImageError {
         write!(fmt, "{}", error::Error::description(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::i32;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBufferBuilder;
+    use command_buffer::commands_raw::CmdBlitImage;
+    use command_buffer::commands_raw::CmdBlitImageError;
+    use format::R8G8B8A8Unorm;
+    use format::R8G8B8A8Uint;
+    use image::StorageImage;
+    use image::Dimensions;
+    use mock;
+    use sampler::Filter;
+
+    #[test]
+    fn scaled_blit() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let a = StorageImage::new(&device, Dimensions::Dim2d { width: 32, height: 32 },
+                                  R8G8B8A8Unorm, Some(queue.family())).unwrap();
+        let b = StorageImage::new(&device, Dimensions::Dim2d { width: 16, height: 16 },
+                                  R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        let _cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .blit_image(a.clone(), [0, 0, 0], [32, 32, 1], 0, 0, b.clone(), [16, 0, 0],
+                        [0, 16, 1], 0, 0, 1, Filter::Linear)
+            .unwrap();
+
+        let calls = mock::calls(&device);
+        assert!(calls.iter().any(|c| *c == "CmdBlitImage"));
+    }
+
+    #[test]
+    fn integer_to_float_rejected() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let a = StorageImage::new(&device, Dimensions::Dim2d { width: 32, height: 32 },
+                                  R8G8B8A8Uint, Some(queue.family())).unwrap();
+        let b = StorageImage::new(&device, Dimensions::Dim2d { width: 32, height: 32 },
+                                  R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        match CmdBlitImage::new(a.clone(), [0, 0, 0], [32, 32, 1], 0, 0, b.clone(), [0, 0, 0],
+                                [32, 32, 1], 0, 0, 1, Filter::Nearest)
+        {
+            Err(CmdBlitImageError::IncompatibleFormatsTypes) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn corners_too_far_apart() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let a = StorageImage::new(&device, Dimensions::Dim2d { width: 32, height: 32 },
+                                  R8G8B8A8Unorm, Some(queue.family())).unwrap();
+        let b = StorageImage::new(&device, Dimensions::Dim2d { width: 32, height: 32 },
+                                  R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        match CmdBlitImage::new(a.clone(), [i32::MAX, 0, 0], [i32::MIN, 32, 1], 0, 0, b.clone(),
+                                [0, 0, 0], [32, 32, 1], 0, 0, 1, Filter::Nearest)
+        {
+            Err(CmdBlitImageError::SourceCoordinatesOutOfRange) => (),
+            _ => panic!()
+        }
+
+        match CmdBlitImage::new(a.clone(), [0, 0, 0], [32, 32, 1], 0, 0, b.clone(),
+                                [0, i32::MIN, 0], [32, i32::MAX, 1], 0, 0, 1, Filter::Nearest)
+        {
+            Err(CmdBlitImageError::DestinationCoordinatesOutOfRange) => (),
+            _ => panic!()
+        }
+    }
+}
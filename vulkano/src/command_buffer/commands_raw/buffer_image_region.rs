@@ -0,0 +1,266 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::cmp;
+
+use buffer::TypedBuffer;
+use command_buffer::commands_raw::region_in_range;
+use image::ImageAccess;
+use image::ImageAspect;
+use image::ImageSubresourceRange;
+use memory::Content;
+use vk;
+
+/// A region to copy between a buffer and an image.
+///
+/// The texels of the region are stored in the buffer row by row, then depth slice by depth slice,
+/// then array layer by array layer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BufferImageRegion {
+    /// Offset in bytes of the first texel of the region in the buffer.
+    pub buffer_offset: usize,
+    /// Number of texels between the start of two consecutive rows in the buffer. `0` means that
+    /// the rows are tightly packed.
+    pub buffer_row_length: u32,
+    /// Number of rows between the start of two consecutive depth slices or array layers in the
+    /// buffer. `0` means that they are tightly packed.
+    pub buffer_image_height: u32,
+    /// Aspect of the image to copy.
+    pub image_aspect: ImageAspect,
+    /// Mipmap level of the image to copy.
+    pub image_mip_level: u32,
+    /// First array layer of the image to copy.
+    pub image_base_array_layer: u32,
+    /// Number of array layers to copy.
+    pub image_layer_count: u32,
+    /// Coordinates of the first texel of the region in the image.
+    pub image_offset: [u32; 3],
+    /// Width, height and depth of the region.
+    pub image_extent: [u32; 3],
+}
+
+impl BufferImageRegion {
+    /// Builds a region that covers all the array layers of a mipmap level of `image`, tightly
+    /// packed at the start of the buffer.
+    ///
+    /// The color aspect is used for color images, and the depth aspect is used for depth and
+    /// depth-stencil images.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the image doesn't have this mipmap level.
+    ///
+    pub fn whole_level<I>(image: &I, mip_level: u32) -> BufferImageRegion
+        where I: ImageAccess
+    {
+        let dims = image.dimensions();
+        let extent = dims.mipmap_width_height_depth(mip_level)
+                         .expect("The image doesn't have this mipmap level");
+
+        BufferImageRegion {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_aspect: default_aspect(image),
+            image_mip_level: mip_level,
+            image_base_array_layer: 0,
+            image_layer_count: dims.array_layers(),
+            image_offset: [0, 0, 0],
+            image_extent: extent,
+        }
+    }
+}
+
+// Returns the aspect that is copied when the user doesn't specify one.
+pub fn default_aspect<I>(image: &I) -> ImageAspect
+    where I: ImageAccess
+{
+    if image.has_color() {
+        ImageAspect::Color
+    } else if image.has_depth() {
+        ImageAspect::Depth
+    } else {
+        ImageAspect::Stencil
+    }
+}
+
+// Returns the smallest range of subresources that contains all the regions. `regions` must not be
+// empty.
+pub fn regions_subresource_range(regions: &[BufferImageRegion]) -> ImageSubresourceRange {
+    debug_assert!(!regions.is_empty());
+
+    let mut mips = u32::max_value() .. 0;
+    let mut layers = u32::max_value() .. 0;
+
+    for region in regions {
+        mips.start = cmp::min(mips.start, region.image_mip_level);
+        mips.end = cmp::max(mips.end, region.image_mip_level + 1);
+        layers.start = cmp::min(layers.start, region.image_base_array_layer);
+        layers.end = cmp::max(layers.end,
+                              region.image_base_array_layer + region.image_layer_count);
+    }
+
+    ImageSubresourceRange::new(mips, layers)
+}
+
+// Error that can happen when checking a `BufferImageRegion`. Converted to the error type of each
+// command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RegionError {
+    EmptyRegion,
+    WrongAspect,
+    OutOfImageRange,
+    InvalidRowLength,
+    UnalignedToBlocks,
+    UnalignedBufferOffset,
+    BufferTooSmall,
+    OverlappingRanges,
+    WrongContentFormat,
+}
+
+// Checks `region` against `buffer` and `image`.
+pub fn check_region<B, I>(buffer: &B, image: &I, region: &BufferImageRegion)
+                          -> Result<(), RegionError>
+    where B: TypedBuffer, B::Content: Content, I: ImageAccess
+{
+    let extent = region.image_extent;
+    if extent[0] == 0 || extent[1] == 0 || extent[2] == 0 || region.image_layer_count == 0 {
+        return Err(RegionError::EmptyRegion);
+    }
+
+    let has_aspect = match region.image_aspect {
+        ImageAspect::Color => image.has_color(),
+        ImageAspect::Depth => image.has_depth(),
+        ImageAspect::Stencil => image.has_stencil(),
+    };
+    if !has_aspect {
+        return Err(RegionError::WrongAspect);
+    }
+
+    let offset = region.image_offset;
+    if !region_in_range(image, [offset[0] as i32, offset[1] as i32, offset[2] as i32], extent,
+                        region.image_base_array_layer, region.image_mip_level,
+                        region.image_layer_count)
+    {
+        return Err(RegionError::OutOfImageRange);
+    }
+
+    if (region.buffer_row_length != 0 && region.buffer_row_length < extent[0]) ||
+       (region.buffer_image_height != 0 && region.buffer_image_height < extent[1])
+    {
+        return Err(RegionError::InvalidRowLength);
+    }
+
+    // For compressed formats, the region must be made of whole blocks. The extent can only stop
+    // in the middle of a block at the edge of the image.
+    let block = image.format().block_dimensions();
+    if block != [1, 1] {
+        let dims = image.dimensions().mipmap_width_height_depth(region.image_mip_level).unwrap();
+        for i in 0 .. 2 {
+            if offset[i] % block[i] != 0 ||
+               (extent[i] % block[i] != 0 && offset[i] + extent[i] != dims[i])
+            {
+                return Err(RegionError::UnalignedToBlocks);
+            }
+        }
+        if region.buffer_row_length % block[0] != 0 || region.buffer_image_height % block[1] != 0 {
+            return Err(RegionError::UnalignedToBlocks);
+        }
+    }
+
+    // The offset must be a multiple of 4 and, for color images, of the size of a texel or of a
+    // block. This includes the offset of the buffer slice within the buffer.
+    let texel_size = region.image_aspect.buffer_texel_size(image.format());
+
+    // The elements of the content of the buffer must be made of whole texels or blocks, or the
+    // texels of whole elements. For example a buffer of `u8` or of `[u8; 4]` matches a
+    // `R8G8B8A8Unorm` image, but a buffer of `[u8; 3]` doesn't.
+    if let Some(texel_size) = texel_size {
+        let content_size = <B::Content as Content>::indiv_size();
+        if content_size == 0 ||
+           (texel_size % content_size != 0 && content_size % texel_size != 0)
+        {
+            return Err(RegionError::WrongContentFormat);
+        }
+    }
+
+    let buffer_offset = buffer.inner().offset + region.buffer_offset;
+    if buffer_offset % 4 != 0 {
+        return Err(RegionError::UnalignedBufferOffset);
+    }
+    if let (ImageAspect::Color, Some(texel_size)) = (region.image_aspect, texel_size) {
+        if buffer_offset % texel_size != 0 {
+            return Err(RegionError::UnalignedBufferOffset);
+        }
+    }
+
+    // The size of the region in the buffer is computed in blocks, which are single texels for
+    // uncompressed formats.
+    if let Some(texel_size) = texel_size {
+        let blocks = |texels: u32, block: u32| ((texels + block - 1) / block) as usize;
+        let width = blocks(extent[0], block[0]);
+        let height = blocks(extent[1], block[1]);
+        let row_length = if region.buffer_row_length == 0 {
+            width
+        } else {
+            blocks(region.buffer_row_length, block[0])
+        };
+        let image_height = if region.buffer_image_height == 0 {
+            height
+        } else {
+            blocks(region.buffer_image_height, block[1])
+        };
+        let slices = extent[2] as usize * region.image_layer_count as usize;
+
+        let num_blocks = ((slices - 1) * image_height + height - 1) * row_length + width;
+        match region.buffer_offset.checked_add(num_blocks * texel_size) {
+            Some(end) if end <= buffer.size() => (),
+            _ => return Err(RegionError::BufferTooSmall),
+        }
+    }
+
+    if buffer.conflicts_image(region.buffer_offset,
+                              buffer.size().saturating_sub(region.buffer_offset), image,
+                              region.image_base_array_layer, region.image_layer_count,
+                              region.image_mip_level, 1)
+    {
+        return Err(RegionError::OverlappingRanges);
+    }
+
+    Ok(())
+}
+
+// Turns a region that has been checked with `check_region` into a Vulkan region. `buffer_offset`
+// is the offset of the buffer slice within the buffer.
+pub fn vk_region(region: &BufferImageRegion, buffer_offset: usize) -> vk::BufferImageCopy {
+    let offset = region.image_offset;
+    let extent = region.image_extent;
+
+    vk::BufferImageCopy {
+        bufferOffset: (buffer_offset + region.buffer_offset) as vk::DeviceSize,
+        bufferRowLength: region.buffer_row_length,
+        bufferImageHeight: region.buffer_image_height,
+        imageSubresource: vk::ImageSubresourceLayers {
+            aspectMask: region.image_aspect.to_aspect_bits(),
+            mipLevel: region.image_mip_level,
+            baseArrayLayer: region.image_base_array_layer,
+            layerCount: region.image_layer_count,
+        },
+        imageOffset: vk::Offset3D {
+            x: offset[0] as i32,
+            y: offset[1] as i32,
+            z: offset[2] as i32,
+        },
+        imageExtent: vk::Extent3D {
+            width: extent[0],
+            height: extent[1],
+            depth: extent[2],
+        },
+    }
+}
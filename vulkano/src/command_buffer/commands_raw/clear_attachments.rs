@@ -7,12 +7,16 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::error;
+use std::fmt;
 use smallvec::SmallVec;
 
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::UnsafeCommandBufferBuilder;
 use command_buffer::pool::CommandPool;
 use device::DeviceOwned;
+use format::ClearValue;
 use VulkanObject;
 use VulkanPointers;
 use vk;
@@ -20,12 +24,118 @@ use vk;
 /// Command that clears framebuffer attachments of the current render pass.
 pub struct CmdClearAttachments {
     // The attachments to clear.
-    attachments: SmallVec<[vk::ClearAttachment; 8]>,
+    attachments: SmallVec<[ClearAttachment; 8]>,
+    // Same as `attachments`, but in the format expected by Vulkan.
+    raw_attachments: SmallVec<[vk::ClearAttachment; 8]>,
     // The rectangles to clear.
-    rects: SmallVec<[vk::ClearRect; 4]>,
+    rects: SmallVec<[ClearRect; 4]>,
+    // Same as `rects`, but in the format expected by Vulkan.
+    raw_rects: SmallVec<[vk::ClearRect; 4]>,
 }
 
-// TODO: add constructor
+impl CmdClearAttachments {
+    /// Builds a new command that clears the given attachments of the current subpass, in each of
+    /// the given rectangles.
+    ///
+    /// The attachments and the rectangles are checked against the current subpass and
+    /// framebuffer when the command is added to a command buffer builder.
+    pub fn new<A, R>(attachments: A, rects: R)
+                     -> Result<CmdClearAttachments, CmdClearAttachmentsError>
+        where A: IntoIterator<Item = ClearAttachment>,
+              R: IntoIterator<Item = ClearRect>
+    {
+        let attachments: SmallVec<[ClearAttachment; 8]> = attachments.into_iter().collect();
+        let rects: SmallVec<[ClearRect; 4]> = rects.into_iter().collect();
+
+        let raw_attachments = try!(attachments.iter().map(|&attachment| {
+            Ok(match attachment {
+                ClearAttachment::Color(index, value) => {
+                    let value = match value {
+                        ClearValue::Float(val) => vk::ClearColorValue::float32(val),
+                        ClearValue::Int(val) => vk::ClearColorValue::int32(val),
+                        ClearValue::Uint(val) => vk::ClearColorValue::uint32(val),
+                        _ => return Err(CmdClearAttachmentsError::InvalidColorClearValue),
+                    };
+
+                    vk::ClearAttachment {
+                        aspectMask: vk::IMAGE_ASPECT_COLOR_BIT,
+                        colorAttachment: index,
+                        clearValue: vk::ClearValue::color(value),
+                    }
+                },
+                ClearAttachment::Depth(depth) => {
+                    vk::ClearAttachment {
+                        aspectMask: vk::IMAGE_ASPECT_DEPTH_BIT,
+                        colorAttachment: 0,
+                        clearValue: vk::ClearValue::depth_stencil(vk::ClearDepthStencilValue {
+                            depth: depth, stencil: 0
+                        }),
+                    }
+                },
+                ClearAttachment::Stencil(stencil) => {
+                    vk::ClearAttachment {
+                        aspectMask: vk::IMAGE_ASPECT_STENCIL_BIT,
+                        colorAttachment: 0,
+                        clearValue: vk::ClearValue::depth_stencil(vk::ClearDepthStencilValue {
+                            depth: 0.0, stencil: stencil
+                        }),
+                    }
+                },
+                ClearAttachment::DepthStencil((depth, stencil)) => {
+                    vk::ClearAttachment {
+                        aspectMask: vk::IMAGE_ASPECT_DEPTH_BIT | vk::IMAGE_ASPECT_STENCIL_BIT,
+                        colorAttachment: 0,
+                        clearValue: vk::ClearValue::depth_stencil(vk::ClearDepthStencilValue {
+                            depth: depth, stencil: stencil
+                        }),
+                    }
+                },
+            })
+        }).collect::<Result<SmallVec<[_; 8]>, _>>());
+
+        if raw_attachments.is_empty() {
+            return Err(CmdClearAttachmentsError::NoAttachments);
+        }
+
+        let raw_rects = try!(rects.iter().map(|rect| {
+            if rect.extent[0] == 0 || rect.extent[1] == 0 || rect.layer_count == 0 {
+                return Err(CmdClearAttachmentsError::EmptyRect);
+            }
+
+            Ok(vk::ClearRect {
+                rect: vk::Rect2D {
+                    offset: vk::Offset2D { x: rect.offset[0], y: rect.offset[1] },
+                    extent: vk::Extent2D { width: rect.extent[0], height: rect.extent[1] },
+                },
+                baseArrayLayer: rect.base_array_layer,
+                layerCount: rect.layer_count,
+            })
+        }).collect::<Result<SmallVec<[_; 4]>, _>>());
+
+        if raw_rects.is_empty() {
+            return Err(CmdClearAttachmentsError::NoRects);
+        }
+
+        Ok(CmdClearAttachments {
+            attachments: attachments,
+            raw_attachments: raw_attachments,
+            rects: rects,
+            raw_rects: raw_rects,
+        })
+    }
+
+    /// Returns the attachments to clear.
+    #[inline]
+    pub fn attachments(&self) -> &[ClearAttachment] {
+        &self.attachments
+    }
+
+    /// Returns the rectangles to clear.
+    #[inline]
+    pub fn rects(&self) -> &[ClearRect] {
+        &self.rects
+    }
+}
 
 unsafe impl<'a, P> AddCommand<&'a CmdClearAttachments> for UnsafeCommandBufferBuilder<P>
     where P: CommandPool
@@ -33,16 +143,182 @@ unsafe impl<'a, P> AddCommand<&'a CmdClearAttachments> for UnsafeCommandBufferBu
     type Out = UnsafeCommandBufferBuilder<P>;
 
     #[inline]
-    fn add(self, command: &'a CmdClearAttachments) -> Self::Out {
+    fn add(self, command: &'a CmdClearAttachments) -> Result<Self::Out, CommandAddError> {
         unsafe {
             let vk = self.device().pointers();
             let cmd = self.internal_object();
 
-            vk.CmdClearAttachments(cmd, command.attachments.len() as u32,
-                                   command.attachments.as_ptr(), command.rects.len() as u32,
-                                   command.rects.as_ptr());
+            vk.CmdClearAttachments(cmd, command.raw_attachments.len() as u32,
+                                   command.raw_attachments.as_ptr(),
+                                   command.raw_rects.len() as u32, command.raw_rects.as_ptr());
+        }
+
+        Ok(self)
+    }
+}
+
+/// An attachment of the current subpass to clear.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ClearAttachment {
+    /// Clears the color attachment with the given index in the list of color attachments of the
+    /// current subpass. The value must be a `Float`, `Int` or `Uint` value.
+    Color(u32, ClearValue),
+    /// Clears the depth component of the depth-stencil attachment.
+    Depth(f32),
+    /// Clears the stencil component of the depth-stencil attachment.
+    Stencil(u32),
+    /// Clears both components of the depth-stencil attachment.
+    DepthStencil((f32, u32)),
+}
+
+/// A rectangle of the framebuffer to clear.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ClearRect {
+    /// Coordinates of the top-left corner of the rectangle.
+    pub offset: [i32; 2],
+    /// Width and height of the rectangle.
+    pub extent: [u32; 2],
+    /// First layer of the framebuffer to clear.
+    pub base_array_layer: u32,
+    /// Number of layers of the framebuffer to clear.
+    pub layer_count: u32,
+}
+
+/// Error that can happen when creating a `CmdClearAttachments`.
+#[derive(Debug, Copy, Clone)]
+pub enum CmdClearAttachmentsError {
+    /// No attachment was passed.
+    NoAttachments,
+    /// No rectangle was passed.
+    NoRects,
+    /// One of the rectangles has a width, height or number of layers of zero.
+    EmptyRect,
+    /// The value used to clear a color attachment is not a color value.
+    InvalidColorClearValue,
+}
+
+impl error::Error for CmdClearAttachmentsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CmdClearAttachmentsError::NoAttachments => {
+                "no attachment was passed"
+            },
+            CmdClearAttachmentsError::NoRects => {
+                "no rectangle was passed"
+            },
+            CmdClearAttachmentsError::EmptyRect => {
+                "one of the rectangles has a width, height or number of layers of zero"
+            },
+            CmdClearAttachmentsError::InvalidColorClearValue => {
+                "the value used to clear a color attachment is not a color value"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CmdClearAttachmentsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandAddError;
+    use command_buffer::CommandBufferBuilder;
+    use command_buffer::CommandBufferBuilderError;
+    use command_buffer::commands_raw::ClearAttachment;
+    use command_buffer::commands_raw::ClearRect;
+    use command_buffer::commands_raw::CmdClearAttachments;
+    use command_buffer::commands_raw::CmdClearAttachmentsError;
+    use format::ClearValue;
+    use framebuffer::EmptySinglePassRenderPassDesc;
+    use framebuffer::Framebuffer;
+    use framebuffer::RenderPass;
+
+    const RECT: ClearRect = ClearRect {
+        offset: [0, 0],
+        extent: [32, 32],
+        base_array_layer: 0,
+        layer_count: 1,
+    };
+
+    #[test]
+    fn invalid_color_value() {
+        match CmdClearAttachments::new(Some(ClearAttachment::Color(0, ClearValue::Depth(1.0))),
+                                       Some(RECT))
+        {
+            Err(CmdClearAttachmentsError::InvalidColorClearValue) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn empty_rect() {
+        let rect = ClearRect { extent: [0, 32], .. RECT };
+        match CmdClearAttachments::new(Some(ClearAttachment::Depth(1.0)), Some(rect)) {
+            Err(CmdClearAttachmentsError::EmptyRect) => (),
+            _ => panic!()
         }
+    }
+
+    #[test]
+    fn outside_render_pass() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        match cb.clear_attachments(Some(ClearAttachment::Depth(1.0)), Some(RECT)) {
+            Err(CommandBufferBuilderError::CommandAddError(err)) => {
+                assert_eq!(err, CommandAddError::ForbiddenOutsideRenderPass);
+            },
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn attachment_not_in_subpass() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let rp = Arc::new(RenderPass::new(device.clone(), EmptySinglePassRenderPassDesc).unwrap());
+        let framebuffer = Framebuffer::new(rp, [32, 32, 1], ()).unwrap();
 
-        self
+        let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .begin_render_pass(framebuffer, false, ()).unwrap();
+        let attachment = ClearAttachment::Color(0, ClearValue::Float([0.0; 4]));
+        match cb.clear_attachments(Some(attachment), Some(RECT)) {
+            Err(CommandBufferBuilderError::CommandAddError(err)) => {
+                assert_eq!(err, CommandAddError::ClearAttachmentNotInSubpass);
+            },
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn rect_out_of_range() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let rp = Arc::new(RenderPass::new(device.clone(), EmptySinglePassRenderPassDesc).unwrap());
+        let framebuffer = Framebuffer::new(rp, [32, 32, 1], ()).unwrap();
+
+        let rects = vec![
+            ClearRect { offset: [16, 0], .. RECT },
+            ClearRect { offset: [-1, 0], extent: [1, 1], .. RECT },
+            ClearRect { layer_count: 2, .. RECT },
+        ];
+
+        for rect in rects {
+            let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+                .begin_render_pass(framebuffer.clone(), false, ()).unwrap();
+            match cb.clear_attachments(Some(ClearAttachment::Depth(1.0)), Some(rect)) {
+                Err(CommandBufferBuilderError::CommandAddError(err)) => {
+                    assert_eq!(err, CommandAddError::ClearRectOutOfRange);
+                },
+                _ => panic!()
+            }
+        }
     }
 }
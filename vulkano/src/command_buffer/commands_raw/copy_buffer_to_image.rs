@@ -10,13 +10,25 @@
 use std::error;
 use std::fmt;
 use std::sync::Arc;
-use buffer::BufferAccess;
+use smallvec::SmallVec;
+
+use buffer::TypedBuffer;
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::UnsafeCommandBufferBuilder;
+use command_buffer::commands_raw::BufferImageRegion;
+use command_buffer::commands_raw::buffer_image_region::RegionError;
+use command_buffer::commands_raw::buffer_image_region::check_region;
+use command_buffer::commands_raw::buffer_image_region::regions_subresource_range;
+use command_buffer::commands_raw::buffer_image_region::vk_region;
+use command_buffer::commands_raw::buffer_image_region::default_aspect;
 use command_buffer::pool::CommandPool;
 use device::Device;
 use device::DeviceOwned;
 use image::ImageAccess;
+use image::ImageSubresourceRange;
+use image::Layout;
+use memory::Content;
 use VulkanObject;
 use VulkanPointers;
 use vk;
@@ -28,47 +40,65 @@ pub struct CmdCopyBufferToImage<S, D> {
     buffer: S,
     // Raw source buffer.
     buffer_raw: vk::Buffer,
-    // Offset in the source.
-    buffer_offset: vk::DeviceSize,
-    buffer_row_length: u32,
-    buffer_image_height: u32,
+    // Offset of the buffer slice within the raw buffer.
+    buffer_offset: usize,
     // The destination image.
     destination: D,
     // Raw destination image.
     destination_raw: vk::Image,
     // Layout of the destination image.
-    destination_layout: vk::ImageLayout,
-    // Offset in the destination.
-    destination_offset: [i32; 3],
-    destination_aspect_mask: vk::ImageAspectFlags,
-    destination_mip_level: u32,
-    destination_base_array_layer: u32,
-    destination_layer_count: u32,
-    // Size.
-    extent: [u32; 3],
+    destination_layout: Layout,
+    // The regions to copy.
+    regions: SmallVec<[BufferImageRegion; 4]>,
 }
 
-impl<S, D> CmdCopyBufferToImage<S, D> where S: BufferAccess, D: ImageAccess {
+impl<S, D> CmdCopyBufferToImage<S, D>
+    where S: TypedBuffer, S::Content: Content, D: ImageAccess
+{
+    /// Builds a command that copies the start of the buffer to all the array layers of the first
+    /// mipmap level of the image.
+    ///
+    /// The color aspect is written for color images, and the depth aspect is written for depth
+    /// and depth-stencil images. Use `with_regions` to write the stencil aspect.
     #[inline]
     pub fn new(source: S, destination: D)
                -> Result<CmdCopyBufferToImage<S, D>, CmdCopyBufferToImageError>
     {
-        let dims = destination.dimensions().width_height_depth();
-        CmdCopyBufferToImage::with_dimensions(source, destination, [0, 0, 0], dims, 0, 1, 0)
+        let region = BufferImageRegion::whole_level(&destination, 0);
+        CmdCopyBufferToImage::with_regions(source, destination, Some(region))
     }
 
+    /// Builds a command that copies the start of the buffer to a single region of the image.
+    ///
+    /// The aspect is chosen the same way as `new`.
     pub fn with_dimensions(source: S, destination: D, offset: [u32; 3], size: [u32; 3],
                            first_layer: u32, num_layers: u32, mipmap: u32)
                            -> Result<CmdCopyBufferToImage<S, D>, CmdCopyBufferToImageError>
     {
-        // FIXME: check buffer content format
-        // FIXME: check that the buffer is large enough
-        // FIXME: check image dimensions
+        let region = BufferImageRegion {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_aspect: default_aspect(&destination),
+            image_mip_level: mipmap,
+            image_base_array_layer: first_layer,
+            image_layer_count: num_layers,
+            image_offset: offset,
+            image_extent: size,
+        };
+
+        CmdCopyBufferToImage::with_regions(source, destination, Some(region))
+    }
 
+    /// Builds a command that copies each of the given regions of the buffer to the image.
+    pub fn with_regions<R>(source: S, destination: D, regions: R)
+                           -> Result<CmdCopyBufferToImage<S, D>, CmdCopyBufferToImageError>
+        where R: IntoIterator<Item = BufferImageRegion>
+    {
         assert_eq!(source.inner().buffer.device().internal_object(),
                    destination.inner().device().internal_object());
 
-        let (source_raw, src_offset) = {
+        let (source_raw, source_offset) = {
             let inner = source.inner();
             if !inner.buffer.usage_transfer_src() {
                 return Err(CmdCopyBufferToImageError::SourceMissingTransferUsage);
@@ -88,36 +118,34 @@ impl<S, D> CmdCopyBufferToImage<S, D> where S: BufferAccess, D: ImageAccess {
             inner.internal_object()
         };
 
-        if source.conflicts_image(0, source.size(), &destination, first_layer, num_layers,
-                                  mipmap, 1)
-        {
-            return Err(CmdCopyBufferToImageError::OverlappingRanges);
-        } else {
-            debug_assert!(!destination.conflicts_buffer(first_layer, num_layers, mipmap,
-                                                        1, &source, 0, source.size()));
-        }
+        let regions = try!(regions.into_iter().map(|region| {
+            check_region(&source, &destination, &region).map(|()| region).map_err(|err| match err {
+                RegionError::EmptyRegion => CmdCopyBufferToImageError::EmptyRegion,
+                RegionError::WrongAspect => CmdCopyBufferToImageError::WrongAspect,
+                RegionError::OutOfImageRange => CmdCopyBufferToImageError::OutOfImageRange,
+                RegionError::InvalidRowLength => CmdCopyBufferToImageError::InvalidRowLength,
+                RegionError::UnalignedToBlocks => CmdCopyBufferToImageError::UnalignedToBlocks,
+                RegionError::UnalignedBufferOffset => {
+                    CmdCopyBufferToImageError::UnalignedBufferOffset
+                },
+                RegionError::BufferTooSmall => CmdCopyBufferToImageError::BufferTooSmall,
+                RegionError::OverlappingRanges => CmdCopyBufferToImageError::OverlappingRanges,
+                RegionError::WrongContentFormat => CmdCopyBufferToImageError::WrongContentFormat,
+            })
+        }).collect::<Result<SmallVec<[_; 4]>, _>>());
 
-        let aspect_mask = if destination.has_color() {
-            vk::IMAGE_ASPECT_COLOR_BIT
-        } else {
-            unimplemented!()        // TODO:
-        };
+        if regions.is_empty() {
+            return Err(CmdCopyBufferToImageError::NoRegions);
+        }
 
         Ok(CmdCopyBufferToImage {
             buffer: source,
             buffer_raw: source_raw,
-            buffer_offset: src_offset as vk::DeviceSize,
-            buffer_row_length: 0,
-            buffer_image_height: 0,
+            buffer_offset: source_offset,
             destination: destination,
             destination_raw: destination_raw,
-            destination_layout: vk::IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL,      // FIXME:
-            destination_offset: [offset[0] as i32, offset[1] as i32, offset[2] as i32],
-            destination_aspect_mask: aspect_mask,
-            destination_mip_level: mipmap,
-            destination_base_array_layer: first_layer,
-            destination_layer_count: num_layers,
-            extent: size,
+            destination_layout: Layout::TransferDstOptimal,
+            regions: regions,
         })
     }
 }
@@ -134,6 +162,32 @@ impl<S, D> CmdCopyBufferToImage<S, D> {
     pub fn destination(&self) -> &D {
         &self.destination
     }
+
+    /// Returns the layout that the destination image must be in when the command is executed.
+    #[inline]
+    pub fn destination_layout(&self) -> Layout {
+        self.destination_layout
+    }
+
+    /// Sets the layout that the destination image is in when the command is executed. The
+    /// default is `TransferDstOptimal`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `layout` is neither `TransferDstOptimal` nor `General`.
+    ///
+    #[inline]
+    pub fn set_destination_layout(&mut self, layout: Layout) {
+        assert!(layout == Layout::TransferDstOptimal || layout == Layout::General,
+                "The destination of a copy must be in the TransferDstOptimal or General layout");
+        self.destination_layout = layout;
+    }
+
+    /// Returns the range of subresources of the destination image that are accessed by the command.
+    #[inline]
+    pub fn destination_subresource_range(&self) -> ImageSubresourceRange {
+        regions_subresource_range(&self.regions)
+    }
 }
 
 unsafe impl<S, D> DeviceOwned for CmdCopyBufferToImage<S, D> where S: DeviceOwned {
@@ -149,40 +203,20 @@ unsafe impl<'a, P, S, D> AddCommand<&'a CmdCopyBufferToImage<S, D>> for UnsafeCo
     type Out = UnsafeCommandBufferBuilder<P>;
 
     #[inline]
-    fn add(self, command: &'a CmdCopyBufferToImage<S, D>) -> Self::Out {
+    fn add(self, command: &'a CmdCopyBufferToImage<S, D>) -> Result<Self::Out, CommandAddError> {
         unsafe {
-            debug_assert!(command.destination_layout == vk::IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL ||
-                          command.destination_layout == vk::IMAGE_LAYOUT_GENERAL);
-
-            let region = vk::BufferImageCopy {
-                bufferOffset: command.buffer_offset,
-                bufferRowLength: command.buffer_row_length,
-                bufferImageHeight: command.buffer_image_height,
-                imageSubresource: vk::ImageSubresourceLayers {
-                    aspectMask: command.destination_aspect_mask,
-                    mipLevel: command.destination_mip_level,
-                    baseArrayLayer: command.destination_base_array_layer,
-                    layerCount: command.destination_layer_count,
-                },
-                imageOffset: vk::Offset3D {
-                    x: command.destination_offset[0],
-                    y: command.destination_offset[1],
-                    z: command.destination_offset[2],
-                },
-                imageExtent: vk::Extent3D {
-                    width: command.extent[0],
-                    height: command.extent[1],
-                    depth: command.extent[2],
-                },
-            };
+            let regions = command.regions.iter()
+                                 .map(|region| vk_region(region, command.buffer_offset))
+                                 .collect::<SmallVec<[_; 4]>>();
 
             let vk = self.device().pointers();
             let cmd = self.internal_object();
             vk.CmdCopyBufferToImage(cmd, command.buffer_raw, command.destination_raw,
-                                    command.destination_layout, 1, &region as *const _);
+                                    command.destination_layout as u32, regions.len() as u32,
+                                    regions.as_ptr());
         }
 
-        self
+        Ok(self)
     }
 }
 
@@ -195,10 +229,28 @@ pub enum CmdCopyBufferToImageError {
     DestinationMissingTransferUsage,
     /// The destination image has more than one sample per pixel.
     DestinationMultisampled,
+    /// No region was passed.
+    NoRegions,
+    /// One of the regions has a width, height, depth or number of layers of zero.
+    EmptyRegion,
+    /// The destination image doesn't have the aspect of one of the regions.
+    WrongAspect,
     /// The dimensions are out of range of the image.
     OutOfImageRange,
+    /// The row length or the image height of one of the regions is smaller than its extent.
+    InvalidRowLength,
+    /// The image has a compressed format, and one of the regions isn't made of whole blocks of
+    /// texels.
+    UnalignedToBlocks,
+    /// The offset of one of the regions in the buffer isn't a multiple of 4 or of the size of a
+    /// texel.
+    UnalignedBufferOffset,
+    /// The source buffer is too small for one of the regions.
+    BufferTooSmall,
     /// The source and destination are overlapping in memory.
     OverlappingRanges,
+    /// The type of the content of the source buffer doesn't match the format of the image.
+    WrongContentFormat,
 }
 
 impl error::Error for CmdCopyBufferToImageError {
@@ -214,12 +266,40 @@ impl error::Error for CmdCopyBufferToImageError {
             CmdCopyBufferToImageError::DestinationMultisampled => {
                 "the destination image has more than one sample per pixel"
             },
+            CmdCopyBufferToImageError::NoRegions => {
+                "no region was passed"
+            },
+            CmdCopyBufferToImageError::EmptyRegion => {
+                "one of the regions has a width, height, depth or number of layers of zero"
+            },
+            CmdCopyBufferToImageError::WrongAspect => {
+                "the destination image doesn't have the aspect of one of the regions"
+            },
             CmdCopyBufferToImageError::OutOfImageRange => {
                 "the dimensions are out of range of the image"
             },
+            CmdCopyBufferToImageError::InvalidRowLength => {
+                "the row length or the image height of one of the regions is smaller than its \
+                 extent"
+            },
+            CmdCopyBufferToImageError::UnalignedToBlocks => {
+                "the image has a compressed format, and one of the regions isn't made of whole \
+                 blocks of texels"
+            },
+            CmdCopyBufferToImageError::UnalignedBufferOffset => {
+                "the offset of one of the regions in the buffer isn't a multiple of 4 or of the \
+                 size of a texel"
+            },
+            CmdCopyBufferToImageError::BufferTooSmall => {
+                "the source buffer is too small for one of the regions"
+            },
             CmdCopyBufferToImageError::OverlappingRanges => {
                 "the source and destination are overlapping in memory"
             },
+            CmdCopyBufferToImageError::WrongContentFormat => {
+                "the type of the content of the source buffer doesn't match the format of the \
+                 image"
+            },
         }
     }
 }
@@ -230,3 +310,227 @@ impl fmt::Display for CmdCopyBufferToImageError {
         write!(fmt, "{}", error::Error::description(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBufferBuilder;
+    use command_buffer::cb::AddCommand;
+    use command_buffer::commands_raw::BufferImageRegion;
+    use command_buffer::commands_raw::CmdCopyBufferToImage;
+    use command_buffer::commands_raw::CmdCopyBufferToImageError;
+    use format::Format;
+    use image::Dimensions;
+    use image::ImageAspect;
+    use image::ImmutableImage;
+    use image::Layout;
+    use image::StorageImage;
+    use mock;
+
+    fn region(aspect: ImageAspect) -> BufferImageRegion {
+        BufferImageRegion {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_aspect: aspect,
+            image_mip_level: 0,
+            image_base_array_layer: 0,
+            image_layer_count: 1,
+            image_offset: [0, 0, 0],
+            image_extent: [16, 16, 1],
+        }
+    }
+
+    #[test]
+    fn array_layers_and_row_length() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_iter(&device, &BufferUsage::transfer_source(),
+                                                    Some(queue.family()),
+                                                    (0 .. 32 * 16 * 3).map(|_| 0u32)).unwrap();
+        let image = StorageImage::new(&device, Dimensions::Dim2dArray { width: 16, height: 16,
+                                                                        array_layers: 3 },
+                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        let regions = vec![
+            BufferImageRegion { buffer_row_length: 32, image_layer_count: 3,
+                                .. region(ImageAspect::Color) },
+        ];
+
+        let _cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .copy_buffer_to_image_regions(buffer, image, regions)
+            .unwrap();
+
+        let calls = mock::calls(&device);
+        assert!(calls.contains(&"CmdCopyBufferToImage"));
+    }
+
+    #[test]
+    fn wrong_content_format() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        // Elements of 3 bytes don't match texels of 4 bytes.
+        let buffer = CpuAccessibleBuffer::from_iter(&device, &BufferUsage::transfer_source(),
+                                                    Some(queue.family()),
+                                                    (0 .. 16 * 16 * 2).map(|_| [0u8; 3])).unwrap();
+        let image = StorageImage::new(&device, Dimensions::Dim2d { width: 16, height: 16 },
+                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        match CmdCopyBufferToImage::new(buffer, image) {
+            Err(CmdCopyBufferToImageError::WrongContentFormat) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn general_layout() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_iter(&device, &BufferUsage::transfer_source(),
+                                                    Some(queue.family()),
+                                                    (0 .. 16 * 16).map(|_| [0u8; 4])).unwrap();
+        let image = StorageImage::new(&device, Dimensions::Dim2d { width: 16, height: 16 },
+                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        let mut command = CmdCopyBufferToImage::new(buffer, image).unwrap();
+        assert_eq!(command.destination_layout(), Layout::TransferDstOptimal);
+        command.set_destination_layout(Layout::General);
+        assert_eq!(command.destination_layout(), Layout::General);
+
+        let _cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .add(command)
+            .unwrap();
+
+        let calls = mock::calls(&device);
+        assert!(calls.contains(&"CmdCopyBufferToImage"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The destination of a copy must be in the TransferDstOptimal or \
+                               General layout")]
+    fn wrong_layout() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_iter(&device, &BufferUsage::transfer_source(),
+                                                    Some(queue.family()),
+                                                    (0 .. 16 * 16).map(|_| [0u8; 4])).unwrap();
+        let image = StorageImage::new(&device, Dimensions::Dim2d { width: 16, height: 16 },
+                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        let mut command = CmdCopyBufferToImage::new(buffer, image).unwrap();
+        command.set_destination_layout(Layout::ShaderReadOnlyOptimal);
+    }
+
+    #[test]
+    fn stencil_aspect() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_iter(&device, &BufferUsage::transfer_source(),
+                                                    Some(queue.family()),
+                                                    (0 .. 16 * 16).map(|_| 0u8)).unwrap();
+        let image = StorageImage::new(&device, Dimensions::Dim2d { width: 16, height: 16 },
+                                      Format::D24Unorm_S8Uint, Some(queue.family())).unwrap();
+
+        match CmdCopyBufferToImage::with_regions(buffer.clone(), image.clone(),
+                                                 Some(region(ImageAspect::Stencil)))
+        {
+            Ok(_) => (),
+            _ => panic!()
+        }
+
+        // The depth values of this format occupy 4 bytes each.
+        match CmdCopyBufferToImage::with_regions(buffer, image,
+                                                 Some(region(ImageAspect::Depth)))
+        {
+            Err(CmdCopyBufferToImageError::BufferTooSmall) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn wrong_aspect() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_iter(&device, &BufferUsage::transfer_source(),
+                                                    Some(queue.family()),
+                                                    (0 .. 16 * 16).map(|_| 0u32)).unwrap();
+        let image = StorageImage::new(&device, Dimensions::Dim2d { width: 16, height: 16 },
+                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        match CmdCopyBufferToImage::with_regions(buffer, image, Some(region(ImageAspect::Depth))) {
+            Err(CmdCopyBufferToImageError::WrongAspect) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn unaligned_buffer_offset() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_iter(&device, &BufferUsage::transfer_source(),
+                                                    Some(queue.family()),
+                                                    (0 .. 16 * 16 + 1).map(|_| 0u32)).unwrap();
+        let image = StorageImage::new(&device, Dimensions::Dim2d { width: 16, height: 16 },
+                                      Format::R16Uint, Some(queue.family())).unwrap();
+
+        let region = BufferImageRegion { buffer_offset: 2, .. region(ImageAspect::Color) };
+        match CmdCopyBufferToImage::with_regions(buffer, image, Some(region)) {
+            Err(CmdCopyBufferToImageError::UnalignedBufferOffset) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn out_of_range() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_iter(&device, &BufferUsage::transfer_source(),
+                                                    Some(queue.family()),
+                                                    (0 .. 16 * 16 * 2).map(|_| 0u32)).unwrap();
+        let image = StorageImage::new(&device, Dimensions::Dim2d { width: 16, height: 16 },
+                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        let region = BufferImageRegion { image_layer_count: 2, .. region(ImageAspect::Color) };
+        match CmdCopyBufferToImage::with_regions(buffer, image, Some(region)) {
+            Err(CmdCopyBufferToImageError::OutOfImageRange) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn compressed_blocks() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        // 5x5 blocks of 8 bytes each.
+        let buffer = CpuAccessibleBuffer::from_iter(&device, &BufferUsage::transfer_source(),
+                                                    Some(queue.family()),
+                                                    (0 .. 5 * 5 * 2).map(|_| 0u32)).unwrap();
+        let image = ImmutableImage::new(&device, Dimensions::Dim2d { width: 18, height: 18 },
+                                        Format::BC1_RGBUnormBlock, Some(queue.family())).unwrap();
+
+        // The region can stop in the middle of a block at the edge of the image.
+        let whole = BufferImageRegion { image_extent: [18, 18, 1], .. region(ImageAspect::Color) };
+        match CmdCopyBufferToImage::with_regions(buffer.clone(), image.clone(), Some(whole)) {
+            Ok(_) => (),
+            _ => panic!()
+        }
+
+        let unaligned = [
+            BufferImageRegion { image_offset: [2, 0, 0], image_extent: [4, 4, 1],
+                                .. region(ImageAspect::Color) },
+            BufferImageRegion { image_extent: [6, 4, 1], .. region(ImageAspect::Color) },
+            BufferImageRegion { buffer_row_length: 18, .. whole },
+        ];
+
+        for region in unaligned.iter() {
+            match CmdCopyBufferToImage::with_regions(buffer.clone(), image.clone(),
+                                                     Some(*region))
+            {
+                Err(CmdCopyBufferToImageError::UnalignedToBlocks) => (),
+                _ => panic!()
+            }
+        }
+    }
+}
@@ -11,10 +11,15 @@ use std::error;
 use std::fmt;
 use std::sync::Arc;
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::UnsafeCommandBufferBuilder;
+use command_buffer::commands_raw::aspect_mask;
+use command_buffer::commands_raw::region_in_range;
 use command_buffer::pool::CommandPool;
 use device::Device;
 use device::DeviceOwned;
+use image::ImageAccess;
+use image::ImageSubresourceRange;
 use VulkanObject;
 use VulkanPointers;
 use vk;
@@ -50,7 +55,99 @@ pub struct CmdCopyImage<S, D> {
     extent: [u32; 3],
 }
 
-// TODO: add constructor
+impl<S, D> CmdCopyImage<S, D> where S: ImageAccess, D: ImageAccess {
+    /// Builds a new command.
+    ///
+    /// The region of size `extent` starting at `source_offset` in the source is copied to the
+    /// region starting at `destination_offset` in the destination. `layer_count` layers are
+    /// copied, starting at `source_base_array_layer` in the source and at
+    /// `destination_base_array_layer` in the destination.
+    ///
+    /// No conversion is performed. The two images must have formats of the same size, and the
+    /// same number of samples.
+    pub fn new(source: S, source_offset: [i32; 3], source_base_array_layer: u32,
+               source_mip_level: u32, destination: D, destination_offset: [i32; 3],
+               destination_base_array_layer: u32, destination_mip_level: u32,
+               extent: [u32; 3], layer_count: u32)
+               -> Result<CmdCopyImage<S, D>, CmdCopyImageError>
+    {
+        assert_eq!(source.inner().device().internal_object(),
+                   destination.inner().device().internal_object());
+
+        let source_raw = {
+            let inner = source.inner();
+            if !inner.usage_transfer_src() {
+                return Err(CmdCopyImageError::SourceMissingTransferUsage);
+            }
+            inner.internal_object()
+        };
+
+        let destination_raw = {
+            let inner = destination.inner();
+            if !inner.usage_transfer_dest() {
+                return Err(CmdCopyImageError::DestinationMissingTransferUsage);
+            }
+            inner.internal_object()
+        };
+
+        if source.samples() != destination.samples() {
+            return Err(CmdCopyImageError::SampleCountMismatch);
+        }
+
+        let source_format = source.format();
+        let destination_format = destination.format();
+
+        if !source_format.is_compatible_with(destination_format) {
+            return Err(CmdCopyImageError::IncompatibleFormats);
+        }
+
+        if layer_count == 0 || extent[0] == 0 || extent[1] == 0 || extent[2] == 0 {
+            return Err(CmdCopyImageError::EmptyRegion);
+        }
+
+        if !region_in_range(&source, source_offset, extent, source_base_array_layer,
+                            source_mip_level, layer_count)
+        {
+            return Err(CmdCopyImageError::SourceCoordinatesOutOfRange);
+        }
+
+        if !region_in_range(&destination, destination_offset, extent,
+                            destination_base_array_layer, destination_mip_level, layer_count)
+        {
+            return Err(CmdCopyImageError::DestinationCoordinatesOutOfRange);
+        }
+
+        if source.conflicts_image(source_base_array_layer, layer_count, source_mip_level, 1,
+                                  &destination, destination_base_array_layer, layer_count,
+                                  destination_mip_level, 1)
+        {
+            return Err(CmdCopyImageError::OverlappingRegions);
+        }
+
+        let source_aspect_mask = aspect_mask(&source);
+        let destination_aspect_mask = aspect_mask(&destination);
+
+        Ok(CmdCopyImage {
+            source: source,
+            source_raw: source_raw,
+            source_layout: vk::IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL,
+            source_offset: source_offset,
+            source_aspect_mask: source_aspect_mask,
+            source_mip_level: source_mip_level,
+            source_base_array_layer: source_base_array_layer,
+            source_layer_count: layer_count,
+            destination: destination,
+            destination_raw: destination_raw,
+            destination_layout: vk::IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL,
+            destination_offset: destination_offset,
+            destination_aspect_mask: destination_aspect_mask,
+            destination_mip_level: destination_mip_level,
+            destination_base_array_layer: destination_base_array_layer,
+            destination_layer_count: layer_count,
+            extent: extent,
+        })
+    }
+}
 
 impl<S, D> CmdCopyImage<S, D> {
     /// Returns the source image.
@@ -64,12 +161,30 @@ impl<S, D> CmdCopyImage<S, D> {
     pub fn destination(&self) -> &D {
         &self.destination
     }
+
+    /// Returns the range of subresources of the source image that are accessed by the command.
+    #[inline]
+    pub fn source_subresource_range(&self) -> ImageSubresourceRange {
+        ImageSubresourceRange::new(self.source_mip_level .. self.source_mip_level + 1,
+                                   self.source_base_array_layer ..
+                                   self.source_base_array_layer + self.source_layer_count)
+    }
+
+    /// Returns the range of subresources of the destination image that are accessed by the
+    /// command.
+    #[inline]
+    pub fn destination_subresource_range(&self) -> ImageSubresourceRange {
+        ImageSubresourceRange::new(self.destination_mip_level .. self.destination_mip_level + 1,
+                                   self.destination_base_array_layer ..
+                                   self.destination_base_array_layer +
+                                   self.destination_layer_count)
+    }
 }
 
-unsafe impl<S, D> DeviceOwned for CmdCopyImage<S, D> where S: DeviceOwned {
+unsafe impl<S, D> DeviceOwned for CmdCopyImage<S, D> where S: ImageAccess {
     #[inline]
     fn device(&self) -> &Arc<Device> {
-        self.source.device()
+        self.source.inner().device()
     }
 }
 
@@ -79,7 +194,7 @@ unsafe impl<'a, P, S, D> AddCommand<&'a CmdCopyImage<S, D>> for UnsafeCommandBuf
     type Out = UnsafeCommandBufferBuilder<P>;
 
     #[inline]
-    fn add(self, command: &'a CmdCopyImage<S, D>) -> Self::Out {
+    fn add(self, command: &'a CmdCopyImage<S, D>) -> Result<Self::Out, CommandAddError> {
         unsafe {
             debug_assert!(command.source_layout == vk::IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL ||
                           command.source_layout == vk::IMAGE_LAYOUT_GENERAL);
@@ -123,19 +238,59 @@ unsafe impl<'a, P, S, D> AddCommand<&'a CmdCopyImage<S, D>> for UnsafeCommandBuf
                             1, &region as *const _);
         }
 
-        self
+        Ok(self)
     }
 }
 
 /// Error that can happen when creating a `CmdCopyImage`.
 #[derive(Debug, Copy, Clone)]
 pub enum CmdCopyImageError {
+    /// The source image is missing the transfer source usage.
+    SourceMissingTransferUsage,
+    /// The destination image is missing the transfer destination usage.
+    DestinationMissingTransferUsage,
+    /// The source and destination images have a different number of samples.
+    SampleCountMismatch,
+    /// The formats of the source and destination images are not compatible.
+    IncompatibleFormats,
+    /// The region to copy is empty.
+    EmptyRegion,
+    /// The source coordinates are out of range of the image.
+    SourceCoordinatesOutOfRange,
+    /// The destination coordinates are out of range of the image.
+    DestinationCoordinatesOutOfRange,
+    /// The source and destination regions are overlapping in memory.
+    OverlappingRegions,
 }
 
 impl error::Error for CmdCopyImageError {
     #[inline]
     fn description(&self) -> &str {
         match *self {
+            CmdCopyImageError::SourceMissingTransferUsage => {
+                "the source image is missing the transfer source usage"
+            },
+            CmdCopyImageError::DestinationMissingTransferUsage => {
+                "the destination image is missing the transfer destination usage"
+            },
+            CmdCopyImageError::SampleCountMismatch => {
+                "the source and destination images have a different number of samples"
+            },
+            CmdCopyImageError::IncompatibleFormats => {
+                "the formats of the source and destination images are not compatible"
+            },
+            CmdCopyImageError::EmptyRegion => {
+                "the region to copy is empty"
+            },
+            CmdCopyImageError::SourceCoordinatesOutOfRange => {
+                "the source coordinates are out of range of the image"
+            },
+            CmdCopyImageError::DestinationCoordinatesOutOfRange => {
+                "the destination coordinates are out of range of the image"
+            },
+            CmdCopyImageError::OverlappingRegions => {
+                "the source and destination regions are overlapping in memory"
+            },
         }
     }
 }
@@ -146,3 +301,84 @@ impl fmt::Display for CmdCopyImageError {
         write!(fmt, "{}", error::Error::description(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBufferBuilder;
+    use command_buffer::commands_raw::CmdCopyImage;
+    use command_buffer::commands_raw::CmdCopyImageError;
+    use format::R8G8B8A8Unorm;
+    use image::StorageImage;
+    use image::Dimensions;
+    use image::ImageSubresourceRange;
+    use mock;
+
+    #[test]
+    fn basic_copy() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let dims = Dimensions::Dim2d { width: 32, height: 32 };
+        let a = StorageImage::new(&device, dims, R8G8B8A8Unorm, Some(queue.family())).unwrap();
+        let b = StorageImage::new(&device, dims, R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        let _cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .copy_image(a.clone(), [0, 0, 0], 0, 0, b.clone(), [8, 8, 0], 0, 0, [16, 16, 1], 1)
+            .unwrap();
+
+        let calls = mock::calls(&device);
+        assert!(calls.iter().any(|c| *c == "CmdCopyImage"));
+    }
+
+    #[test]
+    fn out_of_range() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let dims = Dimensions::Dim2d { width: 32, height: 32 };
+        let a = StorageImage::new(&device, dims, R8G8B8A8Unorm, Some(queue.family())).unwrap();
+        let b = StorageImage::new(&device, dims, R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        match CmdCopyImage::new(a.clone(), [16, 16, 0], 0, 0, b.clone(), [0, 0, 0], 0, 0,
+                                [32, 32, 1], 1)
+        {
+            Err(CmdCopyImageError::SourceCoordinatesOutOfRange) => (),
+            _ => panic!()
+        }
+
+        match CmdCopyImage::new(a.clone(), [0, 0, 0], 0, 1, b.clone(), [0, 0, 0], 0, 0,
+                                [1, 1, 1], 1)
+        {
+            Err(CmdCopyImageError::SourceCoordinatesOutOfRange) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn same_image_overlap() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let dims = Dimensions::Dim2d { width: 32, height: 32 };
+        let a = StorageImage::new(&device, dims, R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        match CmdCopyImage::new(a.clone(), [0, 0, 0], 0, 0, a.clone(), [0, 0, 0], 0, 0,
+                                [16, 16, 1], 1)
+        {
+            Err(CmdCopyImageError::OverlappingRegions) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn same_image_different_layers() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let dims = Dimensions::Dim2dArray { width: 32, height: 32, array_layers: 2 };
+        let a = StorageImage::new(&device, dims, R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        let cmd = CmdCopyImage::new(a.clone(), [0, 0, 0], 0, 0, a.clone(), [0, 0, 0], 1, 0,
+                                    [16, 16, 1], 1).unwrap();
+        assert_eq!(cmd.source_subresource_range(), ImageSubresourceRange::new(0 .. 1, 0 .. 1));
+        assert_eq!(cmd.destination_subresource_range(),
+                   ImageSubresourceRange::new(0 .. 1, 1 .. 2));
+    }
+}
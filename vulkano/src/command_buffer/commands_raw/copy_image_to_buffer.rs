@@ -0,0 +1,387 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+use smallvec::SmallVec;
+
+use buffer::TypedBuffer;
+use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
+use command_buffer::cb::UnsafeCommandBufferBuilder;
+use command_buffer::commands_raw::BufferImageRegion;
+use command_buffer::commands_raw::buffer_image_region::RegionError;
+use command_buffer::commands_raw::buffer_image_region::check_region;
+use command_buffer::commands_raw::buffer_image_region::regions_subresource_range;
+use command_buffer::commands_raw::buffer_image_region::vk_region;
+use command_buffer::pool::CommandPool;
+use device::Device;
+use device::DeviceOwned;
+use image::ImageAccess;
+use image::ImageSubresourceRange;
+use image::ImageAspect;
+use image::Layout;
+use memory::Content;
+use VulkanObject;
+use VulkanPointers;
+use vk;
+
+/// Command that copies from an image to a buffer.
+#[derive(Debug, Clone)]
+pub struct CmdCopyImageToBuffer<S, D> {
+    // The source image.
+    source: S,
+    // Raw source image.
+    source_raw: vk::Image,
+    // Layout of the source image.
+    source_layout: Layout,
+    // The destination buffer.
+    buffer: D,
+    // Raw destination buffer.
+    buffer_raw: vk::Buffer,
+    // Offset of the buffer slice within the raw buffer.
+    buffer_offset: usize,
+    // The regions to copy.
+    regions: SmallVec<[BufferImageRegion; 4]>,
+}
+
+impl<S, D> CmdCopyImageToBuffer<S, D>
+    where S: ImageAccess, D: TypedBuffer, D::Content: Content
+{
+    /// Builds a command that copies all the array layers of the first mipmap level of `source`
+    /// to the start of the buffer.
+    ///
+    /// The color aspect is copied for color images, and the depth aspect is copied for depth and
+    /// depth-stencil images. Use `with_dimensions` or `with_regions` to copy the stencil aspect.
+    #[inline]
+    pub fn new(source: S, destination: D)
+               -> Result<CmdCopyImageToBuffer<S, D>, CmdCopyImageToBufferError>
+    {
+        let region = BufferImageRegion::whole_level(&source, 0);
+        CmdCopyImageToBuffer::with_regions(source, destination, Some(region))
+    }
+
+    /// Builds a command that copies a single region of the image to the start of the buffer.
+    pub fn with_dimensions(source: S, destination: D, offset: [u32; 3], size: [u32; 3],
+                           first_layer: u32, num_layers: u32, mipmap: u32, aspect: ImageAspect)
+                           -> Result<CmdCopyImageToBuffer<S, D>, CmdCopyImageToBufferError>
+    {
+        let region = BufferImageRegion {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_aspect: aspect,
+            image_mip_level: mipmap,
+            image_base_array_layer: first_layer,
+            image_layer_count: num_layers,
+            image_offset: offset,
+            image_extent: size,
+        };
+
+        CmdCopyImageToBuffer::with_regions(source, destination, Some(region))
+    }
+
+    /// Builds a command that copies each of the given regions of the image to the buffer.
+    pub fn with_regions<R>(source: S, destination: D, regions: R)
+                           -> Result<CmdCopyImageToBuffer<S, D>, CmdCopyImageToBufferError>
+        where R: IntoIterator<Item = BufferImageRegion>
+    {
+        assert_eq!(source.inner().device().internal_object(),
+                   destination.inner().buffer.device().internal_object());
+
+        if source.samples() != 1 {
+            return Err(CmdCopyImageToBufferError::SourceMultisampled);
+        }
+
+        let source_raw = {
+            let inner = source.inner();
+            if !inner.usage_transfer_src() {
+                return Err(CmdCopyImageToBufferError::SourceMissingTransferUsage);
+            }
+            inner.internal_object()
+        };
+
+        let (destination_raw, destination_offset) = {
+            let inner = destination.inner();
+            if !inner.buffer.usage_transfer_dest() {
+                return Err(CmdCopyImageToBufferError::DestinationMissingTransferUsage);
+            }
+            (inner.buffer.internal_object(), inner.offset)
+        };
+
+        let regions = try!(regions.into_iter().map(|region| {
+            check_region(&destination, &source, &region).map(|()| region).map_err(|err| match err {
+                RegionError::EmptyRegion => CmdCopyImageToBufferError::EmptyRegion,
+                RegionError::WrongAspect => CmdCopyImageToBufferError::WrongAspect,
+                RegionError::OutOfImageRange => CmdCopyImageToBufferError::OutOfImageRange,
+                RegionError::InvalidRowLength => CmdCopyImageToBufferError::InvalidRowLength,
+                RegionError::UnalignedToBlocks => CmdCopyImageToBufferError::UnalignedToBlocks,
+                RegionError::UnalignedBufferOffset => {
+                    CmdCopyImageToBufferError::UnalignedBufferOffset
+                },
+                RegionError::BufferTooSmall => CmdCopyImageToBufferError::BufferTooSmall,
+                RegionError::OverlappingRanges => CmdCopyImageToBufferError::OverlappingRanges,
+                RegionError::WrongContentFormat => CmdCopyImageToBufferError::WrongContentFormat,
+            })
+        }).collect::<Result<SmallVec<[_; 4]>, _>>());
+
+        if regions.is_empty() {
+            return Err(CmdCopyImageToBufferError::NoRegions);
+        }
+
+        Ok(CmdCopyImageToBuffer {
+            source: source,
+            source_raw: source_raw,
+            source_layout: Layout::TransferSrcOptimal,
+            buffer: destination,
+            buffer_raw: destination_raw,
+            buffer_offset: destination_offset,
+            regions: regions,
+        })
+    }
+}
+
+impl<S, D> CmdCopyImageToBuffer<S, D> {
+    /// Returns the source image.
+    #[inline]
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Returns the range of subresources of the source image that are accessed by the command.
+    #[inline]
+    pub fn source_subresource_range(&self) -> ImageSubresourceRange {
+        regions_subresource_range(&self.regions)
+    }
+
+    /// Returns the layout that the source image must be in when the command is executed.
+    #[inline]
+    pub fn source_layout(&self) -> Layout {
+        self.source_layout
+    }
+
+    /// Sets the layout that the source image is in when the command is executed. The default is
+    /// `TransferSrcOptimal`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `layout` is neither `TransferSrcOptimal` nor `General`.
+    ///
+    #[inline]
+    pub fn set_source_layout(&mut self, layout: Layout) {
+        assert!(layout == Layout::TransferSrcOptimal || layout == Layout::General,
+                "The source of a copy must be in the TransferSrcOptimal or General layout");
+        self.source_layout = layout;
+    }
+
+    /// Returns the destination buffer.
+    #[inline]
+    pub fn destination(&self) -> &D {
+        &self.buffer
+    }
+}
+
+unsafe impl<S, D> DeviceOwned for CmdCopyImageToBuffer<S, D> where D: DeviceOwned {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.buffer.device()
+    }
+}
+
+unsafe impl<'a, P, S, D> AddCommand<&'a CmdCopyImageToBuffer<S, D>> for UnsafeCommandBufferBuilder<P>
+    where P: CommandPool
+{
+    type Out = UnsafeCommandBufferBuilder<P>;
+
+    #[inline]
+    fn add(self, command: &'a CmdCopyImageToBuffer<S, D>) -> Result<Self::Out, CommandAddError> {
+        unsafe {
+            let regions = command.regions.iter()
+                                 .map(|region| vk_region(region, command.buffer_offset))
+                                 .collect::<SmallVec<[_; 4]>>();
+
+            let vk = self.device().pointers();
+            let cmd = self.internal_object();
+            vk.CmdCopyImageToBuffer(cmd, command.source_raw, command.source_layout as u32,
+                                    command.buffer_raw, regions.len() as u32,
+                                    regions.as_ptr());
+        }
+
+        Ok(self)
+    }
+}
+
+/// Error that can happen when creating a `CmdCopyImageToBuffer`.
+#[derive(Debug, Copy, Clone)]
+pub enum CmdCopyImageToBufferError {
+    /// The source image is missing the transfer source usage.
+    SourceMissingTransferUsage,
+    /// The destination buffer is missing the transfer destination usage.
+    DestinationMissingTransferUsage,
+    /// The source image has more than one sample per pixel.
+    SourceMultisampled,
+    /// No region was passed.
+    NoRegions,
+    /// One of the regions has a width, height, depth or number of layers of zero.
+    EmptyRegion,
+    /// The source image doesn't have the aspect of one of the regions.
+    WrongAspect,
+    /// The dimensions are out of range of the image.
+    OutOfImageRange,
+    /// The row length or the image height of one of the regions is smaller than its extent.
+    InvalidRowLength,
+    /// The image has a compressed format, and one of the regions isn't made of whole blocks of
+    /// texels.
+    UnalignedToBlocks,
+    /// The offset of one of the regions in the buffer isn't a multiple of 4 or of the size of a
+    /// texel.
+    UnalignedBufferOffset,
+    /// The destination buffer is too small for one of the regions.
+    BufferTooSmall,
+    /// The source and destination are overlapping in memory.
+    OverlappingRanges,
+    /// The type of the content of the destination buffer doesn't match the format of the image.
+    WrongContentFormat,
+}
+
+impl error::Error for CmdCopyImageToBufferError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CmdCopyImageToBufferError::SourceMissingTransferUsage => {
+                "the source image is missing the transfer source usage"
+            },
+            CmdCopyImageToBufferError::DestinationMissingTransferUsage => {
+                "the destination buffer is missing the transfer destination usage"
+            },
+            CmdCopyImageToBufferError::SourceMultisampled => {
+                "the source image has more than one sample per pixel"
+            },
+            CmdCopyImageToBufferError::NoRegions => {
+                "no region was passed"
+            },
+            CmdCopyImageToBufferError::EmptyRegion => {
+                "one of the regions has a width, height, depth or number of layers of zero"
+            },
+            CmdCopyImageToBufferError::WrongAspect => {
+                "the source image doesn't have the aspect of one of the regions"
+            },
+            CmdCopyImageToBufferError::OutOfImageRange => {
+                "the dimensions are out of range of the image"
+            },
+            CmdCopyImageToBufferError::InvalidRowLength => {
+                "the row length or the image height of one of the regions is smaller than its \
+                 extent"
+            },
+            CmdCopyImageToBufferError::UnalignedToBlocks => {
+                "the image has a compressed format, and one of the regions isn't made of whole \
+                 blocks of texels"
+            },
+            CmdCopyImageToBufferError::UnalignedBufferOffset => {
+                "the offset of one of the regions in the buffer isn't a multiple of 4 or of the \
+                 size of a texel"
+            },
+            CmdCopyImageToBufferError::BufferTooSmall => {
+                "the destination buffer is too small for one of the regions"
+            },
+            CmdCopyImageToBufferError::OverlappingRanges => {
+                "the source and destination are overlapping in memory"
+            },
+            CmdCopyImageToBufferError::WrongContentFormat => {
+                "the type of the content of the destination buffer doesn't match the format of the \
+                 image"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CmdCopyImageToBufferError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::cb::AddCommand;
+    use command_buffer::commands_raw::CmdCopyImageToBuffer;
+    use command_buffer::commands_raw::CmdCopyImageToBufferError;
+    use format::Format;
+    use image::Dimensions;
+    use image::Layout;
+    use image::StorageImage;
+    use mock;
+
+    #[test]
+    fn wrong_content_format() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        // Elements of 3 bytes don't match texels of 4 bytes.
+        let buffer = unsafe {
+            CpuAccessibleBuffer::<[[u8; 3]]>::uninitialized_array(&device, 16 * 16 * 2,
+                                                                   &BufferUsage::transfer_dest(),
+                                                                   Some(queue.family())).unwrap()
+        };
+        let image = StorageImage::new(&device, Dimensions::Dim2d { width: 16, height: 16 },
+                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        match CmdCopyImageToBuffer::new(image, buffer) {
+            Err(CmdCopyImageToBufferError::WrongContentFormat) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn general_layout() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let buffer = unsafe {
+            CpuAccessibleBuffer::<[u8]>::uninitialized_array(&device, 16 * 16 * 4,
+                                                             &BufferUsage::transfer_dest(),
+                                                             Some(queue.family())).unwrap()
+        };
+        let image = StorageImage::new(&device, Dimensions::Dim2d { width: 16, height: 16 },
+                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        let mut command = CmdCopyImageToBuffer::new(image, buffer).unwrap();
+        assert_eq!(command.source_layout(), Layout::TransferSrcOptimal);
+        command.set_source_layout(Layout::General);
+        assert_eq!(command.source_layout(), Layout::General);
+
+        let _cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .add(command)
+            .unwrap();
+
+        let calls = mock::calls(&device);
+        assert!(calls.contains(&"CmdCopyImageToBuffer"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The source of a copy must be in the TransferSrcOptimal or General \
+                               layout")]
+    fn wrong_layout() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let buffer = unsafe {
+            CpuAccessibleBuffer::<[u8]>::uninitialized_array(&device, 16 * 16 * 4,
+                                                             &BufferUsage::transfer_dest(),
+                                                             Some(queue.family())).unwrap()
+        };
+        let image = StorageImage::new(&device, Dimensions::Dim2d { width: 16, height: 16 },
+                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        let mut command = CmdCopyImageToBuffer::new(image, buffer).unwrap();
+        command.set_source_layout(Layout::TransferDstOptimal);
+    }
+}
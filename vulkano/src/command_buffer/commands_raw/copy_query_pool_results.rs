@@ -0,0 +1,200 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::mem;
+use std::ops::Range;
+use std::sync::Arc;
+
+use buffer::BufferAccess;
+use buffer::BufferInner;
+use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
+use command_buffer::cb::UnsafeCommandBufferBuilder;
+use command_buffer::pool::CommandPool;
+use device::Device;
+use device::DeviceOwned;
+use query::QueryPoolAbstract;
+use query::QueryResultFlags;
+use query::QueryType;
+use VulkanObject;
+use VulkanPointers;
+use vk;
+
+/// Command that copies the results of a range of queries to a buffer.
+pub struct CmdCopyQueryPoolResults<Q, B> {
+    // The query pool.
+    pool: Q,
+    // Raw query pool handle.
+    raw_pool: vk::QueryPool,
+    // Index of the first query to copy.
+    first_query: u32,
+    // Number of queries to copy.
+    query_count: u32,
+    // The buffer to write to.
+    destination: B,
+    // Raw buffer handle.
+    destination_handle: vk::Buffer,
+    // Offset within the buffer.
+    destination_offset: vk::DeviceSize,
+    // Number of bytes between the results of two queries.
+    stride: vk::DeviceSize,
+    // Flags to pass to `vkCmdCopyQueryPoolResults`.
+    flags: vk::QueryResultFlags,
+}
+
+impl<Q, B> CmdCopyQueryPoolResults<Q, B>
+    where Q: QueryPoolAbstract, B: BufferAccess
+{
+    /// Builds a command that copies the results of the queries of `range` to `destination`.
+    ///
+    /// The results are written as 64-bits integers, one query after another. Each query writes
+    /// one value per result, followed by its availability if `flags.with_availability` is true.
+    pub fn new(pool: Q, range: Range<u32>, destination: B, flags: QueryResultFlags)
+               -> Result<CmdCopyQueryPoolResults<Q, B>, CmdCopyQueryPoolResultsError>
+    {
+        let (raw_pool, stride) = {
+            let inner = pool.inner();
+
+            if range.start >= range.end || range.end > inner.num_slots() {
+                return Err(CmdCopyQueryPoolResultsError::OutOfRange);
+            }
+
+            if flags.partial {
+                if let QueryType::Timestamp = inner.ty() {
+                    return Err(CmdCopyQueryPoolResultsError::PartialTimestamp);
+                }
+            }
+
+            let num_values = inner.num_values_per_query() +
+                             if flags.with_availability { 1 } else { 0 };
+            (inner.internal_object(), num_values * mem::size_of::<u64>())
+        };
+
+        let query_count = range.end - range.start;
+
+        if destination.size() < query_count as usize * stride {
+            return Err(CmdCopyQueryPoolResultsError::DestinationTooSmall);
+        }
+
+        let (destination_handle, destination_offset) = {
+            let BufferInner { buffer, offset } = destination.inner();
+            if !buffer.usage_transfer_dest() {
+                return Err(CmdCopyQueryPoolResultsError::DestinationMissingTransferUsage);
+            }
+            if offset % mem::size_of::<u64>() != 0 {
+                return Err(CmdCopyQueryPoolResultsError::WrongAlignment);
+            }
+            (buffer.internal_object(), offset)
+        };
+
+        Ok(CmdCopyQueryPoolResults {
+            pool: pool,
+            raw_pool: raw_pool,
+            first_query: range.start,
+            query_count: query_count,
+            destination: destination,
+            destination_handle: destination_handle,
+            destination_offset: destination_offset as vk::DeviceSize,
+            stride: stride as vk::DeviceSize,
+            flags: flags.into(),
+        })
+    }
+}
+
+impl<Q, B> CmdCopyQueryPoolResults<Q, B> {
+    /// Returns the query pool.
+    #[inline]
+    pub fn pool(&self) -> &Q {
+        &self.pool
+    }
+
+    /// Returns the buffer the results are written to.
+    #[inline]
+    pub fn destination(&self) -> &B {
+        &self.destination
+    }
+}
+
+unsafe impl<Q, B> DeviceOwned for CmdCopyQueryPoolResults<Q, B>
+    where Q: QueryPoolAbstract
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.pool.inner().device()
+    }
+}
+
+unsafe impl<'a, P, Q, B> AddCommand<&'a CmdCopyQueryPoolResults<Q, B>> for UnsafeCommandBufferBuilder<P>
+    where P: CommandPool
+{
+    type Out = UnsafeCommandBufferBuilder<P>;
+
+    #[inline]
+    fn add(self, command: &'a CmdCopyQueryPoolResults<Q, B>)
+           -> Result<Self::Out, CommandAddError>
+    {
+        unsafe {
+            let vk = self.device().pointers();
+            let cmd = self.internal_object();
+            vk.CmdCopyQueryPoolResults(cmd, command.raw_pool, command.first_query,
+                                       command.query_count, command.destination_handle,
+                                       command.destination_offset, command.stride,
+                                       command.flags);
+        }
+
+        Ok(self)
+    }
+}
+
+/// Error that can happen when creating a `CmdCopyQueryPoolResults`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CmdCopyQueryPoolResultsError {
+    /// The range of queries is empty or out of the range of the pool.
+    OutOfRange,
+    /// Partial results were requested for timestamp queries.
+    PartialTimestamp,
+    /// The "transfer destination" usage must be enabled on the destination buffer.
+    DestinationMissingTransferUsage,
+    /// The offset of the destination buffer must be a multiple of 8.
+    WrongAlignment,
+    /// The destination buffer is too small to contain the results.
+    DestinationTooSmall,
+}
+
+impl error::Error for CmdCopyQueryPoolResultsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CmdCopyQueryPoolResultsError::OutOfRange => {
+                "the range of queries is empty or out of the range of the pool"
+            },
+            CmdCopyQueryPoolResultsError::PartialTimestamp => {
+                "partial results were requested for timestamp queries"
+            },
+            CmdCopyQueryPoolResultsError::DestinationMissingTransferUsage => {
+                "the transfer destination usage must be enabled on the destination buffer"
+            },
+            CmdCopyQueryPoolResultsError::WrongAlignment => {
+                "the offset of the destination buffer must be a multiple of 8"
+            },
+            CmdCopyQueryPoolResultsError::DestinationTooSmall => {
+                "the destination buffer is too small to contain the results"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CmdCopyQueryPoolResultsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
@@ -0,0 +1,520 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Compute shader that generates a mipmap level of a two-dimensional image from the previous
+//! level. Used by `CmdGenerateMipmaps` for the formats that can't be blitted with a linear filter.
+
+use std::ffi::CStr;
+use std::fmt;
+use std::ptr;
+use std::slice;
+
+use descriptor::descriptor::DescriptorDesc;
+use descriptor::descriptor::DescriptorDescTy;
+use descriptor::descriptor::DescriptorImageDesc;
+use descriptor::descriptor::DescriptorImageDescArray;
+use descriptor::descriptor::DescriptorImageDescDimensions;
+use descriptor::descriptor::ShaderStages;
+use descriptor::descriptor_set::DescriptorPoolAllocError;
+use descriptor::descriptor_set::DescriptorsCount;
+use descriptor::descriptor_set::UnsafeDescriptorPool;
+use descriptor::descriptor_set::UnsafeDescriptorSet;
+use descriptor::pipeline_layout::PipelineLayout;
+use descriptor::pipeline_layout::PipelineLayoutAbstract;
+use descriptor::pipeline_layout::PipelineLayoutDesc;
+use descriptor::pipeline_layout::PipelineLayoutDescNames;
+use descriptor::pipeline_layout::PipelineLayoutDescPcRange;
+use device::Device;
+use format::Format;
+use format::FormatTy;
+use image::ViewType;
+use image::sys::UnsafeImage;
+use image::sys::UnsafeImageView;
+use pipeline::ComputePipeline;
+use pipeline::ComputePipelineAbstract;
+use pipeline::ComputePipelineCreationError;
+use pipeline::shader::ShaderModule;
+use OomError;
+use VulkanObject;
+use VulkanPointers;
+use vk;
+
+/// Pipeline and descriptor sets that generate each mipmap level of an image from the previous one.
+pub struct Downsampler {
+    pipeline: ComputePipeline<PipelineLayout<DownsampleLayout>>,
+    // One view per mipmap level, covering all the array layers.
+    views: Vec<UnsafeImageView>,
+    // Must be kept alive as long as the sets are used.
+    pool: UnsafeDescriptorPool,
+    // The set at index `n` reads from level `n` and writes to level `n + 1`.
+    sets: Vec<UnsafeDescriptorSet>,
+}
+
+impl Downsampler {
+    /// Builds the pipeline, the views and the descriptor sets for all the mipmap levels of
+    /// `image`.
+    ///
+    /// The image must be a two-dimensional color image with the sampled and storage usages,
+    /// whose format is supported by `storage_format`.
+    pub fn new(image: &UnsafeImage) -> Result<Downsampler, OomError> {
+        let device = image.device();
+        let (spirv_format, extended) = storage_format(image.format()).unwrap();
+
+        let (mut words, format_word) = match image.format().ty() {
+            FormatTy::Uint => (UINT_SHADER.to_vec(), INT_FORMAT_WORD),
+            FormatTy::Sint => (SINT_SHADER.to_vec(), INT_FORMAT_WORD),
+            _ => (FLOAT_SHADER.to_vec(), FLOAT_FORMAT_WORD),
+        };
+        words[format_word] = spirv_format;
+        if extended {
+            // StorageImageExtendedFormats
+            words[CAPABILITY_WORD] = 49;
+        }
+
+        let pipeline = unsafe {
+            let spirv = slice::from_raw_parts(words.as_ptr() as *const u8, words.len() * 4);
+            let module = try!(ShaderModule::new(device.clone(), spirv));
+            let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            let entry = module.compute_shader_entry_point::<(), _>(name, DownsampleLayout);
+            match ComputePipeline::new(device, &entry, &()) {
+                Ok(pipeline) => pipeline,
+                Err(ComputePipelineCreationError::OomError(err)) => return Err(err),
+                Err(ComputePipelineCreationError::IncompatiblePipelineLayout) => unreachable!(),
+            }
+        };
+
+        let num_levels = image.mipmap_levels();
+        let array_layers = image.dimensions().array_layers();
+
+        let views = try!((0 .. num_levels).map(|level| unsafe {
+            UnsafeImageView::raw(image, ViewType::Dim2dArray, level .. level + 1,
+                                 0 .. array_layers)
+        }).collect::<Result<Vec<_>, _>>());
+
+        let num_sets = num_levels - 1;
+        let count = DescriptorsCount {
+            sampled_image: num_sets,
+            storage_image: num_sets,
+            .. DescriptorsCount::zero()
+        };
+        let mut pool = try!(UnsafeDescriptorPool::new(device.clone(), &count, num_sets, false));
+
+        let sets = unsafe {
+            let layout = pipeline.descriptor_set_layout(0).unwrap();
+            let sets = match pool.alloc((0 .. num_sets).map(|_| &**layout)) {
+                Ok(sets) => sets,
+                Err(DescriptorPoolAllocError::OutOfHostMemory) => {
+                    return Err(OomError::OutOfHostMemory);
+                },
+                Err(DescriptorPoolAllocError::OutOfDeviceMemory) => {
+                    return Err(OomError::OutOfDeviceMemory);
+                },
+                // The pool has just been created with exactly the right size.
+                Err(_) => unreachable!(),
+            };
+            sets.collect::<Vec<_>>()
+        };
+
+        unsafe {
+            let infos = (0 .. num_sets as usize).map(|n| {
+                [
+                    vk::DescriptorImageInfo {
+                        sampler: 0,
+                        imageView: views[n].internal_object(),
+                        imageLayout: vk::IMAGE_LAYOUT_SHADER_READ_ONLY_OPTIMAL,
+                    },
+                    vk::DescriptorImageInfo {
+                        sampler: 0,
+                        imageView: views[n + 1].internal_object(),
+                        imageLayout: vk::IMAGE_LAYOUT_GENERAL,
+                    },
+                ]
+            }).collect::<Vec<_>>();
+
+            let writes = sets.iter().zip(infos.iter()).flat_map(|(set, infos)| {
+                let ty = [vk::DESCRIPTOR_TYPE_SAMPLED_IMAGE, vk::DESCRIPTOR_TYPE_STORAGE_IMAGE];
+                (0 .. 2).map(move |binding| {
+                    vk::WriteDescriptorSet {
+                        sType: vk::STRUCTURE_TYPE_WRITE_DESCRIPTOR_SET,
+                        pNext: ptr::null(),
+                        dstSet: set.internal_object(),
+                        dstBinding: binding as u32,
+                        dstArrayElement: 0,
+                        descriptorCount: 1,
+                        descriptorType: ty[binding],
+                        pImageInfo: &infos[binding],
+                        pBufferInfo: ptr::null(),
+                        pTexelBufferView: ptr::null(),
+                    }
+                })
+            }).collect::<Vec<_>>();
+
+            let vk = device.pointers();
+            vk.UpdateDescriptorSets(device.internal_object(), writes.len() as u32,
+                                    writes.as_ptr(), 0, ptr::null());
+        }
+
+        Ok(Downsampler {
+            pipeline: pipeline,
+            views: views,
+            pool: pool,
+            sets: sets,
+        })
+    }
+
+    /// Returns the raw compute pipeline.
+    #[inline]
+    pub fn pipeline(&self) -> vk::Pipeline {
+        self.pipeline.inner().internal_object()
+    }
+
+    /// Returns the raw layout of the compute pipeline.
+    #[inline]
+    pub fn pipeline_layout(&self) -> vk::PipelineLayout {
+        PipelineLayoutAbstract::sys(&self.pipeline).internal_object()
+    }
+
+    /// Returns the raw descriptor set that generates the given mipmap level from the previous
+    /// one.
+    #[inline]
+    pub fn descriptor_set(&self, level: u32) -> vk::DescriptorSet {
+        self.sets[level as usize - 1].internal_object()
+    }
+}
+
+impl fmt::Debug for Downsampler {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "<Downsampler with {} levels>", self.views.len())
+    }
+}
+
+/// Returns the SPIR-V image format to use in the shader for a storage image of the given format,
+/// and whether it requires the `StorageImageExtendedFormats` capability. Returns `None` if the
+/// shader can't write to images of this format.
+pub fn storage_format(format: Format) -> Option<(u32, bool)> {
+    Some(match format {
+        Format::R32G32B32A32Sfloat => (1, false),
+        Format::R16G16B16A16Sfloat => (2, false),
+        Format::R32Sfloat => (3, false),
+        Format::R8G8B8A8Unorm => (4, false),
+        Format::R8G8B8A8Snorm => (5, false),
+        Format::R32G32Sfloat => (6, true),
+        Format::R16G16Sfloat => (7, true),
+        Format::B10G11R11UfloatPack32 => (8, true),
+        Format::R16Sfloat => (9, true),
+        Format::R16G16B16A16Unorm => (10, true),
+        Format::A2B10G10R10UnormPack32 => (11, true),
+        Format::R16G16Unorm => (12, true),
+        Format::R8G8Unorm => (13, true),
+        Format::R16Unorm => (14, true),
+        Format::R8Unorm => (15, true),
+        Format::R16G16B16A16Snorm => (16, true),
+        Format::R16G16Snorm => (17, true),
+        Format::R8G8Snorm => (18, true),
+        Format::R16Snorm => (19, true),
+        Format::R8Snorm => (20, true),
+        Format::R32G32B32A32Sint => (21, false),
+        Format::R16G16B16A16Sint => (22, false),
+        Format::R8G8B8A8Sint => (23, false),
+        Format::R32Sint => (24, false),
+        Format::R32G32Sint => (25, true),
+        Format::R16G16Sint => (26, true),
+        Format::R8G8Sint => (27, true),
+        Format::R16Sint => (28, true),
+        Format::R8Sint => (29, true),
+        Format::R32G32B32A32Uint => (30, false),
+        Format::R16G16B16A16Uint => (31, false),
+        Format::R8G8B8A8Uint => (32, false),
+        Format::R32Uint => (33, false),
+        Format::A2B10G10R10UintPack32 => (34, true),
+        Format::R32G32Uint => (35, true),
+        Format::R16G16Uint => (36, true),
+        Format::R8G8Uint => (37, true),
+        Format::R16Uint => (38, true),
+        Format::R8Uint => (39, true),
+        _ => return None,
+    })
+}
+
+/// Returns true if the shader can generate the mipmaps of images of the given format on this
+/// device.
+pub fn supports_format(device: &Device, format: Format) -> bool {
+    let features = format.properties(device.physical_device()).optimal_tiling_features;
+    if !features.sampled_image || !features.storage_image {
+        return false;
+    }
+
+    match storage_format(format) {
+        Some((_, false)) => true,
+        Some((_, true)) => device.enabled_features().shader_storage_image_extended_formats,
+        None => false,
+    }
+}
+
+// Layout of the shader: the previous level as a sampled image at binding 0, and the level to
+// write as a storage image at binding 1.
+#[derive(Debug, Copy, Clone)]
+struct DownsampleLayout;
+
+unsafe impl PipelineLayoutDesc for DownsampleLayout {
+    #[inline]
+    fn num_sets(&self) -> usize {
+        1
+    }
+
+    #[inline]
+    fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+        if set == 0 { Some(2) } else { None }
+    }
+
+    fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        if set != 0 || binding >= 2 {
+            return None;
+        }
+
+        Some(DescriptorDesc {
+            ty: DescriptorDescTy::ImageAccess(DescriptorImageDesc {
+                sampled: binding == 0,
+                dimensions: DescriptorImageDescDimensions::TwoDimensional,
+                format: None,
+                multisampled: false,
+                array_layers: DescriptorImageDescArray::Arrayed { max_layers: None },
+            }),
+            array_count: 1,
+            stages: ShaderStages { compute: true, .. ShaderStages::none() },
+            readonly: binding == 0,
+        })
+    }
+
+    #[inline]
+    fn num_push_constants_ranges(&self) -> usize {
+        0
+    }
+
+    #[inline]
+    fn push_constants_range(&self, _: usize) -> Option<PipelineLayoutDescPcRange> {
+        None
+    }
+}
+
+unsafe impl PipelineLayoutDescNames for DownsampleLayout {
+    #[inline]
+    fn descriptor_by_name(&self, name: &str) -> Option<(usize, usize)> {
+        match name {
+            "src" => Some((0, 0)),
+            "dst" => Some((0, 1)),
+            _ => None,
+        }
+    }
+}
+
+// The shaders below are the SPIR-V for the following GLSL, with `T` being `vec4`, `ivec4` or
+// `uvec4` depending on the type of the format:
+//
+//     layout(local_size_x = 8, local_size_y = 8) in;
+//     layout(set = 0, binding = 0) uniform texture2DArray src;
+//     layout(set = 0, binding = 1, rgba8) uniform writeonly image2DArray dst;
+//
+//     void main() {
+//         ivec3 pos = ivec3(gl_GlobalInvocationID);
+//         if (any(greaterThanEqual(pos.xy, imageSize(dst).xy))) {
+//             return;
+//         }
+//
+//         // The last texel of the previous level is repeated if its size is odd.
+//         ivec2 last = textureSize(src, 0).xy - 1;
+//         ivec2 a = min(pos.xy * 2, last);
+//         ivec2 b = min(pos.xy * 2 + 1, last);
+//         T t0 = texelFetch(src, ivec3(a.x, a.y, pos.z), 0);
+//         T t1 = texelFetch(src, ivec3(b.x, a.y, pos.z), 0);
+//         T t2 = texelFetch(src, ivec3(a.x, b.y, pos.z), 0);
+//         T t3 = texelFetch(src, ivec3(b.x, b.y, pos.z), 0);
+//
+//         // Floats:
+//         imageStore(dst, pos, (t0 + t1 + t2 + t3) * 0.25);
+//         // Integers, averaged without overflowing:
+//         imageStore(dst, pos, (t0 >> 2) + (t1 >> 2) + (t2 >> 2) + (t3 >> 2) +
+//                              (((t0 & 3) + (t1 & 3) + (t2 & 3) + (t3 & 3)) >> 2));
+//     }
+//
+// The image format of `dst` is patched at `FLOAT_FORMAT_WORD` or `INT_FORMAT_WORD` to match the
+// image. The capability at `CAPABILITY_WORD` is `Matrix`, which is implied by `Shader`, and is
+// replaced with `StorageImageExtendedFormats` for the formats that need it.
+
+const CAPABILITY_WORD: usize = 10;
+const FLOAT_FORMAT_WORD: usize = 104;
+const INT_FORMAT_WORD: usize = 101;
+
+static FLOAT_SHADER: [u32; 373] = [
+    0x07230203, 0x00010000, 0x00000000, 0x00000042, 0x00000000, 0x00020011, 0x00000001, 0x00020011,
+    0x00000032, 0x00020011, 0x00000000, 0x0003000e, 0x00000000, 0x00000001, 0x0006000f, 0x00000005,
+    0x00000001, 0x6e69616d, 0x00000000, 0x00000002, 0x00060010, 0x00000001, 0x00000011, 0x00000008,
+    0x00000008, 0x00000001, 0x00040047, 0x00000002, 0x0000000b, 0x0000001c, 0x00040047, 0x00000003,
+    0x00000022, 0x00000000, 0x00040047, 0x00000003, 0x00000021, 0x00000000, 0x00040047, 0x00000004,
+    0x00000022, 0x00000000, 0x00040047, 0x00000004, 0x00000021, 0x00000001, 0x00030047, 0x00000004,
+    0x00000019, 0x00020013, 0x00000005, 0x00030021, 0x00000006, 0x00000005, 0x00020014, 0x00000007,
+    0x00040017, 0x00000008, 0x00000007, 0x00000002, 0x00040015, 0x00000009, 0x00000020, 0x00000001,
+    0x00040015, 0x0000000a, 0x00000020, 0x00000000, 0x00040017, 0x0000000b, 0x00000009, 0x00000002,
+    0x00040017, 0x0000000c, 0x00000009, 0x00000003, 0x00040017, 0x0000000d, 0x0000000a, 0x00000003,
+    0x00030016, 0x0000000e, 0x00000020, 0x00040017, 0x0000000f, 0x0000000e, 0x00000004, 0x00090019,
+    0x00000010, 0x0000000e, 0x00000001, 0x00000000, 0x00000001, 0x00000000, 0x00000001, 0x00000000,
+    0x00090019, 0x00000011, 0x0000000e, 0x00000001, 0x00000000, 0x00000001, 0x00000000, 0x00000002,
+    0x00000004, 0x00040020, 0x00000012, 0x00000000, 0x00000010, 0x00040020, 0x00000013, 0x00000000,
+    0x00000011, 0x00040020, 0x00000014, 0x00000001, 0x0000000d, 0x0004002b, 0x00000009, 0x00000015,
+    0x00000000, 0x0004002b, 0x00000009, 0x00000016, 0x00000001, 0x0004002b, 0x00000009, 0x00000017,
+    0x00000002, 0x0005002c, 0x0000000b, 0x00000018, 0x00000016, 0x00000016, 0x0005002c, 0x0000000b,
+    0x00000019, 0x00000017, 0x00000017, 0x0004002b, 0x0000000e, 0x0000001a, 0x3e800000, 0x0004003b,
+    0x00000014, 0x00000002, 0x00000001, 0x0004003b, 0x00000012, 0x00000003, 0x00000000, 0x0004003b,
+    0x00000013, 0x00000004, 0x00000000, 0x00050036, 0x00000005, 0x00000001, 0x00000000, 0x00000006,
+    0x000200f8, 0x0000001b, 0x0004003d, 0x0000000d, 0x0000001c, 0x00000002, 0x0004007c, 0x0000000c,
+    0x0000001d, 0x0000001c, 0x0004003d, 0x00000011, 0x0000001e, 0x00000004, 0x00040068, 0x0000000c,
+    0x0000001f, 0x0000001e, 0x0007004f, 0x0000000b, 0x00000020, 0x0000001d, 0x0000001d, 0x00000000,
+    0x00000001, 0x0007004f, 0x0000000b, 0x00000021, 0x0000001f, 0x0000001f, 0x00000000, 0x00000001,
+    0x000500af, 0x00000008, 0x00000022, 0x00000020, 0x00000021, 0x0004009a, 0x00000007, 0x00000023,
+    0x00000022, 0x000300f7, 0x00000026, 0x00000000, 0x000400fa, 0x00000023, 0x00000024, 0x00000025,
+    0x000200f8, 0x00000024, 0x000100fd, 0x000200f8, 0x00000025, 0x0004003d, 0x00000010, 0x00000027,
+    0x00000003, 0x00050067, 0x0000000c, 0x00000028, 0x00000027, 0x00000015, 0x0007004f, 0x0000000b,
+    0x00000029, 0x00000028, 0x00000028, 0x00000000, 0x00000001, 0x00050082, 0x0000000b, 0x0000002a,
+    0x00000029, 0x00000018, 0x00050084, 0x0000000b, 0x0000002b, 0x00000020, 0x00000019, 0x00050080,
+    0x0000000b, 0x0000002c, 0x0000002b, 0x00000018, 0x000500b1, 0x00000008, 0x0000002d, 0x0000002b,
+    0x0000002a, 0x000600a9, 0x0000000b, 0x0000002e, 0x0000002d, 0x0000002b, 0x0000002a, 0x000500b1,
+    0x00000008, 0x0000002f, 0x0000002c, 0x0000002a, 0x000600a9, 0x0000000b, 0x00000030, 0x0000002f,
+    0x0000002c, 0x0000002a, 0x00050051, 0x00000009, 0x00000031, 0x0000002e, 0x00000000, 0x00050051,
+    0x00000009, 0x00000032, 0x0000002e, 0x00000001, 0x00050051, 0x00000009, 0x00000033, 0x00000030,
+    0x00000000, 0x00050051, 0x00000009, 0x00000034, 0x00000030, 0x00000001, 0x00050051, 0x00000009,
+    0x00000035, 0x0000001d, 0x00000002, 0x00060050, 0x0000000c, 0x00000036, 0x00000031, 0x00000032,
+    0x00000035, 0x0007005f, 0x0000000f, 0x00000037, 0x00000027, 0x00000036, 0x00000002, 0x00000015,
+    0x00060050, 0x0000000c, 0x00000038, 0x00000033, 0x00000032, 0x00000035, 0x0007005f, 0x0000000f,
+    0x00000039, 0x00000027, 0x00000038, 0x00000002, 0x00000015, 0x00060050, 0x0000000c, 0x0000003a,
+    0x00000031, 0x00000034, 0x00000035, 0x0007005f, 0x0000000f, 0x0000003b, 0x00000027, 0x0000003a,
+    0x00000002, 0x00000015, 0x00060050, 0x0000000c, 0x0000003c, 0x00000033, 0x00000034, 0x00000035,
+    0x0007005f, 0x0000000f, 0x0000003d, 0x00000027, 0x0000003c, 0x00000002, 0x00000015, 0x00050081,
+    0x0000000f, 0x0000003e, 0x00000037, 0x00000039, 0x00050081, 0x0000000f, 0x0000003f, 0x0000003e,
+    0x0000003b, 0x00050081, 0x0000000f, 0x00000040, 0x0000003f, 0x0000003d, 0x0005008e, 0x0000000f,
+    0x00000041, 0x00000040, 0x0000001a, 0x00040063, 0x0000001e, 0x0000001d, 0x00000041, 0x000200f9,
+    0x00000026, 0x000200f8, 0x00000026, 0x000100fd, 0x00010038,
+];
+
+static UINT_SHADER: [u32; 448] = [
+    0x07230203, 0x00010000, 0x00000000, 0x00000050, 0x00000000, 0x00020011, 0x00000001, 0x00020011,
+    0x00000032, 0x00020011, 0x00000000, 0x0003000e, 0x00000000, 0x00000001, 0x0006000f, 0x00000005,
+    0x00000001, 0x6e69616d, 0x00000000, 0x00000002, 0x00060010, 0x00000001, 0x00000011, 0x00000008,
+    0x00000008, 0x00000001, 0x00040047, 0x00000002, 0x0000000b, 0x0000001c, 0x00040047, 0x00000003,
+    0x00000022, 0x00000000, 0x00040047, 0x00000003, 0x00000021, 0x00000000, 0x00040047, 0x00000004,
+    0x00000022, 0x00000000, 0x00040047, 0x00000004, 0x00000021, 0x00000001, 0x00030047, 0x00000004,
+    0x00000019, 0x00020013, 0x00000005, 0x00030021, 0x00000006, 0x00000005, 0x00020014, 0x00000007,
+    0x00040017, 0x00000008, 0x00000007, 0x00000002, 0x00040015, 0x00000009, 0x00000020, 0x00000001,
+    0x00040015, 0x0000000a, 0x00000020, 0x00000000, 0x00040017, 0x0000000b, 0x00000009, 0x00000002,
+    0x00040017, 0x0000000c, 0x00000009, 0x00000003, 0x00040017, 0x0000000d, 0x0000000a, 0x00000003,
+    0x00040017, 0x0000000e, 0x0000000a, 0x00000004, 0x00090019, 0x0000000f, 0x0000000a, 0x00000001,
+    0x00000000, 0x00000001, 0x00000000, 0x00000001, 0x00000000, 0x00090019, 0x00000010, 0x0000000a,
+    0x00000001, 0x00000000, 0x00000001, 0x00000000, 0x00000002, 0x00000004, 0x00040020, 0x00000011,
+    0x00000000, 0x0000000f, 0x00040020, 0x00000012, 0x00000000, 0x00000010, 0x00040020, 0x00000013,
+    0x00000001, 0x0000000d, 0x0004002b, 0x00000009, 0x00000014, 0x00000000, 0x0004002b, 0x00000009,
+    0x00000015, 0x00000001, 0x0004002b, 0x00000009, 0x00000016, 0x00000002, 0x0005002c, 0x0000000b,
+    0x00000017, 0x00000015, 0x00000015, 0x0005002c, 0x0000000b, 0x00000018, 0x00000016, 0x00000016,
+    0x0004002b, 0x0000000a, 0x00000019, 0x00000002, 0x0004002b, 0x0000000a, 0x0000001a, 0x00000003,
+    0x0007002c, 0x0000000e, 0x0000001b, 0x00000019, 0x00000019, 0x00000019, 0x00000019, 0x0007002c,
+    0x0000000e, 0x0000001c, 0x0000001a, 0x0000001a, 0x0000001a, 0x0000001a, 0x0004003b, 0x00000013,
+    0x00000002, 0x00000001, 0x0004003b, 0x00000011, 0x00000003, 0x00000000, 0x0004003b, 0x00000012,
+    0x00000004, 0x00000000, 0x00050036, 0x00000005, 0x00000001, 0x00000000, 0x00000006, 0x000200f8,
+    0x0000001d, 0x0004003d, 0x0000000d, 0x0000001e, 0x00000002, 0x0004007c, 0x0000000c, 0x0000001f,
+    0x0000001e, 0x0004003d, 0x00000010, 0x00000020, 0x00000004, 0x00040068, 0x0000000c, 0x00000021,
+    0x00000020, 0x0007004f, 0x0000000b, 0x00000022, 0x0000001f, 0x0000001f, 0x00000000, 0x00000001,
+    0x0007004f, 0x0000000b, 0x00000023, 0x00000021, 0x00000021, 0x00000000, 0x00000001, 0x000500af,
+    0x00000008, 0x00000024, 0x00000022, 0x00000023, 0x0004009a, 0x00000007, 0x00000025, 0x00000024,
+    0x000300f7, 0x00000028, 0x00000000, 0x000400fa, 0x00000025, 0x00000026, 0x00000027, 0x000200f8,
+    0x00000026, 0x000100fd, 0x000200f8, 0x00000027, 0x0004003d, 0x0000000f, 0x00000029, 0x00000003,
+    0x00050067, 0x0000000c, 0x0000002a, 0x00000029, 0x00000014, 0x0007004f, 0x0000000b, 0x0000002b,
+    0x0000002a, 0x0000002a, 0x00000000, 0x00000001, 0x00050082, 0x0000000b, 0x0000002c, 0x0000002b,
+    0x00000017, 0x00050084, 0x0000000b, 0x0000002d, 0x00000022, 0x00000018, 0x00050080, 0x0000000b,
+    0x0000002e, 0x0000002d, 0x00000017, 0x000500b1, 0x00000008, 0x0000002f, 0x0000002d, 0x0000002c,
+    0x000600a9, 0x0000000b, 0x00000030, 0x0000002f, 0x0000002d, 0x0000002c, 0x000500b1, 0x00000008,
+    0x00000031, 0x0000002e, 0x0000002c, 0x000600a9, 0x0000000b, 0x00000032, 0x00000031, 0x0000002e,
+    0x0000002c, 0x00050051, 0x00000009, 0x00000033, 0x00000030, 0x00000000, 0x00050051, 0x00000009,
+    0x00000034, 0x00000030, 0x00000001, 0x00050051, 0x00000009, 0x00000035, 0x00000032, 0x00000000,
+    0x00050051, 0x00000009, 0x00000036, 0x00000032, 0x00000001, 0x00050051, 0x00000009, 0x00000037,
+    0x0000001f, 0x00000002, 0x00060050, 0x0000000c, 0x00000038, 0x00000033, 0x00000034, 0x00000037,
+    0x0007005f, 0x0000000e, 0x00000039, 0x00000029, 0x00000038, 0x00000002, 0x00000014, 0x00060050,
+    0x0000000c, 0x0000003a, 0x00000035, 0x00000034, 0x00000037, 0x0007005f, 0x0000000e, 0x0000003b,
+    0x00000029, 0x0000003a, 0x00000002, 0x00000014, 0x00060050, 0x0000000c, 0x0000003c, 0x00000033,
+    0x00000036, 0x00000037, 0x0007005f, 0x0000000e, 0x0000003d, 0x00000029, 0x0000003c, 0x00000002,
+    0x00000014, 0x00060050, 0x0000000c, 0x0000003e, 0x00000035, 0x00000036, 0x00000037, 0x0007005f,
+    0x0000000e, 0x0000003f, 0x00000029, 0x0000003e, 0x00000002, 0x00000014, 0x000500c2, 0x0000000e,
+    0x00000040, 0x00000039, 0x0000001b, 0x000500c7, 0x0000000e, 0x00000041, 0x00000039, 0x0000001c,
+    0x000500c2, 0x0000000e, 0x00000042, 0x0000003b, 0x0000001b, 0x000500c7, 0x0000000e, 0x00000043,
+    0x0000003b, 0x0000001c, 0x000500c2, 0x0000000e, 0x00000044, 0x0000003d, 0x0000001b, 0x000500c7,
+    0x0000000e, 0x00000045, 0x0000003d, 0x0000001c, 0x000500c2, 0x0000000e, 0x00000046, 0x0000003f,
+    0x0000001b, 0x000500c7, 0x0000000e, 0x00000047, 0x0000003f, 0x0000001c, 0x00050080, 0x0000000e,
+    0x00000048, 0x00000040, 0x00000042, 0x00050080, 0x0000000e, 0x00000049, 0x00000048, 0x00000044,
+    0x00050080, 0x0000000e, 0x0000004a, 0x00000049, 0x00000046, 0x00050080, 0x0000000e, 0x0000004b,
+    0x00000041, 0x00000043, 0x00050080, 0x0000000e, 0x0000004c, 0x0000004b, 0x00000045, 0x00050080,
+    0x0000000e, 0x0000004d, 0x0000004c, 0x00000047, 0x000500c2, 0x0000000e, 0x0000004e, 0x0000004d,
+    0x0000001b, 0x00050080, 0x0000000e, 0x0000004f, 0x0000004a, 0x0000004e, 0x00040063, 0x00000020,
+    0x0000001f, 0x0000004f, 0x000200f9, 0x00000028, 0x000200f8, 0x00000028, 0x000100fd, 0x00010038,
+];
+
+static SINT_SHADER: [u32; 444] = [
+    0x07230203, 0x00010000, 0x00000000, 0x0000004f, 0x00000000, 0x00020011, 0x00000001, 0x00020011,
+    0x00000032, 0x00020011, 0x00000000, 0x0003000e, 0x00000000, 0x00000001, 0x0006000f, 0x00000005,
+    0x00000001, 0x6e69616d, 0x00000000, 0x00000002, 0x00060010, 0x00000001, 0x00000011, 0x00000008,
+    0x00000008, 0x00000001, 0x00040047, 0x00000002, 0x0000000b, 0x0000001c, 0x00040047, 0x00000003,
+    0x00000022, 0x00000000, 0x00040047, 0x00000003, 0x00000021, 0x00000000, 0x00040047, 0x00000004,
+    0x00000022, 0x00000000, 0x00040047, 0x00000004, 0x00000021, 0x00000001, 0x00030047, 0x00000004,
+    0x00000019, 0x00020013, 0x00000005, 0x00030021, 0x00000006, 0x00000005, 0x00020014, 0x00000007,
+    0x00040017, 0x00000008, 0x00000007, 0x00000002, 0x00040015, 0x00000009, 0x00000020, 0x00000001,
+    0x00040015, 0x0000000a, 0x00000020, 0x00000000, 0x00040017, 0x0000000b, 0x00000009, 0x00000002,
+    0x00040017, 0x0000000c, 0x00000009, 0x00000003, 0x00040017, 0x0000000d, 0x0000000a, 0x00000003,
+    0x00040017, 0x0000000e, 0x00000009, 0x00000004, 0x00090019, 0x0000000f, 0x00000009, 0x00000001,
+    0x00000000, 0x00000001, 0x00000000, 0x00000001, 0x00000000, 0x00090019, 0x00000010, 0x00000009,
+    0x00000001, 0x00000000, 0x00000001, 0x00000000, 0x00000002, 0x00000004, 0x00040020, 0x00000011,
+    0x00000000, 0x0000000f, 0x00040020, 0x00000012, 0x00000000, 0x00000010, 0x00040020, 0x00000013,
+    0x00000001, 0x0000000d, 0x0004002b, 0x00000009, 0x00000014, 0x00000000, 0x0004002b, 0x00000009,
+    0x00000015, 0x00000001, 0x0004002b, 0x00000009, 0x00000016, 0x00000002, 0x0005002c, 0x0000000b,
+    0x00000017, 0x00000015, 0x00000015, 0x0005002c, 0x0000000b, 0x00000018, 0x00000016, 0x00000016,
+    0x0004002b, 0x00000009, 0x00000019, 0x00000003, 0x0007002c, 0x0000000e, 0x0000001a, 0x00000016,
+    0x00000016, 0x00000016, 0x00000016, 0x0007002c, 0x0000000e, 0x0000001b, 0x00000019, 0x00000019,
+    0x00000019, 0x00000019, 0x0004003b, 0x00000013, 0x00000002, 0x00000001, 0x0004003b, 0x00000011,
+    0x00000003, 0x00000000, 0x0004003b, 0x00000012, 0x00000004, 0x00000000, 0x00050036, 0x00000005,
+    0x00000001, 0x00000000, 0x00000006, 0x000200f8, 0x0000001c, 0x0004003d, 0x0000000d, 0x0000001d,
+    0x00000002, 0x0004007c, 0x0000000c, 0x0000001e, 0x0000001d, 0x0004003d, 0x00000010, 0x0000001f,
+    0x00000004, 0x00040068, 0x0000000c, 0x00000020, 0x0000001f, 0x0007004f, 0x0000000b, 0x00000021,
+    0x0000001e, 0x0000001e, 0x00000000, 0x00000001, 0x0007004f, 0x0000000b, 0x00000022, 0x00000020,
+    0x00000020, 0x00000000, 0x00000001, 0x000500af, 0x00000008, 0x00000023, 0x00000021, 0x00000022,
+    0x0004009a, 0x00000007, 0x00000024, 0x00000023, 0x000300f7, 0x00000027, 0x00000000, 0x000400fa,
+    0x00000024, 0x00000025, 0x00000026, 0x000200f8, 0x00000025, 0x000100fd, 0x000200f8, 0x00000026,
+    0x0004003d, 0x0000000f, 0x00000028, 0x00000003, 0x00050067, 0x0000000c, 0x00000029, 0x00000028,
+    0x00000014, 0x0007004f, 0x0000000b, 0x0000002a, 0x00000029, 0x00000029, 0x00000000, 0x00000001,
+    0x00050082, 0x0000000b, 0x0000002b, 0x0000002a, 0x00000017, 0x00050084, 0x0000000b, 0x0000002c,
+    0x00000021, 0x00000018, 0x00050080, 0x0000000b, 0x0000002d, 0x0000002c, 0x00000017, 0x000500b1,
+    0x00000008, 0x0000002e, 0x0000002c, 0x0000002b, 0x000600a9, 0x0000000b, 0x0000002f, 0x0000002e,
+    0x0000002c, 0x0000002b, 0x000500b1, 0x00000008, 0x00000030, 0x0000002d, 0x0000002b, 0x000600a9,
+    0x0000000b, 0x00000031, 0x00000030, 0x0000002d, 0x0000002b, 0x00050051, 0x00000009, 0x00000032,
+    0x0000002f, 0x00000000, 0x00050051, 0x00000009, 0x00000033, 0x0000002f, 0x00000001, 0x00050051,
+    0x00000009, 0x00000034, 0x00000031, 0x00000000, 0x00050051, 0x00000009, 0x00000035, 0x00000031,
+    0x00000001, 0x00050051, 0x00000009, 0x00000036, 0x0000001e, 0x00000002, 0x00060050, 0x0000000c,
+    0x00000037, 0x00000032, 0x00000033, 0x00000036, 0x0007005f, 0x0000000e, 0x00000038, 0x00000028,
+    0x00000037, 0x00000002, 0x00000014, 0x00060050, 0x0000000c, 0x00000039, 0x00000034, 0x00000033,
+    0x00000036, 0x0007005f, 0x0000000e, 0x0000003a, 0x00000028, 0x00000039, 0x00000002, 0x00000014,
+    0x00060050, 0x0000000c, 0x0000003b, 0x00000032, 0x00000035, 0x00000036, 0x0007005f, 0x0000000e,
+    0x0000003c, 0x00000028, 0x0000003b, 0x00000002, 0x00000014, 0x00060050, 0x0000000c, 0x0000003d,
+    0x00000034, 0x00000035, 0x00000036, 0x0007005f, 0x0000000e, 0x0000003e, 0x00000028, 0x0000003d,
+    0x00000002, 0x00000014, 0x000500c3, 0x0000000e, 0x0000003f, 0x00000038, 0x0000001a, 0x000500c7,
+    0x0000000e, 0x00000040, 0x00000038, 0x0000001b, 0x000500c3, 0x0000000e, 0x00000041, 0x0000003a,
+    0x0000001a, 0x000500c7, 0x0000000e, 0x00000042, 0x0000003a, 0x0000001b, 0x000500c3, 0x0000000e,
+    0x00000043, 0x0000003c, 0x0000001a, 0x000500c7, 0x0000000e, 0x00000044, 0x0000003c, 0x0000001b,
+    0x000500c3, 0x0000000e, 0x00000045, 0x0000003e, 0x0000001a, 0x000500c7, 0x0000000e, 0x00000046,
+    0x0000003e, 0x0000001b, 0x00050080, 0x0000000e, 0x00000047, 0x0000003f, 0x00000041, 0x00050080,
+    0x0000000e, 0x00000048, 0x00000047, 0x00000043, 0x00050080, 0x0000000e, 0x00000049, 0x00000048,
+    0x00000045, 0x00050080, 0x0000000e, 0x0000004a, 0x00000040, 0x00000042, 0x00050080, 0x0000000e,
+    0x0000004b, 0x0000004a, 0x00000044, 0x00050080, 0x0000000e, 0x0000004c, 0x0000004b, 0x00000046,
+    0x000500c3, 0x0000000e, 0x0000004d, 0x0000004c, 0x0000001a, 0x00050080, 0x0000000e, 0x0000004e,
+    0x00000049, 0x0000004d, 0x00040063, 0x0000001f, 0x0000001e, 0x0000004e, 0x000200f9, 0x00000027,
+    0x000200f8, 0x00000027, 0x000100fd, 0x00010038,
+];
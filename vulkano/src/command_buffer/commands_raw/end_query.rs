@@ -0,0 +1,131 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
+use command_buffer::cb::UnsafeCommandBufferBuilder;
+use command_buffer::pool::CommandPool;
+use device::Device;
+use device::DeviceOwned;
+use query::QueryPoolAbstract;
+use query::QueryType;
+use VulkanObject;
+use VulkanPointers;
+use vk;
+
+/// Command that ends a query that was started with `CmdBeginQuery`.
+pub struct CmdEndQuery<Q> {
+    // The query pool.
+    pool: Q,
+    // Raw query pool handle.
+    raw_pool: vk::QueryPool,
+    // Index of the query within the pool.
+    query: u32,
+}
+
+impl<Q> CmdEndQuery<Q>
+    where Q: QueryPoolAbstract
+{
+    /// Builds a command that ends the query `query` of `pool`.
+    pub fn new(pool: Q, query: u32) -> Result<CmdEndQuery<Q>, CmdEndQueryError> {
+        let raw_pool = {
+            let inner = pool.inner();
+
+            if query >= inner.num_slots() {
+                return Err(CmdEndQueryError::OutOfRange);
+            }
+
+            if let QueryType::Timestamp = inner.ty() {
+                return Err(CmdEndQueryError::TimestampQuery);
+            }
+
+            inner.internal_object()
+        };
+
+        Ok(CmdEndQuery {
+            pool: pool,
+            raw_pool: raw_pool,
+            query: query,
+        })
+    }
+}
+
+impl<Q> CmdEndQuery<Q> {
+    /// Returns the query pool.
+    #[inline]
+    pub fn pool(&self) -> &Q {
+        &self.pool
+    }
+
+    /// Returns the index of the query within the pool.
+    #[inline]
+    pub fn query(&self) -> u32 {
+        self.query
+    }
+}
+
+unsafe impl<Q> DeviceOwned for CmdEndQuery<Q>
+    where Q: QueryPoolAbstract
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.pool.inner().device()
+    }
+}
+
+unsafe impl<'a, P, Q> AddCommand<&'a CmdEndQuery<Q>> for UnsafeCommandBufferBuilder<P>
+    where P: CommandPool
+{
+    type Out = UnsafeCommandBufferBuilder<P>;
+
+    #[inline]
+    fn add(self, command: &'a CmdEndQuery<Q>) -> Result<Self::Out, CommandAddError> {
+        unsafe {
+            let vk = self.device().pointers();
+            let cmd = self.internal_object();
+            vk.CmdEndQuery(cmd, command.raw_pool, command.query);
+        }
+
+        Ok(self)
+    }
+}
+
+/// Error that can happen when creating a `CmdEndQuery`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CmdEndQueryError {
+    /// The query index is out of the range of the pool.
+    OutOfRange,
+    /// Timestamp queries can't be ended.
+    TimestampQuery,
+}
+
+impl error::Error for CmdEndQueryError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CmdEndQueryError::OutOfRange => {
+                "the query index is out of the range of the pool"
+            },
+            CmdEndQueryError::TimestampQuery => {
+                "timestamp queries can't be ended"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CmdEndQueryError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
@@ -7,14 +7,19 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::error;
+use std::fmt;
 use std::sync::Arc;
 use smallvec::SmallVec;
 
+use command_buffer::CommandBuffer;
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::UnsafeCommandBufferBuilder;
 use command_buffer::pool::CommandPool;
 use device::Device;
 use device::DeviceOwned;
+use framebuffer::RenderPassCompatibility;
 use VulkanObject;
 use VulkanPointers;
 use vk;
@@ -23,25 +28,56 @@ use vk;
 pub struct CmdExecuteCommands<Cb> {
     // Raw list of command buffers to execute.
     raw_list: SmallVec<[vk::CommandBuffer; 4]>,
+    // Render pass and subpass the command buffer was created for, if any.
+    inheritance_subpass: Option<(Arc<RenderPassCompatibility>, u32)>,
     // Command buffer to execute.
     command_buffer: Cb,
 }
 
-impl<Cb> CmdExecuteCommands<Cb> {
+impl<Cb> CmdExecuteCommands<Cb> where Cb: CommandBuffer {
     /// See the documentation of the `execute_commands` method.
+    ///
+    /// Returns an error if the command buffer is not a secondary command buffer.
     #[inline]
-    pub fn new(command_buffer: Cb) -> CmdExecuteCommands<Cb> {
-        unimplemented!()        // TODO:
-        /*let raw_list = {
+    pub fn new(command_buffer: Cb) -> Result<CmdExecuteCommands<Cb>, CmdExecuteCommandsError> {
+        let (raw_list, inheritance_subpass) = {
+            let inner = command_buffer.inner();
+            if !inner.is_secondary() {
+                return Err(CmdExecuteCommandsError::NotSecondary);
+            }
+
             let mut l = SmallVec::new();
-            l.push(command_buffer.inner());
-            l
+            l.push(inner.internal_object());
+            (l, inner.inheritance_subpass())
         };
 
-        CmdExecuteCommands {
+        Ok(CmdExecuteCommands {
             raw_list: raw_list,
+            inheritance_subpass: inheritance_subpass,
             command_buffer: command_buffer,
-        }*/
+        })
+    }
+}
+
+impl<Cb> CmdExecuteCommands<Cb> {
+    /// Returns the command buffer to execute.
+    #[inline]
+    pub fn command_buffer(&self) -> &Cb {
+        &self.command_buffer
+    }
+
+    /// Returns true if the command buffer must be executed inside of a render pass.
+    #[inline]
+    pub fn inside_render_pass(&self) -> bool {
+        self.inheritance_subpass.is_some()
+    }
+
+    /// Returns the summary of the render pass and the index of the subpass the command buffer was
+    /// created for, if any.
+    #[doc(hidden)]
+    #[inline]
+    pub fn inheritance_subpass(&self) -> Option<&(Arc<RenderPassCompatibility>, u32)> {
+        self.inheritance_subpass.as_ref()
     }
 }
 
@@ -60,13 +96,131 @@ unsafe impl<'a, P, Cb> AddCommand<&'a CmdExecuteCommands<Cb>> for UnsafeCommandB
     type Out = UnsafeCommandBufferBuilder<P>;
 
     #[inline]
-    fn add(self, command: &'a CmdExecuteCommands<Cb>) -> Self::Out {
+    fn add(self, command: &'a CmdExecuteCommands<Cb>) -> Result<Self::Out, CommandAddError> {
         unsafe {
             let vk = self.device().pointers();
             let cmd = self.internal_object();
             vk.CmdExecuteCommands(cmd, command.raw_list.len() as u32, command.raw_list.as_ptr());
         }
 
-        self
+        Ok(self)
+    }
+}
+
+/// Error that can happen when creating a `CmdExecuteCommands`.
+#[derive(Debug, Copy, Clone)]
+pub enum CmdExecuteCommandsError {
+    /// Only secondary command buffers can be executed.
+    NotSecondary,
+}
+
+impl error::Error for CmdExecuteCommandsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CmdExecuteCommandsError::NotSecondary => {
+                "only secondary command buffers can be executed"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CmdExecuteCommandsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandAddError;
+    use command_buffer::CommandBufferBuilder;
+    use command_buffer::CommandBufferBuilderError;
+    use command_buffer::commands_raw::CmdExecuteCommands;
+    use command_buffer::commands_raw::CmdExecuteCommandsError;
+    use framebuffer::EmptySinglePassRenderPassDesc;
+    use framebuffer::Framebuffer;
+    use framebuffer::RenderPass;
+    use framebuffer::Subpass;
+    use mock;
+
+    #[test]
+    fn execute_secondary() {
+        let (device, queue) = mock_dev_and_queue!(queue: |q| q.supports_compute());
+        let family = queue.family();
+
+        let buffer = CpuAccessibleBuffer::from_data(&device, &BufferUsage::all(), Some(family),
+                                                    0u32).unwrap();
+
+        let secondary = AutoCommandBufferBuilder::secondary_compute(device.clone(), family)
+            .unwrap()
+            .fill_buffer(buffer.clone(), 5).unwrap()
+            .build().unwrap();
+
+        let _cb = AutoCommandBufferBuilder::new(device.clone(), family).unwrap()
+            .execute_commands(Arc::new(secondary)).unwrap()
+            .build().unwrap();
+
+        let calls = mock::calls(&device);
+        assert!(calls.iter().any(|c| *c == "CmdExecuteCommands"));
+    }
+
+    #[test]
+    fn primary_rejected() {
+        let (device, queue) = mock_dev_and_queue!(queue: |q| q.supports_compute());
+        let family = queue.family();
+
+        let primary = AutoCommandBufferBuilder::new(device.clone(), family).unwrap()
+            .build().unwrap();
+
+        match CmdExecuteCommands::new(primary) {
+            Err(CmdExecuteCommandsError::NotSecondary) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn compatible_render_pass() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        // Two distinct render pass objects that are built from the same description.
+        let rp1 = Arc::new(RenderPass::new(device.clone(), EmptySinglePassRenderPassDesc).unwrap());
+        let rp2 = Arc::new(RenderPass::new(device.clone(), EmptySinglePassRenderPassDesc).unwrap());
+        let framebuffer = Framebuffer::new(rp2, [32, 32, 1], ()).unwrap();
+
+        let secondary = AutoCommandBufferBuilder::secondary_graphics(device.clone(), queue.family(),
+                                                                     Subpass::from(rp1, 0).unwrap())
+            .unwrap()
+            .build().unwrap();
+
+        let _cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .begin_render_pass(framebuffer, true, ()).unwrap()
+            .execute_commands(Arc::new(secondary)).unwrap()
+            .end_render_pass().unwrap()
+            .build().unwrap();
+    }
+
+    #[test]
+    fn render_pass_secondary_outside_render_pass() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let rp = Arc::new(RenderPass::new(device.clone(), EmptySinglePassRenderPassDesc).unwrap());
+        let secondary = AutoCommandBufferBuilder::secondary_graphics(device.clone(), queue.family(),
+                                                                     Subpass::from(rp, 0).unwrap())
+            .unwrap()
+            .build().unwrap();
+
+        let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        match cb.execute_commands(Arc::new(secondary)) {
+            Err(CommandBufferBuilderError::CommandAddError(err)) => {
+                assert_eq!(err, CommandAddError::IncompatibleSubpass);
+            },
+            _ => panic!()
+        }
     }
 }
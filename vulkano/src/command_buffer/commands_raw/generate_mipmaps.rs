@@ -0,0 +1,502 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::ptr;
+use std::sync::Arc;
+use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
+use command_buffer::cb::UnsafeCommandBufferBuilder;
+use command_buffer::commands_raw::CmdPipelineBarrier;
+use command_buffer::commands_raw::aspect_mask;
+use command_buffer::commands_raw::downsample;
+use command_buffer::commands_raw::downsample::Downsampler;
+use command_buffer::pool::CommandPool;
+use device::Device;
+use device::DeviceOwned;
+use format::Format;
+use image::ImageAccess;
+use image::ImageDimensions;
+use image::Layout;
+use sampler::Filter;
+use sync::AccessFlagBits;
+use sync::PipelineStages;
+use OomError;
+use VulkanObject;
+use VulkanPointers;
+use vk;
+
+/// Command that fills the mipmap levels of an image by successively downsampling each level to
+/// the next one.
+///
+/// The first mipmap level must already contain the content of the image. All the array layers of
+/// the image are processed. The image must be in the `TransferDstOptimal` layout before the
+/// command, and is left in this layout afterwards.
+///
+/// If the format of the image supports blitting with a linear filter, each level is transitioned
+/// to the `TransferSrcOptimal` layout and blitted to the next one. Depth and stencil images are
+/// always blitted with a nearest filter, as required by Vulkan.
+///
+/// Otherwise each level of a color image is computed from the previous one by a compute shader
+/// that averages 2x2 texels, which requires a queue that supports compute operations. This is
+/// only possible for two-dimensional images that have the sampled and storage usages, and whose
+/// format is supported as a storage image. The formats that need the
+/// `StorageImageExtendedFormats` SPIR-V capability also require the
+/// `shader_storage_image_extended_formats` feature to be enabled.
+///
+/// > **Note**: Contrary to the other commands of this module, this command doesn't correspond to
+/// > a single Vulkan command but to a chain of pipeline barriers and blits or dispatches.
+#[derive(Debug, Clone)]
+pub struct CmdGenerateMipmaps<I> {
+    // The image.
+    image: I,
+    // Raw image.
+    image_raw: vk::Image,
+    aspect_mask: vk::ImageAspectFlags,
+    // Width, height and depth of each mipmap level.
+    levels: Vec<[u32; 3]>,
+    array_layers: u32,
+    method: Method,
+}
+
+#[derive(Debug, Clone)]
+enum Method {
+    // Blits with the given filter.
+    Blit(vk::Filter),
+    // Dispatches of the compute shader. `None` if the image has a single mipmap level, in which
+    // case there is nothing to do.
+    Compute(Option<Arc<Downsampler>>),
+}
+
+impl CmdGenerateMipmaps<()> {
+    /// Returns true if the compute shader can generate the mipmaps of color images of the given
+    /// format on this device.
+    ///
+    /// The compute shader is used for the color formats that can't be blitted with a linear
+    /// filter.
+    #[inline]
+    pub fn supports_compute(device: &Device, format: Format) -> bool {
+        downsample::supports_format(device, format)
+    }
+}
+
+impl<I> CmdGenerateMipmaps<I> where I: ImageAccess {
+    /// Builds a new command.
+    pub fn new(image: I) -> Result<CmdGenerateMipmaps<I>, CmdGenerateMipmapsError> {
+        if image.samples() != 1 {
+            return Err(CmdGenerateMipmapsError::Multisampled);
+        }
+
+        let dimensions = image.dimensions();
+
+        let (image_raw, method) = {
+            let inner = image.inner();
+            let blit = inner.supports_blit_source() && inner.supports_blit_destination();
+
+            let method = if image.has_color() && !(blit && inner.supports_linear_filtering()) {
+                if !downsample::supports_format(inner.device(), inner.format()) {
+                    return Err(CmdGenerateMipmapsError::FormatNotSupported);
+                }
+                if !inner.usage_transfer_dest() {
+                    return Err(CmdGenerateMipmapsError::MissingTransferUsage);
+                }
+                if !inner.usage_sampled() || !inner.usage_storage() {
+                    return Err(CmdGenerateMipmapsError::MissingComputeUsage);
+                }
+                match dimensions {
+                    ImageDimensions::Dim2d { .. } => (),
+                    _ => return Err(CmdGenerateMipmapsError::UnsupportedDimensions),
+                }
+
+                if inner.mipmap_levels() >= 2 {
+                    Method::Compute(Some(Arc::new(try!(Downsampler::new(inner)))))
+                } else {
+                    Method::Compute(None)
+                }
+            } else {
+                if !inner.usage_transfer_src() || !inner.usage_transfer_dest() {
+                    return Err(CmdGenerateMipmapsError::MissingTransferUsage);
+                }
+                if !blit {
+                    return Err(CmdGenerateMipmapsError::FormatNotSupported);
+                }
+
+                // Depth and stencil formats can only be blitted with a nearest filter.
+                let filter = if image.has_color() { Filter::Linear } else { Filter::Nearest };
+                Method::Blit(filter as u32)
+            };
+
+            (inner.internal_object(), method)
+        };
+
+        let levels = (0 .. image.inner().mipmap_levels()).map(|level| {
+            dimensions.mipmap_width_height_depth(level).unwrap()
+        }).collect();
+
+        Ok(CmdGenerateMipmaps {
+            image_raw: image_raw,
+            aspect_mask: aspect_mask(&image),
+            levels: levels,
+            array_layers: dimensions.array_layers(),
+            method: method,
+            image: image,
+        })
+    }
+}
+
+impl<I> CmdGenerateMipmaps<I> {
+    /// Returns the image.
+    #[inline]
+    pub fn image(&self) -> &I {
+        &self.image
+    }
+
+    /// Returns the number of blits that the command performs. Always 0 if the command uses a
+    /// compute shader.
+    #[inline]
+    pub fn num_blits(&self) -> u32 {
+        match self.method {
+            Method::Blit(_) => self.levels.len() as u32 - 1,
+            Method::Compute(_) => 0,
+        }
+    }
+
+    /// Returns true if the mipmaps are generated with a compute shader instead of blits.
+    ///
+    /// If true, the command must be executed on a queue that supports compute operations, and
+    /// it accesses the image from the compute shader stage instead of the transfer stage.
+    #[inline]
+    pub fn uses_compute(&self) -> bool {
+        match self.method {
+            Method::Blit(_) => false,
+            Method::Compute(_) => true,
+        }
+    }
+}
+
+unsafe impl<I> DeviceOwned for CmdGenerateMipmaps<I> where I: ImageAccess {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.image.inner().device()
+    }
+}
+
+unsafe impl<'a, P, I> AddCommand<&'a CmdGenerateMipmaps<I>> for UnsafeCommandBufferBuilder<P>
+    where P: CommandPool, I: ImageAccess
+{
+    type Out = UnsafeCommandBufferBuilder<P>;
+
+    fn add(self, command: &'a CmdGenerateMipmaps<I>) -> Result<Self::Out, CommandAddError> {
+        match command.method {
+            Method::Blit(filter) => add_blits(self, command, filter),
+            Method::Compute(Some(ref downsampler)) => add_dispatches(self, command, downsampler),
+            Method::Compute(None) => Ok(self),
+        }
+    }
+}
+
+// Records the barriers and the blits that generate the mipmaps.
+fn add_blits<P, I>(cb: UnsafeCommandBufferBuilder<P>, command: &CmdGenerateMipmaps<I>,
+                   filter: vk::Filter)
+                   -> Result<UnsafeCommandBufferBuilder<P>, CommandAddError>
+    where P: CommandPool, I: ImageAccess
+{
+    let transfer = PipelineStages { transfer: true, .. PipelineStages::none() };
+    let read = AccessFlagBits { transfer_read: true, .. AccessFlagBits::none() };
+    let write = AccessFlagBits { transfer_write: true, .. AccessFlagBits::none() };
+    let layers = 0 .. command.array_layers;
+    let num_levels = command.levels.len() as u32;
+
+    let mut cb = cb;
+
+    for level in 1 .. num_levels {
+        // The previous level has just been written. Transition it to the source layout.
+        cb = {
+            let mut barrier = CmdPipelineBarrier::new();
+            unsafe {
+                barrier.add_image_memory_barrier(&command.image, level - 1 .. level,
+                                                 layers.clone(), transfer, write, transfer,
+                                                 read, false, None,
+                                                 Layout::TransferDstOptimal,
+                                                 Layout::TransferSrcOptimal);
+            }
+            try!(cb.add(&barrier))
+        };
+
+        let src = command.levels[level as usize - 1];
+        let dst = command.levels[level as usize];
+
+        let region = vk::ImageBlit {
+            srcSubresource: vk::ImageSubresourceLayers {
+                aspectMask: command.aspect_mask,
+                mipLevel: level - 1,
+                baseArrayLayer: 0,
+                layerCount: command.array_layers,
+            },
+            srcOffsets: [
+                vk::Offset3D { x: 0, y: 0, z: 0 },
+                vk::Offset3D { x: src[0] as i32, y: src[1] as i32, z: src[2] as i32 },
+            ],
+            dstSubresource: vk::ImageSubresourceLayers {
+                aspectMask: command.aspect_mask,
+                mipLevel: level,
+                baseArrayLayer: 0,
+                layerCount: command.array_layers,
+            },
+            dstOffsets: [
+                vk::Offset3D { x: 0, y: 0, z: 0 },
+                vk::Offset3D { x: dst[0] as i32, y: dst[1] as i32, z: dst[2] as i32 },
+            ],
+        };
+
+        unsafe {
+            let vk = cb.device().pointers();
+            let raw = cb.internal_object();
+            vk.CmdBlitImage(raw, command.image_raw, vk::IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL,
+                            command.image_raw, vk::IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL,
+                            1, &region as *const _, filter);
+        }
+    }
+
+    // Transition all the levels but the last one back to the destination layout, so that
+    // the whole image is in the same layout as before the command.
+    if num_levels >= 2 {
+        cb = {
+            let mut barrier = CmdPipelineBarrier::new();
+            unsafe {
+                barrier.add_image_memory_barrier(&command.image, 0 .. num_levels - 1, layers,
+                                                 transfer, read, transfer, write, false, None,
+                                                 Layout::TransferSrcOptimal,
+                                                 Layout::TransferDstOptimal);
+            }
+            try!(cb.add(&barrier))
+        };
+    }
+
+    Ok(cb)
+}
+
+// Records the barriers and the dispatches of the compute shader that generate the mipmaps.
+//
+// Each level is in the `General` layout while it is written, then in the `ShaderReadOnlyOptimal`
+// layout while the next level is computed from it.
+fn add_dispatches<P, I>(cb: UnsafeCommandBufferBuilder<P>, command: &CmdGenerateMipmaps<I>,
+                        downsampler: &Downsampler)
+                        -> Result<UnsafeCommandBufferBuilder<P>, CommandAddError>
+    where P: CommandPool, I: ImageAccess
+{
+    let compute = PipelineStages { compute_shader: true, .. PipelineStages::none() };
+    let read = AccessFlagBits { shader_read: true, .. AccessFlagBits::none() };
+    let write = AccessFlagBits { shader_write: true, .. AccessFlagBits::none() };
+    let read_write = AccessFlagBits { shader_read: true, shader_write: true,
+                                      .. AccessFlagBits::none() };
+    let layers = 0 .. command.array_layers;
+    let num_levels = command.levels.len() as u32;
+
+    let mut cb = cb;
+
+    unsafe {
+        let vk = cb.device().pointers();
+        vk.CmdBindPipeline(cb.internal_object(), vk::PIPELINE_BIND_POINT_COMPUTE,
+                           downsampler.pipeline());
+    }
+
+    for level in 1 .. num_levels {
+        // The content of the first level has been made visible to the compute shader stage
+        // before the command. The other levels have just been written by the shader.
+        let (src_access, src_layout) = if level == 1 {
+            (AccessFlagBits::none(), Layout::TransferDstOptimal)
+        } else {
+            (write, Layout::General)
+        };
+
+        cb = {
+            let mut barrier = CmdPipelineBarrier::new();
+            unsafe {
+                barrier.add_image_memory_barrier(&command.image, level - 1 .. level,
+                                                 layers.clone(), compute, src_access, compute,
+                                                 read, false, None, src_layout,
+                                                 Layout::ShaderReadOnlyOptimal);
+                barrier.add_image_memory_barrier(&command.image, level .. level + 1,
+                                                 layers.clone(), compute,
+                                                 AccessFlagBits::none(), compute, write, false,
+                                                 None, Layout::TransferDstOptimal,
+                                                 Layout::General);
+            }
+            try!(cb.add(&barrier))
+        };
+
+        let dst = command.levels[level as usize];
+
+        unsafe {
+            let vk = cb.device().pointers();
+            let raw = cb.internal_object();
+            let set = downsampler.descriptor_set(level);
+            vk.CmdBindDescriptorSets(raw, vk::PIPELINE_BIND_POINT_COMPUTE,
+                                     downsampler.pipeline_layout(), 0, 1, &set, 0, ptr::null());
+            vk.CmdDispatch(raw, (dst[0] + 7) / 8, (dst[1] + 7) / 8, command.array_layers);
+        }
+    }
+
+    // Transition all the levels back to the destination layout, so that the whole image is in
+    // the same layout as before the command.
+    if num_levels >= 2 {
+        cb = {
+            let mut barrier = CmdPipelineBarrier::new();
+            unsafe {
+                barrier.add_image_memory_barrier(&command.image, 0 .. num_levels - 1,
+                                                 layers.clone(), compute, read, compute,
+                                                 read_write, false, None,
+                                                 Layout::ShaderReadOnlyOptimal,
+                                                 Layout::TransferDstOptimal);
+                barrier.add_image_memory_barrier(&command.image, num_levels - 1 .. num_levels,
+                                                 layers, compute, write, compute, read_write,
+                                                 false, None, Layout::General,
+                                                 Layout::TransferDstOptimal);
+            }
+            try!(cb.add(&barrier))
+        };
+    }
+
+    Ok(cb)
+}
+
+/// Error that can happen when creating a `CmdGenerateMipmaps`.
+#[derive(Debug, Copy, Clone)]
+pub enum CmdGenerateMipmapsError {
+    /// The image is missing the transfer source or the transfer destination usage.
+    MissingTransferUsage,
+    /// The format of the image supports neither blitting nor being written by the compute shader
+    /// that replaces the blits.
+    FormatNotSupported,
+    /// The image has more than one sample per pixel.
+    Multisampled,
+    /// The mipmaps must be generated with a compute shader, but the image is missing the sampled
+    /// or the storage usage.
+    MissingComputeUsage,
+    /// The mipmaps must be generated with a compute shader, but the image isn't a
+    /// two-dimensional image.
+    UnsupportedDimensions,
+    /// Not enough memory to create the objects used by the compute shader.
+    OomError(OomError),
+}
+
+impl error::Error for CmdGenerateMipmapsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CmdGenerateMipmapsError::MissingTransferUsage => {
+                "the image is missing the transfer source or the transfer destination usage"
+            },
+            CmdGenerateMipmapsError::FormatNotSupported => {
+                "the format of the image supports neither blitting nor being written by the \
+                 compute shader that replaces the blits"
+            },
+            CmdGenerateMipmapsError::Multisampled => {
+                "the image has more than one sample per pixel"
+            },
+            CmdGenerateMipmapsError::MissingComputeUsage => {
+                "the mipmaps must be generated with a compute shader, but the image is missing \
+                 the sampled or the storage usage"
+            },
+            CmdGenerateMipmapsError::UnsupportedDimensions => {
+                "the mipmaps must be generated with a compute shader, but the image isn't a \
+                 two-dimensional image"
+            },
+            CmdGenerateMipmapsError::OomError(_) => {
+                "not enough memory to create the objects used by the compute shader"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            CmdGenerateMipmapsError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CmdGenerateMipmapsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for CmdGenerateMipmapsError {
+    #[inline]
+    fn from(err: OomError) -> CmdGenerateMipmapsError {
+        CmdGenerateMipmapsError::OomError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBufferBuilder;
+    use command_buffer::commands_raw::CmdGenerateMipmaps;
+    use command_buffer::commands_raw::CmdGenerateMipmapsError;
+    use format::Format;
+    use image::AttachmentImage;
+    use image::Dimensions;
+    use image::Image;
+    use image::MipmapsCount;
+    use image::immutable::ImmutableImage;
+    use mock;
+
+    #[test]
+    fn blit_chain() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let (_, init) = ImmutableImage::uninitialized(&device,
+                                                      Dimensions::Dim2d { width: 32, height: 8 },
+                                                      Format::R8G8B8A8Unorm, MipmapsCount::Log2,
+                                                      Some(queue.family())).unwrap();
+
+        let _cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .generate_mipmaps(init)
+            .unwrap();
+
+        let calls = mock::calls(&device);
+        assert_eq!(calls.iter().filter(|&&c| c == "CmdBlitImage").count(), 5);
+        // One barrier before each blit, plus the final transition.
+        assert!(calls.iter().filter(|&&c| c == "CmdPipelineBarrier").count() >= 6);
+    }
+
+    #[test]
+    fn missing_compute_usage() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        // Integer formats can't be blitted with a linear filter, so the compute shader is needed.
+        let (_, init) = ImmutableImage::uninitialized(&device,
+                                                      Dimensions::Dim2d { width: 32, height: 8 },
+                                                      Format::R8G8B8A8Uint, MipmapsCount::Log2,
+                                                      Some(queue.family())).unwrap();
+
+        match CmdGenerateMipmaps::new(init) {
+            Err(CmdGenerateMipmapsError::MissingComputeUsage) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn missing_transfer_usage() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let image = AttachmentImage::new(&device, [32, 32], Format::R8G8B8A8Unorm).unwrap();
+
+        match CmdGenerateMipmaps::new(image.access()) {
+            Err(CmdGenerateMipmapsError::MissingTransferUsage) => (),
+            _ => panic!()
+        }
+    }
+}
@@ -9,54 +9,127 @@
 
 //! All the commands used in the internals of vulkano.
 //!
-//! This module only contains the base commands that have direct equivalents in the Vulkan API.
+//! This module only contains the base commands that have direct equivalents in the Vulkan API,
+//! plus `CmdGenerateMipmaps` which records a chain of barriers and blits or compute dispatches.
 
+use image::ImageAccess;
+use vk;
+
+pub use self::begin_query::{CmdBeginQuery, CmdBeginQueryError};
 pub use self::begin_render_pass::CmdBeginRenderPass;
 pub use self::bind_index_buffer::CmdBindIndexBuffer;
 pub use self::bind_descriptor_sets::{CmdBindDescriptorSets, CmdBindDescriptorSetsError};
 pub use self::bind_pipeline::{CmdBindPipeline, CmdBindPipelineSys};
 pub use self::bind_vertex_buffers::CmdBindVertexBuffers;
 pub use self::blit_image::{CmdBlitImage, CmdBlitImageError};
-pub use self::clear_attachments::CmdClearAttachments;
+pub use self::buffer_image_region::BufferImageRegion;
+pub use self::clear_attachments::{CmdClearAttachments, CmdClearAttachmentsError, ClearAttachment, ClearRect};
 pub use self::copy_buffer::{CmdCopyBuffer, CmdCopyBufferError};
 pub use self::copy_buffer_to_image::{CmdCopyBufferToImage, CmdCopyBufferToImageError};
 pub use self::copy_image::{CmdCopyImage, CmdCopyImageError};
+pub use self::copy_image_to_buffer::{CmdCopyImageToBuffer, CmdCopyImageToBufferError};
+pub use self::copy_query_pool_results::{CmdCopyQueryPoolResults, CmdCopyQueryPoolResultsError};
 pub use self::dispatch_raw::{CmdDispatchRaw, CmdDispatchRawError};
 pub use self::draw_indexed_raw::CmdDrawIndexedRaw;
 pub use self::draw_indirect_raw::CmdDrawIndirectRaw;
 pub use self::draw_raw::CmdDrawRaw;
+pub use self::end_query::{CmdEndQuery, CmdEndQueryError};
 pub use self::end_render_pass::CmdEndRenderPass;
-pub use self::execute::CmdExecuteCommands;
+pub use self::execute::{CmdExecuteCommands, CmdExecuteCommandsError};
 pub use self::fill_buffer::{CmdFillBuffer, CmdFillBufferError};
+pub use self::generate_mipmaps::{CmdGenerateMipmaps, CmdGenerateMipmapsError};
 pub use self::next_subpass::CmdNextSubpass;
 pub use self::pipeline_barrier::CmdPipelineBarrier;
 pub use self::push_constants::{CmdPushConstants, CmdPushConstantsError};
+pub use self::reset_query_pool::{CmdResetQueryPool, CmdResetQueryPoolError};
 pub use self::resolve_image::{CmdResolveImage, CmdResolveImageError};
 pub use self::set_event::CmdSetEvent;
 pub use self::set_state::{CmdSetState};
 pub use self::update_buffer::{CmdUpdateBuffer, CmdUpdateBufferError};
+pub use self::write_timestamp::{CmdWriteTimestamp, CmdWriteTimestampError};
 
+mod begin_query;
 mod begin_render_pass;
 mod bind_descriptor_sets;
 mod bind_index_buffer;
 mod bind_pipeline;
 mod bind_vertex_buffers;
 mod blit_image;
+mod buffer_image_region;
 mod clear_attachments;
 mod copy_buffer;
 mod copy_buffer_to_image;
 mod copy_image;
+mod copy_image_to_buffer;
+mod copy_query_pool_results;
 mod dispatch_raw;
+mod downsample;
 mod draw_indexed_raw;
 mod draw_indirect_raw;
 mod draw_raw;
+mod end_query;
 mod end_render_pass;
 mod execute;
 mod fill_buffer;
+mod generate_mipmaps;
 mod next_subpass;
 mod pipeline_barrier;
 mod push_constants;
+mod reset_query_pool;
 mod resolve_image;
 mod set_event;
 mod set_state;
 mod update_buffer;
+mod write_timestamp;
+
+// Returns the aspect mask that covers all the aspects of `image`.
+#[inline]
+fn aspect_mask<I>(image: &I) -> vk::ImageAspectFlags
+    where I: ImageAccess
+{
+    if image.has_color() {
+        return vk::IMAGE_ASPECT_COLOR_BIT;
+    }
+
+    let mut mask = 0;
+    if image.has_depth() {
+        mask |= vk::IMAGE_ASPECT_DEPTH_BIT;
+    }
+    if image.has_stencil() {
+        mask |= vk::IMAGE_ASPECT_STENCIL_BIT;
+    }
+    mask
+}
+
+// Returns true if the region of `image` starting at `offset` and of size `extent` is within the
+// given mipmap level and array layers.
+fn region_in_range<I>(image: &I, offset: [i32; 3], extent: [u32; 3], base_array_layer: u32,
+                      mip_level: u32, layer_count: u32) -> bool
+    where I: ImageAccess
+{
+    if mip_level >= image.inner().mipmap_levels() {
+        return false;
+    }
+
+    match base_array_layer.checked_add(layer_count) {
+        Some(end) if end <= image.dimensions().array_layers() => (),
+        _ => return false,
+    }
+
+    let dims = match image.dimensions().mipmap_width_height_depth(mip_level) {
+        Some(d) => d,
+        None => return false,
+    };
+
+    for i in 0 .. 3 {
+        if offset[i] < 0 {
+            return false;
+        }
+        match (offset[i] as u32).checked_add(extent[i]) {
+            Some(end) if end <= dims[i] => (),
+            _ => return false,
+        }
+    }
+
+    true
+}
@@ -8,6 +8,7 @@
 // according to those terms.
 
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::UnsafeCommandBufferBuilder;
 use command_buffer::pool::CommandPool;
 use device::DeviceOwned;
@@ -31,6 +32,13 @@ impl CmdNextSubpass {
                       else { vk::SUBPASS_CONTENTS_INLINE }
         }
     }
+
+    /// Returns true if the content of the subpass is going to be provided by secondary command
+    /// buffers.
+    #[inline]
+    pub fn secondary(&self) -> bool {
+        self.contents == vk::SUBPASS_CONTENTS_SECONDARY_COMMAND_BUFFERS
+    }
 }
 
 unsafe impl<'a, P> AddCommand<&'a CmdNextSubpass> for UnsafeCommandBufferBuilder<P>
@@ -39,13 +47,13 @@ unsafe impl<'a, P> AddCommand<&'a CmdNextSubpass> for UnsafeCommandBufferBuilder
     type Out = UnsafeCommandBufferBuilder<P>;
 
     #[inline]
-    fn add(self, command: &'a CmdNextSubpass) -> Self::Out {
+    fn add(self, command: &'a CmdNextSubpass) -> Result<Self::Out, CommandAddError> {
         unsafe {
             let vk = self.device().pointers();
             let cmd = self.internal_object();
             vk.CmdNextSubpass(cmd, command.contents);
         }
 
-        self
+        Ok(self)
     }
 }
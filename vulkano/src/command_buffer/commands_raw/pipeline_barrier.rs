@@ -16,9 +16,11 @@ use smallvec::SmallVec;
 use buffer::BufferAccess;
 use buffer::BufferInner;
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::UnsafeCommandBufferBuilder;
 use command_buffer::pool::CommandPool;
 use image::ImageAccess;
+use image::ImageSubresourceRange;
 use image::Layout;
 use sync::AccessFlagBits;
 use sync::PipelineStages;
@@ -206,6 +208,9 @@ impl<'a> CmdPipelineBarrier<'a> {
         debug_assert!(layers.start < layers.end);
         debug_assert!(layers.end <= image.dimensions().array_layers());
 
+        let aspect_mask = ImageSubresourceRange::new(mipmaps.clone(), layers.clone())
+                              .aspect_bits(image.format());
+
         let (src_queue, dest_queue) = if let Some((src_queue, dest_queue)) = queue_transfer {
             (src_queue, dest_queue)
         } else {
@@ -223,7 +228,7 @@ impl<'a> CmdPipelineBarrier<'a> {
             dstQueueFamilyIndex: dest_queue,
             image: image.inner().internal_object(),
             subresourceRange: vk::ImageSubresourceRange {
-                aspectMask: 1 | 2 | 4 | 8,      // FIXME: wrong
+                aspectMask: aspect_mask,
                 baseMipLevel: mipmaps.start,
                 levelCount: mipmaps.end - mipmaps.start,
                 baseArrayLayer: layers.start,
@@ -239,14 +244,14 @@ unsafe impl<'a, P> AddCommand<&'a CmdPipelineBarrier<'a>> for UnsafeCommandBuffe
     type Out = UnsafeCommandBufferBuilder<P>;
 
     #[inline]
-    fn add(self, command: &'a CmdPipelineBarrier<'a>) -> Self::Out {
+    fn add(self, command: &'a CmdPipelineBarrier<'a>) -> Result<Self::Out, CommandAddError> {
         // If barrier is empty, don't do anything.
         if command.src_stage_mask == 0 || command.dst_stage_mask == 0 {
             debug_assert!(command.src_stage_mask == 0 && command.dst_stage_mask == 0);
             debug_assert!(command.memory_barriers.is_empty());
             debug_assert!(command.buffer_barriers.is_empty());
             debug_assert!(command.image_barriers.is_empty());
-            return self;
+            return Ok(self);
         }
 
         unsafe {
@@ -262,6 +267,6 @@ unsafe impl<'a, P> AddCommand<&'a CmdPipelineBarrier<'a>> for UnsafeCommandBuffe
                                   command.image_barriers.as_ptr());
         }
 
-        self
+        Ok(self)
     }
 }
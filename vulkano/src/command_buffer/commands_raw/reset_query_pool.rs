@@ -0,0 +1,121 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
+use command_buffer::cb::UnsafeCommandBufferBuilder;
+use command_buffer::pool::CommandPool;
+use device::Device;
+use device::DeviceOwned;
+use query::QueryPoolAbstract;
+use VulkanObject;
+use VulkanPointers;
+use vk;
+
+/// Command that resets a range of queries of a query pool.
+///
+/// A query must be reset before it is used.
+pub struct CmdResetQueryPool<Q> {
+    // The query pool.
+    pool: Q,
+    // Raw query pool handle.
+    raw_pool: vk::QueryPool,
+    // Index of the first query to reset.
+    first_query: u32,
+    // Number of queries to reset.
+    query_count: u32,
+}
+
+impl<Q> CmdResetQueryPool<Q>
+    where Q: QueryPoolAbstract
+{
+    /// Builds a command that resets the queries of `range` in `pool`.
+    pub fn new(pool: Q, range: Range<u32>)
+               -> Result<CmdResetQueryPool<Q>, CmdResetQueryPoolError>
+    {
+        let raw_pool = {
+            let inner = pool.inner();
+            if range.start >= range.end || range.end > inner.num_slots() {
+                return Err(CmdResetQueryPoolError::OutOfRange);
+            }
+            inner.internal_object()
+        };
+
+        Ok(CmdResetQueryPool {
+            pool: pool,
+            raw_pool: raw_pool,
+            first_query: range.start,
+            query_count: range.end - range.start,
+        })
+    }
+}
+
+impl<Q> CmdResetQueryPool<Q> {
+    /// Returns the query pool.
+    #[inline]
+    pub fn pool(&self) -> &Q {
+        &self.pool
+    }
+}
+
+unsafe impl<Q> DeviceOwned for CmdResetQueryPool<Q>
+    where Q: QueryPoolAbstract
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.pool.inner().device()
+    }
+}
+
+unsafe impl<'a, P, Q> AddCommand<&'a CmdResetQueryPool<Q>> for UnsafeCommandBufferBuilder<P>
+    where P: CommandPool
+{
+    type Out = UnsafeCommandBufferBuilder<P>;
+
+    #[inline]
+    fn add(self, command: &'a CmdResetQueryPool<Q>) -> Result<Self::Out, CommandAddError> {
+        unsafe {
+            let vk = self.device().pointers();
+            let cmd = self.internal_object();
+            vk.CmdResetQueryPool(cmd, command.raw_pool, command.first_query, command.query_count);
+        }
+
+        Ok(self)
+    }
+}
+
+/// Error that can happen when creating a `CmdResetQueryPool`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CmdResetQueryPoolError {
+    /// The range of queries is empty or out of the range of the pool.
+    OutOfRange,
+}
+
+impl error::Error for CmdResetQueryPoolError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CmdResetQueryPoolError::OutOfRange => {
+                "the range of queries is empty or out of the range of the pool"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CmdResetQueryPoolError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
@@ -11,10 +11,14 @@ use std::error;
 use std::fmt;
 use std::sync::Arc;
 use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
 use command_buffer::cb::UnsafeCommandBufferBuilder;
+use command_buffer::commands_raw::region_in_range;
 use command_buffer::pool::CommandPool;
 use device::Device;
 use device::DeviceOwned;
+use image::ImageAccess;
+use image::ImageSubresourceRange;
 use VulkanObject;
 use VulkanPointers;
 use vk;
@@ -50,7 +54,103 @@ pub struct CmdResolveImage<S, D> {
     extent: [u32; 3],
 }
 
-// TODO: add constructor
+impl<S, D> CmdResolveImage<S, D> where S: ImageAccess, D: ImageAccess {
+    /// Builds a new command.
+    ///
+    /// The region of size `extent` starting at `source_offset` in the multisampled source is
+    /// resolved into the region starting at `destination_offset` in the single-sampled
+    /// destination. `layer_count` layers are resolved, starting at `source_base_array_layer` in
+    /// the source and at `destination_base_array_layer` in the destination.
+    ///
+    /// Only color images can be resolved, and the two images must have the same format.
+    pub fn new(source: S, source_offset: [i32; 3], source_base_array_layer: u32,
+               source_mip_level: u32, destination: D, destination_offset: [i32; 3],
+               destination_base_array_layer: u32, destination_mip_level: u32,
+               extent: [u32; 3], layer_count: u32)
+               -> Result<CmdResolveImage<S, D>, CmdResolveImageError>
+    {
+        assert_eq!(source.inner().device().internal_object(),
+                   destination.inner().device().internal_object());
+
+        let source_raw = {
+            let inner = source.inner();
+            if !inner.usage_transfer_src() {
+                return Err(CmdResolveImageError::SourceMissingTransferUsage);
+            }
+            inner.internal_object()
+        };
+
+        let destination_raw = {
+            let inner = destination.inner();
+            if !inner.usage_transfer_dest() {
+                return Err(CmdResolveImageError::DestinationMissingTransferUsage);
+            }
+            inner.internal_object()
+        };
+
+        if source.samples() == 1 {
+            return Err(CmdResolveImageError::SourceNotMultisampled);
+        }
+
+        if destination.samples() != 1 {
+            return Err(CmdResolveImageError::DestinationMultisampled);
+        }
+
+        if source.format() != destination.format() {
+            return Err(CmdResolveImageError::FormatsMismatch);
+        }
+
+        if !source.has_color() {
+            return Err(CmdResolveImageError::NotColorFormat);
+        }
+
+        if layer_count == 0 || extent[0] == 0 || extent[1] == 0 || extent[2] == 0 {
+            return Err(CmdResolveImageError::EmptyRegion);
+        }
+
+        if !region_in_range(&source, source_offset, extent, source_base_array_layer,
+                            source_mip_level, layer_count)
+        {
+            return Err(CmdResolveImageError::SourceCoordinatesOutOfRange);
+        }
+
+        if !region_in_range(&destination, destination_offset, extent,
+                            destination_base_array_layer, destination_mip_level, layer_count)
+        {
+            return Err(CmdResolveImageError::DestinationCoordinatesOutOfRange);
+        }
+
+        if source.conflicts_image(source_base_array_layer, layer_count, source_mip_level, 1,
+                                  &destination, destination_base_array_layer, layer_count,
+                                  destination_mip_level, 1)
+        {
+            return Err(CmdResolveImageError::OverlappingRegions);
+        }
+
+        let source_aspect_mask = vk::IMAGE_ASPECT_COLOR_BIT;
+        let destination_aspect_mask = vk::IMAGE_ASPECT_COLOR_BIT;
+
+        Ok(CmdResolveImage {
+            source: source,
+            source_raw: source_raw,
+            source_layout: vk::IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL,
+            source_offset: source_offset,
+            source_aspect_mask: source_aspect_mask,
+            source_mip_level: source_mip_level,
+            source_base_array_layer: source_base_array_layer,
+            source_layer_count: layer_count,
+            destination: destination,
+            destination_raw: destination_raw,
+            destination_layout: vk::IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL,
+            destination_offset: destination_offset,
+            destination_aspect_mask: destination_aspect_mask,
+            destination_mip_level: destination_mip_level,
+            destination_base_array_layer: destination_base_array_layer,
+            destination_layer_count: layer_count,
+            extent: extent,
+        })
+    }
+}
 
 impl<S, D> CmdResolveImage<S, D> {
     /// Returns the source image.
@@ -64,12 +164,30 @@ impl<S, D> CmdResolveImage<S, D> {
     pub fn destination(&self) -> &D {
         &self.destination
     }
+
+    /// Returns the range of subresources of the source image that are accessed by the command.
+    #[inline]
+    pub fn source_subresource_range(&self) -> ImageSubresourceRange {
+        ImageSubresourceRange::new(self.source_mip_level .. self.source_mip_level + 1,
+                                   self.source_base_array_layer ..
+                                   self.source_base_array_layer + self.source_layer_count)
+    }
+
+    /// Returns the range of subresources of the destination image that are accessed by the
+    /// command.
+    #[inline]
+    pub fn destination_subresource_range(&self) -> ImageSubresourceRange {
+        ImageSubresourceRange::new(self.destination_mip_level .. self.destination_mip_level + 1,
+                                   self.destination_base_array_layer ..
+                                   self.destination_base_array_layer +
+                                   self.destination_layer_count)
+    }
 }
 
-unsafe impl<S, D> DeviceOwned for CmdResolveImage<S, D> where S: DeviceOwned {
+unsafe impl<S, D> DeviceOwned for CmdResolveImage<S, D> where S: ImageAccess {
     #[inline]
     fn device(&self) -> &Arc<Device> {
-        self.source.device()
+        self.source.inner().device()
     }
 }
 
@@ -79,7 +197,7 @@ unsafe impl<'a, P, S, D> AddCommand<&'a CmdResolveImage<S, D>> for UnsafeCommand
     type Out = UnsafeCommandBufferBuilder<P>;
 
     #[inline]
-    fn add(self, command: &'a CmdResolveImage<S, D>) -> Self::Out {
+    fn add(self, command: &'a CmdResolveImage<S, D>) -> Result<Self::Out, CommandAddError> {
         unsafe {
             debug_assert!(command.source_layout == vk::IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL ||
                           command.source_layout == vk::IMAGE_LAYOUT_GENERAL);
@@ -123,19 +241,69 @@ unsafe impl<'a, P, S, D> AddCommand<&'a CmdResolveImage<S, D>> for UnsafeCommand
                                1, &region as *const _);
         }
 
-        self
+        Ok(self)
     }
 }
 
 /// Error that can happen when creating a `CmdResolveImage`.
 #[derive(Debug, Copy, Clone)]
 pub enum CmdResolveImageError {
+    /// The source image is missing the transfer source usage.
+    SourceMissingTransferUsage,
+    /// The destination image is missing the transfer destination usage.
+    DestinationMissingTransferUsage,
+    /// The source image has only one sample per pixel.
+    SourceNotMultisampled,
+    /// The destination image has more than one sample per pixel.
+    DestinationMultisampled,
+    /// The source and destination images have different formats.
+    FormatsMismatch,
+    /// Only color images can be resolved.
+    NotColorFormat,
+    /// The region to resolve is empty.
+    EmptyRegion,
+    /// The source coordinates are out of range of the image.
+    SourceCoordinatesOutOfRange,
+    /// The destination coordinates are out of range of the image.
+    DestinationCoordinatesOutOfRange,
+    /// The source and destination regions are overlapping in memory.
+    OverlappingRegions,
 }
 
 impl error::Error for CmdResolveImageError {
     #[inline]
     fn description(&self) -> &str {
         match *self {
+            CmdResolveImageError::SourceMissingTransferUsage => {
+                "the source image is missing the transfer source usage"
+            },
+            CmdResolveImageError::DestinationMissingTransferUsage => {
+                "the destination image is missing the transfer destination usage"
+            },
+            CmdResolveImageError::SourceNotMultisampled => {
+                "the source image has only one sample per pixel"
+            },
+            CmdResolveImageError::DestinationMultisampled => {
+                "the destination image has more than one sample per pixel"
+            },
+            CmdResolveImageError::FormatsMismatch => {
+                "the source and destination images have different formats"
+            },
+            CmdResolveImageError::NotColorFormat => {
+                "only color images can be resolved"
+            },
+            CmdResolveImageError::EmptyRegion => {
+                "the region to resolve is empty"
+            },
+            CmdResolveImageError::SourceCoordinatesOutOfRange => {
+                "the source coordinates are out of range of the image"
+            },
+            CmdResolveImageError::DestinationCoordinatesOutOfRange => {
+                "the destination coordinates are out of range of the image"
+            },
+            CmdResolveImageError::OverlappingRegions => {
+                "the source and destination regions are overlapping in memory"
+            },
         }
     }
 }
@@ -146,3 +314,66 @@ impl fmt::Display for CmdResolveImageError {
         write!(fmt, "{}", error::Error::description(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBufferBuilder;
+    use command_buffer::commands_raw::CmdResolveImage;
+    use command_buffer::commands_raw::CmdResolveImageError;
+    use format::R8G8B8A8Unorm;
+    use image::AttachmentImage;
+    use image::Image;
+    use image::Usage;
+    use mock;
+
+    #[test]
+    fn basic_resolve() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let usage = Usage { transfer_source: true, transfer_dest: true, .. Usage::none() };
+        let a = AttachmentImage::multisampled_with_usage(&device, [32, 32], 4, R8G8B8A8Unorm,
+                                                         usage).unwrap();
+        let b = AttachmentImage::with_usage(&device, [32, 32], R8G8B8A8Unorm, usage).unwrap();
+
+        let _cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .resolve_image(a.clone(), [0, 0, 0], 0, 0, b.clone(), [0, 0, 0], 0, 0, [32, 32, 1], 1)
+            .unwrap();
+
+        let calls = mock::calls(&device);
+        assert!(calls.iter().any(|c| *c == "CmdResolveImage"));
+    }
+
+    #[test]
+    fn source_not_multisampled() {
+        let (device, _) = mock_dev_and_queue!();
+
+        let usage = Usage { transfer_source: true, transfer_dest: true, .. Usage::none() };
+        let a = AttachmentImage::with_usage(&device, [32, 32], R8G8B8A8Unorm, usage).unwrap();
+        let b = AttachmentImage::with_usage(&device, [32, 32], R8G8B8A8Unorm, usage).unwrap();
+
+        match CmdResolveImage::new(a.access(), [0, 0, 0], 0, 0, b.access(), [0, 0, 0], 0, 0,
+                                   [32, 32, 1], 1)
+        {
+            Err(CmdResolveImageError::SourceNotMultisampled) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn out_of_range() {
+        let (device, _) = mock_dev_and_queue!();
+
+        let usage = Usage { transfer_source: true, transfer_dest: true, .. Usage::none() };
+        let a = AttachmentImage::multisampled_with_usage(&device, [32, 32], 4, R8G8B8A8Unorm,
+                                                         usage).unwrap();
+        let b = AttachmentImage::with_usage(&device, [16, 16], R8G8B8A8Unorm, usage).unwrap();
+
+        match CmdResolveImage::new(a.access(), [0, 0, 0], 0, 0, b.access(), [0, 0, 0], 0, 0,
+                                   [32, 32, 1], 1)
+        {
+            Err(CmdResolveImageError::DestinationCoordinatesOutOfRange) => (),
+            _ => panic!()
+        }
+    }
+}
@@ -0,0 +1,237 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+use command_buffer::cb::AddCommand;
+use command_buffer::cb::CommandAddError;
+use command_buffer::cb::UnsafeCommandBufferBuilder;
+use command_buffer::pool::CommandPool;
+use device::Device;
+use device::DeviceOwned;
+use instance::QueueFamily;
+use query::QueryPoolAbstract;
+use query::QueryType;
+use sync::PipelineStages;
+use VulkanObject;
+use VulkanPointers;
+use vk;
+
+/// Command that writes a timestamp to a timestamp query.
+pub struct CmdWriteTimestamp<Q> {
+    // The query pool.
+    pool: Q,
+    // Raw query pool handle.
+    raw_pool: vk::QueryPool,
+    // Index of the query within the pool.
+    query: u32,
+    // The stage after which the timestamp is written.
+    stage: vk::PipelineStageFlagBits,
+}
+
+impl<Q> CmdWriteTimestamp<Q>
+    where Q: QueryPoolAbstract
+{
+    /// Builds a command that writes a timestamp to the query `query` of `pool` once all the
+    /// previous commands have reached `stage`. The command is meant to be executed by a queue of
+    /// `queue_family`.
+    ///
+    /// Exactly one stage must be enabled in `stage`.
+    pub fn new(pool: Q, query: u32, stage: PipelineStages, queue_family: QueueFamily)
+               -> Result<CmdWriteTimestamp<Q>, CmdWriteTimestampError>
+    {
+        if queue_family.timestamp_valid_bits() == 0 {
+            return Err(CmdWriteTimestampError::TimestampsNotSupported);
+        }
+
+        let raw_pool = {
+            let inner = pool.inner();
+
+            if query >= inner.num_slots() {
+                return Err(CmdWriteTimestampError::OutOfRange);
+            }
+
+            match inner.ty() {
+                QueryType::Timestamp => (),
+                _ => return Err(CmdWriteTimestampError::NotTimestampQuery),
+            }
+
+            inner.internal_object()
+        };
+
+        let stage: vk::PipelineStageFlagBits = stage.into();
+        if stage.count_ones() != 1 {
+            return Err(CmdWriteTimestampError::NotOneStage);
+        }
+
+        Ok(CmdWriteTimestamp {
+            pool: pool,
+            raw_pool: raw_pool,
+            query: query,
+            stage: stage,
+        })
+    }
+}
+
+impl<Q> CmdWriteTimestamp<Q> {
+    /// Returns the query pool.
+    #[inline]
+    pub fn pool(&self) -> &Q {
+        &self.pool
+    }
+
+    /// Returns the index of the query within the pool.
+    #[inline]
+    pub fn query(&self) -> u32 {
+        self.query
+    }
+}
+
+unsafe impl<Q> DeviceOwned for CmdWriteTimestamp<Q>
+    where Q: QueryPoolAbstract
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.pool.inner().device()
+    }
+}
+
+unsafe impl<'a, P, Q> AddCommand<&'a CmdWriteTimestamp<Q>> for UnsafeCommandBufferBuilder<P>
+    where P: CommandPool
+{
+    type Out = UnsafeCommandBufferBuilder<P>;
+
+    #[inline]
+    fn add(self, command: &'a CmdWriteTimestamp<Q>) -> Result<Self::Out, CommandAddError> {
+        unsafe {
+            let vk = self.device().pointers();
+            let cmd = self.internal_object();
+            vk.CmdWriteTimestamp(cmd, command.stage, command.raw_pool, command.query);
+        }
+
+        Ok(self)
+    }
+}
+
+/// Error that can happen when creating a `CmdWriteTimestamp`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CmdWriteTimestampError {
+    /// The query index is out of the range of the pool.
+    OutOfRange,
+    /// The query pool is not a pool of timestamp queries.
+    NotTimestampQuery,
+    /// Exactly one pipeline stage must be specified.
+    NotOneStage,
+    /// The queue family doesn't support timestamps.
+    TimestampsNotSupported,
+}
+
+impl error::Error for CmdWriteTimestampError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CmdWriteTimestampError::OutOfRange => {
+                "the query index is out of the range of the pool"
+            },
+            CmdWriteTimestampError::NotTimestampQuery => {
+                "the query pool is not a pool of timestamp queries"
+            },
+            CmdWriteTimestampError::NotOneStage => {
+                "exactly one pipeline stage must be specified"
+            },
+            CmdWriteTimestampError::TimestampsNotSupported => {
+                "the queue family doesn't support timestamps"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CmdWriteTimestampError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBufferBuilder;
+    use command_buffer::CommandBufferBuilderError;
+    use command_buffer::commands_raw::CmdWriteTimestamp;
+    use command_buffer::commands_raw::CmdWriteTimestampError;
+    use mock;
+    use query::OcclusionQueriesPool;
+    use query::TimestampQueryPool;
+    use sync::PipelineStages;
+
+    #[test]
+    fn write_timestamps() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let pool = TimestampQueryPool::new(&device, 2).unwrap();
+        let top = PipelineStages { top_of_pipe: true, .. PipelineStages::none() };
+        let bottom = PipelineStages { bottom_of_pipe: true, .. PipelineStages::none() };
+
+        let _cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .reset_query_pool(pool.clone(), 0 .. 2).unwrap()
+            .write_timestamp(pool.clone(), 0, top).unwrap()
+            .write_timestamp(pool.clone(), 1, bottom).unwrap();
+
+        let calls = mock::calls(&device);
+        let calls = calls.iter().filter(|c| c.starts_with("Cmd")).cloned().collect::<Vec<_>>();
+        assert_eq!(calls, vec!["CmdResetQueryPool", "CmdWriteTimestamp", "CmdWriteTimestamp"]);
+    }
+
+    #[test]
+    fn wrong_pool_or_stage() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let top = PipelineStages { top_of_pipe: true, .. PipelineStages::none() };
+
+        let occlusion = OcclusionQueriesPool::new(&device, 1);
+        match CmdWriteTimestamp::new(occlusion, 0, top, queue.family()) {
+            Err(CmdWriteTimestampError::NotTimestampQuery) => (),
+            _ => panic!()
+        }
+
+        let pool = TimestampQueryPool::new(&device, 1).unwrap();
+        match CmdWriteTimestamp::new(pool.clone(), 0, PipelineStages::none(), queue.family()) {
+            Err(CmdWriteTimestampError::NotOneStage) => (),
+            _ => panic!()
+        }
+        match CmdWriteTimestamp::new(pool.clone(), 1, top, queue.family()) {
+            Err(CmdWriteTimestampError::OutOfRange) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn timestamps_not_supported() {
+        let (device, queue) = mock_dev_and_queue!(queue: |q| q.timestamp_valid_bits() == 0);
+
+        let pool = TimestampQueryPool::new(&device, 1).unwrap();
+        let top = PipelineStages { top_of_pipe: true, .. PipelineStages::none() };
+
+        match CmdWriteTimestamp::new(pool.clone(), 0, top, queue.family()) {
+            Err(CmdWriteTimestampError::TimestampsNotSupported) => (),
+            _ => panic!()
+        }
+
+        let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        match cb.write_timestamp(pool, 0, top) {
+            Err(CommandBufferBuilderError::CommandBuildError(err)) => {
+                assert_eq!(err, CmdWriteTimestampError::TimestampsNotSupported);
+            },
+            _ => panic!()
+        }
+    }
+}
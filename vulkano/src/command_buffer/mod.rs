@@ -74,9 +74,12 @@
 //! alternative command pool implementations and use them. See the `pool` module for more
 //! information.
 
+pub use self::auto::AutoCommandBuffer;
 pub use self::auto::AutoCommandBufferBuilder;
 pub use self::builder::CommandBufferBuilder;
 pub use self::builder::CommandBufferBuilderBuffered;
+pub use self::builder::CommandBufferBuilderError;
+pub use self::cb::CommandAddError;
 pub use self::traits::CommandBuffer;
 pub use self::traits::CommandBufferBuild;
 pub use self::traits::CommandBufferExecFuture;
@@ -0,0 +1,667 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ptr;
+use smallvec::SmallVec;
+
+use buffer::sys::UnsafeBuffer;
+use device::Queue;
+use format::FormatTy;
+use image::sys::UnsafeImage;
+use memory::DeviceMemory;
+use sync::Fence;
+use sync::Semaphore;
+
+use check_errors;
+use vk;
+use Error;
+use OomError;
+use VulkanObject;
+use VulkanPointers;
+use SynchronizedVulkanObject;
+
+/// Prototype for a submission that binds memory to sparse buffers and images.
+///
+/// Contrary to command buffer submissions, the operations of this submission are not ordered
+/// with the other operations submitted to the same queue. Use semaphores if you need an ordering.
+// TODO: example here
+pub struct SubmitBindSparseBuilder<'a> {
+    wait_semaphores: SmallVec<[vk::Semaphore; 8]>,
+    buffer_binds: SmallVec<[SubmitBindSparseBufferBindBuilder<'a>; 4]>,
+    image_opaque_binds: SmallVec<[SubmitBindSparseImageOpaqueBindBuilder<'a>; 4]>,
+    image_binds: SmallVec<[SubmitBindSparseImageBindBuilder<'a>; 4]>,
+    signal_semaphores: SmallVec<[vk::Semaphore; 8]>,
+    fence: vk::Fence,
+    marker: PhantomData<&'a ()>,
+}
+
+impl<'a> SubmitBindSparseBuilder<'a> {
+    /// Builds a new empty `SubmitBindSparseBuilder`.
+    #[inline]
+    pub fn new() -> SubmitBindSparseBuilder<'a> {
+        SubmitBindSparseBuilder {
+            wait_semaphores: SmallVec::new(),
+            buffer_binds: SmallVec::new(),
+            image_opaque_binds: SmallVec::new(),
+            image_binds: SmallVec::new(),
+            signal_semaphores: SmallVec::new(),
+            fence: 0,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns true if this builder will signal a fence when submitted.
+    #[inline]
+    pub fn has_fence(&self) -> bool {
+        self.fence != 0
+    }
+
+    /// Adds an operation that signals a fence after this submission ends.
+    ///
+    /// # Safety
+    ///
+    /// - The fence must not be signaled at the time when you call `submit()`.
+    ///
+    /// - If you use the fence for multiple submissions, only one at a time must be executed by the
+    ///   GPU. In other words, you must submit one, wait for the fence to be signaled, then reset
+    ///   the fence, and then only submit the second.
+    ///
+    /// - If you submit this builder, the fence must be kept alive until it is signaled by the GPU.
+    ///   Destroying the fence earlier is an undefined behavior.
+    ///
+    /// - The fence, resources, memory and semaphores must all belong to the same device.
+    ///
+    #[inline]
+    pub unsafe fn set_fence_signal(&mut self, fence: &'a Fence) {
+        self.fence = fence.internal_object();
+    }
+
+    /// Adds a semaphore to be waited upon before the memory bindings are performed.
+    ///
+    /// # Safety
+    ///
+    /// - If you submit this builder, the semaphore must be kept alive until you are guaranteed
+    ///   that the GPU has at least started executing the submission.
+    ///
+    /// - If you submit this builder, no other queue must be waiting on these semaphores. In other
+    ///   words, each semaphore signal can only correspond to one semaphore wait.
+    ///
+    /// - If you submit this builder, the semaphores must be signaled when the queue execution
+    ///   reaches this submission, or there must be one or more submissions in queues that are
+    ///   going to signal these semaphores. In other words, you must not block the queue with
+    ///   semaphores that can't get signaled.
+    ///
+    /// - The fence, resources, memory and semaphores must all belong to the same device.
+    ///
+    #[inline]
+    pub unsafe fn add_wait_semaphore(&mut self, semaphore: &'a Semaphore) {
+        self.wait_semaphores.push(semaphore.internal_object());
+    }
+
+    /// Returns the number of semaphores to signal.
+    ///
+    /// In other words, this is the number of times `add_signal_semaphore` has been called.
+    #[inline]
+    pub fn num_signal_semaphores(&self) -> usize {
+        self.signal_semaphores.len()
+    }
+
+    /// Adds a semaphore that is going to be signaled at the end of the submission.
+    ///
+    /// # Safety
+    ///
+    /// - If you submit this builder, the semaphore must be kept alive until you are guaranteed
+    ///   that the GPU has finished executing this submission.
+    ///
+    /// - The semaphore must be in the unsignaled state when queue execution reaches this
+    ///   submission.
+    ///
+    /// - The fence, resources, memory and semaphores must all belong to the same device.
+    ///
+    #[inline]
+    pub unsafe fn add_signal_semaphore(&mut self, semaphore: &'a Semaphore) {
+        self.signal_semaphores.push(semaphore.internal_object());
+    }
+
+    /// Adds a list of memory bindings to a sparse buffer.
+    ///
+    /// The memory binding operations are performed in the order in which they are added.
+    #[inline]
+    pub fn add_buffer_binds(&mut self, binds: SubmitBindSparseBufferBindBuilder<'a>) {
+        self.buffer_binds.push(binds);
+    }
+
+    /// Adds a list of memory bindings to the opaque memory region of a sparse image.
+    ///
+    /// The memory binding operations are performed in the order in which they are added.
+    #[inline]
+    pub fn add_image_opaque_binds(&mut self, binds: SubmitBindSparseImageOpaqueBindBuilder<'a>) {
+        self.image_opaque_binds.push(binds);
+    }
+
+    /// Adds a list of memory bindings to the blocks of a partially-resident image.
+    ///
+    /// The memory binding operations are performed in the order in which they are added.
+    #[inline]
+    pub fn add_image_binds(&mut self, binds: SubmitBindSparseImageBindBuilder<'a>) {
+        self.image_binds.push(binds);
+    }
+
+    /// Submits the memory bindings to the given queue. Calls `vkQueueBindSparse`.
+    ///
+    /// > **Note**: This is an expensive operation, so you may want to merge as many builders as
+    /// > possible together and avoid submitting them one by one.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the queue doesn't support sparse binding operations.
+    ///
+    pub fn submit(self, queue: &Queue) -> Result<(), SubmitBindSparseError> {
+        assert!(queue.family().supports_sparse_binding(),
+                "Tried to submit a bind sparse operation to a queue that doesn't support it");
+
+        unsafe {
+            let vk = queue.device().pointers();
+            let queue = queue.internal_object_guard();
+
+            let buffer_binds = self.buffer_binds.iter().map(|b| {
+                vk::SparseBufferMemoryBindInfo {
+                    buffer: b.buffer,
+                    bindCount: b.binds.len() as u32,
+                    pBinds: b.binds.as_ptr(),
+                }
+            }).collect::<SmallVec<[_; 4]>>();
+
+            let image_opaque_binds = self.image_opaque_binds.iter().map(|b| {
+                vk::SparseImageOpaqueMemoryBindInfo {
+                    image: b.image,
+                    bindCount: b.binds.len() as u32,
+                    pBinds: b.binds.as_ptr(),
+                }
+            }).collect::<SmallVec<[_; 4]>>();
+
+            let image_binds = self.image_binds.iter().map(|b| {
+                vk::SparseImageMemoryBindInfo {
+                    image: b.image,
+                    bindCount: b.binds.len() as u32,
+                    pBinds: b.binds.as_ptr(),
+                }
+            }).collect::<SmallVec<[_; 4]>>();
+
+            let infos = vk::BindSparseInfo {
+                sType: vk::STRUCTURE_TYPE_BIND_SPARSE_INFO,
+                pNext: ptr::null(),
+                waitSemaphoreCount: self.wait_semaphores.len() as u32,
+                pWaitSemaphores: self.wait_semaphores.as_ptr(),
+                bufferBindCount: buffer_binds.len() as u32,
+                pBufferBinds: buffer_binds.as_ptr(),
+                imageOpaqueBindCount: image_opaque_binds.len() as u32,
+                pImageOpaqueBinds: image_opaque_binds.as_ptr(),
+                imageBindCount: image_binds.len() as u32,
+                pImageBinds: image_binds.as_ptr(),
+                signalSemaphoreCount: self.signal_semaphores.len() as u32,
+                pSignalSemaphores: self.signal_semaphores.as_ptr(),
+            };
+
+            try!(check_errors(vk.QueueBindSparse(*queue, 1, &infos, self.fence)));
+            Ok(())
+        }
+    }
+
+    /// Merges this builder with another builder.
+    ///
+    /// # Panic
+    ///
+    /// Panics if both builders have a fence already set.
+    // TODO: create multiple batches instead
+    pub fn merge(mut self, other: Self) -> Self {
+        assert!(self.fence == 0 || other.fence == 0,
+               "Can't merge two bind sparse submissions that both have a fence");
+
+        self.wait_semaphores.extend(other.wait_semaphores);
+        self.buffer_binds.extend(other.buffer_binds);
+        self.image_opaque_binds.extend(other.image_opaque_binds);
+        self.image_binds.extend(other.image_binds);
+        self.signal_semaphores.extend(other.signal_semaphores);
+
+        if self.fence == 0 {
+            self.fence = other.fence;
+        }
+
+        self
+    }
+}
+
+impl<'a> fmt::Debug for SubmitBindSparseBuilder<'a> {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "<Bind sparse operation>")
+    }
+}
+
+/// List of memory bindings to a sparse buffer, to add to a `SubmitBindSparseBuilder`.
+pub struct SubmitBindSparseBufferBindBuilder<'a> {
+    buffer: vk::Buffer,
+    binds: SmallVec<[vk::SparseMemoryBind; 1]>,
+    marker: PhantomData<&'a ()>,
+}
+
+impl<'a> SubmitBindSparseBufferBindBuilder<'a> {
+    /// Builds a new empty list of memory bindings for the given buffer.
+    ///
+    /// # Safety
+    ///
+    /// - The buffer must have been created with sparse binding enabled.
+    ///
+    /// - If you submit the bindings, the buffer must be kept alive until you are guaranteed that
+    ///   the GPU has finished executing the submission.
+    ///
+    #[inline]
+    pub unsafe fn new(buffer: &'a UnsafeBuffer) -> SubmitBindSparseBufferBindBuilder<'a> {
+        SubmitBindSparseBufferBindBuilder {
+            buffer: buffer.internal_object(),
+            binds: SmallVec::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Binds the range `offset .. offset + size` of the buffer to the memory, starting at
+    /// `memory_offset`.
+    ///
+    /// # Safety
+    ///
+    /// - The offsets and the size must be multiples of the alignment of the memory requirements
+    ///   of the buffer, except for a size that reaches the end of the buffer.
+    ///
+    /// - The range must be within the buffer and within the memory.
+    ///
+    /// - The memory must satisfy the memory requirements of the buffer.
+    ///
+    /// - If you submit the bindings, the memory must be kept alive for as long as it is bound to
+    ///   the buffer.
+    ///
+    #[inline]
+    pub unsafe fn add_bind(&mut self, offset: usize, size: usize, memory: &'a DeviceMemory,
+                           memory_offset: usize)
+    {
+        self.binds.push(vk::SparseMemoryBind {
+            resourceOffset: offset as vk::DeviceSize,
+            size: size as vk::DeviceSize,
+            memory: memory.internal_object(),
+            memoryOffset: memory_offset as vk::DeviceSize,
+            flags: 0,
+        });
+    }
+
+    /// Unbinds the range `offset .. offset + size` of the buffer.
+    ///
+    /// # Safety
+    ///
+    /// - The offset and the size must be multiples of the alignment of the memory requirements
+    ///   of the buffer, except for a size that reaches the end of the buffer.
+    ///
+    /// - The range must be within the buffer.
+    ///
+    /// - If the buffer wasn't created with sparse residency enabled, it must be rebound before
+    ///   being used again.
+    ///
+    #[inline]
+    pub unsafe fn add_unbind(&mut self, offset: usize, size: usize) {
+        self.binds.push(vk::SparseMemoryBind {
+            resourceOffset: offset as vk::DeviceSize,
+            size: size as vk::DeviceSize,
+            memory: 0,
+            memoryOffset: 0,
+            flags: 0,
+        });
+    }
+}
+
+impl<'a> fmt::Debug for SubmitBindSparseBufferBindBuilder<'a> {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "<Sparse buffer memory bindings>")
+    }
+}
+
+/// List of memory bindings to the opaque memory region of a sparse image, to add to a
+/// `SubmitBindSparseBuilder`.
+///
+/// The opaque memory region of an image covers its whole memory if the image doesn't use sparse
+/// residency. Otherwise it covers the mipmap tail and the metadata of the image.
+pub struct SubmitBindSparseImageOpaqueBindBuilder<'a> {
+    image: vk::Image,
+    binds: SmallVec<[vk::SparseMemoryBind; 1]>,
+    marker: PhantomData<&'a ()>,
+}
+
+impl<'a> SubmitBindSparseImageOpaqueBindBuilder<'a> {
+    /// Builds a new empty list of memory bindings for the given image.
+    ///
+    /// # Safety
+    ///
+    /// - The image must have been created with sparse binding enabled.
+    ///
+    /// - If you submit the bindings, the image must be kept alive until you are guaranteed that
+    ///   the GPU has finished executing the submission.
+    ///
+    #[inline]
+    pub unsafe fn new(image: &'a UnsafeImage) -> SubmitBindSparseImageOpaqueBindBuilder<'a> {
+        SubmitBindSparseImageOpaqueBindBuilder {
+            image: image.internal_object(),
+            binds: SmallVec::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Binds the range `offset .. offset + size` of the opaque memory region of the image to the
+    /// memory, starting at `memory_offset`.
+    ///
+    /// If `metadata` is true, the range is part of the metadata of the mipmap tail.
+    ///
+    /// # Safety
+    ///
+    /// - The offsets and the size must be multiples of the alignment of the memory requirements
+    ///   of the image, except for a size that reaches the end of the image.
+    ///
+    /// - The range must be within the image and within the memory.
+    ///
+    /// - The memory must satisfy the memory requirements of the image.
+    ///
+    /// - If you submit the bindings, the memory must be kept alive for as long as it is bound to
+    ///   the image.
+    ///
+    #[inline]
+    pub unsafe fn add_bind(&mut self, offset: usize, size: usize, memory: &'a DeviceMemory,
+                           memory_offset: usize, metadata: bool)
+    {
+        self.binds.push(vk::SparseMemoryBind {
+            resourceOffset: offset as vk::DeviceSize,
+            size: size as vk::DeviceSize,
+            memory: memory.internal_object(),
+            memoryOffset: memory_offset as vk::DeviceSize,
+            flags: if metadata { vk::SPARSE_MEMORY_BIND_METADATA_BIT } else { 0 },
+        });
+    }
+
+    /// Unbinds the range `offset .. offset + size` of the opaque memory region of the image.
+    ///
+    /// # Safety
+    ///
+    /// - The offset and the size must be multiples of the alignment of the memory requirements
+    ///   of the image, except for a size that reaches the end of the image.
+    ///
+    /// - The range must be within the image.
+    ///
+    #[inline]
+    pub unsafe fn add_unbind(&mut self, offset: usize, size: usize, metadata: bool) {
+        self.binds.push(vk::SparseMemoryBind {
+            resourceOffset: offset as vk::DeviceSize,
+            size: size as vk::DeviceSize,
+            memory: 0,
+            memoryOffset: 0,
+            flags: if metadata { vk::SPARSE_MEMORY_BIND_METADATA_BIT } else { 0 },
+        });
+    }
+}
+
+impl<'a> fmt::Debug for SubmitBindSparseImageOpaqueBindBuilder<'a> {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "<Sparse image opaque memory bindings>")
+    }
+}
+
+/// List of memory bindings to the blocks of a partially-resident image, to add to a
+/// `SubmitBindSparseBuilder`.
+pub struct SubmitBindSparseImageBindBuilder<'a> {
+    image: vk::Image,
+    aspect_mask: vk::ImageAspectFlags,
+    binds: SmallVec<[vk::SparseImageMemoryBind; 1]>,
+    marker: PhantomData<&'a ()>,
+}
+
+impl<'a> SubmitBindSparseImageBindBuilder<'a> {
+    /// Builds a new empty list of memory bindings for the given image.
+    ///
+    /// The bindings cover all the aspects of the format of the image.
+    ///
+    /// # Safety
+    ///
+    /// - The image must have been created with sparse residency enabled.
+    ///
+    /// - If you submit the bindings, the image must be kept alive until you are guaranteed that
+    ///   the GPU has finished executing the submission.
+    ///
+    #[inline]
+    pub unsafe fn new(image: &'a UnsafeImage) -> SubmitBindSparseImageBindBuilder<'a> {
+        let aspect_mask = match image.format().ty() {
+            FormatTy::Float | FormatTy::Uint | FormatTy::Sint | FormatTy::Compressed => {
+                vk::IMAGE_ASPECT_COLOR_BIT
+            },
+            FormatTy::Depth => vk::IMAGE_ASPECT_DEPTH_BIT,
+            FormatTy::Stencil => vk::IMAGE_ASPECT_STENCIL_BIT,
+            FormatTy::DepthStencil => vk::IMAGE_ASPECT_DEPTH_BIT | vk::IMAGE_ASPECT_STENCIL_BIT,
+        };
+
+        SubmitBindSparseImageBindBuilder {
+            image: image.internal_object(),
+            aspect_mask: aspect_mask,
+            binds: SmallVec::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Binds the region of the given mipmap level and array layer that starts at `offset` and is
+    /// of size `extent` to the memory, starting at `memory_offset`.
+    ///
+    /// # Safety
+    ///
+    /// - The offset must be a multiple of the sparse block granularity of the image, and the
+    ///   extent must be a multiple of it as well except for a region that reaches the edge of the
+    ///   mipmap level.
+    ///
+    /// - The mipmap level must be below the first level of the mipmap tail, and the region must
+    ///   be within the mipmap level.
+    ///
+    /// - The memory range must be within the memory, and its offset must be a multiple of the
+    ///   alignment of the memory requirements of the image.
+    ///
+    /// - The memory must satisfy the memory requirements of the image.
+    ///
+    /// - If you submit the bindings, the memory must be kept alive for as long as it is bound to
+    ///   the image.
+    ///
+    #[inline]
+    pub unsafe fn add_bind(&mut self, mip_level: u32, array_layer: u32, offset: [u32; 3],
+                           extent: [u32; 3], memory: &'a DeviceMemory, memory_offset: usize)
+    {
+        self.push(mip_level, array_layer, offset, extent, memory.internal_object(),
+                  memory_offset as vk::DeviceSize);
+    }
+
+    /// Unbinds the region of the given mipmap level and array layer that starts at `offset` and
+    /// is of size `extent`.
+    ///
+    /// # Safety
+    ///
+    /// - The offset must be a multiple of the sparse block granularity of the image, and the
+    ///   extent must be a multiple of it as well except for a region that reaches the edge of the
+    ///   mipmap level.
+    ///
+    /// - The mipmap level must be below the first level of the mipmap tail, and the region must
+    ///   be within the mipmap level.
+    ///
+    #[inline]
+    pub unsafe fn add_unbind(&mut self, mip_level: u32, array_layer: u32, offset: [u32; 3],
+                             extent: [u32; 3])
+    {
+        self.push(mip_level, array_layer, offset, extent, 0, 0);
+    }
+
+    #[inline]
+    fn push(&mut self, mip_level: u32, array_layer: u32, offset: [u32; 3], extent: [u32; 3],
+            memory: vk::DeviceMemory, memory_offset: vk::DeviceSize)
+    {
+        self.binds.push(vk::SparseImageMemoryBind {
+            subresource: vk::ImageSubresource {
+                aspectMask: self.aspect_mask,
+                mipLevel: mip_level,
+                arrayLayer: array_layer,
+            },
+            offset: vk::Offset3D {
+                x: offset[0] as i32,
+                y: offset[1] as i32,
+                z: offset[2] as i32,
+            },
+            extent: vk::Extent3D {
+                width: extent[0],
+                height: extent[1],
+                depth: extent[2],
+            },
+            memory: memory,
+            memoryOffset: memory_offset,
+            flags: 0,
+        });
+    }
+}
+
+impl<'a> fmt::Debug for SubmitBindSparseImageBindBuilder<'a> {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "<Sparse image memory bindings>")
+    }
+}
+
+/// Error that can happen when submitting the bind sparse prototype.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum SubmitBindSparseError {
+    /// Not enough memory.
+    OomError(OomError),
+
+    /// The connection to the device has been lost.
+    DeviceLost,
+
+    /// Bind sparse operations aren't ordered with the command buffers and presents that are
+    /// submitted after them. They must signal a semaphore first.
+    MissingSignalSemaphore,
+
+    /// Bind sparse operations can't be ordered after a present, since presents can't signal
+    /// semaphores.
+    AfterPresent,
+}
+
+impl error::Error for SubmitBindSparseError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            SubmitBindSparseError::OomError(_) => "not enough memory",
+            SubmitBindSparseError::DeviceLost => "the connection to the device has been lost",
+            SubmitBindSparseError::MissingSignalSemaphore => {
+                "bind sparse operations must signal a semaphore before a command buffer or a \
+                 present can be submitted after them"
+            },
+            SubmitBindSparseError::AfterPresent => {
+                "bind sparse operations can't be ordered after a present"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            SubmitBindSparseError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for SubmitBindSparseError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<Error> for SubmitBindSparseError {
+    #[inline]
+    fn from(err: Error) -> SubmitBindSparseError {
+        match err {
+            err @ Error::OutOfHostMemory => SubmitBindSparseError::OomError(OomError::from(err)),
+            err @ Error::OutOfDeviceMemory => SubmitBindSparseError::OomError(OomError::from(err)),
+            Error::DeviceLost => SubmitBindSparseError::DeviceLost,
+            _ => panic!("unexpected error: {:?}", err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::*;
+    use mock;
+    use sync::Fence;
+
+    #[test]
+    fn empty_submit() {
+        let (device, queue) = mock_dev_and_queue!();
+        let builder = SubmitBindSparseBuilder::new();
+        builder.submit(&queue).unwrap();
+        assert_eq!(mock::calls(&device).last(), Some(&"QueueBindSparse"));
+    }
+
+    #[test]
+    fn signal_fence() {
+        unsafe {
+            let (device, queue) = mock_dev_and_queue!();
+
+            let fence = Fence::new(device.clone()).unwrap();
+            assert!(!fence.ready().unwrap());
+
+            let mut builder = SubmitBindSparseBuilder::new();
+            assert!(!builder.has_fence());
+            builder.set_fence_signal(&fence);
+            assert!(builder.has_fence());
+
+            builder.submit(&queue).unwrap();
+            fence.wait(Duration::from_secs(10)).unwrap();
+            assert!(fence.ready().unwrap());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "to a queue that doesn't support it")]
+    fn queue_without_sparse_binding() {
+        let (_, queue) = mock_dev_and_queue!(queue: |q| !q.supports_sparse_binding());
+
+        let _ = SubmitBindSparseBuilder::new().submit(&queue);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't merge two bind sparse submissions that both have a fence")]
+    fn merge_both_have_fences() {
+        unsafe {
+            let (device, _) = mock_dev_and_queue!();
+
+            let fence1 = Fence::new(device.clone()).unwrap();
+            let fence2 = Fence::new(device.clone()).unwrap();
+
+            let mut builder1 = SubmitBindSparseBuilder::new();
+            builder1.set_fence_signal(&fence1);
+            let mut builder2 = SubmitBindSparseBuilder::new();
+            builder2.set_fence_signal(&fence2);
+
+            let _ = builder1.merge(builder2);
+        }
+    }
+}
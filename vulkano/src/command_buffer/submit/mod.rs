@@ -13,12 +13,18 @@
 //! module. These structs are low-level and unsafe, and are mostly used to implement other parts
 //! of vulkano, so you are encouraged to not use them directly.
 
+pub use self::bind_sparse::SubmitBindSparseBufferBindBuilder;
+pub use self::bind_sparse::SubmitBindSparseBuilder;
+pub use self::bind_sparse::SubmitBindSparseError;
+pub use self::bind_sparse::SubmitBindSparseImageBindBuilder;
+pub use self::bind_sparse::SubmitBindSparseImageOpaqueBindBuilder;
 pub use self::queue_present::SubmitPresentBuilder;
 pub use self::queue_present::SubmitPresentError;
 pub use self::queue_submit::SubmitCommandBufferBuilder;
 pub use self::queue_submit::SubmitCommandBufferError;
 pub use self::semaphores_wait::SubmitSemaphoresWaitBuilder;
 
+mod bind_sparse;
 mod queue_present;
 mod queue_submit;
 mod semaphores_wait;
@@ -30,6 +36,7 @@ pub enum SubmitAnyBuilder<'a> {
     SemaphoresWait(SubmitSemaphoresWaitBuilder<'a>),
     CommandBuffer(SubmitCommandBufferBuilder<'a>),
     QueuePresent(SubmitPresentBuilder<'a>),
+    BindSparse(SubmitBindSparseBuilder<'a>),
 }
 
 impl<'a> SubmitAnyBuilder<'a> {
@@ -17,11 +17,13 @@ use buffer::BufferAccess;
 use command_buffer::cb::UnsafeCommandBuffer;
 use command_buffer::pool::CommandPool;
 use command_buffer::submit::SubmitAnyBuilder;
+use command_buffer::submit::SubmitBindSparseError;
 use command_buffer::submit::SubmitCommandBufferBuilder;
 use device::Device;
 use device::DeviceOwned;
 use device::Queue;
 use image::ImageAccess;
+use image::ImageSubresourceRange;
 use sync::AccessFlagBits;
 use sync::DummyFuture;
 use sync::GpuFuture;
@@ -94,9 +96,23 @@ pub unsafe trait CommandBuffer: DeviceOwned {
     fn check_buffer_access(&self, buffer: &BufferAccess, exclusive: bool, queue: &Queue)
                            -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>;
 
-    fn check_image_access(&self, image: &ImageAccess, exclusive: bool, queue: &Queue)
+    fn check_image_access(&self, image: &ImageAccess, range: &ImageSubresourceRange,
+                          exclusive: bool, queue: &Queue)
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>;
 
+    /// Returns the list of buffers accessed by this command buffer, and whether the access is
+    /// exclusive.
+    ///
+    /// This is used when a secondary command buffer is executed from a primary command buffer.
+    fn buffers_list<'a>(&'a self) -> Box<Iterator<Item = (&'a BufferAccess, bool)> + 'a>;
+
+    /// Returns the list of images accessed by this command buffer, and whether the access is
+    /// exclusive.
+    ///
+    /// This is used when a secondary command buffer is executed from a primary command buffer.
+    fn images_list<'a>(&'a self)
+                       -> Box<Iterator<Item = (&'a ImageAccess, ImageSubresourceRange, bool)> + 'a>;
+
     // FIXME: lots of other methods
 }
 
@@ -132,10 +148,23 @@ unsafe impl<T> CommandBuffer for T where T: SafeDeref, T::Target: CommandBuffer
     }
 
     #[inline]
-    fn check_image_access(&self, image: &ImageAccess, exclusive: bool, queue: &Queue)
+    fn check_image_access(&self, image: &ImageAccess, range: &ImageSubresourceRange,
+                          exclusive: bool, queue: &Queue)
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>
     {
-        (**self).check_image_access(image, exclusive, queue)
+        (**self).check_image_access(image, range, exclusive, queue)
+    }
+
+    #[inline]
+    fn buffers_list<'a>(&'a self) -> Box<Iterator<Item = (&'a BufferAccess, bool)> + 'a> {
+        (**self).buffers_list()
+    }
+
+    #[inline]
+    fn images_list<'a>(&'a self)
+                       -> Box<Iterator<Item = (&'a ImageAccess, ImageSubresourceRange, bool)> + 'a>
+    {
+        (**self).images_list()
     }
 }
 
@@ -185,6 +214,9 @@ unsafe impl<F, Cb> GpuFuture for CommandBufferExecFuture<F, Cb>
                 builder.add_command_buffer(self.command_buffer.inner());
                 SubmitAnyBuilder::CommandBuffer(builder)*/
             },
+            SubmitAnyBuilder::BindSparse(_) => {
+                return Err(Box::new(SubmitBindSparseError::MissingSignalSemaphore));
+            },
         })
     }
 
@@ -239,12 +271,13 @@ unsafe impl<F, Cb> GpuFuture for CommandBufferExecFuture<F, Cb>
     }
 
     #[inline]
-    fn check_image_access(&self, image: &ImageAccess, exclusive: bool, queue: &Queue)
+    fn check_image_access(&self, image: &ImageAccess, range: &ImageSubresourceRange,
+                          exclusive: bool, queue: &Queue)
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>
     {
-        match self.command_buffer.check_image_access(image, exclusive, queue) {
+        match self.command_buffer.check_image_access(image, range, exclusive, queue) {
             Ok(v) => Ok(v),
-            Err(()) => self.previous.check_image_access(image, exclusive, queue),
+            Err(()) => self.previous.check_image_access(image, range, exclusive, queue),
         }
     }
 }
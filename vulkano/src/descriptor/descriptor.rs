@@ -251,10 +251,11 @@ impl DescriptorImageDesc {
             (DescriptorImageDescArray::Arrayed { max_layers: my_max },
              DescriptorImageDescArray::Arrayed { max_layers: other_max }) =>
             {
+                // `None` means that the number of layers isn't limited.
                 match (my_max, other_max) {
                     (Some(m), Some(o)) => if m < o { return false; },
                     (Some(_), None) => (),
-                    (None, _) => return false,
+                    (None, _) => (),
                 };
             },
             _ => return false
@@ -14,6 +14,7 @@ use descriptor::descriptor_set::DescriptorSet;
 use descriptor::descriptor_set::DescriptorSetDesc;
 use descriptor::descriptor_set::UnsafeDescriptorSet;
 use image::ImageAccess;
+use image::ImageSubresourceRange;
 
 /// A collection of descriptor set objects.
 pub unsafe trait DescriptorSetsCollection {
@@ -38,8 +39,10 @@ pub unsafe trait DescriptorSetsCollection {
     /// Returns the list of buffers used by this descriptor set. Includes buffer views.
     fn buffers_list<'a>(&'a self) -> Box<Iterator<Item = &'a BufferAccess> + 'a>;
 
-    /// Returns the list of images used by this descriptor set. Includes image views.
-    fn images_list<'a>(&'a self) -> Box<Iterator<Item = &'a ImageAccess> + 'a>;
+    /// Returns the list of images used by this descriptor set, and the range of subresources of
+    /// each image that is accessed. Includes image views.
+    fn images_list<'a>(&'a self)
+                       -> Box<Iterator<Item = (&'a ImageAccess, ImageSubresourceRange)> + 'a>;
 }
 
 unsafe impl DescriptorSetsCollection for () {
@@ -69,7 +72,9 @@ unsafe impl DescriptorSetsCollection for () {
     }
 
     #[inline]
-    fn images_list<'a>(&'a self) -> Box<Iterator<Item = &'a ImageAccess> + 'a> {
+    fn images_list<'a>(&'a self)
+                       -> Box<Iterator<Item = (&'a ImageAccess, ImageSubresourceRange)> + 'a>
+    {
         Box::new(iter::empty())
     }
 }
@@ -112,7 +117,9 @@ unsafe impl<T> DescriptorSetsCollection for T
     }
 
     #[inline]
-    fn images_list<'a>(&'a self) -> Box<Iterator<Item = &'a ImageAccess> + 'a> {
+    fn images_list<'a>(&'a self)
+                       -> Box<Iterator<Item = (&'a ImageAccess, ImageSubresourceRange)> + 'a>
+    {
         DescriptorSet::images_list(self)
     }
 }
@@ -206,7 +213,10 @@ macro_rules! impl_collection {
             }
 
             #[inline]
-            fn images_list<'a>(&'a self) -> Box<Iterator<Item = &'a ImageAccess> + 'a> {
+            fn images_list<'a>(&'a self)
+                               -> Box<Iterator<Item = (&'a ImageAccess, ImageSubresourceRange)>
+                                      + 'a>
+            {
                 #![allow(non_snake_case)]
 
                 let &(ref first, $(ref $others,)*) = self;
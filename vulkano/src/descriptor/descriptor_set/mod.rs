@@ -38,6 +38,7 @@
 use buffer::BufferAccess;
 use descriptor::descriptor::DescriptorDesc;
 use image::ImageAccess;
+use image::ImageSubresourceRange;
 use SafeDeref;
 
 pub use self::collection::DescriptorSetsCollection;
@@ -72,9 +73,11 @@ pub unsafe trait DescriptorSet: DescriptorSetDesc {
     // TODO: meh for boxing
     fn buffers_list<'a>(&'a self) -> Box<Iterator<Item = &'a BufferAccess> + 'a>;
 
-    /// Returns the list of images used by this descriptor set. Includes image views.
+    /// Returns the list of images used by this descriptor set, and the range of subresources of
+    /// each image that is accessed. Includes image views.
     // TODO: meh for boxing
-    fn images_list<'a>(&'a self) -> Box<Iterator<Item = &'a ImageAccess> + 'a>;
+    fn images_list<'a>(&'a self)
+                       -> Box<Iterator<Item = (&'a ImageAccess, ImageSubresourceRange)> + 'a>;
 }
 
 unsafe impl<T> DescriptorSet for T where T: SafeDeref, T::Target: DescriptorSet {
@@ -89,7 +92,9 @@ unsafe impl<T> DescriptorSet for T where T: SafeDeref, T::Target: DescriptorSet
     }
 
     #[inline]
-    fn images_list<'a>(&'a self) -> Box<Iterator<Item = &'a ImageAccess> + 'a> {
+    fn images_list<'a>(&'a self)
+                       -> Box<Iterator<Item = (&'a ImageAccess, ImageSubresourceRange)> + 'a>
+    {
         (**self).images_list()
     }
 }
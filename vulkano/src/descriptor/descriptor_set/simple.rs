@@ -26,7 +26,9 @@ use descriptor::pipeline_layout::PipelineLayoutAbstract;
 use device::Device;
 use device::DeviceOwned;
 use image::ImageAccess;
+use image::ImageSubresourceRange;
 use image::ImageView;
+use image::ImageViewAccess;
 use image::sys::Layout;
 use sampler::Sampler;
 use sync::AccessFlagBits;
@@ -62,7 +64,9 @@ impl<R, P> SimpleDescriptorSet<R, P> where P: DescriptorPool {
     }
 }
 
-unsafe impl<R, P> DescriptorSet for SimpleDescriptorSet<R, P> where P: DescriptorPool {
+unsafe impl<R, P> DescriptorSet for SimpleDescriptorSet<R, P>
+    where P: DescriptorPool, R: SimpleDescriptorSetResourcesCollection
+{
     #[inline]
     fn inner(&self) -> &UnsafeDescriptorSet {
         self.inner.inner()
@@ -70,12 +74,18 @@ unsafe impl<R, P> DescriptorSet for SimpleDescriptorSet<R, P> where P: Descripto
 
     #[inline]
     fn buffers_list<'a>(&'a self) -> Box<Iterator<Item = &'a BufferAccess> + 'a> {
-        unimplemented!()
+        let mut list = Vec::new();
+        self.resources.add_buffers(&mut list);
+        Box::new(list.into_iter())
     }
 
     #[inline]
-    fn images_list<'a>(&'a self) -> Box<Iterator<Item = &'a ImageAccess> + 'a> {
-        unimplemented!()
+    fn images_list<'a>(&'a self)
+                       -> Box<Iterator<Item = (&'a ImageAccess, ImageSubresourceRange)> + 'a>
+    {
+        let mut list = Vec::new();
+        self.resources.add_images(&mut list);
+        Box::new(list.into_iter())
     }
 }
 
@@ -286,10 +296,6 @@ unsafe impl<L, R, T> SimpleDescriptorSetImageExt<L, R> for T
                 image: image_view,
                 sampler: None,
                 write: !desc.readonly,
-                first_mipmap: 0,            // FIXME:
-                num_mipmaps: 1,         // FIXME:
-                first_layer: 0,         // FIXME:
-                num_layers: 1,          // FIXME:
                 layout: Layout::General,            // FIXME:
                 stage: PipelineStages::none(),          // FIXME:
                 access: AccessFlagBits::none(),         // FIXME:
@@ -328,10 +334,6 @@ unsafe impl<L, R, T> SimpleDescriptorSetImageExt<L, R> for (T, Arc<Sampler>)
                 image: image_view,
                 sampler: Some(self.1),
                 write: !desc.readonly,
-                first_mipmap: 0,            // FIXME:
-                num_mipmaps: 1,         // FIXME:
-                first_layer: 0,         // FIXME:
-                num_layers: 1,          // FIXME:
                 layout: Layout::General,            // FIXME:
                 stage: PipelineStages::none(),          // FIXME:
                 access: AccessFlagBits::none(),         // FIXME:
@@ -371,10 +373,6 @@ unsafe impl<L, R, T> SimpleDescriptorSetImageExt<L, R> for Vec<(T, Arc<Sampler>)
                 image: image_view,
                 sampler: Some(sampler),
                 write: !desc.readonly,
-                first_mipmap: 0,            // FIXME:
-                num_mipmaps: 1,         // FIXME:
-                first_layer: 0,         // FIXME:
-                num_layers: 1,          // FIXME:
                 layout: Layout::General,            // FIXME:
                 stage: PipelineStages::none(),          // FIXME:
                 access: AccessFlagBits::none(),         // FIXME:
@@ -390,18 +388,24 @@ unsafe impl<L, R, T> SimpleDescriptorSetImageExt<L, R> for Vec<(T, Arc<Sampler>)
     }
 }
 
-/*
 /// Internal trait related to the `SimpleDescriptorSet` system.
 pub unsafe trait SimpleDescriptorSetResourcesCollection {
-    #[inline]
-    fn add_transition<'a>(&'a self, sink: &mut CommandsListSink<'a>);
+    /// Appends the buffers of the collection to `list`.
+    fn add_buffers<'a>(&'a self, list: &mut Vec<&'a BufferAccess>);
+
+    /// Appends the images of the collection to `list`.
+    fn add_images<'a>(&'a self, list: &mut Vec<(&'a ImageAccess, ImageSubresourceRange)>);
 }
 
 unsafe impl SimpleDescriptorSetResourcesCollection for () {
     #[inline]
-    fn add_transition<'a>(&'a self, _: &mut CommandsListSink<'a>) {
+    fn add_buffers<'a>(&'a self, _: &mut Vec<&'a BufferAccess>) {
     }
-}*/
+
+    #[inline]
+    fn add_images<'a>(&'a self, _: &mut Vec<(&'a ImageAccess, ImageSubresourceRange)>) {
+    }
+}
 
 /// Internal object related to the `SimpleDescriptorSet` system.
 pub struct SimpleDescriptorSetBuf<B> {
@@ -411,28 +415,18 @@ pub struct SimpleDescriptorSetBuf<B> {
     access: AccessFlagBits,
 }
 
-/*unsafe impl<B> SimpleDescriptorSetResourcesCollection for SimpleDescriptorSetBuf<B>
+unsafe impl<B> SimpleDescriptorSetResourcesCollection for SimpleDescriptorSetBuf<B>
     where B: BufferAccess
 {
     #[inline]
-    fn add_transition<'a>(&'a self, sink: &mut CommandsListSink<'a>) {
-        // TODO: wrong values
-        let stages = PipelineStages {
-            compute_shader: true,
-            all_graphics: true,
-            .. PipelineStages::none()
-        };
-        
-        let access = AccessFlagBits {
-            uniform_read: true,
-            shader_read: true,
-            shader_write: true,
-            .. AccessFlagBits::none()
-        };
+    fn add_buffers<'a>(&'a self, list: &mut Vec<&'a BufferAccess>) {
+        list.push(&self.buffer);
+    }
 
-        sink.add_buffer_transition(&self.buffer, 0, self.buffer.size(), self.write, stages, access);
+    #[inline]
+    fn add_images<'a>(&'a self, _: &mut Vec<(&'a ImageAccess, ImageSubresourceRange)>) {
     }
-}*/
+}
 
 /// Internal object related to the `SimpleDescriptorSet` system.
 pub struct SimpleDescriptorSetBufView<V> where V: BufferViewRef {
@@ -442,68 +436,57 @@ pub struct SimpleDescriptorSetBufView<V> where V: BufferViewRef {
     access: AccessFlagBits,
 }
 
-/*unsafe impl<V> SimpleDescriptorSetResourcesCollection for SimpleDescriptorSetBufView<V>
-    where V: BufferViewRef, V::BufferAccess: BufferAccess
+unsafe impl<V> SimpleDescriptorSetResourcesCollection for SimpleDescriptorSetBufView<V>
+    where V: BufferViewRef
 {
     #[inline]
-    fn add_transition<'a>(&'a self, sink: &mut CommandsListSink<'a>) {
-        // TODO: wrong values
-        let stages = PipelineStages {
-            compute_shader: true,
-            all_graphics: true,
-            .. PipelineStages::none()
-        };
-        
-        let access = AccessFlagBits {
-            uniform_read: true,
-            shader_read: true,
-            shader_write: true,
-            .. AccessFlagBits::none()
-        };
+    fn add_buffers<'a>(&'a self, list: &mut Vec<&'a BufferAccess>) {
+        list.push(self.view.view().buffer());
+    }
 
-        sink.add_buffer_transition(self.view.view().buffer(), 0, self.view.view().buffer().size(),
-                                   self.write, stages, access);
+    #[inline]
+    fn add_images<'a>(&'a self, _: &mut Vec<(&'a ImageAccess, ImageSubresourceRange)>) {
     }
-}*/
+}
 
 /// Internal object related to the `SimpleDescriptorSet` system.
 pub struct SimpleDescriptorSetImg<I> {
     image: I,
     sampler: Option<Arc<Sampler>>,
     write: bool,
-    first_mipmap: u32,
-    num_mipmaps: u32,
-    first_layer: u32,
-    num_layers: u32,
     layout: Layout,
     stage: PipelineStages,
     access: AccessFlagBits,
 }
 
-/*unsafe impl<I> SimpleDescriptorSetResourcesCollection for SimpleDescriptorSetImg<I>
+unsafe impl<I> SimpleDescriptorSetResourcesCollection for SimpleDescriptorSetImg<I>
     where I: ImageViewAccess
 {
     #[inline]
-    fn add_transition<'a>(&'a self, sink: &mut CommandsListSink<'a>) {
-        // TODO: wrong values
-        let stages = PipelineStages {
-            compute_shader: true,
-            all_graphics: true,
-            .. PipelineStages::none()
-        };
-        
-        let access = AccessFlagBits {
-            uniform_read: true,
-            input_attachment_read: true,
-            shader_read: true,
-            shader_write: true,
-            .. AccessFlagBits::none()
-        };
+    fn add_buffers<'a>(&'a self, _: &mut Vec<&'a BufferAccess>) {
+    }
+
+    #[inline]
+    fn add_images<'a>(&'a self, list: &mut Vec<(&'a ImageAccess, ImageSubresourceRange)>) {
+        list.push((self.image.parent(), self.image.subresource_range()));
+    }
+}
+
+unsafe impl<T> SimpleDescriptorSetResourcesCollection for Vec<T>
+    where T: SimpleDescriptorSetResourcesCollection
+{
+    #[inline]
+    fn add_buffers<'a>(&'a self, list: &mut Vec<&'a BufferAccess>) {
+        for elem in self.iter() {
+            elem.add_buffers(list);
+        }
+    }
 
-        // FIXME: adjust layers & mipmaps with the view's parameters
-        sink.add_image_transition(self.image.parent(), self.first_layer, self.num_layers,
-                                  self.first_mipmap, self.num_mipmaps, self.write,
-                                  self.layout, stages, access);
+    #[inline]
+    fn add_images<'a>(&'a self, list: &mut Vec<(&'a ImageAccess, ImageSubresourceRange)>) {
+        for elem in self.iter() {
+            elem.add_images(list);
+        }
     }
 }
 
@@ -512,8 +495,14 @@ unsafe impl<A, B> SimpleDescriptorSetResourcesCollection for (A, B)
           B: SimpleDescriptorSetResourcesCollection
 {
     #[inline]
-    fn add_transition<'a>(&'a self, sink: &mut CommandsListSink<'a>) {
-        self.0.add_transition(sink);
-        self.1.add_transition(sink);
+    fn add_buffers<'a>(&'a self, list: &mut Vec<&'a BufferAccess>) {
+        self.0.add_buffers(list);
+        self.1.add_buffers(list);
     }
-}*/
+
+    #[inline]
+    fn add_images<'a>(&'a self, list: &mut Vec<(&'a ImageAccess, ImageSubresourceRange)>) {
+        self.0.add_images(list);
+        self.1.add_images(list);
+    }
+}
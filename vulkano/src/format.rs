@@ -101,7 +101,68 @@
 //!
 //! // TODO: storage formats
 //!
+//! # Metadata
+//!
+//! Each `Format` can be asked for its static properties, which don't depend on the device:
+//! the dimensions and size of its blocks of texels (`block_dimensions` and `block_size`), its
+//! aspects, the number of bits of its components, its numeric type, and its compatibility
+//! class. Uncompressed formats have blocks of a single texel.
+//!
+//! # Encoding and decoding texels
+//!
+//! `Format::encode_texel` and `Format::decode_texel` convert between a color stored as
+//! `[f32; 4]` and the bytes of a texel of a format, for example to build reference images or to
+//! check the content of an image after it has been copied to a buffer. Formats that implement
+//! `StrongStorage` can do the same with their `Pixel` type. `ClearValue::from_format` and
+//! `DownloadedImage::texel` are built on top of them.
+//!
+//! ```
+//! use vulkano::format::Format;
+//!
+//! let mut texel = [0; 4];
+//! Format::R8G8B8A8Srgb.encode_texel([0.5, 0.0, 1.0, 1.0], &mut texel).unwrap();
+//! assert_eq!(texel, [188, 0, 255, 255]);
+//! ```
+//!
+//! # Querying support
+//!
+//! The features that a physical device supports for a format can be queried with
+//! `Format::properties`. For images, the limits that depend on the type, tiling, usage and flags
+//! of the image can be queried with `Format::image_format_properties`.
+//!
+//! ```no_run
+//! use vulkano::format::Format;
+//! use vulkano::instance::PhysicalDevice;
+//!
+//! # let physical_device: PhysicalDevice = unsafe { ::std::mem::uninitialized() };
+//! let properties = Format::R16G16B16A16Sfloat.properties(physical_device);
+//! if properties.optimal_tiling_features.color_attachment_blend {
+//!     // The format can be used as a color attachment with blending.
+//! }
+//! ```
+//!
+use std::cmp;
+use std::error;
+use std::f64;
+use std::fmt;
+use std::i32;
+use std::mem;
+use std::slice;
+use std::u32;
 use std::vec::IntoIter as VecIntoIter;
+
+use image::ImageAspect;
+use image::ImageCreateFlags;
+use image::ImageTiling;
+use image::ImageType;
+use image::Usage as ImageUsage;
+use instance::PhysicalDevice;
+
+use Error;
+use OomError;
+use VulkanObject;
+use VulkanPointers;
+use check_errors;
 use vk;
 
 // TODO: add enumerations for color, depth, stencil and depthstencil formats
@@ -117,20 +178,58 @@ pub unsafe trait Data {
     // TODO "is_supported" functions that redirect to `Self::ty().is_supported()`
 }
 
-// TODO: that's just an example ; implement for all common data types
-unsafe impl Data for i8 {
-    #[inline]
-    fn ty() -> Format { Format::R8Sint }
+macro_rules! data_impls {
+    ($($ty:ty => $format:ident,)+) => (
+        $(
+            unsafe impl Data for $ty {
+                #[inline]
+                fn ty() -> Format { Format::$format }
+            }
+        )+
+    );
 }
-unsafe impl Data for u8 {
-    #[inline]
-    fn ty() -> Format { Format::R8Uint }
+
+data_impls! {
+    i8 => R8Sint,
+    u8 => R8Uint,
+    i16 => R16Sint,
+    u16 => R16Uint,
+    i32 => R32Sint,
+    u32 => R32Uint,
+    f32 => R32Sfloat,
+    i64 => R64Sint,
+    u64 => R64Uint,
+    f64 => R64Sfloat,
+    [i8; 2] => R8G8Sint,
+    [u8; 2] => R8G8Uint,
+    [i8; 3] => R8G8B8Sint,
+    [u8; 3] => R8G8B8Uint,
+    [i8; 4] => R8G8B8A8Sint,
+    [u8; 4] => R8G8B8A8Uint,
+    [i16; 2] => R16G16Sint,
+    [u16; 2] => R16G16Uint,
+    [i16; 3] => R16G16B16Sint,
+    [u16; 3] => R16G16B16Uint,
+    [i16; 4] => R16G16B16A16Sint,
+    [u16; 4] => R16G16B16A16Uint,
+    [i32; 2] => R32G32Sint,
+    [u32; 2] => R32G32Uint,
+    [f32; 2] => R32G32Sfloat,
+    [i32; 3] => R32G32B32Sint,
+    [u32; 3] => R32G32B32Uint,
+    [f32; 3] => R32G32B32Sfloat,
+    [i32; 4] => R32G32B32A32Sint,
+    [u32; 4] => R32G32B32A32Uint,
+    [f32; 4] => R32G32B32A32Sfloat,
+    [f64; 2] => R64G64Sfloat,
+    [f64; 3] => R64G64B64Sfloat,
+    [f64; 4] => R64G64B64A64Sfloat,
 }
 
 macro_rules! formats {
     ($($name:ident => $vk:ident [$sz:expr] [$($f_ty:tt)*] {$($d_ty:tt)*},)+) => (
         /// An enumeration of all the possible formats.
-        #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
         #[repr(u32)]
         #[allow(missing_docs)]
         #[allow(non_camel_case_types)]
@@ -139,13 +238,6 @@ macro_rules! formats {
         }
 
         impl Format {
-            /*pub fn is_supported_for_vertex_attributes(&self) -> bool {
-                
-            }
-
-            .. other functions ..
-            */
-
             /// Returns the size in bytes of an element of this format. Returns `None` if the
             /// size is irrelevant (for example for compressed formats).
             #[inline]
@@ -491,60 +583,893 @@ formats! {
     D16Unorm_S8Uint => FORMAT_D16_UNORM_S8_UINT [None] [depthstencil] {},
     D24Unorm_S8Uint => FORMAT_D24_UNORM_S8_UINT [None] [depthstencil] {},
     D32Sfloat_S8Uint => FORMAT_D32_SFLOAT_S8_UINT [None] [depthstencil] {},
-    BC1_RGBUnormBlock => FORMAT_BC1_RGB_UNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC1_RGBSrgbBlock => FORMAT_BC1_RGB_SRGB_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC1_RGBAUnormBlock => FORMAT_BC1_RGBA_UNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC1_RGBASrgbBlock => FORMAT_BC1_RGBA_SRGB_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC2UnormBlock => FORMAT_BC2_UNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC2SrgbBlock => FORMAT_BC2_SRGB_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC3UnormBlock => FORMAT_BC3_UNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC3SrgbBlock => FORMAT_BC3_SRGB_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC4UnormBlock => FORMAT_BC4_UNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC4SnormBlock => FORMAT_BC4_SNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC5UnormBlock => FORMAT_BC5_UNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC5SnormBlock => FORMAT_BC5_SNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC6HUfloatBlock => FORMAT_BC6H_UFLOAT_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC6HSfloatBlock => FORMAT_BC6H_SFLOAT_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC7UnormBlock => FORMAT_BC7_UNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC7SrgbBlock => FORMAT_BC7_SRGB_BLOCK [None] [compressed=texture_compression_bc] {},
-    ETC2_R8G8B8UnormBlock => FORMAT_ETC2_R8G8B8_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {},
-    ETC2_R8G8B8SrgbBlock => FORMAT_ETC2_R8G8B8_SRGB_BLOCK [None] [compressed=texture_compression_etc2] {},
-    ETC2_R8G8B8A1UnormBlock => FORMAT_ETC2_R8G8B8A1_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {},
-    ETC2_R8G8B8A1SrgbBlock => FORMAT_ETC2_R8G8B8A1_SRGB_BLOCK [None] [compressed=texture_compression_etc2] {},
-    ETC2_R8G8B8A8UnormBlock => FORMAT_ETC2_R8G8B8A8_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {},
-    ETC2_R8G8B8A8SrgbBlock => FORMAT_ETC2_R8G8B8A8_SRGB_BLOCK [None] [compressed=texture_compression_etc2] {},
-    EAC_R11UnormBlock => FORMAT_EAC_R11_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {},
-    EAC_R11SnormBlock => FORMAT_EAC_R11_SNORM_BLOCK [None] [compressed=texture_compression_etc2] {},
-    EAC_R11G11UnormBlock => FORMAT_EAC_R11G11_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {},
-    EAC_R11G11SnormBlock => FORMAT_EAC_R11G11_SNORM_BLOCK [None] [compressed=texture_compression_etc2] {},
-    ASTC_4x4UnormBlock => FORMAT_ASTC_4x4_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_4x4SrgbBlock => FORMAT_ASTC_4x4_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_5x4UnormBlock => FORMAT_ASTC_5x4_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_5x4SrgbBlock => FORMAT_ASTC_5x4_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_5x5UnormBlock => FORMAT_ASTC_5x5_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_5x5SrgbBlock => FORMAT_ASTC_5x5_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_6x5UnormBlock => FORMAT_ASTC_6x5_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_6x5SrgbBlock => FORMAT_ASTC_6x5_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_6x6UnormBlock => FORMAT_ASTC_6x6_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_6x6SrgbBlock => FORMAT_ASTC_6x6_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_8x5UnormBlock => FORMAT_ASTC_8x5_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_8x5SrgbBlock => FORMAT_ASTC_8x5_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_8x6UnormBlock => FORMAT_ASTC_8x6_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_8x6SrgbBlock => FORMAT_ASTC_8x6_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_8x8UnormBlock => FORMAT_ASTC_8x8_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_8x8SrgbBlock => FORMAT_ASTC_8x8_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_10x5UnormBlock => FORMAT_ASTC_10x5_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_10x5SrgbBlock => FORMAT_ASTC_10x5_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_10x6UnormBlock => FORMAT_ASTC_10x6_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_10x6SrgbBlock => FORMAT_ASTC_10x6_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_10x8UnormBlock => FORMAT_ASTC_10x8_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_10x8SrgbBlock => FORMAT_ASTC_10x8_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_10x10UnormBlock => FORMAT_ASTC_10x10_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_10x10SrgbBlock => FORMAT_ASTC_10x10_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_12x10UnormBlock => FORMAT_ASTC_12x10_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_12x10SrgbBlock => FORMAT_ASTC_12x10_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_12x12UnormBlock => FORMAT_ASTC_12x12_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_12x12SrgbBlock => FORMAT_ASTC_12x12_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
+    BC1_RGBUnormBlock => FORMAT_BC1_RGB_UNORM_BLOCK [None] [compressed=texture_compression_bc] {[u8; 8]},
+    BC1_RGBSrgbBlock => FORMAT_BC1_RGB_SRGB_BLOCK [None] [compressed=texture_compression_bc] {[u8; 8]},
+    BC1_RGBAUnormBlock => FORMAT_BC1_RGBA_UNORM_BLOCK [None] [compressed=texture_compression_bc] {[u8; 8]},
+    BC1_RGBASrgbBlock => FORMAT_BC1_RGBA_SRGB_BLOCK [None] [compressed=texture_compression_bc] {[u8; 8]},
+    BC2UnormBlock => FORMAT_BC2_UNORM_BLOCK [None] [compressed=texture_compression_bc] {[u8; 16]},
+    BC2SrgbBlock => FORMAT_BC2_SRGB_BLOCK [None] [compressed=texture_compression_bc] {[u8; 16]},
+    BC3UnormBlock => FORMAT_BC3_UNORM_BLOCK [None] [compressed=texture_compression_bc] {[u8; 16]},
+    BC3SrgbBlock => FORMAT_BC3_SRGB_BLOCK [None] [compressed=texture_compression_bc] {[u8; 16]},
+    BC4UnormBlock => FORMAT_BC4_UNORM_BLOCK [None] [compressed=texture_compression_bc] {[u8; 8]},
+    BC4SnormBlock => FORMAT_BC4_SNORM_BLOCK [None] [compressed=texture_compression_bc] {[u8; 8]},
+    BC5UnormBlock => FORMAT_BC5_UNORM_BLOCK [None] [compressed=texture_compression_bc] {[u8; 16]},
+    BC5SnormBlock => FORMAT_BC5_SNORM_BLOCK [None] [compressed=texture_compression_bc] {[u8; 16]},
+    BC6HUfloatBlock => FORMAT_BC6H_UFLOAT_BLOCK [None] [compressed=texture_compression_bc] {[u8; 16]},
+    BC6HSfloatBlock => FORMAT_BC6H_SFLOAT_BLOCK [None] [compressed=texture_compression_bc] {[u8; 16]},
+    BC7UnormBlock => FORMAT_BC7_UNORM_BLOCK [None] [compressed=texture_compression_bc] {[u8; 16]},
+    BC7SrgbBlock => FORMAT_BC7_SRGB_BLOCK [None] [compressed=texture_compression_bc] {[u8; 16]},
+    ETC2_R8G8B8UnormBlock => FORMAT_ETC2_R8G8B8_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {[u8; 8]},
+    ETC2_R8G8B8SrgbBlock => FORMAT_ETC2_R8G8B8_SRGB_BLOCK [None] [compressed=texture_compression_etc2] {[u8; 8]},
+    ETC2_R8G8B8A1UnormBlock => FORMAT_ETC2_R8G8B8A1_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {[u8; 8]},
+    ETC2_R8G8B8A1SrgbBlock => FORMAT_ETC2_R8G8B8A1_SRGB_BLOCK [None] [compressed=texture_compression_etc2] {[u8; 8]},
+    ETC2_R8G8B8A8UnormBlock => FORMAT_ETC2_R8G8B8A8_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {[u8; 16]},
+    ETC2_R8G8B8A8SrgbBlock => FORMAT_ETC2_R8G8B8A8_SRGB_BLOCK [None] [compressed=texture_compression_etc2] {[u8; 16]},
+    EAC_R11UnormBlock => FORMAT_EAC_R11_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {[u8; 8]},
+    EAC_R11SnormBlock => FORMAT_EAC_R11_SNORM_BLOCK [None] [compressed=texture_compression_etc2] {[u8; 8]},
+    EAC_R11G11UnormBlock => FORMAT_EAC_R11G11_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {[u8; 16]},
+    EAC_R11G11SnormBlock => FORMAT_EAC_R11G11_SNORM_BLOCK [None] [compressed=texture_compression_etc2] {[u8; 16]},
+    ASTC_4x4UnormBlock => FORMAT_ASTC_4x4_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_4x4SrgbBlock => FORMAT_ASTC_4x4_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_5x4UnormBlock => FORMAT_ASTC_5x4_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_5x4SrgbBlock => FORMAT_ASTC_5x4_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_5x5UnormBlock => FORMAT_ASTC_5x5_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_5x5SrgbBlock => FORMAT_ASTC_5x5_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_6x5UnormBlock => FORMAT_ASTC_6x5_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_6x5SrgbBlock => FORMAT_ASTC_6x5_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_6x6UnormBlock => FORMAT_ASTC_6x6_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_6x6SrgbBlock => FORMAT_ASTC_6x6_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_8x5UnormBlock => FORMAT_ASTC_8x5_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_8x5SrgbBlock => FORMAT_ASTC_8x5_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_8x6UnormBlock => FORMAT_ASTC_8x6_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_8x6SrgbBlock => FORMAT_ASTC_8x6_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_8x8UnormBlock => FORMAT_ASTC_8x8_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_8x8SrgbBlock => FORMAT_ASTC_8x8_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_10x5UnormBlock => FORMAT_ASTC_10x5_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_10x5SrgbBlock => FORMAT_ASTC_10x5_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_10x6UnormBlock => FORMAT_ASTC_10x6_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_10x6SrgbBlock => FORMAT_ASTC_10x6_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_10x8UnormBlock => FORMAT_ASTC_10x8_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_10x8SrgbBlock => FORMAT_ASTC_10x8_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_10x10UnormBlock => FORMAT_ASTC_10x10_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_10x10SrgbBlock => FORMAT_ASTC_10x10_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_12x10UnormBlock => FORMAT_ASTC_12x10_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_12x10SrgbBlock => FORMAT_ASTC_12x10_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_12x12UnormBlock => FORMAT_ASTC_12x12_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+    ASTC_12x12SrgbBlock => FORMAT_ASTC_12x12_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {[u8; 16]},
+}
+
+// Static metadata of the formats. The tables below follow the "Formats" chapter of the
+// specifications.
+impl Format {
+    /// Returns the width and height in texels of a block of this format.
+    ///
+    /// Compressed formats are made of blocks of several texels that are encoded together, and
+    /// copies must be aligned to these blocks. All the other formats have blocks of one texel.
+    pub fn block_dimensions(&self) -> [u32; 2] {
+        match *self {
+            Format::BC1_RGBUnormBlock | Format::BC1_RGBSrgbBlock | Format::BC1_RGBAUnormBlock |
+            Format::BC1_RGBASrgbBlock | Format::BC2UnormBlock | Format::BC2SrgbBlock |
+            Format::BC3UnormBlock | Format::BC3SrgbBlock | Format::BC4UnormBlock |
+            Format::BC4SnormBlock | Format::BC5UnormBlock | Format::BC5SnormBlock |
+            Format::BC6HUfloatBlock | Format::BC6HSfloatBlock | Format::BC7UnormBlock |
+            Format::BC7SrgbBlock | Format::ETC2_R8G8B8UnormBlock | Format::ETC2_R8G8B8SrgbBlock |
+            Format::ETC2_R8G8B8A1UnormBlock | Format::ETC2_R8G8B8A1SrgbBlock |
+            Format::ETC2_R8G8B8A8UnormBlock | Format::ETC2_R8G8B8A8SrgbBlock |
+            Format::EAC_R11UnormBlock | Format::EAC_R11SnormBlock | Format::EAC_R11G11UnormBlock |
+            Format::EAC_R11G11SnormBlock | Format::ASTC_4x4UnormBlock |
+            Format::ASTC_4x4SrgbBlock => [4, 4],
+            Format::ASTC_5x4UnormBlock | Format::ASTC_5x4SrgbBlock => [5, 4],
+            Format::ASTC_5x5UnormBlock | Format::ASTC_5x5SrgbBlock => [5, 5],
+            Format::ASTC_6x5UnormBlock | Format::ASTC_6x5SrgbBlock => [6, 5],
+            Format::ASTC_6x6UnormBlock | Format::ASTC_6x6SrgbBlock => [6, 6],
+            Format::ASTC_8x5UnormBlock | Format::ASTC_8x5SrgbBlock => [8, 5],
+            Format::ASTC_8x6UnormBlock | Format::ASTC_8x6SrgbBlock => [8, 6],
+            Format::ASTC_8x8UnormBlock | Format::ASTC_8x8SrgbBlock => [8, 8],
+            Format::ASTC_10x5UnormBlock | Format::ASTC_10x5SrgbBlock => [10, 5],
+            Format::ASTC_10x6UnormBlock | Format::ASTC_10x6SrgbBlock => [10, 6],
+            Format::ASTC_10x8UnormBlock | Format::ASTC_10x8SrgbBlock => [10, 8],
+            Format::ASTC_10x10UnormBlock | Format::ASTC_10x10SrgbBlock => [10, 10],
+            Format::ASTC_12x10UnormBlock | Format::ASTC_12x10SrgbBlock => [12, 10],
+            Format::ASTC_12x12UnormBlock | Format::ASTC_12x12SrgbBlock => [12, 12],
+            _ => [1, 1],
+        }
+    }
+
+    /// Returns the size in bytes of a block of this format.
+    ///
+    /// This is the same as `size()`, except that compressed formats return the size of one of
+    /// their blocks. Returns `None` for formats that have both a depth and a stencil component,
+    /// as these components are always copied separately and have no defined size together.
+    pub fn block_size(&self) -> Option<usize> {
+        match *self {
+            Format::BC1_RGBUnormBlock | Format::BC1_RGBSrgbBlock | Format::BC1_RGBAUnormBlock |
+            Format::BC1_RGBASrgbBlock | Format::BC4UnormBlock | Format::BC4SnormBlock |
+            Format::ETC2_R8G8B8UnormBlock | Format::ETC2_R8G8B8SrgbBlock |
+            Format::ETC2_R8G8B8A1UnormBlock | Format::ETC2_R8G8B8A1SrgbBlock |
+            Format::EAC_R11UnormBlock | Format::EAC_R11SnormBlock => Some(8),
+            Format::BC2UnormBlock | Format::BC2SrgbBlock | Format::BC3UnormBlock |
+            Format::BC3SrgbBlock | Format::BC5UnormBlock | Format::BC5SnormBlock |
+            Format::BC6HUfloatBlock | Format::BC6HSfloatBlock | Format::BC7UnormBlock |
+            Format::BC7SrgbBlock | Format::ETC2_R8G8B8A8UnormBlock |
+            Format::ETC2_R8G8B8A8SrgbBlock | Format::EAC_R11G11UnormBlock |
+            Format::EAC_R11G11SnormBlock | Format::ASTC_4x4UnormBlock | Format::ASTC_4x4SrgbBlock |
+            Format::ASTC_5x4UnormBlock | Format::ASTC_5x4SrgbBlock | Format::ASTC_5x5UnormBlock |
+            Format::ASTC_5x5SrgbBlock | Format::ASTC_6x5UnormBlock | Format::ASTC_6x5SrgbBlock |
+            Format::ASTC_6x6UnormBlock | Format::ASTC_6x6SrgbBlock | Format::ASTC_8x5UnormBlock |
+            Format::ASTC_8x5SrgbBlock | Format::ASTC_8x6UnormBlock | Format::ASTC_8x6SrgbBlock |
+            Format::ASTC_8x8UnormBlock | Format::ASTC_8x8SrgbBlock | Format::ASTC_10x5UnormBlock |
+            Format::ASTC_10x5SrgbBlock | Format::ASTC_10x6UnormBlock | Format::ASTC_10x6SrgbBlock |
+            Format::ASTC_10x8UnormBlock | Format::ASTC_10x8SrgbBlock |
+            Format::ASTC_10x10UnormBlock | Format::ASTC_10x10SrgbBlock |
+            Format::ASTC_12x10UnormBlock | Format::ASTC_12x10SrgbBlock |
+            Format::ASTC_12x12UnormBlock | Format::ASTC_12x12SrgbBlock => Some(16),
+            _ => self.size(),
+        }
+    }
+
+    /// Returns the aspects that images of this format have.
+    #[inline]
+    pub fn aspects(&self) -> FormatAspects {
+        match self.ty() {
+            FormatTy::Float | FormatTy::Uint | FormatTy::Sint | FormatTy::Compressed => {
+                FormatAspects { color: true, depth: false, stencil: false }
+            },
+            FormatTy::Depth => FormatAspects { color: false, depth: true, stencil: false },
+            FormatTy::Stencil => FormatAspects { color: false, depth: false, stencil: true },
+            FormatTy::DepthStencil => FormatAspects { color: false, depth: true, stencil: true },
+        }
+    }
+
+    /// Returns the number of bits of each component of this format.
+    ///
+    /// For color formats, the components are in the order R, G, B, A regardless of the order in
+    /// which they are stored in memory. For depth and stencil formats, the returned array
+    /// contains the bits of the depth component, then of the stencil component, then zeroes.
+    /// Components that don't exist have 0 bits, and unused padding bits are not counted.
+    ///
+    /// For compressed formats, this is the precision of the components once decoded, as defined
+    /// by the compression scheme. For `E5B9G9R9UfloatPack32`, the shared exponent isn't counted.
+    pub fn components(&self) -> [u8; 4] {
+        match *self {
+            Format::R4G4UnormPack8 => [4, 4, 0, 0],
+            Format::R4G4B4A4UnormPack16 | Format::B4G4R4A4UnormPack16 => [4, 4, 4, 4],
+            Format::R5G6B5UnormPack16 | Format::B5G6R5UnormPack16 | Format::BC1_RGBUnormBlock |
+            Format::BC1_RGBSrgbBlock => [5, 6, 5, 0],
+            Format::R5G5B5A1UnormPack16 | Format::B5G5R5A1UnormPack16 |
+            Format::A1R5G5B5UnormPack16 => [5, 5, 5, 1],
+            Format::R8Unorm | Format::R8Snorm | Format::R8Uscaled | Format::R8Sscaled |
+            Format::R8Uint | Format::R8Sint | Format::R8Srgb | Format::BC4UnormBlock |
+            Format::BC4SnormBlock => [8, 0, 0, 0],
+            Format::R8G8Unorm | Format::R8G8Snorm | Format::R8G8Uscaled | Format::R8G8Sscaled |
+            Format::R8G8Uint | Format::R8G8Sint | Format::R8G8Srgb | Format::BC5UnormBlock |
+            Format::BC5SnormBlock => [8, 8, 0, 0],
+            Format::R8G8B8Unorm | Format::R8G8B8Snorm | Format::R8G8B8Uscaled |
+            Format::R8G8B8Sscaled | Format::R8G8B8Uint | Format::R8G8B8Sint | Format::R8G8B8Srgb |
+            Format::B8G8R8Unorm | Format::B8G8R8Snorm | Format::B8G8R8Uscaled |
+            Format::B8G8R8Sscaled | Format::B8G8R8Uint | Format::B8G8R8Sint | Format::B8G8R8Srgb |
+            Format::ETC2_R8G8B8UnormBlock | Format::ETC2_R8G8B8SrgbBlock => [8, 8, 8, 0],
+            Format::R8G8B8A8Unorm | Format::R8G8B8A8Snorm | Format::R8G8B8A8Uscaled |
+            Format::R8G8B8A8Sscaled | Format::R8G8B8A8Uint | Format::R8G8B8A8Sint |
+            Format::R8G8B8A8Srgb | Format::B8G8R8A8Unorm | Format::B8G8R8A8Snorm |
+            Format::B8G8R8A8Uscaled | Format::B8G8R8A8Sscaled | Format::B8G8R8A8Uint |
+            Format::B8G8R8A8Sint | Format::B8G8R8A8Srgb | Format::A8B8G8R8UnormPack32 |
+            Format::A8B8G8R8SnormPack32 | Format::A8B8G8R8UscaledPack32 |
+            Format::A8B8G8R8SscaledPack32 | Format::A8B8G8R8UintPack32 |
+            Format::A8B8G8R8SintPack32 | Format::A8B8G8R8SrgbPack32 | Format::BC7UnormBlock |
+            Format::BC7SrgbBlock | Format::ETC2_R8G8B8A8UnormBlock |
+            Format::ETC2_R8G8B8A8SrgbBlock | Format::ASTC_4x4UnormBlock |
+            Format::ASTC_4x4SrgbBlock | Format::ASTC_5x4UnormBlock | Format::ASTC_5x4SrgbBlock |
+            Format::ASTC_5x5UnormBlock | Format::ASTC_5x5SrgbBlock | Format::ASTC_6x5UnormBlock |
+            Format::ASTC_6x5SrgbBlock | Format::ASTC_6x6UnormBlock | Format::ASTC_6x6SrgbBlock |
+            Format::ASTC_8x5UnormBlock | Format::ASTC_8x5SrgbBlock | Format::ASTC_8x6UnormBlock |
+            Format::ASTC_8x6SrgbBlock | Format::ASTC_8x8UnormBlock | Format::ASTC_8x8SrgbBlock |
+            Format::ASTC_10x5UnormBlock | Format::ASTC_10x5SrgbBlock |
+            Format::ASTC_10x6UnormBlock | Format::ASTC_10x6SrgbBlock |
+            Format::ASTC_10x8UnormBlock | Format::ASTC_10x8SrgbBlock |
+            Format::ASTC_10x10UnormBlock | Format::ASTC_10x10SrgbBlock |
+            Format::ASTC_12x10UnormBlock | Format::ASTC_12x10SrgbBlock |
+            Format::ASTC_12x12UnormBlock | Format::ASTC_12x12SrgbBlock => [8, 8, 8, 8],
+            Format::A2R10G10B10UnormPack32 | Format::A2R10G10B10SnormPack32 |
+            Format::A2R10G10B10UscaledPack32 | Format::A2R10G10B10SscaledPack32 |
+            Format::A2R10G10B10UintPack32 | Format::A2R10G10B10SintPack32 |
+            Format::A2B10G10R10UnormPack32 | Format::A2B10G10R10SnormPack32 |
+            Format::A2B10G10R10UscaledPack32 | Format::A2B10G10R10SscaledPack32 |
+            Format::A2B10G10R10UintPack32 | Format::A2B10G10R10SintPack32 => [10, 10, 10, 2],
+            Format::R16Unorm | Format::R16Snorm | Format::R16Uscaled | Format::R16Sscaled |
+            Format::R16Uint | Format::R16Sint | Format::R16Sfloat | Format::D16Unorm => {
+                [16, 0, 0, 0]
+            },
+            Format::R16G16Unorm | Format::R16G16Snorm | Format::R16G16Uscaled |
+            Format::R16G16Sscaled | Format::R16G16Uint | Format::R16G16Sint |
+            Format::R16G16Sfloat => [16, 16, 0, 0],
+            Format::R16G16B16Unorm | Format::R16G16B16Snorm | Format::R16G16B16Uscaled |
+            Format::R16G16B16Sscaled | Format::R16G16B16Uint | Format::R16G16B16Sint |
+            Format::R16G16B16Sfloat | Format::BC6HUfloatBlock | Format::BC6HSfloatBlock => {
+                [16, 16, 16, 0]
+            },
+            Format::R16G16B16A16Unorm | Format::R16G16B16A16Snorm | Format::R16G16B16A16Uscaled |
+            Format::R16G16B16A16Sscaled | Format::R16G16B16A16Uint | Format::R16G16B16A16Sint |
+            Format::R16G16B16A16Sfloat => [16, 16, 16, 16],
+            Format::R32Uint | Format::R32Sint | Format::R32Sfloat | Format::D32Sfloat => {
+                [32, 0, 0, 0]
+            },
+            Format::R32G32Uint | Format::R32G32Sint | Format::R32G32Sfloat => [32, 32, 0, 0],
+            Format::R32G32B32Uint | Format::R32G32B32Sint | Format::R32G32B32Sfloat => {
+                [32, 32, 32, 0]
+            },
+            Format::R32G32B32A32Uint | Format::R32G32B32A32Sint | Format::R32G32B32A32Sfloat => {
+                [32, 32, 32, 32]
+            },
+            Format::R64Uint | Format::R64Sint | Format::R64Sfloat => [64, 0, 0, 0],
+            Format::R64G64Uint | Format::R64G64Sint | Format::R64G64Sfloat => [64, 64, 0, 0],
+            Format::R64G64B64Uint | Format::R64G64B64Sint | Format::R64G64B64Sfloat => {
+                [64, 64, 64, 0]
+            },
+            Format::R64G64B64A64Uint | Format::R64G64B64A64Sint | Format::R64G64B64A64Sfloat => {
+                [64, 64, 64, 64]
+            },
+            Format::B10G11R11UfloatPack32 => [11, 11, 10, 0],
+            Format::E5B9G9R9UfloatPack32 => [9, 9, 9, 0],
+            Format::X8_D24UnormPack32 => [24, 0, 0, 0],
+            Format::S8Uint => [0, 8, 0, 0],
+            Format::D16Unorm_S8Uint => [16, 8, 0, 0],
+            Format::D24Unorm_S8Uint => [24, 8, 0, 0],
+            Format::D32Sfloat_S8Uint => [32, 8, 0, 0],
+            Format::BC1_RGBAUnormBlock | Format::BC1_RGBASrgbBlock => [5, 6, 5, 1],
+            Format::BC2UnormBlock | Format::BC2SrgbBlock => [5, 6, 5, 4],
+            Format::BC3UnormBlock | Format::BC3SrgbBlock => [5, 6, 5, 8],
+            Format::ETC2_R8G8B8A1UnormBlock | Format::ETC2_R8G8B8A1SrgbBlock => [8, 8, 8, 1],
+            Format::EAC_R11UnormBlock | Format::EAC_R11SnormBlock => [11, 0, 0, 0],
+            Format::EAC_R11G11UnormBlock | Format::EAC_R11G11SnormBlock => [11, 11, 0, 0],
+        }
+    }
+
+    /// Returns how the components of this format are interpreted.
+    ///
+    /// For formats that have both a depth and a stencil component, this is the type of the depth
+    /// component. The stencil component is always `Uint`.
+    pub fn numeric_type(&self) -> NumericType {
+        match *self {
+            Format::R4G4UnormPack8 | Format::R4G4B4A4UnormPack16 | Format::B4G4R4A4UnormPack16 |
+            Format::R5G6B5UnormPack16 | Format::B5G6R5UnormPack16 | Format::R5G5B5A1UnormPack16 |
+            Format::B5G5R5A1UnormPack16 | Format::A1R5G5B5UnormPack16 | Format::R8Unorm |
+            Format::R8G8Unorm | Format::R8G8B8Unorm | Format::B8G8R8Unorm | Format::R8G8B8A8Unorm |
+            Format::B8G8R8A8Unorm | Format::A8B8G8R8UnormPack32 | Format::A2R10G10B10UnormPack32 |
+            Format::A2B10G10R10UnormPack32 | Format::R16Unorm | Format::R16G16Unorm |
+            Format::R16G16B16Unorm | Format::R16G16B16A16Unorm | Format::D16Unorm |
+            Format::X8_D24UnormPack32 | Format::D16Unorm_S8Uint | Format::D24Unorm_S8Uint |
+            Format::BC1_RGBUnormBlock | Format::BC1_RGBAUnormBlock | Format::BC2UnormBlock |
+            Format::BC3UnormBlock | Format::BC4UnormBlock | Format::BC5UnormBlock |
+            Format::BC7UnormBlock | Format::ETC2_R8G8B8UnormBlock |
+            Format::ETC2_R8G8B8A1UnormBlock | Format::ETC2_R8G8B8A8UnormBlock |
+            Format::EAC_R11UnormBlock | Format::EAC_R11G11UnormBlock | Format::ASTC_4x4UnormBlock |
+            Format::ASTC_5x4UnormBlock | Format::ASTC_5x5UnormBlock | Format::ASTC_6x5UnormBlock |
+            Format::ASTC_6x6UnormBlock | Format::ASTC_8x5UnormBlock | Format::ASTC_8x6UnormBlock |
+            Format::ASTC_8x8UnormBlock | Format::ASTC_10x5UnormBlock |
+            Format::ASTC_10x6UnormBlock | Format::ASTC_10x8UnormBlock |
+            Format::ASTC_10x10UnormBlock | Format::ASTC_12x10UnormBlock |
+            Format::ASTC_12x12UnormBlock => NumericType::Unorm,
+            Format::R8Snorm | Format::R8G8Snorm | Format::R8G8B8Snorm | Format::B8G8R8Snorm |
+            Format::R8G8B8A8Snorm | Format::B8G8R8A8Snorm | Format::A8B8G8R8SnormPack32 |
+            Format::A2R10G10B10SnormPack32 | Format::A2B10G10R10SnormPack32 | Format::R16Snorm |
+            Format::R16G16Snorm | Format::R16G16B16Snorm | Format::R16G16B16A16Snorm |
+            Format::BC4SnormBlock | Format::BC5SnormBlock | Format::EAC_R11SnormBlock |
+            Format::EAC_R11G11SnormBlock => NumericType::Snorm,
+            Format::R8Uscaled | Format::R8G8Uscaled | Format::R8G8B8Uscaled |
+            Format::B8G8R8Uscaled | Format::R8G8B8A8Uscaled | Format::B8G8R8A8Uscaled |
+            Format::A8B8G8R8UscaledPack32 | Format::A2R10G10B10UscaledPack32 |
+            Format::A2B10G10R10UscaledPack32 | Format::R16Uscaled | Format::R16G16Uscaled |
+            Format::R16G16B16Uscaled | Format::R16G16B16A16Uscaled => NumericType::Uscaled,
+            Format::R8Sscaled | Format::R8G8Sscaled | Format::R8G8B8Sscaled |
+            Format::B8G8R8Sscaled | Format::R8G8B8A8Sscaled | Format::B8G8R8A8Sscaled |
+            Format::A8B8G8R8SscaledPack32 | Format::A2R10G10B10SscaledPack32 |
+            Format::A2B10G10R10SscaledPack32 | Format::R16Sscaled | Format::R16G16Sscaled |
+            Format::R16G16B16Sscaled | Format::R16G16B16A16Sscaled => NumericType::Sscaled,
+            Format::R8Uint | Format::R8G8Uint | Format::R8G8B8Uint | Format::B8G8R8Uint |
+            Format::R8G8B8A8Uint | Format::B8G8R8A8Uint | Format::A8B8G8R8UintPack32 |
+            Format::A2R10G10B10UintPack32 | Format::A2B10G10R10UintPack32 | Format::R16Uint |
+            Format::R16G16Uint | Format::R16G16B16Uint | Format::R16G16B16A16Uint |
+            Format::R32Uint | Format::R32G32Uint | Format::R32G32B32Uint |
+            Format::R32G32B32A32Uint | Format::R64Uint | Format::R64G64Uint |
+            Format::R64G64B64Uint | Format::R64G64B64A64Uint | Format::S8Uint => NumericType::Uint,
+            Format::R8Sint | Format::R8G8Sint | Format::R8G8B8Sint | Format::B8G8R8Sint |
+            Format::R8G8B8A8Sint | Format::B8G8R8A8Sint | Format::A8B8G8R8SintPack32 |
+            Format::A2R10G10B10SintPack32 | Format::A2B10G10R10SintPack32 | Format::R16Sint |
+            Format::R16G16Sint | Format::R16G16B16Sint | Format::R16G16B16A16Sint |
+            Format::R32Sint | Format::R32G32Sint | Format::R32G32B32Sint |
+            Format::R32G32B32A32Sint | Format::R64Sint | Format::R64G64Sint |
+            Format::R64G64B64Sint | Format::R64G64B64A64Sint => NumericType::Sint,
+            Format::R8Srgb | Format::R8G8Srgb | Format::R8G8B8Srgb | Format::B8G8R8Srgb |
+            Format::R8G8B8A8Srgb | Format::B8G8R8A8Srgb | Format::A8B8G8R8SrgbPack32 |
+            Format::BC1_RGBSrgbBlock | Format::BC1_RGBASrgbBlock | Format::BC2SrgbBlock |
+            Format::BC3SrgbBlock | Format::BC7SrgbBlock | Format::ETC2_R8G8B8SrgbBlock |
+            Format::ETC2_R8G8B8A1SrgbBlock | Format::ETC2_R8G8B8A8SrgbBlock |
+            Format::ASTC_4x4SrgbBlock | Format::ASTC_5x4SrgbBlock | Format::ASTC_5x5SrgbBlock |
+            Format::ASTC_6x5SrgbBlock | Format::ASTC_6x6SrgbBlock | Format::ASTC_8x5SrgbBlock |
+            Format::ASTC_8x6SrgbBlock | Format::ASTC_8x8SrgbBlock | Format::ASTC_10x5SrgbBlock |
+            Format::ASTC_10x6SrgbBlock | Format::ASTC_10x8SrgbBlock | Format::ASTC_10x10SrgbBlock |
+            Format::ASTC_12x10SrgbBlock | Format::ASTC_12x12SrgbBlock => NumericType::Srgb,
+            Format::R16Sfloat | Format::R16G16Sfloat | Format::R16G16B16Sfloat |
+            Format::R16G16B16A16Sfloat | Format::R32Sfloat | Format::R32G32Sfloat |
+            Format::R32G32B32Sfloat | Format::R32G32B32A32Sfloat | Format::R64Sfloat |
+            Format::R64G64Sfloat | Format::R64G64B64Sfloat | Format::R64G64B64A64Sfloat |
+            Format::D32Sfloat | Format::D32Sfloat_S8Uint | Format::BC6HSfloatBlock => {
+                NumericType::Sfloat
+            },
+            Format::B10G11R11UfloatPack32 | Format::E5B9G9R9UfloatPack32 |
+            Format::BC6HUfloatBlock => NumericType::Ufloat,
+        }
+    }
+
+    /// Returns the compatibility class of this format.
+    ///
+    /// Images of formats of the same class can be copied to each other, and views of an image can
+    /// use any format of the same class as the image.
+    pub fn compatibility(&self) -> FormatCompatibility {
+        match *self {
+            Format::BC1_RGBUnormBlock | Format::BC1_RGBSrgbBlock => FormatCompatibility::Bc1Rgb,
+            Format::BC1_RGBAUnormBlock | Format::BC1_RGBASrgbBlock => FormatCompatibility::Bc1Rgba,
+            Format::BC2UnormBlock | Format::BC2SrgbBlock => FormatCompatibility::Bc2,
+            Format::BC3UnormBlock | Format::BC3SrgbBlock => FormatCompatibility::Bc3,
+            Format::BC4UnormBlock | Format::BC4SnormBlock => FormatCompatibility::Bc4,
+            Format::BC5UnormBlock | Format::BC5SnormBlock => FormatCompatibility::Bc5,
+            Format::BC6HUfloatBlock | Format::BC6HSfloatBlock => FormatCompatibility::Bc6h,
+            Format::BC7UnormBlock | Format::BC7SrgbBlock => FormatCompatibility::Bc7,
+            Format::ETC2_R8G8B8UnormBlock | Format::ETC2_R8G8B8SrgbBlock => {
+                FormatCompatibility::Etc2Rgb
+            },
+            Format::ETC2_R8G8B8A1UnormBlock | Format::ETC2_R8G8B8A1SrgbBlock => {
+                FormatCompatibility::Etc2Rgba
+            },
+            Format::ETC2_R8G8B8A8UnormBlock | Format::ETC2_R8G8B8A8SrgbBlock => {
+                FormatCompatibility::Etc2EacRgba
+            },
+            Format::EAC_R11UnormBlock | Format::EAC_R11SnormBlock => FormatCompatibility::EacR,
+            Format::EAC_R11G11UnormBlock | Format::EAC_R11G11SnormBlock => {
+                FormatCompatibility::EacRg
+            },
+            Format::ASTC_4x4UnormBlock | Format::ASTC_4x4SrgbBlock => {
+                FormatCompatibility::Astc { width: 4, height: 4 }
+            },
+            Format::ASTC_5x4UnormBlock | Format::ASTC_5x4SrgbBlock => {
+                FormatCompatibility::Astc { width: 5, height: 4 }
+            },
+            Format::ASTC_5x5UnormBlock | Format::ASTC_5x5SrgbBlock => {
+                FormatCompatibility::Astc { width: 5, height: 5 }
+            },
+            Format::ASTC_6x5UnormBlock | Format::ASTC_6x5SrgbBlock => {
+                FormatCompatibility::Astc { width: 6, height: 5 }
+            },
+            Format::ASTC_6x6UnormBlock | Format::ASTC_6x6SrgbBlock => {
+                FormatCompatibility::Astc { width: 6, height: 6 }
+            },
+            Format::ASTC_8x5UnormBlock | Format::ASTC_8x5SrgbBlock => {
+                FormatCompatibility::Astc { width: 8, height: 5 }
+            },
+            Format::ASTC_8x6UnormBlock | Format::ASTC_8x6SrgbBlock => {
+                FormatCompatibility::Astc { width: 8, height: 6 }
+            },
+            Format::ASTC_8x8UnormBlock | Format::ASTC_8x8SrgbBlock => {
+                FormatCompatibility::Astc { width: 8, height: 8 }
+            },
+            Format::ASTC_10x5UnormBlock | Format::ASTC_10x5SrgbBlock => {
+                FormatCompatibility::Astc { width: 10, height: 5 }
+            },
+            Format::ASTC_10x6UnormBlock | Format::ASTC_10x6SrgbBlock => {
+                FormatCompatibility::Astc { width: 10, height: 6 }
+            },
+            Format::ASTC_10x8UnormBlock | Format::ASTC_10x8SrgbBlock => {
+                FormatCompatibility::Astc { width: 10, height: 8 }
+            },
+            Format::ASTC_10x10UnormBlock | Format::ASTC_10x10SrgbBlock => {
+                FormatCompatibility::Astc { width: 10, height: 10 }
+            },
+            Format::ASTC_12x10UnormBlock | Format::ASTC_12x10SrgbBlock => {
+                FormatCompatibility::Astc { width: 12, height: 10 }
+            },
+            Format::ASTC_12x12UnormBlock | Format::ASTC_12x12SrgbBlock => {
+                FormatCompatibility::Astc { width: 12, height: 12 }
+            },
+            _ => match self.ty() {
+                FormatTy::Float | FormatTy::Uint | FormatTy::Sint => {
+                    FormatCompatibility::Texel(self.size().unwrap())
+                },
+                _ => FormatCompatibility::DepthStencil(*self),
+            },
+        }
+    }
+
+    /// Returns true if this format is in the same compatibility class as `other`.
+    #[inline]
+    pub fn is_compatible_with(&self, other: Format) -> bool {
+        self.compatibility() == other.compatibility()
+    }
+}
+
+// Conversions between colors and the memory representation of texels.
+impl Format {
+    /// Encodes `value` into the memory representation of a texel of this format, and writes it
+    /// to `output`, whose length must be the size of a texel.
+    ///
+    /// The components of `value` are in the same order as the ones returned by `components()`.
+    /// In other words, R, G, B and A for color formats, and depth then stencil for depth and
+    /// stencil formats. The components that the format doesn't have are ignored.
+    ///
+    /// Values are converted the way Vulkan would convert them. For example values are clamped
+    /// to `[0, 1]` and rounded for `Unorm` formats, the R, G and B components are converted to
+    /// the sRGB color space for `Srgb` formats, and values of integer formats are rounded and
+    /// clamped to the representable range. Multi-byte components and packed formats use the
+    /// endianness of the host, like Vulkan does.
+    ///
+    /// Returns an error if the format is compressed or is a combined depth-stencil format, as
+    /// the memory representation of their texels is either not made of independent texels or
+    /// implementation-defined.
+    pub fn encode_texel(&self, value: [f32; 4], output: &mut [u8]) -> Result<(), TexelError> {
+        let size = try!(self.size().ok_or(TexelError::UnsupportedFormat));
+        if output.len() != size {
+            return Err(TexelError::WrongDataSize { expected: size, obtained: output.len() });
+        }
+
+        if *self == Format::E5B9G9R9UfloatPack32 {
+            write_bits(output, encode_shared_exponent(value));
+            return Ok(());
+        }
+
+        let (packed, components) = try!(self.texel_layout().ok_or(TexelError::UnsupportedFormat));
+        let numeric_type = self.numeric_type();
+
+        let mut word = 0;
+        let mut offset = 0;
+        for &(index, bits) in components {
+            let raw = match index {
+                Some(index) => encode_component(numeric_type, index, bits, value[index]),
+                None => 0,
+            };
+
+            if packed {
+                word = (word << bits) | raw;
+            } else {
+                let bytes = bits as usize / 8;
+                write_bits(&mut output[offset .. offset + bytes], raw);
+                offset += bytes;
+            }
+        }
+
+        if packed {
+            write_bits(output, word);
+        }
+
+        Ok(())
+    }
+
+    /// Decodes the texel of this format whose memory representation is `data`. This is the
+    /// opposite of `encode_texel`.
+    ///
+    /// The components that the format doesn't have are set to 0, except for the alpha component
+    /// of color formats which is set to 1.
+    pub fn decode_texel(&self, data: &[u8]) -> Result<[f32; 4], TexelError> {
+        let size = try!(self.size().ok_or(TexelError::UnsupportedFormat));
+        if data.len() != size {
+            return Err(TexelError::WrongDataSize { expected: size, obtained: data.len() });
+        }
+
+        if *self == Format::E5B9G9R9UfloatPack32 {
+            return Ok(decode_shared_exponent(read_bits(data)));
+        }
+
+        let (packed, components) = try!(self.texel_layout().ok_or(TexelError::UnsupportedFormat));
+        let numeric_type = self.numeric_type();
+
+        let mut value = if self.aspects().color { [0.0, 0.0, 0.0, 1.0] } else { [0.0; 4] };
+
+        // For packed formats, `shift` is the position of the end of the next component.
+        let word = if packed { read_bits(data) } else { 0 };
+        let mut shift = data.len() as u32 * 8;
+        let mut offset = 0;
+        for &(index, bits) in components {
+            let raw = if packed {
+                shift -= bits;
+                (word >> shift) & mask(bits)
+            } else {
+                let bytes = bits as usize / 8;
+                offset += bytes;
+                read_bits(&data[offset - bytes .. offset])
+            };
+
+            if let Some(index) = index {
+                value[index] = decode_component(numeric_type, index, bits, raw);
+            }
+        }
+
+        Ok(value)
+    }
+
+    // Returns whether the format is packed, and the index in the value and the number of bits of
+    // each component in memory order. For packed formats, the first component is stored in the
+    // most significant bits. An index of `None` corresponds to unused bits.
+    //
+    // Returns `None` for compressed, combined depth-stencil and shared exponent formats.
+    fn texel_layout(&self) -> Option<(bool, &'static [(Option<usize>, u32)])> {
+        let layout: (bool, &'static [(Option<usize>, u32)]) = match *self {
+            Format::R4G4UnormPack8 => (true, &[(Some(0), 4), (Some(1), 4)]),
+            Format::R4G4B4A4UnormPack16 =>
+                (true, &[(Some(0), 4), (Some(1), 4), (Some(2), 4), (Some(3), 4)]),
+            Format::B4G4R4A4UnormPack16 =>
+                (true, &[(Some(2), 4), (Some(1), 4), (Some(0), 4), (Some(3), 4)]),
+            Format::R5G6B5UnormPack16 => (true, &[(Some(0), 5), (Some(1), 6), (Some(2), 5)]),
+            Format::B5G6R5UnormPack16 => (true, &[(Some(2), 5), (Some(1), 6), (Some(0), 5)]),
+            Format::R5G5B5A1UnormPack16 =>
+                (true, &[(Some(0), 5), (Some(1), 5), (Some(2), 5), (Some(3), 1)]),
+            Format::B5G5R5A1UnormPack16 =>
+                (true, &[(Some(2), 5), (Some(1), 5), (Some(0), 5), (Some(3), 1)]),
+            Format::A1R5G5B5UnormPack16 =>
+                (true, &[(Some(3), 1), (Some(0), 5), (Some(1), 5), (Some(2), 5)]),
+            Format::R8Unorm | Format::R8Snorm | Format::R8Uscaled | Format::R8Sscaled |
+            Format::R8Uint | Format::R8Sint | Format::R8Srgb => (false, &[(Some(0), 8)]),
+            Format::R8G8Unorm | Format::R8G8Snorm | Format::R8G8Uscaled | Format::R8G8Sscaled |
+            Format::R8G8Uint | Format::R8G8Sint | Format::R8G8Srgb =>
+                (false, &[(Some(0), 8), (Some(1), 8)]),
+            Format::R8G8B8Unorm | Format::R8G8B8Snorm | Format::R8G8B8Uscaled |
+            Format::R8G8B8Sscaled | Format::R8G8B8Uint | Format::R8G8B8Sint | Format::R8G8B8Srgb =>
+                (false, &[(Some(0), 8), (Some(1), 8), (Some(2), 8)]),
+            Format::B8G8R8Unorm | Format::B8G8R8Snorm | Format::B8G8R8Uscaled |
+            Format::B8G8R8Sscaled | Format::B8G8R8Uint | Format::B8G8R8Sint | Format::B8G8R8Srgb =>
+                (false, &[(Some(2), 8), (Some(1), 8), (Some(0), 8)]),
+            Format::R8G8B8A8Unorm | Format::R8G8B8A8Snorm | Format::R8G8B8A8Uscaled |
+            Format::R8G8B8A8Sscaled | Format::R8G8B8A8Uint | Format::R8G8B8A8Sint |
+            Format::R8G8B8A8Srgb =>
+                (false, &[(Some(0), 8), (Some(1), 8), (Some(2), 8), (Some(3), 8)]),
+            Format::B8G8R8A8Unorm | Format::B8G8R8A8Snorm | Format::B8G8R8A8Uscaled |
+            Format::B8G8R8A8Sscaled | Format::B8G8R8A8Uint | Format::B8G8R8A8Sint |
+            Format::B8G8R8A8Srgb =>
+                (false, &[(Some(2), 8), (Some(1), 8), (Some(0), 8), (Some(3), 8)]),
+            Format::A8B8G8R8UnormPack32 | Format::A8B8G8R8SnormPack32 |
+            Format::A8B8G8R8UscaledPack32 | Format::A8B8G8R8SscaledPack32 |
+            Format::A8B8G8R8UintPack32 | Format::A8B8G8R8SintPack32 | Format::A8B8G8R8SrgbPack32 =>
+                (true, &[(Some(3), 8), (Some(2), 8), (Some(1), 8), (Some(0), 8)]),
+            Format::A2R10G10B10UnormPack32 | Format::A2R10G10B10SnormPack32 |
+            Format::A2R10G10B10UscaledPack32 | Format::A2R10G10B10SscaledPack32 |
+            Format::A2R10G10B10UintPack32 | Format::A2R10G10B10SintPack32 =>
+                (true, &[(Some(3), 2), (Some(0), 10), (Some(1), 10), (Some(2), 10)]),
+            Format::A2B10G10R10UnormPack32 | Format::A2B10G10R10SnormPack32 |
+            Format::A2B10G10R10UscaledPack32 | Format::A2B10G10R10SscaledPack32 |
+            Format::A2B10G10R10UintPack32 | Format::A2B10G10R10SintPack32 =>
+                (true, &[(Some(3), 2), (Some(2), 10), (Some(1), 10), (Some(0), 10)]),
+            Format::R16Unorm | Format::R16Snorm | Format::R16Uscaled | Format::R16Sscaled |
+            Format::R16Uint | Format::R16Sint | Format::R16Sfloat | Format::D16Unorm =>
+                (false, &[(Some(0), 16)]),
+            Format::R16G16Unorm | Format::R16G16Snorm | Format::R16G16Uscaled |
+            Format::R16G16Sscaled | Format::R16G16Uint | Format::R16G16Sint |
+            Format::R16G16Sfloat => (false, &[(Some(0), 16), (Some(1), 16)]),
+            Format::R16G16B16Unorm | Format::R16G16B16Snorm | Format::R16G16B16Uscaled |
+            Format::R16G16B16Sscaled | Format::R16G16B16Uint | Format::R16G16B16Sint |
+            Format::R16G16B16Sfloat => (false, &[(Some(0), 16), (Some(1), 16), (Some(2), 16)]),
+            Format::R16G16B16A16Unorm | Format::R16G16B16A16Snorm | Format::R16G16B16A16Uscaled |
+            Format::R16G16B16A16Sscaled | Format::R16G16B16A16Uint | Format::R16G16B16A16Sint |
+            Format::R16G16B16A16Sfloat =>
+                (false, &[(Some(0), 16), (Some(1), 16), (Some(2), 16), (Some(3), 16)]),
+            Format::R32Uint | Format::R32Sint | Format::R32Sfloat | Format::D32Sfloat =>
+                (false, &[(Some(0), 32)]),
+            Format::R32G32Uint | Format::R32G32Sint | Format::R32G32Sfloat =>
+                (false, &[(Some(0), 32), (Some(1), 32)]),
+            Format::R32G32B32Uint | Format::R32G32B32Sint | Format::R32G32B32Sfloat =>
+                (false, &[(Some(0), 32), (Some(1), 32), (Some(2), 32)]),
+            Format::R32G32B32A32Uint | Format::R32G32B32A32Sint | Format::R32G32B32A32Sfloat =>
+                (false, &[(Some(0), 32), (Some(1), 32), (Some(2), 32), (Some(3), 32)]),
+            Format::R64Uint | Format::R64Sint | Format::R64Sfloat => (false, &[(Some(0), 64)]),
+            Format::R64G64Uint | Format::R64G64Sint | Format::R64G64Sfloat =>
+                (false, &[(Some(0), 64), (Some(1), 64)]),
+            Format::R64G64B64Uint | Format::R64G64B64Sint | Format::R64G64B64Sfloat =>
+                (false, &[(Some(0), 64), (Some(1), 64), (Some(2), 64)]),
+            Format::R64G64B64A64Uint | Format::R64G64B64A64Sint | Format::R64G64B64A64Sfloat =>
+                (false, &[(Some(0), 64), (Some(1), 64), (Some(2), 64), (Some(3), 64)]),
+            Format::B10G11R11UfloatPack32 => (true, &[(Some(2), 10), (Some(1), 11), (Some(0), 11)]),
+            Format::X8_D24UnormPack32 => (true, &[(None, 8), (Some(0), 24)]),
+            Format::S8Uint => (false, &[(Some(1), 8)]),
+            _ => return None,
+        };
+
+        Some(layout)
+    }
+}
+
+/// Error that can happen when encoding or decoding a texel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TexelError {
+    /// The format is compressed or is a combined depth-stencil format.
+    UnsupportedFormat,
+
+    /// The length of the data doesn't match the size of a texel of the format.
+    WrongDataSize {
+        /// Size of a texel of the format.
+        expected: usize,
+        /// Length of the data that was passed.
+        obtained: usize,
+    },
+}
+
+impl error::Error for TexelError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            TexelError::UnsupportedFormat => {
+                "the texels of this format can't be encoded or decoded on the CPU"
+            },
+            TexelError::WrongDataSize { .. } => {
+                "the length of the data doesn't match the size of a texel of the format"
+            },
+        }
+    }
+}
+
+impl fmt::Display for TexelError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[inline]
+fn mask(bits: u32) -> u64 {
+    if bits >= 64 { !0 } else { (1 << bits) - 1 }
+}
+
+// Writes the lowest bits of `value` to `output` with the endianness of the host.
+fn write_bits(output: &mut [u8], value: u64) {
+    let len = output.len();
+    for (i, byte) in output.iter_mut().enumerate() {
+        let shift = if cfg!(target_endian = "little") { i } else { len - 1 - i };
+        *byte = (value >> (shift * 8)) as u8;
+    }
+}
+
+// Reads `data` as an integer with the endianness of the host.
+fn read_bits(data: &[u8]) -> u64 {
+    let len = data.len();
+    data.iter().enumerate().fold(0, |value, (i, &byte)| {
+        let shift = if cfg!(target_endian = "little") { i } else { len - 1 - i };
+        value | ((byte as u64) << (shift * 8))
+    })
+}
+
+// Converts a component of a texel to its representation on `bits` bits.
+fn encode_component(ty: NumericType, index: usize, bits: u32, value: f32) -> u64 {
+    let value = value as f64;
+    let max_unsigned = mask(bits) as f64;
+    let max_signed = mask(bits - 1) as f64;
+
+    // Note that `max` and `min` turn NaNs into the other value.
+    match ty {
+        NumericType::Unorm => (value.max(0.0).min(1.0) * max_unsigned).round() as u64,
+        NumericType::Srgb if index < 3 => {
+            (linear_to_srgb(value.max(0.0).min(1.0)) * max_unsigned).round() as u64
+        },
+        NumericType::Srgb => (value.max(0.0).min(1.0) * max_unsigned).round() as u64,
+        NumericType::Snorm => {
+            ((value.max(-1.0).min(1.0) * max_signed).round() as i64) as u64 & mask(bits)
+        },
+        NumericType::Uscaled | NumericType::Uint => {
+            let value = value.round().max(0.0);
+            if value >= max_unsigned { mask(bits) } else { value as u64 }
+        },
+        NumericType::Sscaled | NumericType::Sint => {
+            let value = value.round().max(-max_signed - 1.0);
+            if value >= max_signed { mask(bits - 1) } else { value as i64 as u64 & mask(bits) }
+        },
+        NumericType::Sfloat => match bits {
+            16 => encode_half(value),
+            32 => (value as f32).to_bits() as u64,
+            64 => value.to_bits(),
+            _ => unreachable!(),
+        },
+        NumericType::Ufloat => encode_ufloat(value, bits - 5),
+    }
+}
+
+// Converts the representation of a component of a texel on `bits` bits to its value.
+fn decode_component(ty: NumericType, index: usize, bits: u32, raw: u64) -> f32 {
+    let max_unsigned = mask(bits) as f64;
+    let max_signed = mask(bits - 1) as f64;
+    // Sign-extends `raw` to 64 bits.
+    let signed = ((raw << (64 - bits)) as i64 >> (64 - bits)) as f64;
+
+    let value = match ty {
+        NumericType::Unorm => raw as f64 / max_unsigned,
+        NumericType::Srgb if index < 3 => srgb_to_linear(raw as f64 / max_unsigned),
+        NumericType::Srgb => raw as f64 / max_unsigned,
+        NumericType::Snorm => (signed / max_signed).max(-1.0),
+        NumericType::Uscaled | NumericType::Uint => raw as f64,
+        NumericType::Sscaled | NumericType::Sint => signed,
+        NumericType::Sfloat => match bits {
+            16 => decode_half(raw),
+            32 => f32::from_bits(raw as u32) as f64,
+            64 => f64::from_bits(raw),
+            _ => unreachable!(),
+        },
+        NumericType::Ufloat => decode_small_float(raw, bits - 5),
+    };
+
+    value as f32
+}
+
+fn linear_to_srgb(value: f64) -> f64 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// Encodes a non-negative finite value into a floating-point value with 5 bits of exponent and
+// `mantissa_bits` bits of mantissa, without sign bit. Rounds to the nearest representable
+// value, which can be the infinity.
+fn encode_small_float(value: f64, mantissa_bits: u32) -> u64 {
+    const MIN_EXPONENT: i32 = -14;
+    const INFINITY: u64 = 0x1f;
+
+    if value >= 65536.0 {
+        return INFINITY << mantissa_bits;
+    }
+
+    let mut exponent = value.log2().floor().max(MIN_EXPONENT as f64) as i32;
+    while exponent > MIN_EXPONENT && value < 2.0f64.powi(exponent) {
+        exponent -= 1;
+    }
+    while value >= 2.0f64.powi(exponent + 1) {
+        exponent += 1;
+    }
+
+    let scaled = value / 2.0f64.powi(exponent) * 2.0f64.powi(mantissa_bits as i32);
+    if value < 2.0f64.powi(MIN_EXPONENT) {
+        // Denormalized value. Rounding up to the smallest normalized value gives the right bits.
+        scaled.round() as u64
+    } else {
+        // The rounding can overflow the mantissa, in which case the exponent is increased.
+        let biased_exponent = (exponent + 15) as u64;
+        (biased_exponent << mantissa_bits) + (scaled.round() as u64 - (1 << mantissa_bits))
+    }
+}
+
+// Decodes a floating-point value with 5 bits of exponent and `mantissa_bits` bits of mantissa,
+// without sign bit.
+fn decode_small_float(raw: u64, mantissa_bits: u32) -> f64 {
+    let exponent = (raw >> mantissa_bits) & 0x1f;
+    let mantissa = (raw & mask(mantissa_bits)) as f64 / 2.0f64.powi(mantissa_bits as i32);
+
+    match exponent {
+        0 => mantissa * 2.0f64.powi(-14),
+        0x1f if mantissa == 0.0 => f64::INFINITY,
+        0x1f => f64::NAN,
+        e => (1.0 + mantissa) * 2.0f64.powi(e as i32 - 15),
+    }
+}
+
+fn encode_half(value: f64) -> u64 {
+    let sign = if value.is_sign_negative() { 0x8000 } else { 0 };
+    let magnitude = if value.is_nan() {
+        0x7e00
+    } else if value.is_infinite() {
+        0x7c00
+    } else {
+        encode_small_float(value.abs(), 10)
+    };
+
+    sign | magnitude
+}
+
+fn decode_half(raw: u64) -> f64 {
+    let magnitude = decode_small_float(raw & 0x7fff, 10);
+    if raw & 0x8000 != 0 { -magnitude } else { magnitude }
+}
+
+// Unsigned floating-point values, as used by `B10G11R11UfloatPack32`. Negative values become 0
+// and values that are too large become the largest finite value.
+fn encode_ufloat(value: f64, mantissa_bits: u32) -> u64 {
+    let max_finite = (0x1e << mantissa_bits) | mask(mantissa_bits);
+
+    if value.is_nan() {
+        (0x1f << mantissa_bits) | 1
+    } else if value <= 0.0 {
+        0
+    } else if value.is_infinite() {
+        0x1f << mantissa_bits
+    } else {
+        cmp::min(encode_small_float(value, mantissa_bits), max_finite)
+    }
+}
+
+// Encodes a color into `E5B9G9R9UfloatPack32`, as described in the Vulkan specs.
+fn encode_shared_exponent(value: [f32; 4]) -> u64 {
+    const MANTISSA_BITS: i32 = 9;
+    const BIAS: i32 = 15;
+    let max = 511.0 / 512.0 * 65536.0;
+
+    let clamp = |c: f32| (c as f64).max(0.0).min(max);
+    let (r, g, b) = (clamp(value[0]), clamp(value[1]), clamp(value[2]));
+    let max_component = r.max(g).max(b);
+
+    let mut exponent = max_component.log2().floor().max((-BIAS - 1) as f64) as i32 + 1 + BIAS;
+    let max_scaled = (max_component / 2.0f64.powi(exponent - BIAS - MANTISSA_BITS)).round();
+    if max_scaled >= 512.0 {
+        exponent += 1;
+    }
+
+    let scale = |c: f64| (c / 2.0f64.powi(exponent - BIAS - MANTISSA_BITS)).round() as u64;
+    ((exponent as u64) << 27) | (scale(b) << 18) | (scale(g) << 9) | scale(r)
+}
+
+fn decode_shared_exponent(raw: u64) -> [f32; 4] {
+    let factor = 2.0f64.powi((raw >> 27) as i32 - 15 - 9);
+    let component = |shift: u64| (((raw >> shift) & 0x1ff) as f64 * factor) as f32;
+    [component(0), component(9), component(18), 1.0]
+}
+
+/// Aspects of the images of a format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FormatAspects {
+    /// The format has color components.
+    pub color: bool,
+    /// The format has a depth component.
+    pub depth: bool,
+    /// The format has a stencil component.
+    pub stencil: bool,
+}
+
+/// How the components of a format are interpreted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum NumericType {
+    /// Unsigned integers converted to floating-point values in the range `[0, 1]`.
+    Unorm,
+    /// Signed integers converted to floating-point values in the range `[-1, 1]`.
+    Snorm,
+    /// Unsigned integers converted to floating-point values of the same magnitude.
+    Uscaled,
+    /// Signed integers converted to floating-point values of the same magnitude.
+    Sscaled,
+    /// Unsigned integers.
+    Uint,
+    /// Signed integers.
+    Sint,
+    /// Unsigned floating-point values.
+    Ufloat,
+    /// Signed floating-point values.
+    Sfloat,
+    /// Like `Unorm`, except that the R, G and B components are in the sRGB color space.
+    Srgb,
+}
+
+/// Compatibility class of a format. Obtained by calling `Format::compatibility`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[allow(missing_docs)]
+pub enum FormatCompatibility {
+    /// Uncompressed color formats whose texels have the given size in bytes.
+    Texel(usize),
+    Bc1Rgb,
+    Bc1Rgba,
+    Bc2,
+    Bc3,
+    Bc4,
+    Bc5,
+    Bc6h,
+    Bc7,
+    Etc2Rgb,
+    Etc2Rgba,
+    Etc2EacRgba,
+    EacR,
+    EacRg,
+    /// ASTC formats with the given block dimensions.
+    Astc { width: u32, height: u32 },
+    /// Depth and stencil formats are only compatible with themselves.
+    DepthStencil(Format),
 }
 
 pub unsafe trait FormatDesc {
@@ -655,8 +1580,33 @@ unsafe impl PossibleFloatOrCompressedFormatDesc for Format {
     }
 }
 
+/// Format descriptions whose elements can be read and written from the CPU as a Rust type.
+///
+/// For compressed formats, a `Pixel` is a whole block of texels.
 pub unsafe trait StrongStorage: FormatDesc {
     type Pixel: Copy;
+
+    /// Encodes a color into a pixel of this format. See `Format::encode_texel`.
+    fn encode_pixel(&self, value: [f32; 4]) -> Result<Self::Pixel, TexelError> {
+        unsafe {
+            let mut pixel: Self::Pixel = mem::zeroed();
+            {
+                let bytes = slice::from_raw_parts_mut(&mut pixel as *mut Self::Pixel as *mut u8,
+                                                      mem::size_of::<Self::Pixel>());
+                try!(self.format().encode_texel(value, bytes));
+            }
+            Ok(pixel)
+        }
+    }
+
+    /// Decodes a pixel of this format into a color. See `Format::decode_texel`.
+    fn decode_pixel(&self, pixel: Self::Pixel) -> Result<[f32; 4], TexelError> {
+        let bytes = unsafe {
+            slice::from_raw_parts(&pixel as *const Self::Pixel as *const u8,
+                                  mem::size_of::<Self::Pixel>())
+        };
+        self.format().decode_texel(bytes)
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -670,6 +1620,275 @@ pub enum FormatTy {
     Compressed,
 }
 
+impl Format {
+    /// Queries the features that the physical device supports for this format.
+    ///
+    /// The features of images depend on whether they use linear or optimal tiling, while buffer
+    /// views and vertex buffers use the `buffer_features`.
+    pub fn properties(&self, physical_device: PhysicalDevice) -> FormatProperties {
+        unsafe {
+            let vk_i = physical_device.instance().pointers();
+            let mut output = mem::uninitialized();
+            vk_i.GetPhysicalDeviceFormatProperties(physical_device.internal_object(),
+                                                   *self as u32, &mut output);
+
+            FormatProperties {
+                linear_tiling_features: FormatFeatures::from_bits(output.linearTilingFeatures),
+                optimal_tiling_features: FormatFeatures::from_bits(output.optimalTilingFeatures),
+                buffer_features: FormatFeatures::from_bits(output.bufferFeatures),
+            }
+        }
+    }
+
+    /// Queries the limits of images of this format with the given type, tiling, usage and
+    /// flags.
+    ///
+    /// Returns `FormatNotSupported` if the physical device doesn't support this combination at
+    /// all.
+    pub fn image_format_properties(&self, physical_device: PhysicalDevice, ty: ImageType,
+                                   tiling: ImageTiling, usage: &ImageUsage,
+                                   flags: &ImageCreateFlags)
+                                   -> Result<ImageFormatProperties, ImageFormatPropertiesError>
+    {
+        unsafe {
+            let vk_i = physical_device.instance().pointers();
+            let mut output = mem::uninitialized();
+            let r = vk_i.GetPhysicalDeviceImageFormatProperties(physical_device.internal_object(),
+                                                                *self as u32, ty as u32,
+                                                                tiling as u32,
+                                                                usage.to_usage_bits(),
+                                                                flags.to_bits(), &mut output);
+
+            match check_errors(r) {
+                Ok(_) => (),
+                Err(Error::FormatNotSupported) => {
+                    return Err(ImageFormatPropertiesError::FormatNotSupported);
+                },
+                Err(err) => return Err(err.into()),
+            }
+
+            Ok(ImageFormatProperties {
+                max_extent: [output.maxExtent.width, output.maxExtent.height,
+                             output.maxExtent.depth],
+                max_mipmaps: output.maxMipLevels,
+                max_array_layers: output.maxArrayLayers,
+                sample_counts: output.sampleCounts,
+                max_resource_size: output.maxResourceSize as usize,
+            })
+        }
+    }
+}
+
+/// Features that a physical device supports for a format, depending on how the format is used.
+///
+/// Obtained by calling `Format::properties`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FormatProperties {
+    /// Features supported by images that use linear tiling.
+    pub linear_tiling_features: FormatFeatures,
+    /// Features supported by images that use optimal tiling.
+    pub optimal_tiling_features: FormatFeatures,
+    /// Features supported by buffers.
+    pub buffer_features: FormatFeatures,
+}
+
+/// List of features that can be supported for a format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FormatFeatures {
+    /// Can be used in a sampled image.
+    pub sampled_image: bool,
+    /// Can be used in a storage image.
+    pub storage_image: bool,
+    /// Can be used in a storage image with atomic operations.
+    pub storage_image_atomic: bool,
+    /// Can be used in a uniform texel buffer.
+    pub uniform_texel_buffer: bool,
+    /// Can be used in a storage texel buffer.
+    pub storage_texel_buffer: bool,
+    /// Can be used in a storage texel buffer with atomic operations.
+    pub storage_texel_buffer_atomic: bool,
+    /// Can be used as the format of a vertex attribute.
+    pub vertex_buffer: bool,
+    /// Can be used as a color attachment or an input attachment.
+    pub color_attachment: bool,
+    /// Can be used as a color attachment with blending.
+    pub color_attachment_blend: bool,
+    /// Can be used as a depth-stencil attachment or an input attachment.
+    pub depth_stencil_attachment: bool,
+    /// Can be used as the source of a blit.
+    pub blit_src: bool,
+    /// Can be used as the destination of a blit.
+    pub blit_dst: bool,
+    /// Can be sampled with a linear filter.
+    pub sampled_image_filter_linear: bool,
+    /// Can be used as the source of a transfer. Only reported if the `khr_maintenance1` extension
+    /// is supported.
+    pub transfer_src: bool,
+    /// Can be used as the destination of a transfer. Only reported if the `khr_maintenance1`
+    /// extension is supported.
+    pub transfer_dst: bool,
+}
+
+impl FormatFeatures {
+    /// Builds a `FormatFeatures` with all values set to false.
+    #[inline]
+    pub fn none() -> FormatFeatures {
+        FormatFeatures::from_bits(0)
+    }
+
+    /// Returns true if all the features of `other` are in `self`.
+    #[inline]
+    pub fn superset_of(&self, other: &FormatFeatures) -> bool {
+        let other = other.to_bits();
+        (self.to_bits() & other) == other
+    }
+
+    /// Returns the features of `self` that are not in `other`.
+    #[inline]
+    pub fn difference(&self, other: &FormatFeatures) -> FormatFeatures {
+        FormatFeatures::from_bits(self.to_bits() & !other.to_bits())
+    }
+
+    #[inline]
+    #[doc(hidden)]
+    pub fn from_bits(val: vk::FormatFeatureFlags) -> FormatFeatures {
+        FormatFeatures {
+            sampled_image: (val & vk::FORMAT_FEATURE_SAMPLED_IMAGE_BIT) != 0,
+            storage_image: (val & vk::FORMAT_FEATURE_STORAGE_IMAGE_BIT) != 0,
+            storage_image_atomic: (val & vk::FORMAT_FEATURE_STORAGE_IMAGE_ATOMIC_BIT) != 0,
+            uniform_texel_buffer: (val & vk::FORMAT_FEATURE_UNIFORM_TEXEL_BUFFER_BIT) != 0,
+            storage_texel_buffer: (val & vk::FORMAT_FEATURE_STORAGE_TEXEL_BUFFER_BIT) != 0,
+            storage_texel_buffer_atomic: (val &
+                                          vk::FORMAT_FEATURE_STORAGE_TEXEL_BUFFER_ATOMIC_BIT) != 0,
+            vertex_buffer: (val & vk::FORMAT_FEATURE_VERTEX_BUFFER_BIT) != 0,
+            color_attachment: (val & vk::FORMAT_FEATURE_COLOR_ATTACHMENT_BIT) != 0,
+            color_attachment_blend: (val & vk::FORMAT_FEATURE_COLOR_ATTACHMENT_BLEND_BIT) != 0,
+            depth_stencil_attachment: (val &
+                                       vk::FORMAT_FEATURE_DEPTH_STENCIL_ATTACHMENT_BIT) != 0,
+            blit_src: (val & vk::FORMAT_FEATURE_BLIT_SRC_BIT) != 0,
+            blit_dst: (val & vk::FORMAT_FEATURE_BLIT_DST_BIT) != 0,
+            sampled_image_filter_linear: (val &
+                                          vk::FORMAT_FEATURE_SAMPLED_IMAGE_FILTER_LINEAR_BIT) != 0,
+            transfer_src: (val & vk::FORMAT_FEATURE_TRANSFER_SRC_BIT_KHR) != 0,
+            transfer_dst: (val & vk::FORMAT_FEATURE_TRANSFER_DST_BIT_KHR) != 0,
+        }
+    }
+
+    #[inline]
+    #[doc(hidden)]
+    pub fn to_bits(&self) -> vk::FormatFeatureFlags {
+        let mut result = 0;
+        if self.sampled_image { result |= vk::FORMAT_FEATURE_SAMPLED_IMAGE_BIT; }
+        if self.storage_image { result |= vk::FORMAT_FEATURE_STORAGE_IMAGE_BIT; }
+        if self.storage_image_atomic { result |= vk::FORMAT_FEATURE_STORAGE_IMAGE_ATOMIC_BIT; }
+        if self.uniform_texel_buffer { result |= vk::FORMAT_FEATURE_UNIFORM_TEXEL_BUFFER_BIT; }
+        if self.storage_texel_buffer { result |= vk::FORMAT_FEATURE_STORAGE_TEXEL_BUFFER_BIT; }
+        if self.storage_texel_buffer_atomic {
+            result |= vk::FORMAT_FEATURE_STORAGE_TEXEL_BUFFER_ATOMIC_BIT;
+        }
+        if self.vertex_buffer { result |= vk::FORMAT_FEATURE_VERTEX_BUFFER_BIT; }
+        if self.color_attachment { result |= vk::FORMAT_FEATURE_COLOR_ATTACHMENT_BIT; }
+        if self.color_attachment_blend {
+            result |= vk::FORMAT_FEATURE_COLOR_ATTACHMENT_BLEND_BIT;
+        }
+        if self.depth_stencil_attachment {
+            result |= vk::FORMAT_FEATURE_DEPTH_STENCIL_ATTACHMENT_BIT;
+        }
+        if self.blit_src { result |= vk::FORMAT_FEATURE_BLIT_SRC_BIT; }
+        if self.blit_dst { result |= vk::FORMAT_FEATURE_BLIT_DST_BIT; }
+        if self.sampled_image_filter_linear {
+            result |= vk::FORMAT_FEATURE_SAMPLED_IMAGE_FILTER_LINEAR_BIT;
+        }
+        if self.transfer_src { result |= vk::FORMAT_FEATURE_TRANSFER_SRC_BIT_KHR; }
+        if self.transfer_dst { result |= vk::FORMAT_FEATURE_TRANSFER_DST_BIT_KHR; }
+        result
+    }
+}
+
+/// Limits of the images of a format, for a given image type, tiling, usage and flags.
+///
+/// Obtained by calling `Format::image_format_properties`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ImageFormatProperties {
+    /// Maximum width, height and depth of the image.
+    pub max_extent: [u32; 3],
+    /// Maximum number of mipmap levels.
+    pub max_mipmaps: u32,
+    /// Maximum number of array layers.
+    pub max_array_layers: u32,
+    /// Bitmask of the supported numbers of samples. Bit `n` is set if `2^n` samples are
+    /// supported.
+    pub sample_counts: u32,
+    /// Upper bound of the total size in bytes of the image. The real limit may be lower.
+    pub max_resource_size: usize,
+}
+
+impl ImageFormatProperties {
+    /// Returns true if images can have `samples` samples.
+    #[inline]
+    pub fn supports_samples(&self, samples: u32) -> bool {
+        samples.is_power_of_two() && (self.sample_counts & samples) != 0
+    }
+}
+
+/// Error that can happen when querying the properties of images of a format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImageFormatPropertiesError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The format isn't supported for this combination of type, tiling, usage and flags.
+    FormatNotSupported,
+}
+
+impl error::Error for ImageFormatPropertiesError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ImageFormatPropertiesError::OomError(_) => "not enough memory available",
+            ImageFormatPropertiesError::FormatNotSupported => {
+                "the format isn't supported for this combination of type, tiling, usage and flags"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ImageFormatPropertiesError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ImageFormatPropertiesError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for ImageFormatPropertiesError {
+    #[inline]
+    fn from(err: OomError) -> ImageFormatPropertiesError {
+        ImageFormatPropertiesError::OomError(err)
+    }
+}
+
+impl From<Error> for ImageFormatPropertiesError {
+    #[inline]
+    fn from(err: Error) -> ImageFormatPropertiesError {
+        match err {
+            err @ Error::OutOfHostMemory => {
+                ImageFormatPropertiesError::OomError(OomError::from(err))
+            },
+            err @ Error::OutOfDeviceMemory => {
+                ImageFormatPropertiesError::OomError(OomError::from(err))
+            },
+            _ => panic!("unexpected error: {:?}", err)
+        }
+    }
+}
+
 /// Describes a uniform value that will be used to fill an image.
 // TODO: should have the same layout as `vk::ClearValue` for performances
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -690,6 +1909,64 @@ pub enum ClearValue {
     DepthStencil((f32, u32)),
 }
 
+impl ClearValue {
+    /// Builds the value that clears an image of the given format to `value`.
+    ///
+    /// The components of `value` are in the same order as for `Format::encode_texel`, and are
+    /// converted the same way. For example they are clamped to `[0, 1]` for `Unorm` formats and
+    /// rounded for integer formats. The depth of depth-stencil formats is the first component,
+    /// and the stencil is the second one.
+    ///
+    /// Returns an error if the format is compressed.
+    pub fn from_format(format: Format, value: [f32; 4]) -> Result<ClearValue, TexelError> {
+        // Converts `value` to the memory representation of a texel of `format`, and back.
+        fn convert(format: Format, value: [f32; 4]) -> Result<[f32; 4], TexelError> {
+            let size = try!(format.size().ok_or(TexelError::UnsupportedFormat));
+            let mut data = [0; 32];
+            try!(format.encode_texel(value, &mut data[.. size]));
+            format.decode_texel(&data[.. size])
+        }
+
+        // The converted values of 32 and 64 bits integers can be just above the maximum because
+        // of the precision of `f32`.
+        fn to_u32(value: f32) -> u32 {
+            if value >= u32::MAX as f32 { u32::MAX } else { value as u32 }
+        }
+
+        fn to_i32(value: f32) -> i32 {
+            if value >= i32::MAX as f32 {
+                i32::MAX
+            } else if value <= i32::MIN as f32 {
+                i32::MIN
+            } else {
+                value as i32
+            }
+        }
+
+        Ok(match format.ty() {
+            FormatTy::Float | FormatTy::Compressed => {
+                ClearValue::Float(try!(convert(format, value)))
+            },
+            FormatTy::Uint => {
+                let v = try!(convert(format, value));
+                ClearValue::Uint([to_u32(v[0]), to_u32(v[1]), to_u32(v[2]), to_u32(v[3])])
+            },
+            FormatTy::Sint => {
+                let v = try!(convert(format, value));
+                ClearValue::Int([to_i32(v[0]), to_i32(v[1]), to_i32(v[2]), to_i32(v[3])])
+            },
+            FormatTy::Depth => ClearValue::Depth(try!(convert(format, value))[0]),
+            FormatTy::Stencil => ClearValue::Stencil(try!(convert(format, value))[1] as u32),
+            FormatTy::DepthStencil => {
+                let depth_format = ImageAspect::Depth.buffer_texel_format(format).unwrap();
+                let depth = try!(convert(depth_format, value))[0];
+                let stencil = try!(convert(Format::S8Uint, value))[1] as u32;
+                ClearValue::DepthStencil((depth, stencil))
+            },
+        })
+    }
+}
+
 // TODO: remove all these From implementations once they are no longer needed
 
 impl From<[f32; 1]> for ClearValue {
@@ -837,3 +2114,306 @@ macro_rules! impl_clear_values_tuple {
 }
 
 impl_clear_values_tuple!(A B C D E F G H I J K L M N O P Q R S T U V W X Y Z);
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+    use std::u32;
+    use format::ClearValue;
+    use format::Data;
+    use format::Format;
+    use format::FormatAspects;
+    use format::FormatCompatibility;
+    use format::FormatFeatures;
+    use format::FormatTy;
+    use format::NumericType;
+    use format::StrongStorage;
+    use format::TexelError;
+    use image::ImageCreateFlags;
+    use image::ImageTiling;
+    use image::ImageType;
+    use image::Usage as ImageUsage;
+
+    #[test]
+    fn mandatory_properties() {
+        // The features below are guaranteed by the specs for `R8G8B8A8Unorm`.
+        let (device, _) = gfx_dev_and_queue!();
+
+        let props = Format::R8G8B8A8Unorm.properties(device.physical_device());
+        assert!(props.optimal_tiling_features.sampled_image);
+        assert!(props.optimal_tiling_features.color_attachment);
+        assert!(props.optimal_tiling_features.blit_src);
+        assert!(props.buffer_features.uniform_texel_buffer);
+        assert!(props.buffer_features.vertex_buffer);
+    }
+
+    #[test]
+    fn image_format_properties() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = ImageUsage {
+            sampled: true,
+            .. ImageUsage::none()
+        };
+
+        let props = Format::R8G8B8A8Unorm.image_format_properties(device.physical_device(),
+                                                                  ImageType::Dim2d,
+                                                                  ImageTiling::Optimal, &usage,
+                                                                  &ImageCreateFlags::none())
+                                         .unwrap();
+        assert!(props.max_extent[0] >= 4096 && props.max_extent[1] >= 4096);
+        assert!(props.supports_samples(1));
+        assert!(!props.supports_samples(3));
+    }
+
+    #[test]
+    fn features_difference() {
+        let a = FormatFeatures {
+            sampled_image: true,
+            storage_image: true,
+            .. FormatFeatures::none()
+        };
+
+        let b = FormatFeatures {
+            sampled_image: true,
+            .. FormatFeatures::none()
+        };
+
+        assert!(a.superset_of(&b));
+        assert!(!b.superset_of(&a));
+        assert_eq!(a.difference(&b), FormatFeatures { storage_image: true, .. FormatFeatures::none() });
+        assert_eq!(b.difference(&a), FormatFeatures::none());
+    }
+
+    #[test]
+    fn block_dimensions() {
+        assert_eq!(Format::R8G8B8A8Unorm.block_dimensions(), [1, 1]);
+        assert_eq!(Format::D24Unorm_S8Uint.block_dimensions(), [1, 1]);
+        assert_eq!(Format::BC1_RGBUnormBlock.block_dimensions(), [4, 4]);
+        assert_eq!(Format::EAC_R11G11SnormBlock.block_dimensions(), [4, 4]);
+        assert_eq!(Format::ASTC_10x8SrgbBlock.block_dimensions(), [10, 8]);
+    }
+
+    #[test]
+    fn block_size() {
+        assert_eq!(Format::R8G8B8A8Unorm.block_size(), Some(4));
+        assert_eq!(Format::R32G32B32Sfloat.block_size(), Some(12));
+        assert_eq!(Format::BC1_RGBAUnormBlock.block_size(), Some(8));
+        assert_eq!(Format::BC7SrgbBlock.block_size(), Some(16));
+        assert_eq!(Format::ASTC_12x12UnormBlock.block_size(), Some(16));
+        assert_eq!(Format::D24Unorm_S8Uint.block_size(), None);
+    }
+
+    #[test]
+    fn aspects() {
+        assert_eq!(Format::BC3UnormBlock.aspects(),
+                   FormatAspects { color: true, depth: false, stencil: false });
+        assert_eq!(Format::D32Sfloat.aspects(),
+                   FormatAspects { color: false, depth: true, stencil: false });
+        assert_eq!(Format::D16Unorm_S8Uint.aspects(),
+                   FormatAspects { color: false, depth: true, stencil: true });
+    }
+
+    #[test]
+    fn components() {
+        assert_eq!(Format::R5G6B5UnormPack16.components(), [5, 6, 5, 0]);
+        assert_eq!(Format::B8G8R8A8Srgb.components(), [8, 8, 8, 8]);
+        assert_eq!(Format::A2B10G10R10UnormPack32.components(), [10, 10, 10, 2]);
+        assert_eq!(Format::D24Unorm_S8Uint.components(), [24, 8, 0, 0]);
+        assert_eq!(Format::S8Uint.components(), [0, 8, 0, 0]);
+    }
+
+    #[test]
+    fn numeric_type() {
+        assert_eq!(Format::R8G8B8A8Srgb.numeric_type(), NumericType::Srgb);
+        assert_eq!(Format::R16G16Sscaled.numeric_type(), NumericType::Sscaled);
+        assert_eq!(Format::B10G11R11UfloatPack32.numeric_type(), NumericType::Ufloat);
+        assert_eq!(Format::BC6HSfloatBlock.numeric_type(), NumericType::Sfloat);
+        assert_eq!(Format::D16Unorm.numeric_type(), NumericType::Unorm);
+    }
+
+    #[test]
+    fn compatibility() {
+        assert!(Format::R32Uint.is_compatible_with(Format::R8G8B8A8Unorm));
+        assert!(Format::BC1_RGBUnormBlock.is_compatible_with(Format::BC1_RGBSrgbBlock));
+        assert!(!Format::BC1_RGBUnormBlock.is_compatible_with(Format::BC1_RGBAUnormBlock));
+        assert!(!Format::R16Uint.is_compatible_with(Format::D16Unorm));
+        assert!(!Format::D24Unorm_S8Uint.is_compatible_with(Format::D32Sfloat));
+        assert_eq!(Format::ASTC_6x5UnormBlock.compatibility(),
+                   FormatCompatibility::Astc { width: 6, height: 5 });
+    }
+
+    #[test]
+    fn data_sizes() {
+        fn check<T: Data>() {
+            assert_eq!(Some(mem::size_of::<T>()), T::ty().size());
+        }
+
+        check::<u8>();
+        check::<i16>();
+        check::<f32>();
+        check::<[u8; 3]>();
+        check::<[u16; 4]>();
+        check::<[f32; 2]>();
+        check::<[f64; 4]>();
+    }
+
+    #[test]
+    fn strong_storage_block_size() {
+        fn check<F: StrongStorage>(format: F) {
+            assert_eq!(Some(mem::size_of::<F::Pixel>()), format.format().block_size());
+        }
+
+        check(super::R5G6B5UnormPack16);
+        check(super::R32G32B32A32Sfloat);
+        check(super::BC1_RGBUnormBlock);
+        check(super::BC4SnormBlock);
+        check(super::BC5UnormBlock);
+        check(super::ETC2_R8G8B8A1SrgbBlock);
+        check(super::ASTC_10x10UnormBlock);
+    }
+
+    #[test]
+    fn texel_round_trip() {
+        // These values are exactly representable by all the formats that have the components.
+        let values = [[1.0, 0.0, 1.0, 0.0], [0.0, 1.0, 0.0, 1.0]];
+
+        for num in 0 .. 200 {
+            let format = match Format::from_num(num) {
+                Some(f) => f,
+                None => continue,
+            };
+
+            let size = match format.size() {
+                Some(s) if format.ty() != FormatTy::Compressed => s,
+                _ => continue,
+            };
+
+            let components = format.components();
+            for value in values.iter() {
+                let mut data = vec![0; size];
+                format.encode_texel(*value, &mut data).unwrap();
+                let decoded = format.decode_texel(&data).unwrap();
+
+                for i in 0 .. 4 {
+                    if components[i] != 0 {
+                        assert_eq!(decoded[i], value[i], "{:?}", format);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn texel_srgb() {
+        let pixel = super::R8G8B8A8Srgb.encode_pixel([0.5, 0.0, 1.0, 0.5]).unwrap();
+        assert_eq!(pixel, [188, 0, 255, 128]);
+
+        let decoded = super::R8G8B8A8Srgb.decode_pixel(pixel).unwrap();
+        assert!((decoded[0] - 0.5).abs() < 0.005);
+        assert!((decoded[3] - 0.5).abs() < 0.005);
+    }
+
+    #[test]
+    fn texel_packed() {
+        let pixel = super::A2B10G10R10UnormPack32.encode_pixel([1.0, 0.0, 0.0, 1.0]).unwrap();
+        assert_eq!(pixel, 0xc00003ff);
+
+        let pixel = super::R5G6B5UnormPack16.encode_pixel([0.0, 1.0, 0.0, 0.0]).unwrap();
+        assert_eq!(pixel, 0x07e0);
+
+        let pixel = super::A2R10G10B10SintPack32.encode_pixel([-1.0, 0.0, 600.0, -5.0]).unwrap();
+        assert_eq!(pixel, 0xbff001ff);
+        assert_eq!(super::A2R10G10B10SintPack32.decode_pixel(pixel).unwrap(),
+                   [-1.0, 0.0, 511.0, -2.0]);
+    }
+
+    #[test]
+    fn texel_ufloat() {
+        let format = super::B10G11R11UfloatPack32;
+        let pixel = format.encode_pixel([1.0, 1.0, 1.0, 1.0]).unwrap();
+        assert_eq!(pixel, 0x781e03c0);
+        assert_eq!(format.decode_pixel(pixel).unwrap(), [1.0, 1.0, 1.0, 1.0]);
+
+        // Negative values become 0, and large values become the largest finite value.
+        let pixel = format.encode_pixel([-1.0, 1.0e9, 0.0, 0.0]).unwrap();
+        assert_eq!(format.decode_pixel(pixel).unwrap(), [0.0, 65024.0, 0.0, 1.0]);
+
+        let format = super::E5B9G9R9UfloatPack32;
+        let pixel = format.encode_pixel([1.0, 1.0, 1.0, 1.0]).unwrap();
+        assert_eq!(pixel, 0x84020100);
+        assert_eq!(format.decode_pixel(pixel).unwrap(), [1.0, 1.0, 1.0, 1.0]);
+        let pixel = format.encode_pixel([0.5, 2.0, 0.25, 1.0]).unwrap();
+        assert_eq!(format.decode_pixel(pixel).unwrap(), [0.5, 2.0, 0.25, 1.0]);
+    }
+
+    #[test]
+    fn texel_half() {
+        fn encode(value: f32) -> u16 {
+            let mut data = [0; 2];
+            Format::R16Sfloat.encode_texel([value, 0.0, 0.0, 0.0], &mut data).unwrap();
+            u16::from_ne_bytes(data)
+        }
+
+        fn decode(raw: u16) -> f32 {
+            let data = raw.to_ne_bytes();
+            Format::R16Sfloat.decode_texel(&data).unwrap()[0]
+        }
+
+        assert_eq!(encode(1.0), 0x3c00);
+        assert_eq!(encode(-2.0), 0xc000);
+        assert_eq!(encode(65504.0), 0x7bff);
+        assert_eq!(encode(1.0e6), 0x7c00);
+        assert_eq!(encode(2.0f32.powi(-24)), 0x0001);
+        assert_eq!(decode(0x3555), 1365.0 / 4096.0);
+        assert_eq!(decode(0x0001), 2.0f32.powi(-24));
+        assert!(decode(0x7e00).is_nan());
+    }
+
+    #[test]
+    fn texel_depth() {
+        fn encode(format: Format, value: [f32; 4]) -> u32 {
+            let mut data = [0; 4];
+            format.encode_texel(value, &mut data).unwrap();
+            u32::from_ne_bytes(data)
+        }
+
+        assert_eq!(encode(Format::X8_D24UnormPack32, [1.0, 0.0, 0.0, 0.0]), 0x00ffffff);
+        assert_eq!(encode(Format::X8_D24UnormPack32, [0.5, 0.0, 0.0, 0.0]), 0x00800000);
+        assert_eq!(encode(Format::D32Sfloat, [0.25, 0.0, 0.0, 0.0]),
+                   0.25f32.to_bits());
+
+        let mut stencil = [0];
+        Format::S8Uint.encode_texel([0.0, 42.0, 0.0, 0.0], &mut stencil).unwrap();
+        assert_eq!(stencil, [42]);
+    }
+
+    #[test]
+    fn clear_value_from_format() {
+        assert_eq!(ClearValue::from_format(Format::R8G8B8A8Unorm, [0.0, 2.0, -1.0, 1.0]),
+                   Ok(ClearValue::Float([0.0, 1.0, 0.0, 1.0])));
+        assert_eq!(ClearValue::from_format(Format::R8G8Uint, [3.4, 300.0, 0.0, 0.0]),
+                   Ok(ClearValue::Uint([3, 255, 0, 1])));
+        assert_eq!(ClearValue::from_format(Format::R32Sint, [-5.6, 0.0, 0.0, 0.0]),
+                   Ok(ClearValue::Int([-6, 0, 0, 1])));
+        assert_eq!(ClearValue::from_format(Format::R32Uint, [1.0e10, 0.0, 0.0, 0.0]),
+                   Ok(ClearValue::Uint([u32::MAX, 0, 0, 1])));
+        assert_eq!(ClearValue::from_format(Format::D16Unorm, [2.0, 0.0, 0.0, 0.0]),
+                   Ok(ClearValue::Depth(1.0)));
+        assert_eq!(ClearValue::from_format(Format::D32Sfloat_S8Uint, [0.5, 42.0, 0.0, 0.0]),
+                   Ok(ClearValue::DepthStencil((0.5, 42))));
+        assert_eq!(ClearValue::from_format(Format::BC1_RGBUnormBlock, [0.0; 4]),
+                   Err(TexelError::UnsupportedFormat));
+    }
+
+    #[test]
+    fn texel_errors() {
+        let mut data = [0; 16];
+        assert_eq!(Format::BC1_RGBUnormBlock.encode_texel([0.0; 4], &mut data[.. 8]),
+                   Err(TexelError::UnsupportedFormat));
+        assert_eq!(Format::D24Unorm_S8Uint.decode_texel(&data[.. 4]),
+                   Err(TexelError::UnsupportedFormat));
+        assert_eq!(Format::R8G8B8A8Unorm.encode_texel([0.0; 4], &mut data),
+                   Err(TexelError::WrongDataSize { expected: 4, obtained: 16 }));
+    }
+}
@@ -129,8 +129,9 @@ pub struct Framebuffer<Rp, A> {
 impl<Rp> Framebuffer<Rp, Box<AttachmentsList + Send + Sync>> {
     /// Builds a new framebuffer.
     ///
-    /// The `attachments` parameter depends on which render pass implementation is used.
-    // TODO: allow ImageView
+    /// The `attachments` parameter depends on which render pass implementation is used. In
+    /// order to draw to a part of an image, for example a single face of a cubemap or a single
+    /// mipmap level, pass an `image::view::ImageView` as attachment.
     pub fn new<Ia>(render_pass: Rp, dimensions: [u32; 3], attachments: Ia)
                    -> Result<Arc<Framebuffer<Rp, Box<AttachmentsList + Send + Sync>>>, FramebufferCreationError>
         where Rp: RenderPassAbstract + RenderPassDescAttachmentsList<Ia>
@@ -141,6 +142,14 @@ impl<Rp> Framebuffer<Rp, Box<AttachmentsList + Send + Sync>> {
         // For more safety, we do some additional `debug_assert`s below.
         let attachments = try!(render_pass.check_attachments_list(attachments));
 
+        if attachments.raw_image_view_handles().iter().any(|view| !view.identity_swizzle()) {
+            return Err(FramebufferCreationError::AttachmentNotIdentitySwizzled);
+        }
+
+        if attachments.raw_image_view_handles().iter().any(|view| view.mipmap_levels() != 1) {
+            return Err(FramebufferCreationError::AttachmentMultipleMipmapLevels);
+        }
+
         // Checking the dimensions against the limits.
         {
             let limits = render_pass.device().physical_device().limits();
@@ -345,6 +354,8 @@ pub enum FramebufferCreationError {
     DimensionsTooLarge,
     /// One of the attachments has a component swizzle that is different from identity.
     AttachmentNotIdentitySwizzled,
+    /// One of the attachments is a view that covers more than one mipmap level.
+    AttachmentMultipleMipmapLevels,
     /// One of the attachments is too small compared to the requested framebuffer dimensions.
     AttachmentTooSmall,
 }
@@ -366,6 +377,9 @@ impl error::Error for FramebufferCreationError {
             FramebufferCreationError::AttachmentNotIdentitySwizzled => {
                 "one of the attachments has a component swizzle that is different from identity"
             },
+            FramebufferCreationError::AttachmentMultipleMipmapLevels => {
+                "one of the attachments is a view that covers more than one mipmap level"
+            },
             FramebufferCreationError::AttachmentTooSmall => {
                 "one of the attachments is too small compared to the requested framebuffer \
                  dimensions"
@@ -16,6 +16,7 @@ macro_rules! single_pass_renderpass {
         pass: {
             color: [$($color_atch:ident),*],
             depth_stencil: {$($depth_atch:ident)*}
+            $(, resolve: [$($resolve_atch:ident),*])*
         }
     ) => (
         ordered_passes_renderpass!(
@@ -26,6 +27,7 @@ macro_rules! single_pass_renderpass {
                     color: [$($color_atch),*],
                     depth_stencil: {$($depth_atch)*},
                     input: []
+                    $(, resolve: [$($resolve_atch),*])*
                 }
             ]
         )
@@ -33,6 +35,10 @@ macro_rules! single_pass_renderpass {
 }
 
 /// Builds a `RenderPass` object whose template parameter is of undeterminate type.
+///
+/// Each pass can optionally end with a `resolve: [..]` list. If it is present, it must contain
+/// one single-sampled attachment per color attachment of the pass, and each multisampled color
+/// attachment is resolved into the corresponding resolve attachment at the end of the pass.
 #[macro_export]
 macro_rules! ordered_passes_renderpass {
     (
@@ -55,6 +61,7 @@ macro_rules! ordered_passes_renderpass {
                     color: [$($color_atch:ident),*],
                     depth_stencil: {$($depth_atch:ident)*},
                     input: [$($input_atch:ident),*]
+                    $(, resolve: [$($resolve_atch:ident),*])*
                 }
             ),*
         ]
@@ -246,11 +253,16 @@ macro_rules! ordered_passes_renderpass {
                                     ($input_atch, Layout::ShaderReadOnlyOptimal)
                                 ),*
                             ],
-                            resolve_attachments: vec![],
+                            resolve_attachments: vec![
+                                $($(
+                                    ($resolve_atch, Layout::ColorAttachmentOptimal),
+                                )*)*
+                            ],
                             preserve_attachments: (0 .. attachment_num).filter(|&a| {
                                 $(if a == $color_atch { return false; })*
                                 $(if a == $depth_atch { return false; })*
                                 $(if a == $input_atch { return false; })*
+                                $($(if a == $resolve_atch { return false; })*)*
                                 true
                             }).collect()
                         });
@@ -330,6 +342,15 @@ macro_rules! ordered_passes_renderpass {
                             final_layout = Some(Layout::ShaderReadOnlyOptimal);
                         }
                     )*
+
+                    $($(
+                        if $resolve_atch == num {
+                            if initial_layout.is_none() {
+                                initial_layout = Some(Layout::ColorAttachmentOptimal);
+                            }
+                            final_layout = Some(Layout::ColorAttachmentOptimal);
+                        }
+                    )*)*
                 })*
 
                 $(if $atch_name == num {
@@ -519,6 +540,6 @@ macro_rules! ordered_passes_renderpass {
     };
 
     ([] __impl_clear_values__ [$prev:ident] [$($prev_params:ident),*] [$next:ident: $other:ident $(, $rest:ident: $rest_load:ident)*] [$first_param:ident, $($rest_params:ident),*]) => {
-        ordered_passes_renderpass!{[] __impl_clear_values__ [$prev] [$($prev_params,)*] [$($rest: $rest_load),*] [$first_param, $($rest_params),*]}
+        ordered_passes_renderpass!{[] __impl_clear_values__ [$prev] [$($prev_params),*] [$($rest: $rest_load),*] [$first_param, $($rest_params),*]}
     };
 }
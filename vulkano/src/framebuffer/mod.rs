@@ -109,6 +109,7 @@ pub use self::sys::RenderPassCreationError;
 pub use self::sys::RenderPassSys;
 pub use self::traits::FramebufferAbstract;
 pub use self::traits::RenderPassDescClearValues;
+pub use self::traits::RenderPassCompatibility;
 pub use self::traits::RenderPassCompatible;
 pub use self::traits::RenderPassDescAttachmentsList;
 pub use self::traits::RenderPassAbstract;
@@ -103,6 +103,22 @@ impl<D> RenderPass<D> where D: RenderPassDesc {
             }
         }).collect::<SmallVec<[_; 16]>>();
 
+        // If a pass has resolve attachments, there must be one single-sampled resolve attachment
+        // per color attachment.
+        for pass in description.subpasses() {
+            if pass.resolve_attachments.is_empty() {
+                continue;
+            }
+
+            if pass.resolve_attachments.len() != pass.color_attachments.len() {
+                return Err(RenderPassCreationError::ResolveAttachmentsCountMismatch);
+            }
+
+            if pass.resolve_attachments.iter().any(|a| attachments[a.0].samples != 1) {
+                return Err(RenderPassCreationError::ResolveAttachmentMultisampled);
+            }
+        }
+
         // We need to pass pointers to vkAttachmentReference structs when creating the render pass.
         // Therefore we need to allocate them in advance.
         //
@@ -111,11 +127,6 @@ impl<D> RenderPass<D> where D: RenderPassDesc {
         // stencil attachment reference.
         let attachment_references = description.subpasses().flat_map(|pass| {
             // Performing some validation with debug asserts.
-            debug_assert!(pass.resolve_attachments.is_empty() ||
-                          pass.resolve_attachments.len() == pass.color_attachments.len());
-            debug_assert!(pass.resolve_attachments.iter().all(|a| {
-                              attachments[a.0].samples == 1
-                          }));
             debug_assert!(pass.resolve_attachments.is_empty() ||
                           pass.color_attachments.iter().all(|a| {
                               attachments[a.0].samples > 1
@@ -423,6 +434,10 @@ pub enum RenderPassCreationError {
     OomError(OomError),
     /// The maximum number of color attachments has been exceeded.
     ColorAttachmentsLimitExceeded,
+    /// A pass has resolve attachments, but not as many as color attachments.
+    ResolveAttachmentsCountMismatch,
+    /// A resolve attachment has more than one sample per pixel.
+    ResolveAttachmentMultisampled,
 }
 
 impl error::Error for RenderPassCreationError {
@@ -433,6 +448,12 @@ impl error::Error for RenderPassCreationError {
             RenderPassCreationError::ColorAttachmentsLimitExceeded => {
                 "the maximum number of color attachments has been exceeded"
             },
+            RenderPassCreationError::ResolveAttachmentsCountMismatch => {
+                "the number of resolve attachments doesn't match the number of color attachments"
+            },
+            RenderPassCreationError::ResolveAttachmentMultisampled => {
+                "a resolve attachment has more than one sample per pixel"
+            },
         }
     }
 
@@ -478,6 +499,8 @@ impl From<Error> for RenderPassCreationError {
 mod tests {
     use format::Format;
     use framebuffer::RenderPass;
+    use framebuffer::RenderPassDesc;
+    use image::Layout;
     use framebuffer::RenderPassCreationError;
 
     #[test]
@@ -539,4 +562,78 @@ mod tests {
         assert_ne!(granularity[0], 0);
         assert_ne!(granularity[1], 0);
     }
+
+    #[test]
+    fn multisampled_resolve() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp = single_pass_renderpass! {
+            device.clone(),
+            attachments: {
+                msaa: { load: Clear, store: DontCare, format: Format::R8G8B8A8Unorm, samples: 4, },
+                color: { load: DontCare, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: {
+                color: [msaa],
+                depth_stencil: {},
+                resolve: [color]
+            }
+        }.unwrap();
+
+        let subpass = rp.subpass(0).unwrap();
+        assert_eq!(subpass.color_attachments, vec![(0, Layout::ColorAttachmentOptimal)]);
+        assert_eq!(subpass.resolve_attachments, vec![(1, Layout::ColorAttachmentOptimal)]);
+        assert!(subpass.preserve_attachments.is_empty());
+
+        let color = rp.attachment(1).unwrap();
+        assert_eq!(color.initial_layout, Layout::ColorAttachmentOptimal);
+        assert_eq!(color.final_layout, Layout::ColorAttachmentOptimal);
+    }
+
+    #[test]
+    fn resolve_count_mismatch() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp = single_pass_renderpass! {
+            device.clone(),
+            attachments: {
+                msaa: { load: Clear, store: DontCare, format: Format::R8G8B8A8Unorm, samples: 4, },
+                a: { load: DontCare, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, },
+                b: { load: DontCare, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: {
+                color: [msaa],
+                depth_stencil: {},
+                resolve: [a, b]
+            }
+        };
+
+        match rp {
+            Err(RenderPassCreationError::ResolveAttachmentsCountMismatch) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn multisampled_resolve_target() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp = single_pass_renderpass! {
+            device.clone(),
+            attachments: {
+                msaa: { load: Clear, store: DontCare, format: Format::R8G8B8A8Unorm, samples: 4, },
+                color: { load: DontCare, store: Store, format: Format::R8G8B8A8Unorm, samples: 4, }
+            },
+            pass: {
+                color: [msaa],
+                depth_stencil: {},
+                resolve: [color]
+            }
+        };
+
+        match rp {
+            Err(RenderPassCreationError::ResolveAttachmentMultisampled) => (),
+            _ => panic!()
+        }
+    }
 }
@@ -9,14 +9,17 @@
 
 use device::DeviceOwned;
 use format::ClearValue;
+use format::Format;
 use framebuffer::AttachmentsList;
 use framebuffer::FramebufferCreationError;
 use framebuffer::FramebufferSys;
 use framebuffer::RenderPassDesc;
 use framebuffer::RenderPassSys;
+use image::Layout as ImageLayout;
 use pipeline::shader::ShaderInterfaceDef;
 
 use SafeDeref;
+use vk;
 
 /// Trait for objects that contain a Vulkan framebuffer object.
 ///
@@ -237,17 +240,106 @@ pub unsafe trait RenderPassCompatible<Other: ?Sized>: RenderPassDesc where Other
 unsafe impl<A, B: ?Sized> RenderPassCompatible<B> for A
     where A: RenderPassDesc, B: RenderPassDesc
 {
+    #[inline]
     fn is_compatible_with(&self, other: &B) -> bool {
-        // FIXME:
-        /*for (atch1, atch2) in (&self).attachments().zip(other.attachments()) {
-            if !atch1.is_compatible_with(&atch2) {
-                return false;
+        RenderPassCompatibility::new(self).is_compatible_with(&RenderPassCompatibility::new(other))
+    }
+}
+
+/// Summary of a render pass that contains everything that matters when checking whether two
+/// render passes are compatible.
+///
+/// Contrary to the description of the render pass, this struct doesn't borrow anything and can
+/// be stored next to the raw render pass, for example in a command buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderPassCompatibility {
+    // Format and number of samples of the attachments referenced by each subpass.
+    subpasses: Vec<SubpassCompatibility>,
+    // The dependencies between subpasses. Always empty if there is only one subpass.
+    dependencies: Vec<(usize, usize, vk::PipelineStageFlagBits, vk::PipelineStageFlagBits,
+                       vk::AccessFlagBits, vk::AccessFlagBits, bool)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SubpassCompatibility {
+    color_attachments: Vec<(Format, u32)>,
+    depth_stencil: Option<(Format, u32)>,
+    input_attachments: Vec<(Format, u32)>,
+    resolve_attachments: Vec<(Format, u32)>,
+}
+
+impl RenderPassCompatibility {
+    /// Builds the summary of a render pass description.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if a subpass references an attachment that doesn't exist.
+    ///
+    pub fn new<D>(desc: &D) -> RenderPassCompatibility
+        where D: ?Sized + RenderPassDesc
+    {
+        let attachment = |&(num, _): &(usize, ImageLayout)| {
+            let atch = desc.attachment(num).expect("Subpass references a non-existing attachment");
+            (atch.format, atch.samples)
+        };
+
+        let subpasses = (0 .. desc.num_subpasses()).map(|num| {
+            let pass = desc.subpass(num).unwrap();
+            SubpassCompatibility {
+                color_attachments: pass.color_attachments.iter().map(&attachment).collect(),
+                depth_stencil: pass.depth_stencil.as_ref().map(&attachment),
+                input_attachments: pass.input_attachments.iter().map(&attachment).collect(),
+                resolve_attachments: pass.resolve_attachments.iter().map(&attachment).collect(),
             }
-        }*/
+        }).collect::<Vec<_>>();
+
+        // Render passes that contain only one subpass are compatible regardless of their
+        // dependencies.
+        let dependencies = if subpasses.len() >= 2 {
+            (0 .. desc.num_dependencies()).map(|num| {
+                let dep = desc.dependency(num).unwrap();
+                (dep.source_subpass, dep.destination_subpass, dep.src_stages.into(),
+                 dep.dst_stages.into(), dep.src_access.into(), dep.dst_access.into(),
+                 dep.by_region)
+            }).collect()
+        } else {
+            Vec::new()
+        };
 
-        return true;
+        RenderPassCompatibility {
+            subpasses: subpasses,
+            dependencies: dependencies,
+        }
+    }
 
-        // FIXME: finish
+    /// Returns true if the two render passes are compatible, as defined in the
+    /// `Render Pass Compatibility` section of the Vulkan specs.
+    ///
+    /// Two render passes are compatible if their subpasses reference attachments with the same
+    /// formats and numbers of samples, and, if they have more than one subpass, if they have the
+    /// same dependencies.
+    #[inline]
+    pub fn is_compatible_with(&self, other: &RenderPassCompatibility) -> bool {
+        self == other
+    }
+
+    /// Returns the number of subpasses.
+    #[inline]
+    pub fn num_subpasses(&self) -> u32 {
+        self.subpasses.len() as u32
+    }
+
+    /// Returns the number of color attachments of a subpass. Returns `None` if out of range.
+    #[inline]
+    pub fn num_color_attachments(&self, subpass: u32) -> Option<u32> {
+        self.subpasses.get(subpass as usize).map(|p| p.color_attachments.len() as u32)
+    }
+
+    /// Returns the format of the depth-stencil attachment of a subpass. Returns `None` if out of
+    /// range or if the subpass doesn't have a depth-stencil attachment.
+    #[inline]
+    pub fn depth_stencil_format(&self, subpass: u32) -> Option<Format> {
+        self.subpasses.get(subpass as usize).and_then(|p| p.depth_stencil.map(|(f, _)| f))
     }
 }
 
@@ -345,3 +437,60 @@ impl<L> Into<(L, u32)> for Subpass<L> {
         (self.render_pass, self.subpass_id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use format::Format;
+    use framebuffer::RenderPassCompatibility;
+
+    #[test]
+    fn compatible_load_store_ops() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp1 = single_pass_renderpass!(device.clone(),
+            attachments: {
+                a: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: { color: [a], depth_stencil: {} }
+        ).unwrap();
+
+        let rp2 = single_pass_renderpass!(device.clone(),
+            attachments: {
+                a: { load: DontCare, store: DontCare, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: { color: [a], depth_stencil: {} }
+        ).unwrap();
+
+        let c1 = RenderPassCompatibility::new(&rp1);
+        let c2 = RenderPassCompatibility::new(&rp2);
+        assert!(c1.is_compatible_with(&c2));
+        assert_eq!(c1.num_color_attachments(0), Some(1));
+        assert_eq!(c1.depth_stencil_format(0), None);
+    }
+
+    #[test]
+    fn incompatible_formats() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp1 = single_pass_renderpass!(device.clone(),
+            attachments: {
+                a: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, },
+                d: { load: Clear, store: Store, format: Format::D16Unorm, samples: 1, }
+            },
+            pass: { color: [a], depth_stencil: {d} }
+        ).unwrap();
+
+        let rp2 = single_pass_renderpass!(device.clone(),
+            attachments: {
+                a: { load: Clear, store: Store, format: Format::R8G8B8A8Srgb, samples: 1, },
+                d: { load: Clear, store: Store, format: Format::D16Unorm, samples: 1, }
+            },
+            pass: { color: [a], depth_stencil: {d} }
+        ).unwrap();
+
+        let c1 = RenderPassCompatibility::new(&rp1);
+        let c2 = RenderPassCompatibility::new(&rp2);
+        assert!(!c1.is_compatible_with(&c2));
+        assert_eq!(c1.depth_stencil_format(0), Some(Format::D16Unorm));
+    }
+}
@@ -13,6 +13,7 @@ use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 
+use buffer::sys::SparseLevel;
 use device::Device;
 use device::Queue;
 use format::ClearValue;
@@ -21,6 +22,7 @@ use format::FormatDesc;
 use format::FormatTy;
 use image::Dimensions;
 use image::ImageDimensions;
+use image::ImageSubresourceRange;
 use image::ViewType;
 use image::sys::ImageCreationError;
 use image::sys::Layout;
@@ -33,18 +35,21 @@ use image::traits::ImageContent;
 use image::traits::ImageViewAccess;
 use image::traits::Image;
 use image::traits::ImageView;
+use memory::DedicatedAlloc;
+use memory::MemoryUsage;
 use memory::pool::AllocLayout;
 use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
+use memory::pool::PotentialDedicatedAllocation;
 use memory::pool::StdMemoryPool;
 use sync::Sharing;
 
 /// ImageAccess whose purpose is to be used as a framebuffer attachment.
 ///
 /// The image is always two-dimensional and has only one mipmap, but it can have any kind of
-/// format. Trying to use a format that the backend doesn't support for rendering will result in
-/// an error being returned when creating the image. Once you have an `AttachmentImage`, you are
-/// guaranteed that you will be able to draw on it.
+/// format and any number of samples. Trying to use a format that the backend doesn't support for
+/// rendering will result in an error being returned when creating the image. Once you have an
+/// `AttachmentImage`, you are guaranteed that you will be able to draw on it.
 ///
 /// The template parameter of `AttachmentImage` is a type that describes the format of the image.
 ///
@@ -67,6 +72,13 @@ use sync::Sharing;
 /// or a depth buffer that is only used once) then use a transient image as it may improve
 /// performances.
 ///
+/// # Multisampling
+///
+/// Calling `AttachmentImage::multisampled` creates an image with more than one sample per pixel.
+/// Multisampled images can't be presented or sampled like regular images, instead they are
+/// usually resolved to a single-sampled image at the end of a subpass. See the `resolve` field
+/// of the `ordered_passes_renderpass!` macro.
+///
 // TODO: forbid reading transient images outside render passes?
 #[derive(Debug)]
 pub struct AttachmentImage<F, A = Arc<StdMemoryPool>> where A: MemoryPool {
@@ -77,7 +89,7 @@ pub struct AttachmentImage<F, A = Arc<StdMemoryPool>> where A: MemoryPool {
     view: UnsafeImageView,
 
     // Memory used to back the image.
-    memory: A::Alloc,
+    memory: PotentialDedicatedAllocation<A::Alloc>,
 
     // Format.
     format: F,
@@ -100,7 +112,8 @@ impl<F> AttachmentImage<F> {
                -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
         where F: FormatDesc
     {
-        AttachmentImage::new_impl(device, dimensions, format, Usage::none())
+        AttachmentImage::new_impl(device, dimensions, format, Usage::none(), 1,
+                                  &Device::standard_pool(device), MemoryUsage::GpuOnly)
     }
 
     /// Same as `new`, but lets you specify additional usages.
@@ -109,7 +122,51 @@ impl<F> AttachmentImage<F> {
                       -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
         where F: FormatDesc
     {
-        AttachmentImage::new_impl(device, dimensions, format, usage)
+        AttachmentImage::new_impl(device, dimensions, format, usage, 1,
+                                  &Device::standard_pool(device), default_memory_usage(&usage))
+    }
+
+    /// Same as `new`, but creates an image with `samples` samples per pixel.
+    ///
+    /// Returns `UnsupportedSamplesCount` if `samples` isn't a power of two or isn't supported by
+    /// the device for attachments of this format.
+    #[inline]
+    pub fn multisampled(device: &Arc<Device>, dimensions: [u32; 2], samples: u32, format: F)
+                        -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
+        where F: FormatDesc
+    {
+        AttachmentImage::new_impl(device, dimensions, format, Usage::none(), samples,
+                                  &Device::standard_pool(device), MemoryUsage::GpuOnly)
+    }
+
+    /// Same as `multisampled`, but lets you specify additional usages.
+    #[inline]
+    pub fn multisampled_with_usage(device: &Arc<Device>, dimensions: [u32; 2], samples: u32,
+                                   format: F, usage: Usage)
+                                   -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
+        where F: FormatDesc
+    {
+        AttachmentImage::new_impl(device, dimensions, format, usage, samples,
+                                  &Device::standard_pool(device), default_memory_usage(&usage))
+    }
+
+    /// Same as `multisampled`, except that the image will be transient.
+    ///
+    /// This is usually what you want for a multisampled image that is resolved at the end of a
+    /// subpass, since its content isn't needed after the render pass.
+    #[inline]
+    pub fn transient_multisampled(device: &Arc<Device>, dimensions: [u32; 2], samples: u32,
+                                  format: F)
+                                  -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
+        where F: FormatDesc
+    {
+        let base_usage = Usage {
+            transient_attachment: true,
+            .. Usage::none()
+        };
+
+        AttachmentImage::new_impl(device, dimensions, format, base_usage, samples,
+                                  &Device::standard_pool(device), MemoryUsage::Transient)
     }
 
     /// Same as `new`, except that the image will be transient.
@@ -126,11 +183,49 @@ impl<F> AttachmentImage<F> {
             .. Usage::none()
         };
 
-        AttachmentImage::new_impl(device, dimensions, format, base_usage)
+        AttachmentImage::new_impl(device, dimensions, format, base_usage, 1,
+                                  &Device::standard_pool(device), MemoryUsage::Transient)
     }
+}
 
-    fn new_impl(device: &Arc<Device>, dimensions: [u32; 2], format: F, base_usage: Usage)
-                -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
+impl<F, A> AttachmentImage<F, A> where A: MemoryPool {
+    /// Same as `with_usage`, but allocates the memory of the image from `pool`, in a memory type
+    /// chosen according to `memory_usage`.
+    ///
+    /// `with_usage` uses `MemoryUsage::Transient` if `usage` contains the transient attachment
+    /// usage, and `MemoryUsage::GpuOnly` otherwise. The memory of the image is never mapped, even
+    /// if the chosen memory type is host-visible.
+    ///
+    /// Returns `UnsupportedMemoryUsage` if `memory_usage` requires host-visible memory and none
+    /// of the memory types that can hold the image is host-visible.
+    #[inline]
+    pub fn with_pool(device: &Arc<Device>, dimensions: [u32; 2], format: F, usage: Usage,
+                     pool: &A, memory_usage: MemoryUsage)
+                     -> Result<Arc<AttachmentImage<F, A>>, ImageCreationError>
+        where F: FormatDesc
+    {
+        AttachmentImage::new_impl(device, dimensions, format, usage, 1, pool, memory_usage)
+    }
+
+    /// Same as `transient`, but allocates the memory of the image from `pool`, in a memory type
+    /// chosen according to `memory_usage`. See `with_pool`.
+    #[inline]
+    pub fn transient_with_pool(device: &Arc<Device>, dimensions: [u32; 2], format: F, pool: &A,
+                               memory_usage: MemoryUsage)
+                               -> Result<Arc<AttachmentImage<F, A>>, ImageCreationError>
+        where F: FormatDesc
+    {
+        let base_usage = Usage {
+            transient_attachment: true,
+            .. Usage::none()
+        };
+
+        AttachmentImage::new_impl(device, dimensions, format, base_usage, 1, pool, memory_usage)
+    }
+
+    fn new_impl(device: &Arc<Device>, dimensions: [u32; 2], format: F, base_usage: Usage,
+                samples: u32, pool: &A, memory_usage: MemoryUsage)
+                -> Result<Arc<AttachmentImage<F, A>>, ImageCreationError>
         where F: FormatDesc
     {
         // TODO: check dimensions against the max_framebuffer_width/height/layers limits
@@ -152,20 +247,20 @@ impl<F> AttachmentImage<F> {
         let (image, mem_reqs) = unsafe {
             try!(UnsafeImage::new(device, &usage, format.format(),
                                   ImageDimensions::Dim2d { width: dimensions[0], height: dimensions[1], array_layers: 1, cubemap_compatible: false },
-                                  1, 1, Sharing::Exclusive::<Empty<u32>>, false, false))
+                                  samples, 1, Sharing::Exclusive::<Empty<u32>>, SparseLevel::none(),
+                                  false, false))
         };
 
-        let mem_ty = {
-            let device_local = device.physical_device().memory_types()
-                                     .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
-                                     .filter(|t| t.is_device_local());
-            let any = device.physical_device().memory_types()
-                            .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0);
-            device_local.chain(any).next().unwrap()
+        let mem_ty = match memory_usage.choose_memory_type(device.physical_device(),
+                                                           mem_reqs.memory_type_bits)
+        {
+            Some(ty) => ty,
+            None => return Err(ImageCreationError::UnsupportedMemoryUsage),
         };
 
-        let mem = try!(MemoryPool::alloc(&Device::standard_pool(device), mem_ty,
-                                         mem_reqs.size, mem_reqs.alignment, AllocLayout::Optimal));
+        let mem = try!(MemoryPool::alloc_from_requirements(pool, device, mem_ty, &mem_reqs,
+                                                           AllocLayout::Optimal,
+                                                           DedicatedAlloc::Image(&image)));
         debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
         unsafe { try!(image.bind_memory(mem.memory(), mem.offset())); }
 
@@ -183,9 +278,7 @@ impl<F> AttachmentImage<F> {
             gpu_lock: AtomicUsize::new(0),
         }))
     }
-}
 
-impl<F, A> AttachmentImage<F, A> where A: MemoryPool {
     /// Returns the dimensions of the image.
     #[inline]
     pub fn dimensions(&self) -> [u32; 2] {
@@ -194,6 +287,16 @@ impl<F, A> AttachmentImage<F, A> where A: MemoryPool {
     }
 }
 
+// Memory usage of the images created without a pool.
+#[inline]
+fn default_memory_usage(usage: &Usage) -> MemoryUsage {
+    if usage.transient_attachment {
+        MemoryUsage::Transient
+    } else {
+        MemoryUsage::GpuOnly
+    }
+}
+
 /// GPU access to an attachment image.
 pub struct AttachmentImageAccess<F, A> where A: MemoryPool {
     img: Arc<AttachmentImage<F, A>>,
@@ -226,12 +329,16 @@ unsafe impl<F, A> ImageAccess for AttachmentImageAccess<F, A>
     }
 
     #[inline]
-    fn conflict_key(&self, _: u32, _: u32, _: u32, _: u32) -> u64 {
-        self.img.image.key()
+    fn conflict_key(&self, first_layer: u32, num_layers: u32, first_mipmap: u32, num_mipmaps: u32)
+                    -> u64
+    {
+        self.img.image.subresource_key(first_layer, num_layers, first_mipmap, num_mipmaps)
     }
 
     #[inline]
-    fn try_gpu_lock(&self, _: bool, _: &Queue) -> bool {
+    fn try_gpu_lock(&self, _: &ImageSubresourceRange, _: bool, _: &Queue) -> bool {
+        // An attachment image only has one mipmap level and one array layer, so there is no
+        // point in tracking the ranges individually.
         if self.already_locked.swap(true, Ordering::SeqCst) == true {
             return false;
         }
@@ -240,7 +347,7 @@ unsafe impl<F, A> ImageAccess for AttachmentImageAccess<F, A>
     }
 
     #[inline]
-    unsafe fn increase_gpu_lock(&self) {
+    unsafe fn increase_gpu_lock(&self, _: &ImageSubresourceRange) {
         debug_assert!(self.already_locked.load(Ordering::SeqCst));
         let val = self.img.gpu_lock.fetch_add(1, Ordering::SeqCst);
         debug_assert!(val >= 1);
@@ -370,6 +477,9 @@ unsafe impl<F, A> ImageViewAccess for AttachmentImageAccess<F, A>
 mod tests {
     use super::AttachmentImage;
     use format::Format;
+    use image::ImageAccess;
+    use image::sys::ImageCreationError;
+    use image::traits::Image;
 
     #[test]
     fn create_regular() {
@@ -388,4 +498,30 @@ mod tests {
         let (device, _) = gfx_dev_and_queue!();
         let _img = AttachmentImage::new(&device, [32, 32], Format::D16Unorm).unwrap();
     }
+
+    #[test]
+    fn create_multisampled() {
+        let (device, _) = gfx_dev_and_queue!();
+        let img = AttachmentImage::multisampled(&device, [32, 32], 4,
+                                                Format::R8G8B8A8Unorm).unwrap();
+        assert_eq!(img.access().samples(), 4);
+    }
+
+    #[test]
+    fn multisampled_unsupported_samples() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        match AttachmentImage::multisampled(&device, [32, 32], 3, Format::R8G8B8A8Unorm) {
+            Err(ImageCreationError::UnsupportedSamplesCount { obtained: 3 }) => (),
+            _ => panic!()
+        }
+
+        let samples = device.physical_device().limits().framebuffer_color_sample_counts();
+        if samples & 64 == 0 {
+            match AttachmentImage::multisampled(&device, [32, 32], 64, Format::R8G8B8A8Unorm) {
+                Err(ImageCreationError::UnsupportedSamplesCount { obtained: 64 }) => (),
+                _ => panic!()
+            }
+        }
+    }
 }
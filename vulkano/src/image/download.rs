@@ -0,0 +1,447 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use buffer::BufferUsage;
+use buffer::CpuAccessibleBuffer;
+use buffer::cpu_access::LockError;
+use command_buffer::AutoCommandBuffer;
+use command_buffer::AutoCommandBufferBuilder;
+use command_buffer::CommandBuffer;
+use command_buffer::CommandBufferBuild;
+use command_buffer::CommandBufferBuilder;
+use command_buffer::CommandBufferExecFuture;
+use device::Queue;
+use format::Format;
+use image::CubeFace;
+use image::ImageAspect;
+use image::traits::Image;
+use image::traits::ImageAccess;
+use sync::DummyFuture;
+use sync::FenceSignalFuture;
+use sync::GpuFuture;
+use OomError;
+
+/// Copy of the content of an image to the CPU that is being executed by the GPU.
+///
+/// All the array layers of the first mipmap level are copied. Only one aspect of the image is
+/// copied at a time, which means that you need two downloads in order to read both the depth and
+/// the stencil values of a depth-stencil image.
+#[must_use = "Dropping this object will immediately block the thread until the GPU has finished processing the copy"]
+pub struct ImageDownload {
+    // Buffer the content is copied to.
+    buffer: Arc<CpuAccessibleBuffer<[u8]>>,
+    // Future that is signaled when the copy is finished.
+    future: FenceSignalFuture<CommandBufferExecFuture<DummyFuture, AutoCommandBuffer>>,
+    format: Format,
+    aspect: ImageAspect,
+    dimensions: [u32; 3],
+    array_layers: u32,
+    texel_size: usize,
+}
+
+impl ImageDownload {
+    /// Records a command buffer that copies the given aspect of `source` to the CPU, and submits
+    /// it to `queue`.
+    ///
+    /// Returns an error if `source` doesn't have the transfer source usage, has more than one
+    /// sample per pixel, doesn't have the requested aspect or has a compressed format.
+    pub fn new<I>(source: I, aspect: ImageAspect, queue: Arc<Queue>)
+                  -> Result<ImageDownload, ImageDownloadError>
+        where I: Image + Clone,
+              I::Access: ImageAccess + Clone + Send + Sync + 'static
+    {
+        let source_access = source.clone().access();
+        if !source_access.inner().usage_transfer_src() {
+            return Err(ImageDownloadError::MissingTransferSourceUsage);
+        }
+
+        if source_access.samples() != 1 {
+            return Err(ImageDownloadError::MultisampledImage);
+        }
+
+        let has_aspect = match aspect {
+            ImageAspect::Color => source_access.has_color(),
+            ImageAspect::Depth => source_access.has_depth(),
+            ImageAspect::Stencil => source_access.has_stencil(),
+        };
+        if !has_aspect {
+            return Err(ImageDownloadError::MissingAspect);
+        }
+
+        let format = source_access.format();
+        let texel_size = match aspect.buffer_texel_size(format) {
+            Some(s) => s,
+            None => return Err(ImageDownloadError::CompressedFormat),
+        };
+
+        let dims = source_access.dimensions();
+        let whd = dims.width_height_depth();
+        let len = whd[0] as usize * whd[1] as usize * whd[2] as usize *
+                  dims.array_layers() as usize * texel_size;
+
+        let buffer = unsafe {
+            try!(CpuAccessibleBuffer::uninitialized_array(queue.device(), len,
+                                                          &BufferUsage::transfer_dest(),
+                                                          Some(queue.family())))
+        };
+
+        let cb = {
+            let cb = try!(AutoCommandBufferBuilder::new(queue.device().clone(), queue.family()));
+            // Can't fail, since the usages, the number of samples and the aspect have been
+            // checked.
+            let cb = cb.copy_image_to_buffer_dimensions(source, buffer.clone(), [0, 0, 0], whd,
+                                                        0, dims.array_layers(), 0, aspect)
+                       .unwrap();
+            try!(CommandBufferBuild::build(cb))
+        };
+
+        let future = cb.execute(queue).then_signal_fence();
+        try!(future.flush());
+
+        Ok(ImageDownload {
+            buffer: buffer,
+            future: future,
+            format: format,
+            aspect: aspect,
+            dimensions: whd,
+            array_layers: dims.array_layers(),
+            texel_size: texel_size,
+        })
+    }
+
+    /// Blocks until the copy is finished or the timeout is reached.
+    ///
+    /// A value of `0` for the timeout is valid and means that the function should never block.
+    #[inline]
+    pub fn wait(&self, timeout: Duration) -> Result<(), ImageDownloadError> {
+        try!(self.future.wait(timeout));
+        Ok(())
+    }
+
+    /// Blocks until the copy is finished, then returns the content of the image.
+    pub fn into_content(self) -> Result<DownloadedImage, ImageDownloadError> {
+        // Destroying the future waits for the copy to be finished.
+        drop(self.future);
+
+        // Can only fail if the memory can't be invalidated, since nobody else has access to the
+        // buffer.
+        let data = match self.buffer.read() {
+            Ok(d) => d.to_vec(),
+            Err(LockError::OomError(err)) => return Err(ImageDownloadError::OomError(err)),
+            Err(LockError::AlreadyLocked) => unreachable!(),
+        };
+
+        Ok(DownloadedImage {
+            data: data,
+            format: self.format,
+            aspect: self.aspect,
+            width: self.dimensions[0],
+            height: self.dimensions[1],
+            depth: self.dimensions[2],
+            array_layers: self.array_layers,
+            texel_size: self.texel_size,
+            row_pitch: self.dimensions[0] as usize * self.texel_size,
+        })
+    }
+}
+
+/// Content of an image that has been copied to the CPU.
+///
+/// The texels are tightly packed. Rows are `row_pitch` bytes apart, and are stored one after
+/// another for each depth slice, then for each array layer.
+#[derive(Debug, Clone)]
+pub struct DownloadedImage {
+    /// The texels.
+    pub data: Vec<u8>,
+    /// Format of the image.
+    pub format: Format,
+    /// Aspect of the image that has been copied.
+    pub aspect: ImageAspect,
+    /// Width of the image in texels.
+    pub width: u32,
+    /// Height of the image in texels.
+    pub height: u32,
+    /// Depth of the image in texels.
+    pub depth: u32,
+    /// Number of array layers.
+    pub array_layers: u32,
+    /// Size in bytes of a texel in `data`.
+    ///
+    /// For the depth aspect this is the size of the depth component alone, and for the stencil
+    /// aspect this is always 1. Depth values of 24-bits formats are stored in 4 bytes.
+    pub texel_size: usize,
+    /// Number of bytes between the start of two consecutive rows.
+    pub row_pitch: usize,
+}
+
+impl DownloadedImage {
+    /// Returns the texels of an array layer.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `layer` is out of range.
+    ///
+    pub fn layer(&self, layer: u32) -> &[u8] {
+        assert!(layer < self.array_layers, "The array layer is out of range");
+        let layer_size = self.row_pitch * self.height as usize * self.depth as usize;
+        let start = layer as usize * layer_size;
+        &self.data[start .. start + layer_size]
+    }
+
+    /// Returns the texels of a face of the cube number `cube` of a cubemap or cubemap array.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the image doesn't have this cube.
+    ///
+    #[inline]
+    pub fn face(&self, cube: u32, face: CubeFace) -> &[u8] {
+        self.layer(face.array_layer(cube))
+    }
+
+    /// Decodes the texel at the given coordinates of an array layer. See `Format::decode_texel`.
+    ///
+    /// Depth values are returned in the first component and stencil values in the second one.
+    /// The texels of 3D images are read from the first depth slice.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the coordinates or `layer` are out of range.
+    ///
+    pub fn texel(&self, x: u32, y: u32, layer: u32) -> [f32; 4] {
+        assert!(x < self.width && y < self.height, "The coordinates are out of range");
+        let start = y as usize * self.row_pitch + x as usize * self.texel_size;
+        let data = &self.layer(layer)[start .. start + self.texel_size];
+
+        // Can't fail, since the image has the aspect and compressed formats can't be downloaded.
+        let format = self.aspect.buffer_texel_format(self.format).unwrap();
+        format.decode_texel(data).unwrap()
+    }
+}
+
+/// Error that can happen when downloading the content of an image.
+#[derive(Debug)]
+pub enum ImageDownloadError {
+    /// Not enough memory to create the destination buffer or the command buffer.
+    OomError(OomError),
+
+    /// Error while submitting the copy or waiting for it to finish.
+    FutureError(Box<error::Error>),
+
+    /// The source image is missing the transfer source usage.
+    MissingTransferSourceUsage,
+
+    /// The source image has more than one sample per pixel.
+    MultisampledImage,
+
+    /// The source image doesn't have the requested aspect.
+    MissingAspect,
+
+    /// The format of the source image is compressed.
+    CompressedFormat,
+}
+
+impl error::Error for ImageDownloadError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ImageDownloadError::OomError(_) => "not enough memory available",
+            ImageDownloadError::FutureError(_) => "error while submitting or waiting for the copy",
+            ImageDownloadError::MissingTransferSourceUsage => {
+                "the source image is missing the transfer source usage"
+            },
+            ImageDownloadError::MultisampledImage => {
+                "the source image has more than one sample per pixel"
+            },
+            ImageDownloadError::MissingAspect => {
+                "the source image doesn't have the requested aspect"
+            },
+            ImageDownloadError::CompressedFormat => {
+                "the format of the source image is compressed"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ImageDownloadError::OomError(ref err) => Some(err),
+            ImageDownloadError::FutureError(ref err) => Some(&**err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ImageDownloadError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for ImageDownloadError {
+    #[inline]
+    fn from(err: OomError) -> ImageDownloadError {
+        ImageDownloadError::OomError(err)
+    }
+}
+
+impl From<Box<error::Error>> for ImageDownloadError {
+    #[inline]
+    fn from(err: Box<error::Error>) -> ImageDownloadError {
+        ImageDownloadError::FutureError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use format::Format;
+    use image::AttachmentImage;
+    use image::CubeFace;
+    use image::Dimensions;
+    use image::DownloadedImage;
+    use image::ImageAspect;
+    use image::ImageDownload;
+    use image::ImageDownloadError;
+    use image::StorageImage;
+    use image::Usage;
+    use mock;
+
+    #[test]
+    fn download_color() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let usage = Usage { transfer_source: true, .. Usage::none() };
+        let image = AttachmentImage::with_usage(&device, [16, 8], Format::R8G8B8A8Unorm,
+                                                usage).unwrap();
+
+        let content = ImageDownload::new(image, ImageAspect::Color, queue.clone()).unwrap()
+                                    .into_content().unwrap();
+        assert_eq!(content.row_pitch, 16 * 4);
+        assert_eq!(content.data.len(), 16 * 8 * 4);
+
+        let calls = mock::calls(&device);
+        assert!(calls.contains(&"CmdCopyImageToBuffer"));
+        assert!(calls.contains(&"QueueSubmit"));
+    }
+
+    #[test]
+    fn download_depth_stencil_separately() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let usage = Usage { transfer_source: true, .. Usage::none() };
+        let image = AttachmentImage::with_usage(&device, [16, 8], Format::D24Unorm_S8Uint,
+                                                usage).unwrap();
+
+        let depth = ImageDownload::new(image.clone(), ImageAspect::Depth, queue.clone()).unwrap()
+                                  .into_content().unwrap();
+        assert_eq!(depth.texel_size, 4);
+        assert_eq!(depth.data.len(), 16 * 8 * 4);
+
+        let stencil = ImageDownload::new(image, ImageAspect::Stencil, queue.clone()).unwrap()
+                                    .into_content().unwrap();
+        assert_eq!(stencil.texel_size, 1);
+        assert_eq!(stencil.row_pitch, 16);
+    }
+
+    #[test]
+    fn download_cubemap_faces() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = StorageImage::new(&device, Dimensions::Cubemap { size: 16 },
+                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        let content = ImageDownload::new(image, ImageAspect::Color, queue.clone()).unwrap()
+                                    .into_content().unwrap();
+        assert_eq!(content.array_layers, 6);
+        assert_eq!(content.data.len(), 6 * 16 * 16 * 4);
+        assert_eq!(content.face(0, CubeFace::NegativeZ).len(), 16 * 16 * 4);
+        assert_eq!(content.face(0, CubeFace::NegativeZ).as_ptr(),
+                   content.layer(5).as_ptr());
+    }
+
+    #[test]
+    fn decode_texels() {
+        let mut data = vec![0; 2 * 2 * 4];
+        Format::R8G8B8A8Unorm.encode_texel([1.0, 0.0, 1.0, 0.0], &mut data[12 .. 16]).unwrap();
+
+        let content = DownloadedImage {
+            data: data,
+            format: Format::R8G8B8A8Unorm,
+            aspect: ImageAspect::Color,
+            width: 2,
+            height: 1,
+            depth: 1,
+            array_layers: 2,
+            texel_size: 4,
+            row_pitch: 2 * 4,
+        };
+        assert_eq!(content.texel(0, 0, 0), [0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(content.texel(1, 0, 1), [1.0, 0.0, 1.0, 0.0]);
+
+        let mut data = vec![0; 4];
+        Format::X8_D24UnormPack32.encode_texel([1.0, 0.0, 0.0, 0.0], &mut data).unwrap();
+
+        let content = DownloadedImage {
+            data: data,
+            format: Format::D24Unorm_S8Uint,
+            aspect: ImageAspect::Depth,
+            width: 1,
+            height: 1,
+            depth: 1,
+            array_layers: 1,
+            texel_size: 4,
+            row_pitch: 4,
+        };
+        assert_eq!(content.texel(0, 0, 0), [1.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn wrong_aspect() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let usage = Usage { transfer_source: true, .. Usage::none() };
+        let image = AttachmentImage::with_usage(&device, [16, 8], Format::R8G8B8A8Unorm,
+                                                usage).unwrap();
+        match ImageDownload::new(image, ImageAspect::Depth, queue.clone()) {
+            Err(ImageDownloadError::MissingAspect) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn missing_usage() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let image = AttachmentImage::new(&device, [16, 8], Format::R8G8B8A8Unorm).unwrap();
+        match ImageDownload::new(image, ImageAspect::Color, queue.clone()) {
+            Err(ImageDownloadError::MissingTransferSourceUsage) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn multisampled() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let usage = Usage { transfer_source: true, .. Usage::none() };
+        let image = AttachmentImage::multisampled_with_usage(&device, [16, 8], 4,
+                                                             Format::R8G8B8A8Unorm,
+                                                             usage).unwrap();
+        match ImageDownload::new(image, ImageAspect::Color, queue.clone()) {
+            Err(ImageDownloadError::MultisampledImage) => (),
+            _ => panic!()
+        }
+    }
+}
@@ -8,14 +8,35 @@
 // according to those terms.
 
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use smallvec::SmallVec;
 
+use buffer::Buffer;
+use buffer::BufferAccess;
+use buffer::BufferUsage;
+use buffer::TypedBuffer;
+use buffer::CpuAccessibleBuffer;
+use buffer::sys::SparseLevel;
+use command_buffer::AutoCommandBuffer;
+use command_buffer::AutoCommandBufferBuilder;
+use command_buffer::CommandBuffer;
+use command_buffer::CommandBufferBuild;
+use command_buffer::CommandBufferBuilder;
+use command_buffer::CommandBufferBuilderError;
+use command_buffer::CommandBufferExecFuture;
+use command_buffer::commands_raw::CmdCopyBufferToImageError;
+use command_buffer::commands_raw::CmdGenerateMipmaps;
+use command_buffer::commands_raw::CmdGenerateMipmapsError;
 use device::Device;
 use device::Queue;
 use format::Format;
 use format::FormatDesc;
+use format::FormatFeatures;
+use format::FormatTy;
 use image::Dimensions;
 use image::ImageDimensions;
+use image::ImageSubresourceRange;
 use image::MipmapsCount;
 use image::sys::ImageCreationError;
 use image::sys::Layout;
@@ -28,12 +49,17 @@ use image::traits::ImageViewAccess;
 use image::traits::Image;
 use image::traits::ImageView;
 use instance::QueueFamily;
+use memory::Content;
+use memory::MemoryUsage;
 use memory::pool::AllocLayout;
 use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
 use memory::pool::StdMemoryPool;
+use sync::DummyFuture;
 use sync::Sharing;
 
+use VulkanObject;
+
 /// Image whose purpose is to be used for read-only purposes. You can write to the image once,
 /// but then you must only ever read from it. TODO: clarify because of blit operations
 // TODO: type (2D, 3D, array, etc.) as template parameter
@@ -47,8 +73,10 @@ pub struct ImmutableImage<F, A = Arc<StdMemoryPool>> where A: MemoryPool {
 }
 
 impl<F> ImmutableImage<F> {
-    /// Builds a new immutable image.
-    // TODO: one mipmap is probably not a great default
+    /// Builds a new immutable image with a single mipmap level.
+    ///
+    /// Use `from_iter` or `from_buffer` with `MipmapsCount::Log2` in order to build an image
+    /// whose mipmaps are generated from its content.
     #[inline]
     pub fn new<'a, I>(device: &Arc<Device>, dimensions: Dimensions, format: F, queue_families: I)
                       -> Result<Arc<ImmutableImage<F>>, ImageCreationError>
@@ -58,17 +86,256 @@ impl<F> ImmutableImage<F> {
     }
 
     /// Builds a new immutable image with the given number of mipmaps.
+    #[inline]
     pub fn with_mipmaps<'a, I, M>(device: &Arc<Device>, dimensions: Dimensions, format: F,
                                   mipmaps: M, queue_families: I)
                                   -> Result<Arc<ImmutableImage<F>>, ImageCreationError>
         where F: FormatDesc, I: IntoIterator<Item = QueueFamily<'a>>, M: Into<MipmapsCount>
     {
-        let usage = Usage {
+        let (image, _) = try!(ImmutableImage::uninitialized(device, dimensions, format, mipmaps,
+                                                            queue_families));
+        Ok(image)
+    }
+
+    /// Builds a new immutable image whose content is uninitialized.
+    ///
+    /// Returns the image, and an object that must be used as the destination of the commands
+    /// that initialize the content of the image. This object transitions the image from the
+    /// `Undefined` layout, and can only be used in a single submission.
+    pub fn uninitialized<'a, I, M>(device: &Arc<Device>, dimensions: Dimensions, format: F,
+                                   mipmaps: M, queue_families: I)
+                                   -> Result<(Arc<ImmutableImage<F>>,
+                                              Arc<ImmutableImageInitialization<F>>),
+                                             ImageCreationError>
+        where F: FormatDesc, I: IntoIterator<Item = QueueFamily<'a>>, M: Into<MipmapsCount>
+    {
+        ImmutableImage::uninitialized_with_pool(device, dimensions, format, mipmaps,
+                                                queue_families, &Device::standard_pool(device),
+                                                MemoryUsage::GpuOnly)
+    }
+
+    /// Builds an immutable image from the texels of an iterator.
+    ///
+    /// The texels are written to a temporary CPU-accessible buffer, and a command buffer that
+    /// copies them to the first mipmap level of the image is submitted to `queue`. The image can
+    /// only be used on the family of this queue.
+    ///
+    /// If the image has more than one mipmap level, the other levels are then generated on the
+    /// GPU by blitting each level to the next one with a linear filter, or with a nearest filter
+    /// for depth and stencil formats. If a color format can't be blitted with a linear filter,
+    /// the image also gets the storage usage and the levels are generated with a compute shader
+    /// instead, in which case `queue` must support compute operations. See `CmdGenerateMipmaps`
+    /// for more information.
+    ///
+    /// Returns the image and a future that represents the upload. You must submit the operations
+    /// that use the image after this future.
+    ///
+    /// Returns `FormatNotSupportedForUsage` if the image has more than one mipmap level and its
+    /// format supports neither blitting nor the compute shader. Returns `SourceTooSmall` if the
+    /// iterator doesn't contain enough data for the image, and `WrongSourceContentFormat` if the
+    /// type of its items doesn't match the format of the image.
+    pub fn from_iter<P, I, M>(iter: I, dimensions: Dimensions, format: F, mipmaps: M,
+                              queue: Arc<Queue>)
+                              -> Result<(Arc<ImmutableImage<F>>, ImmutableImageFromBufferFuture),
+                                        ImageCreationError>
+        where P: Send + Sync + Clone + 'static,
+              F: FormatDesc + Send + Sync + 'static,
+              I: ExactSizeIterator<Item = P>,
+              M: Into<MipmapsCount>
+    {
+        let source = try!(CpuAccessibleBuffer::from_iter(queue.device(),
+                                                         &BufferUsage::transfer_source(),
+                                                         Some(queue.family()), iter));
+        ImmutableImage::from_buffer(source, dimensions, format, mipmaps, queue)
+    }
+
+    /// Builds an immutable image whose first mipmap level is a copy of the content of a buffer.
+    ///
+    /// The buffer must contain the texels of each array layer one after another, tightly packed.
+    /// The texels of a 3D image are stored depth slice by depth slice. The faces of a cubemap are
+    /// stored in the order of the `CubeFace` enum, and the cubes of a cubemap array one after
+    /// another. See `from_iter` for more information.
+    ///
+    /// Returns `SourceMissingTransferUsage` if `source` doesn't have the transfer source usage,
+    /// `SourceTooSmall` if it is too small for the image, `WrongSourceContentFormat` if the type
+    /// of its content doesn't match the format of the image, and `UnalignedSourceOffset` if its
+    /// offset isn't a multiple of 4 or of the size of a texel.
+    pub fn from_buffer<B, M>(source: B, dimensions: Dimensions, format: F, mipmaps: M,
+                             queue: Arc<Queue>)
+                             -> Result<(Arc<ImmutableImage<F>>, ImmutableImageFromBufferFuture),
+                                       ImageCreationError>
+        where B: Buffer + Clone,
+              B::Access: TypedBuffer + Clone + Send + Sync + 'static,
+              <B::Access as TypedBuffer>::Content: Content,
+              F: FormatDesc + Send + Sync + 'static,
+              M: Into<MipmapsCount>
+    {
+        let source_access = source.clone().access();
+        if !source_access.inner().buffer.usage_transfer_src() {
+            return Err(ImageCreationError::SourceMissingTransferUsage);
+        }
+
+        if let Some(texel_size) = format.format().size() {
+            let dims = dimensions.to_image_dimensions();
+            let whd = dims.width_height_depth();
+            let required = whd[0] as usize * whd[1] as usize * whd[2] as usize *
+                           dims.array_layers() as usize * texel_size;
+            if source_access.size() < required {
+                return Err(ImageCreationError::SourceTooSmall);
+            }
+        }
+
+        let mipmaps = mipmaps.into();
+        let generate_mipmaps = match mipmaps {
+            MipmapsCount::One => false,
+            MipmapsCount::Specific(num) => num >= 2,
+            MipmapsCount::Log2 => {
+                let whd = dimensions.to_image_dimensions().width_height_depth();
+                whd.iter().any(|&d| d >= 2)
+            },
+        };
+
+        let mut usage = ImmutableImage::<F>::usage();
+
+        if generate_mipmaps {
+            let device = queue.device();
+            let format = format.format();
+            let features = format.properties(device.physical_device()).optimal_tiling_features;
+            let blit = features.blit_src && features.blit_dst;
+            let color = match format.ty() {
+                FormatTy::Depth | FormatTy::Stencil | FormatTy::DepthStencil => false,
+                _ => true,
+            };
+
+            if color && !(blit && features.sampled_image_filter_linear) {
+                // The mipmaps are generated with a compute shader that writes to the image.
+                if !CmdGenerateMipmaps::supports_compute(device, format) {
+                    return Err(ImageCreationError::FormatNotSupportedForUsage {
+                        format: format,
+                        missing_features: FormatFeatures {
+                            blit_src: !features.blit_src,
+                            blit_dst: !features.blit_dst,
+                            sampled_image_filter_linear: !features.sampled_image_filter_linear,
+                            storage_image: true,
+                            .. FormatFeatures::none()
+                        },
+                    });
+                }
+                usage.storage = true;
+            } else if !blit {
+                return Err(ImageCreationError::FormatNotSupportedForUsage {
+                    format: format,
+                    missing_features: FormatFeatures {
+                        blit_src: !features.blit_src,
+                        blit_dst: !features.blit_dst,
+                        .. FormatFeatures::none()
+                    },
+                });
+            }
+        }
+
+        let pool = Device::standard_pool(queue.device());
+        let (image, init) = try!(ImmutableImage::uninitialized_impl(queue.device(), dimensions,
+                                                                    format, mipmaps, usage,
+                                                                    Some(queue.family()), &pool,
+                                                                    MemoryUsage::GpuOnly));
+
+        let dims = image.image.dimensions();
+
+        let cb = {
+            let cb = try!(AutoCommandBufferBuilder::new(queue.device().clone(), queue.family()));
+            let cb = match cb.copy_buffer_to_image_dimensions(source, init.clone(), [0, 0, 0],
+                                                              dims.width_height_depth(), 0,
+                                                              dims.array_layers(), 0)
+            {
+                Ok(cb) => cb,
+                Err(CommandBufferBuilderError::CommandBuildError(err)) => {
+                    return Err(match err {
+                        CmdCopyBufferToImageError::BufferTooSmall => {
+                            ImageCreationError::SourceTooSmall
+                        },
+                        CmdCopyBufferToImageError::WrongContentFormat => {
+                            ImageCreationError::WrongSourceContentFormat
+                        },
+                        CmdCopyBufferToImageError::UnalignedBufferOffset => {
+                            ImageCreationError::UnalignedSourceOffset
+                        },
+                        // The other errors can't happen, since the usages have been checked and
+                        // the whole image is copied.
+                        _ => unreachable!(),
+                    });
+                },
+                Err(CommandBufferBuilderError::CommandAddError(_)) => unreachable!(),
+            };
+            let cb = if generate_mipmaps {
+                match cb.generate_mipmaps(init) {
+                    Ok(cb) => cb,
+                    Err(CommandBufferBuilderError::CommandBuildError(
+                            CmdGenerateMipmapsError::OomError(err))) => return Err(err.into()),
+                    // The other errors can't happen, since the usages and the support of the
+                    // format have been checked.
+                    Err(_) => unreachable!(),
+                }
+            } else {
+                cb
+            };
+            try!(CommandBufferBuild::build(cb))
+        };
+
+        let future = cb.execute(queue);
+        Ok((image, future))
+    }
+}
+
+impl<F, A> ImmutableImage<F, A> where A: MemoryPool {
+    /// Same as `uninitialized`, but allocates the memory of the image from `pool`, in a memory
+    /// type chosen according to `memory_usage`.
+    ///
+    /// `uninitialized` uses `MemoryUsage::GpuOnly`. The memory of the image is never mapped, even
+    /// if the chosen memory type is host-visible.
+    ///
+    /// Returns `UnsupportedMemoryUsage` if `memory_usage` requires host-visible memory and none
+    /// of the memory types that can hold the image is host-visible.
+    #[inline]
+    pub fn uninitialized_with_pool<'a, I, M>(device: &Arc<Device>, dimensions: Dimensions,
+                                             format: F, mipmaps: M, queue_families: I, pool: &A,
+                                             memory_usage: MemoryUsage)
+                                             -> Result<(Arc<ImmutableImage<F, A>>,
+                                                        Arc<ImmutableImageInitialization<F, A>>),
+                                                       ImageCreationError>
+        where F: FormatDesc, I: IntoIterator<Item = QueueFamily<'a>>, M: Into<MipmapsCount>
+    {
+        ImmutableImage::uninitialized_impl(device, dimensions, format, mipmaps,
+                                           ImmutableImage::<F, A>::usage(), queue_families, pool,
+                                           memory_usage)
+    }
+
+    // Usage of the images, without the storage usage that the compute shader generating the
+    // mipmaps needs.
+    #[inline]
+    fn usage() -> Usage {
+        Usage {
             transfer_source: true,  // for blits
             transfer_dest: true,
             sampled: true,
             .. Usage::none()
-        };
+        }
+    }
+
+    // Implementation of `uninitialized`, with the usage of the image.
+    fn uninitialized_impl<'a, I, M>(device: &Arc<Device>, dimensions: Dimensions, format: F,
+                                    mipmaps: M, usage: Usage, queue_families: I, pool: &A,
+                                    memory_usage: MemoryUsage)
+                                    -> Result<(Arc<ImmutableImage<F, A>>,
+                                               Arc<ImmutableImageInitialization<F, A>>),
+                                              ImageCreationError>
+        where F: FormatDesc, I: IntoIterator<Item = QueueFamily<'a>>, M: Into<MipmapsCount>
+    {
+        if let Dimensions::CubemapArray { .. } = dimensions {
+            if !device.enabled_features().image_cube_array {
+                return Err(ImageCreationError::ImageCubeArrayFeatureNotEnabled);
+            }
+        }
 
         let queue_families = queue_families.into_iter().map(|f| f.id())
                                            .collect::<SmallVec<[u32; 4]>>();
@@ -81,20 +348,18 @@ impl<F> ImmutableImage<F> {
             };
 
             try!(UnsafeImage::new(device, &usage, format.format(), dimensions.to_image_dimensions(),
-                                  1, mipmaps, sharing, false, false))
+                                  1, mipmaps, sharing, SparseLevel::none(), false, false))
         };
 
-        let mem_ty = {
-            let device_local = device.physical_device().memory_types()
-                                     .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
-                                     .filter(|t| t.is_device_local());
-            let any = device.physical_device().memory_types()
-                            .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0);
-            device_local.chain(any).next().unwrap()
+        let mem_ty = match memory_usage.choose_memory_type(device.physical_device(),
+                                                           mem_reqs.memory_type_bits)
+        {
+            Some(ty) => ty,
+            None => return Err(ImageCreationError::UnsupportedMemoryUsage),
         };
 
-        let mem = try!(MemoryPool::alloc(&Device::standard_pool(device), mem_ty,
-                                         mem_reqs.size, mem_reqs.alignment, AllocLayout::Optimal));
+        let mem = try!(MemoryPool::alloc(pool, mem_ty, mem_reqs.size, mem_reqs.alignment,
+                                         AllocLayout::Optimal));
         debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
         unsafe { try!(image.bind_memory(mem.memory(), mem.offset())); }
 
@@ -103,16 +368,26 @@ impl<F> ImmutableImage<F> {
                                       0 .. image.dimensions().array_layers()))
         };
 
-        Ok(Arc::new(ImmutableImage {
+        let image = Arc::new(ImmutableImage {
             image: image,
             view: view,
             memory: mem,
             dimensions: dimensions,
             format: format,
-        }))
+        });
+
+        let init = Arc::new(ImmutableImageInitialization {
+            image: image.clone(),
+            used: AtomicBool::new(false),
+        });
+
+        Ok((image, init))
     }
 }
 
+/// Future that represents the upload of the content of an `ImmutableImage`.
+pub type ImmutableImageFromBufferFuture = CommandBufferExecFuture<DummyFuture, AutoCommandBuffer>;
+
 impl<F, A> ImmutableImage<F, A> where A: MemoryPool {
     /// Returns the dimensions of the image.
     #[inline]
@@ -178,17 +453,21 @@ unsafe impl<F, A> ImageAccess for ImmutableImage<F, A> where F: 'static + Send +
     }
 
     #[inline]
-    fn conflict_key(&self, _: u32, _: u32, _: u32, _: u32) -> u64 {
-        self.image.key()
+    fn conflict_key(&self, first_layer: u32, num_layers: u32, first_mipmap: u32, num_mipmaps: u32)
+                    -> u64
+    {
+        self.image.subresource_key(first_layer, num_layers, first_mipmap, num_mipmaps)
     }
 
     #[inline]
-    fn try_gpu_lock(&self, exclusive_access: bool, queue: &Queue) -> bool {
+    fn try_gpu_lock(&self, _: &ImageSubresourceRange, exclusive_access: bool, queue: &Queue)
+                    -> bool
+    {
         true        // FIXME:
     }
 
     #[inline]
-    unsafe fn increase_gpu_lock(&self) {
+    unsafe fn increase_gpu_lock(&self, _: &ImageSubresourceRange) {
         // FIXME:
     }
 }
@@ -245,3 +524,321 @@ unsafe impl<F: 'static, A> ImageViewAccess for ImmutableImage<F, A>
         true
     }
 }
+
+/// Object that must be used to initialize the content of an `ImmutableImage`.
+///
+/// Obtained with `ImmutableImage::uninitialized`. The image is in the `Undefined` layout when the
+/// object is first used, and all of its subresources, including the ones that the command buffer
+/// doesn't access, are transitioned to the layout of the `ImmutableImage` at the end of the
+/// command buffer.
+///
+/// The object can only be used in a single submission. All the accesses to the image conflict
+/// with each other, so that they are locked as a whole when the command buffer is submitted.
+pub struct ImmutableImageInitialization<F, A = Arc<StdMemoryPool>> where A: MemoryPool {
+    image: Arc<ImmutableImage<F, A>>,
+    // True if the object has already been used in a submission.
+    used: AtomicBool,
+}
+
+impl<F, A> ImmutableImageInitialization<F, A> where A: MemoryPool {
+    /// Returns the image being initialized.
+    #[inline]
+    pub fn image(&self) -> &Arc<ImmutableImage<F, A>> {
+        &self.image
+    }
+}
+
+unsafe impl<F, A> Image for Arc<ImmutableImageInitialization<F, A>>
+    where F: 'static + Send + Sync, A: MemoryPool
+{
+    type Access = Self;
+
+    #[inline]
+    fn access(self) -> Self {
+        self
+    }
+
+    #[inline]
+    fn format(&self) -> Format {
+        self.image.image.format()
+    }
+
+    #[inline]
+    fn samples(&self) -> u32 {
+        self.image.image.samples()
+    }
+
+    #[inline]
+    fn dimensions(&self) -> ImageDimensions {
+        self.image.image.dimensions()
+    }
+}
+
+unsafe impl<F, A> ImageAccess for ImmutableImageInitialization<F, A>
+    where F: 'static + Send + Sync, A: MemoryPool
+{
+    #[inline]
+    fn inner(&self) -> &UnsafeImage {
+        &self.image.image
+    }
+
+    #[inline]
+    fn default_layout(&self) -> Layout {
+        Layout::ShaderReadOnlyOptimal
+    }
+
+    #[inline]
+    fn initial_layout_requirement(&self) -> Layout {
+        Layout::Undefined
+    }
+
+    #[inline]
+    fn final_layout_requirement(&self) -> Layout {
+        Layout::ShaderReadOnlyOptimal
+    }
+
+    #[inline]
+    fn conflicts_image(&self, _: u32, _: u32, _: u32, _: u32, other: &ImageAccess, _: u32, _: u32,
+                       _: u32, _: u32) -> bool
+    {
+        // The image is initialized as a whole, so that the ranges accessed by a command buffer
+        // are merged into a single lock.
+        self.image.image.internal_object() == other.inner().internal_object()
+    }
+
+    #[inline]
+    fn conflict_key(&self, _: u32, _: u32, _: u32, _: u32) -> u64 {
+        self.image.image.subresource_key(0, self.image.image.dimensions().array_layers(), 0,
+                                         self.image.image.mipmap_levels())
+    }
+
+    #[inline]
+    fn try_gpu_lock(&self, _: &ImageSubresourceRange, _: bool, _: &Queue) -> bool {
+        // The content of the image would be lost if it was transitioned from `Undefined` again.
+        !self.used.swap(true, Ordering::SeqCst)
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self, _: &ImageSubresourceRange) {
+        debug_assert!(self.used.load(Ordering::SeqCst));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use device::Device;
+    use format::Format;
+    use image::Dimensions;
+    use image::ImageAccess;
+    use image::ImageSubresourceRange;
+    use image::MipmapsCount;
+    use image::immutable::ImmutableImage;
+    use image::sys::ImageCreationError;
+    use image::sys::Layout;
+    use memory::MemoryUsage;
+    use memory::pool::MemoryPoolAlloc;
+    use mock;
+    use sync::GpuFuture;
+
+    #[test]
+    fn from_iter() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let (image, future) = ImmutableImage::from_iter((0 .. 64 * 64).map(|_| [0u8; 4]),
+                                                        Dimensions::Dim2d { width: 64, height: 64 },
+                                                        Format::R8G8B8A8Unorm,
+                                                        MipmapsCount::One,
+                                                        queue.clone()).unwrap();
+        future.flush().unwrap();
+
+        assert_eq!(image.mipmap_levels(), 1);
+        let calls = mock::calls(&device);
+        assert!(calls.contains(&"CmdCopyBufferToImage"));
+        assert!(!calls.contains(&"CmdBlitImage"));
+        assert!(calls.contains(&"QueueSubmit"));
+    }
+
+    #[test]
+    fn from_iter_log2_mipmaps() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let (image, future) = ImmutableImage::from_iter((0 .. 64 * 32).map(|_| [0u8; 4]),
+                                                        Dimensions::Dim2d { width: 64, height: 32 },
+                                                        Format::R8G8B8A8Unorm,
+                                                        MipmapsCount::Log2,
+                                                        queue.clone()).unwrap();
+        future.flush().unwrap();
+
+        assert_eq!(image.mipmap_levels(), 7);
+        let calls = mock::calls(&device);
+        assert!(calls.contains(&"CmdCopyBufferToImage"));
+        assert_eq!(calls.iter().filter(|&&c| c == "CmdBlitImage").count(), 6);
+    }
+
+    #[test]
+    fn uninitialized_memory_usage() {
+        let (device, queue) = mock_dev_and_queue!();
+        let dims = Dimensions::Dim2d { width: 32, height: 32 };
+
+        let (image, _) = ImmutableImage::uninitialized(&device, dims, Format::R8G8B8A8Unorm,
+                                                       MipmapsCount::One,
+                                                       Some(queue.family())).unwrap();
+        assert!(image.memory.memory().memory_type().is_device_local());
+
+        let (image, _) = ImmutableImage::uninitialized_with_pool(&device, dims,
+                                                                 Format::R8G8B8A8Unorm,
+                                                                 MipmapsCount::One,
+                                                                 Some(queue.family()),
+                                                                 &Device::standard_pool(&device),
+                                                                 MemoryUsage::CpuToGpu).unwrap();
+        assert!(image.memory.memory().memory_type().is_host_visible());
+    }
+
+    #[test]
+    fn from_iter_mipmaps_with_compute() {
+        // Integer formats can't be blitted with a linear filter.
+        let (device, queue) = mock_dev_and_queue!(queue: |q| q.supports_compute() &&
+                                                            !q.supports_graphics());
+
+        let (image, future) = ImmutableImage::from_iter((0 .. 64 * 32).map(|_| [0u8; 4]),
+                                                        Dimensions::Dim2d { width: 64, height: 32 },
+                                                        Format::R8G8B8A8Uint,
+                                                        MipmapsCount::Log2,
+                                                        queue.clone()).unwrap();
+        future.flush().unwrap();
+
+        assert_eq!(image.mipmap_levels(), 7);
+        assert!(image.inner().usage_storage());
+        let calls = mock::calls(&device);
+        assert!(calls.contains(&"CreateComputePipelines"));
+        assert_eq!(calls.iter().filter(|&&c| c == "CmdDispatch").count(), 6);
+        assert_eq!(calls.iter().filter(|&&c| c == "CmdBlitImage").count(), 0);
+    }
+
+    #[test]
+    fn compute_mipmaps_extended_format_requires_feature() {
+        let (_, queue) = mock_dev_and_queue!();
+
+        let res = ImmutableImage::from_iter((0 .. 16 * 16).map(|_| 0u8),
+                                            Dimensions::Dim2d { width: 16, height: 16 },
+                                            Format::R8Uint, MipmapsCount::Log2, queue.clone());
+        match res {
+            Err(ImageCreationError::FormatNotSupportedForUsage { missing_features, .. }) => {
+                assert!(missing_features.storage_image);
+                assert!(missing_features.sampled_image_filter_linear);
+            },
+            _ => panic!()
+        }
+
+        let (_, queue) = mock_dev_and_queue!(shader_storage_image_extended_formats);
+
+        let (image, _) = ImmutableImage::from_iter((0 .. 16 * 16).map(|_| 0u8),
+                                                   Dimensions::Dim2d { width: 16, height: 16 },
+                                                   Format::R8Uint, MipmapsCount::Log2,
+                                                   queue.clone()).unwrap();
+        assert_eq!(image.mipmap_levels(), 5);
+    }
+
+    #[test]
+    fn from_iter_cubemap() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let (image, future) = ImmutableImage::from_iter((0 .. 6 * 16 * 16).map(|_| [0u8; 4]),
+                                                        Dimensions::Cubemap { size: 16 },
+                                                        Format::R8G8B8A8Unorm,
+                                                        MipmapsCount::Log2,
+                                                        queue.clone()).unwrap();
+        future.flush().unwrap();
+
+        assert_eq!(image.dimensions().array_layers(), 6);
+        assert_eq!(image.mipmap_levels(), 5);
+        let calls = mock::calls(&device);
+        assert!(calls.contains(&"CmdCopyBufferToImage"));
+        assert_eq!(calls.iter().filter(|&&c| c == "CmdBlitImage").count(), 4);
+    }
+
+    #[test]
+    fn cubemap_array_requires_feature() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let dims = Dimensions::CubemapArray { size: 16, array_layers: 2 };
+        let res = ImmutableImage::new(&device, dims, Format::R8G8B8A8Unorm, Some(queue.family()));
+        match res {
+            Err(ImageCreationError::ImageCubeArrayFeatureNotEnabled) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn from_iter_not_enough_data() {
+        let (_, queue) = gfx_dev_and_queue!();
+        let res = ImmutableImage::from_iter((0 .. 16).map(|_| [0u8; 4]),
+                                            Dimensions::Dim2d { width: 64, height: 64 },
+                                            Format::R8G8B8A8Unorm, MipmapsCount::One,
+                                            queue.clone());
+        match res {
+            Err(ImageCreationError::SourceTooSmall) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn from_iter_wrong_content_format() {
+        let (_, queue) = gfx_dev_and_queue!();
+        // Elements of 3 bytes don't match texels of 4 bytes.
+        let res = ImmutableImage::from_iter((0 .. 64 * 64 * 2).map(|_| [0u8; 3]),
+                                            Dimensions::Dim2d { width: 64, height: 64 },
+                                            Format::R8G8B8A8Unorm, MipmapsCount::One,
+                                            queue.clone());
+        match res {
+            Err(ImageCreationError::WrongSourceContentFormat) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn from_buffer_missing_usage() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let usage = BufferUsage { uniform_buffer: true, .. BufferUsage::none() };
+        let source = CpuAccessibleBuffer::from_iter(&device, &usage, Some(queue.family()),
+                                                    (0 .. 64 * 64).map(|_| [0u8; 4])).unwrap();
+        let res = ImmutableImage::from_buffer(source, Dimensions::Dim2d { width: 64, height: 64 },
+                                              Format::R8G8B8A8Unorm, MipmapsCount::One,
+                                              queue.clone());
+        match res {
+            Err(ImageCreationError::SourceMissingTransferUsage) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn initialization_layouts() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let (_, init) = ImmutableImage::uninitialized(&device,
+                                                      Dimensions::Dim2d { width: 64, height: 64 },
+                                                      Format::R8G8B8A8Unorm, 1,
+                                                      Some(queue.family())).unwrap();
+        assert_eq!(init.initial_layout_requirement(), Layout::Undefined);
+        assert_eq!(init.final_layout_requirement(), Layout::ShaderReadOnlyOptimal);
+        let range = ImageSubresourceRange::whole_image(&init);
+        assert!(init.try_gpu_lock(&range, true, &queue));
+        assert!(!init.try_gpu_lock(&range, true, &queue));
+    }
+
+    #[test]
+    fn initialization_accesses_conflict() {
+        let (device, queue) = mock_dev_and_queue!();
+
+        let (_, init) = ImmutableImage::uninitialized(&device,
+                                                      Dimensions::Dim2d { width: 64, height: 64 },
+                                                      Format::R8G8B8A8Unorm, 2,
+                                                      Some(queue.family())).unwrap();
+        // Distinct mipmap levels must be locked together.
+        assert!(init.conflicts_image(0, 1, 0, 1, &init, 0, 1, 1, 1));
+        assert_eq!(init.conflict_key(0, 1, 0, 1), init.conflict_key(0, 1, 1, 1));
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Mutex;
+
+use image::ImageSubresourceRange;
+
+/// Keeps track of the ranges of subresources of an image that are locked for usage on the GPU.
+///
+/// This is a helper for implementations of `ImageAccess::try_gpu_lock`. Two locks conflict if
+/// their ranges overlap and at least one of them is exclusive, which means that for example one
+/// face of a cubemap can be written while another face is being read.
+#[derive(Debug)]
+pub struct SubresourceLocks {
+    locks: Mutex<Vec<Lock>>,
+}
+
+#[derive(Debug)]
+struct Lock {
+    range: ImageSubresourceRange,
+    exclusive: bool,
+    // Number of times this range has been locked.
+    count: usize,
+}
+
+impl SubresourceLocks {
+    /// Builds a new object where nothing is locked.
+    #[inline]
+    pub fn new() -> SubresourceLocks {
+        SubresourceLocks {
+            locks: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Tries to lock `range`. Returns `false` if a range that overlaps `range` is already locked,
+    /// and either this lock or the existing one is exclusive.
+    pub fn try_lock(&self, range: &ImageSubresourceRange, exclusive: bool) -> bool {
+        let mut locks = self.locks.lock().unwrap();
+
+        if locks.iter().any(|l| (l.exclusive || exclusive) && l.range.overlaps(range)) {
+            return false;
+        }
+
+        if !exclusive {
+            if let Some(lock) = locks.iter_mut().find(|l| !l.exclusive && l.range == *range) {
+                lock.count += 1;
+                return true;
+            }
+        }
+
+        locks.push(Lock {
+            range: *range,
+            exclusive: exclusive,
+            count: 1,
+        });
+
+        true
+    }
+
+    /// Locks `range` again. Supposes that `range` is already covered by a lock, and simply
+    /// increases the count of this lock by one.
+    pub fn increase(&self, range: &ImageSubresourceRange) {
+        let mut locks = self.locks.lock().unwrap();
+        let lock = find_lock(&mut locks, range);
+        debug_assert!(lock.is_some(), "The range to lock again is not locked");
+        if let Some(lock) = lock {
+            lock.count += 1;
+        }
+    }
+
+    /// Releases one lock on `range`.
+    pub fn unlock(&self, range: &ImageSubresourceRange) {
+        let mut locks = self.locks.lock().unwrap();
+
+        let remove = {
+            let lock = find_lock(&mut locks, range);
+            debug_assert!(lock.is_some(), "The range to unlock is not locked");
+            match lock {
+                Some(lock) => {
+                    lock.count -= 1;
+                    lock.count == 0
+                },
+                None => false,
+            }
+        };
+
+        if remove {
+            locks.retain(|l| l.count != 0);
+        }
+    }
+}
+
+// Finds the lock of exactly `range`, or else the first lock whose range contains `range`.
+fn find_lock<'a>(locks: &'a mut Vec<Lock>, range: &ImageSubresourceRange)
+                 -> Option<&'a mut Lock>
+{
+    match locks.iter().position(|l| l.range == *range) {
+        Some(pos) => Some(&mut locks[pos]),
+        None => locks.iter_mut().find(|l| l.range.contains(range)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::ImageSubresourceRange;
+    use image::SubresourceLocks;
+
+    #[test]
+    fn exclusive_disjoint_layers() {
+        let locks = SubresourceLocks::new();
+        assert!(locks.try_lock(&ImageSubresourceRange::new(0 .. 1, 0 .. 1), true));
+        assert!(locks.try_lock(&ImageSubresourceRange::new(0 .. 1, 1 .. 2), true));
+        assert!(!locks.try_lock(&ImageSubresourceRange::new(0 .. 1, 0 .. 6), false));
+    }
+
+    #[test]
+    fn shared_then_exclusive() {
+        let locks = SubresourceLocks::new();
+        let mip0 = ImageSubresourceRange::new(0 .. 1, 0 .. 1);
+        let mip1 = ImageSubresourceRange::new(1 .. 2, 0 .. 1);
+
+        assert!(locks.try_lock(&mip0, false));
+        assert!(locks.try_lock(&mip0, false));
+        assert!(locks.try_lock(&mip1, true));
+        assert!(!locks.try_lock(&mip0, true));
+
+        locks.unlock(&mip0);
+        assert!(!locks.try_lock(&mip0, true));
+        locks.unlock(&mip0);
+        assert!(locks.try_lock(&mip0, true));
+    }
+
+    #[test]
+    fn increase_contained_range() {
+        let locks = SubresourceLocks::new();
+        let whole = ImageSubresourceRange::new(0 .. 4, 0 .. 6);
+        let face = ImageSubresourceRange::new(0 .. 4, 2 .. 3);
+
+        assert!(locks.try_lock(&whole, true));
+        locks.increase(&face);
+        locks.unlock(&whole);
+        assert!(!locks.try_lock(&face, false));
+        locks.unlock(&face);
+        assert!(locks.try_lock(&face, false));
+    }
+}
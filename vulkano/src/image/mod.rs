@@ -40,17 +40,41 @@
 //! - An `AttachmentImage` can be used when you want to draw to an image.
 //! - An `ImmutableImage` stores data which never need be changed after the initial upload,
 //!   like a texture.
+//! - A `SparseImage` is only partially backed by memory, and the memory of each block can be
+//!   bound and unbound individually, for example for virtual texturing.
+//!
+//! All these images can be used as a view that covers the whole image. If you need a view over
+//! some mipmap levels, some array layers or one aspect of an image, use the `view::ImageView`
+//! struct.
 //!
 //! # Low-level informations
 //!
 //! To be written.
 //!
 
+use std::cmp;
+use std::ops::Range;
+use format::Format;
+use format::FormatTy;
+use vk;
+
 pub use self::attachment::AttachmentImage;
+pub use self::download::DownloadedImage;
+pub use self::download::ImageDownload;
+pub use self::download::ImageDownloadError;
 pub use self::immutable::ImmutableImage;
+pub use self::lock::SubresourceLocks;
+pub use self::sparse::SparseBlock;
+pub use self::sparse::SparseImage;
+pub use self::sparse::SparseImageBindError;
+pub use self::sparse::SparseImageBindFuture;
+pub use self::sparse::SparseImageBinds;
 pub use self::storage::StorageImage;
 pub use self::swapchain::SwapchainImage;
+pub use self::sys::ImageCreateFlags;
 pub use self::sys::ImageCreationError;
+pub use self::sys::ImageTiling;
+pub use self::sys::ImageType;
 pub use self::sys::Layout;
 pub use self::sys::Usage;
 pub use self::traits::ImageAccess;
@@ -59,11 +83,210 @@ pub use self::traits::Image;
 pub use self::traits::ImageView;
 
 pub mod attachment;     // TODO: make private
+mod download;
 pub mod immutable;      // TODO: make private
+mod lock;
+mod sparse;
 mod storage;
 pub mod swapchain;      // TODO: make private
 pub mod sys;
 pub mod traits;
+pub mod view;
+
+/// Aspect of an image that an operation accesses.
+///
+/// Color images only have a color aspect. Depth-stencil images have both a depth and a stencil
+/// aspect, and transfer operations must access each of them separately.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ImageAspect {
+    /// The color aspect of a color image.
+    Color,
+    /// The depth aspect of a depth or depth-stencil image.
+    Depth,
+    /// The stencil aspect of a stencil or depth-stencil image.
+    Stencil,
+}
+
+impl ImageAspect {
+    /// Returns the size in bytes that a texel of this aspect of an image of the given format
+    /// occupies in a buffer, when copying between a buffer and an image.
+    ///
+    /// For compressed formats, this is the size of a whole block of texels. Depth values of
+    /// 24-bits formats occupy 4 bytes, and stencil values always occupy 1 byte.
+    /// Returns `None` if the format doesn't have this aspect.
+    pub fn buffer_texel_size(&self, format: Format) -> Option<usize> {
+        let aspects = format.aspects();
+        match *self {
+            ImageAspect::Color if aspects.color => format.block_size(),
+            ImageAspect::Depth if aspects.depth => {
+                if format.components()[0] <= 16 { Some(2) } else { Some(4) }
+            },
+            ImageAspect::Stencil if aspects.stencil => Some(1),
+            _ => None,
+        }
+    }
+
+    /// Returns the format of the texels of this aspect of an image of the given format, as they
+    /// are stored in a buffer when copying between a buffer and an image.
+    ///
+    /// This is the format itself for the color aspect, the depth format with the same depth
+    /// component for the depth aspect, and `S8Uint` for the stencil aspect. Returns `None` if the
+    /// format doesn't have this aspect.
+    pub fn buffer_texel_format(&self, format: Format) -> Option<Format> {
+        let aspects = format.aspects();
+        match *self {
+            ImageAspect::Color if aspects.color => Some(format),
+            ImageAspect::Depth if aspects.depth => Some(match format {
+                Format::D16Unorm_S8Uint => Format::D16Unorm,
+                Format::D24Unorm_S8Uint => Format::X8_D24UnormPack32,
+                Format::D32Sfloat_S8Uint => Format::D32Sfloat,
+                format => format,
+            }),
+            ImageAspect::Stencil if aspects.stencil => Some(Format::S8Uint),
+            _ => None,
+        }
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn to_aspect_bits(&self) -> vk::ImageAspectFlags {
+        match *self {
+            ImageAspect::Color => vk::IMAGE_ASPECT_COLOR_BIT,
+            ImageAspect::Depth => vk::IMAGE_ASPECT_DEPTH_BIT,
+            ImageAspect::Stencil => vk::IMAGE_ASPECT_STENCIL_BIT,
+        }
+    }
+}
+
+/// Range of subresources of an image.
+///
+/// An image is made of one subresource per aspect, mipmap level and array layer. Operations that
+/// only access some of the subresources of an image, like rendering to one face of a cubemap or
+/// blitting from one mipmap level to the next, don't conflict with operations that access the
+/// other subresources.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ImageSubresourceRange {
+    /// The aspect that is accessed, or `None` for all the aspects of the image.
+    pub aspect: Option<ImageAspect>,
+    /// The first mipmap level of the range.
+    pub first_mipmap_level: u32,
+    /// The number of mipmap levels of the range. Must not be 0.
+    pub num_mipmap_levels: u32,
+    /// The first array layer of the range. The faces of cubemaps are array layers.
+    pub first_array_layer: u32,
+    /// The number of array layers of the range. Must not be 0.
+    pub num_array_layers: u32,
+}
+
+impl ImageSubresourceRange {
+    /// Builds a range that covers all the aspects of the given mipmap levels and array layers.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the ranges is empty.
+    ///
+    #[inline]
+    pub fn new(mipmap_levels: Range<u32>, array_layers: Range<u32>) -> ImageSubresourceRange {
+        assert!(mipmap_levels.start < mipmap_levels.end);
+        assert!(array_layers.start < array_layers.end);
+
+        ImageSubresourceRange {
+            aspect: None,
+            first_mipmap_level: mipmap_levels.start,
+            num_mipmap_levels: mipmap_levels.end - mipmap_levels.start,
+            first_array_layer: array_layers.start,
+            num_array_layers: array_layers.end - array_layers.start,
+        }
+    }
+
+    /// Builds a range that covers all the subresources of an image.
+    #[inline]
+    pub fn whole_image<I: ?Sized>(image: &I) -> ImageSubresourceRange
+        where I: ImageAccess
+    {
+        ImageSubresourceRange::new(0 .. image.inner().mipmap_levels(),
+                                   0 .. image.dimensions().array_layers())
+    }
+
+    /// Returns the mipmap levels covered by the range.
+    #[inline]
+    pub fn mipmap_levels(&self) -> Range<u32> {
+        self.first_mipmap_level .. self.first_mipmap_level + self.num_mipmap_levels
+    }
+
+    /// Returns the array layers covered by the range.
+    #[inline]
+    pub fn array_layers(&self) -> Range<u32> {
+        self.first_array_layer .. self.first_array_layer + self.num_array_layers
+    }
+
+    /// Returns true if the two ranges have at least one subresource in common.
+    #[inline]
+    pub fn overlaps(&self, other: &ImageSubresourceRange) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    /// Returns true if all the subresources of `other` are in `self`.
+    #[inline]
+    pub fn contains(&self, other: &ImageSubresourceRange) -> bool {
+        let aspect = match (self.aspect, other.aspect) {
+            (None, _) => true,
+            (Some(a), Some(b)) => a == b,
+            (Some(_), None) => false,
+        };
+
+        let mips = self.mipmap_levels();
+        let layers = self.array_layers();
+        aspect && mips.start <= other.first_mipmap_level &&
+            mips.end >= other.mipmap_levels().end && layers.start <= other.first_array_layer &&
+            layers.end >= other.array_layers().end
+    }
+
+    /// Returns the subresources that are both in `self` and in `other`, or `None` if the ranges
+    /// don't overlap.
+    pub fn intersection(&self, other: &ImageSubresourceRange) -> Option<ImageSubresourceRange> {
+        let aspect = match (self.aspect, other.aspect) {
+            (None, a) | (a, None) => a,
+            (Some(a), Some(b)) if a == b => Some(a),
+            _ => return None,
+        };
+
+        let mips_start = cmp::max(self.first_mipmap_level, other.first_mipmap_level);
+        let mips_end = cmp::min(self.mipmap_levels().end, other.mipmap_levels().end);
+        let layers_start = cmp::max(self.first_array_layer, other.first_array_layer);
+        let layers_end = cmp::min(self.array_layers().end, other.array_layers().end);
+
+        if mips_start >= mips_end || layers_start >= layers_end {
+            return None;
+        }
+
+        Some(ImageSubresourceRange {
+            aspect: aspect,
+            first_mipmap_level: mips_start,
+            num_mipmap_levels: mips_end - mips_start,
+            first_array_layer: layers_start,
+            num_array_layers: layers_end - layers_start,
+        })
+    }
+
+    /// Returns the aspect mask to pass to Vulkan for this range of an image of the given format.
+    #[doc(hidden)]
+    #[inline]
+    pub fn aspect_bits(&self, format: Format) -> vk::ImageAspectFlags {
+        if let Some(aspect) = self.aspect {
+            return aspect.to_aspect_bits();
+        }
+
+        match format.ty() {
+            FormatTy::Float | FormatTy::Uint | FormatTy::Sint | FormatTy::Compressed => {
+                vk::IMAGE_ASPECT_COLOR_BIT
+            },
+            FormatTy::Depth => vk::IMAGE_ASPECT_DEPTH_BIT,
+            FormatTy::Stencil => vk::IMAGE_ASPECT_STENCIL_BIT,
+            FormatTy::DepthStencil => vk::IMAGE_ASPECT_DEPTH_BIT | vk::IMAGE_ASPECT_STENCIL_BIT,
+        }
+    }
+}
 
 /// Specifies how many mipmaps must be allocated.
 ///
@@ -132,6 +355,17 @@ pub enum ComponentSwizzle {
     Alpha,
 }
 
+impl Swizzle {
+    /// Returns true if every component returns the value it would normally have.
+    #[inline]
+    pub fn is_identity(&self) -> bool {
+        (self.r == ComponentSwizzle::Identity || self.r == ComponentSwizzle::Red) &&
+        (self.g == ComponentSwizzle::Identity || self.g == ComponentSwizzle::Green) &&
+        (self.b == ComponentSwizzle::Identity || self.b == ComponentSwizzle::Blue) &&
+        (self.a == ComponentSwizzle::Identity || self.a == ComponentSwizzle::Alpha)
+    }
+}
+
 impl Default for ComponentSwizzle {
     #[inline]
     fn default() -> ComponentSwizzle {
@@ -245,7 +479,8 @@ impl Dimensions {
                                          array_layers: array_layers, cubemap_compatible: false }
             },
             Dimensions::Dim3d { width, height, depth } => {
-                ImageDimensions::Dim3d { width: width, height: height, depth: depth }
+                ImageDimensions::Dim3d { width: width, height: height, depth: depth,
+                                         array_2d_compatible: false }
             },
             Dimensions::Cubemap { size } => {
                 ImageDimensions::Dim2d { width: size, height: size, array_layers: 6,
@@ -273,6 +508,39 @@ impl Dimensions {
     }
 }
 
+/// Face of a cubemap.
+///
+/// The faces of a cubemap are stored in consecutive array layers of the image, in the order of
+/// the variants of this enum. The faces of the cube number `n` of a cubemap array start at the
+/// array layer `6 * n`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeFace {
+    /// Returns the array layer that contains this face of the cube number `cube` of a cubemap or
+    /// cubemap array image.
+    #[inline]
+    pub fn array_layer(&self, cube: u32) -> u32 {
+        let face = match *self {
+            CubeFace::PositiveX => 0,
+            CubeFace::NegativeX => 1,
+            CubeFace::PositiveY => 2,
+            CubeFace::NegativeY => 3,
+            CubeFace::PositiveZ => 4,
+            CubeFace::NegativeZ => 5,
+        };
+
+        cube * 6 + face
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ViewType {
     Dim1d,
@@ -284,11 +552,16 @@ pub enum ViewType {
     CubemapArray,
 }
 
+/// Dimensions of an image, as seen by the Vulkan implementation.
+///
+/// A cubemap is a 2D image with 6 array layers per cube that is `cubemap_compatible`. A 3D image
+/// that is `array_2d_compatible` can be viewed as a 2D array whose array layers are the depth
+/// slices of the image. This requires the `khr_maintenance1` extension.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ImageDimensions {
     Dim1d { width: u32, array_layers: u32 },
     Dim2d { width: u32, height: u32, array_layers: u32, cubemap_compatible: bool },
-    Dim3d { width: u32, height: u32, depth: u32 }
+    Dim3d { width: u32, height: u32, depth: u32, array_2d_compatible: bool }
 }
 
 impl ImageDimensions {
@@ -337,4 +610,22 @@ impl ImageDimensions {
             ImageDimensions::Dim3d { .. }  => 1,
         }
     }
+
+    /// Returns the width, height and depth of the given mipmap level.
+    ///
+    /// Returns `None` if the image is too small to have this mipmap level.
+    #[inline]
+    pub fn mipmap_width_height_depth(&self, level: u32) -> Option<[u32; 3]> {
+        if level >= 32 {
+            return None;
+        }
+
+        let dims = self.width_height_depth();
+        if level != 0 && (dims[0] | dims[1] | dims[2]) >> level == 0 {
+            return None;
+        }
+
+        Some([cmp::max(1, dims[0] >> level), cmp::max(1, dims[1] >> level),
+              cmp::max(1, dims[2] >> level)])
+    }
 }
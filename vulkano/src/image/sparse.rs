@@ -0,0 +1,1063 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::cmp;
+use std::collections::HashMap;
+use std::error;
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use smallvec::SmallVec;
+
+use buffer::BufferAccess;
+use buffer::sys::SparseLevel;
+use command_buffer::submit::SubmitAnyBuilder;
+use command_buffer::submit::SubmitBindSparseBuilder;
+use command_buffer::submit::SubmitBindSparseError;
+use command_buffer::submit::SubmitBindSparseImageBindBuilder;
+use command_buffer::submit::SubmitBindSparseImageOpaqueBindBuilder;
+use command_buffer::submit::SubmitSemaphoresWaitBuilder;
+use device::Device;
+use device::DeviceOwned;
+use device::Queue;
+use format::ClearValue;
+use format::Format;
+use format::FormatDesc;
+use image::Dimensions;
+use image::ImageDimensions;
+use image::ImageSubresourceRange;
+use image::MipmapsCount;
+use image::SubresourceLocks;
+use image::sys::ImageCreationError;
+use image::sys::Layout;
+use image::sys::SparseImageMemoryRequirements;
+use image::sys::UnsafeImage;
+use image::sys::UnsafeImageView;
+use image::sys::Usage;
+use image::traits::ImageAccess;
+use image::traits::ImageClearValue;
+use image::traits::ImageContent;
+use image::traits::ImageViewAccess;
+use image::traits::Image;
+use image::traits::ImageView;
+use instance::QueueFamily;
+use memory::MemoryRequirements;
+use memory::MemoryUsage;
+use memory::pool::AllocLayout;
+use memory::pool::MemoryPool;
+use memory::pool::MemoryPoolAlloc;
+use memory::pool::StdMemoryPool;
+use sync::AccessFlagBits;
+use sync::DummyFuture;
+use sync::GpuFuture;
+use sync::PipelineStages;
+use sync::Semaphore;
+use sync::Sharing;
+
+use OomError;
+use VulkanObject;
+
+/// Image whose memory is only partially resident, for example for virtual texturing.
+///
+/// A sparse image is created without any memory. Each mipmap level is split into blocks of
+/// `granularity()` texels, and memory must be bound to a block with a `SparseImageBinds` before
+/// the content of this block can be accessed. The smallest mipmap levels, starting from
+/// `mip_tail_first_level()`, form the mipmap tail which is bound as a whole for each array layer.
+///
+/// Creating a sparse image requires the `sparse_binding` feature, and the
+/// `sparse_residency_image2d` or `sparse_residency_image3d` feature depending on the dimensions.
+#[derive(Debug)]
+pub struct SparseImage<F, A = Arc<StdMemoryPool>> where A: MemoryPool {
+    // Inner implementation.
+    image: UnsafeImage,
+
+    // We maintain a view of the whole image.
+    view: UnsafeImageView,
+
+    // Pool used to allocate the memory of the blocks and of the mipmap tails.
+    pool: A,
+
+    // Identifier of the memory type the blocks and the mipmap tails are allocated from.
+    memory_type: u32,
+
+    // Memory requirements of the image. The alignment is the size of a block.
+    mem_reqs: MemoryRequirements,
+
+    // Sparse memory requirements of the aspects of the format.
+    sparse_reqs: SparseImageMemoryRequirements,
+
+    // Memory bound to each block, and to the mipmap tail of each array layer.
+    blocks: Mutex<HashMap<SparseBlock, Arc<A::Alloc>>>,
+    mip_tails: Mutex<HashMap<u32, Arc<A::Alloc>>>,
+
+    // Dimensions of the image view.
+    dimensions: Dimensions,
+
+    // Format.
+    format: F,
+
+    // Queue families allowed to access this image.
+    queue_families: SmallVec<[u32; 4]>,
+
+    // Ranges of subresources of this image that are locked on the GPU side.
+    gpu_locks: SubresourceLocks,
+}
+
+impl<F> SparseImage<F> {
+    /// Creates a new sparse image with the given dimensions, format and number of mipmaps. No
+    /// memory is bound to the image.
+    ///
+    /// Returns `UnsupportedDimensions` if the dimensions are one-dimensional, since 1D images
+    /// can't be partially resident.
+    #[inline]
+    pub fn new<'a, M, I>(device: &Arc<Device>, dimensions: Dimensions, format: F, mipmaps: M,
+                         queue_families: I)
+                         -> Result<Arc<SparseImage<F>>, ImageCreationError>
+        where F: FormatDesc,
+              M: Into<MipmapsCount>,
+              I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        SparseImage::new_with_pool(device, dimensions, format, mipmaps, queue_families,
+                                   Device::standard_pool(device), MemoryUsage::GpuOnly)
+    }
+}
+
+impl<F, A> SparseImage<F, A> where A: MemoryPool {
+    /// Same as `new`, but allocates the memory of the blocks and of the mipmap tails from `pool`,
+    /// in a memory type chosen according to `memory_usage`.
+    ///
+    /// `new` uses `MemoryUsage::GpuOnly`. The memory is never mapped, even if the chosen memory
+    /// type is host-visible.
+    ///
+    /// Returns `UnsupportedMemoryUsage` if `memory_usage` requires host-visible memory and none
+    /// of the memory types that can hold the image is host-visible.
+    pub fn new_with_pool<'a, M, I>(device: &Arc<Device>, dimensions: Dimensions, format: F,
+                                   mipmaps: M, queue_families: I, pool: A,
+                                   memory_usage: MemoryUsage)
+                                   -> Result<Arc<SparseImage<F, A>>, ImageCreationError>
+        where F: FormatDesc,
+              M: Into<MipmapsCount>,
+              I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        let usage = Usage {
+            transfer_source: true,
+            transfer_dest: true,
+            sampled: true,
+            .. Usage::none()
+        };
+
+        let sparse = SparseLevel {
+            sparse: true,
+            sparse_residency: true,
+            sparse_aliased: false,
+        };
+
+        match dimensions {
+            Dimensions::Dim1d { .. } | Dimensions::Dim1dArray { .. } => {
+                return Err(ImageCreationError::UnsupportedDimensions {
+                    dimensions: dimensions.to_image_dimensions(),
+                });
+            },
+            _ => (),
+        }
+
+        if let Dimensions::CubemapArray { .. } = dimensions {
+            if !device.enabled_features().image_cube_array {
+                return Err(ImageCreationError::ImageCubeArrayFeatureNotEnabled);
+            }
+        }
+
+        let queue_families = queue_families.into_iter().map(|f| f.id())
+                                           .collect::<SmallVec<[u32; 4]>>();
+
+        let (image, mem_reqs) = unsafe {
+            let sharing = if queue_families.len() >= 2 {
+                Sharing::Concurrent(queue_families.iter().cloned())
+            } else {
+                Sharing::Exclusive
+            };
+
+            try!(UnsafeImage::new(device, &usage, format.format(), dimensions.to_image_dimensions(),
+                                  1, mipmaps, sharing, sparse, false, false))
+        };
+
+        let memory_type = match memory_usage.choose_memory_type(device.physical_device(),
+                                                                mem_reqs.memory_type_bits)
+        {
+            Some(ty) => ty.id(),
+            None => return Err(ImageCreationError::UnsupportedMemoryUsage),
+        };
+
+        // The metadata aspect, if any, is reported separately and isn't handled.
+        // TODO: bind the metadata of the mipmap tails
+        let sparse_reqs = match image.sparse_memory_requirements().into_iter()
+                                     .find(|r| r.color || r.depth || r.stencil)
+        {
+            Some(r) => r,
+            None => return Err(ImageCreationError::FormatNotSupported),
+        };
+
+        let view = unsafe {
+            try!(UnsafeImageView::raw(&image, dimensions.to_view_type(), 0 .. image.mipmap_levels(),
+                                      0 .. image.dimensions().array_layers()))
+        };
+
+        Ok(Arc::new(SparseImage {
+            image: image,
+            view: view,
+            pool: pool,
+            memory_type: memory_type,
+            mem_reqs: mem_reqs,
+            sparse_reqs: sparse_reqs,
+            blocks: Mutex::new(HashMap::new()),
+            mip_tails: Mutex::new(HashMap::new()),
+            dimensions: dimensions,
+            format: format,
+            queue_families: queue_families,
+            gpu_locks: SubresourceLocks::new(),
+        }))
+    }
+}
+
+impl<F, A> SparseImage<F, A> where A: MemoryPool {
+    /// Returns the dimensions of the image.
+    #[inline]
+    pub fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    /// Returns the number of bytes of memory that back each block.
+    #[inline]
+    pub fn block_size(&self) -> usize {
+        self.mem_reqs.alignment
+    }
+
+    /// Returns the width, height and depth in texels of a block.
+    #[inline]
+    pub fn granularity(&self) -> [u32; 3] {
+        self.sparse_reqs.granularity
+    }
+
+    /// Returns the first mipmap level that is part of the mipmap tail.
+    ///
+    /// Equal to the number of mipmap levels of the image if the image doesn't have a mipmap tail.
+    #[inline]
+    pub fn mip_tail_first_level(&self) -> u32 {
+        cmp::min(self.sparse_reqs.mip_tail_first_lod, self.image.mipmap_levels())
+    }
+
+    /// Returns true if the mipmap tail is shared between all the array layers. If that's the
+    /// case, the mipmap tail must be bound with array layer 0.
+    #[inline]
+    pub fn single_mip_tail(&self) -> bool {
+        self.sparse_reqs.single_mip_tail
+    }
+
+    /// Returns the number of blocks in each dimension of the given mipmap level.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the mipmap level is part of the mipmap tail.
+    ///
+    pub fn num_blocks(&self, mip_level: u32) -> [u32; 3] {
+        assert!(mip_level < self.mip_tail_first_level(),
+                "The mipmap level is part of the mipmap tail");
+
+        let dims = self.image.dimensions().mipmap_width_height_depth(mip_level).unwrap();
+        let granularity = self.sparse_reqs.granularity;
+        [(dims[0] + granularity[0] - 1) / granularity[0],
+         (dims[1] + granularity[1] - 1) / granularity[1],
+         (dims[2] + granularity[2] - 1) / granularity[2]]
+    }
+
+    /// Returns true if memory is bound to the given block, or is going to be bound to it by a
+    /// binding operation that has already been built.
+    #[inline]
+    pub fn is_resident(&self, block: SparseBlock) -> bool {
+        self.blocks.lock().unwrap().contains_key(&block)
+    }
+
+    /// Returns true if memory is bound to the mipmap tail of the given array layer, or is going
+    /// to be bound to it by a binding operation that has already been built.
+    #[inline]
+    pub fn is_mip_tail_resident(&self, array_layer: u32) -> bool {
+        self.mip_tails.lock().unwrap().contains_key(&array_layer)
+    }
+
+    /// Returns the number of blocks that have memory bound to them.
+    #[inline]
+    pub fn num_resident_blocks(&self) -> usize {
+        self.blocks.lock().unwrap().len()
+    }
+
+    // Panics if the block is out of range.
+    fn check_block(&self, block: &SparseBlock) {
+        assert!(block.array_layer < self.image.dimensions().array_layers(),
+                "The array layer of the block is out of range");
+        let num_blocks = self.num_blocks(block.mip_level);
+        assert!(block.x < num_blocks[0] && block.y < num_blocks[1] && block.z < num_blocks[2],
+                "The coordinates of the block are out of range");
+    }
+
+    // Panics if the mipmap tail is out of range.
+    fn check_mip_tail(&self, array_layer: u32) {
+        assert!(self.mip_tail_first_level() < self.image.mipmap_levels(),
+                "The image doesn't have a mipmap tail");
+        if self.sparse_reqs.single_mip_tail {
+            assert_eq!(array_layer, 0, "The mipmap tail is shared between the array layers");
+        } else {
+            assert!(array_layer < self.image.dimensions().array_layers(),
+                    "The array layer of the mipmap tail is out of range");
+        }
+    }
+
+    // Returns the region of the image covered by a block.
+    fn block_region(&self, block: &SparseBlock) -> ([u32; 3], [u32; 3]) {
+        let dims = self.image.dimensions().mipmap_width_height_depth(block.mip_level).unwrap();
+        let granularity = self.sparse_reqs.granularity;
+
+        let offset = [block.x * granularity[0], block.y * granularity[1],
+                      block.z * granularity[2]];
+        let extent = [cmp::min(granularity[0], dims[0] - offset[0]),
+                      cmp::min(granularity[1], dims[1] - offset[1]),
+                      cmp::min(granularity[2], dims[2] - offset[2])];
+        (offset, extent)
+    }
+
+    // Returns the range of subresources that covers the whole image.
+    fn whole_range(&self) -> ImageSubresourceRange {
+        ImageSubresourceRange::new(0 .. self.image.mipmap_levels(),
+                                   0 .. self.image.dimensions().array_layers())
+    }
+
+    // Allocates memory for a block or a mipmap tail.
+    fn alloc(&self, size: usize) -> Result<A::Alloc, OomError> {
+        let device = self.image.device();
+        // Can't fail, since the memory type has been chosen from the ones of the device.
+        let mem_ty = device.physical_device().memory_type_by_id(self.memory_type).unwrap();
+        let alloc = try!(self.pool.alloc(mem_ty, size, self.mem_reqs.alignment,
+                                         AllocLayout::Optimal));
+        debug_assert!((alloc.offset() % self.mem_reqs.alignment) == 0);
+        Ok(alloc)
+    }
+}
+
+/// Identifies a block of a mipmap level of a `SparseImage`.
+///
+/// The coordinates are in blocks and not in texels. The block covers the texels from
+/// `x * granularity[0]`, `y * granularity[1]` and `z * granularity[2]`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SparseBlock {
+    pub mip_level: u32,
+    pub array_layer: u32,
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
+
+/// List of memory binding operations on a `SparseImage`.
+///
+/// Memory is allocated when a binding is added to the list, and the memory of the blocks that
+/// are rebound or unbound is freed once the GPU has finished executing the operations. The
+/// operations are performed in the order in which they are added.
+pub struct SparseImageBinds<F, A = Arc<StdMemoryPool>> where A: MemoryPool {
+    image: Arc<SparseImage<F, A>>,
+    blocks: Vec<(SparseBlock, Option<Arc<A::Alloc>>)>,
+    mip_tails: Vec<(u32, Option<Arc<A::Alloc>>)>,
+}
+
+impl<F, A> SparseImageBinds<F, A> where A: MemoryPool {
+    /// Builds a new empty list of binding operations for the given image.
+    #[inline]
+    pub fn new(image: Arc<SparseImage<F, A>>) -> SparseImageBinds<F, A> {
+        SparseImageBinds {
+            image: image,
+            blocks: Vec::new(),
+            mip_tails: Vec::new(),
+        }
+    }
+
+    /// Returns true if the list doesn't contain any operation.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty() && self.mip_tails.is_empty()
+    }
+
+    /// Allocates memory and binds it to a block.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the block is out of range or is part of the mipmap tail.
+    ///
+    pub fn bind(&mut self, block: SparseBlock) -> Result<(), OomError> {
+        self.image.check_block(&block);
+        let alloc = try!(self.image.alloc(self.image.block_size()));
+        self.blocks.push((block, Some(Arc::new(alloc))));
+        Ok(())
+    }
+
+    /// Unbinds the memory of a block.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the block is out of range or is part of the mipmap tail.
+    ///
+    pub fn unbind(&mut self, block: SparseBlock) {
+        self.image.check_block(&block);
+        self.blocks.push((block, None));
+    }
+
+    /// Allocates memory and binds it to the mipmap tail of the given array layer.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the image doesn't have a mipmap tail.
+    /// - Panics if the array layer is out of range, or is not 0 if the mipmap tail is shared
+    ///   between the array layers.
+    ///
+    pub fn bind_mip_tail(&mut self, array_layer: u32) -> Result<(), OomError> {
+        self.image.check_mip_tail(array_layer);
+        let alloc = try!(self.image.alloc(self.image.sparse_reqs.mip_tail_size));
+        self.mip_tails.push((array_layer, Some(Arc::new(alloc))));
+        Ok(())
+    }
+
+    /// Unbinds the memory of the mipmap tail of the given array layer.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the image doesn't have a mipmap tail.
+    /// - Panics if the array layer is out of range, or is not 0 if the mipmap tail is shared
+    ///   between the array layers.
+    ///
+    pub fn unbind_mip_tail(&mut self, array_layer: u32) {
+        self.image.check_mip_tail(array_layer);
+        self.mip_tails.push((array_layer, None));
+    }
+
+    /// Submits the binding operations to a queue.
+    ///
+    /// > **Note**: This is just a shortcut for `execute_after`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the queue doesn't support sparse binding operations.
+    ///
+    #[inline]
+    pub fn execute(self, queue: Arc<Queue>)
+                   -> Result<SparseImageBindFuture<DummyFuture, F, A>, SparseImageBindError>
+        where F: 'static + Send + Sync
+    {
+        let device = queue.device().clone();
+        self.execute_after(DummyFuture::new(device), queue)
+    }
+
+    /// Submits the binding operations to a queue after an existing future.
+    ///
+    /// The residency reported by the image is updated once the operations have been submitted.
+    ///
+    /// The whole image is locked for exclusive access until the operations have finished, since
+    /// they can free memory that the image uses. Returns `ImageInUse` if the image is already in
+    /// use by an operation that isn't part of `future`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the queue doesn't support sparse binding operations.
+    /// - Panics if the device of the image is not the same as the device of the future.
+    ///
+    pub fn execute_after<P>(self, future: P, queue: Arc<Queue>)
+                            -> Result<SparseImageBindFuture<P, F, A>, SparseImageBindError>
+        where P: GpuFuture, F: 'static + Send + Sync
+    {
+        assert_eq!(self.image.image.device().internal_object(),
+                   future.device().internal_object());
+        assert!(queue.family().supports_sparse_binding(),
+                "Tried to submit a bind sparse operation to a queue that doesn't support it");
+
+        let device = queue.device().clone();
+        let wait_semaphore = try!(Semaphore::new(device.clone()));
+        let semaphore = try!(Semaphore::new(device));
+
+        // If the previous future already has exclusive access to the image, the operations are
+        // ordered after the ones of the future and no additional lock is needed.
+        let range = self.image.whole_range();
+        let locked = match future.check_image_access(&*self.image, &range, true, &queue) {
+            Ok(_) => false,
+            Err(()) => {
+                if !self.image.gpu_locks.try_lock(&range, true) {
+                    return Err(SparseImageBindError::ImageInUse);
+                }
+                true
+            },
+        };
+
+        Ok(SparseImageBindFuture {
+            previous: future,
+            queue: queue,
+            binds: self,
+            released: Mutex::new(Vec::new()),
+            wait_semaphore: wait_semaphore,
+            semaphore: semaphore,
+            locked: locked,
+            submitted: Mutex::new(false),
+            finished: AtomicBool::new(false),
+        })
+    }
+
+    // Updates the memory bound to the image after the operations have been submitted, and
+    // returns the memory that was previously bound.
+    fn apply(&self) -> Vec<Arc<A::Alloc>> {
+        let mut released = Vec::new();
+
+        {
+            let mut blocks = self.image.blocks.lock().unwrap();
+            for &(block, ref alloc) in self.blocks.iter() {
+                let previous = match *alloc {
+                    Some(ref alloc) => blocks.insert(block, alloc.clone()),
+                    None => blocks.remove(&block),
+                };
+                released.extend(previous);
+            }
+        }
+
+        {
+            let mut mip_tails = self.image.mip_tails.lock().unwrap();
+            for &(layer, ref alloc) in self.mip_tails.iter() {
+                let previous = match *alloc {
+                    Some(ref alloc) => mip_tails.insert(layer, alloc.clone()),
+                    None => mip_tails.remove(&layer),
+                };
+                released.extend(previous);
+            }
+        }
+
+        released
+    }
+}
+
+/// Represents memory binding operations on a `SparseImage` being executed by the GPU, and the
+/// moment when the execution finishes.
+#[must_use = "Dropping this object will immediately block the thread until the GPU has finished processing the submission"]
+pub struct SparseImageBindFuture<P, F, A = Arc<StdMemoryPool>>
+    where P: GpuFuture, A: MemoryPool
+{
+    previous: P,
+    queue: Arc<Queue>,
+    binds: SparseImageBinds<F, A>,
+    // Memory that was bound before the operations. Kept alive until the GPU has finished
+    // executing them.
+    released: Mutex<Vec<Arc<A::Alloc>>>,
+    // Signaled by the previous command buffers, since bind sparse operations aren't ordered with
+    // them.
+    wait_semaphore: Semaphore,
+    // Signaled by the bind sparse operation.
+    semaphore: Semaphore,
+    // True if the image has been locked by this future, and must be unlocked once the operations
+    // have finished.
+    locked: bool,
+    // True if the operations have already been submitted.
+    // If flush is called multiple times, we want to block so that only one flushing is executed.
+    // Therefore we use a `Mutex<bool>` and not an `AtomicBool`.
+    submitted: Mutex<bool>,
+    finished: AtomicBool,
+}
+
+unsafe impl<P, F, A> GpuFuture for SparseImageBindFuture<P, F, A>
+    where P: GpuFuture, A: MemoryPool
+{
+    #[inline]
+    fn cleanup_finished(&mut self) {
+        self.previous.cleanup_finished();
+    }
+
+    #[inline]
+    unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, Box<Error>> {
+        // Flushing the binding part, since the operations that come after must wait on the
+        // semaphore.
+        try!(self.flush());
+
+        let mut sem = SubmitSemaphoresWaitBuilder::new();
+        sem.add_wait_semaphore(&self.semaphore);
+        Ok(SubmitAnyBuilder::SemaphoresWait(sem))
+    }
+
+    fn flush(&self) -> Result<(), Box<Error>> {
+        unsafe {
+            let mut submitted = self.submitted.lock().unwrap();
+            if *submitted {
+                return Ok(());
+            }
+
+            let mut builder = match try!(self.previous.build_submission()) {
+                SubmitAnyBuilder::Empty => SubmitBindSparseBuilder::new(),
+                SubmitAnyBuilder::SemaphoresWait(sem) => sem.into(),
+                SubmitAnyBuilder::CommandBuffer(mut cb) => {
+                    debug_assert_eq!(cb.num_signal_semaphores(), 0);
+                    cb.add_signal_semaphore(&self.wait_semaphore);
+                    try!(cb.submit(self.previous.queue().unwrap()));
+                    let mut builder = SubmitBindSparseBuilder::new();
+                    builder.add_wait_semaphore(&self.wait_semaphore);
+                    builder
+                },
+                SubmitAnyBuilder::QueuePresent(_) => {
+                    return Err(Box::new(SubmitBindSparseError::AfterPresent));
+                },
+                SubmitAnyBuilder::BindSparse(builder) => builder,
+            };
+
+            let image = &self.binds.image;
+
+            if !self.binds.blocks.is_empty() {
+                let mut binds = SubmitBindSparseImageBindBuilder::new(&image.image);
+                for &(ref block, ref alloc) in self.binds.blocks.iter() {
+                    let (offset, extent) = image.block_region(block);
+                    match *alloc {
+                        Some(ref alloc) => {
+                            binds.add_bind(block.mip_level, block.array_layer, offset, extent,
+                                           alloc.memory(), alloc.offset());
+                        },
+                        None => {
+                            binds.add_unbind(block.mip_level, block.array_layer, offset, extent);
+                        },
+                    }
+                }
+                builder.add_image_binds(binds);
+            }
+
+            if !self.binds.mip_tails.is_empty() {
+                let reqs = &image.sparse_reqs;
+                let mut binds = SubmitBindSparseImageOpaqueBindBuilder::new(&image.image);
+                for &(layer, ref alloc) in self.binds.mip_tails.iter() {
+                    let offset = reqs.mip_tail_offset + layer as usize * reqs.mip_tail_stride;
+                    match *alloc {
+                        Some(ref alloc) => {
+                            binds.add_bind(offset, reqs.mip_tail_size, alloc.memory(),
+                                           alloc.offset(), false);
+                        },
+                        None => {
+                            binds.add_unbind(offset, reqs.mip_tail_size, false);
+                        },
+                    }
+                }
+                builder.add_image_opaque_binds(binds);
+            }
+
+            debug_assert_eq!(builder.num_signal_semaphores(), 0);
+            builder.add_signal_semaphore(&self.semaphore);
+            try!(builder.submit(&self.queue));
+
+            *self.released.lock().unwrap() = self.binds.apply();
+
+            // Only write `true` here in order to try again next time if we failed to submit.
+            *submitted = true;
+            Ok(())
+        }
+    }
+
+    #[inline]
+    unsafe fn signal_finished(&self) {
+        debug_assert!(*self.submitted.lock().unwrap());
+        self.finished.store(true, Ordering::SeqCst);
+        self.unlock();
+        self.previous.signal_finished();
+    }
+
+    #[inline]
+    fn queue_change_allowed(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<&Arc<Queue>> {
+        Some(&self.queue)
+    }
+
+    #[inline]
+    fn check_buffer_access(&self, buffer: &BufferAccess, exclusive: bool, queue: &Queue)
+                           -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>
+    {
+        self.previous.check_buffer_access(buffer, exclusive, queue).map(|_| None)
+    }
+
+    #[inline]
+    fn check_image_access(&self, image: &ImageAccess, range: &ImageSubresourceRange,
+                          exclusive: bool, queue: &Queue)
+                          -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>
+    {
+        // The operations on the image are ordered with the ones that come after thanks to the
+        // semaphore, and the image is locked for exclusive access until they have finished.
+        if image.inner().internal_object() == self.binds.image.image.internal_object() {
+            return Ok(None);
+        }
+
+        self.previous.check_image_access(image, range, exclusive, queue).map(|_| None)
+    }
+}
+
+impl<P, F, A> SparseImageBindFuture<P, F, A> where P: GpuFuture, A: MemoryPool {
+    // Releases the lock on the image taken by `execute_after`. Must only be called once the
+    // operations have finished.
+    fn unlock(&self) {
+        if self.locked {
+            let image = &self.binds.image;
+            image.gpu_locks.unlock(&image.whole_range());
+        }
+    }
+}
+
+unsafe impl<P, F, A> DeviceOwned for SparseImageBindFuture<P, F, A>
+    where P: GpuFuture, A: MemoryPool
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.queue.device()
+    }
+}
+
+impl<P, F, A> Drop for SparseImageBindFuture<P, F, A> where P: GpuFuture, A: MemoryPool {
+    fn drop(&mut self) {
+        unsafe {
+            if !*self.finished.get_mut() {
+                // Errors can't be returned from a destructor. If the submission failed, the
+                // memory bound to the image is left untouched, and if waiting failed, the device
+                // is lost and won't execute anything anymore.
+                let _ = self.flush();
+                // Block until the queue finished.
+                let _ = self.queue.wait();
+                self.unlock();
+                self.previous.signal_finished();
+            }
+        }
+    }
+}
+
+/// Error that can happen when executing binding operations on a `SparseImage`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SparseImageBindError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The image is in use by an operation that isn't part of the future the binding operations
+    /// are executed after.
+    ImageInUse,
+}
+
+impl error::Error for SparseImageBindError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            SparseImageBindError::OomError(_) => "not enough memory",
+            SparseImageBindError::ImageInUse => {
+                "the image is in use by an operation that isn't part of the previous future"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            SparseImageBindError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for SparseImageBindError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for SparseImageBindError {
+    #[inline]
+    fn from(err: OomError) -> SparseImageBindError {
+        SparseImageBindError::OomError(err)
+    }
+}
+
+unsafe impl<F, A> Image for Arc<SparseImage<F, A>>
+    where F: 'static + Send + Sync, A: MemoryPool
+{
+    type Access = Self;
+
+    #[inline]
+    fn access(self) -> Self {
+        self
+    }
+
+    #[inline]
+    fn format(&self) -> Format {
+        self.image.format()
+    }
+
+    #[inline]
+    fn samples(&self) -> u32 {
+        self.image.samples()
+    }
+
+    #[inline]
+    fn dimensions(&self) -> ImageDimensions {
+        self.image.dimensions()
+    }
+}
+
+unsafe impl<F, A> ImageView for Arc<SparseImage<F, A>>
+    where F: 'static + Send + Sync, A: MemoryPool
+{
+    type Access = Self;
+
+    #[inline]
+    fn access(self) -> Self {
+        self
+    }
+}
+
+unsafe impl<F, A> ImageAccess for SparseImage<F, A> where F: 'static + Send + Sync, A: MemoryPool {
+    #[inline]
+    fn inner(&self) -> &UnsafeImage {
+        &self.image
+    }
+
+    #[inline]
+    fn default_layout(&self) -> Layout {
+        Layout::General
+    }
+
+    #[inline]
+    fn conflict_key(&self, first_layer: u32, num_layers: u32, first_mipmap: u32, num_mipmaps: u32)
+                    -> u64
+    {
+        self.image.subresource_key(first_layer, num_layers, first_mipmap, num_mipmaps)
+    }
+
+    #[inline]
+    fn try_gpu_lock(&self, range: &ImageSubresourceRange, exclusive_access: bool, _: &Queue)
+                    -> bool
+    {
+        self.gpu_locks.try_lock(range, exclusive_access)
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self, range: &ImageSubresourceRange) {
+        self.gpu_locks.increase(range)
+    }
+}
+
+unsafe impl<F, A> ImageClearValue<F::ClearValue> for SparseImage<F, A>
+    where F: FormatDesc + 'static + Send + Sync, A: MemoryPool
+{
+    #[inline]
+    fn decode(&self, value: F::ClearValue) -> Option<ClearValue> {
+        Some(self.format.decode_clear_value(value))
+    }
+}
+
+unsafe impl<P, F, A> ImageContent<P> for SparseImage<F, A>
+    where F: 'static + Send + Sync, A: MemoryPool
+{
+    #[inline]
+    fn matches_format(&self) -> bool {
+        self.image.format().block_size() == Some(mem::size_of::<P>())
+    }
+}
+
+unsafe impl<F, A> ImageViewAccess for SparseImage<F, A>
+    where F: 'static + Send + Sync, A: MemoryPool
+{
+    #[inline]
+    fn parent(&self) -> &ImageAccess {
+        self
+    }
+
+    #[inline]
+    fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    #[inline]
+    fn inner(&self) -> &UnsafeImageView {
+        &self.view
+    }
+
+    #[inline]
+    fn descriptor_set_storage_image_layout(&self) -> Layout {
+        Layout::General
+    }
+
+    #[inline]
+    fn descriptor_set_combined_image_sampler_layout(&self) -> Layout {
+        Layout::General
+    }
+
+    #[inline]
+    fn descriptor_set_sampled_image_layout(&self) -> Layout {
+        Layout::General
+    }
+
+    #[inline]
+    fn descriptor_set_input_attachment_layout(&self) -> Layout {
+        Layout::General
+    }
+
+    #[inline]
+    fn identity_swizzle(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SparseBlock;
+    use super::SparseImage;
+    use super::SparseImageBindError;
+    use super::SparseImageBinds;
+    use format::Format;
+    use image::Dimensions;
+    use image::sys::ImageCreationError;
+    use image::traits::ImageContent;
+    use mock;
+    use sync::GpuFuture;
+
+    #[test]
+    fn missing_feature() {
+        let (device, queue) = gfx_dev_and_queue!(sparse_binding);
+        match SparseImage::new(&device, Dimensions::Dim2d { width: 256, height: 256 },
+                               Format::R8G8B8A8Unorm, 1, Some(queue.family()))
+        {
+            Err(ImageCreationError::SparseResidencyImage2dFeatureNotEnabled) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn one_dimensional() {
+        let (device, queue) = mock_dev_and_queue!(sparse_binding, sparse_residency_image2d);
+        match SparseImage::new(&device, Dimensions::Dim1d { width: 256 },
+                               Format::R8G8B8A8Unorm, 1, Some(queue.family()))
+        {
+            Err(ImageCreationError::UnsupportedDimensions { .. }) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn blocks_and_mip_tail() {
+        let (device, queue) = mock_dev_and_queue!(sparse_binding, sparse_residency_image2d);
+        let image = SparseImage::new(&device, Dimensions::Dim2d { width: 256, height: 128 },
+                                     Format::R8G8B8A8Unorm, 4, Some(queue.family())).unwrap();
+
+        // The mock uses blocks of 64x64 texels. The third level is 64x32, and isn't a multiple
+        // of the granularity anymore.
+        assert_eq!(image.granularity(), [64, 64, 1]);
+        assert_eq!(image.mip_tail_first_level(), 2);
+        assert_eq!(image.num_blocks(0), [4, 2, 1]);
+        assert_eq!(image.num_resident_blocks(), 0);
+    }
+
+    #[test]
+    fn matches_format() {
+        let (device, queue) = mock_dev_and_queue!(sparse_binding, sparse_residency_image2d);
+        let image = SparseImage::new(&device, Dimensions::Dim2d { width: 256, height: 128 },
+                                     Format::R8G8B8A8Unorm, 1, Some(queue.family())).unwrap();
+
+        assert!(ImageContent::<[u8; 4]>::matches_format(&*image));
+        assert!(!ImageContent::<u8>::matches_format(&*image));
+    }
+
+    #[test]
+    #[should_panic(expected = "The mipmap level is part of the mipmap tail")]
+    fn bind_block_in_mip_tail() {
+        let (device, queue) = mock_dev_and_queue!(sparse_binding, sparse_residency_image2d);
+        let image = SparseImage::new(&device, Dimensions::Dim2d { width: 256, height: 256 },
+                                     Format::R8G8B8A8Unorm, 9, Some(queue.family())).unwrap();
+        assert_eq!(image.mip_tail_first_level(), 3);
+
+        let mut binds = SparseImageBinds::new(image);
+        let _ = binds.bind(SparseBlock { mip_level: 3, array_layer: 0, x: 0, y: 0, z: 0 });
+    }
+
+    #[test]
+    #[should_panic(expected = "The coordinates of the block are out of range")]
+    fn bind_block_out_of_range() {
+        let (device, queue) = mock_dev_and_queue!(sparse_binding, sparse_residency_image2d);
+        let image = SparseImage::new(&device, Dimensions::Dim2d { width: 256, height: 256 },
+                                     Format::R8G8B8A8Unorm, 1, Some(queue.family())).unwrap();
+
+        let mut binds = SparseImageBinds::new(image);
+        let _ = binds.bind(SparseBlock { mip_level: 0, array_layer: 0, x: 4, y: 0, z: 0 });
+    }
+
+    #[test]
+    fn bind_and_unbind() {
+        let (device, queue) = mock_dev_and_queue!(sparse_binding, sparse_residency_image2d);
+        let image = SparseImage::new(&device, Dimensions::Dim2d { width: 256, height: 256 },
+                                     Format::R8G8B8A8Unorm, 9, Some(queue.family())).unwrap();
+        let block = SparseBlock { mip_level: 1, array_layer: 0, x: 1, y: 1, z: 0 };
+
+        let mut binds = SparseImageBinds::new(image.clone());
+        binds.bind(block).unwrap();
+        binds.bind_mip_tail(0).unwrap();
+        assert!(!image.is_resident(block));
+
+        let future = binds.execute(queue.clone()).unwrap();
+        assert!(!image.is_resident(block));
+        future.flush().unwrap();
+        drop(future);
+        assert!(image.is_resident(block));
+        assert!(image.is_mip_tail_resident(0));
+        assert_eq!(image.num_resident_blocks(), 1);
+        assert_eq!(mock::calls(&device).iter().filter(|&&c| c == "QueueBindSparse").count(), 1);
+
+        let mut binds = SparseImageBinds::new(image.clone());
+        binds.unbind(block);
+        let future = binds.execute(queue.clone()).unwrap();
+        future.flush().unwrap();
+        drop(future);
+        assert!(!image.is_resident(block));
+        assert_eq!(image.num_resident_blocks(), 0);
+    }
+
+    #[test]
+    fn image_in_use() {
+        let (device, queue) = mock_dev_and_queue!(sparse_binding, sparse_residency_image2d);
+        let image = SparseImage::new(&device, Dimensions::Dim2d { width: 256, height: 256 },
+                                     Format::R8G8B8A8Unorm, 1, Some(queue.family())).unwrap();
+        let block = SparseBlock { mip_level: 0, array_layer: 0, x: 0, y: 0, z: 0 };
+
+        let mut binds = SparseImageBinds::new(image.clone());
+        binds.bind(block).unwrap();
+        let future = binds.execute(queue.clone()).unwrap();
+
+        // The first operations haven't finished, so the memory of the block can't be unbound
+        // from another chain of futures.
+        let mut binds = SparseImageBinds::new(image.clone());
+        binds.unbind(block);
+        match binds.execute(queue.clone()) {
+            Err(SparseImageBindError::ImageInUse) => (),
+            _ => panic!()
+        }
+
+        // Unbinding after the first operations is allowed.
+        let mut binds = SparseImageBinds::new(image.clone());
+        binds.unbind(block);
+        let future = future.then_bind_sparse(queue.clone(), binds).unwrap();
+        future.flush().unwrap();
+        drop(future);
+        assert!(!image.is_resident(block));
+
+        // The image is unlocked once the operations have finished.
+        let mut binds = SparseImageBinds::new(image.clone());
+        binds.bind(block).unwrap();
+        binds.execute(queue.clone()).unwrap().flush().unwrap();
+    }
+}
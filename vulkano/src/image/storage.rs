@@ -9,10 +9,9 @@
 
 use std::iter::Empty;
 use std::sync::Arc;
-use std::sync::atomic::AtomicUsize;
-use std::sync::atomic::Ordering;
 use smallvec::SmallVec;
 
+use buffer::sys::SparseLevel;
 use device::Device;
 use device::Queue;
 use format::ClearValue;
@@ -21,6 +20,8 @@ use format::FormatTy;
 use format::Format;
 use image::Dimensions;
 use image::ImageDimensions;
+use image::ImageSubresourceRange;
+use image::SubresourceLocks;
 use image::sys::ImageCreationError;
 use image::sys::Layout;
 use image::sys::UnsafeImage;
@@ -33,9 +34,12 @@ use image::traits::ImageViewAccess;
 use image::traits::Image;
 use image::traits::ImageView;
 use instance::QueueFamily;
+use memory::DedicatedAlloc;
+use memory::MemoryUsage;
 use memory::pool::AllocLayout;
 use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
+use memory::pool::PotentialDedicatedAllocation;
 use memory::pool::StdMemoryPool;
 use sync::Sharing;
 
@@ -50,7 +54,7 @@ pub struct StorageImage<F, A = Arc<StdMemoryPool>> where A: MemoryPool {
     view: UnsafeImageView,
 
     // Memory used to back the image.
-    memory: A::Alloc,
+    memory: PotentialDedicatedAllocation<A::Alloc>,
 
     // Dimensions of the image view.
     dimensions: Dimensions,
@@ -61,16 +65,44 @@ pub struct StorageImage<F, A = Arc<StdMemoryPool>> where A: MemoryPool {
     // Queue families allowed to access this image.
     queue_families: SmallVec<[u32; 4]>,
 
-    // Number of times this image is locked on the GPU side.
-    gpu_lock: AtomicUsize,
+    // Ranges of subresources of this image that are locked on the GPU side.
+    gpu_locks: SubresourceLocks,
 }
 
 impl<F> StorageImage<F> {
     /// Creates a new image with the given dimensions and format.
+    ///
+    /// Cubemap images store their faces in consecutive array layers, in the order of the
+    /// `CubeFace` enum. Creating a cubemap array requires the `image_cube_array` feature.
+    ///
+    /// If the `khr_maintenance1` extension is enabled, 3D images are created 2D-array-compatible,
+    /// which means that you can create 2D views of their depth slices with `ImageView`, for
+    /// example in order to render to them.
+    #[inline]
     pub fn new<'a, I>(device: &Arc<Device>, dimensions: Dimensions, format: F, queue_families: I)
                       -> Result<Arc<StorageImage<F>>, ImageCreationError>
         where F: FormatDesc,
                  I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        StorageImage::new_with_pool(device, dimensions, format, queue_families,
+                                    &Device::standard_pool(device), MemoryUsage::GpuOnly)
+    }
+}
+
+impl<F, A> StorageImage<F, A> where A: MemoryPool {
+    /// Same as `new`, but allocates the memory of the image from `pool`, in a memory type chosen
+    /// according to `memory_usage`.
+    ///
+    /// `new` uses `MemoryUsage::GpuOnly`. The memory of the image is never mapped, even if the
+    /// chosen memory type is host-visible.
+    ///
+    /// Returns `UnsupportedMemoryUsage` if `memory_usage` requires host-visible memory and none
+    /// of the memory types that can hold the image is host-visible.
+    pub fn new_with_pool<'a, I>(device: &Arc<Device>, dimensions: Dimensions, format: F,
+                                queue_families: I, pool: &A, memory_usage: MemoryUsage)
+                                -> Result<Arc<StorageImage<F, A>>, ImageCreationError>
+        where F: FormatDesc,
+              I: IntoIterator<Item = QueueFamily<'a>>
     {
         let is_depth = match format.format().ty() {
             FormatTy::Depth => true,
@@ -91,6 +123,24 @@ impl<F> StorageImage<F> {
             transient_attachment: false,
         };
 
+        if let Dimensions::CubemapArray { .. } = dimensions {
+            if !device.enabled_features().image_cube_array {
+                return Err(ImageCreationError::ImageCubeArrayFeatureNotEnabled);
+            }
+        }
+
+        let image_dimensions = match dimensions.to_image_dimensions() {
+            ImageDimensions::Dim3d { width, height, depth, .. } => {
+                ImageDimensions::Dim3d {
+                    width: width,
+                    height: height,
+                    depth: depth,
+                    array_2d_compatible: device.loaded_extensions().khr_maintenance1,
+                }
+            },
+            dims => dims,
+        };
+
         let queue_families = queue_families.into_iter().map(|f| f.id())
                                            .collect::<SmallVec<[u32; 4]>>();
 
@@ -101,21 +151,21 @@ impl<F> StorageImage<F> {
                 Sharing::Exclusive
             };
 
-            try!(UnsafeImage::new(device, &usage, format.format(), dimensions.to_image_dimensions(),
-                                  1, 1, Sharing::Exclusive::<Empty<u32>>, false, false))
+            try!(UnsafeImage::new(device, &usage, format.format(), image_dimensions,
+                                  1, 1, Sharing::Exclusive::<Empty<u32>>, SparseLevel::none(),
+                                  false, false))
         };
 
-        let mem_ty = {
-            let device_local = device.physical_device().memory_types()
-                                     .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
-                                     .filter(|t| t.is_device_local());
-            let any = device.physical_device().memory_types()
-                            .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0);
-            device_local.chain(any).next().unwrap()
+        let mem_ty = match memory_usage.choose_memory_type(device.physical_device(),
+                                                           mem_reqs.memory_type_bits)
+        {
+            Some(ty) => ty,
+            None => return Err(ImageCreationError::UnsupportedMemoryUsage),
         };
 
-        let mem = try!(MemoryPool::alloc(&Device::standard_pool(device), mem_ty,
-                                         mem_reqs.size, mem_reqs.alignment, AllocLayout::Optimal));
+        let mem = try!(MemoryPool::alloc_from_requirements(pool, device, mem_ty, &mem_reqs,
+                                                           AllocLayout::Optimal,
+                                                           DedicatedAlloc::Image(&image)));
         debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
         unsafe { try!(image.bind_memory(mem.memory(), mem.offset())); }
 
@@ -131,7 +181,7 @@ impl<F> StorageImage<F> {
             dimensions: dimensions,
             format: format,
             queue_families: queue_families,
-            gpu_lock: AtomicUsize::new(0),
+            gpu_locks: SubresourceLocks::new(),
         }))
     }
 }
@@ -195,25 +245,22 @@ unsafe impl<F, A> ImageAccess for StorageImage<F, A> where F: 'static + Send + S
     }
 
     #[inline]
-    fn conflict_key(&self, _: u32, _: u32, _: u32, _: u32) -> u64 {
-        self.image.key()
+    fn conflict_key(&self, first_layer: u32, num_layers: u32, first_mipmap: u32, num_mipmaps: u32)
+                    -> u64
+    {
+        self.image.subresource_key(first_layer, num_layers, first_mipmap, num_mipmaps)
     }
 
     #[inline]
-    fn try_gpu_lock(&self, _: bool, _: &Queue) -> bool {
-        let val = self.gpu_lock.fetch_add(1, Ordering::SeqCst);
-        if val == 1 {
-            true
-        } else {
-            self.gpu_lock.fetch_sub(1, Ordering::SeqCst);
-            false
-        }
+    fn try_gpu_lock(&self, range: &ImageSubresourceRange, exclusive_access: bool, _: &Queue)
+                    -> bool
+    {
+        self.gpu_locks.try_lock(range, exclusive_access)
     }
 
     #[inline]
-    unsafe fn increase_gpu_lock(&self) {
-        let val = self.gpu_lock.fetch_add(1, Ordering::SeqCst);
-        debug_assert!(val >= 1);
+    unsafe fn increase_gpu_lock(&self, range: &ImageSubresourceRange) {
+        self.gpu_locks.increase(range)
     }
 }
 
@@ -282,8 +329,14 @@ unsafe impl<F, A> ImageViewAccess for StorageImage<F, A>
 #[cfg(test)]
 mod tests {
     use super::StorageImage;
+    use device::Device;
     use format::Format;
+    use image::CubeFace;
     use image::Dimensions;
+    use image::ImageAccess;
+    use image::ImageSubresourceRange;
+    use memory::MemoryUsage;
+    use memory::pool::MemoryPoolAlloc;
 
     #[test]
     fn create() {
@@ -291,4 +344,49 @@ mod tests {
         let _img = StorageImage::new(&device, Dimensions::Dim2d { width: 32, height: 32 },
                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
     }
+
+    #[test]
+    fn memory_usage() {
+        let (device, queue) = mock_dev_and_queue!();
+        let dims = Dimensions::Dim2d { width: 32, height: 32 };
+
+        let img = StorageImage::new(&device, dims, Format::R8G8B8A8Unorm,
+                                    Some(queue.family())).unwrap();
+        assert!(img.memory.memory().memory_type().is_device_local());
+
+        let img = StorageImage::new_with_pool(&device, dims, Format::R8G8B8A8Unorm,
+                                              Some(queue.family()),
+                                              &Device::standard_pool(&device),
+                                              MemoryUsage::GpuToCpu).unwrap();
+        assert!(img.memory.memory().memory_type().is_host_visible());
+    }
+
+    #[test]
+    fn lock_cubemap_faces() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let img = StorageImage::new(&device, Dimensions::Cubemap { size: 32 },
+                                    Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        let face = |f: CubeFace| {
+            let layer = f.array_layer(0);
+            ImageSubresourceRange::new(0 .. 1, layer .. layer + 1)
+        };
+
+        assert!(img.try_gpu_lock(&face(CubeFace::PositiveX), true, &queue));
+        assert!(img.try_gpu_lock(&face(CubeFace::NegativeZ), true, &queue));
+        assert!(!img.try_gpu_lock(&face(CubeFace::PositiveX), false, &queue));
+        assert!(!img.try_gpu_lock(&ImageSubresourceRange::whole_image(&img), true, &queue));
+    }
+
+    #[test]
+    fn conflict_key_per_subresource() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let img = StorageImage::new(&device, Dimensions::Cubemap { size: 32 },
+                                    Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        assert_eq!(img.conflict_key(2, 1, 0, 1), img.conflict_key(2, 1, 0, 1));
+        assert!(img.conflict_key(2, 1, 0, 1) != img.conflict_key(3, 1, 0, 1));
+        assert!(!img.conflicts_image(2, 1, 0, 1, &img, 3, 1, 0, 1));
+        assert!(img.conflicts_image(2, 1, 0, 1, &img, 0, 6, 0, 1));
+    }
 }
@@ -15,6 +15,7 @@ use format::Format;
 use format::FormatDesc;
 use image::ImageDimensions;
 use image::Dimensions;
+use image::ImageSubresourceRange;
 use image::ViewType;
 use image::traits::ImageAccess;
 use image::traits::ImageClearValue;
@@ -104,18 +105,20 @@ unsafe impl ImageAccess for SwapchainImage {
     }
 
     #[inline]
-    fn conflict_key(&self, _: u32, _: u32, _: u32, _: u32) -> u64 {
-        self.image.key()
+    fn conflict_key(&self, first_layer: u32, num_layers: u32, first_mipmap: u32, num_mipmaps: u32)
+                    -> u64
+    {
+        self.image.subresource_key(first_layer, num_layers, first_mipmap, num_mipmaps)
     }
 
     #[inline]
-    fn try_gpu_lock(&self, _: bool, _: &Queue) -> bool {
+    fn try_gpu_lock(&self, _: &ImageSubresourceRange, _: bool, _: &Queue) -> bool {
         // Swapchain image are only accessible after being acquired.
         false
     }
 
     #[inline]
-    unsafe fn increase_gpu_lock(&self) {
+    unsafe fn increase_gpu_lock(&self, _: &ImageSubresourceRange) {
     }
 }
 
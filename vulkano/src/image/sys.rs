@@ -13,19 +13,29 @@
 //! other image or image view types of this library, and all custom image or image view types
 //! that you create must wrap around the types in this module.
 
+use std::cmp;
+use std::collections::hash_map::DefaultHasher;
 use std::error;
 use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
 use std::mem;
 use std::ops::Range;
 use std::ptr;
 use std::sync::Arc;
 use smallvec::SmallVec;
 
+use buffer::sys::SparseLevel;
 use device::Device;
 use format::Format;
+use format::FormatFeatures;
 use format::FormatTy;
+use format::ImageFormatPropertiesError;
+use image::ComponentSwizzle;
+use image::ImageAspect;
 use image::ImageDimensions;
 use image::MipmapsCount;
+use image::Swizzle;
 use image::ViewType;
 use memory::DeviceMemory;
 use memory::MemoryRequirements;
@@ -61,9 +71,10 @@ pub struct UnsafeImage {
     dimensions: ImageDimensions,
     samples: u32,
     mipmaps: u32,
+    sparse: SparseLevel,
 
     // Features that are supported for this particular format.
-    format_features: vk::FormatFeatureFlagBits,
+    format_features: FormatFeatures,
 
     // `vkDestroyImage` is called only if `needs_destruction` is true.
     needs_destruction: bool,
@@ -77,11 +88,15 @@ impl UnsafeImage {
     /// - Panics if one of the dimensions is 0.
     /// - Panics if the number of mipmaps is 0.
     /// - Panics if the number of samples is 0.
+    /// - Panics if `sparse.sparse` is false and `sparse.sparse_residency` or
+    ///   `sparse.sparse_aliased` is true.
+    /// - Panics if `sparse.sparse_residency` is true and the image is one-dimensional or uses
+    ///   linear tiling.
     ///
     #[inline]
     pub unsafe fn new<'a, Mi, I>(device: &Arc<Device>, usage: &Usage, format: Format,
                                  dimensions: ImageDimensions, num_samples: u32, mipmaps: Mi,
-                                 sharing: Sharing<I>, linear_tiling: bool,
+                                 sharing: Sharing<I>, sparse: SparseLevel, linear_tiling: bool,
                                  preinitialized_layout: bool)
                                  -> Result<(UnsafeImage, MemoryRequirements), ImageCreationError>
         where Mi: Into<MipmapsCount>, I: Iterator<Item = u32>
@@ -92,60 +107,61 @@ impl UnsafeImage {
         };
 
         UnsafeImage::new_impl(device, usage, format, dimensions, num_samples, mipmaps.into(),
-                              sharing, linear_tiling, preinitialized_layout)
+                              sharing, sparse, linear_tiling, preinitialized_layout)
     }
 
     // Non-templated version to avoid inlining and improve compile times.
     unsafe fn new_impl(device: &Arc<Device>, usage: &Usage, format: Format,
                        dimensions: ImageDimensions, num_samples: u32, mipmaps: MipmapsCount,
                        (sh_mode, sh_indices): (vk::SharingMode, SmallVec<[u32; 8]>),
-                       linear_tiling: bool, preinitialized_layout: bool)
+                       sparse: SparseLevel, linear_tiling: bool, preinitialized_layout: bool)
                        -> Result<(UnsafeImage, MemoryRequirements), ImageCreationError>
     {
         // TODO: doesn't check that the proper features are enabled
 
         let vk = device.pointers();
-        let vk_i = device.instance().pointers();
 
         // Checking if image usage conforms to what is supported.
         let format_features = {
-            let physical_device = device.physical_device().internal_object();
-
-            let mut output = mem::uninitialized();
-            vk_i.GetPhysicalDeviceFormatProperties(physical_device, format as u32, &mut output);
-
+            let properties = format.properties(device.physical_device());
             let features = if linear_tiling {
-                output.linearTilingFeatures
+                properties.linear_tiling_features
             } else {
-                output.optimalTilingFeatures
+                properties.optimal_tiling_features
             };
 
-            if features == 0 {
+            if features == FormatFeatures::none() {
                 return Err(ImageCreationError::FormatNotSupported);
             }
 
-            if usage.sampled && (features & vk::FORMAT_FEATURE_SAMPLED_IMAGE_BIT == 0) {
-                return Err(ImageCreationError::UnsupportedUsage);
-            }
-            if usage.storage && (features & vk::FORMAT_FEATURE_STORAGE_IMAGE_BIT == 0) {
-                return Err(ImageCreationError::UnsupportedUsage);
-            }
-            if usage.color_attachment && (features & vk::FORMAT_FEATURE_COLOR_ATTACHMENT_BIT == 0) {
-                return Err(ImageCreationError::UnsupportedUsage);
-            }
-            if usage.depth_stencil_attachment && (features & vk::FORMAT_FEATURE_DEPTH_STENCIL_ATTACHMENT_BIT == 0) {
-                return Err(ImageCreationError::UnsupportedUsage);
-            }
-            if usage.input_attachment && (features & (vk::FORMAT_FEATURE_COLOR_ATTACHMENT_BIT | vk::FORMAT_FEATURE_DEPTH_STENCIL_ATTACHMENT_BIT) == 0) {
-                return Err(ImageCreationError::UnsupportedUsage);
-            }
-            if device.loaded_extensions().khr_maintenance1 {
-                if usage.transfer_source && (features & vk::FORMAT_FEATURE_TRANSFER_SRC_BIT_KHR == 0) {
-                    return Err(ImageCreationError::UnsupportedUsage);
-                }
-                if usage.transfer_dest && (features & vk::FORMAT_FEATURE_TRANSFER_DST_BIT_KHR == 0) {
-                    return Err(ImageCreationError::UnsupportedUsage);
-                }
+            // An input attachment can be either a color or a depth-stencil attachment, depending
+            // on the format.
+            let input_depth_stencil = match format.ty() {
+                FormatTy::Depth | FormatTy::Stencil | FormatTy::DepthStencil => true,
+                _ => false,
+            };
+
+            // The transfer features are only reported by implementations that support
+            // `khr_maintenance1`.
+            let maintenance1 = device.loaded_extensions().khr_maintenance1;
+
+            let required = FormatFeatures {
+                sampled_image: usage.sampled,
+                storage_image: usage.storage,
+                color_attachment: usage.color_attachment ||
+                                  (usage.input_attachment && !input_depth_stencil),
+                depth_stencil_attachment: usage.depth_stencil_attachment ||
+                                          (usage.input_attachment && input_depth_stencil),
+                transfer_src: usage.transfer_source && maintenance1,
+                transfer_dst: usage.transfer_dest && maintenance1,
+                .. FormatFeatures::none()
+            };
+
+            if !features.superset_of(&required) {
+                return Err(ImageCreationError::FormatNotSupportedForUsage {
+                    format: format,
+                    missing_features: required.difference(&features),
+                });
             }
 
             features
@@ -174,24 +190,18 @@ impl UnsafeImage {
         // check for additional image capabilities (section 31.4 of the specs).
         let mut capabilities_error = None;
 
-        // Compute the maximum number of mipmaps.
+        // Compute the maximum number of mipmaps, which is `floor(log2(max(w, h, d))) + 1`.
         // TODO: only compte if necessary?
         let max_mipmaps = {
-            let smallest_dim: u32 = match dimensions {
+            let largest_dim: u32 = match dimensions {
                 ImageDimensions::Dim1d { width, .. } => width,
-                ImageDimensions::Dim2d { width, height, .. } => {
-                    if width < height { width } else { height }
-                },
-                ImageDimensions::Dim3d { width, height, depth } => {
-                    if width < height {
-                        if depth < width { depth } else { width }
-                    } else {
-                        if depth < height { depth } else { height }
-                    }
+                ImageDimensions::Dim2d { width, height, .. } => cmp::max(width, height),
+                ImageDimensions::Dim3d { width, height, depth, .. } => {
+                    cmp::max(cmp::max(width, height), depth)
                 },
             };
 
-            32 - smallest_dim.leading_zeros()
+            32 - largest_dim.leading_zeros()
         };
 
         // Compute the number of mipmaps.
@@ -294,33 +304,85 @@ impl UnsafeImage {
             }
         }
 
+        // Checking sparse features.
+        assert!(sparse.sparse || !sparse.sparse_residency, "Can't enable sparse residency without \
+                                                            enabling sparse binding as well");
+        assert!(sparse.sparse || !sparse.sparse_aliased, "Can't enable sparse aliasing without \
+                                                          enabling sparse binding as well");
+        if sparse.sparse && !device.enabled_features().sparse_binding {
+            return Err(ImageCreationError::SparseBindingFeatureNotEnabled);
+        }
+        if sparse.sparse_residency {
+            assert!(!linear_tiling, "Can't enable sparse residency on an image with linear tiling");
+
+            match dimensions {
+                ImageDimensions::Dim1d { .. } => {
+                    panic!("Can't enable sparse residency on a one-dimensional image")
+                },
+                ImageDimensions::Dim2d { .. } => {
+                    if !device.enabled_features().sparse_residency_image2d {
+                        return Err(ImageCreationError::SparseResidencyImage2dFeatureNotEnabled);
+                    }
+                },
+                ImageDimensions::Dim3d { .. } => {
+                    if !device.enabled_features().sparse_residency_image3d {
+                        return Err(ImageCreationError::SparseResidencyImage3dFeatureNotEnabled);
+                    }
+                },
+            }
+
+            let samples_feature = match num_samples {
+                1 => true,
+                2 => device.enabled_features().sparse_residency2_samples,
+                4 => device.enabled_features().sparse_residency4_samples,
+                8 => device.enabled_features().sparse_residency8_samples,
+                16 => device.enabled_features().sparse_residency16_samples,
+                _ => false,
+            };
+            if !samples_feature {
+                return Err(ImageCreationError::SparseResidencySamplesFeatureNotEnabled {
+                    samples: num_samples
+                });
+            }
+        }
+        if sparse.sparse_aliased && !device.enabled_features().sparse_residency_aliased {
+            return Err(ImageCreationError::SparseResidencyAliasedFeatureNotEnabled);
+        }
+
         // Decoding the dimensions.
-        let (ty, extent, array_layers, flags) = match dimensions {
+        let mut flags = ImageCreateFlags::none();
+        let (ty, extent, array_layers) = match dimensions {
             ImageDimensions::Dim1d { width, array_layers } => {
                 if width == 0 || array_layers == 0 {
                     return Err(ImageCreationError::UnsupportedDimensions { dimensions: dimensions });
                 }
                 let extent = vk::Extent3D { width: width, height: 1, depth: 1 };
-                (vk::IMAGE_TYPE_1D, extent, array_layers, 0)
+                (ImageType::Dim1d, extent, array_layers)
             },
             ImageDimensions::Dim2d { width, height, array_layers, cubemap_compatible } => {
                 if width == 0 || height == 0 || array_layers == 0 {
                     return Err(ImageCreationError::UnsupportedDimensions { dimensions: dimensions });
                 }
-                if cubemap_compatible && width != height {
+                if cubemap_compatible && (width != height || array_layers < 6) {
                     return Err(ImageCreationError::UnsupportedDimensions { dimensions: dimensions });
                 }
                 let extent = vk::Extent3D { width: width, height: height, depth: 1 };
-                let flags = if cubemap_compatible { vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT }
-                            else { 0 };
-                (vk::IMAGE_TYPE_2D, extent, array_layers, flags)
+                flags.cube_compatible = cubemap_compatible;
+                (ImageType::Dim2d, extent, array_layers)
             },
-            ImageDimensions::Dim3d { width, height, depth } => {
+            ImageDimensions::Dim3d { width, height, depth, array_2d_compatible } => {
                 if width == 0 || height == 0 || depth == 0 {
                     return Err(ImageCreationError::UnsupportedDimensions { dimensions: dimensions });
                 }
                 let extent = vk::Extent3D { width: width, height: height, depth: depth };
-                (vk::IMAGE_TYPE_3D, extent, 1, 0)
+                if array_2d_compatible {
+                    if !device.loaded_extensions().khr_maintenance1 {
+                        return Err(ImageCreationError::Maintenance1ExtensionNotEnabled);
+                    }
+                    assert!(!sparse.sparse, "Can't make a sparse 3D image 2D-array-compatible");
+                    flags.array_2d_compatible = true;
+                }
+                (ImageType::Dim3d, extent, 1)
             },
         };
 
@@ -330,20 +392,20 @@ impl UnsafeImage {
             capabilities_error = Some(err);
         }
         match ty {
-            vk::IMAGE_TYPE_1D => {
+            ImageType::Dim1d => {
                 if extent.width > device.physical_device().limits().max_image_dimension_1d() {
                     let err = ImageCreationError::UnsupportedDimensions { dimensions: dimensions };
                     capabilities_error = Some(err);
                 }
             },
-            vk::IMAGE_TYPE_2D => {
+            ImageType::Dim2d => {
                 let limit = device.physical_device().limits().max_image_dimension_2d();
                 if extent.width > limit || extent.height > limit {
                     let err = ImageCreationError::UnsupportedDimensions { dimensions: dimensions };
                     capabilities_error = Some(err);
                 }
 
-                if (flags & vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT) != 0 {
+                if flags.cube_compatible {
                     let limit = device.physical_device().limits().max_image_dimension_cube();
                     debug_assert_eq!(extent.width, extent.height);      // checked above
                     if extent.width > limit {
@@ -352,64 +414,61 @@ impl UnsafeImage {
                     }
                 }
             },
-            vk::IMAGE_TYPE_3D => {
+            ImageType::Dim3d => {
                 let limit = device.physical_device().limits().max_image_dimension_3d();
                 if extent.width > limit || extent.height > limit || extent.depth > limit {
                     let err = ImageCreationError::UnsupportedDimensions { dimensions: dimensions };
                     capabilities_error = Some(err);
                 }
             },
-            _ => unreachable!()
         };
 
-        let usage = usage.to_usage_bits();
+        flags.sparse_binding = sparse.sparse;
+        flags.sparse_residency = sparse.sparse_residency;
+        flags.sparse_aliased = sparse.sparse_aliased;
+
+        let tiling = if linear_tiling {
+            ImageTiling::Linear
+        } else {
+            ImageTiling::Optimal
+        };
 
         // Now that all checks have been performed, if any of the check failed we query the Vulkan
         // implementation for additional image capabilities.
         if let Some(capabilities_error) = capabilities_error {
-            let tiling = if linear_tiling {
-                vk::IMAGE_TILING_LINEAR
-            } else {
-                vk::IMAGE_TILING_OPTIMAL
+            let props = match format.image_format_properties(device.physical_device(), ty, tiling,
+                                                             usage, &flags)
+            {
+                Ok(props) => props,
+                Err(ImageFormatPropertiesError::FormatNotSupported) => {
+                    return Err(ImageCreationError::FormatNotSupported);
+                },
+                Err(ImageFormatPropertiesError::OomError(err)) => return Err(err.into()),
             };
 
-            let mut output = mem::uninitialized();
-            let physical_device = device.physical_device().internal_object();
-            let r = vk_i.GetPhysicalDeviceImageFormatProperties(physical_device, format as u32, ty,
-                                                                tiling, usage, 0 /* TODO */,
-                                                                &mut output);
-
-            match check_errors(r) {
-                Ok(_) => (),
-                Err(Error::FormatNotSupported) => return Err(ImageCreationError::FormatNotSupported),
-                Err(err) => return Err(err.into()),
-            }
-
-            if extent.width > output.maxExtent.width || extent.height > output.maxExtent.height ||
-               extent.depth > output.maxExtent.depth || mipmaps > output.maxMipLevels ||
-               array_layers > output.maxArrayLayers || (num_samples & output.sampleCounts) == 0
+            if extent.width > props.max_extent[0] || extent.height > props.max_extent[1] ||
+               extent.depth > props.max_extent[2] || mipmaps > props.max_mipmaps ||
+               array_layers > props.max_array_layers || !props.supports_samples(num_samples)
             {
                 return Err(capabilities_error);
             }
         }
 
+        let usage = usage.to_usage_bits();
+
         // Everything now ok. Creating the image.
         let image = {
             let infos = vk::ImageCreateInfo {
                 sType: vk::STRUCTURE_TYPE_IMAGE_CREATE_INFO,
                 pNext: ptr::null(),
-                flags: flags,
-                imageType: ty,
+                flags: flags.to_bits(),
+                imageType: ty as u32,
                 format: format as u32,
                 extent: extent,
                 mipLevels: mipmaps,
                 arrayLayers: array_layers,
                 samples: num_samples,
-                tiling: if linear_tiling {
-                    vk::IMAGE_TILING_LINEAR
-                } else {
-                    vk::IMAGE_TILING_OPTIMAL
-                },
+                tiling: tiling as u32,
                 usage: usage,
                 sharingMode: sh_mode,
                 queueFamilyIndexCount: sh_indices.len() as u32,
@@ -427,11 +486,45 @@ impl UnsafeImage {
             output
         };
 
-        let mem_reqs: vk::MemoryRequirements = {
-            let mut output = mem::uninitialized();
-            vk.GetImageMemoryRequirements(device.internal_object(), image, &mut output);
-            debug_assert!(output.memoryTypeBits != 0);
-            output
+        let mem_reqs: MemoryRequirements = {
+            if device.loaded_extensions().khr_get_memory_requirements2 {
+                let infos = vk::ImageMemoryRequirementsInfo2KHR {
+                    sType: vk::STRUCTURE_TYPE_IMAGE_MEMORY_REQUIREMENTS_INFO_2_KHR,
+                    pNext: ptr::null(),
+                    image: image,
+                };
+
+                let mut dedicated = vk::MemoryDedicatedRequirementsKHR {
+                    sType: vk::STRUCTURE_TYPE_MEMORY_DEDICATED_REQUIREMENTS_KHR,
+                    pNext: ptr::null_mut(),
+                    prefersDedicatedAllocation: vk::FALSE,
+                    requiresDedicatedAllocation: vk::FALSE,
+                };
+
+                let mut output = vk::MemoryRequirements2KHR {
+                    sType: vk::STRUCTURE_TYPE_MEMORY_REQUIREMENTS_2_KHR,
+                    pNext: if device.loaded_extensions().khr_dedicated_allocation {
+                        &mut dedicated as *mut _ as *mut _
+                    } else {
+                        ptr::null_mut()
+                    },
+                    memoryRequirements: mem::uninitialized(),
+                };
+
+                vk.GetImageMemoryRequirements2KHR(device.internal_object(), &infos, &mut output);
+                debug_assert!(output.memoryRequirements.memoryTypeBits != 0);
+
+                let mut output: MemoryRequirements = output.memoryRequirements.into();
+                output.prefers_dedicated = dedicated.prefersDedicatedAllocation != vk::FALSE;
+                output.requires_dedicated = dedicated.requiresDedicatedAllocation != vk::FALSE;
+                output
+
+            } else {
+                let mut output: vk::MemoryRequirements = mem::uninitialized();
+                vk.GetImageMemoryRequirements(device.internal_object(), image, &mut output);
+                debug_assert!(output.memoryTypeBits != 0);
+                output.into()
+            }
         };
 
         let image = UnsafeImage {
@@ -442,11 +535,12 @@ impl UnsafeImage {
             dimensions: dimensions,
             samples: num_samples,
             mipmaps: mipmaps,
+            sparse: sparse,
             format_features: format_features,
             needs_destruction: true,
         };
 
-        Ok((image, mem_reqs.into()))
+        Ok((image, mem_reqs))
     }
 
     /// Creates an image from a raw handle. The image won't be destroyed.
@@ -456,13 +550,9 @@ impl UnsafeImage {
                            dimensions: ImageDimensions, samples: u32, mipmaps: u32)
                            -> UnsafeImage
     {
-        let vk_i = device.instance().pointers();
-        let physical_device = device.physical_device().internal_object();
+        let properties = format.properties(device.physical_device());
 
-        let mut output = mem::uninitialized();
-        vk_i.GetPhysicalDeviceFormatProperties(physical_device, format as u32, &mut output);
-
-        // TODO: check that usage is correct in regard to `output`?
+        // TODO: check that usage is correct in regard to `properties`?
 
         UnsafeImage {
             device: device.clone(),
@@ -472,7 +562,8 @@ impl UnsafeImage {
             dimensions: dimensions,
             samples: samples,
             mipmaps: mipmaps,
-            format_features: output.optimalTilingFeatures,
+            sparse: SparseLevel::none(),
+            format_features: properties.optimal_tiling_features,
             needs_destruction: false,       // TODO: pass as parameter
         }
     }
@@ -482,6 +573,9 @@ impl UnsafeImage {
     {
         let vk = self.device.pointers();
 
+        // Sparse images are bound with a bind sparse operation on a queue instead.
+        debug_assert!(!self.sparse.sparse);
+
         // We check for correctness in debug mode.
         debug_assert!({
             let mut mem_reqs = mem::uninitialized();
@@ -522,12 +616,68 @@ impl UnsafeImage {
         self.samples
     }
 
+    /// Returns the sparse features that the image was created with.
+    #[inline]
+    pub fn sparse_level(&self) -> SparseLevel {
+        self.sparse
+    }
+
+    /// Queries the sparse memory requirements of the image, one entry per group of aspects of
+    /// the format that share the same requirements.
+    ///
+    /// Returns an empty list if the image wasn't created with sparse residency.
+    pub fn sparse_memory_requirements(&self) -> Vec<SparseImageMemoryRequirements> {
+        unsafe {
+            let vk = self.device.pointers();
+
+            let mut num = 0;
+            vk.GetImageSparseMemoryRequirements(self.device.internal_object(), self.image,
+                                                &mut num, ptr::null_mut());
+
+            let mut output = Vec::with_capacity(num as usize);
+            vk.GetImageSparseMemoryRequirements(self.device.internal_object(), self.image,
+                                                &mut num, output.as_mut_ptr());
+            output.set_len(num as usize);
+
+            output.into_iter().map(|req: vk::SparseImageMemoryRequirements| {
+                let props = req.formatProperties;
+                SparseImageMemoryRequirements {
+                    color: (props.aspectMask & vk::IMAGE_ASPECT_COLOR_BIT) != 0,
+                    depth: (props.aspectMask & vk::IMAGE_ASPECT_DEPTH_BIT) != 0,
+                    stencil: (props.aspectMask & vk::IMAGE_ASPECT_STENCIL_BIT) != 0,
+                    metadata: (props.aspectMask & vk::IMAGE_ASPECT_METADATA_BIT) != 0,
+                    granularity: [props.imageGranularity.width, props.imageGranularity.height,
+                                  props.imageGranularity.depth],
+                    single_mip_tail: (props.flags & vk::SPARSE_IMAGE_FORMAT_SINGLE_MIPTAIL_BIT) != 0,
+                    aligned_mip_size: (props.flags & vk::SPARSE_IMAGE_FORMAT_ALIGNED_MIP_SIZE_BIT) != 0,
+                    nonstandard_block_size: (props.flags &
+                                             vk::SPARSE_IMAGE_FORMAT_NONSTANDARD_BLOCK_SIZE_BIT) != 0,
+                    mip_tail_first_lod: req.imageMipTailFirstLod,
+                    mip_tail_size: req.imageMipTailSize as usize,
+                    mip_tail_offset: req.imageMipTailOffset as usize,
+                    mip_tail_stride: req.imageMipTailStride as usize,
+                }
+            }).collect()
+        }
+    }
+
     /// Returns a key unique to each `UnsafeImage`. Can be used for the `conflicts_key` method.
     #[inline]
     pub fn key(&self) -> u64 {
         self.image
     }
 
+    /// Returns a key unique to each range of subresources of each `UnsafeImage`. Can be used for
+    /// the `conflict_key` method.
+    #[inline]
+    pub fn subresource_key(&self, first_layer: u32, num_layers: u32, first_mipmap: u32,
+                           num_mipmaps: u32) -> u64
+    {
+        let mut hasher = DefaultHasher::new();
+        (self.image, first_layer, num_layers, first_mipmap, num_mipmaps).hash(&mut hasher);
+        hasher.finish()
+    }
+
     /// Queries the layout of an image in memory. Only valid for images with linear tiling.
     ///
     /// This function is only valid for images with a color format. See the other similar functions
@@ -615,19 +765,19 @@ impl UnsafeImage {
     /// Returns true if the image can be used as a source for blits.
     #[inline]
     pub fn supports_blit_source(&self) -> bool {
-        (self.format_features & vk::FORMAT_FEATURE_BLIT_SRC_BIT) != 0
+        self.format_features.blit_src
     }
 
     /// Returns true if the image can be used as a destination for blits.
     #[inline]
     pub fn supports_blit_destination(&self) -> bool {
-        (self.format_features & vk::FORMAT_FEATURE_BLIT_DST_BIT) != 0
+        self.format_features.blit_dst
     }
 
     /// Returns true if the image can be sampled with a linear filtering.
     #[inline]
     pub fn supports_linear_filtering(&self) -> bool {
-        (self.format_features & vk::FORMAT_FEATURE_SAMPLED_IMAGE_FILTER_LINEAR_BIT) != 0
+        self.format_features.sampled_image_filter_linear
     }
 
     #[inline]
@@ -707,10 +857,47 @@ pub enum ImageCreationError {
     UnsupportedDimensions { dimensions: ImageDimensions },
     /// The requested format is not supported by the Vulkan implementation.
     FormatNotSupported,
-    /// The format is supported, but at least one of the requested usages is not supported.
+    /// The requested combination of usages is not allowed. For example `transient_attachment`
+    /// was combined with a usage that isn't an attachment.
     UnsupportedUsage,
+    /// The format doesn't support some of the features required by the requested usage.
+    FormatNotSupportedForUsage {
+        /// The format of the image.
+        format: Format,
+        /// The features that the usage requires but that the format doesn't support.
+        missing_features: FormatFeatures,
+    },
     /// The `shader_storage_image_multisample` feature must be enabled to create such an image.
     ShaderStorageImageMultisampleFeatureNotEnabled,
+    /// The `sparse_binding` feature must be enabled to create such an image.
+    SparseBindingFeatureNotEnabled,
+    /// The `sparse_residency_image2d` feature must be enabled to create such an image.
+    SparseResidencyImage2dFeatureNotEnabled,
+    /// The `sparse_residency_image3d` feature must be enabled to create such an image.
+    SparseResidencyImage3dFeatureNotEnabled,
+    /// The `sparse_residency*_samples` feature that corresponds to the number of samples must be
+    /// enabled to create such an image.
+    SparseResidencySamplesFeatureNotEnabled { samples: u32 },
+    /// The `sparse_residency_aliased` feature must be enabled to create such an image.
+    SparseResidencyAliasedFeatureNotEnabled,
+    /// The `khr_maintenance1` extension must be enabled to create a 2D-array-compatible 3D image.
+    Maintenance1ExtensionNotEnabled,
+    /// The `image_cube_array` feature must be enabled to create a cubemap array image.
+    ImageCubeArrayFeatureNotEnabled,
+    /// None of the memory types that can hold the image can be used with the requested memory
+    /// usage.
+    UnsupportedMemoryUsage,
+    /// The buffer that holds the initial content of the image is missing the transfer source
+    /// usage.
+    SourceMissingTransferUsage,
+    /// The buffer that holds the initial content of the image is too small for the image.
+    SourceTooSmall,
+    /// The type of the content of the buffer that holds the initial content of the image doesn't
+    /// match the format of the image.
+    WrongSourceContentFormat,
+    /// The offset of the buffer that holds the initial content of the image isn't a multiple of
+    /// 4 or of the size of a texel.
+    UnalignedSourceOffset,
 }
 
 impl error::Error for ImageCreationError {
@@ -726,12 +913,57 @@ impl error::Error for ImageCreationError {
                                                                  one of the dimensions is 0",
             ImageCreationError::FormatNotSupported => "the requested format is not supported by \
                                                        the Vulkan implementation",
-            ImageCreationError::UnsupportedUsage => "the format is supported, but at least one \
-                                                     of the requested usages is not supported",
+            ImageCreationError::UnsupportedUsage => "the requested combination of usages is \
+                                                     not allowed",
+            ImageCreationError::FormatNotSupportedForUsage { .. } => {
+                "the format doesn't support some of the features required by the requested usage"
+            },
             ImageCreationError::ShaderStorageImageMultisampleFeatureNotEnabled => {
                 "the `shader_storage_image_multisample` feature must be enabled to create such \
                  an image"
             },
+            ImageCreationError::SparseBindingFeatureNotEnabled => {
+                "the `sparse_binding` feature must be enabled to create such an image"
+            },
+            ImageCreationError::SparseResidencyImage2dFeatureNotEnabled => {
+                "the `sparse_residency_image2d` feature must be enabled to create such an image"
+            },
+            ImageCreationError::SparseResidencyImage3dFeatureNotEnabled => {
+                "the `sparse_residency_image3d` feature must be enabled to create such an image"
+            },
+            ImageCreationError::SparseResidencySamplesFeatureNotEnabled { .. } => {
+                "the `sparse_residency*_samples` feature that corresponds to the number of \
+                 samples must be enabled to create such an image"
+            },
+            ImageCreationError::SparseResidencyAliasedFeatureNotEnabled => {
+                "the `sparse_residency_aliased` feature must be enabled to create such an image"
+            },
+            ImageCreationError::Maintenance1ExtensionNotEnabled => {
+                "the `khr_maintenance1` extension must be enabled to create a \
+                 2D-array-compatible 3D image"
+            },
+            ImageCreationError::ImageCubeArrayFeatureNotEnabled => {
+                "the `image_cube_array` feature must be enabled to create a cubemap array image"
+            },
+            ImageCreationError::UnsupportedMemoryUsage => {
+                "none of the memory types that can hold the image can be used with the requested \
+                 memory usage"
+            },
+            ImageCreationError::SourceMissingTransferUsage => {
+                "the buffer that holds the initial content of the image is missing the transfer \
+                 source usage"
+            },
+            ImageCreationError::SourceTooSmall => {
+                "the buffer that holds the initial content of the image is too small for the image"
+            },
+            ImageCreationError::WrongSourceContentFormat => {
+                "the type of the content of the buffer that holds the initial content of the image \
+                 doesn't match the format of the image"
+            },
+            ImageCreationError::UnalignedSourceOffset => {
+                "the offset of the buffer that holds the initial content of the image isn't a \
+                 multiple of 4 or of the size of a texel"
+            },
         }
     }
 
@@ -793,6 +1025,43 @@ pub struct LinearLayout {
     pub depth_pitch: usize,
 }
 
+/// Describes the sparse memory requirements of some aspects of an image.
+///
+/// Obtained by calling `sparse_memory_requirements` on the image.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SparseImageMemoryRequirements {
+    /// True if these requirements apply to the color aspect.
+    pub color: bool,
+    /// True if these requirements apply to the depth aspect.
+    pub depth: bool,
+    /// True if these requirements apply to the stencil aspect.
+    pub stencil: bool,
+    /// True if these requirements apply to the metadata of the image. The metadata is always
+    /// bound through the opaque memory region of the image.
+    pub metadata: bool,
+    /// Width, height and depth in texels of a sparse block. Each sparse block is bound to one
+    /// memory range whose size is the alignment of the memory requirements of the image.
+    pub granularity: [u32; 3],
+    /// True if the mipmap tail is shared between all the array layers.
+    pub single_mip_tail: bool,
+    /// True if the first mipmap level of the mipmap tail is the first level whose dimensions
+    /// are not a multiple of the granularity.
+    pub aligned_mip_size: bool,
+    /// True if the granularity isn't the standard block size of the format.
+    pub nonstandard_block_size: bool,
+    /// First mipmap level that is part of the mipmap tail. Mipmap levels from this one can only
+    /// be bound through the opaque memory region of the image.
+    pub mip_tail_first_lod: u32,
+    /// Size in bytes of the mipmap tail of one array layer, or of the whole image if
+    /// `single_mip_tail` is true.
+    pub mip_tail_size: usize,
+    /// Offset of the mipmap tail of the first array layer in the opaque memory region.
+    pub mip_tail_offset: usize,
+    /// Number of bytes between the mipmap tails of two consecutive array layers in the opaque
+    /// memory region. Meaningless if `single_mip_tail` is true.
+    pub mip_tail_stride: usize,
+}
+
 #[derive(Debug)]
 pub struct UnsafeImageView {
     view: vk::ImageView,
@@ -800,19 +1069,58 @@ pub struct UnsafeImageView {
     usage: vk::ImageUsageFlagBits,
     identity_swizzle: bool,
     format: Format,
+    mipmap_levels: u32,
 }
 
 impl UnsafeImageView {
     /// See the docs of new().
+    #[inline]
     pub unsafe fn raw(image: &UnsafeImage, ty: ViewType, mipmap_levels: Range<u32>,
                       array_layers: Range<u32>) -> Result<UnsafeImageView, OomError>
+    {
+        UnsafeImageView::raw_with_options(image, ty, mipmap_levels, array_layers, None,
+                                          Swizzle::default())
+    }
+
+    /// Same as `raw()`, but also lets you choose the aspect of the image that the view covers and
+    /// the component swizzling of the view.
+    ///
+    /// If `aspect` is `None`, the view covers all the aspects of the image.
+    ///
+    /// # Panic
+    ///
+    /// Same as `new()`, plus:
+    ///
+    /// - Panics if the image doesn't have the requested aspect.
+    ///
+    pub unsafe fn raw_with_options(image: &UnsafeImage, ty: ViewType, mipmap_levels: Range<u32>,
+                                   array_layers: Range<u32>, aspect: Option<ImageAspect>,
+                                   swizzle: Swizzle) -> Result<UnsafeImageView, OomError>
     {
         let vk = image.device.pointers();
 
         assert!(mipmap_levels.end > mipmap_levels.start);
         assert!(mipmap_levels.end <= image.mipmaps);
         assert!(array_layers.end > array_layers.start);
-        assert!(array_layers.end <= image.dimensions.array_layers());
+
+        // The array layers of a 2D view of a 3D image are the depth slices of a single mipmap
+        // level.
+        let view_of_3d_slices = match (image.dimensions, ty) {
+            (ImageDimensions::Dim3d { array_2d_compatible: true, .. }, ViewType::Dim2d) |
+            (ImageDimensions::Dim3d { array_2d_compatible: true, .. }, ViewType::Dim2dArray) => {
+                true
+            },
+            _ => false,
+        };
+
+        if view_of_3d_slices {
+            assert_eq!(mipmap_levels.end - mipmap_levels.start, 1);
+            let depth = image.dimensions.mipmap_width_height_depth(mipmap_levels.start)
+                             .unwrap()[2];
+            assert!(array_layers.end <= depth);
+        } else {
+            assert!(array_layers.end <= image.dimensions.array_layers());
+        }
 
         let aspect_mask = match image.format.ty() {
             FormatTy::Float | FormatTy::Uint | FormatTy::Sint | FormatTy::Compressed => {
@@ -823,6 +1131,14 @@ impl UnsafeImageView {
             FormatTy::DepthStencil => vk::IMAGE_ASPECT_DEPTH_BIT | vk::IMAGE_ASPECT_STENCIL_BIT,
         };
 
+        let aspect_mask = match aspect {
+            Some(aspect) => {
+                assert!(aspect_mask & aspect.to_aspect_bits() != 0);
+                aspect.to_aspect_bits()
+            },
+            None => aspect_mask,
+        };
+
         let view_type = match (image.dimensions(), ty, array_layers.end - array_layers.start) {
             (ImageDimensions::Dim1d { .. }, ViewType::Dim1d, 1) => vk::IMAGE_VIEW_TYPE_1D,
             (ImageDimensions::Dim1d { .. }, ViewType::Dim1dArray, _) => vk::IMAGE_VIEW_TYPE_1D_ARRAY,
@@ -837,6 +1153,12 @@ impl UnsafeImageView {
                 vk::IMAGE_VIEW_TYPE_CUBE_ARRAY
             },
             (ImageDimensions::Dim3d { .. }, ViewType::Dim3d, _) => vk::IMAGE_VIEW_TYPE_3D,
+            (ImageDimensions::Dim3d { .. }, ViewType::Dim2d, 1) if view_of_3d_slices => {
+                vk::IMAGE_VIEW_TYPE_2D
+            },
+            (ImageDimensions::Dim3d { .. }, ViewType::Dim2dArray, _) if view_of_3d_slices => {
+                vk::IMAGE_VIEW_TYPE_2D_ARRAY
+            },
             _ => panic!()
         };
 
@@ -848,7 +1170,12 @@ impl UnsafeImageView {
                 image: image.internal_object(),
                 viewType: view_type,
                 format: image.format as u32,
-                components: vk::ComponentMapping { r: 0, g: 0, b: 0, a: 0 },     // FIXME:
+                components: vk::ComponentMapping {
+                    r: component_swizzle(swizzle.r),
+                    g: component_swizzle(swizzle.g),
+                    b: component_swizzle(swizzle.b),
+                    a: component_swizzle(swizzle.a),
+                },
                 subresourceRange: vk::ImageSubresourceRange {
                     aspectMask: aspect_mask,
                     baseMipLevel: mipmap_levels.start,
@@ -868,8 +1195,9 @@ impl UnsafeImageView {
             view: view,
             device: image.device.clone(),
             usage: image.usage,
-            identity_swizzle: true,     // FIXME:
+            identity_swizzle: swizzle.is_identity(),
             format: image.format,
+            mipmap_levels: mipmap_levels.end - mipmap_levels.start,
         })
     }
 
@@ -882,7 +1210,9 @@ impl UnsafeImageView {
     ///
     /// - Panics if `mipmap_levels` or `array_layers` is out of range of the image.
     /// - Panics if the view types doesn't match the dimensions of the image (for example a 2D
-    ///   view from a 3D image).
+    ///   view from a 3D image that isn't 2D-array-compatible).
+    /// - Panics if trying to create a 2D or 2D array view of a 3D image with more than one
+    ///   mipmap level, or with array layers out of range of the depth of the mipmap level.
     /// - Panics if trying to create a cubemap with a number of array layers different from 6.
     /// - Panics if trying to create a cubemap array with a number of array layers not a multiple
     ///   of 6.
@@ -900,6 +1230,18 @@ impl UnsafeImageView {
         self.format
     }
 
+    /// Returns true if the view doesn't use components swizzling.
+    #[inline]
+    pub fn identity_swizzle(&self) -> bool {
+        self.identity_swizzle
+    }
+
+    /// Returns the number of mipmap levels covered by the view.
+    #[inline]
+    pub fn mipmap_levels(&self) -> u32 {
+        self.mipmap_levels
+    }
+
     #[inline]
     pub fn usage_transfer_src(&self) -> bool {
         (self.usage & vk::IMAGE_USAGE_TRANSFER_SRC_BIT) != 0
@@ -960,6 +1302,20 @@ impl Drop for UnsafeImageView {
     }
 }
 
+// Turns a `ComponentSwizzle` into its Vulkan equivalent.
+#[inline]
+fn component_swizzle(swizzle: ComponentSwizzle) -> vk::ComponentSwizzle {
+    match swizzle {
+        ComponentSwizzle::Identity => vk::COMPONENT_SWIZZLE_IDENTITY,
+        ComponentSwizzle::Zero => vk::COMPONENT_SWIZZLE_ZERO,
+        ComponentSwizzle::One => vk::COMPONENT_SWIZZLE_ONE,
+        ComponentSwizzle::Red => vk::COMPONENT_SWIZZLE_R,
+        ComponentSwizzle::Green => vk::COMPONENT_SWIZZLE_G,
+        ComponentSwizzle::Blue => vk::COMPONENT_SWIZZLE_B,
+        ComponentSwizzle::Alpha => vk::COMPONENT_SWIZZLE_A,
+    }
+}
+
 /// Describes how an image is going to be used. This is **not** an optimization.
 ///
 /// If you try to use an image in a way that you didn't declare, a panic will happen.
@@ -1074,6 +1430,70 @@ impl Usage {
     }
 }
 
+/// Type of an image, regardless of its dimensions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum ImageType {
+    Dim1d = vk::IMAGE_TYPE_1D,
+    Dim2d = vk::IMAGE_TYPE_2D,
+    Dim3d = vk::IMAGE_TYPE_3D,
+}
+
+/// Arrangement of the texels of an image in memory.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum ImageTiling {
+    /// Implementation-defined arrangement that is the most efficient for the GPU.
+    Optimal = vk::IMAGE_TILING_OPTIMAL,
+    /// Texels are laid out row by row. The layout can be queried with `color_linear_layout`.
+    Linear = vk::IMAGE_TILING_LINEAR,
+}
+
+/// Additional properties that an image can be created with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ImageCreateFlags {
+    /// The memory of the image is bound with sparse binding operations.
+    pub sparse_binding: bool,
+    /// The image can be partially resident in memory.
+    pub sparse_residency: bool,
+    /// The memory of the image can alias the memory of other resources.
+    pub sparse_aliased: bool,
+    /// Views of the image can have a different format than the image.
+    pub mutable_format: bool,
+    /// Cubemap views can be created from the image.
+    pub cube_compatible: bool,
+    /// 2D and 2D array views can be created from the 3D image.
+    pub array_2d_compatible: bool,
+}
+
+impl ImageCreateFlags {
+    /// Builds an `ImageCreateFlags` with all values set to false.
+    #[inline]
+    pub fn none() -> ImageCreateFlags {
+        ImageCreateFlags {
+            sparse_binding: false,
+            sparse_residency: false,
+            sparse_aliased: false,
+            mutable_format: false,
+            cube_compatible: false,
+            array_2d_compatible: false,
+        }
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn to_bits(&self) -> vk::ImageCreateFlags {
+        let mut result = 0;
+        if self.sparse_binding { result |= vk::IMAGE_CREATE_SPARSE_BINDING_BIT; }
+        if self.sparse_residency { result |= vk::IMAGE_CREATE_SPARSE_RESIDENCY_BIT; }
+        if self.sparse_aliased { result |= vk::IMAGE_CREATE_SPARSE_ALIASED_BIT; }
+        if self.mutable_format { result |= vk::IMAGE_CREATE_MUTABLE_FORMAT_BIT; }
+        if self.cube_compatible { result |= vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT; }
+        if self.array_2d_compatible { result |= vk::IMAGE_CREATE_2D_ARRAY_COMPATIBLE_BIT_KHR; }
+        result
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u32)]
 pub enum Layout {
@@ -1098,6 +1518,7 @@ mod tests {
     use super::UnsafeImage;
     use super::Usage;
 
+    use buffer::sys::SparseLevel;
     use image::ImageDimensions;
     use format::Format;
     use sync::Sharing;
@@ -1115,7 +1536,7 @@ mod tests {
             UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
                              ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 1,
                                                       cubemap_compatible: false }, 1, 1,
-                             Sharing::Exclusive::<Empty<_>>, false, false)
+                             Sharing::Exclusive::<Empty<_>>, SparseLevel::none(), false, false)
         }.unwrap();
     }
 
@@ -1133,7 +1554,7 @@ mod tests {
             UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
                              ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 1,
                                                       cubemap_compatible: false }, 1, 1,
-                             Sharing::Exclusive::<Empty<_>>, false, false)
+                             Sharing::Exclusive::<Empty<_>>, SparseLevel::none(), false, false)
         }.unwrap();
     }
 
@@ -1150,7 +1571,7 @@ mod tests {
             UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
                              ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 1,
                                                       cubemap_compatible: false }, 0, 1,
-                             Sharing::Exclusive::<Empty<_>>, false, false)
+                             Sharing::Exclusive::<Empty<_>>, SparseLevel::none(), false, false)
         };
 
         match res {
@@ -1172,7 +1593,7 @@ mod tests {
             UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
                              ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 1,
                                                       cubemap_compatible: false }, 5, 1,
-                             Sharing::Exclusive::<Empty<_>>, false, false)
+                             Sharing::Exclusive::<Empty<_>>, SparseLevel::none(), false, false)
         };
 
         match res {
@@ -1194,7 +1615,7 @@ mod tests {
             UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
                              ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 1,
                                                       cubemap_compatible: false }, 1, 0,
-                             Sharing::Exclusive::<Empty<_>>, false, false)
+                             Sharing::Exclusive::<Empty<_>>, SparseLevel::none(), false, false)
         };
 
         match res {
@@ -1217,7 +1638,7 @@ mod tests {
             UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
                              ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 1,
                                                       cubemap_compatible: false }, 1, u32::MAX,
-                             Sharing::Exclusive::<Empty<_>>, false, false)
+                             Sharing::Exclusive::<Empty<_>>, SparseLevel::none(), false, false)
         };
 
         match res {
@@ -1242,7 +1663,7 @@ mod tests {
             UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
                              ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 1,
                                                       cubemap_compatible: false }, 2, 1,
-                             Sharing::Exclusive::<Empty<_>>, false, false)
+                             Sharing::Exclusive::<Empty<_>>, SparseLevel::none(), false, false)
         };
 
         match res {
@@ -1265,12 +1686,38 @@ mod tests {
             UnsafeImage::new(&device, &usage, Format::ASTC_5x4UnormBlock,
                              ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 1,
                                                       cubemap_compatible: false }, 1, u32::MAX,
-                             Sharing::Exclusive::<Empty<_>>, false, false)
+                             Sharing::Exclusive::<Empty<_>>, SparseLevel::none(), false, false)
+        };
+
+        match res {
+            Err(ImageCreationError::FormatNotSupported) => (),
+            Err(ImageCreationError::FormatNotSupportedForUsage { format, missing_features }) => {
+                assert_eq!(format, Format::ASTC_5x4UnormBlock);
+                assert!(missing_features.color_attachment);
+            },
+            _ => panic!()
+        };
+    }
+
+    #[test]
+    fn unsupported_format() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = Usage {
+            sampled: true,
+            .. Usage::none()
+        };
+
+        // TODO: what if R64G64B64A64Sfloat is supported?
+        let res = unsafe {
+            UnsafeImage::new(&device, &usage, Format::R64G64B64A64Sfloat,
+                             ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 1,
+                                                      cubemap_compatible: false }, 1, 1,
+                             Sharing::Exclusive::<Empty<_>>, SparseLevel::none(), false, false)
         };
 
         match res {
             Err(ImageCreationError::FormatNotSupported) => (),
-            Err(ImageCreationError::UnsupportedUsage) => (),
             _ => panic!()
         };
     }
@@ -1289,7 +1736,7 @@ mod tests {
             UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
                              ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 1,
                                                       cubemap_compatible: false }, 1, 1,
-                             Sharing::Exclusive::<Empty<_>>, false, false)
+                             Sharing::Exclusive::<Empty<_>>, SparseLevel::none(), false, false)
         };
 
         match res {
@@ -1311,7 +1758,29 @@ mod tests {
             UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
                              ImageDimensions::Dim2d { width: 32, height: 64, array_layers: 1,
                                                       cubemap_compatible: true }, 1, 1,
-                             Sharing::Exclusive::<Empty<_>>, false, false)
+                             Sharing::Exclusive::<Empty<_>>, SparseLevel::none(), false, false)
+        };
+
+        match res {
+            Err(ImageCreationError::UnsupportedDimensions { .. }) => (),
+            _ => panic!()
+        };
+    }
+
+    #[test]
+    fn cubecompatible_not_enough_layers() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = Usage {
+            sampled: true,
+            .. Usage::none()
+        };
+
+        let res = unsafe {
+            UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
+                             ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 5,
+                                                      cubemap_compatible: true }, 1, 1,
+                             Sharing::Exclusive::<Empty<_>>, SparseLevel::none(), false, false)
         };
 
         match res {
@@ -1319,4 +1788,137 @@ mod tests {
             _ => panic!()
         };
     }
+
+    #[test]
+    fn array_2d_compatible_requires_maintenance1() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = Usage {
+            sampled: true,
+            .. Usage::none()
+        };
+
+        let res = unsafe {
+            UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
+                             ImageDimensions::Dim3d { width: 32, height: 32, depth: 8,
+                                                      array_2d_compatible: true }, 1, 1,
+                             Sharing::Exclusive::<Empty<_>>, SparseLevel::none(), false, false)
+        };
+
+        match res {
+            Err(ImageCreationError::Maintenance1ExtensionNotEnabled) => (),
+            _ => panic!()
+        };
+    }
+
+    #[test]
+    fn sparse_binding_feature_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = Usage {
+            sampled: true,
+            .. Usage::none()
+        };
+
+        let sparse = SparseLevel { sparse: true, sparse_residency: false, sparse_aliased: false };
+
+        let res = unsafe {
+            UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
+                             ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 1,
+                                                      cubemap_compatible: false }, 1, 1,
+                             Sharing::Exclusive::<Empty<_>>, sparse, false, false)
+        };
+
+        match res {
+            Err(ImageCreationError::SparseBindingFeatureNotEnabled) => (),
+            _ => panic!()
+        };
+    }
+
+    #[test]
+    fn sparse_residency_image2d_feature_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!(sparse_binding);
+
+        let usage = Usage {
+            sampled: true,
+            .. Usage::none()
+        };
+
+        let sparse = SparseLevel { sparse: true, sparse_residency: true, sparse_aliased: false };
+
+        let res = unsafe {
+            UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
+                             ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 1,
+                                                      cubemap_compatible: false }, 1, 1,
+                             Sharing::Exclusive::<Empty<_>>, sparse, false, false)
+        };
+
+        match res {
+            Err(ImageCreationError::SparseResidencyImage2dFeatureNotEnabled) => (),
+            _ => panic!()
+        };
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't enable sparse residency without enabling sparse binding")]
+    fn sparse_residency_without_binding() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = Usage {
+            sampled: true,
+            .. Usage::none()
+        };
+
+        let sparse = SparseLevel { sparse: false, sparse_residency: true, sparse_aliased: false };
+
+        let _ = unsafe {
+            UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
+                             ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 1,
+                                                      cubemap_compatible: false }, 1, 1,
+                             Sharing::Exclusive::<Empty<_>>, sparse, false, false)
+        };
+    }
+
+    #[test]
+    fn sparse_memory_requirements() {
+        let (device, _) = gfx_dev_and_queue!(sparse_binding, sparse_residency_image2d);
+
+        let usage = Usage {
+            sampled: true,
+            .. Usage::none()
+        };
+
+        let sparse = SparseLevel { sparse: true, sparse_residency: true, sparse_aliased: false };
+
+        let (img, _) = unsafe {
+            UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
+                             ImageDimensions::Dim2d { width: 256, height: 256, array_layers: 1,
+                                                      cubemap_compatible: false }, 1, 9,
+                             Sharing::Exclusive::<Empty<_>>, sparse, false, false)
+        }.unwrap();
+
+        assert!(img.sparse_level().sparse_residency);
+        let reqs = img.sparse_memory_requirements();
+        assert!(reqs.iter().any(|r| r.color));
+        assert!(reqs.iter().all(|r| r.mip_tail_first_lod <= 9));
+    }
+
+    #[test]
+    fn sparse_memory_requirements_non_sparse() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = Usage {
+            sampled: true,
+            .. Usage::none()
+        };
+
+        let (img, _) = unsafe {
+            UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
+                             ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 1,
+                                                      cubemap_compatible: false }, 1, 1,
+                             Sharing::Exclusive::<Empty<_>>, SparseLevel::none(), false, false)
+        }.unwrap();
+
+        assert!(img.sparse_memory_requirements().is_empty());
+    }
 }
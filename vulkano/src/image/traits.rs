@@ -11,14 +11,9 @@ use buffer::BufferAccess;
 use device::Queue;
 use format::ClearValue;
 use format::Format;
-use format::PossibleFloatFormatDesc;
-use format::PossibleUintFormatDesc;
-use format::PossibleSintFormatDesc;
-use format::PossibleDepthFormatDesc;
-use format::PossibleStencilFormatDesc;
-use format::PossibleDepthStencilFormatDesc;
 use image::Dimensions;
 use image::ImageDimensions;
+use image::ImageSubresourceRange;
 use image::sys::Layout;
 use image::sys::UnsafeImage;
 use image::sys::UnsafeImageView;
@@ -59,24 +54,21 @@ pub unsafe trait ImageAccess {
     /// Returns true if the image is a color image.
     #[inline]
     fn has_color(&self) -> bool {
-        let format = self.format();
-        format.is_float() || format.is_uint() || format.is_sint()
+        self.format().aspects().color
     }
 
     /// Returns true if the image has a depth component. In other words, if it is a depth or a
     /// depth-stencil format. 
     #[inline]
     fn has_depth(&self) -> bool {
-        let format = self.format();
-        format.is_depth() || format.is_depth_stencil()
+        self.format().aspects().depth
     }
 
     /// Returns true if the image has a stencil component. In other words, if it is a stencil or a
     /// depth-stencil format. 
     #[inline]
     fn has_stencil(&self) -> bool {
-        let format = self.format();
-        format.is_stencil() || format.is_depth_stencil()
+        self.format().aspects().stencil
     }
 
     /// Returns the number of samples of this image.
@@ -107,6 +99,24 @@ pub unsafe trait ImageAccess {
     /// and the layout it must be returned to before the end of the command buffer.
     fn default_layout(&self) -> Layout;
 
+    /// Returns the layout that the image is in when it is first used in a primary command buffer.
+    ///
+    /// This is usually the same as `default_layout()`, except for objects that are used to
+    /// initialize the content of an image, in which case it can be `Undefined`.
+    #[inline]
+    fn initial_layout_requirement(&self) -> Layout {
+        self.default_layout()
+    }
+
+    /// Returns the layout that the image must be returned to before the end of a primary
+    /// command buffer.
+    ///
+    /// This is usually the same as `default_layout()`.
+    #[inline]
+    fn final_layout_requirement(&self) -> Layout {
+        self.default_layout()
+    }
+
     /// Returns true if an access to `self` (as defined by `self_first_layer`, `self_num_layers`,
     /// `self_first_mipmap` and `self_num_mipmaps`) potentially overlaps the same memory as an
     /// access to `other` (as defined by `other_offset` and `other_size`).
@@ -135,45 +145,72 @@ pub unsafe trait ImageAccess {
     {
         // TODO: should we really provide a default implementation?
 
-        // TODO: debug asserts to check for ranges
+        debug_assert!(self_num_layers >= 1 && self_num_mipmaps >= 1);
+        debug_assert!(other_num_layers >= 1 && other_num_mipmaps >= 1);
+        debug_assert!(self_first_layer + self_num_layers <= self.dimensions().array_layers());
+        debug_assert!(self_first_mipmap + self_num_mipmaps <= self.inner().mipmap_levels());
+        debug_assert!(other_first_layer + other_num_layers <= other.dimensions().array_layers());
+        debug_assert!(other_first_mipmap + other_num_mipmaps <= other.inner().mipmap_levels());
 
         if self.inner().internal_object() != other.inner().internal_object() {
             return false;
         }
 
-        true
+        let self_range = ImageSubresourceRange {
+            aspect: None,
+            first_mipmap_level: self_first_mipmap,
+            num_mipmap_levels: self_num_mipmaps,
+            first_array_layer: self_first_layer,
+            num_array_layers: self_num_layers,
+        };
+
+        let other_range = ImageSubresourceRange {
+            aspect: None,
+            first_mipmap_level: other_first_mipmap,
+            num_mipmap_levels: other_num_mipmaps,
+            first_array_layer: other_first_layer,
+            num_array_layers: other_num_layers,
+        };
+
+        self_range.overlaps(&other_range)
     }
 
     /// Returns a key that uniquely identifies the range given by
     /// first_layer/num_layers/first_mipmap/num_mipmaps.
     ///
-    /// Two ranges that potentially overlap in memory should return the same key.
+    /// Two accesses to the same range of the same memory should return the same key. Different
+    /// ranges of subresources of an image return different keys, even if they partially overlap,
+    /// so that accesses to distinct subresources don't conflict.
     ///
     /// The key is shared amongst all buffers and images, which means that you can make several
     /// different image objects share the same memory, or make some image objects share memory
     /// with buffers, as long as they return the same key.
     ///
     /// Since it is possible to accidentally return the same key for memory ranges that don't
-    /// overlap, the `conflicts_image` or `conflicts_buffer` function should always be called to
-    /// verify whether they actually overlap.
+    /// overlap, and different keys for ranges that partially overlap, the `conflicts_image` or
+    /// `conflicts_buffer` function should always be called to verify whether they actually
+    /// overlap.
     fn conflict_key(&self, first_layer: u32, num_layers: u32, first_mipmap: u32, num_mipmaps: u32)
                     -> u64;
 
-    /// Locks the resource for usage on the GPU. Returns `false` if the lock was already acquired.
+    /// Locks the given range of subresources for usage on the GPU. Returns `false` if a range
+    /// that overlaps `range` was already locked and either lock is exclusive.
     ///
-    /// This function implementation should remember that it has been called and return `false` if
-    /// it gets called a second time.
+    /// This function implementation should remember which ranges have been locked, so that
+    /// locking different faces of a cubemap or different mipmap levels doesn't conflict. The
+    /// `SubresourceLocks` struct can be used for this purpose.
     ///
     /// The only way to know that the GPU has stopped accessing a queue is when the image object
     /// gets destroyed. Therefore you are encouraged to use temporary objects or handles (similar
     /// to a lock) in order to represent a GPU access.
-    fn try_gpu_lock(&self, exclusive_access: bool, queue: &Queue) -> bool;
+    fn try_gpu_lock(&self, range: &ImageSubresourceRange, exclusive_access: bool, queue: &Queue)
+                    -> bool;
 
-    /// Locks the resource for usage on the GPU. Supposes that the resource is already locked, and
-    /// simply increases the lock by one.
+    /// Locks the given range of subresources for usage on the GPU. Supposes that the range is
+    /// already locked, and simply increases the lock by one.
     ///
-    /// Must only be called after `try_gpu_lock()` succeeded.
-    unsafe fn increase_gpu_lock(&self);
+    /// Must only be called after `try_gpu_lock()` succeeded for a range that contains `range`.
+    unsafe fn increase_gpu_lock(&self, range: &ImageSubresourceRange);
 }
 
 unsafe impl<T> ImageAccess for T where T: SafeDeref, T::Target: ImageAccess {
@@ -187,6 +224,36 @@ unsafe impl<T> ImageAccess for T where T: SafeDeref, T::Target: ImageAccess {
         (**self).default_layout()
     }
 
+    #[inline]
+    fn initial_layout_requirement(&self) -> Layout {
+        (**self).initial_layout_requirement()
+    }
+
+    #[inline]
+    fn final_layout_requirement(&self) -> Layout {
+        (**self).final_layout_requirement()
+    }
+
+    #[inline]
+    fn conflicts_buffer(&self, self_first_layer: u32, self_num_layers: u32, self_first_mipmap: u32,
+                        self_num_mipmaps: u32, other: &BufferAccess, other_offset: usize,
+                        other_size: usize) -> bool
+    {
+        (**self).conflicts_buffer(self_first_layer, self_num_layers, self_first_mipmap,
+                                  self_num_mipmaps, other, other_offset, other_size)
+    }
+
+    #[inline]
+    fn conflicts_image(&self, self_first_layer: u32, self_num_layers: u32, self_first_mipmap: u32,
+                       self_num_mipmaps: u32, other: &ImageAccess,
+                       other_first_layer: u32, other_num_layers: u32, other_first_mipmap: u32,
+                       other_num_mipmaps: u32) -> bool
+    {
+        (**self).conflicts_image(self_first_layer, self_num_layers, self_first_mipmap,
+                                 self_num_mipmaps, other, other_first_layer, other_num_layers,
+                                 other_first_mipmap, other_num_mipmaps)
+    }
+
     #[inline]
     fn conflict_key(&self, first_layer: u32, num_layers: u32, first_mipmap: u32, num_mipmaps: u32)
                     -> u64
@@ -195,13 +262,15 @@ unsafe impl<T> ImageAccess for T where T: SafeDeref, T::Target: ImageAccess {
     }
 
     #[inline]
-    fn try_gpu_lock(&self, exclusive_access: bool, queue: &Queue) -> bool {
-        (**self).try_gpu_lock(exclusive_access, queue)
+    fn try_gpu_lock(&self, range: &ImageSubresourceRange, exclusive_access: bool, queue: &Queue)
+                    -> bool
+    {
+        (**self).try_gpu_lock(range, exclusive_access, queue)
     }
 
     #[inline]
-    unsafe fn increase_gpu_lock(&self) {
-        (**self).increase_gpu_lock()
+    unsafe fn increase_gpu_lock(&self, range: &ImageSubresourceRange) {
+        (**self).increase_gpu_lock(range)
     }
 }
 
@@ -247,6 +316,14 @@ pub unsafe trait ImageViewAccess {
         self.parent().samples()
     }
 
+    /// Returns the range of subresources of the parent image that the view covers.
+    ///
+    /// The default implementation returns the whole image.
+    #[inline]
+    fn subresource_range(&self) -> ImageSubresourceRange {
+        ImageSubresourceRange::whole_image(self.parent())
+    }
+
     /// Returns the image layout to use in a descriptor with the given subresource.
     fn descriptor_set_storage_image_layout(&self) -> Layout;
     /// Returns the image layout to use in a descriptor with the given subresource.
@@ -288,6 +365,11 @@ unsafe impl<T> ImageViewAccess for T where T: SafeDeref, T::Target: ImageViewAcc
         (**self).dimensions()
     }
 
+    #[inline]
+    fn subresource_range(&self) -> ImageSubresourceRange {
+        (**self).subresource_range()
+    }
+
     #[inline]
     fn descriptor_set_storage_image_layout(&self) -> Layout {
         (**self).descriptor_set_storage_image_layout()
@@ -0,0 +1,554 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Views over a part of an image.
+//!
+//! The image types of this library all provide a view that covers the whole image. If you need
+//! to access only some mipmap levels, some array layers or one aspect of an image, or if you need
+//! to interpret a 2D array image as a cubemap, you can create an `ImageView` instead.
+//!
+//! # Example
+//!
+//! ```ignore
+//! // Renders to the face number 3 of a cubemap.
+//! let face = ImageView::new(cubemap.clone(), ViewType::Dim2d, 0 .. 1, 3 .. 4, None,
+//!                           Swizzle::default()).unwrap();
+//! ```
+
+use std::error;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+use device::Device;
+use device::DeviceOwned;
+use image::Dimensions;
+use image::ImageAccess;
+use image::ImageAspect;
+use image::ImageDimensions;
+use image::ImageSubresourceRange;
+use image::ImageViewAccess;
+use image::Swizzle;
+use image::ViewType;
+use image::sys::Layout;
+use image::sys::UnsafeImageView;
+use image::traits;
+
+use OomError;
+
+/// View over a range of mipmap levels and array layers of an image.
+///
+/// The view holds `image`, which keeps the image alive and lets the command buffers lock it when
+/// the view is used. The layouts of the view in descriptor sets are the ones of `image`.
+#[derive(Debug)]
+pub struct ImageView<I> {
+    image: I,
+    view: UnsafeImageView,
+    ty: ViewType,
+    dimensions: Dimensions,
+    mipmap_levels: Range<u32>,
+    array_layers: Range<u32>,
+    aspect: Option<ImageAspect>,
+    swizzle: Swizzle,
+}
+
+impl<I> ImageView<I> where I: ImageAccess {
+    /// Builds a new view over `image`.
+    ///
+    /// `mipmap_levels` and `array_layers` are the ranges of the image that the view covers. The
+    /// first level of the view is the first level of the range. Cubemap views cover 6 array layers
+    /// per cube, in the order of the `CubeFace` enum.
+    ///
+    /// 2D and 2D array views of a 3D image are possible if the image is 2D-array-compatible. In
+    /// that case the view must cover a single mipmap level, and `array_layers` is the range of
+    /// depth slices of this level that the view covers.
+    ///
+    /// If `aspect` is `None`, the view covers all the aspects of the image. Otherwise only the
+    /// given aspect is covered, for example in order to sample the depth of a depth-stencil image.
+    ///
+    /// Note that views that don't have identity swizzling can't be used as framebuffer
+    /// attachments.
+    pub fn new(image: I, ty: ViewType, mipmap_levels: Range<u32>, array_layers: Range<u32>,
+               aspect: Option<ImageAspect>, swizzle: Swizzle)
+               -> Result<Arc<ImageView<I>>, ImageViewCreationError>
+    {
+        let image_dims = image.dimensions();
+
+        if mipmap_levels.start >= mipmap_levels.end ||
+           mipmap_levels.end > image.inner().mipmap_levels()
+        {
+            return Err(ImageViewCreationError::MipmapLevelsOutOfRange);
+        }
+
+        let view_of_3d_slices = match (image_dims, ty) {
+            (ImageDimensions::Dim3d { array_2d_compatible: true, .. }, ViewType::Dim2d) |
+            (ImageDimensions::Dim3d { array_2d_compatible: true, .. }, ViewType::Dim2dArray) => {
+                true
+            },
+            _ => false,
+        };
+
+        // Checked before the array layers, since the meaning of `array_layers` depends on the
+        // type of view.
+        let compatible_ty = match (image_dims, ty) {
+            (ImageDimensions::Dim1d { .. }, ViewType::Dim1d) |
+            (ImageDimensions::Dim1d { .. }, ViewType::Dim1dArray) |
+            (ImageDimensions::Dim2d { .. }, ViewType::Dim2d) |
+            (ImageDimensions::Dim2d { .. }, ViewType::Dim2dArray) |
+            (ImageDimensions::Dim3d { .. }, ViewType::Dim3d) => true,
+            (ImageDimensions::Dim2d { cubemap_compatible, .. }, ViewType::Cubemap) |
+            (ImageDimensions::Dim2d { cubemap_compatible, .. }, ViewType::CubemapArray) => {
+                cubemap_compatible
+            },
+            (ImageDimensions::Dim3d { .. }, ViewType::Dim2d) |
+            (ImageDimensions::Dim3d { .. }, ViewType::Dim2dArray) => view_of_3d_slices,
+            _ => false,
+        };
+
+        if !compatible_ty {
+            return Err(ImageViewCreationError::IncompatibleViewType);
+        }
+
+        let max_layers = if view_of_3d_slices {
+            if mipmap_levels.end - mipmap_levels.start != 1 {
+                return Err(ImageViewCreationError::MipmapLevelsOutOfRange);
+            }
+            // Can't fail, since the range of mipmap levels has been checked.
+            image_dims.mipmap_width_height_depth(mipmap_levels.start).unwrap()[2]
+        } else {
+            image_dims.array_layers()
+        };
+
+        if array_layers.start >= array_layers.end || array_layers.end > max_layers {
+            return Err(ImageViewCreationError::ArrayLayersOutOfRange);
+        }
+
+        let num_layers = array_layers.end - array_layers.start;
+        let layers_ok = match ty {
+            ViewType::Dim1d | ViewType::Dim2d => num_layers == 1,
+            ViewType::Dim1dArray | ViewType::Dim2dArray | ViewType::Dim3d => true,
+            ViewType::Cubemap => num_layers == 6,
+            ViewType::CubemapArray => num_layers % 6 == 0,
+        };
+
+        if !layers_ok {
+            return Err(ImageViewCreationError::WrongNumberOfArrayLayers);
+        }
+
+        if ty == ViewType::CubemapArray &&
+           !image.inner().device().enabled_features().image_cube_array
+        {
+            return Err(ImageViewCreationError::ImageCubeArrayFeatureNotEnabled);
+        }
+
+        if let Some(aspect) = aspect {
+            let has_aspect = match aspect {
+                ImageAspect::Color => image.has_color(),
+                ImageAspect::Depth => image.has_depth(),
+                ImageAspect::Stencil => image.has_stencil(),
+            };
+
+            if !has_aspect {
+                return Err(ImageViewCreationError::WrongAspect);
+            }
+        }
+
+        let dimensions = {
+            // Can't fail, since the range of mipmap levels has been checked.
+            let whd = image_dims.mipmap_width_height_depth(mipmap_levels.start).unwrap();
+
+            match ty {
+                ViewType::Dim1d => Dimensions::Dim1d { width: whd[0] },
+                ViewType::Dim1dArray => {
+                    Dimensions::Dim1dArray { width: whd[0], array_layers: num_layers }
+                },
+                ViewType::Dim2d => Dimensions::Dim2d { width: whd[0], height: whd[1] },
+                ViewType::Dim2dArray => {
+                    Dimensions::Dim2dArray { width: whd[0], height: whd[1],
+                                             array_layers: num_layers }
+                },
+                ViewType::Dim3d => {
+                    Dimensions::Dim3d { width: whd[0], height: whd[1], depth: whd[2] }
+                },
+                ViewType::Cubemap => Dimensions::Cubemap { size: whd[0] },
+                ViewType::CubemapArray => {
+                    Dimensions::CubemapArray { size: whd[0], array_layers: num_layers / 6 }
+                },
+            }
+        };
+
+        let view = unsafe {
+            try!(UnsafeImageView::raw_with_options(image.inner(), ty, mipmap_levels.clone(),
+                                                   array_layers.clone(), aspect, swizzle))
+        };
+
+        Ok(Arc::new(ImageView {
+            image: image,
+            view: view,
+            ty: ty,
+            dimensions: dimensions,
+            mipmap_levels: mipmap_levels,
+            array_layers: array_layers,
+            aspect: aspect,
+            swizzle: swizzle,
+        }))
+    }
+}
+
+impl<I> ImageView<I> {
+    /// Returns the image this view was created from.
+    #[inline]
+    pub fn image(&self) -> &I {
+        &self.image
+    }
+
+    /// Returns the type of the view.
+    #[inline]
+    pub fn view_type(&self) -> ViewType {
+        self.ty
+    }
+
+    /// Returns the range of mipmap levels of the image that the view covers.
+    #[inline]
+    pub fn mipmap_levels(&self) -> Range<u32> {
+        self.mipmap_levels.clone()
+    }
+
+    /// Returns the range of array layers of the image that the view covers.
+    #[inline]
+    pub fn array_layers(&self) -> Range<u32> {
+        self.array_layers.clone()
+    }
+
+    /// Returns the aspect of the image that the view covers, or `None` if it covers all of them.
+    #[inline]
+    pub fn aspect(&self) -> Option<ImageAspect> {
+        self.aspect
+    }
+
+    /// Returns the component swizzling of the view.
+    #[inline]
+    pub fn swizzle(&self) -> Swizzle {
+        self.swizzle
+    }
+
+    /// Returns the range of subresources of the image that the view covers.
+    ///
+    /// The depth slices of a 3D image all belong to its only array layer, so a 2D view of some
+    /// slices covers the whole mipmap level.
+    #[inline]
+    pub fn subresource_range(&self) -> ImageSubresourceRange
+        where I: ImageAccess
+    {
+        let layers = match self.image.dimensions() {
+            ImageDimensions::Dim3d { .. } => 0 .. 1,
+            _ => self.array_layers.clone(),
+        };
+
+        ImageSubresourceRange {
+            aspect: self.aspect,
+            .. ImageSubresourceRange::new(self.mipmap_levels.clone(), layers)
+        }
+    }
+}
+
+unsafe impl<I> DeviceOwned for ImageView<I> where I: ImageAccess {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.image.inner().device()
+    }
+}
+
+unsafe impl<I> traits::ImageView for Arc<ImageView<I>> where I: ImageAccess + ImageViewAccess {
+    type Access = Self;
+
+    #[inline]
+    fn access(self) -> Self {
+        self
+    }
+}
+
+unsafe impl<I> ImageViewAccess for ImageView<I> where I: ImageAccess + ImageViewAccess {
+    #[inline]
+    fn parent(&self) -> &ImageAccess {
+        &self.image
+    }
+
+    #[inline]
+    fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    #[inline]
+    fn inner(&self) -> &UnsafeImageView {
+        &self.view
+    }
+
+    #[inline]
+    fn subresource_range(&self) -> ImageSubresourceRange {
+        ImageView::subresource_range(self)
+    }
+
+    // The view doesn't have a layout of its own. The image is in the same layout whether it is
+    // used through the view or directly.
+
+    #[inline]
+    fn descriptor_set_storage_image_layout(&self) -> Layout {
+        self.image.descriptor_set_storage_image_layout()
+    }
+
+    #[inline]
+    fn descriptor_set_combined_image_sampler_layout(&self) -> Layout {
+        self.image.descriptor_set_combined_image_sampler_layout()
+    }
+
+    #[inline]
+    fn descriptor_set_sampled_image_layout(&self) -> Layout {
+        self.image.descriptor_set_sampled_image_layout()
+    }
+
+    #[inline]
+    fn descriptor_set_input_attachment_layout(&self) -> Layout {
+        self.image.descriptor_set_input_attachment_layout()
+    }
+
+    #[inline]
+    fn identity_swizzle(&self) -> bool {
+        self.swizzle.is_identity()
+    }
+}
+
+/// Error that can happen when creating an `ImageView`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImageViewCreationError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The range of mipmap levels is empty or out of range of the image, or a 2D view of a 3D
+    /// image covers more than one mipmap level.
+    MipmapLevelsOutOfRange,
+    /// The range of array layers is empty or out of range of the image.
+    ArrayLayersOutOfRange,
+    /// The view type can't be used with the dimensions of the image. Cubemap views can only be
+    /// created from images that are cubemap-compatible, and 2D views of 3D images can only be
+    /// created from images that are 2D-array-compatible.
+    IncompatibleViewType,
+    /// The number of array layers doesn't match the view type. Non-array views must have 1 layer,
+    /// cubemaps must have 6 layers and cubemap arrays must have a multiple of 6 layers.
+    WrongNumberOfArrayLayers,
+    /// The `image_cube_array` feature must be enabled to create a cubemap array view.
+    ImageCubeArrayFeatureNotEnabled,
+    /// The image doesn't have the requested aspect.
+    WrongAspect,
+}
+
+impl error::Error for ImageViewCreationError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ImageViewCreationError::OomError(_) => "not enough memory available",
+            ImageViewCreationError::MipmapLevelsOutOfRange => {
+                "the range of mipmap levels is empty or out of range of the image, or a 2D view \
+                 of a 3D image covers more than one mipmap level"
+            },
+            ImageViewCreationError::ArrayLayersOutOfRange => {
+                "the range of array layers is empty or out of range of the image"
+            },
+            ImageViewCreationError::IncompatibleViewType => {
+                "the view type can't be used with the dimensions of the image"
+            },
+            ImageViewCreationError::WrongNumberOfArrayLayers => {
+                "the number of array layers doesn't match the view type"
+            },
+            ImageViewCreationError::ImageCubeArrayFeatureNotEnabled => {
+                "the `image_cube_array` feature must be enabled to create a cubemap array view"
+            },
+            ImageViewCreationError::WrongAspect => {
+                "the image doesn't have the requested aspect"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ImageViewCreationError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for ImageViewCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for ImageViewCreationError {
+    #[inline]
+    fn from(err: OomError) -> ImageViewCreationError {
+        ImageViewCreationError::OomError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use format::Format;
+    use image::AttachmentImage;
+    use image::ComponentSwizzle;
+    use image::Dimensions;
+    use image::ImageAspect;
+    use image::Image;
+    use image::ImageSubresourceRange;
+    use image::ImageViewAccess;
+    use image::MipmapsCount;
+    use image::StorageImage;
+    use image::Swizzle;
+    use image::Usage;
+    use image::ViewType;
+    use image::immutable::ImmutableImage;
+    use image::sys::Layout;
+    use image::view::ImageView;
+    use image::view::ImageViewCreationError;
+
+    #[test]
+    fn cubemap_face() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = StorageImage::new(&device, Dimensions::Cubemap { size: 64 },
+                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        let cube = ImageView::new(image.clone(), ViewType::Cubemap, 0 .. 1, 0 .. 6, None,
+                                  Swizzle::default()).unwrap();
+        assert_eq!(cube.dimensions(), Dimensions::Cubemap { size: 64 });
+
+        let face = ImageView::new(image, ViewType::Dim2d, 0 .. 1, 3 .. 4, None,
+                                  Swizzle::default()).unwrap();
+        assert_eq!(face.dimensions(), Dimensions::Dim2d { width: 64, height: 64 });
+        assert_eq!(face.array_layers(), 3 .. 4);
+        assert_eq!(face.subresource_range(), ImageSubresourceRange::new(0 .. 1, 3 .. 4));
+        assert!(!face.subresource_range().overlaps(&ImageSubresourceRange::new(0 .. 1, 2 .. 3)));
+    }
+
+    #[test]
+    fn cubemap_wrong_layers() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = StorageImage::new(&device, Dimensions::Cubemap { size: 64 },
+                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        match ImageView::new(image, ViewType::Cubemap, 0 .. 1, 0 .. 5, None, Swizzle::default()) {
+            Err(ImageViewCreationError::WrongNumberOfArrayLayers) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn cubemap_from_non_cube_compatible() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let dims = Dimensions::Dim2dArray { width: 64, height: 64, array_layers: 6 };
+        let image = StorageImage::new(&device, dims, Format::R8G8B8A8Unorm,
+                                      Some(queue.family())).unwrap();
+
+        match ImageView::new(image, ViewType::Cubemap, 0 .. 1, 0 .. 6, None, Swizzle::default()) {
+            Err(ImageViewCreationError::IncompatibleViewType) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn slice_of_non_array_2d_compatible_3d_image() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        // The `khr_maintenance1` extension isn't enabled, so the image isn't
+        // 2D-array-compatible.
+        let dims = Dimensions::Dim3d { width: 32, height: 32, depth: 8 };
+        let image = StorageImage::new(&device, dims, Format::R8G8B8A8Unorm,
+                                      Some(queue.family())).unwrap();
+
+        match ImageView::new(image, ViewType::Dim2d, 0 .. 1, 2 .. 3, None, Swizzle::default()) {
+            Err(ImageViewCreationError::IncompatibleViewType) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn single_mipmap() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let (image, _) = ImmutableImage::uninitialized(&device,
+                                                       Dimensions::Dim2d { width: 64, height: 32 },
+                                                       Format::R8G8B8A8Unorm, MipmapsCount::Log2,
+                                                       Some(queue.family())).unwrap();
+
+        let view = ImageView::new(image.clone(), ViewType::Dim2d, 3 .. 4, 0 .. 1, None,
+                                  Swizzle::default()).unwrap();
+        assert_eq!(view.dimensions(), Dimensions::Dim2d { width: 8, height: 4 });
+
+        match ImageView::new(image, ViewType::Dim2d, 3 .. 8, 0 .. 1, None, Swizzle::default()) {
+            Err(ImageViewCreationError::MipmapLevelsOutOfRange) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn depth_only() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = StorageImage::new(&device, Dimensions::Dim2d { width: 32, height: 32 },
+                                      Format::D24Unorm_S8Uint, Some(queue.family())).unwrap();
+
+        let view = ImageView::new(image.clone(), ViewType::Dim2d, 0 .. 1, 0 .. 1,
+                                  Some(ImageAspect::Depth), Swizzle::default()).unwrap();
+        assert_eq!(view.aspect(), Some(ImageAspect::Depth));
+
+        match ImageView::new(image, ViewType::Dim2d, 0 .. 1, 0 .. 1, Some(ImageAspect::Color),
+                             Swizzle::default())
+        {
+            Err(ImageViewCreationError::WrongAspect) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn depth_only_attachment_layouts() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = Usage { sampled: true, .. Usage::none() };
+        let image = AttachmentImage::with_usage(&device, [32, 32], Format::D24Unorm_S8Uint,
+                                                usage).unwrap();
+
+        let view = ImageView::new(image.access(), ViewType::Dim2d, 0 .. 1, 0 .. 1,
+                                  Some(ImageAspect::Depth), Swizzle::default()).unwrap();
+        assert_eq!(view.descriptor_set_sampled_image_layout(), Layout::ShaderReadOnlyOptimal);
+        assert_eq!(view.descriptor_set_combined_image_sampler_layout(),
+                   Layout::ShaderReadOnlyOptimal);
+    }
+
+    #[test]
+    fn swizzle() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = StorageImage::new(&device, Dimensions::Dim2d { width: 32, height: 32 },
+                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        let swizzle = Swizzle { r: ComponentSwizzle::Blue, b: ComponentSwizzle::Red,
+                                .. Swizzle::default() };
+        let view = ImageView::new(image.clone(), ViewType::Dim2d, 0 .. 1, 0 .. 1, None,
+                                  swizzle).unwrap();
+        assert!(!view.identity_swizzle());
+        assert!(!view.inner().identity_swizzle());
+
+        let swizzle = Swizzle { g: ComponentSwizzle::Green, .. Swizzle::default() };
+        let view = ImageView::new(image, ViewType::Dim2d, 0 .. 1, 0 .. 1, None,
+                                  swizzle).unwrap();
+        assert!(view.identity_swizzle());
+    }
+}
@@ -19,6 +19,8 @@ use VulkanObject;
 use VulkanPointers;
 use instance::PhysicalDevice;
 use instance::loader;
+use instance::loader::FunctionPointers;
+use instance::loader::Loader;
 use instance::loader::LoadingError;
 use vk;
 use check_errors;
@@ -100,7 +102,15 @@ macro_rules! instance_extensions {
         impl $sname {
             /// See the docs of supported_by_core().
             pub fn supported_by_core_raw() -> Result<$sname, SupportedExtensionsError> {
-                let entry_points = try!(loader::entry_points());
+                $sname::supported_by_core_raw_with_loader(try!(loader::auto_loader()))
+            }
+
+            /// Same as `supported_by_core_raw()`, but allows specifying a loader.
+            pub fn supported_by_core_raw_with_loader<L>(ptrs: &FunctionPointers<L>)
+                                                        -> Result<$sname, SupportedExtensionsError>
+                where L: Loader
+            {
+                let entry_points = ptrs.entry_points();
 
                 let properties: Vec<vk::ExtensionProperties> = unsafe {
                     let mut num = 0;
@@ -136,6 +146,18 @@ macro_rules! instance_extensions {
                     Err(SupportedExtensionsError::OomError(e)) => panic!("{:?}", e),
                 }
             }
+
+            /// Same as `supported_by_core()`, but allows specifying a loader.
+            pub fn supported_by_core_with_loader<L>(ptrs: &FunctionPointers<L>)
+                                                    -> Result<$sname, LoadingError>
+                where L: Loader
+            {
+                match $sname::supported_by_core_raw_with_loader(ptrs) {
+                    Ok(l) => Ok(l),
+                    Err(SupportedExtensionsError::LoadingError(e)) => Err(e),
+                    Err(SupportedExtensionsError::OomError(e)) => panic!("{:?}", e),
+                }
+            }
         }
     );
 }
@@ -211,6 +233,8 @@ device_extensions! {
     khr_display_swapchain => b"VK_KHR_display_swapchain",
     khr_sampler_mirror_clamp_to_edge => b"VK_KHR_sampler_mirror_clamp_to_edge",
     khr_maintenance1 => b"VK_KHR_maintenance1",
+    khr_get_memory_requirements2 => b"VK_KHR_get_memory_requirements2",
+    khr_dedicated_allocation => b"VK_KHR_dedicated_allocation",
 }
 
 /// Error that can happen when loading the list of layers.
@@ -13,12 +13,15 @@ use std::ffi::CStr;
 use std::ffi::CString;
 use std::fmt;
 use std::mem;
+use std::ops::Deref;
 use std::ptr;
 use std::slice;
 use std::sync::Arc;
 use smallvec::SmallVec;
 
 use instance::loader;
+use instance::loader::FunctionPointers;
+use instance::loader::Loader;
 use instance::loader::LoadingError;
 use check_errors;
 use Error;
@@ -87,6 +90,24 @@ pub struct Instance {
     vk: vk::InstancePointers,
     extensions: InstanceExtensions,
     layers: SmallVec<[CString; 16]>,
+    function_pointers: OwnedOrRef<FunctionPointers<Box<Loader + Send + Sync>>>,
+}
+
+enum OwnedOrRef<T: 'static> {
+    Owned(T),
+    Ref(&'static T),
+}
+
+impl<T> Deref for OwnedOrRef<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        match *self {
+            OwnedOrRef::Owned(ref v) => v,
+            OwnedOrRef::Ref(v) => v,
+        }
+    }
 }
 
 impl Instance {
@@ -123,11 +144,43 @@ impl Instance {
             CString::new(layer).unwrap()
         }).collect::<SmallVec<[_; 16]>>();
 
-        Instance::new_inner(app_infos, extensions, layers)
+        let function_pointers = try!(loader::auto_loader());
+        Instance::new_inner(app_infos, extensions, layers, OwnedOrRef::Ref(function_pointers))
+    }
+
+    /// Same as `new`, but allows specifying a loader where to load Vulkan from.
+    ///
+    /// This makes it possible to use an implementation other than the system's default one, for
+    /// example a Vulkan library shipped alongside the application or a software implementation.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use vulkano::instance::Instance;
+    /// use vulkano::instance::InstanceExtensions;
+    /// use vulkano::instance::loader::DynamicLibraryLoader;
+    /// use vulkano::instance::loader::FunctionPointers;
+    ///
+    /// let loader = unsafe { DynamicLibraryLoader::new("libvulkan.so.1").unwrap() };
+    /// let ptrs = FunctionPointers::new(Box::new(loader) as Box<_>);
+    /// let instance = Instance::with_loader(ptrs, None, &InstanceExtensions::none(), None).unwrap();
+    /// ```
+    pub fn with_loader<'a, L>(loader: FunctionPointers<Box<Loader + Send + Sync>>,
+                              app_infos: Option<&ApplicationInfo>, extensions: &InstanceExtensions,
+                              layers: L) -> Result<Arc<Instance>, InstanceCreationError>
+        where L: IntoIterator<Item = &'a &'a str>
+    {
+        let layers = layers.into_iter().map(|&layer| {
+            CString::new(layer).unwrap()
+        }).collect::<SmallVec<[_; 16]>>();
+
+        Instance::new_inner(app_infos, extensions, layers, OwnedOrRef::Owned(loader))
     }
 
     fn new_inner(app_infos: Option<&ApplicationInfo>, extensions: &InstanceExtensions,
-                 layers: SmallVec<[CString; 16]>) -> Result<Arc<Instance>, InstanceCreationError>
+                 layers: SmallVec<[CString; 16]>,
+                 function_pointers: OwnedOrRef<FunctionPointers<Box<Loader + Send + Sync>>>)
+                 -> Result<Arc<Instance>, InstanceCreationError>
     {
         // TODO: For now there are still buggy drivers that will segfault if you don't pass any
         //       appinfos. Therefore for now we ensure that it can't be `None`.
@@ -174,8 +227,6 @@ impl Instance {
             extension.as_ptr()
         }).collect::<SmallVec<[_; 32]>>();
 
-        let entry_points = try!(loader::entry_points());
-
         // Creating the Vulkan instance.
         let instance = unsafe {
             let mut output = mem::uninitialized();
@@ -194,15 +245,15 @@ impl Instance {
                 ppEnabledExtensionNames: extensions_list.as_ptr(),
             };
 
+            let entry_points = function_pointers.entry_points();
             try!(check_errors(entry_points.CreateInstance(&infos, ptr::null(), &mut output)));
             output
         };
 
         // Loading the function pointers of the newly-created instance.
         let vk = {
-            let f = loader::static_functions().unwrap();        // TODO: return proper error
             vk::InstancePointers::load(|name| unsafe {
-                mem::transmute(f.GetInstanceProcAddr(instance, name.as_ptr()))
+                mem::transmute(function_pointers.get_instance_proc_addr(instance, name.as_ptr()))
             })
         };
 
@@ -270,6 +321,7 @@ impl Instance {
             vk: vk,
             extensions: extensions.clone(),
             layers: layers,
+            function_pointers: function_pointers,
         }))
     }
 
@@ -857,6 +909,13 @@ impl<'a> QueueFamily<'a> {
         (self.flags() & vk::QUEUE_SPARSE_BINDING_BIT) != 0
     }
 
+    /// Returns the number of bits of the timestamps written by queues of this family that are
+    /// meaningful, or 0 if the queues of this family don't support timestamps.
+    #[inline]
+    pub fn timestamp_valid_bits(&self) -> u32 {
+        self.physical_device.infos().queue_families[self.id as usize].timestampValidBits
+    }
+
     /// Internal utility function that returns the flags of this queue family.
     #[inline]
     fn flags(&self) -> u32 {
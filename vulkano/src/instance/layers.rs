@@ -18,6 +18,8 @@ use OomError;
 use Error;
 use vk;
 use instance::loader;
+use instance::loader::FunctionPointers;
+use instance::loader::Loader;
 use instance::loader::LoadingError;
 use version::Version;
 
@@ -45,8 +47,16 @@ use version::Version;
 /// }
 /// ```
 pub fn layers_list() -> Result<LayersIterator, LayersListError> {
+    layers_list_from_loader(try!(loader::auto_loader()))
+}
+
+/// Same as `layers_list()`, but allows specifying a loader.
+pub fn layers_list_from_loader<L>(ptrs: &FunctionPointers<L>)
+                                  -> Result<LayersIterator, LayersListError>
+    where L: Loader
+{
     unsafe {
-        let entry_points = try!(loader::entry_points());
+        let entry_points = ptrs.entry_points();
 
         let mut num = 0;
         try!(check_errors({
@@ -7,75 +7,214 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+//! Vulkan implementation loading system.
+//!
+//! Before vulkano can do anything, it first needs to find an implementation of Vulkan. A Vulkan
+//! implementation is defined as a single `vkGetInstanceProcAddr` function, which can be accessed
+//! through the `Loader` trait.
+//!
+//! This module provides various implementations of the `Loader` trait:
+//!
+//! - `DynamicLibraryLoader` loads a shared library (eg. a `.so` or a `.dll`) from a path and
+//!   retreives the `vkGetInstanceProcAddr` symbol from it.
+//! - `ProcAddrLoader` wraps a raw `vkGetInstanceProcAddr` function pointer, which is useful if
+//!   the implementation is linked statically or lives in the same process.
+//!
+//! Once you have a loader, you can wrap it in a `FunctionPointers` and pass it to
+//! `Instance::with_loader()`. If you don't care about any of this, the `auto_loader()` function
+//! tries to locate the system's default Vulkan loader and is what `Instance::new()` uses.
+
 use std::error;
 use std::fmt;
 use std::mem;
+use std::ops::Deref;
+use std::os::raw::c_char;
+use std::os::raw::c_void;
 use std::path::Path;
-use std::ptr;
 
 use shared_library;
 use vk;
 
-lazy_static! {
-    static ref VK_LIB: Result<shared_library::dynamic_library::DynamicLibrary, LoadingError> = {
-        #[cfg(windows)] fn get_path() -> &'static Path { Path::new("vulkan-1.dll") }
-        #[cfg(all(unix, not(target_os = "android")))] fn get_path() -> &'static Path { Path::new("libvulkan.so.1") }
-        #[cfg(target_os = "android")] fn get_path() -> &'static Path { Path::new("libvulkan.so") }
-        let path = get_path();
-        shared_library::dynamic_library::DynamicLibrary::open(Some(path))
-                                    .map_err(|err| LoadingError::LibraryLoadFailure(err))
-    };
-
-    static ref VK_STATIC: Result<vk::Static, LoadingError> = {
-        match *VK_LIB {
-            Ok(ref lib) => {
-                let mut err = None;
-                let result = vk::Static::load(|name| unsafe {
-                    let name = name.to_str().unwrap();
-                    match lib.symbol(name) {
-                        Ok(s) => s,
-                        Err(_) => {     // TODO: return error?
-                            err = Some(LoadingError::MissingEntryPoint(name.to_owned()));
-                            ptr::null()
-                        }
-                    }
-                });
-
-                if let Some(err) = err {
-                    Err(err)
-                } else {
-                    Ok(result)
-                }
-            },
-            Err(ref err) => Err(err.clone()),
+/// Implemented on objects that grant access to a Vulkan implementation.
+pub unsafe trait Loader {
+    /// Calls the `vkGetInstanceProcAddr` function. The parameters are the same.
+    ///
+    /// The returned function must stay valid for as long as `self` is alive.
+    fn get_instance_proc_addr(&self, instance: vk::Instance, name: *const c_char)
+                              -> extern "system" fn() -> ();
+}
+
+unsafe impl<T> Loader for T where T: Deref, T::Target: Loader {
+    #[inline]
+    fn get_instance_proc_addr(&self, instance: vk::Instance, name: *const c_char)
+                              -> extern "system" fn() -> ()
+    {
+        (**self).get_instance_proc_addr(instance, name)
+    }
+}
+
+/// Type of the `vkGetInstanceProcAddr` function.
+pub type GetInstanceProcAddr = extern "system" fn(vk::Instance, *const c_char)
+                                                  -> extern "system" fn() -> ();
+
+/// Wraps a raw `vkGetInstanceProcAddr` function pointer, for example one that was obtained from a
+/// statically-linked implementation.
+#[derive(Copy, Clone)]
+pub struct ProcAddrLoader {
+    get_proc_addr: GetInstanceProcAddr,
+}
+
+impl ProcAddrLoader {
+    /// Builds a loader from a `vkGetInstanceProcAddr` function.
+    ///
+    /// # Safety
+    ///
+    /// - The function must behave as described by the Vulkan specifications.
+    /// - The function must stay valid for as long as the loader is alive.
+    ///
+    #[inline]
+    pub unsafe fn new(get_proc_addr: GetInstanceProcAddr) -> ProcAddrLoader {
+        ProcAddrLoader {
+            get_proc_addr: get_proc_addr,
         }
-    };
-
-    static ref VK_ENTRY: Result<vk::EntryPoints, LoadingError> = {
-        match *VK_STATIC {
-            Ok(ref lib) => {
-                // At this point we assume that if one of the functions fails to load, it is an
-                // implementation bug and not a real-life situation that could be handled by
-                // an error.
-                Ok(vk::EntryPoints::load(|name| unsafe {
-                    mem::transmute(lib.GetInstanceProcAddr(0, name.as_ptr()))
-                }))
-            },
-            Err(ref err) => Err(err.clone()),
+    }
+}
+
+unsafe impl Loader for ProcAddrLoader {
+    #[inline]
+    fn get_instance_proc_addr(&self, instance: vk::Instance, name: *const c_char)
+                              -> extern "system" fn() -> ()
+    {
+        (self.get_proc_addr)(instance, name)
+    }
+}
+
+impl fmt::Debug for ProcAddrLoader {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "<Vulkan vkGetInstanceProcAddr loader>")
+    }
+}
+
+/// Implementation of `Loader` that loads Vulkan from a dynamic library.
+pub struct DynamicLibraryLoader {
+    vk_lib: shared_library::dynamic_library::DynamicLibrary,
+    get_proc_addr: GetInstanceProcAddr,
+}
+
+impl DynamicLibraryLoader {
+    /// Tries to load the dynamic library at the given path, and tries to load
+    /// `vkGetInstanceProcAddr` in it.
+    ///
+    /// # Safety
+    ///
+    /// - The dynamic library must be a valid Vulkan implementation.
+    ///
+    pub unsafe fn new<P>(path: P) -> Result<DynamicLibraryLoader, LoadingError>
+        where P: AsRef<Path>
+    {
+        let vk_lib = try!(shared_library::dynamic_library::DynamicLibrary::open(Some(path.as_ref()))
+                                    .map_err(LoadingError::LibraryLoadFailure));
+
+        let get_proc_addr = {
+            let ptr: *mut c_void = try!(vk_lib.symbol("vkGetInstanceProcAddr")
+                .map_err(|_| LoadingError::MissingEntryPoint("vkGetInstanceProcAddr".to_owned())));
+            mem::transmute(ptr)
+        };
+
+        Ok(DynamicLibraryLoader {
+            vk_lib: vk_lib,
+            get_proc_addr: get_proc_addr,
+        })
+    }
+}
+
+unsafe impl Loader for DynamicLibraryLoader {
+    #[inline]
+    fn get_instance_proc_addr(&self, instance: vk::Instance, name: *const c_char)
+                              -> extern "system" fn() -> ()
+    {
+        (self.get_proc_addr)(instance, name)
+    }
+}
+
+impl fmt::Debug for DynamicLibraryLoader {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "<Vulkan dynamic library loader>")
+    }
+}
+
+/// Wraps around a loader and contains function pointers.
+pub struct FunctionPointers<L> {
+    loader: L,
+    entry_points: vk::EntryPoints,
+}
+
+impl<L> FunctionPointers<L> {
+    /// Loads some global function pointer from the loader.
+    pub fn new(loader: L) -> FunctionPointers<L>
+        where L: Loader
+    {
+        let entry_points = vk::EntryPoints::load(|name| unsafe {
+            mem::transmute(loader.get_instance_proc_addr(0, name.as_ptr()))
+        });
+
+        FunctionPointers {
+            loader: loader,
+            entry_points: entry_points,
         }
-    };
+    }
+
+    /// Returns the collection of Vulkan entry points from the Vulkan loader.
+    #[doc(hidden)]
+    #[inline]
+    pub fn entry_points(&self) -> &vk::EntryPoints {
+        &self.entry_points
+    }
+
+    /// Calls `get_instance_proc_addr` on the underlying loader.
+    #[inline]
+    pub fn get_instance_proc_addr(&self, instance: vk::Instance, name: *const c_char)
+                                  -> extern "system" fn() -> ()
+        where L: Loader
+    {
+        self.loader.get_instance_proc_addr(instance, name)
+    }
 }
 
-/// Returns the collection of static functions from the Vulkan loader, or an error if failed to
-/// open the loader.
-pub fn static_functions() -> Result<&'static vk::Static, LoadingError> {
-    VK_STATIC.as_ref().map_err(|err| err.clone())
+impl<L> fmt::Debug for FunctionPointers<L> {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "<Vulkan function pointers>")
+    }
 }
 
-/// Returns the collection of Vulkan entry points from the Vulkan loader, or an error if failed to
-/// open the loader.
-pub fn entry_points() -> Result<&'static vk::EntryPoints, LoadingError> {
-    VK_ENTRY.as_ref().map_err(|err| err.clone())
+/// Returns the default `FunctionPointers` for this system.
+///
+/// This function tries to auto-guess where to find the Vulkan implementation, and loads it in a
+/// `lazy_static`. The content of the lazy_static is then returned, or an error if we failed to
+/// load Vulkan.
+pub fn auto_loader()
+    -> Result<&'static FunctionPointers<Box<Loader + Send + Sync>>, LoadingError>
+{
+    lazy_static! {
+        static ref DEFAULT_LOADER: Result<FunctionPointers<Box<Loader + Send + Sync>>, LoadingError> = {
+            #[cfg(windows)] fn get_path() -> &'static Path { Path::new("vulkan-1.dll") }
+            #[cfg(all(unix, not(target_os = "android")))] fn get_path() -> &'static Path { Path::new("libvulkan.so.1") }
+            #[cfg(target_os = "android")] fn get_path() -> &'static Path { Path::new("libvulkan.so") }
+
+            unsafe {
+                let loader = try!(DynamicLibraryLoader::new(get_path()));
+                Ok(FunctionPointers::new(Box::new(loader) as Box<_>))
+            }
+        };
+    }
+
+    match *DEFAULT_LOADER {
+        Ok(ref ptr) => Ok(ptr),
+        Err(ref err) => Err(err.clone()),
+    }
 }
 
 /// Error that can happen when loading the Vulkan loader.
@@ -117,3 +256,19 @@ impl fmt::Display for LoadingError {
         write!(fmt, "{}", error::Error::description(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use instance::loader::DynamicLibraryLoader;
+    use instance::loader::LoadingError;
+
+    #[test]
+    fn dl_open_error() {
+        unsafe {
+            match DynamicLibraryLoader::new("_non_existing_library.void") {
+                Err(LoadingError::LibraryLoadFailure(_)) => (),
+                _ => panic!()
+            }
+        }
+    }
+}
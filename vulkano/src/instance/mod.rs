@@ -92,6 +92,13 @@
 //! let _instance = Instance::new(Some(&app_infos), &InstanceExtensions::none(), None).unwrap();
 //! ```
 //!
+//! # Loading Vulkan
+//!
+//! By default `Instance::new()` loads the system's Vulkan loader (eg. `libvulkan.so.1` on Linux
+//! or `vulkan-1.dll` on Windows). If you want to use another implementation, for example a
+//! library that you ship with your application, you can use `Instance::with_loader()` instead.
+//! See the `loader` module for more information.
+//!
 //! # Enumerating physical devices and creating a device
 //!
 //! After you have created an instance, the next step is usually to enumerate the physical devices
@@ -121,6 +128,7 @@ pub use self::instance::MemoryHeapsIter;
 pub use self::instance::MemoryHeap;
 pub use self::instance::Limits;
 pub use self::layers::layers_list;
+pub use self::layers::layers_list_from_loader;
 pub use self::layers::LayerProperties;
 pub use self::layers::LayersIterator;
 pub use self::layers::LayersListError;
@@ -128,8 +136,8 @@ pub use self::loader::LoadingError;
 pub use version::Version;
 
 pub mod debug;
+pub mod loader;
 
 mod extensions;
 mod instance;
 mod layers;
-mod loader;
@@ -62,6 +62,7 @@ extern crate vk_sys as vk;
 
 #[macro_use]
 mod tests;
+mod mock;
 
 mod features;
 mod version;
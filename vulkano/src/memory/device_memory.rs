@@ -15,6 +15,8 @@ use std::ops::Range;
 use std::os::raw::c_void;
 use std::sync::Arc;
 
+use buffer::sys::UnsafeBuffer;
+use image::sys::UnsafeImage;
 use instance::MemoryType;
 use device::Device;
 use memory::Content;
@@ -65,6 +67,26 @@ impl<D> DeviceMemory<D> where D: SafeDeref<Target = Device> {
     pub fn alloc(device: &D, memory_type: MemoryType, size: usize)
                  -> Result<DeviceMemory<D>, OomError>
         where D: Clone
+    {
+        DeviceMemory::dedicated_alloc(device, memory_type, size, DedicatedAlloc::None)
+    }
+
+    /// Same as `alloc`, but the memory is reserved for a single buffer or image.
+    ///
+    /// If the `khr_dedicated_allocation` extension is enabled on the device, the driver is told
+    /// which resource the memory is for, which may let it place the resource more efficiently.
+    /// Otherwise this is equivalent to `alloc`.
+    ///
+    /// The resource must then be bound at offset 0 of the returned memory.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `size` is 0.
+    /// - Panics if `memory_type` doesn't belong to the same physical device as `device`.
+    ///
+    pub fn dedicated_alloc(device: &D, memory_type: MemoryType, size: usize,
+                           resource: DedicatedAlloc) -> Result<DeviceMemory<D>, OomError>
+        where D: Clone
     {
         assert!(size >= 1);
         assert_eq!(device.physical_device().internal_object(),
@@ -77,9 +99,36 @@ impl<D> DeviceMemory<D> where D: SafeDeref<Target = Device> {
         let vk = device.pointers();
 
         let memory = unsafe {
+            let dedicated = if device.loaded_extensions().khr_dedicated_allocation {
+                match resource {
+                    DedicatedAlloc::Buffer(buffer) => {
+                        Some(vk::MemoryDedicatedAllocateInfoKHR {
+                            sType: vk::STRUCTURE_TYPE_MEMORY_DEDICATED_ALLOCATE_INFO_KHR,
+                            pNext: ptr::null(),
+                            image: 0,
+                            buffer: buffer.internal_object(),
+                        })
+                    },
+                    DedicatedAlloc::Image(image) => {
+                        Some(vk::MemoryDedicatedAllocateInfoKHR {
+                            sType: vk::STRUCTURE_TYPE_MEMORY_DEDICATED_ALLOCATE_INFO_KHR,
+                            pNext: ptr::null(),
+                            image: image.internal_object(),
+                            buffer: 0,
+                        })
+                    },
+                    DedicatedAlloc::None => None,
+                }
+            } else {
+                None
+            };
+
             let infos = vk::MemoryAllocateInfo {
                 sType: vk::STRUCTURE_TYPE_MEMORY_ALLOCATE_INFO,
-                pNext: ptr::null(),
+                pNext: match dedicated {
+                    Some(ref d) => d as *const vk::MemoryDedicatedAllocateInfoKHR as *const _,
+                    None => ptr::null(),
+                },
                 allocationSize: size as u64,
                 memoryTypeIndex: memory_type.id(),
             };
@@ -105,16 +154,37 @@ impl<D> DeviceMemory<D> where D: SafeDeref<Target = Device> {
     /// - Panics if `memory_type` doesn't belong to the same physical device as `device`.
     /// - Panics if the memory type is not host-visible.
     ///
+    #[inline]
     pub fn alloc_and_map(device: &D, memory_type: MemoryType, size: usize)
                          -> Result<MappedDeviceMemory<D>, OomError>
         where D: Clone
+    {
+        DeviceMemory::dedicated_alloc_and_map(device, memory_type, size, DedicatedAlloc::None)
+    }
+
+    /// Equivalent of `dedicated_alloc` for `alloc_and_map`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `memory_type` doesn't belong to the same physical device as `device`.
+    /// - Panics if the memory type is not host-visible.
+    ///
+    pub fn dedicated_alloc_and_map(device: &D, memory_type: MemoryType, size: usize,
+                                   resource: DedicatedAlloc)
+                                   -> Result<MappedDeviceMemory<D>, OomError>
+        where D: Clone
     {
         let vk = device.pointers();
 
         assert!(memory_type.is_host_visible());
-        let mem = try!(DeviceMemory::alloc(device, memory_type, size));
+        let mem = try!(DeviceMemory::dedicated_alloc(device, memory_type, size, resource));
 
         let coherent = memory_type.is_host_coherent();
+        let atom_size = if coherent {
+            1
+        } else {
+            device.physical_device().limits().non_coherent_atom_size() as usize
+        };
 
         let ptr = unsafe {
             let mut output = mem::uninitialized();
@@ -128,6 +198,7 @@ impl<D> DeviceMemory<D> where D: SafeDeref<Target = Device> {
             memory: mem,
             pointer: ptr,
             coherent: coherent,
+            atom_size: atom_size,
         })
     }
 
@@ -150,6 +221,17 @@ impl<D> DeviceMemory<D> where D: SafeDeref<Target = Device> {
     }
 }
 
+/// Resource that a dedicated allocation is made for. See `DeviceMemory::dedicated_alloc`.
+#[derive(Debug, Copy, Clone)]
+pub enum DedicatedAlloc<'a> {
+    /// The allocation isn't dedicated to a particular resource.
+    None,
+    /// The allocation will be used for this buffer.
+    Buffer(&'a UnsafeBuffer),
+    /// The allocation will be used for this image.
+    Image(&'a UnsafeImage),
+}
+
 unsafe impl<D> VulkanObject for DeviceMemory<D> where D: SafeDeref<Target = Device> {
     type Object = vk::DeviceMemory;
 
@@ -178,6 +260,12 @@ impl<D> Drop for DeviceMemory<D> where D: SafeDeref<Target = Device> {
 /// In order to access the content of the allocated memory, you can use the `read_write` method.
 /// This method returns a guard object that derefs to the content. 
 ///
+/// If the memory type is not host-coherent, the writes made by the CPU must be flushed before
+/// the device can see them, and the writes made by the device must be invalidated before the CPU
+/// can see them. The `read` and `read_write` methods take care of this automatically, and the
+/// `flush_range` and `invalidate_range` methods are available if you need more control. In both
+/// cases the ranges are extended to multiples of the `non_coherent_atom_size` limit of the device.
+///
 /// # Example
 ///
 /// ```no_run
@@ -195,7 +283,7 @@ impl<D> Drop for DeviceMemory<D> where D: SafeDeref<Target = Device> {
 /// // Get access to the content. Note that this is very unsafe for two reasons: 1) the content is
 /// // uninitialized, and 2) the access is unsynchronized.
 /// unsafe {
-///     let mut content = memory.read_write::<[u8]>(0 .. 1024);
+///     let mut content = memory.read_write::<[u8]>(0 .. 1024).unwrap();
 ///     content[12] = 54;       // `content` derefs to a `&[u8]` or a `&mut [u8]`
 /// }
 /// ```
@@ -204,6 +292,8 @@ pub struct MappedDeviceMemory<D = Arc<Device>> where D: SafeDeref<Target = Devic
     memory: DeviceMemory<D>,
     pointer: *mut c_void,
     coherent: bool,
+    // Value of `non_coherent_atom_size`, or 1 if the memory is coherent.
+    atom_size: usize,
 }
 
 impl<D> MappedDeviceMemory<D> where D: SafeDeref<Target = Device> {
@@ -214,6 +304,105 @@ impl<D> MappedDeviceMemory<D> where D: SafeDeref<Target = Device> {
         &self.memory
     }
 
+    /// Returns true if the memory is host-coherent, in which case flushing and invalidating are
+    /// no-ops.
+    #[inline]
+    pub fn is_coherent(&self) -> bool {
+        self.coherent
+    }
+
+    /// Makes the writes done by the CPU in the given range visible to the device.
+    ///
+    /// The range is extended so that it is aligned to the `non_coherent_atom_size` limit. Does
+    /// nothing if the memory is host-coherent.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the range is out of the memory.
+    ///
+    #[inline]
+    pub fn flush_range(&self, range: Range<usize>) -> Result<(), OomError> {
+        if self.coherent {
+            return Ok(());
+        }
+
+        let range = self.atom_range(range);
+        let vk = self.memory.device().pointers();
+        unsafe {
+            try!(check_errors(vk.FlushMappedMemoryRanges(self.memory.device().internal_object(),
+                                                         1, &range)));
+        }
+        Ok(())
+    }
+
+    /// Makes the writes done by the device in the given range visible to the CPU.
+    ///
+    /// The range is extended so that it is aligned to the `non_coherent_atom_size` limit. Does
+    /// nothing if the memory is host-coherent.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the range is out of the memory.
+    ///
+    /// # Safety
+    ///
+    /// Any write made by the CPU in the extended range that hasn't been flushed is lost.
+    ///
+    #[inline]
+    pub unsafe fn invalidate_range(&self, range: Range<usize>) -> Result<(), OomError> {
+        if self.coherent {
+            return Ok(());
+        }
+
+        let range = self.atom_range(range);
+        let vk = self.memory.device().pointers();
+        try!(check_errors(vk.InvalidateMappedMemoryRanges(self.memory.device().internal_object(),
+                                                          1, &range)));
+        Ok(())
+    }
+
+    // Builds a `VkMappedMemoryRange` that covers `range`, with the start and end aligned to the
+    // atom size. The end doesn't need to be aligned if it is the end of the memory.
+    fn atom_range(&self, range: Range<usize>) -> vk::MappedMemoryRange {
+        assert!(range.start <= range.end);
+        assert!(range.end <= self.memory.size());
+
+        let start = range.start - range.start % self.atom_size;
+        let end = match range.end % self.atom_size {
+            0 => range.end,
+            rem => range.end - rem + self.atom_size,
+        };
+        let end = if end > self.memory.size() { self.memory.size() } else { end };
+
+        vk::MappedMemoryRange {
+            sType: vk::STRUCTURE_TYPE_MAPPED_MEMORY_RANGE,
+            pNext: ptr::null(),
+            memory: self.memory.internal_object(),
+            offset: start as u64,
+            size: (end - start) as u64,
+        }
+    }
+
+    /// Gives access to the content of the memory, for reading only.
+    ///
+    /// Same as `read_write`, except that the range is not flushed when the returned object is
+    /// destroyed. Any modification made through it may therefore never become visible to the
+    /// device.
+    ///
+    /// # Safety
+    ///
+    /// Same as `read_write`.
+    ///
+    #[inline]
+    pub unsafe fn read<T: ?Sized>(&self, range: Range<usize>)
+                                  -> Result<CpuAccess<T, D>, OomError>
+        where T: Content + 'static
+    {
+        let mut access = try!(self.read_write(range));
+        access.write = false;
+        Ok(access)
+    }
+
     /// Gives access to the content of the memory.
     ///
     /// This function takes care of calling `vkInvalidateMappedMemoryRanges` and
@@ -221,6 +410,8 @@ impl<D> MappedDeviceMemory<D> where D: SafeDeref<Target = Device> {
     /// smallest range as possible, and to not call this function multiple times in a row for
     /// several small changes.
     ///
+    /// Returns an error if the memory is not host-coherent and invalidating the range failed.
+    ///
     /// # Safety
     ///
     /// - Type safety is not checked. You must ensure that `T` corresponds to the content of the
@@ -229,32 +420,21 @@ impl<D> MappedDeviceMemory<D> where D: SafeDeref<Target = Device> {
     ///   the `MappedDeviceMemory`.
     ///
     #[inline]
-    pub unsafe fn read_write<T: ?Sized>(&self, range: Range<usize>) -> CpuAccess<T, D>
+    pub unsafe fn read_write<T: ?Sized>(&self, range: Range<usize>)
+                                        -> Result<CpuAccess<T, D>, OomError>
         where T: Content + 'static
     {
-        let vk = self.memory.device().pointers();
         let pointer = T::ref_from_ptr((self.pointer as usize + range.start) as *mut _,
                                       range.end - range.start).unwrap();       // TODO: error
 
-        if !self.coherent {
-            let range = vk::MappedMemoryRange {
-                sType: vk::STRUCTURE_TYPE_MAPPED_MEMORY_RANGE,
-                pNext: ptr::null(),
-                memory: self.memory.internal_object(),
-                offset: range.start as u64,
-                size: (range.end - range.start) as u64,
-            };
-
-            // TODO: check result?
-            vk.InvalidateMappedMemoryRanges(self.memory.device().internal_object(), 1, &range);
-        }
+        try!(self.invalidate_range(range.clone()));
 
-        CpuAccess {
+        Ok(CpuAccess {
             pointer: pointer,
             mem: self,
-            coherent: self.coherent,
+            write: true,
             range: range,
-        }
+        })
     }
 }
 
@@ -278,7 +458,8 @@ impl<D> Drop for MappedDeviceMemory<D> where D: SafeDeref<Target = Device> {
 pub struct CpuAccess<'a, T: ?Sized + 'a, D = Arc<Device>> where D: SafeDeref<Target = Device> + 'a {
     pointer: *mut T,
     mem: &'a MappedDeviceMemory<D>,
-    coherent: bool,
+    // True if the range must be flushed when the object is destroyed.
+    write: bool,
     range: Range<usize>,
 }
 
@@ -293,12 +474,16 @@ impl<'a, T: ?Sized + 'a, D: 'a> CpuAccess<'a, T, D> where D: SafeDeref<Target =
     pub fn map<U: ?Sized + 'a, F>(self, f: F) -> CpuAccess<'a, U, D>
         where F: FnOnce(*mut T) -> *mut U
     {
-        CpuAccess {
+        let access = CpuAccess {
             pointer: f(self.pointer),
             mem: self.mem,
-            coherent: self.coherent,
+            write: self.write,
             range: self.range.clone(),  // TODO: ?
-        }
+        };
+
+        // The range will be flushed when `access` is destroyed.
+        mem::forget(self);
+        access
     }
 }
 
@@ -328,23 +513,11 @@ impl<'a, T: ?Sized + 'a, D: 'a> DerefMut for CpuAccess<'a, T, D>
 impl<'a, T: ?Sized + 'a, D: 'a> Drop for CpuAccess<'a, T, D> where D: SafeDeref<Target = Device> {
     #[inline]
     fn drop(&mut self) {
-        // If the memory doesn't have the `coherent` flag, we need to flush the data.
-        if !self.coherent {
-            let vk = self.mem.memory().device().pointers();
-
-            let range = vk::MappedMemoryRange {
-                sType: vk::STRUCTURE_TYPE_MAPPED_MEMORY_RANGE,
-                pNext: ptr::null(),
-                memory: self.mem.memory().internal_object(),
-                offset: self.range.start as u64,
-                size: (self.range.end - self.range.start) as u64,
-            };
-
+        // If the memory doesn't have the `coherent` flag, we need to flush the data. This is
+        // handled by `flush_range`.
+        if self.write {
             // TODO: check result?
-            unsafe {
-                vk.FlushMappedMemoryRanges(self.mem.memory().device().internal_object(),
-                                           1, &range);
-            }
+            let _ = self.mem.flush_range(self.range.clone());
         }
     }
 }
@@ -353,6 +526,7 @@ impl<'a, T: ?Sized + 'a, D: 'a> Drop for CpuAccess<'a, T, D> where D: SafeDeref<
 mod tests {
     use OomError;
     use memory::DeviceMemory;
+    use mock;
 
     #[test]
     fn create() {
@@ -401,4 +575,56 @@ mod tests {
 
         panic!()
     }
+
+    #[test]
+    fn non_coherent_flush_invalidate() {
+        let (device, _) = mock_dev_and_queue!(queue: |_| true);
+        let mem_ty = device.physical_device().memory_types()
+                           .filter(|t| t.is_host_visible() && !t.is_host_coherent())
+                           .next().unwrap();
+
+        // The mock checks that the ranges are aligned to the atom size, except at the end of
+        // the memory.
+        let memory = DeviceMemory::alloc_and_map(&device, mem_ty, 1000).unwrap();
+        assert!(!memory.is_coherent());
+        let calls_before = mock::calls(&device).len();
+
+        unsafe {
+            let mut content = memory.read_write::<[u8]>(70 .. 130).unwrap();
+            content[0] = 12;
+        }
+        assert_eq!(&mock::calls(&device)[calls_before ..],
+                   &["InvalidateMappedMemoryRanges", "FlushMappedMemoryRanges"]);
+
+        unsafe {
+            let content = memory.read::<[u8]>(950 .. 1000).unwrap();
+            assert_eq!(content[0], 0);
+        }
+        assert_eq!(&mock::calls(&device)[calls_before + 2 ..], &["InvalidateMappedMemoryRanges"]);
+
+        memory.flush_range(999 .. 1000).unwrap();
+        unsafe { memory.invalidate_range(0 .. 1).unwrap(); }
+        assert_eq!(mock::calls(&device)[calls_before + 3 ..].len(), 2);
+    }
+
+    #[test]
+    fn coherent_no_flush() {
+        let (device, _) = mock_dev_and_queue!(queue: |_| true);
+        let mem_ty = device.physical_device().memory_types()
+                           .filter(|t| t.is_host_coherent())
+                           .next().unwrap();
+
+        let memory = DeviceMemory::alloc_and_map(&device, mem_ty, 1000).unwrap();
+        assert!(memory.is_coherent());
+
+        unsafe {
+            let mut content = memory.read_write::<[u8]>(70 .. 130).unwrap();
+            content[0] = 12;
+        }
+        memory.flush_range(0 .. 1000).unwrap();
+
+        let calls = mock::calls(&device);
+        assert!(!calls.contains(&"FlushMappedMemoryRanges"));
+        assert!(!calls.contains(&"InvalidateMappedMemoryRanges"));
+    }
 }
@@ -49,6 +49,9 @@
 //! you use the memory types that are earlier in the list. This means that selecting a memory type
 //! should always be done by enumerating them and taking the first one that matches our criterias.
 //!
+//! The `MemoryUsage` enum implements this for the common ways a resource is accessed, and is
+//! what the buffer and image types of vulkano use to choose their memory type.
+//!
 //! ## In practice
 //!
 //! In practice, desktop machines usually have two memory heaps: one that represents the RAM of
@@ -92,12 +95,16 @@ use std::slice;
 use vk;
 
 pub use self::device_memory::CpuAccess;
+pub use self::device_memory::DedicatedAlloc;
 pub use self::device_memory::DeviceMemory;
 pub use self::device_memory::MappedDeviceMemory;
 pub use self::pool::MemoryPool;
+pub use self::usage::MemoryTypeProperties;
+pub use self::usage::MemoryUsage;
 
 mod device_memory;
 pub mod pool;
+mod usage;
 
 /// Represents requirements expressed by the Vulkan implementation.
 #[derive(Debug, Copy, Clone)]
@@ -112,6 +119,15 @@ pub struct MemoryRequirements {
     /// Indicates which memory types can be used. Each bit that is set to 1 means that the memory
     /// type whose index is the same as the position of the bit can be used.
     pub memory_type_bits: u32,
+
+    /// True if the implementation would prefer the resource to have its own `DeviceMemory`
+    /// instead of being sub-allocated. Always false if the `khr_dedicated_allocation` extension
+    /// isn't enabled.
+    pub prefers_dedicated: bool,
+
+    /// True if the resource must have its own `DeviceMemory`. Always false if the
+    /// `khr_dedicated_allocation` extension isn't enabled.
+    pub requires_dedicated: bool,
 }
 
 #[doc(hidden)]
@@ -122,6 +138,8 @@ impl From<vk::MemoryRequirements> for MemoryRequirements {
             size: reqs.size as usize,
             alignment: reqs.alignment as usize,
             memory_type_bits: reqs.memoryTypeBits,
+            prefers_dedicated: false,
+            requires_dedicated: false,
         }
     }
 }
@@ -0,0 +1,379 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Buddy allocator used to sub-allocate ranges of a single memory block.
+//!
+//! The block is recursively split in two halves ("buddies") until a node of the right size is
+//! found. Every node is aligned to its own size, which means that the alignment requirement of
+//! an allocation is honored by simply requesting a node at least as large as the alignment.
+//!
+//! In addition to this, the allocator keeps track of the layout of the objects that live in
+//! each page of `buffer_image_granularity` bytes. A node smaller than a page is never handed
+//! out if the page it lives in already contains an object with a different layout. Nodes that
+//! are at least as large as a page always cover whole pages and don't need any tracking.
+//!
+//! This module doesn't know anything about Vulkan and is only concerned with offsets.
+
+use std::cmp;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+use fnv::FnvHasher;
+
+use memory::pool::AllocLayout;
+
+/// Size of the smallest node that the allocator can hand out.
+const MIN_NODE_SIZE: usize = 64;
+
+/// Sub-allocator for a block of memory.
+#[derive(Debug)]
+pub struct BuddyAllocator {
+    // Size of the whole block. Always a power of two.
+    size: usize,
+    // Value of `buffer_image_granularity`, rounded up to a power of two.
+    granularity: usize,
+    // For each order, the offsets of the nodes that are free. The node size of order `n` is
+    // `MIN_NODE_SIZE << n`. We use a `BTreeSet` so that the lowest offset is always picked
+    // first, which keeps the allocations packed at the start of the block.
+    free: Vec<BTreeSet<usize>>,
+    // Order of each node currently allocated and the number of bytes that were requested,
+    // indexed by its offset.
+    allocated: HashMap<usize, (usize, usize), BuildHasherDefault<FnvHasher>>,
+    // Sum of the sizes that were requested for the nodes currently allocated.
+    allocated_bytes: usize,
+    // For each page of `granularity` bytes that contains nodes smaller than a page, the layout
+    // of the objects within this page and the number of such nodes.
+    pages: HashMap<usize, (AllocLayout, usize), BuildHasherDefault<FnvHasher>>,
+}
+
+impl BuddyAllocator {
+    /// Builds a new allocator that manages `size` bytes.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `size` is not a power of two or is smaller than the minimal node size.
+    /// - Panics if `granularity` is 0.
+    ///
+    pub fn new(size: usize, granularity: usize) -> BuddyAllocator {
+        assert!(size.is_power_of_two());
+        assert!(size >= MIN_NODE_SIZE);
+        assert!(granularity != 0);
+
+        let max_order = order_of(size);
+        let mut free = (0 .. max_order + 1).map(|_| BTreeSet::new()).collect::<Vec<_>>();
+        free[max_order].insert(0);
+
+        BuddyAllocator {
+            size: size,
+            granularity: granularity.next_power_of_two(),
+            free: free,
+            allocated: HashMap::default(),
+            allocated_bytes: 0,
+            pages: HashMap::default(),
+        }
+    }
+
+    /// Returns the number of bytes managed by this allocator.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the number of allocations currently alive.
+    #[inline]
+    pub fn num_allocations(&self) -> usize {
+        self.allocated.len()
+    }
+
+    /// Returns the sum of the sizes that were requested by the allocations currently alive.
+    ///
+    /// Because of the rounding to powers of two, the amount of memory actually consumed is
+    /// usually higher.
+    #[inline]
+    pub fn allocated_bytes(&self) -> usize {
+        self.allocated_bytes
+    }
+
+    /// Returns the size of the largest free node, which is the largest request that can
+    /// succeed regardless of its alignment and layout.
+    #[inline]
+    pub fn largest_free_range(&self) -> usize {
+        match self.free.iter().rposition(|list| !list.is_empty()) {
+            Some(order) => MIN_NODE_SIZE << order,
+            None => 0,
+        }
+    }
+
+    /// Returns the number of bytes that a request of `size` bytes aligned to `alignment` would
+    /// actually consume.
+    #[inline]
+    pub fn node_size(size: usize, alignment: usize) -> usize {
+        cmp::max(cmp::max(size, alignment), MIN_NODE_SIZE).next_power_of_two()
+    }
+
+    /// Tries to allocate `size` bytes aligned to `alignment`. Returns the offset of the
+    /// allocation, or `None` if there isn't enough contiguous free space.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `size` or `alignment` is 0.
+    /// - Panics if `alignment` is not a power of two.
+    ///
+    pub fn alloc(&mut self, size: usize, alignment: usize, layout: AllocLayout) -> Option<usize> {
+        assert!(size != 0);
+        assert!(alignment.is_power_of_two());
+
+        let node_size = BuddyAllocator::node_size(size, alignment);
+        if node_size > self.size {
+            return None;
+        }
+
+        let order = order_of(node_size);
+
+        // Find the smallest free node that can hold the allocation.
+        let mut found = None;
+        for search_order in order .. self.free.len() {
+            let search_size = MIN_NODE_SIZE << search_order;
+
+            let candidate = self.free[search_order].iter().cloned().find(|&offset| {
+                // If the node that we return covers whole pages, or if the node that we split
+                // covers whole pages, then there's no possible conflict with a neighbour.
+                if node_size >= self.granularity || search_size >= self.granularity {
+                    return true;
+                }
+
+                match self.pages.get(&(offset / self.granularity)) {
+                    Some(&(page_layout, _)) => page_layout == layout,
+                    None => true,
+                }
+            });
+
+            if let Some(offset) = candidate {
+                found = Some((search_order, offset));
+                break;
+            }
+        }
+
+        let (mut current_order, offset) = match found {
+            Some(f) => f,
+            None => return None,
+        };
+
+        // Split the node until it has the right size. We always keep the lower half and
+        // release the upper half.
+        self.free[current_order].remove(&offset);
+        while current_order > order {
+            current_order -= 1;
+            self.free[current_order].insert(offset + (MIN_NODE_SIZE << current_order));
+        }
+
+        self.allocated.insert(offset, (order, size));
+        self.allocated_bytes += size;
+
+        if node_size < self.granularity {
+            let entry = self.pages.entry(offset / self.granularity).or_insert((layout, 0));
+            debug_assert_eq!(entry.0, layout);
+            entry.1 += 1;
+        }
+
+        Some(offset)
+    }
+
+    /// Frees the allocation that starts at `offset`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `offset` doesn't correspond to an allocation.
+    ///
+    pub fn free(&mut self, offset: usize) {
+        let (mut order, size) = self.allocated.remove(&offset)
+                                              .expect("freeing an unknown allocation");
+        self.allocated_bytes -= size;
+
+        if (MIN_NODE_SIZE << order) < self.granularity {
+            let page = offset / self.granularity;
+            let empty = {
+                let entry = self.pages.get_mut(&page).unwrap();
+                entry.1 -= 1;
+                entry.1 == 0
+            };
+            if empty {
+                self.pages.remove(&page);
+            }
+        }
+
+        // Merge the node with its buddy for as long as the buddy is free.
+        let mut offset = offset;
+        while order + 1 < self.free.len() {
+            let buddy = offset ^ (MIN_NODE_SIZE << order);
+            if !self.free[order].remove(&buddy) {
+                break;
+            }
+            offset = cmp::min(offset, buddy);
+            order += 1;
+        }
+
+        self.free[order].insert(offset);
+    }
+}
+
+// Returns the order of a node of the given size. The size must be a power of two greater or
+// equal to `MIN_NODE_SIZE`.
+#[inline]
+fn order_of(node_size: usize) -> usize {
+    debug_assert!(node_size.is_power_of_two());
+    debug_assert!(node_size >= MIN_NODE_SIZE);
+    (node_size / MIN_NODE_SIZE).trailing_zeros() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use memory::pool::AllocLayout;
+    use super::BuddyAllocator;
+
+    #[test]
+    fn basic_alloc() {
+        let mut alloc = BuddyAllocator::new(1024, 1);
+        assert_eq!(alloc.alloc(100, 1, AllocLayout::Linear), Some(0));
+        assert_eq!(alloc.alloc(100, 1, AllocLayout::Linear), Some(128));
+        assert_eq!(alloc.alloc(200, 1, AllocLayout::Linear), Some(256));
+        assert_eq!(alloc.alloc(512, 1, AllocLayout::Linear), Some(512));
+        assert_eq!(alloc.alloc(1, 1, AllocLayout::Linear), None);
+    }
+
+    #[test]
+    fn too_large() {
+        let mut alloc = BuddyAllocator::new(1024, 1);
+        assert_eq!(alloc.alloc(1025, 1, AllocLayout::Linear), None);
+        assert_eq!(alloc.alloc(1, 2048, AllocLayout::Linear), None);
+        assert!(alloc.allocated.is_empty());
+    }
+
+    #[test]
+    fn alignment() {
+        let mut alloc = BuddyAllocator::new(4096, 1);
+        assert_eq!(alloc.alloc(16, 1, AllocLayout::Linear), Some(0));
+        let offset = alloc.alloc(16, 512, AllocLayout::Linear).unwrap();
+        assert_eq!(offset % 512, 0);
+        assert_eq!(offset, 512);
+    }
+
+    #[test]
+    fn free_and_merge() {
+        let mut alloc = BuddyAllocator::new(1024, 1);
+        let a = alloc.alloc(64, 1, AllocLayout::Linear).unwrap();
+        let b = alloc.alloc(64, 1, AllocLayout::Linear).unwrap();
+        let c = alloc.alloc(256, 1, AllocLayout::Linear).unwrap();
+        assert_eq!(alloc.alloc(1024, 1, AllocLayout::Linear), None);
+
+        alloc.free(b);
+        alloc.free(a);
+        alloc.free(c);
+        assert!(alloc.allocated.is_empty());
+
+        // Everything has been merged back into a single node.
+        assert_eq!(alloc.alloc(1024, 1, AllocLayout::Linear), Some(0));
+    }
+
+    #[test]
+    fn reuse_freed_hole() {
+        let mut alloc = BuddyAllocator::new(1024, 1);
+        let a = alloc.alloc(64, 1, AllocLayout::Linear).unwrap();
+        let _b = alloc.alloc(64, 1, AllocLayout::Linear).unwrap();
+        let _c = alloc.alloc(128, 1, AllocLayout::Linear).unwrap();
+        alloc.free(a);
+        assert_eq!(alloc.alloc(32, 1, AllocLayout::Linear), Some(a));
+    }
+
+    #[test]
+    fn statistics() {
+        let mut alloc = BuddyAllocator::new(1024, 1);
+        assert_eq!(alloc.num_allocations(), 0);
+        assert_eq!(alloc.allocated_bytes(), 0);
+        assert_eq!(alloc.largest_free_range(), 1024);
+
+        let a = alloc.alloc(100, 1, AllocLayout::Linear).unwrap();
+        let b = alloc.alloc(300, 1, AllocLayout::Linear).unwrap();
+        assert_eq!(alloc.num_allocations(), 2);
+        assert_eq!(alloc.allocated_bytes(), 400);
+        assert_eq!(alloc.largest_free_range(), 256);
+
+        alloc.free(b);
+        assert_eq!(alloc.num_allocations(), 1);
+        assert_eq!(alloc.allocated_bytes(), 100);
+        assert_eq!(alloc.largest_free_range(), 512);
+
+        alloc.free(a);
+        assert_eq!(alloc.largest_free_range(), 1024);
+    }
+
+    #[test]
+    #[should_panic]
+    fn double_free() {
+        let mut alloc = BuddyAllocator::new(1024, 1);
+        let a = alloc.alloc(64, 1, AllocLayout::Linear).unwrap();
+        alloc.free(a);
+        alloc.free(a);
+    }
+
+    #[test]
+    fn granularity_separates_layouts() {
+        let mut alloc = BuddyAllocator::new(4096, 1024);
+        let linear = alloc.alloc(64, 1, AllocLayout::Linear).unwrap();
+        let optimal = alloc.alloc(64, 1, AllocLayout::Optimal).unwrap();
+        assert_eq!(linear, 0);
+        assert_eq!(optimal / 1024, 1);
+
+        // Objects with the same layout are packed in the same page.
+        let linear2 = alloc.alloc(64, 1, AllocLayout::Linear).unwrap();
+        let optimal2 = alloc.alloc(64, 1, AllocLayout::Optimal).unwrap();
+        assert_eq!(linear2 / 1024, 0);
+        assert_eq!(optimal2 / 1024, 1);
+    }
+
+    #[test]
+    fn granularity_page_released() {
+        let mut alloc = BuddyAllocator::new(2048, 1024);
+        let a = alloc.alloc(64, 1, AllocLayout::Linear).unwrap();
+        let b = alloc.alloc(1024, 1, AllocLayout::Linear).unwrap();
+        assert_eq!(a, 0);
+        assert_eq!(b, 1024);
+
+        // The only page with free space contains a linear object.
+        assert_eq!(alloc.alloc(64, 1, AllocLayout::Optimal), None);
+
+        // Once the linear object is gone, the page can be used for optimal objects.
+        alloc.free(a);
+        assert_eq!(alloc.alloc(64, 1, AllocLayout::Optimal), Some(0));
+    }
+
+    #[test]
+    fn granularity_large_allocs() {
+        let mut alloc = BuddyAllocator::new(4096, 1024);
+        assert_eq!(alloc.alloc(1024, 1, AllocLayout::Linear), Some(0));
+        assert_eq!(alloc.alloc(1024, 1, AllocLayout::Optimal), Some(1024));
+        assert_eq!(alloc.alloc(2048, 1, AllocLayout::Linear), Some(2048));
+    }
+
+    #[test]
+    fn many_small_allocs() {
+        let mut alloc = BuddyAllocator::new(1 << 20, 1);
+        let offsets = (0 .. (1 << 20) / 64)
+            .map(|_| alloc.alloc(64, 1, AllocLayout::Linear).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(alloc.alloc(1, 1, AllocLayout::Linear), None);
+
+        for offset in offsets {
+            alloc.free(offset);
+        }
+
+        assert!(alloc.allocated.is_empty());
+        assert_eq!(alloc.alloc(1 << 20, 1, AllocLayout::Linear), Some(0));
+    }
+}
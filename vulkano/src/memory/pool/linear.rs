@@ -0,0 +1,505 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::cmp;
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+use std::sync::Arc;
+use std::sync::Mutex;
+use fnv::FnvHasher;
+
+use device::Device;
+use device::DeviceOwned;
+use instance::MemoryType;
+use memory::pool::AllocLayout;
+use memory::pool::MemoryPool;
+use memory::pool::MemoryPoolAlloc;
+use memory::DeviceMemory;
+use memory::MappedDeviceMemory;
+use OomError;
+
+/// Memory pool that allocates memory linearly, meant for resources that only live for a frame.
+///
+/// For each memory type, the pool holds a ring of `DeviceMemory` blocks. Allocating simply
+/// consists in bumping a pointer in the current block, which makes it much cheaper than with a
+/// `StdMemoryPool`. When an allocation is dropped, its memory is not individually reclaimed.
+/// Instead a block is reused from its start as soon as all the allocations made from it have been
+/// dropped.
+///
+/// # Usage
+///
+/// Use this pool for the resources that you create at each frame, for example with
+/// `CpuAccessibleBuffer::from_data_with_pool` or `CpuBufferPool::with_pool`. The resources are
+/// kept alive by the command buffers and the `GpuFuture` of the frame that uses them, and are
+/// destroyed when this future has completed and has been cleaned up (for example with
+/// `cleanup_finished`). At this point the memory of the frame is freed wholesale and the next
+/// frames can reuse it.
+///
+/// Avoid using this pool for long-lived resources, as a single one of them is enough to prevent
+/// the whole block it belongs to from being reused.
+///
+/// The pool never frees its blocks by itself. Allocations that are larger than the block size get
+/// a block of their own, which is kept for later allocations. Call `trim` to free these blocks
+/// once they are no longer in use.
+///
+#[derive(Debug)]
+pub struct LinearMemoryPool {
+    device: Arc<Device>,
+
+    // Minimum size of the blocks allocated from the device.
+    block_size: usize,
+
+    // Value of the `buffer_image_granularity` limit of the device.
+    granularity: usize,
+
+    // Value of the `non_coherent_atom_size` limit of the device.
+    atom_size: usize,
+
+    // For each memory type index, stores the associated ring of blocks.
+    rings: Mutex<HashMap<u32, Ring, BuildHasherDefault<FnvHasher>>>,
+}
+
+impl LinearMemoryPool {
+    /// Creates a new pool that allocates blocks of `block_size` bytes from the device.
+    ///
+    /// Allocations that are larger than `block_size` get a block of their own.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `block_size` is 0.
+    ///
+    #[inline]
+    pub fn new(device: &Arc<Device>, block_size: usize) -> Arc<LinearMemoryPool> {
+        assert!(block_size != 0);
+
+        let cap = device.physical_device().memory_types().len();
+        let hasher = BuildHasherDefault::<FnvHasher>::default();
+
+        Arc::new(LinearMemoryPool {
+            device: device.clone(),
+            block_size: block_size,
+            granularity: device.physical_device().limits().buffer_image_granularity() as usize,
+            atom_size: device.physical_device().limits().non_coherent_atom_size() as usize,
+            rings: Mutex::new(HashMap::with_capacity_and_hasher(cap, hasher)),
+        })
+    }
+
+    /// Returns the device this pool operates on.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    /// Returns the minimum size of the blocks allocated from the device.
+    #[inline]
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Frees the blocks that are larger than `block_size` and whose allocations have all been
+    /// dropped.
+    ///
+    /// You can call this function after a frame that needed unusually large allocations, so that
+    /// their memory is returned to the device. The blocks of the default size are kept.
+    pub fn trim(&self) {
+        let mut rings = self.rings.lock().unwrap();
+
+        for ring in rings.values_mut() {
+            let mut index = 0;
+            while index < ring.blocks.len() {
+                let unused = {
+                    let block = &ring.blocks[index];
+                    block.size > self.block_size && block.state.lock().unwrap().num_allocs == 0
+                };
+
+                if !unused {
+                    index += 1;
+                    continue;
+                }
+
+                // Nobody else holds the block, since all its allocations have been dropped.
+                ring.blocks.remove(index);
+                if ring.current > index {
+                    ring.current -= 1;
+                }
+            }
+
+            if ring.current >= ring.blocks.len() {
+                ring.current = 0;
+            }
+        }
+    }
+}
+
+unsafe impl DeviceOwned for LinearMemoryPool {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+unsafe impl MemoryPool for Arc<LinearMemoryPool> {
+    type Alloc = LinearMemoryPoolAlloc;
+
+    fn alloc(&self, memory_type: MemoryType, size: usize, alignment: usize,
+             layout: AllocLayout) -> Result<LinearMemoryPoolAlloc, OomError>
+    {
+        assert!(size != 0);
+        assert!(alignment != 0);
+        assert_eq!(self.device.physical_device().index(), memory_type.physical_device().index());
+
+        // Flushing or invalidating non-coherent memory affects whole atoms, so we must make sure
+        // that two allocations never share an atom. Aligning the start of all the allocations of
+        // the block is enough for this.
+        let alignment = if memory_type.is_host_visible() && !memory_type.is_host_coherent() {
+            cmp::max(alignment, self.atom_size)
+        } else {
+            alignment
+        };
+
+        let mut rings = self.rings.lock().unwrap();
+        let ring = rings.entry(memory_type.id()).or_insert_with(|| {
+            Ring { blocks: Vec::new(), current: 0 }
+        });
+
+        // Try to allocate from the current block.
+        if let Some(block) = ring.blocks.get(ring.current) {
+            if let Some(offset) = block.try_alloc(size, alignment, layout, self.granularity) {
+                return Ok(LinearMemoryPoolAlloc {
+                    block: block.clone(),
+                    offset: offset,
+                    size: size,
+                });
+            }
+        }
+
+        // Then look for a block whose allocations have all been freed. We visit the blocks in
+        // ring order, so that the blocks of the oldest frames are the first to be reused.
+        for n in 1 .. ring.blocks.len() {
+            let index = (ring.current + n) % ring.blocks.len();
+            let offset = ring.blocks[index].try_alloc_if_free(size, alignment, layout);
+            if let Some(offset) = offset {
+                ring.current = index;
+                return Ok(LinearMemoryPoolAlloc {
+                    block: ring.blocks[index].clone(),
+                    offset: offset,
+                    size: size,
+                });
+            }
+        }
+
+        // We need to allocate a new block.
+        let block_size = cmp::max(self.block_size, size);
+        let memory = if memory_type.is_host_visible() {
+            BlockMemory::Mapped(try!(DeviceMemory::alloc_and_map(&self.device, memory_type,
+                                                                 block_size)))
+        } else {
+            BlockMemory::Unmapped(try!(DeviceMemory::alloc(&self.device, memory_type,
+                                                           block_size)))
+        };
+
+        let block = Arc::new(Block {
+            memory: memory,
+            size: block_size,
+            state: Mutex::new(BlockState { offset: 0, num_allocs: 0, last_layout: None }),
+        });
+
+        let offset = block.try_alloc(size, alignment, layout, self.granularity).unwrap();
+        debug_assert_eq!(offset, 0);
+
+        // The new block is inserted right after the current one, so that it is the last one to
+        // be visited when looking for free blocks.
+        let index = cmp::min(ring.current + 1, ring.blocks.len());
+        ring.blocks.insert(index, block.clone());
+        ring.current = index;
+
+        Ok(LinearMemoryPoolAlloc {
+            block: block,
+            offset: offset,
+            size: size,
+        })
+    }
+}
+
+// Blocks of memory of a memory type.
+#[derive(Debug)]
+struct Ring {
+    blocks: Vec<Arc<Block>>,
+    // Index within `blocks` of the block that allocations are taken from.
+    current: usize,
+}
+
+#[derive(Debug)]
+struct Block {
+    memory: BlockMemory,
+    size: usize,
+    state: Mutex<BlockState>,
+}
+
+#[derive(Debug)]
+enum BlockMemory {
+    Unmapped(DeviceMemory),
+    Mapped(MappedDeviceMemory),
+}
+
+#[derive(Debug)]
+struct BlockState {
+    // Offset of the first free byte of the block.
+    offset: usize,
+    // Number of allocations of this block that are still alive.
+    num_allocs: usize,
+    // Layout of the last object that was allocated, or `None` if the block is empty.
+    last_layout: Option<AllocLayout>,
+}
+
+impl Block {
+    // Tries to allocate memory at the end of the block.
+    fn try_alloc(&self, size: usize, alignment: usize, layout: AllocLayout, granularity: usize)
+                 -> Option<usize>
+    {
+        #[inline] fn align(val: usize, al: usize) -> usize { al * (1 + (val - 1) / al) }
+
+        let mut state = self.state.lock().unwrap();
+
+        let start = match state.last_layout {
+            None => 0,
+            // If the previous object has a different layout, the new object must start on a new
+            // page of `buffer_image_granularity` bytes.
+            Some(last) if last != layout => align(align(state.offset, alignment), granularity),
+            Some(_) => align(state.offset, alignment),
+        };
+
+        if start + size > self.size {
+            return None;
+        }
+
+        state.offset = start + size;
+        state.num_allocs += 1;
+        state.last_layout = Some(layout);
+        Some(start)
+    }
+
+    // Same as `try_alloc`, but only succeeds if the block is entirely free.
+    fn try_alloc_if_free(&self, size: usize, alignment: usize, layout: AllocLayout)
+                         -> Option<usize>
+    {
+        if self.state.lock().unwrap().num_allocs != 0 {
+            return None;
+        }
+
+        // The block is empty, so the granularity doesn't matter. Since nobody else can allocate
+        // from the block while we hold the lock on the ring, the block stays empty in-between.
+        self.try_alloc(size, alignment, layout, 1)
+    }
+
+    fn free(&self) {
+        let mut state = self.state.lock().unwrap();
+        debug_assert!(state.num_allocs >= 1);
+        state.num_allocs -= 1;
+
+        if state.num_allocs == 0 {
+            state.offset = 0;
+            state.last_layout = None;
+        }
+    }
+}
+
+/// Allocation from a `LinearMemoryPool`.
+#[derive(Debug)]
+pub struct LinearMemoryPoolAlloc {
+    block: Arc<Block>,
+    offset: usize,
+    size: usize,
+}
+
+impl LinearMemoryPoolAlloc {
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+unsafe impl MemoryPoolAlloc for LinearMemoryPoolAlloc {
+    #[inline]
+    fn memory(&self) -> &DeviceMemory {
+        match self.block.memory {
+            BlockMemory::Unmapped(ref mem) => mem,
+            BlockMemory::Mapped(ref mem) => mem.memory(),
+        }
+    }
+
+    #[inline]
+    fn mapped_memory(&self) -> Option<&MappedDeviceMemory> {
+        match self.block.memory {
+            BlockMemory::Unmapped(_) => None,
+            BlockMemory::Mapped(ref mem) => Some(mem),
+        }
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl Drop for LinearMemoryPoolAlloc {
+    #[inline]
+    fn drop(&mut self) {
+        self.block.free();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use buffer::CpuBufferPool;
+    use format::R8G8B8A8Unorm;
+    use image::AttachmentImage;
+    use image::Usage as ImageUsage;
+    use memory::pool::AllocLayout;
+    use memory::pool::LinearMemoryPool;
+    use memory::pool::MemoryPool;
+    use memory::pool::MemoryPoolAlloc;
+    use memory::DeviceMemory;
+    use memory::MemoryUsage;
+    use mock;
+
+    #[test]
+    fn bump_and_reuse() {
+        let (device, _) = mock_dev_and_queue!(queue: |_| true);
+        let mem_ty = device.physical_device().memory_types().next().unwrap();
+        let pool = LinearMemoryPool::new(&device, 4096);
+
+        let a = pool.alloc(mem_ty, 100, 16, AllocLayout::Linear).unwrap();
+        let b = pool.alloc(mem_ty, 100, 16, AllocLayout::Linear).unwrap();
+        assert_eq!(a.offset(), 0);
+        assert_eq!(b.offset(), 112);
+        assert!(a.mapped_memory().is_none());
+
+        let block = a.memory() as *const DeviceMemory;
+        drop(a);
+
+        // The block is still partially used, so allocations continue at the end.
+        let c = pool.alloc(mem_ty, 100, 16, AllocLayout::Linear).unwrap();
+        assert_eq!(c.offset(), 224);
+
+        // Once everything is freed, the block is reused from the start.
+        drop(b);
+        drop(c);
+        let d = pool.alloc(mem_ty, 100, 16, AllocLayout::Linear).unwrap();
+        assert_eq!(d.offset(), 0);
+        assert_eq!(d.memory() as *const DeviceMemory, block);
+        assert_eq!(mock::calls(&device).iter().filter(|&&c| c == "AllocateMemory").count(), 1);
+    }
+
+    #[test]
+    fn granularity() {
+        let (device, _) = mock_dev_and_queue!(queue: |_| true);
+        let mem_ty = device.physical_device().memory_types().next().unwrap();
+        let granularity = device.physical_device().limits().buffer_image_granularity() as usize;
+        let pool = LinearMemoryPool::new(&device, 4 * granularity);
+
+        let _a = pool.alloc(mem_ty, 16, 1, AllocLayout::Linear).unwrap();
+        let b = pool.alloc(mem_ty, 16, 1, AllocLayout::Optimal).unwrap();
+        let c = pool.alloc(mem_ty, 16, 1, AllocLayout::Optimal).unwrap();
+        let d = pool.alloc(mem_ty, 16, 1, AllocLayout::Linear).unwrap();
+        assert_eq!(b.offset(), granularity);
+        assert_eq!(c.offset(), granularity + 16);
+        assert_eq!(d.offset(), 2 * granularity);
+    }
+
+    #[test]
+    fn non_coherent_atoms() {
+        let (device, _) = mock_dev_and_queue!(queue: |_| true);
+        let mem_ty = device.physical_device().memory_types()
+                           .filter(|t| t.is_host_visible() && !t.is_host_coherent())
+                           .next().unwrap();
+        let atom = device.physical_device().limits().non_coherent_atom_size() as usize;
+        let pool = LinearMemoryPool::new(&device, 4096);
+
+        let a = pool.alloc(mem_ty, 16, 4, AllocLayout::Linear).unwrap();
+        let b = pool.alloc(mem_ty, 16, 4, AllocLayout::Linear).unwrap();
+        assert_eq!(a.offset(), 0);
+        assert_eq!(b.offset(), atom);
+    }
+
+    #[test]
+    fn ring() {
+        let (device, _) = mock_dev_and_queue!(queue: |_| true);
+        let mem_ty = device.physical_device().memory_types().next().unwrap();
+        let pool = LinearMemoryPool::new(&device, 1024);
+
+        // Two frames, each filling a block.
+        let frame1 = pool.alloc(mem_ty, 1024, 1, AllocLayout::Linear).unwrap();
+        let frame2 = pool.alloc(mem_ty, 1024, 1, AllocLayout::Linear).unwrap();
+        let block1 = frame1.memory() as *const DeviceMemory;
+        let block2 = frame2.memory() as *const DeviceMemory;
+        assert!(block1 != block2);
+
+        // Once the first frame is finished, its block is reused by the third frame.
+        drop(frame1);
+        let frame3 = pool.alloc(mem_ty, 1024, 1, AllocLayout::Linear).unwrap();
+        assert_eq!(frame3.memory() as *const DeviceMemory, block1);
+
+        // Large allocations get their own block.
+        let large = pool.alloc(mem_ty, 4096, 1, AllocLayout::Linear).unwrap();
+        assert_eq!(large.offset(), 0);
+        assert_eq!(large.memory().size(), 4096);
+        assert_eq!(mock::calls(&device).iter().filter(|&&c| c == "AllocateMemory").count(), 3);
+    }
+
+    #[test]
+    fn trim() {
+        let (device, _) = mock_dev_and_queue!(queue: |_| true);
+        let mem_ty = device.physical_device().memory_types().next().unwrap();
+        let pool = LinearMemoryPool::new(&device, 1024);
+
+        let small = pool.alloc(mem_ty, 1024, 1, AllocLayout::Linear).unwrap();
+        let large = pool.alloc(mem_ty, 4096, 1, AllocLayout::Linear).unwrap();
+        let block = small.memory() as *const DeviceMemory;
+
+        // Blocks that are still in use are kept.
+        pool.trim();
+        assert_eq!(mock::calls(&device).iter().filter(|&&c| c == "FreeMemory").count(), 0);
+
+        // Only the large block is freed, and the other one is reused.
+        drop(small);
+        drop(large);
+        pool.trim();
+        assert_eq!(mock::calls(&device).iter().filter(|&&c| c == "FreeMemory").count(), 1);
+
+        let a = pool.alloc(mem_ty, 1024, 1, AllocLayout::Linear).unwrap();
+        assert_eq!(a.memory() as *const DeviceMemory, block);
+        assert_eq!(mock::calls(&device).iter().filter(|&&c| c == "AllocateMemory").count(), 2);
+    }
+
+    #[test]
+    fn resources_with_pool() {
+        let (device, _) = mock_dev_and_queue!(queue: |_| true);
+        let pool = LinearMemoryPool::new(&device, 1024 * 1024);
+
+        let buffer = CpuAccessibleBuffer::from_data_with_pool(&device, &BufferUsage::all(),
+                                                              iter::empty(), 5u32, &pool,
+                                                              MemoryUsage::CpuOnly).unwrap();
+        assert_eq!(*buffer.read().unwrap(), 5);
+
+        let buffer_pool = CpuBufferPool::<u32, _>::with_pool(device.clone(),
+                                                             &BufferUsage::all(),
+                                                             iter::empty(), pool.clone(),
+                                                             MemoryUsage::CpuToGpu)
+                                         .unwrap();
+        let _sub = buffer_pool.next(12);
+
+        let _image = AttachmentImage::with_pool(&device, [32, 32], R8G8B8A8Unorm,
+                                                ImageUsage::none(), &pool,
+                                                MemoryUsage::GpuOnly).unwrap();
+    }
+}
@@ -7,22 +7,37 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::sync::Arc;
+
+use device::Device;
 use instance::MemoryType;
+use memory::DedicatedAlloc;
 use memory::DeviceMemory;
 use memory::MappedDeviceMemory;
+use memory::MemoryRequirements;
 use OomError;
 
+pub use self::linear::LinearMemoryPool;
+pub use self::linear::LinearMemoryPoolAlloc;
+pub use self::pool::MemoryStats;
 pub use self::pool::StdMemoryPool;
 pub use self::pool::StdMemoryPoolAlloc;
+pub use self::pool::StdMemoryPoolStats;
 pub use self::host_visible::StdHostVisibleMemoryTypePool;
 pub use self::host_visible::StdHostVisibleMemoryTypePoolAlloc;
 pub use self::non_host_visible::StdNonHostVisibleMemoryTypePool;
 pub use self::non_host_visible::StdNonHostVisibleMemoryTypePoolAlloc;
 
+mod buddy;
 mod host_visible;
+mod linear;
 mod non_host_visible;
 mod pool;
 
+/// Size above which `alloc_from_requirements` gives a resource its own `DeviceMemory` when the
+/// `khr_dedicated_allocation` extension isn't enabled.
+pub const DEDICATED_ALLOCATION_THRESHOLD: usize = 16 * 1024 * 1024;
+
 /// Pool of GPU-visible memory that can be allocated from.
 // TODO: remove 'static + Send + Sync
 pub unsafe trait MemoryPool: 'static + Send + Sync {
@@ -46,6 +61,80 @@ pub unsafe trait MemoryPool: 'static + Send + Sync {
     ///
     fn alloc(&self, ty: MemoryType, size: usize, alignment: usize, layout: AllocLayout)
              -> Result<Self::Alloc, OomError>;
+
+    /// Allocates memory for a resource with the given requirements.
+    ///
+    /// The resource gets its own `DeviceMemory` instead of being allocated from the pool if the
+    /// requirements say that the implementation requires or prefers it. If the
+    /// `khr_dedicated_allocation` extension isn't enabled, this is instead done for resources
+    /// whose size is at least `DEDICATED_ALLOCATION_THRESHOLD`.
+    ///
+    /// The memory is mapped if `ty` is host-visible.
+    ///
+    /// # Safety
+    ///
+    /// Same as `alloc`. In addition, `resource` must be the resource that the requirements
+    /// belong to, or `DedicatedAlloc::None`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `device` isn't the device which was used to create this pool.
+    /// - Panics if `ty` doesn't belong to the same physical device as `device`.
+    /// - Panics if the requirements don't allow `ty`.
+    ///
+    fn alloc_from_requirements(&self, device: &Arc<Device>, ty: MemoryType,
+                               requirements: &MemoryRequirements, layout: AllocLayout,
+                               resource: DedicatedAlloc)
+                               -> Result<PotentialDedicatedAllocation<Self::Alloc>, OomError>
+        where Self: Sized
+    {
+        assert!(requirements.memory_type_bits & (1 << ty.id()) != 0);
+
+        let dedicated = match resource {
+            DedicatedAlloc::None => false,
+            _ if requirements.requires_dedicated || requirements.prefers_dedicated => true,
+            _ if device.loaded_extensions().khr_dedicated_allocation => false,
+            _ => requirements.size >= DEDICATED_ALLOCATION_THRESHOLD,
+        };
+
+        if !dedicated {
+            let alloc = try!(self.alloc(ty, requirements.size, requirements.alignment, layout));
+            return Ok(PotentialDedicatedAllocation::Generic(alloc));
+        }
+
+        self.alloc_dedicated(device, ty, requirements.size, resource)
+    }
+
+    /// Allocates a `DeviceMemory` of `size` bytes that is reserved to `resource`. Called by
+    /// `alloc_from_requirements` for the resources that must or should get their own memory.
+    ///
+    /// The default implementation allocates the memory directly from the device. Pools that
+    /// keep track of what they allocate should override it.
+    ///
+    /// The memory is mapped if `ty` is host-visible.
+    ///
+    /// # Safety
+    ///
+    /// The returned object must have an offset of 0 and must be at least `size` bytes long.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `device` isn't the device which was used to create this pool.
+    /// - Panics if `ty` doesn't belong to the same physical device as `device`.
+    /// - Panics if `size` is 0.
+    ///
+    fn alloc_dedicated(&self, device: &Arc<Device>, ty: MemoryType, size: usize,
+                       resource: DedicatedAlloc)
+                       -> Result<PotentialDedicatedAllocation<Self::Alloc>, OomError>
+    {
+        if ty.is_host_visible() {
+            let mem = try!(DeviceMemory::dedicated_alloc_and_map(device, ty, size, resource));
+            Ok(PotentialDedicatedAllocation::DedicatedMapped(mem))
+        } else {
+            let mem = try!(DeviceMemory::dedicated_alloc(device, ty, size, resource));
+            Ok(PotentialDedicatedAllocation::Dedicated(mem))
+        }
+    }
 }
 
 /// Object that represents a single allocation. Its destructor should free the chunk.
@@ -61,6 +150,61 @@ pub unsafe trait MemoryPoolAlloc: 'static + Send + Sync {
     /// Returns the offset at the start of the memory where the first byte of this allocation
     /// resides.
     fn offset(&self) -> usize;
+
+    /// Returns true if the memory was allocated with `MemoryPool::alloc_dedicated` and belongs
+    /// to a single resource.
+    #[inline]
+    fn is_dedicated(&self) -> bool {
+        false
+    }
+}
+
+/// Allocation returned by `MemoryPool::alloc_from_requirements`. Either comes from the pool or
+/// is a `DeviceMemory` of its own.
+#[derive(Debug)]
+pub enum PotentialDedicatedAllocation<A> {
+    /// The memory was allocated from the pool.
+    Generic(A),
+    /// The resource has its own memory.
+    Dedicated(DeviceMemory),
+    /// The resource has its own memory, which is mapped.
+    DedicatedMapped(MappedDeviceMemory),
+}
+
+unsafe impl<A> MemoryPoolAlloc for PotentialDedicatedAllocation<A> where A: MemoryPoolAlloc {
+    #[inline]
+    fn mapped_memory(&self) -> Option<&MappedDeviceMemory> {
+        match *self {
+            PotentialDedicatedAllocation::Generic(ref alloc) => alloc.mapped_memory(),
+            PotentialDedicatedAllocation::Dedicated(_) => None,
+            PotentialDedicatedAllocation::DedicatedMapped(ref mem) => Some(mem),
+        }
+    }
+
+    #[inline]
+    fn memory(&self) -> &DeviceMemory {
+        match *self {
+            PotentialDedicatedAllocation::Generic(ref alloc) => alloc.memory(),
+            PotentialDedicatedAllocation::Dedicated(ref mem) => mem,
+            PotentialDedicatedAllocation::DedicatedMapped(ref mem) => mem.memory(),
+        }
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        match *self {
+            PotentialDedicatedAllocation::Generic(ref alloc) => alloc.offset(),
+            _ => 0,
+        }
+    }
+
+    #[inline]
+    fn is_dedicated(&self) -> bool {
+        match *self {
+            PotentialDedicatedAllocation::Generic(ref alloc) => alloc.is_dedicated(),
+            _ => true,
+        }
+    }
 }
 
 /// Layout of the object being allocated.
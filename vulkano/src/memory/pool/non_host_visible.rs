@@ -8,7 +8,6 @@
 // according to those terms.
 
 use std::cmp;
-use std::ops::Range;
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -16,6 +15,9 @@ use device::Device;
 use instance::Instance;
 use instance::MemoryType;
 use memory::DeviceMemory;
+use memory::pool::AllocLayout;
+use memory::pool::MemoryStats;
+use memory::pool::buddy::BuddyAllocator;
 use OomError;
 
 /// Memory pool that operates on a given memory type.
@@ -23,8 +25,10 @@ use OomError;
 pub struct StdNonHostVisibleMemoryTypePool {
     device: Arc<Device>,
     memory_type: u32,
-    // TODO: obviously very inefficient
-    occupied: Mutex<Vec<(Arc<DeviceMemory>, Vec<Range<usize>>)>>,
+    // Value of the `buffer_image_granularity` limit of the device.
+    granularity: usize,
+    // List of blocks allocated from the device, and the sub-allocator of each of them.
+    occupied: Mutex<Vec<(Arc<DeviceMemory>, BuddyAllocator)>>,
 }
 
 impl StdNonHostVisibleMemoryTypePool {
@@ -45,76 +49,100 @@ impl StdNonHostVisibleMemoryTypePool {
         Arc::new(StdNonHostVisibleMemoryTypePool {
             device: device.clone(),
             memory_type: memory_type.id(),
+            granularity: device.physical_device().limits().buffer_image_granularity() as usize,
             occupied: Mutex::new(Vec::new()),
         })
     }
 
     /// Allocates memory from the pool.
     ///
+    /// If the object is placed next to an object with a different `layout`, the boundary
+    /// between the two is aligned to the `buffer_image_granularity` limit of the device.
+    ///
     /// # Panic
     ///
     /// - Panics if `size` is 0.
     /// - Panics if `alignment` is 0.
     ///
-    pub fn alloc(me: &Arc<Self>, size: usize, alignment: usize)
+    #[inline]
+    pub fn alloc(me: &Arc<Self>, size: usize, alignment: usize, layout: AllocLayout)
                  -> Result<StdNonHostVisibleMemoryTypePoolAlloc, OomError>
+    {
+        StdNonHostVisibleMemoryTypePool::alloc_with_limit(me, size, alignment, layout, None)
+    }
+
+    /// Same as `alloc`, except that `OutOfDeviceMemory` is returned if a new block has to be
+    /// allocated and the total size of the blocks held by the pool would then exceed
+    /// `max_reserved` bytes.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `size` is 0.
+    /// - Panics if `alignment` is 0.
+    ///
+    pub fn alloc_with_limit(me: &Arc<Self>, size: usize, alignment: usize, layout: AllocLayout,
+                            max_reserved: Option<usize>)
+                            -> Result<StdNonHostVisibleMemoryTypePoolAlloc, OomError>
     {
         assert!(size != 0);
         assert!(alignment != 0);
 
-        #[inline] fn align(val: usize, al: usize) -> usize { al * (1 + (val - 1) / al) }
-
-        // Find a location.
         let mut occupied = me.occupied.lock().unwrap();
 
         // Try finding an entry in already-allocated chunks.
-        for &mut (ref dev_mem, ref mut entries) in occupied.iter_mut() {
-            // Try find some free space in-between two entries.
-            for i in 0 .. entries.len().saturating_sub(1) {
-                let entry1 = entries[i].clone();
-                let entry1_end = align(entry1.end, alignment);
-                let entry2 = entries[i + 1].clone();
-                if entry1_end + size <= entry2.start {
-                    entries.insert(i + 1, entry1_end .. entry1_end + size);
-                    return Ok(StdNonHostVisibleMemoryTypePoolAlloc {
-                        pool: me.clone(),
-                        memory: dev_mem.clone(),
-                        offset: entry1_end,
-                        size: size,
-                    });
-                }
-            }
-
-            // Try append at the end.
-            let last_end = entries.last().map(|e| align(e.end, alignment)).unwrap_or(0);
-            if last_end + size <= dev_mem.size() {
-                entries.push(last_end .. last_end + size);
+        for &mut (ref dev_mem, ref mut allocator) in occupied.iter_mut() {
+            if let Some(offset) = allocator.alloc(size, alignment, layout) {
                 return Ok(StdNonHostVisibleMemoryTypePoolAlloc {
                     pool: me.clone(),
                     memory: dev_mem.clone(),
-                    offset: last_end,
+                    offset: offset,
                     size: size,
                 });
             }
         }
 
         // We need to allocate a new block.
-        let new_block = {
-            const MIN_BLOCK_SIZE: usize = 8 * 1024 * 1024;      // 8 MB
-            let to_alloc = cmp::max(MIN_BLOCK_SIZE, size.next_power_of_two());
-            let new_block = try!(DeviceMemory::alloc(&me.device, me.memory_type(), to_alloc));
-            Arc::new(new_block)
-        };
-
-        occupied.push((new_block.clone(), vec![0 .. size]));
+        const MIN_BLOCK_SIZE: usize = 8 * 1024 * 1024;      // 8 MB
+        let to_alloc = cmp::max(MIN_BLOCK_SIZE, BuddyAllocator::node_size(size, alignment));
+        if let Some(max_reserved) = max_reserved {
+            let reserved = occupied.iter().fold(0, |acc, b| acc + b.1.size());
+            if reserved + to_alloc > max_reserved {
+                return Err(OomError::OutOfDeviceMemory);
+            }
+        }
+
+        let new_block = try!(DeviceMemory::alloc(&me.device, me.memory_type(), to_alloc));
+        let new_block = Arc::new(new_block);
+
+        let mut allocator = BuddyAllocator::new(to_alloc, me.granularity);
+        let offset = allocator.alloc(size, alignment, layout).unwrap();
+        debug_assert_eq!(offset, 0);
+
+        occupied.push((new_block.clone(), allocator));
         Ok(StdNonHostVisibleMemoryTypePoolAlloc {
             pool: me.clone(),
             memory: new_block,
-            offset: 0,
+            offset: offset,
             size: size,
         })
     }
 
+    /// Returns statistics about the memory held by this pool.
+    pub fn stats(&self) -> MemoryStats {
+        let occupied = self.occupied.lock().unwrap();
+
+        let mut stats = MemoryStats::default();
+        for &(_, ref allocator) in occupied.iter() {
+            stats.block_count += 1;
+            stats.allocation_count += allocator.num_allocations();
+            stats.allocated_bytes += allocator.allocated_bytes();
+            stats.reserved_bytes += allocator.size();
+            stats.largest_free_range = cmp::max(stats.largest_free_range,
+                                                allocator.largest_free_range());
+        }
+        stats
+    }
+
     /// Returns the device this pool operates on.
     #[inline]
     pub fn device(&self) -> &Arc<Device> {
@@ -157,9 +185,9 @@ impl Drop for StdNonHostVisibleMemoryTypePoolAlloc {
     fn drop(&mut self) {
         let mut occupied = self.pool.occupied.lock().unwrap();
 
-        let entries = occupied.iter_mut()
-                              .find(|e| &*e.0 as *const DeviceMemory == &*self.memory).unwrap();
+        let entry = occupied.iter_mut()
+                            .find(|e| &*e.0 as *const DeviceMemory == &*self.memory).unwrap();
 
-        entries.1.retain(|e| e.start != self.offset);
+        entry.1.free(self.offset);
     }
 }
@@ -7,6 +7,7 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::cmp;
 use std::collections::HashMap;
 use std::collections::hash_map::Entry;
 use std::hash::BuildHasherDefault;
@@ -15,14 +16,19 @@ use std::sync::Mutex;
 use fnv::FnvHasher;
 
 use device::Device;
+use device::DeviceOwned;
+use instance::Instance;
+use instance::MemoryHeap;
 use instance::MemoryType;
 use memory::pool::AllocLayout;
 use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
+use memory::pool::PotentialDedicatedAllocation;
 use memory::pool::StdHostVisibleMemoryTypePool;
 use memory::pool::StdHostVisibleMemoryTypePoolAlloc;
 use memory::pool::StdNonHostVisibleMemoryTypePool;
 use memory::pool::StdNonHostVisibleMemoryTypePoolAlloc;
+use memory::DedicatedAlloc;
 use memory::DeviceMemory;
 use memory::MappedDeviceMemory;
 use OomError;
@@ -32,7 +38,15 @@ pub struct StdMemoryPool {
     device: Arc<Device>,
 
     // For each memory type index, stores the associated pool.
-    pools: Mutex<HashMap<(u32, AllocLayout), Pool, BuildHasherDefault<FnvHasher>>>,
+    pools: Mutex<HashMap<u32, Pool, BuildHasherDefault<FnvHasher>>>,
+
+    // For each memory heap index, the maximum number of bytes that the pool is allowed to
+    // allocate from the device.
+    heap_limits: Mutex<Vec<Option<usize>>>,
+
+    // For each memory type index, the number of dedicated allocations that are alive and their
+    // total size.
+    dedicated: Mutex<Vec<(usize, usize)>>,
 }
 
 impl StdMemoryPool {
@@ -45,8 +59,109 @@ impl StdMemoryPool {
         Arc::new(StdMemoryPool {
             device: device.clone(),
             pools: Mutex::new(HashMap::with_capacity_and_hasher(cap, hasher)),
+            heap_limits: Mutex::new(vec![None; device.physical_device().memory_heaps().len()]),
+            dedicated: Mutex::new(vec![(0, 0); cap]),
         })
     }
+
+    /// Returns statistics about the memory currently held by the pool.
+    pub fn stats(&self) -> StdMemoryPoolStats {
+        let physical_device = self.device.physical_device();
+        let mut stats = StdMemoryPoolStats {
+            heaps: vec![MemoryStats::default(); physical_device.memory_heaps().len()],
+            types: vec![MemoryStats::default(); physical_device.memory_types().len()],
+        };
+
+        let pools = self.pools.lock().unwrap();
+        for (&id, pool) in pools.iter() {
+            let pool_stats = pool.stats();
+            let heap = physical_device.memory_type_by_id(id).unwrap().heap().id();
+            stats.types[id as usize].merge(&pool_stats);
+            stats.heaps[heap as usize].merge(&pool_stats);
+        }
+
+        let dedicated = self.dedicated.lock().unwrap();
+        for (id, &(count, bytes)) in dedicated.iter().enumerate() {
+            let dedicated_stats = MemoryStats {
+                block_count: count,
+                allocation_count: count,
+                allocated_bytes: bytes,
+                reserved_bytes: bytes,
+                largest_free_range: 0,
+            };
+            let heap = physical_device.memory_type_by_id(id as u32).unwrap().heap().id();
+            stats.types[id].merge(&dedicated_stats);
+            stats.heaps[heap as usize].merge(&dedicated_stats);
+        }
+
+        stats
+    }
+
+    /// Limits the number of bytes that the pool can allocate from the device in the given heap.
+    /// Pass `None` to remove the limit.
+    ///
+    /// Once the limit is reached, allocations that can't be served from the blocks that the pool
+    /// already holds will return `OomError::OutOfDeviceMemory`. Blocks that have already been
+    /// allocated are not freed if they exceed a new limit. Dedicated allocations count towards
+    /// the limit as well.
+    ///
+    /// Note that the pool returned by `Device::standard_pool` is destroyed and re-created when
+    /// nothing references it anymore. You should keep it alive if you set a limit on it.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `heap` doesn't belong to the same physical device as the pool.
+    ///
+    pub fn set_heap_limit(&self, heap: MemoryHeap, limit: Option<usize>) {
+        self.check_heap(heap);
+        self.heap_limits.lock().unwrap()[heap.id() as usize] = limit;
+    }
+
+    /// Returns the limit that was set with `set_heap_limit`, if any.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `heap` doesn't belong to the same physical device as the pool.
+    ///
+    pub fn heap_limit(&self, heap: MemoryHeap) -> Option<usize> {
+        self.check_heap(heap);
+        self.heap_limits.lock().unwrap()[heap.id() as usize]
+    }
+
+    // Returns the number of bytes of the given heap held by the pool, without counting the
+    // memory type pool of `except`.
+    fn heap_usage(&self, pools: &HashMap<u32, Pool, BuildHasherDefault<FnvHasher>>, heap: u32,
+                  except: Option<u32>) -> usize
+    {
+        let physical_device = self.device.physical_device();
+        let mut total = 0;
+        for (&id, pool) in pools.iter() {
+            let ty = physical_device.memory_type_by_id(id).unwrap();
+            if Some(id) != except && ty.heap().id() == heap {
+                total += pool.stats().reserved_bytes;
+            }
+        }
+        for (id, &(_, bytes)) in self.dedicated.lock().unwrap().iter().enumerate() {
+            if physical_device.memory_type_by_id(id as u32).unwrap().heap().id() == heap {
+                total += bytes;
+            }
+        }
+        total
+    }
+
+    #[inline]
+    fn check_heap(&self, heap: MemoryHeap) {
+        assert_eq!(&**self.device.physical_device().instance() as *const Instance,
+                   &**heap.physical_device().instance() as *const Instance);
+        assert_eq!(self.device.physical_device().index(), heap.physical_device().index());
+    }
+}
+
+unsafe impl DeviceOwned for StdMemoryPool {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
 }
 
 unsafe impl MemoryPool for Arc<StdMemoryPool> {
@@ -57,41 +172,84 @@ unsafe impl MemoryPool for Arc<StdMemoryPool> {
     {
         let mut pools = self.pools.lock().unwrap();
 
-        match pools.entry((memory_type.id(), layout)) {
-            Entry::Occupied(entry) => {
-                match entry.get() {
-                    &Pool::HostVisible(ref pool) => {
-                        let alloc = try!(StdHostVisibleMemoryTypePool::alloc(&pool, size, alignment));
-                        let inner = StdMemoryPoolAllocInner::HostVisible(alloc);
-                        Ok(StdMemoryPoolAlloc { inner: inner, pool: self.clone() })
-                    },
-                    &Pool::NonHostVisible(ref pool) => {
-                        let alloc = try!(StdNonHostVisibleMemoryTypePool::alloc(&pool, size, alignment));
-                        let inner = StdMemoryPoolAllocInner::NonHostVisible(alloc);
-                        Ok(StdMemoryPoolAlloc { inner: inner, pool: self.clone() })
-                    },
-                }
-            },
+        // If the heap is limited, compute how much the pool of this memory type is allowed to
+        // hold. This takes into account the memory held by the other memory types of the heap
+        // and by the dedicated allocations.
+        let heap = memory_type.heap().id();
+        let max_reserved = self.heap_limits.lock().unwrap()[heap as usize].map(|limit| {
+            limit.saturating_sub(self.heap_usage(&pools, heap, Some(memory_type.id())))
+        });
 
+        let pool = match pools.entry(memory_type.id()) {
+            Entry::Occupied(entry) => entry.into_mut(),
             Entry::Vacant(entry) => {
-                match memory_type.is_host_visible() {
+                let pool = match memory_type.is_host_visible() {
                     true => {
-                        let pool = StdHostVisibleMemoryTypePool::new(&self.device, memory_type);
-                        entry.insert(Pool::HostVisible(pool.clone()));
-                        let alloc = try!(StdHostVisibleMemoryTypePool::alloc(&pool, size, alignment));
-                        let inner = StdMemoryPoolAllocInner::HostVisible(alloc);
-                        Ok(StdMemoryPoolAlloc { inner: inner, pool: self.clone() })
+                        Pool::HostVisible(StdHostVisibleMemoryTypePool::new(&self.device,
+                                                                            memory_type))
                     },
                     false => {
-                        let pool = StdNonHostVisibleMemoryTypePool::new(&self.device, memory_type);
-                        entry.insert(Pool::NonHostVisible(pool.clone()));
-                        let alloc = try!(StdNonHostVisibleMemoryTypePool::alloc(&pool, size, alignment));
-                        let inner = StdMemoryPoolAllocInner::NonHostVisible(alloc);
-                        Ok(StdMemoryPoolAlloc { inner: inner, pool: self.clone() })
+                        Pool::NonHostVisible(StdNonHostVisibleMemoryTypePool::new(&self.device,
+                                                                                  memory_type))
                     },
-                }
+                };
+                entry.insert(pool)
+            },
+        };
+
+        let inner = match *pool {
+            Pool::HostVisible(ref pool) => {
+                let alloc = StdHostVisibleMemoryTypePool::alloc_with_limit(pool, size, alignment,
+                                                                           layout, max_reserved);
+                StdMemoryPoolAllocInner::HostVisible(try!(alloc))
+            },
+            Pool::NonHostVisible(ref pool) => {
+                let alloc = StdNonHostVisibleMemoryTypePool::alloc_with_limit(pool, size,
+                                                                              alignment, layout,
+                                                                              max_reserved);
+                StdMemoryPoolAllocInner::NonHostVisible(try!(alloc))
             },
+        };
+
+        Ok(StdMemoryPoolAlloc { inner: inner, pool: self.clone() })
+    }
+
+    fn alloc_dedicated(&self, device: &Arc<Device>, memory_type: MemoryType, size: usize,
+                       resource: DedicatedAlloc)
+                       -> Result<PotentialDedicatedAllocation<StdMemoryPoolAlloc>, OomError>
+    {
+        assert_eq!(&**device as *const Device, &*self.device as *const Device);
+
+        // Locking the pools prevents another allocation from going over the heap limit in the
+        // meantime.
+        let pools = self.pools.lock().unwrap();
+
+        let heap = memory_type.heap().id();
+        if let Some(limit) = self.heap_limits.lock().unwrap()[heap as usize] {
+            if self.heap_usage(&pools, heap, None) + size > limit {
+                return Err(OomError::OutOfDeviceMemory);
+            }
+        }
+
+        let inner = if memory_type.is_host_visible() {
+            let mem = try!(DeviceMemory::dedicated_alloc_and_map(&self.device, memory_type, size,
+                                                                 resource));
+            StdMemoryPoolAllocInner::DedicatedMapped(mem)
+        } else {
+            let mem = try!(DeviceMemory::dedicated_alloc(&self.device, memory_type, size,
+                                                         resource));
+            StdMemoryPoolAllocInner::Dedicated(mem)
+        };
+
+        {
+            let mut dedicated = self.dedicated.lock().unwrap();
+            let entry = &mut dedicated[memory_type.id() as usize];
+            entry.0 += 1;
+            entry.1 += size;
         }
+
+        let alloc = StdMemoryPoolAlloc { inner: inner, pool: self.clone() };
+        Ok(PotentialDedicatedAllocation::Generic(alloc))
     }
 }
 
@@ -101,6 +259,64 @@ enum Pool {
     NonHostVisible(Arc<StdNonHostVisibleMemoryTypePool>),
 }
 
+impl Pool {
+    #[inline]
+    fn stats(&self) -> MemoryStats {
+        match *self {
+            Pool::HostVisible(ref pool) => pool.stats(),
+            Pool::NonHostVisible(ref pool) => pool.stats(),
+        }
+    }
+}
+
+/// Statistics about the memory held by a `StdMemoryPool`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StdMemoryPoolStats {
+    /// Statistics for each memory heap of the physical device, indexed by the heap id.
+    pub heaps: Vec<MemoryStats>,
+    /// Statistics for each memory type of the physical device, indexed by the memory type id.
+    pub types: Vec<MemoryStats>,
+}
+
+impl StdMemoryPoolStats {
+    /// Returns the statistics of all the heaps combined.
+    #[inline]
+    pub fn total(&self) -> MemoryStats {
+        let mut total = MemoryStats::default();
+        for heap in self.heaps.iter() {
+            total.merge(heap);
+        }
+        total
+    }
+}
+
+/// Statistics about the memory held by a pool for a memory type or a memory heap.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Number of `DeviceMemory` blocks that were allocated from the device.
+    pub block_count: usize,
+    /// Number of allocations currently alive.
+    pub allocation_count: usize,
+    /// Sum of the sizes that were requested by the allocations currently alive.
+    pub allocated_bytes: usize,
+    /// Total size of the `DeviceMemory` blocks. The difference with `allocated_bytes` is memory
+    /// that is either free or lost to fragmentation and alignment.
+    pub reserved_bytes: usize,
+    /// Size of the largest range that can be allocated without allocating a new block.
+    pub largest_free_range: usize,
+}
+
+impl MemoryStats {
+    #[inline]
+    fn merge(&mut self, other: &MemoryStats) {
+        self.block_count += other.block_count;
+        self.allocation_count += other.allocation_count;
+        self.allocated_bytes += other.allocated_bytes;
+        self.reserved_bytes += other.reserved_bytes;
+        self.largest_free_range = cmp::max(self.largest_free_range, other.largest_free_range);
+    }
+}
+
 #[derive(Debug)]
 pub struct StdMemoryPoolAlloc {
     inner: StdMemoryPoolAllocInner,
@@ -113,6 +329,8 @@ impl StdMemoryPoolAlloc {
         match self.inner {
             StdMemoryPoolAllocInner::NonHostVisible(ref mem) => mem.size(),
             StdMemoryPoolAllocInner::HostVisible(ref mem) => mem.size(),
+            StdMemoryPoolAllocInner::Dedicated(ref mem) => mem.size(),
+            StdMemoryPoolAllocInner::DedicatedMapped(ref mem) => mem.memory().size(),
         }
     }
 }
@@ -123,6 +341,8 @@ unsafe impl MemoryPoolAlloc for StdMemoryPoolAlloc {
         match self.inner {
             StdMemoryPoolAllocInner::NonHostVisible(ref mem) => mem.memory(),
             StdMemoryPoolAllocInner::HostVisible(ref mem) => mem.memory().memory(),
+            StdMemoryPoolAllocInner::Dedicated(ref mem) => mem,
+            StdMemoryPoolAllocInner::DedicatedMapped(ref mem) => mem.memory(),
         }
     }
 
@@ -131,6 +351,8 @@ unsafe impl MemoryPoolAlloc for StdMemoryPoolAlloc {
         match self.inner {
             StdMemoryPoolAllocInner::NonHostVisible(_) => None,
             StdMemoryPoolAllocInner::HostVisible(ref mem) => Some(mem.memory()),
+            StdMemoryPoolAllocInner::Dedicated(_) => None,
+            StdMemoryPoolAllocInner::DedicatedMapped(ref mem) => Some(mem),
         }
     }
 
@@ -139,12 +361,257 @@ unsafe impl MemoryPoolAlloc for StdMemoryPoolAlloc {
         match self.inner {
             StdMemoryPoolAllocInner::NonHostVisible(ref mem) => mem.offset(),
             StdMemoryPoolAllocInner::HostVisible(ref mem) => mem.offset(),
+            StdMemoryPoolAllocInner::Dedicated(_) => 0,
+            StdMemoryPoolAllocInner::DedicatedMapped(_) => 0,
+        }
+    }
+
+    #[inline]
+    fn is_dedicated(&self) -> bool {
+        match self.inner {
+            StdMemoryPoolAllocInner::Dedicated(_) => true,
+            StdMemoryPoolAllocInner::DedicatedMapped(_) => true,
+            _ => false,
         }
     }
 }
 
+impl Drop for StdMemoryPoolAlloc {
+    fn drop(&mut self) {
+        let memory = match self.inner {
+            StdMemoryPoolAllocInner::Dedicated(ref mem) => mem,
+            StdMemoryPoolAllocInner::DedicatedMapped(ref mem) => mem.memory(),
+            _ => return,
+        };
+
+        let mut dedicated = self.pool.dedicated.lock().unwrap();
+        let entry = &mut dedicated[memory.memory_type().id() as usize];
+        entry.0 -= 1;
+        entry.1 -= memory.size();
+    }
+}
+
 #[derive(Debug)]
 enum StdMemoryPoolAllocInner {
     NonHostVisible(StdNonHostVisibleMemoryTypePoolAlloc),
     HostVisible(StdHostVisibleMemoryTypePoolAlloc),
+    Dedicated(DeviceMemory),
+    DedicatedMapped(MappedDeviceMemory),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter::Empty;
+    use buffer::BufferUsage;
+    use buffer::sys::SparseLevel;
+    use buffer::sys::UnsafeBuffer;
+    use device::Device;
+    use device::DeviceExtensions;
+    use features::Features;
+    use format::Format;
+    use image::AttachmentImage;
+    use instance::PhysicalDevice;
+    use memory::DedicatedAlloc;
+    use memory::pool::AllocLayout;
+    use memory::pool::MemoryPool;
+    use memory::pool::MemoryPoolAlloc;
+    use memory::pool::MemoryStats;
+    use memory::pool::StdMemoryPool;
+    use memory::pool::DEDICATED_ALLOCATION_THRESHOLD;
+    use mock;
+    use sync::Sharing;
+    use OomError;
+
+    #[test]
+    fn stats() {
+        let (device, _) = mock_dev_and_queue!();
+        let physical = device.physical_device();
+        let mem_ty = physical.memory_types().find(|t| t.is_host_visible()).unwrap();
+        let heap = mem_ty.heap().id() as usize;
+
+        let pool = StdMemoryPool::new(&device);
+        assert_eq!(pool.stats().total(), MemoryStats::default());
+
+        let a = pool.alloc(mem_ty, 1000, 4, AllocLayout::Linear).unwrap();
+        let b = pool.alloc(mem_ty, 24, 4, AllocLayout::Linear).unwrap();
+
+        let stats = pool.stats();
+        assert_eq!(stats.types[mem_ty.id() as usize], stats.heaps[heap]);
+        assert_eq!(stats.heaps[heap].block_count, 1);
+        assert_eq!(stats.heaps[heap].allocation_count, 2);
+        assert_eq!(stats.heaps[heap].allocated_bytes, 1024);
+        assert_eq!(stats.heaps[heap].reserved_bytes, 8 * 1024 * 1024);
+        assert_eq!(stats.heaps[heap].largest_free_range, 4 * 1024 * 1024);
+        assert_eq!(stats.total(), stats.heaps[heap]);
+
+        drop(a);
+        drop(b);
+
+        let stats = pool.stats();
+        assert_eq!(stats.heaps[heap].block_count, 1);
+        assert_eq!(stats.heaps[heap].allocation_count, 0);
+        assert_eq!(stats.heaps[heap].allocated_bytes, 0);
+        assert_eq!(stats.heaps[heap].largest_free_range, 8 * 1024 * 1024);
+    }
+
+    #[test]
+    fn heap_limit() {
+        let (device, _) = mock_dev_and_queue!();
+        let physical = device.physical_device();
+        let mem_ty = physical.memory_types().find(|t| t.is_host_visible()).unwrap();
+
+        let pool = StdMemoryPool::new(&device);
+        pool.set_heap_limit(mem_ty.heap(), Some(8 * 1024 * 1024));
+        assert_eq!(pool.heap_limit(mem_ty.heap()), Some(8 * 1024 * 1024));
+
+        // The first block fits in the limit, and further allocations can use it.
+        let _a = pool.alloc(mem_ty, 4 * 1024 * 1024, 4, AllocLayout::Linear).unwrap();
+        let _b = pool.alloc(mem_ty, 4 * 1024 * 1024, 4, AllocLayout::Linear).unwrap();
+
+        // A second block would go over the limit.
+        match pool.alloc(mem_ty, 16, 4, AllocLayout::Linear) {
+            Err(OomError::OutOfDeviceMemory) => (),
+            _ => panic!()
+        }
+
+        // The limit applies to the other memory types of the same heap as well.
+        if let Some(other_ty) = physical.memory_types().find(|t| {
+            t.id() != mem_ty.id() && t.heap().id() == mem_ty.heap().id()
+        }) {
+            match pool.alloc(other_ty, 16, 4, AllocLayout::Linear) {
+                Err(OomError::OutOfDeviceMemory) => (),
+                _ => panic!()
+            }
+        }
+
+        pool.set_heap_limit(mem_ty.heap(), None);
+        let _c = pool.alloc(mem_ty, 16, 4, AllocLayout::Linear).unwrap();
+        assert_eq!(pool.stats().heaps[mem_ty.heap().id() as usize].block_count, 2);
+    }
+
+    #[test]
+    fn dedicated_alloc_extension() {
+        let instance = mock::instance();
+        let physical = PhysicalDevice::enumerate(&instance).next().unwrap();
+        let family = physical.queue_families().next().unwrap();
+        let extensions = DeviceExtensions {
+            khr_get_memory_requirements2: true,
+            khr_dedicated_allocation: true,
+            .. DeviceExtensions::none()
+        };
+        let (device, _) = Device::new(&physical, &Features::none(), &extensions,
+                                      Some((family, 0.5))).unwrap();
+        let mem_ty = physical.memory_types().find(|t| t.is_device_local()).unwrap();
+        let pool = StdMemoryPool::new(&device);
+
+        unsafe {
+            let (small, reqs) = UnsafeBuffer::new(&device, 1024, &BufferUsage::all(),
+                                                  Sharing::Exclusive::<Empty<_>>,
+                                                  SparseLevel::none()).unwrap();
+            assert!(!reqs.prefers_dedicated && !reqs.requires_dedicated);
+            let alloc = pool.alloc_from_requirements(&device, mem_ty, &reqs, AllocLayout::Linear,
+                                                     DedicatedAlloc::Buffer(&small)).unwrap();
+            assert!(!alloc.is_dedicated());
+            small.bind_memory(alloc.memory(), alloc.offset()).unwrap();
+
+            // The mock requires a dedicated allocation for large resources, and checks that the
+            // memory is bound to the resource it was allocated for.
+            let (large, reqs) = UnsafeBuffer::new(&device, 8 * 1024 * 1024, &BufferUsage::all(),
+                                                  Sharing::Exclusive::<Empty<_>>,
+                                                  SparseLevel::none()).unwrap();
+            assert!(reqs.requires_dedicated);
+            let alloc = pool.alloc_from_requirements(&device, mem_ty, &reqs, AllocLayout::Linear,
+                                                     DedicatedAlloc::Buffer(&large)).unwrap();
+            assert!(alloc.is_dedicated());
+            assert_eq!(alloc.offset(), 0);
+            large.bind_memory(alloc.memory(), alloc.offset()).unwrap();
+        }
+
+        let _image = AttachmentImage::new(&device, [1024, 1024], Format::R8G8B8A8Unorm).unwrap();
+
+        let calls = mock::calls(&device);
+        assert!(calls.contains(&"GetBufferMemoryRequirements2KHR"));
+        assert!(calls.contains(&"GetImageMemoryRequirements2KHR"));
+    }
+
+    #[test]
+    fn dedicated_alloc_heuristic() {
+        let (device, _) = mock_dev_and_queue!();
+        let physical = device.physical_device();
+        let mem_ty = physical.memory_types().find(|t| t.is_device_local()).unwrap();
+        let pool = StdMemoryPool::new(&device);
+
+        unsafe {
+            let (small, reqs) = UnsafeBuffer::new(&device, 1024 * 1024, &BufferUsage::all(),
+                                                  Sharing::Exclusive::<Empty<_>>,
+                                                  SparseLevel::none()).unwrap();
+            let small_alloc = pool.alloc_from_requirements(&device, mem_ty, &reqs,
+                                                           AllocLayout::Linear,
+                                                           DedicatedAlloc::Buffer(&small))
+                                  .unwrap();
+            assert!(!small_alloc.is_dedicated());
+
+            let (large, reqs) = UnsafeBuffer::new(&device, DEDICATED_ALLOCATION_THRESHOLD,
+                                                  &BufferUsage::all(),
+                                                  Sharing::Exclusive::<Empty<_>>,
+                                                  SparseLevel::none()).unwrap();
+            assert!(!reqs.prefers_dedicated && !reqs.requires_dedicated);
+            let large_alloc = pool.alloc_from_requirements(&device, mem_ty, &reqs,
+                                                           AllocLayout::Linear,
+                                                           DedicatedAlloc::Buffer(&large))
+                                  .unwrap();
+            assert!(large_alloc.is_dedicated());
+            assert_eq!(large_alloc.offset(), 0);
+            large.bind_memory(large_alloc.memory(), large_alloc.offset()).unwrap();
+
+            // The dedicated allocation is counted by the pool, next to the block of the small one.
+            let stats = pool.stats().total();
+            assert_eq!(stats.block_count, 2);
+            assert_eq!(stats.allocation_count, 2);
+            assert_eq!(stats.allocated_bytes, 1024 * 1024 + DEDICATED_ALLOCATION_THRESHOLD);
+            assert_eq!(stats.reserved_bytes, 8 * 1024 * 1024 + DEDICATED_ALLOCATION_THRESHOLD);
+        }
+
+        // The dedicated memory is freed with its allocation, but the block is kept.
+        let stats = pool.stats().total();
+        assert_eq!(stats.block_count, 1);
+        assert_eq!(stats.allocation_count, 0);
+        assert_eq!(stats.reserved_bytes, 8 * 1024 * 1024);
+    }
+
+    #[test]
+    fn dedicated_alloc_heap_limit() {
+        let (device, _) = mock_dev_and_queue!();
+        let mem_ty = device.physical_device().memory_types().find(|t| t.is_device_local())
+                           .unwrap();
+        let pool = StdMemoryPool::new(&device);
+        pool.set_heap_limit(mem_ty.heap(), Some(DEDICATED_ALLOCATION_THRESHOLD + 1024 * 1024));
+
+        unsafe {
+            let (large, reqs) = UnsafeBuffer::new(&device, DEDICATED_ALLOCATION_THRESHOLD,
+                                                  &BufferUsage::all(),
+                                                  Sharing::Exclusive::<Empty<_>>,
+                                                  SparseLevel::none()).unwrap();
+            let alloc = pool.alloc_from_requirements(&device, mem_ty, &reqs, AllocLayout::Linear,
+                                                     DedicatedAlloc::Buffer(&large)).unwrap();
+            assert!(alloc.is_dedicated());
+
+            // The dedicated allocation doesn't leave enough room for a new block.
+            match pool.alloc(mem_ty, 16, 4, AllocLayout::Linear) {
+                Err(OomError::OutOfDeviceMemory) => (),
+                _ => panic!()
+            }
+
+            // Nor for another dedicated allocation.
+            let (other, reqs) = UnsafeBuffer::new(&device, DEDICATED_ALLOCATION_THRESHOLD,
+                                                  &BufferUsage::all(),
+                                                  Sharing::Exclusive::<Empty<_>>,
+                                                  SparseLevel::none()).unwrap();
+            match pool.alloc_from_requirements(&device, mem_ty, &reqs, AllocLayout::Linear,
+                                               DedicatedAlloc::Buffer(&other)) {
+                Err(OomError::OutOfDeviceMemory) => (),
+                _ => panic!()
+            }
+        }
+    }
 }
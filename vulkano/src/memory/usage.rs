@@ -0,0 +1,310 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use instance::MemoryType;
+use instance::PhysicalDevice;
+
+/// Describes how a resource is going to be accessed, in order to choose the memory type that
+/// it is allocated from.
+///
+/// Each memory type that the resource can use gets a score depending on its properties, and the
+/// memory type with the highest score is chosen. If multiple memory types have the same score,
+/// the first one is chosen, as the implementation orders the memory types from the most to the
+/// least efficient.
+///
+/// The usages that need the CPU to access the memory never choose a memory type that is not
+/// host-visible. The other usages always find a memory type, even if it doesn't have the
+/// properties that they would prefer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MemoryUsage {
+    /// The resource is only accessed by the GPU.
+    ///
+    /// Prefers device-local memory, then memory that isn't lazily allocated, then memory that
+    /// isn't host-visible so that the memory that is both device-local and host-visible remains
+    /// available for the other usages.
+    GpuOnly,
+
+    /// The resource is written by the CPU and read by the GPU, for example uniform buffers that
+    /// are updated every frame.
+    ///
+    /// Requires host-visible memory. Prefers memory that is also device-local, then host-coherent
+    /// memory, then memory that isn't host-cached.
+    CpuToGpu,
+
+    /// The resource is mostly accessed by the CPU, for example a staging buffer used to upload
+    /// data.
+    ///
+    /// Requires host-visible memory. Prefers host-coherent memory, then memory that isn't
+    /// device-local, then memory that isn't host-cached.
+    CpuOnly,
+
+    /// The resource is written by the GPU and read by the CPU, for example to read back the
+    /// results of a computation.
+    ///
+    /// Requires host-visible memory. Prefers host-cached memory, then host-coherent memory, then
+    /// memory that isn't device-local.
+    GpuToCpu,
+
+    /// The resource is a transient attachment whose content doesn't need to be stored in memory.
+    ///
+    /// Prefers lazily-allocated memory, then device-local memory, then memory that isn't
+    /// host-visible.
+    Transient,
+}
+
+impl MemoryUsage {
+    /// Returns true if this usage only accepts host-visible memory types.
+    #[inline]
+    pub fn requires_host_visible(&self) -> bool {
+        match *self {
+            MemoryUsage::CpuToGpu | MemoryUsage::CpuOnly | MemoryUsage::GpuToCpu => true,
+            MemoryUsage::GpuOnly | MemoryUsage::Transient => false,
+        }
+    }
+
+    /// Returns the score of a memory type with the given properties for this usage, or `None`
+    /// if the memory type can't be used. Higher is better.
+    pub fn score(&self, ty: &MemoryTypeProperties) -> Option<u32> {
+        if self.requires_host_visible() && !ty.host_visible {
+            return None;
+        }
+
+        // The preferences of each usage, from the most to the least important. Each preference
+        // weighs more than all the less important ones combined.
+        let preferences = match *self {
+            MemoryUsage::GpuOnly => [ty.device_local, !ty.lazily_allocated, !ty.host_visible],
+            MemoryUsage::CpuToGpu => [ty.device_local, ty.host_coherent, !ty.host_cached],
+            MemoryUsage::CpuOnly => [ty.host_coherent, !ty.device_local, !ty.host_cached],
+            MemoryUsage::GpuToCpu => [ty.host_cached, ty.host_coherent, !ty.device_local],
+            MemoryUsage::Transient => [ty.lazily_allocated, ty.device_local, !ty.host_visible],
+        };
+
+        Some(preferences.iter().fold(0, |score, &pref| (score << 1) | if pref { 1 } else { 0 }))
+    }
+
+    /// Chooses a memory type in a list of memory types. Returns the index of the memory type
+    /// in the list, or `None` if none of them can be used.
+    ///
+    /// Only the memory types whose bit is set in `memory_type_bits` are considered, similar to
+    /// the `memory_type_bits` field of `MemoryRequirements`.
+    pub fn choose<I>(&self, types: I, memory_type_bits: u32) -> Option<usize>
+        where I: IntoIterator<Item = MemoryTypeProperties>
+    {
+        let mut best: Option<(usize, u32)> = None;
+
+        for (index, ty) in types.into_iter().enumerate() {
+            if index >= 32 || (memory_type_bits & (1 << index)) == 0 {
+                continue;
+            }
+
+            let score = match self.score(&ty) {
+                Some(s) => s,
+                None => continue,
+            };
+
+            // Strict comparison, so that the first memory type wins in case of a tie.
+            if best.map(|(_, best_score)| score > best_score).unwrap_or(true) {
+                best = Some((index, score));
+            }
+        }
+
+        best.map(|(index, _)| index)
+    }
+
+    /// Chooses a memory type of a physical device. Returns `None` if none of the memory types
+    /// allowed by `memory_type_bits` can be used.
+    #[inline]
+    pub fn choose_memory_type<'a>(&self, physical_device: PhysicalDevice<'a>,
+                                  memory_type_bits: u32) -> Option<MemoryType<'a>>
+    {
+        let types = physical_device.memory_types().map(MemoryTypeProperties::from);
+        self.choose(types, memory_type_bits).map(|id| {
+            physical_device.memory_type_by_id(id as u32).unwrap()
+        })
+    }
+}
+
+/// Properties of a memory type that are taken into account by `MemoryUsage`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MemoryTypeProperties {
+    /// See `MemoryType::is_device_local`.
+    pub device_local: bool,
+    /// See `MemoryType::is_host_visible`.
+    pub host_visible: bool,
+    /// See `MemoryType::is_host_coherent`.
+    pub host_coherent: bool,
+    /// See `MemoryType::is_host_cached`.
+    pub host_cached: bool,
+    /// See `MemoryType::is_lazily_allocated`.
+    pub lazily_allocated: bool,
+}
+
+impl MemoryTypeProperties {
+    /// Builds a `MemoryTypeProperties` with all the properties set to false.
+    #[inline]
+    pub fn none() -> MemoryTypeProperties {
+        MemoryTypeProperties {
+            device_local: false,
+            host_visible: false,
+            host_coherent: false,
+            host_cached: false,
+            lazily_allocated: false,
+        }
+    }
+}
+
+impl<'a> From<MemoryType<'a>> for MemoryTypeProperties {
+    #[inline]
+    fn from(ty: MemoryType<'a>) -> MemoryTypeProperties {
+        MemoryTypeProperties {
+            device_local: ty.is_device_local(),
+            host_visible: ty.is_host_visible(),
+            host_coherent: ty.is_host_coherent(),
+            host_cached: ty.is_host_cached(),
+            lazily_allocated: ty.is_lazily_allocated(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use instance::PhysicalDevice;
+    use mock;
+    use super::MemoryTypeProperties;
+    use super::MemoryUsage;
+
+    const DEVICE_LOCAL: MemoryTypeProperties = MemoryTypeProperties {
+        device_local: true,
+        host_visible: false,
+        host_coherent: false,
+        host_cached: false,
+        lazily_allocated: false,
+    };
+
+    const HOST_COHERENT: MemoryTypeProperties = MemoryTypeProperties {
+        device_local: false,
+        host_visible: true,
+        host_coherent: true,
+        host_cached: false,
+        lazily_allocated: false,
+    };
+
+    const HOST_CACHED: MemoryTypeProperties = MemoryTypeProperties {
+        device_local: false,
+        host_visible: true,
+        host_coherent: true,
+        host_cached: true,
+        lazily_allocated: false,
+    };
+
+    const DEVICE_LOCAL_HOST_COHERENT: MemoryTypeProperties = MemoryTypeProperties {
+        device_local: true,
+        host_visible: true,
+        host_coherent: true,
+        host_cached: false,
+        lazily_allocated: false,
+    };
+
+    const DEVICE_LOCAL_HOST_CACHED: MemoryTypeProperties = MemoryTypeProperties {
+        device_local: true,
+        host_visible: true,
+        host_coherent: true,
+        host_cached: true,
+        lazily_allocated: false,
+    };
+
+    const LAZY: MemoryTypeProperties = MemoryTypeProperties {
+        device_local: true,
+        host_visible: false,
+        host_coherent: false,
+        host_cached: false,
+        lazily_allocated: true,
+    };
+
+    #[test]
+    fn discrete() {
+        let types = [DEVICE_LOCAL, HOST_COHERENT, HOST_CACHED];
+        let choose = |usage: MemoryUsage| usage.choose(types.iter().cloned(), 0b111);
+
+        assert_eq!(choose(MemoryUsage::GpuOnly), Some(0));
+        assert_eq!(choose(MemoryUsage::CpuToGpu), Some(1));
+        assert_eq!(choose(MemoryUsage::CpuOnly), Some(1));
+        assert_eq!(choose(MemoryUsage::GpuToCpu), Some(2));
+        assert_eq!(choose(MemoryUsage::Transient), Some(0));
+    }
+
+    #[test]
+    fn discrete_with_host_visible_device_memory() {
+        let types = [DEVICE_LOCAL, HOST_COHERENT, DEVICE_LOCAL_HOST_COHERENT];
+        let choose = |usage: MemoryUsage| usage.choose(types.iter().cloned(), 0b111);
+
+        assert_eq!(choose(MemoryUsage::GpuOnly), Some(0));
+        assert_eq!(choose(MemoryUsage::CpuToGpu), Some(2));
+        assert_eq!(choose(MemoryUsage::CpuOnly), Some(1));
+        assert_eq!(choose(MemoryUsage::GpuToCpu), Some(1));
+    }
+
+    #[test]
+    fn unified() {
+        let types = [DEVICE_LOCAL_HOST_COHERENT, DEVICE_LOCAL_HOST_CACHED, LAZY];
+        let choose = |usage: MemoryUsage| usage.choose(types.iter().cloned(), 0b111);
+
+        assert_eq!(choose(MemoryUsage::GpuOnly), Some(0));
+        assert_eq!(choose(MemoryUsage::CpuToGpu), Some(0));
+        assert_eq!(choose(MemoryUsage::CpuOnly), Some(0));
+        assert_eq!(choose(MemoryUsage::GpuToCpu), Some(1));
+        assert_eq!(choose(MemoryUsage::Transient), Some(2));
+    }
+
+    #[test]
+    fn memory_type_bits() {
+        let types = [DEVICE_LOCAL, HOST_COHERENT, HOST_CACHED];
+
+        // The device-local type is excluded, the first of the two fallbacks is chosen.
+        assert_eq!(MemoryUsage::GpuOnly.choose(types.iter().cloned(), 0b110), Some(1));
+        // Only the device-local type is allowed, which can't be accessed by the CPU.
+        assert_eq!(MemoryUsage::CpuOnly.choose(types.iter().cloned(), 0b001), None);
+        assert_eq!(MemoryUsage::GpuToCpu.choose(types.iter().cloned(), 0b011), Some(1));
+        assert_eq!(MemoryUsage::GpuOnly.choose(types.iter().cloned(), 0), None);
+    }
+
+    #[test]
+    fn ties_choose_first() {
+        let types = [HOST_COHERENT, DEVICE_LOCAL, DEVICE_LOCAL];
+        assert_eq!(MemoryUsage::GpuOnly.choose(types.iter().cloned(), 0b111), Some(1));
+        assert_eq!(MemoryUsage::GpuOnly.choose(types.iter().cloned(), 0b101), Some(2));
+    }
+
+    #[test]
+    fn physical_device() {
+        let instance = mock::instance();
+        let physical = PhysicalDevice::enumerate(&instance).next().unwrap();
+        let choose = |usage: MemoryUsage| {
+            let ty = usage.choose_memory_type(physical, !0).unwrap();
+            MemoryTypeProperties::from(ty)
+        };
+
+        assert!(choose(MemoryUsage::GpuOnly).device_local);
+        let readback = choose(MemoryUsage::GpuToCpu);
+        assert!(readback.host_cached && readback.host_coherent);
+        let staging = choose(MemoryUsage::CpuOnly);
+        assert!(staging.host_coherent && !staging.host_cached);
+    }
+
+    #[test]
+    fn score() {
+        assert_eq!(MemoryUsage::CpuOnly.score(&DEVICE_LOCAL), None);
+        assert_eq!(MemoryUsage::CpuOnly.score(&MemoryTypeProperties::none()), None);
+        assert!(MemoryUsage::GpuOnly.score(&MemoryTypeProperties::none()).is_some());
+        assert!(MemoryUsage::GpuOnly.score(&DEVICE_LOCAL) >
+                MemoryUsage::GpuOnly.score(&DEVICE_LOCAL_HOST_COHERENT));
+        assert!(MemoryUsage::GpuOnly.score(&DEVICE_LOCAL_HOST_COHERENT) >
+                MemoryUsage::GpuOnly.score(&HOST_COHERENT));
+    }
+}
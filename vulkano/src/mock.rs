@@ -0,0 +1,1731 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! In-process mock implementation of Vulkan, used to run the tests on machines without a GPU.
+//!
+//! The mock exposes a single `vkGetInstanceProcAddr` function that can be passed to a
+//! `ProcAddrLoader`. It reports one CPU physical device with three queue families, four memory
+//! types and the `VK_KHR_dedicated_allocation` extensions, hands out fake handles for every
+//! object, and records the name of each device-level function call so that tests can check what
+//! vulkano submitted.
+//!
+//! No command is ever executed. Submitting to a queue immediately signals the fence, and
+//! host-visible memory is backed by a regular `Vec` when it gets mapped.
+
+#![cfg(test)]
+#![allow(non_snake_case)]
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ffi::CStr;
+use std::mem;
+use std::os::raw::c_char;
+use std::os::raw::c_void;
+use std::ptr;
+use std::slice;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use device::Device;
+use format::Format;
+use format::FormatTy;
+use instance::Instance;
+use instance::InstanceExtensions;
+use instance::loader::FunctionPointers;
+use instance::loader::ProcAddrLoader;
+use VulkanObject;
+use vk;
+
+/// Size of the device-local heap reported by the mock.
+pub const DEVICE_LOCAL_HEAP_SIZE: u64 = 256 * 1024 * 1024;
+/// Size of the host-visible heap reported by the mock.
+pub const HOST_HEAP_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Builds a new `Instance` that uses the mock implementation.
+pub fn instance() -> Arc<Instance> {
+    let loader = unsafe { ProcAddrLoader::new(GetInstanceProcAddr) };
+    let ptrs = FunctionPointers::new(Box::new(loader) as Box<_>);
+    Instance::with_loader(ptrs, None, &InstanceExtensions::none(), None).unwrap()
+}
+
+/// Returns the list of the device-level functions that were called on a device created from
+/// a mock instance, in order.
+///
+/// # Panic
+///
+/// - Panics if the device doesn't belong to a mock instance.
+///
+pub fn calls(device: &Device) -> Vec<&'static str> {
+    assert_eq!(device.physical_device().name(), MOCK_DEVICE_NAME,
+               "The device doesn't belong to a mock instance");
+    let device = unsafe { &*(device.internal_object() as *const MockDevice) };
+    device.calls.lock().unwrap().clone()
+}
+
+struct MockInstance {
+    // Only used for its address, which is the handle of the physical device.
+    physical_device: u8,
+}
+
+struct MockDevice {
+    calls: Mutex<Vec<&'static str>>,
+    state: Mutex<DeviceState>,
+    queues: Mutex<Vec<Box<MockQueue>>>,
+}
+
+#[derive(Default)]
+struct DeviceState {
+    next_handle: u64,
+    heaps_usage: [u64; 2],
+    memory: HashMap<u64, Allocation>,
+    buffers: HashMap<u64, u64>,
+    images: HashMap<u64, u64>,
+    sparse_images: HashMap<u64, SparseImage>,
+    // Buffers and images that were reported as requiring a dedicated allocation.
+    requires_dedicated: HashSet<u64>,
+    fences: HashMap<u64, bool>,
+    events: HashMap<u64, bool>,
+}
+
+struct Allocation {
+    heap: usize,
+    size: u64,
+    data: Vec<u8>,
+    // Buffer or image passed with `VkMemoryDedicatedAllocateInfoKHR`, if any.
+    dedicated_to: Option<u64>,
+}
+
+// Description of an image created with sparse binding.
+struct SparseImage {
+    residency: bool,
+    ty: vk::ImageType,
+    extent: [u32; 3],
+    mip_levels: u32,
+    array_layers: u32,
+}
+
+// Common header of the structs that can be part of a `pNext` chain.
+#[repr(C)]
+struct BaseStructure {
+    sType: vk::StructureType,
+    pNext: *const c_void,
+}
+
+// Looks for a struct of the given type in a `pNext` chain.
+unsafe fn find_in_chain(mut next: *const c_void, ty: vk::StructureType) -> *const c_void {
+    while !next.is_null() {
+        let base = &*(next as *const BaseStructure);
+        if base.sType == ty {
+            return next;
+        }
+        next = base.pNext;
+    }
+
+    ptr::null()
+}
+
+struct MockQueue {
+    device: *const MockDevice,
+    family: u32,
+    index: u32,
+}
+
+struct MockCommandBuffer {
+    device: *const MockDevice,
+}
+
+impl MockDevice {
+    #[inline]
+    fn record(&self, name: &'static str) {
+        self.calls.lock().unwrap().push(name);
+    }
+}
+
+impl DeviceState {
+    #[inline]
+    fn new_handle(&mut self) -> u64 {
+        self.next_handle += 1;
+        self.next_handle
+    }
+}
+
+#[inline]
+unsafe fn device<'a>(device: vk::Device) -> &'a MockDevice {
+    &*(device as *const MockDevice)
+}
+
+#[inline]
+unsafe fn queue<'a>(queue: vk::Queue) -> &'a MockQueue {
+    &*(queue as *const MockQueue)
+}
+
+#[inline]
+unsafe fn command_buffer_device<'a>(cb: vk::CommandBuffer) -> &'a MockDevice {
+    &*(*(cb as *const MockCommandBuffer)).device
+}
+
+// Memory types reported by the mock: `(property flags, heap index)`.
+const MEMORY_TYPES: [(u32, u32); 4] = [
+    (vk::MEMORY_PROPERTY_DEVICE_LOCAL_BIT, 0),
+    (vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT | vk::MEMORY_PROPERTY_HOST_COHERENT_BIT, 1),
+    (vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT | vk::MEMORY_PROPERTY_HOST_COHERENT_BIT |
+     vk::MEMORY_PROPERTY_HOST_CACHED_BIT, 1),
+    (vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT | vk::MEMORY_PROPERTY_HOST_CACHED_BIT, 1),
+];
+
+// Value of the `nonCoherentAtomSize` limit reported by the mock.
+const NON_COHERENT_ATOM_SIZE: u64 = 64;
+
+// Flags, number of queues and number of valid bits in timestamps of each queue family.
+const QUEUE_FAMILIES: [(u32, u32, u32); 3] = [
+    (vk::QUEUE_GRAPHICS_BIT | vk::QUEUE_COMPUTE_BIT | vk::QUEUE_TRANSFER_BIT |
+     vk::QUEUE_SPARSE_BINDING_BIT, 4, 64),
+    (vk::QUEUE_COMPUTE_BIT | vk::QUEUE_TRANSFER_BIT, 2, 64),
+    (vk::QUEUE_TRANSFER_BIT, 1, 0),
+];
+
+const MOCK_DEVICE_NAME: &'static str = "vulkano mock device";
+
+// Device extensions reported by the mock.
+const DEVICE_EXTENSIONS: [&'static [u8]; 2] = [
+    b"VK_KHR_get_memory_requirements2",
+    b"VK_KHR_dedicated_allocation",
+];
+
+// Buffers and images at least this large are reported as requiring a dedicated allocation when
+// `VK_KHR_dedicated_allocation` is used.
+const DEDICATED_SIZE: u64 = 4 * 1024 * 1024;
+
+const BUFFER_ALIGNMENT: u64 = 256;
+const IMAGE_ALIGNMENT: u64 = 1024;
+
+// Size of a sparse block, which is also the alignment of images created with sparse binding.
+// Since the mock assumes 16 bytes per texel, the granularities below all cover 64 kiB.
+const SPARSE_BLOCK_SIZE: u64 = 64 * 1024;
+const SPARSE_GRANULARITY_2D: [u32; 3] = [64, 64, 1];
+const SPARSE_GRANULARITY_3D: [u32; 3] = [16, 16, 16];
+
+/// Writes a list of elements with the usual "two-call" Vulkan idiom.
+unsafe fn write_array<T, F>(count: *mut u32, out: *mut T, len: u32, mut f: F) -> vk::Result
+    where F: FnMut(usize) -> T
+{
+    if out.is_null() {
+        *count = len;
+        return vk::SUCCESS;
+    }
+
+    let written = (*count).min(len);
+    for n in 0 .. written as usize {
+        ptr::write(out.offset(n as isize), f(n));
+    }
+    *count = written;
+
+    if written < len { vk::INCOMPLETE } else { vk::SUCCESS }
+}
+
+macro_rules! proc_table {
+    ($name:expr, $($f:ident,)*) => ({
+        match $name {
+            $(
+                n if n == concat!("vk", stringify!($f)).as_bytes() => {
+                    Some($f as *const c_void)
+                },
+            )*
+            _ => None
+        }
+    });
+}
+
+extern "system" fn GetInstanceProcAddr(_: vk::Instance, name_ptr: *const c_char)
+                                       -> extern "system" fn() -> ()
+{
+    let name = unsafe { CStr::from_ptr(name_ptr) }.to_bytes();
+
+    let ptr = proc_table!(name,
+        CreateInstance,
+        EnumerateInstanceExtensionProperties,
+        EnumerateInstanceLayerProperties,
+        DestroyInstance,
+        GetDeviceProcAddr,
+        EnumeratePhysicalDevices,
+        EnumerateDeviceExtensionProperties,
+        EnumerateDeviceLayerProperties,
+        CreateDevice,
+        GetPhysicalDeviceFeatures,
+        GetPhysicalDeviceFormatProperties,
+        GetPhysicalDeviceImageFormatProperties,
+        GetPhysicalDeviceProperties,
+        GetPhysicalDeviceQueueFamilyProperties,
+        GetPhysicalDeviceMemoryProperties,
+        GetPhysicalDeviceSparseImageFormatProperties,
+    );
+
+    match ptr {
+        Some(ptr) => unsafe { mem::transmute(ptr) },
+        None => GetDeviceProcAddr(0, name_ptr),
+    }
+}
+
+extern "system" fn GetDeviceProcAddr(_: vk::Device, name: *const c_char)
+                                     -> extern "system" fn() -> ()
+{
+    let name = unsafe { CStr::from_ptr(name) }.to_bytes();
+
+    let ptr = proc_table!(name,
+        DestroyDevice,
+        GetDeviceQueue,
+        QueueSubmit,
+        QueueWaitIdle,
+        DeviceWaitIdle,
+        AllocateMemory,
+        FreeMemory,
+        MapMemory,
+        UnmapMemory,
+        FlushMappedMemoryRanges,
+        InvalidateMappedMemoryRanges,
+        GetDeviceMemoryCommitment,
+        BindBufferMemory,
+        BindImageMemory,
+        GetBufferMemoryRequirements,
+        GetImageMemoryRequirements,
+        GetBufferMemoryRequirements2KHR,
+        GetImageMemoryRequirements2KHR,
+        GetImageSparseMemoryRequirements,
+        QueueBindSparse,
+        CreateFence,
+        DestroyFence,
+        ResetFences,
+        GetFenceStatus,
+        WaitForFences,
+        CreateSemaphore,
+        DestroySemaphore,
+        CreateEvent,
+        DestroyEvent,
+        GetEventStatus,
+        SetEvent,
+        ResetEvent,
+        CreateQueryPool,
+        DestroyQueryPool,
+        GetQueryPoolResults,
+        CreateBuffer,
+        DestroyBuffer,
+        CreateBufferView,
+        DestroyBufferView,
+        CreateImage,
+        DestroyImage,
+        GetImageSubresourceLayout,
+        CreateImageView,
+        DestroyImageView,
+        CreateShaderModule,
+        DestroyShaderModule,
+        CreatePipelineCache,
+        DestroyPipelineCache,
+        GetPipelineCacheData,
+        MergePipelineCaches,
+        CreateGraphicsPipelines,
+        CreateComputePipelines,
+        DestroyPipeline,
+        CreatePipelineLayout,
+        DestroyPipelineLayout,
+        CreateSampler,
+        DestroySampler,
+        CreateDescriptorSetLayout,
+        DestroyDescriptorSetLayout,
+        CreateDescriptorPool,
+        DestroyDescriptorPool,
+        ResetDescriptorPool,
+        AllocateDescriptorSets,
+        FreeDescriptorSets,
+        UpdateDescriptorSets,
+        CreateFramebuffer,
+        DestroyFramebuffer,
+        CreateRenderPass,
+        DestroyRenderPass,
+        GetRenderAreaGranularity,
+        CreateCommandPool,
+        DestroyCommandPool,
+        ResetCommandPool,
+        TrimCommandPoolKHR,
+        AllocateCommandBuffers,
+        FreeCommandBuffers,
+        BeginCommandBuffer,
+        EndCommandBuffer,
+        ResetCommandBuffer,
+        CmdBindPipeline,
+        CmdSetViewport,
+        CmdSetScissor,
+        CmdSetLineWidth,
+        CmdSetDepthBias,
+        CmdSetBlendConstants,
+        CmdSetDepthBounds,
+        CmdSetStencilCompareMask,
+        CmdSetStencilWriteMask,
+        CmdSetStencilReference,
+        CmdBindDescriptorSets,
+        CmdBindIndexBuffer,
+        CmdBindVertexBuffers,
+        CmdDraw,
+        CmdDrawIndexed,
+        CmdDrawIndirect,
+        CmdDrawIndexedIndirect,
+        CmdDispatch,
+        CmdDispatchIndirect,
+        CmdCopyBuffer,
+        CmdCopyImage,
+        CmdBlitImage,
+        CmdCopyBufferToImage,
+        CmdCopyImageToBuffer,
+        CmdUpdateBuffer,
+        CmdFillBuffer,
+        CmdClearColorImage,
+        CmdClearDepthStencilImage,
+        CmdClearAttachments,
+        CmdResolveImage,
+        CmdSetEvent,
+        CmdResetEvent,
+        CmdWaitEvents,
+        CmdPipelineBarrier,
+        CmdBeginQuery,
+        CmdEndQuery,
+        CmdResetQueryPool,
+        CmdWriteTimestamp,
+        CmdCopyQueryPoolResults,
+        CmdPushConstants,
+        CmdBeginRenderPass,
+        CmdNextSubpass,
+        CmdEndRenderPass,
+        CmdExecuteCommands,
+    );
+
+    // A null pointer is how Vulkan reports an unsupported function. `vk-sys` then replaces it
+    // with a function that panics when called.
+    unsafe { mem::transmute(ptr.unwrap_or(ptr::null())) }
+}
+
+extern "system" fn CreateInstance(_: *const vk::InstanceCreateInfo,
+                                  _: *const vk::AllocationCallbacks,
+                                  out: *mut vk::Instance) -> vk::Result
+{
+    unsafe {
+        *out = Box::into_raw(Box::new(MockInstance { physical_device: 0 })) as vk::Instance;
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn EnumerateInstanceExtensionProperties(_: *const c_char, count: *mut u32,
+                                                        out: *mut vk::ExtensionProperties)
+                                                        -> vk::Result
+{
+    unsafe { write_array(count, out, 0, |_| unreachable!()) }
+}
+
+extern "system" fn EnumerateInstanceLayerProperties(count: *mut u32,
+                                                    out: *mut vk::LayerProperties) -> vk::Result
+{
+    unsafe { write_array(count, out, 0, |_| unreachable!()) }
+}
+
+extern "system" fn DestroyInstance(instance: vk::Instance, _: *const vk::AllocationCallbacks) {
+    unsafe {
+        drop(Box::from_raw(instance as *mut MockInstance));
+    }
+}
+
+extern "system" fn EnumeratePhysicalDevices(instance: vk::Instance, count: *mut u32,
+                                            out: *mut vk::PhysicalDevice) -> vk::Result
+{
+    unsafe {
+        let instance = &*(instance as *const MockInstance);
+        let handle = &instance.physical_device as *const u8 as vk::PhysicalDevice;
+        write_array(count, out, 1, |_| handle)
+    }
+}
+
+extern "system" fn EnumerateDeviceExtensionProperties(_: vk::PhysicalDevice, _: *const c_char,
+                                                      count: *mut u32,
+                                                      out: *mut vk::ExtensionProperties)
+                                                      -> vk::Result
+{
+    unsafe {
+        write_array(count, out, DEVICE_EXTENSIONS.len() as u32, |n| {
+            let mut props = vk::ExtensionProperties {
+                extensionName: [0; vk::MAX_EXTENSION_NAME_SIZE as usize],
+                specVersion: 1,
+            };
+            for (dest, &src) in props.extensionName.iter_mut().zip(DEVICE_EXTENSIONS[n]) {
+                *dest = src as c_char;
+            }
+            props
+        })
+    }
+}
+
+extern "system" fn EnumerateDeviceLayerProperties(_: vk::PhysicalDevice, count: *mut u32,
+                                                  out: *mut vk::LayerProperties) -> vk::Result
+{
+    unsafe { write_array(count, out, 0, |_| unreachable!()) }
+}
+
+extern "system" fn GetPhysicalDeviceFeatures(_: vk::PhysicalDevice,
+                                             out: *mut vk::PhysicalDeviceFeatures)
+{
+    // All the members of `VkPhysicalDeviceFeatures` are `VkBool32`s, and the mock supports
+    // everything.
+    unsafe {
+        let len = mem::size_of::<vk::PhysicalDeviceFeatures>() / mem::size_of::<vk::Bool32>();
+        let out = slice::from_raw_parts_mut(out as *mut vk::Bool32, len);
+        for f in out.iter_mut() {
+            *f = vk::TRUE;
+        }
+    }
+}
+
+// Like most implementations, the mock doesn't support the 64-bit formats at all. This lets the
+// tests exercise the errors returned for unsupported formats.
+fn is_unsupported_format(format: vk::Format) -> bool {
+    format >= vk::FORMAT_R64_UINT && format <= vk::FORMAT_R64G64B64A64_SFLOAT
+}
+
+extern "system" fn GetPhysicalDeviceFormatProperties(_: vk::PhysicalDevice, format: vk::Format,
+                                                     out: *mut vk::FormatProperties)
+{
+    if is_unsupported_format(format) {
+        unsafe {
+            *out = vk::FormatProperties {
+                linearTilingFeatures: 0,
+                optimalTilingFeatures: 0,
+                bufferFeatures: 0,
+            };
+        }
+        return;
+    }
+
+    // Compressed formats can only be sampled and read from.
+    let compressed = format >= vk::FORMAT_BC1_RGB_UNORM_BLOCK &&
+                     format <= vk::FORMAT_ASTC_12x12_SRGB_BLOCK;
+
+    // As required by Vulkan, integer formats can't be sampled with a linear filter.
+    let integer = match Format::from_num(format).map(|f| f.ty()) {
+        Some(FormatTy::Uint) | Some(FormatTy::Sint) => true,
+        _ => false,
+    };
+
+    let image_features = if compressed {
+        vk::FORMAT_FEATURE_SAMPLED_IMAGE_BIT |
+        vk::FORMAT_FEATURE_BLIT_SRC_BIT |
+        vk::FORMAT_FEATURE_SAMPLED_IMAGE_FILTER_LINEAR_BIT
+    } else {
+        vk::FORMAT_FEATURE_SAMPLED_IMAGE_BIT |
+        vk::FORMAT_FEATURE_STORAGE_IMAGE_BIT |
+        vk::FORMAT_FEATURE_STORAGE_IMAGE_ATOMIC_BIT |
+        vk::FORMAT_FEATURE_COLOR_ATTACHMENT_BIT |
+        vk::FORMAT_FEATURE_COLOR_ATTACHMENT_BLEND_BIT |
+        vk::FORMAT_FEATURE_DEPTH_STENCIL_ATTACHMENT_BIT |
+        vk::FORMAT_FEATURE_BLIT_SRC_BIT |
+        vk::FORMAT_FEATURE_BLIT_DST_BIT |
+        if integer { 0 } else { vk::FORMAT_FEATURE_SAMPLED_IMAGE_FILTER_LINEAR_BIT }
+    };
+    let buffer_features = vk::FORMAT_FEATURE_UNIFORM_TEXEL_BUFFER_BIT |
+                          vk::FORMAT_FEATURE_STORAGE_TEXEL_BUFFER_BIT |
+                          vk::FORMAT_FEATURE_STORAGE_TEXEL_BUFFER_ATOMIC_BIT |
+                          vk::FORMAT_FEATURE_VERTEX_BUFFER_BIT;
+
+    unsafe {
+        *out = vk::FormatProperties {
+            linearTilingFeatures: image_features,
+            optimalTilingFeatures: image_features,
+            bufferFeatures: buffer_features,
+        };
+    }
+}
+
+extern "system" fn GetPhysicalDeviceImageFormatProperties(_: vk::PhysicalDevice,
+                                                          format: vk::Format, _: vk::ImageType,
+                                                          _: vk::ImageTiling,
+                                                          _: vk::ImageUsageFlags,
+                                                          _: vk::ImageCreateFlags,
+                                                          out: *mut vk::ImageFormatProperties)
+                                                          -> vk::Result
+{
+    if is_unsupported_format(format) {
+        return vk::ERROR_FORMAT_NOT_SUPPORTED;
+    }
+
+    unsafe {
+        *out = vk::ImageFormatProperties {
+            maxExtent: vk::Extent3D { width: 16384, height: 16384, depth: 2048 },
+            maxMipLevels: 15,
+            maxArrayLayers: 2048,
+            sampleCounts: vk::SAMPLE_COUNT_1_BIT | vk::SAMPLE_COUNT_2_BIT |
+                          vk::SAMPLE_COUNT_4_BIT | vk::SAMPLE_COUNT_8_BIT,
+            maxResourceSize: 1 << 31,
+        };
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn GetPhysicalDeviceProperties(_: vk::PhysicalDevice,
+                                               out: *mut vk::PhysicalDeviceProperties)
+{
+    unsafe {
+        let mut props: vk::PhysicalDeviceProperties = mem::zeroed();
+        props.apiVersion = 1 << 22;
+        props.deviceType = vk::PHYSICAL_DEVICE_TYPE_CPU;
+        for (dest, src) in props.deviceName.iter_mut().zip(MOCK_DEVICE_NAME.bytes()) {
+            *dest = src as c_char;
+        }
+
+        let samples = vk::SAMPLE_COUNT_1_BIT | vk::SAMPLE_COUNT_2_BIT |
+                      vk::SAMPLE_COUNT_4_BIT | vk::SAMPLE_COUNT_8_BIT;
+
+        {
+            let l = &mut props.limits;
+            l.maxImageDimension1D = 16384;
+            l.maxImageDimension2D = 16384;
+            l.maxImageDimension3D = 2048;
+            l.maxImageDimensionCube = 16384;
+            l.maxImageArrayLayers = 2048;
+            l.maxTexelBufferElements = 128 * 1024 * 1024;
+            l.maxUniformBufferRange = 65536;
+            l.maxStorageBufferRange = 128 * 1024 * 1024;
+            l.maxPushConstantsSize = 256;
+            l.maxMemoryAllocationCount = 4096;
+            l.maxSamplerAllocationCount = 4000;
+            l.bufferImageGranularity = 1024;
+            l.sparseAddressSpaceSize = 1 << 40;
+            l.maxBoundDescriptorSets = 8;
+            l.maxPerStageDescriptorSamplers = 1024;
+            l.maxPerStageDescriptorUniformBuffers = 64;
+            l.maxPerStageDescriptorStorageBuffers = 64;
+            l.maxPerStageDescriptorSampledImages = 1024;
+            l.maxPerStageDescriptorStorageImages = 64;
+            l.maxPerStageDescriptorInputAttachments = 8;
+            l.maxPerStageResources = 4096;
+            l.maxDescriptorSetSamplers = 4096;
+            l.maxDescriptorSetUniformBuffers = 256;
+            l.maxDescriptorSetUniformBuffersDynamic = 16;
+            l.maxDescriptorSetStorageBuffers = 256;
+            l.maxDescriptorSetStorageBuffersDynamic = 16;
+            l.maxDescriptorSetSampledImages = 4096;
+            l.maxDescriptorSetStorageImages = 256;
+            l.maxDescriptorSetInputAttachments = 8;
+            l.maxVertexInputAttributes = 32;
+            l.maxVertexInputBindings = 32;
+            l.maxVertexInputAttributeOffset = 2047;
+            l.maxVertexInputBindingStride = 2048;
+            l.maxVertexOutputComponents = 128;
+            l.maxTessellationGenerationLevel = 64;
+            l.maxTessellationPatchSize = 32;
+            l.maxTessellationControlPerVertexInputComponents = 128;
+            l.maxTessellationControlPerVertexOutputComponents = 128;
+            l.maxTessellationControlPerPatchOutputComponents = 120;
+            l.maxTessellationControlTotalOutputComponents = 4096;
+            l.maxTessellationEvaluationInputComponents = 128;
+            l.maxTessellationEvaluationOutputComponents = 128;
+            l.maxGeometryShaderInvocations = 32;
+            l.maxGeometryInputComponents = 128;
+            l.maxGeometryOutputComponents = 128;
+            l.maxGeometryOutputVertices = 256;
+            l.maxGeometryTotalOutputComponents = 1024;
+            l.maxFragmentInputComponents = 128;
+            l.maxFragmentOutputAttachments = 8;
+            l.maxFragmentDualSrcAttachments = 1;
+            l.maxFragmentCombinedOutputResources = 8;
+            l.maxComputeSharedMemorySize = 32768;
+            l.maxComputeWorkGroupCount = [65535, 65535, 65535];
+            l.maxComputeWorkGroupInvocations = 1024;
+            l.maxComputeWorkGroupSize = [1024, 1024, 64];
+            l.subPixelPrecisionBits = 8;
+            l.subTexelPrecisionBits = 8;
+            l.mipmapPrecisionBits = 8;
+            l.maxDrawIndexedIndexValue = 0xffffffff;
+            l.maxDrawIndirectCount = 0xffffffff;
+            l.maxSamplerLodBias = 16.0;
+            l.maxSamplerAnisotropy = 16.0;
+            l.maxViewports = 16;
+            l.maxViewportDimensions = [16384, 16384];
+            l.viewportBoundsRange = [-32768.0, 32767.0];
+            l.viewportSubPixelBits = 8;
+            l.minMemoryMapAlignment = 64;
+            l.minTexelBufferOffsetAlignment = 16;
+            l.minUniformBufferOffsetAlignment = 256;
+            l.minStorageBufferOffsetAlignment = 16;
+            l.minTexelOffset = -8;
+            l.maxTexelOffset = 7;
+            l.minTexelGatherOffset = -32;
+            l.maxTexelGatherOffset = 31;
+            l.minInterpolationOffset = -0.5;
+            l.maxInterpolationOffset = 0.4375;
+            l.subPixelInterpolationOffsetBits = 4;
+            l.maxFramebufferWidth = 16384;
+            l.maxFramebufferHeight = 16384;
+            l.maxFramebufferLayers = 2048;
+            l.framebufferColorSampleCounts = samples;
+            l.framebufferDepthSampleCounts = samples;
+            l.framebufferStencilSampleCounts = samples;
+            l.framebufferNoAttachmentsSampleCounts = samples;
+            l.maxColorAttachments = 8;
+            l.sampledImageColorSampleCounts = samples;
+            l.sampledImageIntegerSampleCounts = samples;
+            l.sampledImageDepthSampleCounts = samples;
+            l.sampledImageStencilSampleCounts = samples;
+            l.storageImageSampleCounts = samples;
+            l.maxSampleMaskWords = 1;
+            l.timestampComputeAndGraphics = vk::TRUE;
+            l.timestampPeriod = 1.0;
+            l.maxClipDistances = 8;
+            l.maxCullDistances = 8;
+            l.maxCombinedClipAndCullDistances = 8;
+            l.discreteQueuePriorities = 2;
+            l.pointSizeRange = [1.0, 64.0];
+            l.lineWidthRange = [1.0, 8.0];
+            l.pointSizeGranularity = 1.0;
+            l.lineWidthGranularity = 1.0;
+            l.strictLines = vk::TRUE;
+            l.standardSampleLocations = vk::TRUE;
+            l.optimalBufferCopyOffsetAlignment = 1;
+            l.optimalBufferCopyRowPitchAlignment = 1;
+            l.nonCoherentAtomSize = NON_COHERENT_ATOM_SIZE;
+        }
+
+        *out = props;
+    }
+}
+
+extern "system" fn GetPhysicalDeviceQueueFamilyProperties(_: vk::PhysicalDevice,
+                                                          count: *mut u32,
+                                                          out: *mut vk::QueueFamilyProperties)
+{
+    unsafe {
+        write_array(count, out, QUEUE_FAMILIES.len() as u32, |n| {
+            vk::QueueFamilyProperties {
+                queueFlags: QUEUE_FAMILIES[n].0,
+                queueCount: QUEUE_FAMILIES[n].1,
+                timestampValidBits: QUEUE_FAMILIES[n].2,
+                minImageTransferGranularity: vk::Extent3D { width: 1, height: 1, depth: 1 },
+            }
+        });
+    }
+}
+
+extern "system" fn GetPhysicalDeviceMemoryProperties(_: vk::PhysicalDevice,
+                                                     out: *mut vk::PhysicalDeviceMemoryProperties)
+{
+    unsafe {
+        let mut props: vk::PhysicalDeviceMemoryProperties = mem::zeroed();
+
+        props.memoryTypeCount = MEMORY_TYPES.len() as u32;
+        for (n, &(flags, heap)) in MEMORY_TYPES.iter().enumerate() {
+            props.memoryTypes[n].propertyFlags = flags;
+            props.memoryTypes[n].heapIndex = heap;
+        }
+
+        props.memoryHeapCount = 2;
+        props.memoryHeaps[0].size = DEVICE_LOCAL_HEAP_SIZE;
+        props.memoryHeaps[0].flags = vk::MEMORY_HEAP_DEVICE_LOCAL_BIT;
+        props.memoryHeaps[1].size = HOST_HEAP_SIZE;
+        props.memoryHeaps[1].flags = 0;
+
+        *out = props;
+    }
+}
+
+extern "system" fn GetPhysicalDeviceSparseImageFormatProperties(
+    _: vk::PhysicalDevice, _: vk::Format, _: vk::ImageType, _: vk::SampleCountFlagBits,
+    _: vk::ImageUsageFlags, _: vk::ImageTiling, count: *mut u32,
+    out: *mut vk::SparseImageFormatProperties)
+{
+    unsafe { write_array(count, out, 0, |_| unreachable!()); }
+}
+
+extern "system" fn CreateDevice(_: vk::PhysicalDevice, _: *const vk::DeviceCreateInfo,
+                                _: *const vk::AllocationCallbacks, out: *mut vk::Device)
+                                -> vk::Result
+{
+    let device = Box::new(MockDevice {
+        calls: Mutex::new(Vec::new()),
+        state: Mutex::new(Default::default()),
+        queues: Mutex::new(Vec::new()),
+    });
+
+    unsafe {
+        *out = Box::into_raw(device) as vk::Device;
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn DestroyDevice(device: vk::Device, _: *const vk::AllocationCallbacks) {
+    unsafe {
+        drop(Box::from_raw(device as *mut MockDevice));
+    }
+}
+
+extern "system" fn GetDeviceQueue(device_handle: vk::Device, family: u32, index: u32,
+                                  out: *mut vk::Queue)
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("GetDeviceQueue");
+        let mut queues = device.queues.lock().unwrap();
+
+        let existing = queues.iter().position(|q| q.family == family && q.index == index);
+        let pos = match existing {
+            Some(pos) => pos,
+            None => {
+                queues.push(Box::new(MockQueue {
+                    device: device as *const MockDevice,
+                    family: family,
+                    index: index,
+                }));
+                queues.len() - 1
+            },
+        };
+
+        *out = &*queues[pos] as *const MockQueue as vk::Queue;
+    }
+}
+
+extern "system" fn QueueSubmit(queue_handle: vk::Queue, _: u32, _: *const vk::SubmitInfo,
+                               fence: vk::Fence) -> vk::Result
+{
+    unsafe {
+        let device = &*queue(queue_handle).device;
+        device.record("QueueSubmit");
+        if fence != 0 {
+            device.state.lock().unwrap().fences.insert(fence, true);
+        }
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn QueueBindSparse(queue_handle: vk::Queue, count: u32,
+                                   infos: *const vk::BindSparseInfo, fence: vk::Fence)
+                                   -> vk::Result
+{
+    unsafe {
+        let device = &*queue(queue_handle).device;
+        device.record("QueueBindSparse");
+
+        let mut state = device.state.lock().unwrap();
+        for infos in slice::from_raw_parts(infos, count as usize) {
+            let image_binds = slice::from_raw_parts(infos.pImageBinds,
+                                                    infos.imageBindCount as usize);
+            for image_binds in image_binds {
+                let image = state.sparse_images.get(&image_binds.image)
+                                 .expect("Bound memory to an image without sparse residency");
+                assert!(image.residency, "Bound memory to an image without sparse residency");
+
+                let binds = slice::from_raw_parts(image_binds.pBinds,
+                                                  image_binds.bindCount as usize);
+                for bind in binds {
+                    assert!(bind.subresource.mipLevel < image.mip_levels);
+                    assert!(bind.subresource.arrayLayer < image.array_layers);
+                    assert!(bind.memory == 0 || state.memory.contains_key(&bind.memory),
+                            "Bound an image to memory that doesn't exist");
+                }
+            }
+        }
+
+        if fence != 0 {
+            state.fences.insert(fence, true);
+        }
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn QueueWaitIdle(queue_handle: vk::Queue) -> vk::Result {
+    unsafe { (*queue(queue_handle).device).record("QueueWaitIdle"); }
+    vk::SUCCESS
+}
+
+extern "system" fn DeviceWaitIdle(device_handle: vk::Device) -> vk::Result {
+    unsafe { device(device_handle).record("DeviceWaitIdle"); }
+    vk::SUCCESS
+}
+
+extern "system" fn AllocateMemory(device_handle: vk::Device,
+                                  infos: *const vk::MemoryAllocateInfo,
+                                  _: *const vk::AllocationCallbacks, out: *mut vk::DeviceMemory)
+                                  -> vk::Result
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("AllocateMemory");
+        let infos = &*infos;
+        let heap = MEMORY_TYPES[infos.memoryTypeIndex as usize].1 as usize;
+        let heap_size = if heap == 0 { DEVICE_LOCAL_HEAP_SIZE } else { HOST_HEAP_SIZE };
+
+        let mut state = device.state.lock().unwrap();
+        match state.heaps_usage[heap].checked_add(infos.allocationSize) {
+            Some(total) if total <= heap_size => state.heaps_usage[heap] = total,
+            _ => return vk::ERROR_OUT_OF_DEVICE_MEMORY,
+        }
+
+        let dedicated = find_in_chain(infos.pNext,
+                                      vk::STRUCTURE_TYPE_MEMORY_DEDICATED_ALLOCATE_INFO_KHR)
+                            as *const vk::MemoryDedicatedAllocateInfoKHR;
+        let dedicated_to = if dedicated.is_null() {
+            None
+        } else {
+            let dedicated = &*dedicated;
+            assert!((dedicated.image == 0) != (dedicated.buffer == 0));
+            Some(if dedicated.image != 0 { dedicated.image } else { dedicated.buffer })
+        };
+
+        let handle = state.new_handle();
+        state.memory.insert(handle, Allocation {
+            heap: heap,
+            size: infos.allocationSize,
+            data: Vec::new(),
+            dedicated_to: dedicated_to,
+        });
+        *out = handle;
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn FreeMemory(device_handle: vk::Device, memory: vk::DeviceMemory,
+                              _: *const vk::AllocationCallbacks)
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("FreeMemory");
+        let mut state = device.state.lock().unwrap();
+        let alloc = state.memory.remove(&memory).expect("Freeing unknown memory");
+        state.heaps_usage[alloc.heap] -= alloc.size;
+    }
+}
+
+extern "system" fn MapMemory(device_handle: vk::Device, memory: vk::DeviceMemory,
+                             offset: vk::DeviceSize, _: vk::DeviceSize, _: vk::MemoryMapFlags,
+                             out: *mut *mut c_void) -> vk::Result
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("MapMemory");
+        let mut state = device.state.lock().unwrap();
+        let alloc = state.memory.get_mut(&memory).expect("Mapping unknown memory");
+        assert!(alloc.heap != 0, "Mapping memory that isn't host-visible");
+
+        // The backing storage is only created when needed, so that large device-local
+        // allocations don't cost anything.
+        if alloc.data.is_empty() {
+            alloc.data = vec![0; alloc.size as usize];
+        }
+
+        *out = alloc.data.as_mut_ptr().offset(offset as isize) as *mut c_void;
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn UnmapMemory(device_handle: vk::Device, _: vk::DeviceMemory) {
+    unsafe { device(device_handle).record("UnmapMemory"); }
+}
+
+extern "system" fn FlushMappedMemoryRanges(device_handle: vk::Device, count: u32,
+                                           ranges: *const vk::MappedMemoryRange) -> vk::Result
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("FlushMappedMemoryRanges");
+        check_mapped_ranges(device, slice::from_raw_parts(ranges, count as usize));
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn InvalidateMappedMemoryRanges(device_handle: vk::Device, count: u32,
+                                                ranges: *const vk::MappedMemoryRange)
+                                                -> vk::Result
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("InvalidateMappedMemoryRanges");
+        check_mapped_ranges(device, slice::from_raw_parts(ranges, count as usize));
+    }
+    vk::SUCCESS
+}
+
+// Checks the valid usage rules of `VkMappedMemoryRange`.
+fn check_mapped_ranges(device: &MockDevice, ranges: &[vk::MappedMemoryRange]) {
+    let state = device.state.lock().unwrap();
+
+    for range in ranges {
+        let alloc = state.memory.get(&range.memory).expect("Flushing unknown memory");
+        assert!(!alloc.data.is_empty(), "Flushing memory that isn't mapped");
+        assert_eq!(range.offset % NON_COHERENT_ATOM_SIZE, 0);
+        if range.size != vk::WHOLE_SIZE {
+            assert!(range.offset + range.size <= alloc.size);
+            assert!(range.size % NON_COHERENT_ATOM_SIZE == 0 ||
+                    range.offset + range.size == alloc.size);
+        }
+    }
+}
+
+extern "system" fn GetDeviceMemoryCommitment(device_handle: vk::Device,
+                                             memory: vk::DeviceMemory, out: *mut vk::DeviceSize)
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("GetDeviceMemoryCommitment");
+        *out = device.state.lock().unwrap().memory[&memory].size;
+    }
+}
+
+extern "system" fn BindBufferMemory(device_handle: vk::Device, buffer: vk::Buffer,
+                                    memory: vk::DeviceMemory, offset: vk::DeviceSize)
+                                    -> vk::Result
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("BindBufferMemory");
+        check_dedicated_binding(device, buffer, memory, offset);
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn BindImageMemory(device_handle: vk::Device, image: vk::Image,
+                                   memory: vk::DeviceMemory, offset: vk::DeviceSize)
+                                   -> vk::Result
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("BindImageMemory");
+        check_dedicated_binding(device, image, memory, offset);
+    }
+    vk::SUCCESS
+}
+
+// Checks the valid usage rules of dedicated allocations when binding memory to a resource.
+fn check_dedicated_binding(device: &MockDevice, resource: u64, memory: vk::DeviceMemory,
+                           offset: vk::DeviceSize)
+{
+    let state = device.state.lock().unwrap();
+    let alloc = state.memory.get(&memory).expect("Binding unknown memory");
+
+    match alloc.dedicated_to {
+        Some(r) => {
+            assert_eq!(r, resource, "Binding memory dedicated to another resource");
+            assert_eq!(offset, 0);
+        },
+        None => {
+            assert!(!state.requires_dedicated.contains(&resource),
+                    "The resource requires a dedicated allocation");
+        },
+    }
+}
+
+extern "system" fn GetBufferMemoryRequirements(device_handle: vk::Device, buffer: vk::Buffer,
+                                               out: *mut vk::MemoryRequirements)
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("GetBufferMemoryRequirements");
+        let size = device.state.lock().unwrap().buffers[&buffer];
+        *out = vk::MemoryRequirements {
+            size: size,
+            alignment: BUFFER_ALIGNMENT,
+            memoryTypeBits: (1 << MEMORY_TYPES.len()) - 1,
+        };
+    }
+}
+
+extern "system" fn GetImageMemoryRequirements(device_handle: vk::Device, image: vk::Image,
+                                              out: *mut vk::MemoryRequirements)
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("GetImageMemoryRequirements");
+        *out = image_memory_requirements(device, image);
+    }
+}
+
+extern "system" fn GetBufferMemoryRequirements2KHR(device_handle: vk::Device,
+                                                   infos: *const vk::BufferMemoryRequirementsInfo2KHR,
+                                                   out: *mut vk::MemoryRequirements2KHR)
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("GetBufferMemoryRequirements2KHR");
+        let buffer = (*infos).buffer;
+        let size = device.state.lock().unwrap().buffers[&buffer];
+        (*out).memoryRequirements = vk::MemoryRequirements {
+            size: size,
+            alignment: BUFFER_ALIGNMENT,
+            memoryTypeBits: (1 << MEMORY_TYPES.len()) - 1,
+        };
+        write_dedicated_requirements(device, buffer, size, (*out).pNext);
+    }
+}
+
+extern "system" fn GetImageMemoryRequirements2KHR(device_handle: vk::Device,
+                                                  infos: *const vk::ImageMemoryRequirementsInfo2KHR,
+                                                  out: *mut vk::MemoryRequirements2KHR)
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("GetImageMemoryRequirements2KHR");
+        let image = (*infos).image;
+        (*out).memoryRequirements = image_memory_requirements(device, image);
+
+        // Sparse resources never require a dedicated allocation.
+        let sparse = device.state.lock().unwrap().sparse_images.contains_key(&image);
+        let size = if sparse { 0 } else { (*out).memoryRequirements.size };
+        write_dedicated_requirements(device, image, size, (*out).pNext);
+    }
+}
+
+fn image_memory_requirements(device: &MockDevice, image: vk::Image) -> vk::MemoryRequirements {
+    let state = device.state.lock().unwrap();
+    let sparse = state.sparse_images.contains_key(&image);
+
+    vk::MemoryRequirements {
+        size: state.images[&image],
+        alignment: if sparse { SPARSE_BLOCK_SIZE } else { IMAGE_ALIGNMENT },
+        memoryTypeBits: (1 << MEMORY_TYPES.len()) - 1,
+    }
+}
+
+// Fills the `VkMemoryDedicatedRequirementsKHR` of a `pNext` chain, if there is one.
+unsafe fn write_dedicated_requirements(device: &MockDevice, resource: u64, size: u64,
+                                       chain: *mut c_void)
+{
+    let out = find_in_chain(chain, vk::STRUCTURE_TYPE_MEMORY_DEDICATED_REQUIREMENTS_KHR)
+                  as *mut vk::MemoryDedicatedRequirementsKHR;
+    if out.is_null() {
+        return;
+    }
+
+    let dedicated = size >= DEDICATED_SIZE;
+    (*out).prefersDedicatedAllocation = if dedicated { vk::TRUE } else { vk::FALSE };
+    (*out).requiresDedicatedAllocation = if dedicated { vk::TRUE } else { vk::FALSE };
+    if dedicated {
+        device.state.lock().unwrap().requires_dedicated.insert(resource);
+    }
+}
+
+extern "system" fn GetImageSparseMemoryRequirements(device_handle: vk::Device,
+                                                    image: vk::Image, count: *mut u32,
+                                                    out: *mut vk::SparseImageMemoryRequirements)
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("GetImageSparseMemoryRequirements");
+
+        let state = device.state.lock().unwrap();
+        let image = match state.sparse_images.get(&image) {
+            Some(image) if image.residency => image,
+            _ => {
+                write_array(count, out, 0, |_| unreachable!());
+                return;
+            },
+        };
+
+        let granularity = if image.ty == vk::IMAGE_TYPE_3D {
+            SPARSE_GRANULARITY_3D
+        } else {
+            SPARSE_GRANULARITY_2D
+        };
+
+        // The mipmap tail starts at the first level whose dimensions aren't a multiple of the
+        // granularity.
+        let mip_tail_first_lod = (0 .. image.mip_levels).find(|&level| {
+            (0 .. 3).any(|n| {
+                let dim = if (image.extent[n] >> level) == 0 { 1 } else { image.extent[n] >> level };
+                dim % granularity[n] != 0
+            })
+        }).unwrap_or(image.mip_levels);
+
+        let mip_tail_size = if mip_tail_first_lod < image.mip_levels {
+            SPARSE_BLOCK_SIZE
+        } else {
+            0
+        };
+
+        write_array(count, out, 1, |_| {
+            vk::SparseImageMemoryRequirements {
+                formatProperties: vk::SparseImageFormatProperties {
+                    aspectMask: vk::IMAGE_ASPECT_COLOR_BIT,
+                    imageGranularity: vk::Extent3D {
+                        width: granularity[0],
+                        height: granularity[1],
+                        depth: granularity[2],
+                    },
+                    flags: vk::SPARSE_IMAGE_FORMAT_ALIGNED_MIP_SIZE_BIT,
+                },
+                imageMipTailFirstLod: mip_tail_first_lod,
+                imageMipTailSize: mip_tail_size,
+                imageMipTailOffset: 0,
+                imageMipTailStride: mip_tail_size,
+            }
+        });
+    }
+}
+
+extern "system" fn CreateFence(device_handle: vk::Device, infos: *const vk::FenceCreateInfo,
+                               _: *const vk::AllocationCallbacks, out: *mut vk::Fence)
+                               -> vk::Result
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("CreateFence");
+        let mut state = device.state.lock().unwrap();
+        let handle = state.new_handle();
+        let signaled = ((*infos).flags & vk::FENCE_CREATE_SIGNALED_BIT) != 0;
+        state.fences.insert(handle, signaled);
+        *out = handle;
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn DestroyFence(device_handle: vk::Device, fence: vk::Fence,
+                                _: *const vk::AllocationCallbacks)
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("DestroyFence");
+        device.state.lock().unwrap().fences.remove(&fence);
+    }
+}
+
+extern "system" fn ResetFences(device_handle: vk::Device, count: u32, fences: *const vk::Fence)
+                               -> vk::Result
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("ResetFences");
+        let mut state = device.state.lock().unwrap();
+        for fence in slice::from_raw_parts(fences, count as usize) {
+            state.fences.insert(*fence, false);
+        }
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn GetFenceStatus(device_handle: vk::Device, fence: vk::Fence) -> vk::Result {
+    unsafe {
+        let device = device(device_handle);
+        device.record("GetFenceStatus");
+        if device.state.lock().unwrap().fences[&fence] { vk::SUCCESS } else { vk::NOT_READY }
+    }
+}
+
+extern "system" fn WaitForFences(device_handle: vk::Device, count: u32, fences: *const vk::Fence,
+                                 wait_all: vk::Bool32, _: u64) -> vk::Result
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("WaitForFences");
+        let state = device.state.lock().unwrap();
+        let mut signaled = slice::from_raw_parts(fences, count as usize)
+            .iter().map(|f| state.fences[f]);
+
+        // Since the work is never actually executed, waiting for a fence that isn't signaled
+        // would block forever.
+        let ok = if wait_all != 0 { signaled.all(|s| s) } else { signaled.any(|s| s) };
+        if ok { vk::SUCCESS } else { vk::TIMEOUT }
+    }
+}
+
+extern "system" fn CreateEvent(device_handle: vk::Device, _: *const vk::EventCreateInfo,
+                               _: *const vk::AllocationCallbacks, out: *mut vk::Event)
+                               -> vk::Result
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("CreateEvent");
+        let mut state = device.state.lock().unwrap();
+        let handle = state.new_handle();
+        state.events.insert(handle, false);
+        *out = handle;
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn DestroyEvent(device_handle: vk::Device, event: vk::Event,
+                                _: *const vk::AllocationCallbacks)
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("DestroyEvent");
+        device.state.lock().unwrap().events.remove(&event);
+    }
+}
+
+extern "system" fn GetEventStatus(device_handle: vk::Device, event: vk::Event) -> vk::Result {
+    unsafe {
+        let device = device(device_handle);
+        device.record("GetEventStatus");
+        if device.state.lock().unwrap().events[&event] { vk::EVENT_SET } else { vk::EVENT_RESET }
+    }
+}
+
+extern "system" fn SetEvent(device_handle: vk::Device, event: vk::Event) -> vk::Result {
+    unsafe {
+        let device = device(device_handle);
+        device.record("SetEvent");
+        device.state.lock().unwrap().events.insert(event, true);
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn ResetEvent(device_handle: vk::Device, event: vk::Event) -> vk::Result {
+    unsafe {
+        let device = device(device_handle);
+        device.record("ResetEvent");
+        device.state.lock().unwrap().events.insert(event, false);
+    }
+    vk::SUCCESS
+}
+
+// The first value of each query is `1000` times the index of the query, and the other values are
+// zero.
+extern "system" fn GetQueryPoolResults(device_handle: vk::Device, _: vk::QueryPool, first: u32,
+                                       count: u32, data_size: usize, data: *mut c_void,
+                                       stride: vk::DeviceSize, flags: vk::QueryResultFlags)
+                                       -> vk::Result
+{
+    unsafe {
+        device(device_handle).record("GetQueryPoolResults");
+        ptr::write_bytes(data as *mut u8, 0, data_size);
+        for n in 0 .. count {
+            let value = (first + n) as u64 * 1000;
+            let dest = (data as *mut u8).offset((n as vk::DeviceSize * stride) as isize);
+            if (flags & vk::QUERY_RESULT_64_BIT) != 0 {
+                ptr::copy_nonoverlapping(&value as *const u64 as *const u8, dest, 8);
+            } else {
+                let value = value as u32;
+                ptr::copy_nonoverlapping(&value as *const u32 as *const u8, dest, 4);
+            }
+        }
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn CreateBuffer(device_handle: vk::Device, infos: *const vk::BufferCreateInfo,
+                                _: *const vk::AllocationCallbacks, out: *mut vk::Buffer)
+                                -> vk::Result
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("CreateBuffer");
+        let mut state = device.state.lock().unwrap();
+        let handle = state.new_handle();
+        state.buffers.insert(handle, (*infos).size);
+        *out = handle;
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn DestroyBuffer(device_handle: vk::Device, buffer: vk::Buffer,
+                                 _: *const vk::AllocationCallbacks)
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("DestroyBuffer");
+        let mut state = device.state.lock().unwrap();
+        state.buffers.remove(&buffer);
+        state.requires_dedicated.remove(&buffer);
+    }
+}
+
+extern "system" fn CreateImage(device_handle: vk::Device, infos: *const vk::ImageCreateInfo,
+                               _: *const vk::AllocationCallbacks, out: *mut vk::Image)
+                               -> vk::Result
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("CreateImage");
+        let infos = &*infos;
+
+        // We don't know the size of a texel, so we assume the largest possible one. The mipmaps
+        // at most double the size of the image.
+        let texels = infos.extent.width as u64 * infos.extent.height as u64 *
+                     infos.extent.depth as u64 * infos.arrayLayers as u64 *
+                     infos.samples as u64;
+        let size = texels * 16 * if infos.mipLevels > 1 { 2 } else { 1 };
+
+        let mut state = device.state.lock().unwrap();
+        let handle = state.new_handle();
+
+        if (infos.flags & vk::IMAGE_CREATE_SPARSE_BINDING_BIT) != 0 {
+            state.sparse_images.insert(handle, SparseImage {
+                residency: (infos.flags & vk::IMAGE_CREATE_SPARSE_RESIDENCY_BIT) != 0,
+                ty: infos.imageType,
+                extent: [infos.extent.width, infos.extent.height, infos.extent.depth],
+                mip_levels: infos.mipLevels,
+                array_layers: infos.arrayLayers,
+            });
+        }
+
+        let size = if state.sparse_images.contains_key(&handle) {
+            (size + SPARSE_BLOCK_SIZE - 1) / SPARSE_BLOCK_SIZE * SPARSE_BLOCK_SIZE
+        } else {
+            size
+        };
+        state.images.insert(handle, size);
+        *out = handle;
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn DestroyImage(device_handle: vk::Device, image: vk::Image,
+                                _: *const vk::AllocationCallbacks)
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("DestroyImage");
+        let mut state = device.state.lock().unwrap();
+        state.images.remove(&image);
+        state.sparse_images.remove(&image);
+        state.requires_dedicated.remove(&image);
+    }
+}
+
+extern "system" fn GetImageSubresourceLayout(device_handle: vk::Device, _: vk::Image,
+                                             _: *const vk::ImageSubresource,
+                                             out: *mut vk::SubresourceLayout)
+{
+    unsafe {
+        device(device_handle).record("GetImageSubresourceLayout");
+        *out = mem::zeroed();
+    }
+}
+
+extern "system" fn GetPipelineCacheData(device_handle: vk::Device, _: vk::PipelineCache,
+                                        size: *mut usize, _: *mut c_void) -> vk::Result
+{
+    unsafe {
+        device(device_handle).record("GetPipelineCacheData");
+        *size = 0;
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn MergePipelineCaches(device_handle: vk::Device, _: vk::PipelineCache, _: u32,
+                                       _: *const vk::PipelineCache) -> vk::Result
+{
+    unsafe { device(device_handle).record("MergePipelineCaches"); }
+    vk::SUCCESS
+}
+
+extern "system" fn CreateGraphicsPipelines(device_handle: vk::Device, _: vk::PipelineCache,
+                                           count: u32,
+                                           _: *const vk::GraphicsPipelineCreateInfo,
+                                           _: *const vk::AllocationCallbacks,
+                                           out: *mut vk::Pipeline) -> vk::Result
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("CreateGraphicsPipelines");
+        let mut state = device.state.lock().unwrap();
+        for n in 0 .. count as isize {
+            *out.offset(n) = state.new_handle();
+        }
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn CreateComputePipelines(device_handle: vk::Device, _: vk::PipelineCache,
+                                          count: u32, _: *const vk::ComputePipelineCreateInfo,
+                                          _: *const vk::AllocationCallbacks,
+                                          out: *mut vk::Pipeline) -> vk::Result
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("CreateComputePipelines");
+        let mut state = device.state.lock().unwrap();
+        for n in 0 .. count as isize {
+            *out.offset(n) = state.new_handle();
+        }
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn ResetDescriptorPool(device_handle: vk::Device, _: vk::DescriptorPool,
+                                       _: vk::DescriptorPoolResetFlags) -> vk::Result
+{
+    unsafe { device(device_handle).record("ResetDescriptorPool"); }
+    vk::SUCCESS
+}
+
+extern "system" fn AllocateDescriptorSets(device_handle: vk::Device,
+                                          infos: *const vk::DescriptorSetAllocateInfo,
+                                          out: *mut vk::DescriptorSet) -> vk::Result
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("AllocateDescriptorSets");
+        let mut state = device.state.lock().unwrap();
+        for n in 0 .. (*infos).descriptorSetCount as isize {
+            *out.offset(n) = state.new_handle();
+        }
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn FreeDescriptorSets(device_handle: vk::Device, _: vk::DescriptorPool, _: u32,
+                                      _: *const vk::DescriptorSet) -> vk::Result
+{
+    unsafe { device(device_handle).record("FreeDescriptorSets"); }
+    vk::SUCCESS
+}
+
+extern "system" fn UpdateDescriptorSets(device_handle: vk::Device, _: u32,
+                                        _: *const vk::WriteDescriptorSet, _: u32,
+                                        _: *const vk::CopyDescriptorSet)
+{
+    unsafe { device(device_handle).record("UpdateDescriptorSets"); }
+}
+
+extern "system" fn GetRenderAreaGranularity(device_handle: vk::Device, _: vk::RenderPass,
+                                            out: *mut vk::Extent2D)
+{
+    unsafe {
+        device(device_handle).record("GetRenderAreaGranularity");
+        *out = vk::Extent2D { width: 1, height: 1 };
+    }
+}
+
+extern "system" fn ResetCommandPool(device_handle: vk::Device, _: vk::CommandPool,
+                                    _: vk::CommandPoolResetFlags) -> vk::Result
+{
+    unsafe { device(device_handle).record("ResetCommandPool"); }
+    vk::SUCCESS
+}
+
+extern "system" fn TrimCommandPoolKHR(device_handle: vk::Device, _: vk::CommandPool,
+                                      _: vk::CommandPoolTrimFlagsKHR)
+{
+    unsafe { device(device_handle).record("TrimCommandPoolKHR"); }
+}
+
+extern "system" fn AllocateCommandBuffers(device_handle: vk::Device,
+                                          infos: *const vk::CommandBufferAllocateInfo,
+                                          out: *mut vk::CommandBuffer) -> vk::Result
+{
+    unsafe {
+        let device = device(device_handle);
+        device.record("AllocateCommandBuffers");
+        for n in 0 .. (*infos).commandBufferCount as isize {
+            let cb = Box::new(MockCommandBuffer { device: device as *const MockDevice });
+            *out.offset(n) = Box::into_raw(cb) as vk::CommandBuffer;
+        }
+    }
+    vk::SUCCESS
+}
+
+extern "system" fn FreeCommandBuffers(device_handle: vk::Device, _: vk::CommandPool, count: u32,
+                                      command_buffers: *const vk::CommandBuffer)
+{
+    unsafe {
+        device(device_handle).record("FreeCommandBuffers");
+        for &cb in slice::from_raw_parts(command_buffers, count as usize) {
+            drop(Box::from_raw(cb as *mut MockCommandBuffer));
+        }
+    }
+}
+
+/// Defines a function that creates a non-dispatchable object and writes its handle.
+macro_rules! create_fn {
+    ($name:ident, $infos:ty, $out:ty) => (
+        extern "system" fn $name(device_handle: vk::Device, _: *const $infos,
+                                 _: *const vk::AllocationCallbacks, out: *mut $out) -> vk::Result
+        {
+            unsafe {
+                let device = device(device_handle);
+                device.record(stringify!($name));
+                *out = device.state.lock().unwrap().new_handle();
+            }
+            vk::SUCCESS
+        }
+    );
+}
+
+/// Defines a function that destroys a non-dispatchable object.
+macro_rules! destroy_fn {
+    ($name:ident, $obj:ty) => (
+        extern "system" fn $name(device_handle: vk::Device, _: $obj,
+                                 _: *const vk::AllocationCallbacks)
+        {
+            unsafe { device(device_handle).record(stringify!($name)); }
+        }
+    );
+}
+
+/// Defines a function that operates on a command buffer.
+macro_rules! cmd_fn {
+    ($name:ident, ($($param:ty),*) -> vk::Result) => (
+        extern "system" fn $name(cb: vk::CommandBuffer $(, _: $param)*) -> vk::Result {
+            unsafe { command_buffer_device(cb).record(stringify!($name)); }
+            vk::SUCCESS
+        }
+    );
+    ($name:ident, ($($param:ty),*)) => (
+        extern "system" fn $name(cb: vk::CommandBuffer $(, _: $param)*) {
+            unsafe { command_buffer_device(cb).record(stringify!($name)); }
+        }
+    );
+}
+
+create_fn!(CreateSemaphore, vk::SemaphoreCreateInfo, vk::Semaphore);
+destroy_fn!(DestroySemaphore, vk::Semaphore);
+create_fn!(CreateQueryPool, vk::QueryPoolCreateInfo, vk::QueryPool);
+destroy_fn!(DestroyQueryPool, vk::QueryPool);
+create_fn!(CreateBufferView, vk::BufferViewCreateInfo, vk::BufferView);
+destroy_fn!(DestroyBufferView, vk::BufferView);
+create_fn!(CreateImageView, vk::ImageViewCreateInfo, vk::ImageView);
+destroy_fn!(DestroyImageView, vk::ImageView);
+create_fn!(CreateShaderModule, vk::ShaderModuleCreateInfo, vk::ShaderModule);
+destroy_fn!(DestroyShaderModule, vk::ShaderModule);
+create_fn!(CreatePipelineCache, vk::PipelineCacheCreateInfo, vk::PipelineCache);
+destroy_fn!(DestroyPipelineCache, vk::PipelineCache);
+destroy_fn!(DestroyPipeline, vk::Pipeline);
+create_fn!(CreatePipelineLayout, vk::PipelineLayoutCreateInfo, vk::PipelineLayout);
+destroy_fn!(DestroyPipelineLayout, vk::PipelineLayout);
+create_fn!(CreateSampler, vk::SamplerCreateInfo, vk::Sampler);
+destroy_fn!(DestroySampler, vk::Sampler);
+create_fn!(CreateDescriptorSetLayout, vk::DescriptorSetLayoutCreateInfo, vk::DescriptorSetLayout);
+destroy_fn!(DestroyDescriptorSetLayout, vk::DescriptorSetLayout);
+create_fn!(CreateDescriptorPool, vk::DescriptorPoolCreateInfo, vk::DescriptorPool);
+destroy_fn!(DestroyDescriptorPool, vk::DescriptorPool);
+create_fn!(CreateFramebuffer, vk::FramebufferCreateInfo, vk::Framebuffer);
+destroy_fn!(DestroyFramebuffer, vk::Framebuffer);
+create_fn!(CreateRenderPass, vk::RenderPassCreateInfo, vk::RenderPass);
+destroy_fn!(DestroyRenderPass, vk::RenderPass);
+create_fn!(CreateCommandPool, vk::CommandPoolCreateInfo, vk::CommandPool);
+destroy_fn!(DestroyCommandPool, vk::CommandPool);
+
+cmd_fn!(BeginCommandBuffer, (*const vk::CommandBufferBeginInfo) -> vk::Result);
+cmd_fn!(EndCommandBuffer, () -> vk::Result);
+cmd_fn!(ResetCommandBuffer, (vk::CommandBufferResetFlags) -> vk::Result);
+cmd_fn!(CmdBindPipeline, (vk::PipelineBindPoint, vk::Pipeline));
+cmd_fn!(CmdSetViewport, (u32, u32, *const vk::Viewport));
+cmd_fn!(CmdSetScissor, (u32, u32, *const vk::Rect2D));
+cmd_fn!(CmdSetLineWidth, (f32));
+cmd_fn!(CmdSetDepthBias, (f32, f32, f32));
+cmd_fn!(CmdSetBlendConstants, ([f32; 4]));
+cmd_fn!(CmdSetDepthBounds, (f32, f32));
+cmd_fn!(CmdSetStencilCompareMask, (vk::StencilFaceFlags, u32));
+cmd_fn!(CmdSetStencilWriteMask, (vk::StencilFaceFlags, u32));
+cmd_fn!(CmdSetStencilReference, (vk::StencilFaceFlags, u32));
+cmd_fn!(CmdBindDescriptorSets, (vk::PipelineBindPoint, vk::PipelineLayout, u32, u32,
+                                *const vk::DescriptorSet, u32, *const u32));
+cmd_fn!(CmdBindIndexBuffer, (vk::Buffer, vk::DeviceSize, vk::IndexType));
+cmd_fn!(CmdBindVertexBuffers, (u32, u32, *const vk::Buffer, *const vk::DeviceSize));
+cmd_fn!(CmdDraw, (u32, u32, u32, u32));
+cmd_fn!(CmdDrawIndexed, (u32, u32, u32, i32, u32));
+cmd_fn!(CmdDrawIndirect, (vk::Buffer, vk::DeviceSize, u32, u32));
+cmd_fn!(CmdDrawIndexedIndirect, (vk::Buffer, vk::DeviceSize, u32, u32));
+cmd_fn!(CmdDispatch, (u32, u32, u32));
+cmd_fn!(CmdDispatchIndirect, (vk::Buffer, vk::DeviceSize));
+cmd_fn!(CmdCopyBuffer, (vk::Buffer, vk::Buffer, u32, *const vk::BufferCopy));
+cmd_fn!(CmdCopyImage, (vk::Image, vk::ImageLayout, vk::Image, vk::ImageLayout, u32,
+                       *const vk::ImageCopy));
+cmd_fn!(CmdBlitImage, (vk::Image, vk::ImageLayout, vk::Image, vk::ImageLayout, u32,
+                       *const vk::ImageBlit, vk::Filter));
+cmd_fn!(CmdCopyBufferToImage, (vk::Buffer, vk::Image, vk::ImageLayout, u32,
+                               *const vk::BufferImageCopy));
+cmd_fn!(CmdCopyImageToBuffer, (vk::Image, vk::ImageLayout, vk::Buffer, u32,
+                               *const vk::BufferImageCopy));
+cmd_fn!(CmdUpdateBuffer, (vk::Buffer, vk::DeviceSize, vk::DeviceSize, *const u32));
+cmd_fn!(CmdFillBuffer, (vk::Buffer, vk::DeviceSize, vk::DeviceSize, u32));
+cmd_fn!(CmdClearColorImage, (vk::Image, vk::ImageLayout, *const vk::ClearColorValue, u32,
+                             *const vk::ImageSubresourceRange));
+cmd_fn!(CmdClearDepthStencilImage, (vk::Image, vk::ImageLayout,
+                                    *const vk::ClearDepthStencilValue, u32,
+                                    *const vk::ImageSubresourceRange));
+cmd_fn!(CmdClearAttachments, (u32, *const vk::ClearAttachment, u32, *const vk::ClearRect));
+cmd_fn!(CmdResolveImage, (vk::Image, vk::ImageLayout, vk::Image, vk::ImageLayout, u32,
+                          *const vk::ImageResolve));
+cmd_fn!(CmdSetEvent, (vk::Event, vk::PipelineStageFlags));
+cmd_fn!(CmdResetEvent, (vk::Event, vk::PipelineStageFlags));
+cmd_fn!(CmdWaitEvents, (u32, *const vk::Event, vk::PipelineStageFlags, vk::PipelineStageFlags,
+                        u32, *const vk::MemoryBarrier, u32, *const vk::BufferMemoryBarrier,
+                        u32, *const vk::ImageMemoryBarrier));
+cmd_fn!(CmdPipelineBarrier, (vk::PipelineStageFlags, vk::PipelineStageFlags,
+                             vk::DependencyFlags, u32, *const vk::MemoryBarrier, u32,
+                             *const vk::BufferMemoryBarrier, u32,
+                             *const vk::ImageMemoryBarrier));
+cmd_fn!(CmdBeginQuery, (vk::QueryPool, u32, vk::QueryControlFlags));
+cmd_fn!(CmdEndQuery, (vk::QueryPool, u32));
+cmd_fn!(CmdResetQueryPool, (vk::QueryPool, u32, u32));
+cmd_fn!(CmdWriteTimestamp, (vk::PipelineStageFlagBits, vk::QueryPool, u32));
+cmd_fn!(CmdCopyQueryPoolResults, (vk::QueryPool, u32, u32, vk::Buffer, vk::DeviceSize,
+                                  vk::DeviceSize, vk::QueryResultFlags));
+cmd_fn!(CmdPushConstants, (vk::PipelineLayout, vk::ShaderStageFlags, u32, u32,
+                           *const c_void));
+cmd_fn!(CmdBeginRenderPass, (*const vk::RenderPassBeginInfo, vk::SubpassContents));
+cmd_fn!(CmdNextSubpass, (vk::SubpassContents));
+cmd_fn!(CmdEndRenderPass, ());
+cmd_fn!(CmdExecuteCommands, (u32, *const vk::CommandBuffer));
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use features::Features;
+    use instance::PhysicalDevice;
+    use memory::DeviceMemory;
+    use mock;
+    use sync::Fence;
+    use OomError;
+
+    #[test]
+    fn physical_device() {
+        let instance = mock::instance();
+        let physical = PhysicalDevice::enumerate(&instance).next().unwrap();
+        assert_eq!(physical.name(), mock::MOCK_DEVICE_NAME);
+        assert_eq!(physical.queue_families().len(), 3);
+        assert_eq!(physical.memory_types().len(), 4);
+        assert!(physical.supported_features().superset_of(&Features::all()));
+    }
+
+    #[test]
+    fn records_buffer_creation() {
+        let (device, queue) = mock_dev_and_queue!();
+        let _buffer = CpuAccessibleBuffer::from_data(&device, &BufferUsage::all(),
+                                                     Some(queue.family()), 12u32).unwrap();
+
+        let calls = mock::calls(&device);
+        let create = calls.iter().position(|&c| c == "CreateBuffer").unwrap();
+        let bind = calls.iter().position(|&c| c == "BindBufferMemory").unwrap();
+        assert!(create < bind);
+    }
+
+    #[test]
+    fn mapped_memory_is_writable() {
+        let (device, queue) = mock_dev_and_queue!();
+        let buffer = CpuAccessibleBuffer::from_data(&device, &BufferUsage::all(),
+                                                    Some(queue.family()), 12u32).unwrap();
+        assert_eq!(*buffer.read().unwrap(), 12);
+        *buffer.write().unwrap() = 7;
+        assert_eq!(*buffer.read().unwrap(), 7);
+    }
+
+    #[test]
+    fn heap_exhaustion() {
+        let (device, _) = mock_dev_and_queue!();
+        let mem_ty = device.physical_device().memory_types().next().unwrap();
+        let _a = DeviceMemory::alloc(&device, mem_ty,
+                                     mock::DEVICE_LOCAL_HEAP_SIZE as usize).unwrap();
+        match DeviceMemory::alloc(&device, mem_ty, 1) {
+            Err(OomError::OutOfDeviceMemory) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn unsignaled_fence_times_out() {
+        let (device, _) = mock_dev_and_queue!();
+        let fence = Fence::new(device.clone()).unwrap();
+        assert!(fence.wait(Duration::new(0, 0)).is_err());
+    }
+}
@@ -30,6 +30,7 @@ use descriptor::pipeline_layout::PipelineLayoutSuperset;
 use descriptor::pipeline_layout::PipelineLayoutSys;
 use descriptor::pipeline_layout::EmptyPipelineDesc;
 use format::ClearValue;
+use format::Format;
 use framebuffer::AttachmentsList;
 use framebuffer::LayoutAttachmentDescription;
 use framebuffer::LayoutPassDescription;
@@ -538,7 +539,12 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
 
             let mut attribute_descriptions = SmallVec::<[_; 8]>::new();
             for (loc, binding, info) in attribs_iter {
-                // TODO: check attribute format support
+                if !info.format.properties(device.physical_device()).buffer_features.vertex_buffer {
+                    return Err(GraphicsPipelineCreationError::VertexFormatNotSupported {
+                        location: loc as u32,
+                        format: info.format,
+                    });
+                }
 
                 if info.offset > device.physical_device().limits().max_vertex_input_attribute_offset() as usize {
                     return Err(GraphicsPipelineCreationError::MaxVertexInputAttributeOffsetExceeded {
@@ -1328,6 +1334,14 @@ pub enum GraphicsPipelineCreationError {
         obtained: usize,
     },
 
+    /// The format of a vertex attribute is not supported for vertex buffers.
+    VertexFormatNotSupported {
+        /// Location of the faulty attribute.
+        location: u32,
+        /// The format of the attribute.
+        format: Format,
+    },
+
     /// The user requested to use primitive restart, but the primitive topology doesn't support it.
     PrimitiveDoesntSupportPrimitiveRestart {
         /// The topology that doesn't support primitive restart.
@@ -1461,6 +1475,9 @@ impl error::Error for GraphicsPipelineCreationError {
             GraphicsPipelineCreationError::MaxVertexInputAttributesExceeded { .. } => {
                 "the maximum number of vertex attributes has been exceeded"
             },
+            GraphicsPipelineCreationError::VertexFormatNotSupported { .. } => {
+                "the format of a vertex attribute is not supported for vertex buffers"
+            },
             GraphicsPipelineCreationError::PrimitiveDoesntSupportPrimitiveRestart { .. } => {
                 "the user requested to use primitive restart, but the primitive topology \
                  doesn't support it"
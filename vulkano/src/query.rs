@@ -12,12 +12,19 @@
 //! In Vulkan, queries are not created individually. Instead you manipulate **query pools**, which
 //! represent a collection of queries. Whenever you use a query, you have to specify both the query
 //! pool and the slot id within that query pool.
+//!
+//! Queries are reset, started, ended and written with the corresponding commands of the
+//! `CommandBufferBuilder` trait. Once the command buffer has been executed, the results can be
+//! read with the `get_results` method of the pool or copied to a buffer with the
+//! `copy_query_pool_results` command.
 
 use std::error;
 use std::fmt;
 use std::mem;
+use std::ops::Range;
 use std::ptr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use device::Device;
 
@@ -25,13 +32,28 @@ use check_errors;
 use Error;
 use OomError;
 use SafeDeref;
+use Success;
 use VulkanObject;
 use VulkanPointers;
 use vk;
 
+/// Trait for objects that contain a Vulkan query pool.
+pub unsafe trait QueryPoolAbstract {
+    /// Returns the inner unsafe query pool.
+    fn inner(&self) -> &UnsafeQueryPool;
+}
+
+unsafe impl<T> QueryPoolAbstract for T where T: SafeDeref, T::Target: QueryPoolAbstract {
+    #[inline]
+    fn inner(&self) -> &UnsafeQueryPool {
+        (**self).inner()
+    }
+}
+
 pub struct UnsafeQueryPool<P = Arc<Device>> where P: SafeDeref<Target = Device> {
     pool: vk::QueryPool,
     device: P,
+    ty: QueryType,
     num_slots: u32,
 }
 
@@ -72,21 +94,109 @@ impl<P> UnsafeQueryPool<P> where P: SafeDeref<Target = Device> {
         Ok(UnsafeQueryPool {
             pool: pool,
             device: device,
+            ty: ty,
             num_slots: num_slots,
         })
     }
 
+    /// Returns the type of queries of that query pool.
+    #[inline]
+    pub fn ty(&self) -> QueryType {
+        self.ty
+    }
+
     /// Returns the number of slots of that query pool.
     #[inline]
     pub fn num_slots(&self) -> u32 {
         self.num_slots
     }
 
+    /// Returns the number of values that each query produces.
+    ///
+    /// This is 1 for occlusion and timestamp queries, and the number of enabled statistics for
+    /// pipeline statistics queries.
+    #[inline]
+    pub fn num_values_per_query(&self) -> usize {
+        match self.ty {
+            QueryType::Occlusion | QueryType::Timestamp => 1,
+            QueryType::PipelineStatistics(flags) => {
+                let flags: vk::QueryPipelineStatisticFlags = flags.into();
+                flags.count_ones() as usize
+            },
+        }
+    }
+
     /// Returns the device used to create the pool.
     #[inline]
     pub fn device(&self) -> &P {
         &self.device
     }
+
+    /// Copies the results of the queries of `range` into `destination`.
+    ///
+    /// The results are written one query after another. Each query writes
+    /// `num_values_per_query()` values, followed by its availability if
+    /// `flags.with_availability` is true. The availability is non-zero if the result of the
+    /// query was available.
+    ///
+    /// Returns `Ok(true)` if the results of all the queries were available. If `flags.wait` is
+    /// false and some results were not available yet, returns `Ok(false)` and the values of these
+    /// queries are only meaningful if `flags.partial` is true.
+    pub fn get_results(&self, range: Range<u32>, destination: &mut [u64],
+                       flags: QueryResultFlags) -> Result<bool, QueryResultsError>
+    {
+        if range.start > range.end || range.end > self.num_slots {
+            return Err(QueryResultsError::OutOfRange);
+        }
+
+        if flags.partial {
+            if let QueryType::Timestamp = self.ty {
+                return Err(QueryResultsError::PartialTimestamp);
+            }
+        }
+
+        let stride = self.num_values_per_query() + if flags.with_availability { 1 } else { 0 };
+        let num_queries = (range.end - range.start) as usize;
+        if destination.len() < num_queries * stride {
+            return Err(QueryResultsError::DestinationTooSmall);
+        }
+
+        if num_queries == 0 {
+            return Ok(true);
+        }
+
+        unsafe {
+            let vk = self.device.pointers();
+            let data_size = num_queries * stride * mem::size_of::<u64>();
+            let stride = (stride * mem::size_of::<u64>()) as vk::DeviceSize;
+            let data = destination.as_mut_ptr() as *mut _;
+            let result = try!(check_errors(vk.GetQueryPoolResults(self.device.internal_object(),
+                                                                  self.pool, range.start,
+                                                                  num_queries as u32, data_size,
+                                                                  data, stride, flags.into())));
+            match result {
+                Success::Success => Ok(true),
+                Success::NotReady => Ok(false),
+                _ => unreachable!()
+            }
+        }
+    }
+}
+
+unsafe impl<P> VulkanObject for UnsafeQueryPool<P> where P: SafeDeref<Target = Device> {
+    type Object = vk::QueryPool;
+
+    #[inline]
+    fn internal_object(&self) -> vk::QueryPool {
+        self.pool
+    }
+}
+
+unsafe impl QueryPoolAbstract for UnsafeQueryPool {
+    #[inline]
+    fn inner(&self) -> &UnsafeQueryPool {
+        self
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -96,6 +206,49 @@ pub enum QueryType {
     Timestamp,
 }
 
+/// Flags that control how the results of queries are retrieved.
+///
+/// The results are always retrieved as 64-bits integers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct QueryResultFlags {
+    /// Wait until the results of all the queries are available.
+    pub wait: bool,
+    /// Write the availability of each query after its results.
+    pub with_availability: bool,
+    /// Write the intermediate results of the queries that are not available yet. Not allowed for
+    /// timestamp queries.
+    pub partial: bool,
+}
+
+impl QueryResultFlags {
+    /// Builds a `QueryResultFlags` with all the flags set to false.
+    #[inline]
+    pub fn none() -> QueryResultFlags {
+        QueryResultFlags {
+            wait: false,
+            with_availability: false,
+            partial: false,
+        }
+    }
+}
+
+impl Into<vk::QueryResultFlags> for QueryResultFlags {
+    #[inline]
+    fn into(self) -> vk::QueryResultFlags {
+        let mut result = vk::QUERY_RESULT_64_BIT;
+        if self.wait {
+            result |= vk::QUERY_RESULT_WAIT_BIT;
+        }
+        if self.with_availability {
+            result |= vk::QUERY_RESULT_WITH_AVAILABILITY_BIT;
+        }
+        if self.partial {
+            result |= vk::QUERY_RESULT_PARTIAL_BIT;
+        }
+        result
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct QueryPipelineStatisticFlags {
     pub input_assembly_vertices: bool,
@@ -235,6 +388,67 @@ impl From<Error> for QueryPoolCreationError {
     }
 }
 
+/// Error that can happen when retrieving the results of queries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QueryResultsError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The device has been lost.
+    DeviceLost,
+    /// The range of queries is out of the range of the pool.
+    OutOfRange,
+    /// The destination is too small to contain the results.
+    DestinationTooSmall,
+    /// Partial results were requested for timestamp queries.
+    PartialTimestamp,
+}
+
+impl error::Error for QueryResultsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            QueryResultsError::OomError(_) => "not enough memory available",
+            QueryResultsError::DeviceLost => "the device has been lost",
+            QueryResultsError::OutOfRange => {
+                "the range of queries is out of the range of the pool"
+            },
+            QueryResultsError::DestinationTooSmall => {
+                "the destination is too small to contain the results"
+            },
+            QueryResultsError::PartialTimestamp => {
+                "partial results were requested for timestamp queries"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            QueryResultsError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for QueryResultsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<Error> for QueryResultsError {
+    #[inline]
+    fn from(err: Error) -> QueryResultsError {
+        match err {
+            err @ Error::OutOfHostMemory => QueryResultsError::OomError(OomError::from(err)),
+            err @ Error::OutOfDeviceMemory => QueryResultsError::OomError(OomError::from(err)),
+            Error::DeviceLost => QueryResultsError::DeviceLost,
+            _ => panic!("unexpected error: {:?}", err)
+        }
+    }
+}
+
 pub struct OcclusionQueriesPool {
     inner: UnsafeQueryPool,
 }
@@ -281,12 +495,162 @@ impl OcclusionQueriesPool {
     }
 }
 
+unsafe impl QueryPoolAbstract for OcclusionQueriesPool {
+    #[inline]
+    fn inner(&self) -> &UnsafeQueryPool {
+        &self.inner
+    }
+}
+
+/// A pool of timestamp queries.
+///
+/// Timestamps are written with the `write_timestamp` command of command buffers.
+pub struct TimestampQueryPool {
+    inner: UnsafeQueryPool,
+}
+
+impl TimestampQueryPool {
+    /// Builds a new query pool.
+    pub fn new(device: &Arc<Device>, num_slots: u32)
+               -> Result<Arc<TimestampQueryPool>, OomError>
+    {
+        let inner = match UnsafeQueryPool::new(device.clone(), QueryType::Timestamp, num_slots) {
+            Ok(q) => q,
+            Err(QueryPoolCreationError::OomError(err)) => return Err(err),
+            Err(QueryPoolCreationError::PipelineStatisticsQueryFeatureNotEnabled) => {
+                unreachable!()
+            },
+        };
+
+        Ok(Arc::new(TimestampQueryPool {
+            inner: inner,
+        }))
+    }
+
+    /// Returns the number of slots of that query pool.
+    #[inline]
+    pub fn num_slots(&self) -> u32 {
+        self.inner.num_slots()
+    }
+
+    /// Returns the device that was used to create this pool.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        self.inner.device()
+    }
+
+    /// Returns the timestamps written in the queries of `range`.
+    ///
+    /// The timestamps are converted to durations with the `timestamp_period` limit of the
+    /// physical device. Their origin is unspecified, therefore only the difference between two
+    /// timestamps is meaningful.
+    ///
+    /// If `wait` is true, blocks until all the timestamps have been written. Otherwise returns
+    /// `Ok(None)` if some of them are not available yet.
+    pub fn get_results(&self, range: Range<u32>, wait: bool)
+                       -> Result<Option<Vec<Duration>>, QueryResultsError>
+    {
+        let mut values = vec![0; (range.end.saturating_sub(range.start)) as usize];
+        let flags = QueryResultFlags { wait: wait, .. QueryResultFlags::none() };
+        if !try!(self.inner.get_results(range, &mut values, flags)) {
+            return Ok(None);
+        }
+
+        let period = self.device().physical_device().limits().timestamp_period() as f64;
+        Ok(Some(values.into_iter().map(|value| {
+            let nanos = value as f64 * period;
+            Duration::new((nanos / 1_000_000_000.0) as u64,
+                          (nanos % 1_000_000_000.0) as u32)
+        }).collect()))
+    }
+}
+
+unsafe impl QueryPoolAbstract for TimestampQueryPool {
+    #[inline]
+    fn inner(&self) -> &UnsafeQueryPool {
+        &self.inner
+    }
+}
+
+/// A pool of pipeline statistics queries.
+///
+/// Requires the `pipeline_statistics_query` feature to be enabled on the device.
+pub struct PipelineStatisticsQueryPool {
+    inner: UnsafeQueryPool,
+    flags: QueryPipelineStatisticFlags,
+}
+
+impl PipelineStatisticsQueryPool {
+    /// Builds a new query pool whose queries gather the statistics enabled in `flags`.
+    pub fn new(device: &Arc<Device>, num_slots: u32, flags: QueryPipelineStatisticFlags)
+               -> Result<Arc<PipelineStatisticsQueryPool>, QueryPoolCreationError>
+    {
+        let ty = QueryType::PipelineStatistics(flags);
+        let inner = try!(UnsafeQueryPool::new(device.clone(), ty, num_slots));
+
+        Ok(Arc::new(PipelineStatisticsQueryPool {
+            inner: inner,
+            flags: flags,
+        }))
+    }
+
+    /// Returns the statistics gathered by the queries of that pool.
+    #[inline]
+    pub fn flags(&self) -> QueryPipelineStatisticFlags {
+        self.flags
+    }
+
+    /// Returns the number of slots of that query pool.
+    #[inline]
+    pub fn num_slots(&self) -> u32 {
+        self.inner.num_slots()
+    }
+
+    /// Returns the device that was used to create this pool.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        self.inner.device()
+    }
+
+    /// Returns the statistics gathered by the queries of `range`.
+    ///
+    /// Each query produces one value for each statistic enabled in `flags()`, in the order of the
+    /// fields of `QueryPipelineStatisticFlags`.
+    ///
+    /// If `wait` is true, blocks until all the results are available. Otherwise returns
+    /// `Ok(None)` if some of them are not available yet.
+    pub fn get_results(&self, range: Range<u32>, wait: bool)
+                       -> Result<Option<Vec<u64>>, QueryResultsError>
+    {
+        let num_queries = range.end.saturating_sub(range.start) as usize;
+        let mut values = vec![0; num_queries * self.inner.num_values_per_query()];
+        let flags = QueryResultFlags { wait: wait, .. QueryResultFlags::none() };
+        if !try!(self.inner.get_results(range, &mut values, flags)) {
+            return Ok(None);
+        }
+
+        Ok(Some(values))
+    }
+}
+
+unsafe impl QueryPoolAbstract for PipelineStatisticsQueryPool {
+    #[inline]
+    fn inner(&self) -> &UnsafeQueryPool {
+        &self.inner
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
     use query::OcclusionQueriesPool;
+    use query::PipelineStatisticsQueryPool;
     use query::QueryPipelineStatisticFlags;
     use query::QueryPoolCreationError;
+    use query::QueryResultFlags;
+    use query::QueryResultsError;
     use query::QueryType;
+    use query::TimestampQueryPool;
     use query::UnsafeQueryPool;
 
     #[test]
@@ -305,4 +669,40 @@ mod tests {
             _ => panic!()
         };
     }
+
+    #[test]
+    fn timestamp_results() {
+        let (device, _) = mock_dev_and_queue!(queue: |_| true);
+
+        // The mock writes `1000 * index` in each query and has a timestamp period of 1ns.
+        let pool = TimestampQueryPool::new(&device, 4).unwrap();
+        let results = pool.get_results(1 .. 3, true).unwrap().unwrap();
+        assert_eq!(results, vec![Duration::new(0, 1000), Duration::new(0, 2000)]);
+
+        let mut values = [0; 4];
+        let flags = QueryResultFlags { partial: true, .. QueryResultFlags::none() };
+        match pool.inner.get_results(0 .. 4, &mut values, flags) {
+            Err(QueryResultsError::PartialTimestamp) => (),
+            _ => panic!()
+        };
+    }
+
+    #[test]
+    fn pipeline_statistics_results() {
+        let (device, _) = mock_dev_and_queue!(queue: |_| true; pipeline_statistics_query);
+
+        let flags = QueryPipelineStatisticFlags {
+            vertex_shader_invocations: true,
+            fragment_shader_invocations: true,
+            .. QueryPipelineStatisticFlags::none()
+        };
+        let pool = PipelineStatisticsQueryPool::new(&device, 8, flags).unwrap();
+        let results = pool.get_results(2 .. 4, true).unwrap().unwrap();
+        assert_eq!(results, vec![2000, 0, 3000, 0]);
+
+        match pool.get_results(6 .. 9, true) {
+            Err(QueryResultsError::OutOfRange) => (),
+            _ => panic!()
+        };
+    }
 }
@@ -20,6 +20,7 @@ use std::time::Duration;
 
 use buffer::BufferAccess;
 use command_buffer::submit::SubmitAnyBuilder;
+use command_buffer::submit::SubmitBindSparseError;
 use command_buffer::submit::SubmitPresentBuilder;
 use command_buffer::submit::SubmitSemaphoresWaitBuilder;
 use device::Device;
@@ -28,6 +29,7 @@ use device::Queue;
 use format::Format;
 use format::FormatDesc;
 use image::ImageAccess;
+use image::ImageSubresourceRange;
 use image::ImageDimensions;
 use image::sys::UnsafeImage;
 use image::sys::Usage as ImageUsage;
@@ -323,7 +325,8 @@ impl Swapchain {
         // Normally if `check_image_access` returns false we're supposed to call the `gpu_access`
         // function on the image instead. But since we know that this method on `SwapchainImage`
         // always returns false anyway (by design), we don't need to do it.
-        assert!(before.check_image_access(&swapchain_image, true, &queue).is_ok());         // TODO: return error instead
+        let range = ImageSubresourceRange::whole_image(&swapchain_image);
+        assert!(before.check_image_access(&swapchain_image, &range, true, &queue).is_ok());         // TODO: return error instead
 
         PresentFuture {
             previous: before,
@@ -482,7 +485,8 @@ unsafe impl GpuFuture for SwapchainAcquireFuture {
     }
 
     #[inline]
-    fn check_image_access(&self, image: &ImageAccess, exclusive: bool, queue: &Queue)
+    fn check_image_access(&self, image: &ImageAccess, range: &ImageSubresourceRange,
+                          exclusive: bool, queue: &Queue)
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>
     {
         if let Some(sc_img) = self.image.upgrade() {
@@ -636,6 +640,9 @@ unsafe impl<P> GpuFuture for PresentFuture<P> where P: GpuFuture {
                 builder.add_swapchain(self.command_buffer.inner(), self.image_id);
                 SubmitAnyBuilder::CommandBuffer(builder)*/
             },
+            SubmitAnyBuilder::BindSparse(_) => {
+                return Err(Box::new(SubmitBindSparseError::MissingSignalSemaphore));
+            },
         })
     }
 
@@ -673,7 +680,8 @@ unsafe impl<P> GpuFuture for PresentFuture<P> where P: GpuFuture {
     }
 
     #[inline]
-    fn check_image_access(&self, image: &ImageAccess, exclusive: bool, queue: &Queue)
+    fn check_image_access(&self, image: &ImageAccess, range: &ImageSubresourceRange,
+                          exclusive: bool, queue: &Queue)
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>
     {
         unimplemented!()        // TODO: VK specs don't say whether it is legal to do that
@@ -24,6 +24,11 @@ use device::Device;
 use device::DeviceOwned;
 use device::Queue;
 use image::ImageAccess;
+use image::ImageSubresourceRange;
+use image::SparseImageBindError;
+use image::SparseImageBindFuture;
+use image::SparseImageBinds;
+use memory::pool::MemoryPool;
 use swapchain::Swapchain;
 use swapchain::PresentFuture;
 use sync::AccessFlagBits;
@@ -95,7 +100,8 @@ pub unsafe trait GpuFuture: DeviceOwned {
                            -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>;
 
     /// Checks whether submitting something after this future grants access (exclusive or shared,
-    /// depending on the parameter) to the given image on the given queue.
+    /// depending on the parameter) to the given range of subresources of an image on the given
+    /// queue.
     ///
     /// If the access is granted, returns the pipeline stage and access flags of the latest usage
     /// of this resource, or `None` if irrelevant.
@@ -105,7 +111,8 @@ pub unsafe trait GpuFuture: DeviceOwned {
     ///
     /// > **Note**: Keep in mind that changing the layout of an image also requires exclusive
     /// > access.
-    fn check_image_access(&self, image: &ImageAccess, exclusive: bool, queue: &Queue)
+    fn check_image_access(&self, image: &ImageAccess, range: &ImageSubresourceRange,
+                         exclusive: bool, queue: &Queue)
                          -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>;
 
     /// Joins this future with another one, representing the moment when both events have happened.
@@ -149,6 +156,18 @@ pub unsafe trait GpuFuture: DeviceOwned {
         command_buffer.execute_after(self, queue)
     }
 
+    /// Binds or unbinds the memory of the blocks of a sparse image after this future.
+    ///
+    /// > **Note**: This is just a shortcut function. The actual implementation is in
+    /// > `SparseImageBinds::execute_after`.
+    #[inline]
+    fn then_bind_sparse<F, A>(self, queue: Arc<Queue>, binds: SparseImageBinds<F, A>)
+                              -> Result<SparseImageBindFuture<Self, F, A>, SparseImageBindError>
+        where Self: Sized, F: 'static + Send + Sync, A: MemoryPool
+    {
+        binds.execute_after(self, queue)
+    }
+
     /// Signals a semaphore after this future. Returns another future that represents the signal.
     #[inline]
     fn then_signal_semaphore(self) -> SemaphoreSignalFuture<Self> where Self: Sized {
@@ -233,10 +252,11 @@ unsafe impl<F: ?Sized> GpuFuture for Box<F> where F: GpuFuture {
     }
 
     #[inline]
-    fn check_image_access(&self, image: &ImageAccess, exclusive: bool, queue: &Queue)
+    fn check_image_access(&self, image: &ImageAccess, range: &ImageSubresourceRange,
+                          exclusive: bool, queue: &Queue)
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>
     {
-        (**self).check_image_access(image, exclusive, queue)
+        (**self).check_image_access(image, range, exclusive, queue)
     }
 }
 
@@ -293,7 +313,8 @@ unsafe impl GpuFuture for DummyFuture {
     }
 
     #[inline]
-    fn check_image_access(&self, image: &ImageAccess, exclusive: bool, queue: &Queue)
+    fn check_image_access(&self, image: &ImageAccess, range: &ImageSubresourceRange,
+                           exclusive: bool, queue: &Queue)
                            -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>
     {
         Err(())
@@ -367,6 +388,11 @@ unsafe impl<F> GpuFuture for SemaphoreSignalFuture<F> where F: GpuFuture {
                     builder.add_signal_semaphore(&self.semaphore);
                     try!(builder.submit(&queue));       // FIXME: problematic because if we return an error and flush() is called again, then we'll submit the present twice
                 },
+                SubmitAnyBuilder::BindSparse(mut builder) => {
+                    debug_assert_eq!(builder.num_signal_semaphores(), 0);
+                    builder.add_signal_semaphore(&self.semaphore);
+                    try!(builder.submit(&queue));
+                },
             };
 
             // Only write `true` here in order to try again next time if an error occurs.
@@ -400,10 +426,11 @@ unsafe impl<F> GpuFuture for SemaphoreSignalFuture<F> where F: GpuFuture {
     }
 
     #[inline]
-    fn check_image_access(&self, image: &ImageAccess, exclusive: bool, queue: &Queue)
+    fn check_image_access(&self, image: &ImageAccess, range: &ImageSubresourceRange,
+                           exclusive: bool, queue: &Queue)
                            -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>
     {
-        self.previous.check_image_access(image, exclusive, queue).map(|_| None)
+        self.previous.check_image_access(image, range, exclusive, queue).map(|_| None)
     }
 }
 
@@ -439,6 +466,18 @@ pub struct FenceSignalFuture<F> where F: GpuFuture {
     flushed: Mutex<bool>,
 }
 
+impl<F> FenceSignalFuture<F> where F: GpuFuture {
+    /// Flushes the future if necessary, then blocks until the fence is signaled or the timeout
+    /// is reached.
+    ///
+    /// A value of `0` for the timeout is valid and means that the function should never block.
+    pub fn wait(&self, timeout: Duration) -> Result<(), Box<Error>> {
+        try!(self.flush());
+        try!(self.fence.wait(timeout));
+        Ok(())
+    }
+}
+
 unsafe impl<F> GpuFuture for FenceSignalFuture<F> where F: GpuFuture {
     #[inline]
     fn cleanup_finished(&mut self) {
@@ -494,6 +533,11 @@ unsafe impl<F> GpuFuture for FenceSignalFuture<F> where F: GpuFuture {
                     b.set_fence_signal(&self.fence);
                     try!(b.submit(&queue));       // FIXME: problematic because if we return an error and flush() is called again, then we'll submit the present twice
                 },
+                SubmitAnyBuilder::BindSparse(mut sparse) => {
+                    debug_assert!(!sparse.has_fence());
+                    sparse.set_fence_signal(&self.fence);
+                    try!(sparse.submit(&queue));
+                },
             };
 
             // Only write `true` here in order to try again next time if an error occurs.
@@ -531,10 +575,11 @@ unsafe impl<F> GpuFuture for FenceSignalFuture<F> where F: GpuFuture {
     }
 
     #[inline]
-    fn check_image_access(&self, image: &ImageAccess, exclusive: bool, queue: &Queue)
+    fn check_image_access(&self, image: &ImageAccess, range: &ImageSubresourceRange,
+                          exclusive: bool, queue: &Queue)
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, ()> {
         if let Some(ref previous) = self.previous {
-            previous.check_image_access(image, exclusive, queue)
+            previous.check_image_access(image, range, exclusive, queue)
         } else {
             Err(())
         }
@@ -637,6 +682,33 @@ unsafe impl<A, B> GpuFuture for JoinFuture<A, B> where A: GpuFuture, B: GpuFutur
             (SubmitAnyBuilder::QueuePresent(a), SubmitAnyBuilder::CommandBuffer(b)) => {
                 unimplemented!()
             },
+            (SubmitAnyBuilder::BindSparse(a), SubmitAnyBuilder::BindSparse(b)) => {
+                SubmitAnyBuilder::BindSparse(a.merge(b))
+            },
+            (SubmitAnyBuilder::SemaphoresWait(a), SubmitAnyBuilder::BindSparse(b)) => {
+                try!(b.submit(&self.second.queue().clone().unwrap()));
+                SubmitAnyBuilder::SemaphoresWait(a)
+            },
+            (SubmitAnyBuilder::BindSparse(a), SubmitAnyBuilder::SemaphoresWait(b)) => {
+                try!(a.submit(&self.first.queue().clone().unwrap()));
+                SubmitAnyBuilder::SemaphoresWait(b)
+            },
+            (SubmitAnyBuilder::CommandBuffer(a), SubmitAnyBuilder::BindSparse(b)) => {
+                try!(b.submit(&self.second.queue().clone().unwrap()));
+                SubmitAnyBuilder::CommandBuffer(a)
+            },
+            (SubmitAnyBuilder::BindSparse(a), SubmitAnyBuilder::CommandBuffer(b)) => {
+                try!(a.submit(&self.first.queue().clone().unwrap()));
+                SubmitAnyBuilder::CommandBuffer(b)
+            },
+            (SubmitAnyBuilder::QueuePresent(a), SubmitAnyBuilder::BindSparse(b)) => {
+                try!(b.submit(&self.second.queue().clone().unwrap()));
+                SubmitAnyBuilder::QueuePresent(a)
+            },
+            (SubmitAnyBuilder::BindSparse(a), SubmitAnyBuilder::QueuePresent(b)) => {
+                try!(a.submit(&self.first.queue().clone().unwrap()));
+                SubmitAnyBuilder::QueuePresent(b)
+            },
         })
     }
 
@@ -690,11 +762,12 @@ unsafe impl<A, B> GpuFuture for JoinFuture<A, B> where A: GpuFuture, B: GpuFutur
     }
 
     #[inline]
-    fn check_image_access(&self, image: &ImageAccess, exclusive: bool, queue: &Queue)
+    fn check_image_access(&self, image: &ImageAccess, range: &ImageSubresourceRange,
+                          exclusive: bool, queue: &Queue)
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, ()>
     {
-        let first = self.first.check_image_access(image, exclusive, queue);
-        let second = self.second.check_image_access(image, exclusive, queue);
+        let first = self.first.check_image_access(image, range, exclusive, queue);
+        let second = self.second.check_image_access(image, range, exclusive, queue);
         debug_assert!(!exclusive || !(first.is_ok() && second.is_ok()), "Two futures gave \
                                                                          exclusive access to the \
                                                                          same resource");
@@ -9,14 +9,18 @@
 
 #![cfg(test)]
 
-/// Creates an instance or returns if initialization fails.
+/// Creates an instance.
+///
+/// If the system's Vulkan implementation can't be loaded or doesn't expose any physical device,
+/// falls back to the in-process mock implementation so that the tests still run.
 macro_rules! instance {
     () => ({
         use instance;
+        use mock;
 
         match instance::Instance::new(None, &instance::InstanceExtensions::none(), None) {
-            Ok(i) => i,
-            Err(_) => return
+            Ok(ref i) if instance::PhysicalDevice::enumerate(i).next().is_some() => i.clone(),
+            _ => mock::instance()
         }
     })
 }
@@ -65,3 +69,39 @@ macro_rules! gfx_dev_and_queue {
         (device, queues.next().unwrap())
     });
 }
+
+/// Creates a device and a queue on the in-process mock implementation.
+///
+/// Unlike `gfx_dev_and_queue!`, this never uses the system's Vulkan implementation. Use it in
+/// the tests that look at the calls recorded by the mock with `mock::calls`. The queue is of the
+/// first family that supports graphics operations, unless a filter is passed with `queue:`.
+macro_rules! mock_dev_and_queue {
+    (queue: $filter:expr; $($feature:ident),*) => ({
+        use instance;
+        use device::Device;
+        use device::DeviceExtensions;
+        use features::Features;
+        use mock;
+
+        let instance = mock::instance();
+        let physical = instance::PhysicalDevice::enumerate(&instance).next().unwrap();
+        let queue = physical.queue_families().find($filter).unwrap();
+
+        let features = Features {
+            $(
+                $feature: true,
+            )*
+            .. Features::none()
+        };
+
+        let (device, mut queues) = Device::new(&physical, &features, &DeviceExtensions::none(),
+                                               [(queue, 0.5)].iter().cloned()).unwrap();
+        (device, queues.next().unwrap())
+    });
+
+    (queue: $filter:expr) => (mock_dev_and_queue!(queue: $filter;));
+
+    ($($feature:ident),*) => (
+        mock_dev_and_queue!(queue: |q| q.supports_graphics(); $($feature),*)
+    );
+}